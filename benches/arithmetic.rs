@@ -0,0 +1,74 @@
+//! Measures the interpreter on an arithmetic-heavy loop, which mostly executes binary instructions on the top of the
+//! operand stack.
+//!
+//! Run with `cargo bench --bench arithmetic -- --save-baseline` before a change and with `cargo bench --bench
+//! arithmetic` after it to compare against that run.
+
+use std::fs;
+use std::time::Instant;
+use rust_wasm_runtime::exec::{BlockType, Engine, Instance, Instruction};
+use rust_wasm_runtime::parse::{ModuleBuilder, Type};
+
+const ITERATIONS: i32 = 2_000_000;
+/// Number of measurements, of which the fastest is reported, as it is the least disturbed by other processes.
+const RUNS: usize = 5;
+/// File in which `--save-baseline` stores the time per instruction.
+const BASELINE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/target/arithmetic-baseline");
+
+fn main() {
+	let mut builder = ModuleBuilder::new();
+	let signature = builder.signature(vec![Type::I32], vec![Type::I32]);
+	// Sums up `(n * 3 ^ 5) & 7` while counting down `n` to zero
+	let body = vec![
+		Instruction::Loop {
+			block_type: BlockType::Empty,
+			instructions: vec![
+				Instruction::LocalGet(1),
+				Instruction::LocalGet(0),
+				Instruction::I32Const(3),
+				Instruction::I32Mul,
+				Instruction::I32Const(5),
+				Instruction::I32Xor,
+				Instruction::I32Const(7),
+				Instruction::I32And,
+				Instruction::I32Add,
+				Instruction::LocalSet(1),
+				Instruction::LocalGet(0),
+				Instruction::I32Const(-1),
+				Instruction::I32Add,
+				Instruction::LocalTee(0),
+				Instruction::BrIf { label_index: 0 },
+			],
+		},
+		Instruction::LocalGet(1),
+	];
+	let function = builder.function(signature, vec![Type::I32], body);
+	builder.export_function("bench", function);
+	let mut instance = Instance::new(&Engine::default(), builder.build()).expect("The module instantiates");
+
+	let (elapsed, result) = (0..RUNS)
+		.map(|_| {
+			let start = Instant::now();
+			let result = instance.call::<i32>("bench", ITERATIONS).expect("The loop does not trap");
+			(start.elapsed(), result)
+		})
+		.min()
+		.expect("There is at least one run");
+	let instructions = ITERATIONS as f64 * 15.0;
+	let ns_per_instruction = elapsed.as_nanos() as f64 / instructions;
+	println!(
+		"{} iterations in {:?} ({:.1} ns per instruction), result {}",
+		ITERATIONS, elapsed, ns_per_instruction, result
+	);
+
+	if std::env::args().any(|arg| arg == "--save-baseline") {
+		fs::write(BASELINE, ns_per_instruction.to_string()).expect("The baseline can be written");
+		println!("Saved as baseline in {}", BASELINE);
+	} else if let Ok(baseline) = fs::read_to_string(BASELINE) {
+		let baseline: f64 = baseline.trim().parse().expect("The baseline is a number");
+		println!(
+			"{:+.1}% compared to the baseline of {:.1} ns per instruction",
+			(ns_per_instruction / baseline - 1.0) * 100.0, baseline
+		);
+	}
+}
@@ -0,0 +1,71 @@
+//! Measures the interpreter on a loop of loads and stores, whose time is mostly spent on accessing the memory.
+//!
+//! Run with `cargo bench --bench memory -- --save-baseline` before a change and with `cargo bench --bench memory`
+//! after it to compare against that run.
+
+use std::fs;
+use std::time::Instant;
+use rust_wasm_runtime::exec::{BlockType, Engine, Instance, Instruction, MemArg};
+use rust_wasm_runtime::parse::{ModuleBuilder, Type};
+
+const ITERATIONS: i32 = 2_000_000;
+/// Number of measurements, of which the fastest is reported, as it is the least disturbed by other processes.
+const RUNS: usize = 5;
+/// File in which `--save-baseline` stores the time per instruction.
+const BASELINE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/target/memory-baseline");
+
+fn main() {
+	let mut builder = ModuleBuilder::new();
+	builder.memory(1, Some(1));
+	let signature = builder.signature(vec![Type::I32], vec![]);
+	// Stores `n` and copies 16 bytes four times while counting down `n` to zero
+	let mut instructions = vec![
+		Instruction::LocalGet(0),
+		Instruction::I32Const(0xffc),
+		Instruction::I32And,
+		Instruction::LocalGet(0),
+		Instruction::I32Store(MemArg { align: 2, offset: 0 }),
+	];
+	for copy in 0..4 {
+		instructions.extend([
+			Instruction::I32Const(copy * 0x10),
+			Instruction::I32Const(0),
+			Instruction::V128Load(MemArg { align: 4, offset: 0x100 }),
+			Instruction::V128Store(MemArg { align: 4, offset: 0x200 }),
+		]);
+	}
+	instructions.extend([
+		Instruction::LocalGet(0),
+		Instruction::I32Const(-1),
+		Instruction::I32Add,
+		Instruction::LocalTee(0),
+		Instruction::BrIf { label_index: 0 },
+	]);
+	let body = vec![Instruction::Loop { block_type: BlockType::Empty, instructions }];
+	let function = builder.function(signature, vec![], body);
+	builder.export_function("bench", function);
+	let mut instance = Instance::new(&Engine::default(), builder.build()).expect("The module instantiates");
+
+	let elapsed = (0..RUNS)
+		.map(|_| {
+			let start = Instant::now();
+			instance.call::<()>("bench", ITERATIONS).expect("The accesses are in bounds");
+			start.elapsed()
+		})
+		.min()
+		.expect("There is at least one run");
+	let instructions = ITERATIONS as f64 * 26.0;
+	let ns_per_instruction = elapsed.as_nanos() as f64 / instructions;
+	println!("{} iterations in {:?} ({:.1} ns per instruction)", ITERATIONS, elapsed, ns_per_instruction);
+
+	if std::env::args().any(|arg| arg == "--save-baseline") {
+		fs::write(BASELINE, ns_per_instruction.to_string()).expect("The baseline can be written");
+		println!("Saved as baseline in {}", BASELINE);
+	} else if let Ok(baseline) = fs::read_to_string(BASELINE) {
+		let baseline: f64 = baseline.trim().parse().expect("The baseline is a number");
+		println!(
+			"{:+.1}% compared to the baseline of {:.1} ns per instruction",
+			(ns_per_instruction / baseline - 1.0) * 100.0, baseline
+		);
+	}
+}
@@ -0,0 +1,12 @@
+//! Prints the arguments, without the program name, and an environment variable.
+
+use std::env;
+
+fn main() {
+	let args: Vec<String> = env::args().skip(1).collect();
+	println!("{} args", args.len());
+	for arg in &args {
+		println!("{}", arg);
+	}
+	println!("GREETING={}", env::var("GREETING").unwrap_or_default());
+}
@@ -0,0 +1,18 @@
+// Integer arithmetic with recursion, 64 bit multiplication and division.
+#include <stdio.h>
+
+static int fib(int n) {
+	return n < 2 ? n : fib(n - 1) + fib(n - 2);
+}
+
+int main(void) {
+	printf("fib(20) = %d\n", fib(20));
+	long long factorial = 1;
+	for (int i = 2; i <= 15; i++) {
+		factorial *= i;
+	}
+	printf("15! = %lld\n", factorial);
+	printf("1000 / 7 = %d rem %d\n", 1000 / 7, 1000 % 7);
+	printf("-1000 >> 3 = %d\n", -1000 >> 3);
+	return 0;
+}
@@ -0,0 +1,21 @@
+//! Creates, reads, renames, lists and removes files in the preopened working directory.
+
+use std::fs;
+
+fn main() {
+	fs::write("hello.txt", "Hello, files!\n").unwrap();
+	print!("{}", fs::read_to_string("hello.txt").unwrap());
+	println!("{} bytes", fs::metadata("hello.txt").unwrap().len());
+
+	fs::create_dir("sub").unwrap();
+	fs::rename("hello.txt", "sub/renamed.txt").unwrap();
+	let mut names: Vec<String> = fs::read_dir("sub").unwrap()
+		.map(|entry| entry.unwrap().file_name().into_string().unwrap())
+		.collect();
+	names.sort();
+	println!("sub: {}", names.join(", "));
+	println!("hello.txt exists: {}", fs::metadata("hello.txt").is_ok());
+
+	fs::remove_file("sub/renamed.txt").unwrap();
+	println!("sub is empty: {}", fs::read_dir("sub").unwrap().next().is_none());
+}
@@ -0,0 +1,19 @@
+// Calls through a table of function pointers, which compiles to `call_indirect`.
+#include <stdio.h>
+
+typedef int (*operation)(int, int);
+
+static int add(int a, int b) { return a + b; }
+static int sub(int a, int b) { return a - b; }
+static int mul(int a, int b) { return a * b; }
+
+static operation operations[] = { add, sub, mul };
+static const char symbols[] = "+-*";
+
+int main(void) {
+	// Volatile, so that the compiler cannot resolve the callees statically
+	for (volatile int i = 0; i < 3; i++) {
+		printf("7 %c 3 = %d\n", symbols[i], operations[i](7, 3));
+	}
+	return 0;
+}
@@ -0,0 +1,20 @@
+//! Heap allocations, which grow the memory, and loads and stores of all widths.
+
+fn main() {
+	let mut numbers: Vec<u64> = (0..100_000).rev().collect();
+	numbers.sort_unstable();
+	let sum: u64 = numbers.iter().sum();
+	let squares: u64 = numbers.iter().map(|x| x * x % 1000).sum();
+	println!("{} numbers from {} to {}", numbers.len(), numbers[0], numbers[numbers.len() - 1]);
+	println!("sum {} squares mod 1000 {}", sum, squares);
+
+	let mut buffer = vec![0u8; 4 << 20];
+	for (i, byte) in buffer.iter_mut().enumerate() {
+		*byte = i as u8;
+	}
+	let checksum = buffer.iter().fold(0u32, |acc, &byte| acc.wrapping_mul(31).wrapping_add(byte as u32));
+	println!("{} bytes checksum {:08x}", buffer.len(), checksum);
+
+	let words: Vec<u16> = buffer.chunks_exact(2).take(4).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+	println!("words {:04x?}", words);
+}
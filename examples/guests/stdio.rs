@@ -0,0 +1,15 @@
+//! Reads stdin line by line and writes to stdout and stderr.
+
+use std::io::{self, BufRead, Write};
+
+fn main() {
+	let stdout = io::stdout();
+	let mut stdout = stdout.lock();
+	let mut lines = 0;
+	for line in io::stdin().lock().lines() {
+		writeln!(stdout, "{}", line.unwrap().to_uppercase()).unwrap();
+		lines += 1;
+	}
+	writeln!(stdout, "{} lines", lines).unwrap();
+	eprintln!("done");
+}
@@ -0,0 +1,18 @@
+;; Prints messages through the built-in `env.log` import instead of WASI.
+;;
+;; Run with `rust-wasm-runtime run --env-log log.wasm` after compiling it with `wat2wasm examples/log.wat`.
+(module
+  (import "env" "log" (func $log (param i32 i32 i32)))
+  (memory 1)
+  (data (i32.const 0) "Hello from the guest!")
+  (data (i32.const 32) "Counting down")
+
+  (func (export "_start") (local $i i32)
+    ;; info
+    (call $log (i32.const 0) (i32.const 21) (i32.const 2))
+    (local.set $i (i32.const 3))
+    (loop $countdown
+      ;; debug, once per iteration
+      (call $log (i32.const 32) (i32.const 13) (i32.const 3))
+      (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+      (br_if $countdown (local.get $i)))))
@@ -0,0 +1,140 @@
+#ifndef RUST_WASM_RUNTIME_H
+#define RUST_WASM_RUNTIME_H
+
+/* Generated by cbindgen from src/capi.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The type of an [`RwrValue`].
+ */
+typedef enum RwrValueKind {
+  RWR_VALUE_KIND_I32,
+  RWR_VALUE_KIND_I64,
+  RWR_VALUE_KIND_F32,
+  RWR_VALUE_KIND_F64,
+} RwrValueKind;
+
+/**
+ * Why a function of the C API failed.
+ */
+typedef struct RwrError RwrError;
+
+/**
+ * An instance of a module with its own memory, tables and globals.
+ */
+typedef struct RwrInstance RwrInstance;
+
+/**
+ * A parsed module, which can be instantiated many times.
+ */
+typedef struct RwrModule RwrModule;
+
+/**
+ * The payload of an [`RwrValue`], of which the field named by its kind is valid.
+ */
+typedef union RwrValueOf {
+  int32_t i32;
+  int64_t i64;
+  float f32;
+  double f64;
+} RwrValueOf;
+
+/**
+ * An argument or result of [`rwr_call`]. Only numbers are supported.
+ */
+typedef struct RwrValue {
+  RwrValueKind kind;
+  RwrValueOf of;
+} RwrValue;
+
+/**
+ * Parses the binary module of `len` bytes at `bytes`. Returns null on failure.
+ *
+ * # Safety
+ *
+ * `bytes` points to `len` readable bytes, and `error` is null or valid for writes.
+ */
+RwrModule *rwr_module_new(const uint8_t *bytes, size_t len, RwrError **error);
+
+/**
+ * Releases `module`. Its instances stay valid.
+ *
+ * # Safety
+ *
+ * `module` is null or was returned by [`rwr_module_new`] and not released before.
+ */
+void rwr_module_delete(RwrModule *module);
+
+/**
+ * Instantiates `module` with the WASI imports of [`Linker::new`]. Returns null on failure, e.g. if an import is not
+ * defined.
+ *
+ * # Safety
+ *
+ * `module` was returned by [`rwr_module_new`], and `error` is null or valid for writes.
+ */
+RwrInstance *rwr_instance_new(const RwrModule *module, RwrError **error);
+
+/**
+ * Releases `instance`.
+ *
+ * # Safety
+ *
+ * `instance` is null or was returned by [`rwr_instance_new`] and not released before.
+ */
+void rwr_instance_delete(RwrInstance *instance);
+
+/**
+ * Calls the exported function `name` of `instance` with `num_args` arguments at `args` and writes its results to
+ * `results`, which has room for `num_results` values. Returns false on failure, e.g. if the function traps or has
+ * another number of results.
+ *
+ * # Safety
+ *
+ * `instance` was returned by [`rwr_instance_new`], `name` is a nul terminated string, `args` points to `num_args`
+ * readable values, `results` to `num_results` writable values, and `error` is null or valid for writes.
+ */
+bool rwr_call(RwrInstance *instance,
+              const char *name,
+              const RwrValue *args,
+              size_t num_args,
+              RwrValue *results,
+              size_t num_results,
+              RwrError **error);
+
+/**
+ * The bytes of the memory of `instance`, whose number is written to `len`. Returns null and writes 0 if the
+ * instance has no memory.
+ *
+ * The pointer is only valid until the next call into the instance, which may grow and thus move the memory.
+ *
+ * # Safety
+ *
+ * `instance` was returned by [`rwr_instance_new`], and `len` is valid for writes.
+ */
+uint8_t *rwr_memory_data(RwrInstance *instance, size_t *len);
+
+/**
+ * The message of `error` as nul terminated UTF-8 string, which is valid until the error is released.
+ *
+ * # Safety
+ *
+ * `error` was stored by a function of the C API and not released before.
+ */
+const char *rwr_error_message(const RwrError *error);
+
+/**
+ * Releases `error`.
+ *
+ * # Safety
+ *
+ * `error` is null or was stored by a function of the C API and not released before.
+ */
+void rwr_error_delete(RwrError *error);
+
+#endif /* RUST_WASM_RUNTIME_H */
@@ -0,0 +1,241 @@
+//! C API for embedding the runtime from other languages, e.g. C, or Python and Node through their FFI modules.
+//!
+//! Built as `cdylib` with the `capi` feature. The declarations are in `include/rust_wasm_runtime.h`, which is
+//! generated with `cbindgen --config cbindgen.toml --output include/rust_wasm_runtime.h`.
+//!
+//! Objects are created by `rwr_*_new` functions and released by the matching `rwr_*_delete` function. Functions
+//! which can fail take an `RwrError **` as last parameter, into which they store an error on failure if it is not
+//! null. The caller releases the error with [`rwr_error_delete`].
+
+use std::ffi::{c_char, CStr, CString};
+use std::{fmt, ptr, slice};
+use crate::exec::{Config, Instance, Linker, Value};
+use crate::parse::Module;
+use crate::shared::Shared;
+
+/// A parsed module, which can be instantiated many times.
+pub struct RwrModule(Shared<Module>);
+
+/// An instance of a module with its own memory, tables and globals.
+pub struct RwrInstance(Instance);
+
+/// Why a function of the C API failed.
+pub struct RwrError(CString);
+
+/// The type of an [`RwrValue`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RwrValueKind {
+	I32,
+	I64,
+	F32,
+	F64,
+}
+
+/// The payload of an [`RwrValue`], of which the field named by its kind is valid.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union RwrValueOf {
+	pub i32: i32,
+	pub i64: i64,
+	pub f32: f32,
+	pub f64: f64,
+}
+
+/// An argument or result of [`rwr_call`]. Only numbers are supported.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RwrValue {
+	pub kind: RwrValueKind,
+	pub of: RwrValueOf,
+}
+
+impl From<RwrValue> for Value {
+	fn from(value: RwrValue) -> Self {
+		// The kind names the initialized field
+		unsafe {
+			match value.kind {
+				RwrValueKind::I32 => Value::I32(value.of.i32),
+				RwrValueKind::I64 => Value::I64(value.of.i64),
+				RwrValueKind::F32 => Value::F32(value.of.f32),
+				RwrValueKind::F64 => Value::F64(value.of.f64),
+			}
+		}
+	}
+}
+
+impl TryFrom<&Value> for RwrValue {
+	type Error = String;
+
+	fn try_from(value: &Value) -> Result<Self, String> {
+		Ok(match *value {
+			Value::I32(i32) => RwrValue { kind: RwrValueKind::I32, of: RwrValueOf { i32 } },
+			Value::I64(i64) => RwrValue { kind: RwrValueKind::I64, of: RwrValueOf { i64 } },
+			Value::F32(f32) => RwrValue { kind: RwrValueKind::F32, of: RwrValueOf { f32 } },
+			Value::F64(f64) => RwrValue { kind: RwrValueKind::F64, of: RwrValueOf { f64 } },
+			ref other => return Err(format!("the result {:?} is not supported by the C API", other)),
+		})
+	}
+}
+
+/// Stores an error with `message` into `error` if it is not null.
+unsafe fn set_error(error: *mut *mut RwrError, message: impl fmt::Display) {
+	if error.is_null() {
+		return;
+	}
+	// Messages are C strings, which cannot contain nul bytes
+	let message = CString::new(message.to_string().replace('\0', "")).unwrap_or_default();
+	*error = Box::into_raw(Box::new(RwrError(message)));
+}
+
+/// Parses the binary module of `len` bytes at `bytes`. Returns null on failure.
+///
+/// # Safety
+///
+/// `bytes` points to `len` readable bytes, and `error` is null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rwr_module_new(bytes: *const u8, len: usize, error: *mut *mut RwrError) -> *mut RwrModule {
+	let bytes: &[u8] = match bytes.is_null() {
+		true => &[],
+		false => slice::from_raw_parts(bytes, len),
+	};
+	match Module::from_bytes(bytes.to_vec()) {
+		Ok(module) => Box::into_raw(Box::new(RwrModule(Shared::new(module)))),
+		Err(parsing_error) => {
+			set_error(error, parsing_error);
+			ptr::null_mut()
+		},
+	}
+}
+
+/// Releases `module`. Its instances stay valid.
+///
+/// # Safety
+///
+/// `module` is null or was returned by [`rwr_module_new`] and not released before.
+#[no_mangle]
+pub unsafe extern "C" fn rwr_module_delete(module: *mut RwrModule) {
+	if !module.is_null() {
+		drop(Box::from_raw(module));
+	}
+}
+
+/// Instantiates `module` with the WASI imports of [`Linker::new`]. Returns null on failure, e.g. if an import is not
+/// defined.
+///
+/// # Safety
+///
+/// `module` was returned by [`rwr_module_new`], and `error` is null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rwr_instance_new(module: *const RwrModule, error: *mut *mut RwrError) -> *mut RwrInstance {
+	let module = Shared::clone(&(*module).0);
+	match Linker::new().instantiate(module, Config::default()) {
+		Ok(instance) => Box::into_raw(Box::new(RwrInstance(instance))),
+		Err(instantiation_error) => {
+			set_error(error, instantiation_error);
+			ptr::null_mut()
+		},
+	}
+}
+
+/// Releases `instance`.
+///
+/// # Safety
+///
+/// `instance` is null or was returned by [`rwr_instance_new`] and not released before.
+#[no_mangle]
+pub unsafe extern "C" fn rwr_instance_delete(instance: *mut RwrInstance) {
+	if !instance.is_null() {
+		drop(Box::from_raw(instance));
+	}
+}
+
+/// Calls the exported function `name` of `instance` with `num_args` arguments at `args` and writes its results to
+/// `results`, which has room for `num_results` values. Returns false on failure, e.g. if the function traps or has
+/// another number of results.
+///
+/// # Safety
+///
+/// `instance` was returned by [`rwr_instance_new`], `name` is a nul terminated string, `args` points to `num_args`
+/// readable values, `results` to `num_results` writable values, and `error` is null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rwr_call(
+	instance: *mut RwrInstance, name: *const c_char, args: *const RwrValue, num_args: usize, results: *mut RwrValue,
+	num_results: usize, error: *mut *mut RwrError,
+) -> bool {
+	let instance = &mut (*instance).0;
+	let name = CStr::from_ptr(name).to_string_lossy();
+	let Some(func) = instance.get_func(&name) else {
+		set_error(error, format!("unknown export {}", name));
+		return false;
+	};
+	let args = match args.is_null() {
+		true => Vec::new(),
+		false => slice::from_raw_parts(args, num_args).iter().map(|&arg| Value::from(arg)).collect(),
+	};
+	let values = match func.call(instance, args) {
+		Ok(values) => values,
+		Err(call_error) => {
+			set_error(error, call_error);
+			return false;
+		},
+	};
+	if values.len() != num_results {
+		set_error(error, format!("{} returned {} results instead of {}", name, values.len(), num_results));
+		return false;
+	}
+	for (i, value) in values.iter().enumerate() {
+		match RwrValue::try_from(value) {
+			Ok(value) => *results.add(i) = value,
+			Err(message) => {
+				set_error(error, message);
+				return false;
+			},
+		}
+	}
+	true
+}
+
+/// The bytes of the memory of `instance`, whose number is written to `len`. Returns null and writes 0 if the
+/// instance has no memory.
+///
+/// The pointer is only valid until the next call into the instance, which may grow and thus move the memory.
+///
+/// # Safety
+///
+/// `instance` was returned by [`rwr_instance_new`], and `len` is valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rwr_memory_data(instance: *mut RwrInstance, len: *mut usize) -> *mut u8 {
+	match (*instance).0.memory_mut() {
+		Some(memory) => {
+			*len = memory.data.len();
+			memory.data.as_mut_ptr()
+		},
+		None => {
+			*len = 0;
+			ptr::null_mut()
+		},
+	}
+}
+
+/// The message of `error` as nul terminated UTF-8 string, which is valid until the error is released.
+///
+/// # Safety
+///
+/// `error` was stored by a function of the C API and not released before.
+#[no_mangle]
+pub unsafe extern "C" fn rwr_error_message(error: *const RwrError) -> *const c_char {
+	(*error).0.as_ptr()
+}
+
+/// Releases `error`.
+///
+/// # Safety
+///
+/// `error` is null or was stored by a function of the C API and not released before.
+#[no_mangle]
+pub unsafe extern "C" fn rwr_error_delete(error: *mut RwrError) {
+	if !error.is_null() {
+		drop(Box::from_raw(error));
+	}
+}
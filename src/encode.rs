@@ -0,0 +1,782 @@
+//! Encoding of a [Module] into the [binary format](https://webassembly.github.io/spec/core/binary/index.html),
+//! which is the inverse of the [Parser](crate::parse::Parser).
+//!
+//! Together with the [ModuleBuilder](crate::parse::ModuleBuilder) this allows transforming modules, e.g. by parsing a
+//! binary, rewriting function bodies and encoding the result again.
+
+use crate::exec::{BlockType, FunctionSignature, Identifier, Instruction, MemArg};
+use crate::parse::{
+	AtomicOpcode, DataMode, DataSegment, ElementMode, ElementSegment, ExportKind, ExtensionOpcode, LimitKind, Module,
+	Opcode, SectionId, SimdOpcode, Type,
+};
+use std::ops::Range;
+
+/// Encodes `module` as a binary, which contains the sections in the order required by the specification followed by
+/// the custom sections.
+///
+/// # Panics
+/// If the signature of a function is not part of the type section of `module`.
+pub fn encode(module: &Module) -> Vec<u8> {
+	let mut binary = vec![0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00];
+
+	if !module.types.is_empty() {
+		let mut types = Vec::new();
+		write_u32(&mut types, module.types.len());
+		for signature in &module.types {
+			types.push(Type::Function as u8);
+			write_types(&mut types, &signature.params);
+			write_types(&mut types, &signature.results);
+		}
+		write_section(&mut binary, SectionId::Type, &types);
+	}
+
+	let imported_tables = module.table_blueprints.iter().filter(|table| table.import.is_some());
+	let imported_memory = module.memory_blueprint.iter().filter(|memory| memory.import.is_some());
+	let imported_globals = module.globals.iter().filter(|global| global.import.is_some());
+	let num_imports = module.functions.num_imports() + imported_tables.clone().count()
+		+ imported_memory.clone().count() + imported_globals.clone().count();
+	if num_imports > 0 {
+		let mut imports = Vec::new();
+		write_u32(&mut imports, num_imports);
+		for import in module.functions.imports() {
+			write_import_name(&mut imports, &import.name, ExportKind::Function);
+			write_u32(&mut imports, type_index(module, &import.signature));
+		}
+		for table in imported_tables {
+			write_import_name(&mut imports, table.import.as_ref().expect("Filtered imports"), ExportKind::Table);
+			imports.push(table.element_type as u8);
+			write_limits(&mut imports, &table.limit, false);
+		}
+		for memory in imported_memory {
+			write_import_name(&mut imports, memory.import.as_ref().expect("Filtered imports"), ExportKind::Memory);
+			write_limits(&mut imports, &memory.page_limit, memory.shared);
+		}
+		for global in imported_globals {
+			write_import_name(&mut imports, global.import.as_ref().expect("Filtered imports"), ExportKind::Global);
+			imports.push(global.ty as u8);
+			imports.push(global.mutable as u8);
+		}
+		write_section(&mut binary, SectionId::Import, &imports);
+	}
+
+	if module.functions.num_local() > 0 {
+		let mut functions = Vec::new();
+		write_u32(&mut functions, module.functions.num_local());
+		for function in module.functions.local() {
+			write_u32(&mut functions, type_index(module, &function.signature));
+		}
+		write_section(&mut binary, SectionId::Function, &functions);
+	}
+
+	let defined_tables: Vec<_> = module.table_blueprints.iter().filter(|table| table.import.is_none()).collect();
+	if !defined_tables.is_empty() {
+		let mut tables = Vec::new();
+		write_u32(&mut tables, defined_tables.len());
+		for table in defined_tables {
+			tables.push(table.element_type as u8);
+			write_limits(&mut tables, &table.limit, false);
+		}
+		write_section(&mut binary, SectionId::Table, &tables);
+	}
+
+	if let Some(memory) = module.memory_blueprint.as_ref().filter(|memory| memory.import.is_none()) {
+		let mut memories = Vec::new();
+		write_u32(&mut memories, 1);
+		write_limits(&mut memories, &memory.page_limit, memory.shared);
+		write_section(&mut binary, SectionId::Memory, &memories);
+	}
+
+	if !module.tags.is_empty() {
+		let mut tags = Vec::new();
+		write_u32(&mut tags, module.tags.len());
+		for signature in &module.tags {
+			// The attribute 0 denotes an exception
+			tags.push(0);
+			write_u32(&mut tags, type_index(module, signature));
+		}
+		write_section(&mut binary, SectionId::Tag, &tags);
+	}
+
+	let defined_globals: Vec<_> = module.globals.iter().filter(|global| global.import.is_none()).collect();
+	if !defined_globals.is_empty() {
+		let mut globals = Vec::new();
+		write_u32(&mut globals, defined_globals.len());
+		for global in defined_globals {
+			globals.push(global.ty as u8);
+			globals.push(global.mutable as u8);
+			write_expression(&mut globals, &global.init.instructions);
+		}
+		write_section(&mut binary, SectionId::Global, &globals);
+	}
+
+	let exports: Vec<_> = module.exports().into_iter()
+		.map(|export| (export.name, export.kind, export.index))
+		.collect();
+	if !exports.is_empty() {
+		let mut export_section = Vec::new();
+		write_u32(&mut export_section, exports.len());
+		for (name, kind, index) in exports {
+			write_name(&mut export_section, &name);
+			export_section.push(kind as u8);
+			write_u32(&mut export_section, index);
+		}
+		write_section(&mut binary, SectionId::Export, &export_section);
+	}
+
+	if let Some(start) = module.start {
+		let mut start_section = Vec::new();
+		write_u32(&mut start_section, start);
+		write_section(&mut binary, SectionId::Start, &start_section);
+	}
+
+	if !module.elements.is_empty() {
+		let mut elements = Vec::new();
+		write_u32(&mut elements, module.elements.len());
+		for segment in &module.elements {
+			write_element_segment(&mut elements, segment);
+		}
+		write_section(&mut binary, SectionId::Element, &elements);
+	}
+
+	let data_segments = data_segments(module);
+	if module.data_count.is_some() {
+		let mut data_count = Vec::new();
+		write_u32(&mut data_count, data_segments.len());
+		write_section(&mut binary, SectionId::DataCount, &data_count);
+	}
+
+	if module.functions.num_local() > 0 {
+		let mut code = Vec::new();
+		write_u32(&mut code, module.functions.num_local());
+		for function in module.functions.local() {
+			let mut body = Vec::new();
+			write_locals(&mut body, &function.locals);
+			write_expression(&mut body, &function.body);
+			write_u32(&mut code, body.len());
+			code.extend(body);
+		}
+		write_section(&mut binary, SectionId::Code, &code);
+	}
+
+	if !data_segments.is_empty() {
+		let mut data = Vec::new();
+		write_u32(&mut data, data_segments.len());
+		for segment in data_segments {
+			let bytes = match segment {
+				Data::Active(segment) => {
+					data.push(DataMode::ActiveMemory0 as u8);
+					match &segment.offset {
+						Some(offset) => write_expression(&mut data, &offset.instructions),
+						None => write_expression(&mut data, &[Instruction::I32Const(segment.addr as i32)]),
+					}
+					&segment.data[..]
+				},
+				Data::Passive(bytes) => {
+					data.push(DataMode::Passive as u8);
+					bytes
+				},
+			};
+			write_u32(&mut data, bytes.len());
+			data.extend_from_slice(bytes);
+		}
+		write_section(&mut binary, SectionId::Data, &data);
+	}
+
+	for custom_section in &module.custom_sections {
+		let mut content = Vec::new();
+		write_name(&mut content, &custom_section.name);
+		content.extend_from_slice(&custom_section.data);
+		write_section(&mut binary, SectionId::Custom, &content);
+	}
+
+	binary
+}
+
+/// Index of the first signature in the type section of `module` which equals `signature`.
+fn type_index(module: &Module, signature: &FunctionSignature) -> usize {
+	module.types.iter()
+		.position(|declared| **declared == *signature)
+		.expect("Signature is declared in the type section")
+}
+
+pub(crate) fn write_u32(buffer: &mut Vec<u8>, value: usize) {
+	leb128::write::unsigned(buffer, value as u64).expect("Writing to a Vec does not fail");
+}
+
+fn write_i64(buffer: &mut Vec<u8>, value: i64) {
+	leb128::write::signed(buffer, value).expect("Writing to a Vec does not fail");
+}
+
+pub(crate) fn write_name(buffer: &mut Vec<u8>, name: &str) {
+	write_u32(buffer, name.len());
+	buffer.extend_from_slice(name.as_bytes());
+}
+
+/// Writes the module and field name of an import of `kind`, which is followed by its type.
+fn write_import_name(buffer: &mut Vec<u8>, name: &Identifier, kind: ExportKind) {
+	write_name(buffer, &name.module);
+	write_name(buffer, &name.field);
+	buffer.push(kind as u8);
+}
+
+/// Writes the section with its id and size.
+pub(crate) fn write_section(buffer: &mut Vec<u8>, id: SectionId, content: &[u8]) {
+	buffer.push(id as u8);
+	write_u32(buffer, content.len());
+	buffer.extend_from_slice(content);
+}
+
+fn write_types(buffer: &mut Vec<u8>, types: &[Type]) {
+	write_u32(buffer, types.len());
+	buffer.extend(types.iter().map(|ty| *ty as u8));
+}
+
+/// A segment of the data section.
+enum Data<'m> {
+	Active(&'m DataSegment),
+	Passive(&'m [u8]),
+}
+
+/// The data segments of `module` by data index. Active segments fill the indices of the active ones in
+/// [`Module::passive_data`] in order, and an index without one becomes an empty passive segment, which behaves like the
+/// dropped active segment. Remaining active segments follow, e.g. the ones added by [`wizen`](crate::wizen::wizen).
+fn data_segments(module: &Module) -> Vec<Data<'_>> {
+	let mut active = module.memory_blueprint.iter().flat_map(|memory| &memory.init);
+	let mut segments: Vec<Data> = module.passive_data.iter()
+		.map(|passive| match passive {
+			Some(bytes) => Data::Passive(bytes),
+			None => active.next().map_or(Data::Passive(&[]), Data::Active),
+		})
+		.collect();
+	segments.extend(active.map(Data::Active));
+	segments
+}
+
+/// Writes the limits, where a maximum of `u32::MAX` is the absence of a maximum like in the parser.
+fn write_limits(buffer: &mut Vec<u8>, limit: &Range<usize>, shared: bool) {
+	if shared {
+		buffer.push(LimitKind::SharedMinMax as u8);
+		write_u32(buffer, limit.start);
+		write_u32(buffer, limit.end);
+	} else if limit.end == u32::MAX as usize {
+		buffer.push(LimitKind::Min as u8);
+		write_u32(buffer, limit.start);
+	} else {
+		buffer.push(LimitKind::MinMax as u8);
+		write_u32(buffer, limit.start);
+		write_u32(buffer, limit.end);
+	}
+}
+
+/// Writes the local declarations, merging consecutive locals of the same type into one declaration.
+fn write_locals(buffer: &mut Vec<u8>, locals: &[Type]) {
+	let mut declarations: Vec<(usize, Type)> = Vec::new();
+	for local in locals {
+		match declarations.last_mut() {
+			Some((count, ty)) if ty == local => *count += 1,
+			_ => declarations.push((1, *local)),
+		}
+	}
+	write_u32(buffer, declarations.len());
+	for (count, ty) in declarations {
+		write_u32(buffer, count);
+		buffer.push(ty as u8);
+	}
+}
+
+/// Writes the element segment in the form with expressions, which can represent all segments.
+fn write_element_segment(buffer: &mut Vec<u8>, segment: &ElementSegment) {
+	match segment.mode {
+		ElementMode::Active { table, offset, ref offset_expr } => {
+			write_u32(buffer, 6);
+			write_u32(buffer, table);
+			match offset_expr {
+				Some(offset_expr) => write_expression(buffer, &offset_expr.instructions),
+				None => write_expression(buffer, &[Instruction::I32Const(offset as i32)]),
+			}
+		},
+		ElementMode::Passive => write_u32(buffer, 5),
+		ElementMode::Declarative => write_u32(buffer, 7),
+	}
+	buffer.push(segment.element_type as u8);
+	write_u32(buffer, segment.init.len());
+	for element in &segment.init {
+		let expression = match element {
+			Some(function_index) => Instruction::RefFunc(*function_index),
+			None => Instruction::RefNull(segment.element_type),
+		};
+		write_expression(buffer, &[expression]);
+	}
+}
+
+/// Writes the instructions followed by [`Opcode::End`].
+fn write_expression(buffer: &mut Vec<u8>, instructions: &[Instruction]) {
+	for instruction in instructions {
+		write_instruction(buffer, instruction);
+	}
+	buffer.push(Opcode::End as u8);
+}
+
+fn write_block_type(buffer: &mut Vec<u8>, block_type: &BlockType) {
+	match block_type {
+		BlockType::Empty => buffer.push(0x40),
+		BlockType::Value(ty) => buffer.push(*ty as u8),
+		BlockType::TypeIndex(type_index) => write_i64(buffer, *type_index as i64),
+	}
+}
+
+fn write_memarg(buffer: &mut Vec<u8>, mem_arg: &MemArg) {
+	write_u32(buffer, mem_arg.align as usize);
+	write_u32(buffer, mem_arg.offset as usize);
+}
+
+/// Writes the `prefix` opcode followed by the sub-opcode `opcode`.
+fn write_prefixed(buffer: &mut Vec<u8>, prefix: Opcode, opcode: u32) {
+	buffer.push(prefix as u8);
+	write_u32(buffer, opcode as usize);
+}
+
+fn write_instruction(buffer: &mut Vec<u8>, instruction: &Instruction) {
+	match instruction {
+		Instruction::Block { block_type, instructions } => {
+			buffer.push(Opcode::Block as u8);
+			write_block_type(buffer, block_type);
+			write_expression(buffer, instructions);
+		},
+		Instruction::Loop { block_type, instructions } => {
+			buffer.push(Opcode::Loop as u8);
+			write_block_type(buffer, block_type);
+			write_expression(buffer, instructions);
+		},
+		Instruction::If { block_type, if_instructions, else_instructions } => {
+			buffer.push(Opcode::If as u8);
+			write_block_type(buffer, block_type);
+			for instruction in if_instructions {
+				write_instruction(buffer, instruction);
+			}
+			if !else_instructions.is_empty() {
+				buffer.push(Opcode::Else as u8);
+			}
+			write_expression(buffer, else_instructions);
+		},
+		Instruction::Try { block_type, instructions, catches, delegate } => {
+			buffer.push(Opcode::Try as u8);
+			write_block_type(buffer, block_type);
+			for instruction in instructions {
+				write_instruction(buffer, instruction);
+			}
+			for catch in catches {
+				match catch.tag {
+					Some(tag) => write_indexed(buffer, Opcode::Catch, tag),
+					None => buffer.push(Opcode::CatchAll as u8),
+				}
+				for instruction in &catch.instructions {
+					write_instruction(buffer, instruction);
+				}
+			}
+			match delegate {
+				Some(label_index) => write_indexed(buffer, Opcode::Delegate, *label_index as usize),
+				None => buffer.push(Opcode::End as u8),
+			}
+		},
+		Instruction::Throw(tag) => write_indexed(buffer, Opcode::Throw, *tag),
+		Instruction::Rethrow(label_index) => write_indexed(buffer, Opcode::Rethrow, *label_index as usize),
+		Instruction::Br { label_index } => {
+			buffer.push(Opcode::Br as u8);
+			write_u32(buffer, *label_index as usize);
+		},
+		Instruction::BrIf { label_index } => {
+			buffer.push(Opcode::BrIf as u8);
+			write_u32(buffer, *label_index as usize);
+		},
+		Instruction::BrTable { label_indexes, default } => {
+			buffer.push(Opcode::BrTable as u8);
+			write_u32(buffer, label_indexes.len());
+			for label in label_indexes {
+				write_u32(buffer, *label as usize);
+			}
+			write_u32(buffer, *default as usize);
+		},
+		Instruction::Call { function_index } => {
+			buffer.push(Opcode::Call as u8);
+			write_u32(buffer, *function_index);
+		},
+		Instruction::CallIndirect { table_index, type_index } => {
+			buffer.push(Opcode::CallIndirect as u8);
+			write_u32(buffer, *type_index);
+			write_u32(buffer, *table_index);
+		},
+		Instruction::ReturnCall { function_index } => {
+			buffer.push(Opcode::ReturnCall as u8);
+			write_u32(buffer, *function_index);
+		},
+		Instruction::ReturnCallIndirect { table_index, type_index } => {
+			buffer.push(Opcode::ReturnCallIndirect as u8);
+			write_u32(buffer, *type_index);
+			write_u32(buffer, *table_index);
+		},
+		Instruction::RefNull(ty) => {
+			buffer.push(Opcode::RefNull as u8);
+			buffer.push(*ty as u8);
+		},
+		// The operand types of a typed select are not known, but an untyped select is valid for numeric operands
+		Instruction::Select | Instruction::SelectValueType => buffer.push(Opcode::Select as u8),
+		Instruction::Extension => unimplemented!("Encoding the placeholder {:?}", instruction),
+		Instruction::RefFunc(index) => write_indexed(buffer, Opcode::RefFunc, *index),
+		Instruction::LocalGet(index) => write_indexed(buffer, Opcode::LocalGet, *index),
+		Instruction::LocalSet(index) => write_indexed(buffer, Opcode::LocalSet, *index),
+		Instruction::LocalTee(index) => write_indexed(buffer, Opcode::LocalTee, *index),
+		Instruction::GlobalGet(index) => write_indexed(buffer, Opcode::GlobalGet, *index),
+		Instruction::GlobalSet(index) => write_indexed(buffer, Opcode::GlobalSet, *index),
+		Instruction::TableGet(index) => write_indexed(buffer, Opcode::TableGet, *index),
+		Instruction::TableSet(index) => write_indexed(buffer, Opcode::TableSet, *index),
+		Instruction::I32Load(mem_arg) => write_memory_instruction(buffer, Opcode::I32Load, mem_arg),
+		Instruction::I64Load(mem_arg) => write_memory_instruction(buffer, Opcode::I64Load, mem_arg),
+		Instruction::F32Load(mem_arg) => write_memory_instruction(buffer, Opcode::F32Load, mem_arg),
+		Instruction::F64Load(mem_arg) => write_memory_instruction(buffer, Opcode::F64Load, mem_arg),
+		Instruction::I32Load8s(mem_arg) => write_memory_instruction(buffer, Opcode::I32Load8s, mem_arg),
+		Instruction::I32Load8u(mem_arg) => write_memory_instruction(buffer, Opcode::I32Load8u, mem_arg),
+		Instruction::I32Load16s(mem_arg) => write_memory_instruction(buffer, Opcode::I32Load16s, mem_arg),
+		Instruction::I32Load16u(mem_arg) => write_memory_instruction(buffer, Opcode::I32Load16u, mem_arg),
+		Instruction::I64Load8s(mem_arg) => write_memory_instruction(buffer, Opcode::I64Load8s, mem_arg),
+		Instruction::I64Load8u(mem_arg) => write_memory_instruction(buffer, Opcode::I64Load8u, mem_arg),
+		Instruction::I64Load16s(mem_arg) => write_memory_instruction(buffer, Opcode::I64Load16s, mem_arg),
+		Instruction::I64Load16u(mem_arg) => write_memory_instruction(buffer, Opcode::I64Load16u, mem_arg),
+		Instruction::I64Load32s(mem_arg) => write_memory_instruction(buffer, Opcode::I64Load32s, mem_arg),
+		Instruction::I64Load32u(mem_arg) => write_memory_instruction(buffer, Opcode::I64Load32u, mem_arg),
+		Instruction::I32Store(mem_arg) => write_memory_instruction(buffer, Opcode::I32Store, mem_arg),
+		Instruction::I64Store(mem_arg) => write_memory_instruction(buffer, Opcode::I64Store, mem_arg),
+		Instruction::F32Store(mem_arg) => write_memory_instruction(buffer, Opcode::F32Store, mem_arg),
+		Instruction::F64Store(mem_arg) => write_memory_instruction(buffer, Opcode::F64Store, mem_arg),
+		Instruction::I32Store8(mem_arg) => write_memory_instruction(buffer, Opcode::I32Store8, mem_arg),
+		Instruction::I32Store16(mem_arg) => write_memory_instruction(buffer, Opcode::I32Store16, mem_arg),
+		Instruction::I64Store8(mem_arg) => write_memory_instruction(buffer, Opcode::I64Store8, mem_arg),
+		Instruction::I64Store16(mem_arg) => write_memory_instruction(buffer, Opcode::I64Store16, mem_arg),
+		Instruction::I64Store32(mem_arg) => write_memory_instruction(buffer, Opcode::I64Store32, mem_arg),
+		// Followed by the index of the memory
+		Instruction::MemorySize => buffer.extend([Opcode::MemorySize as u8, 0x00]),
+		Instruction::MemoryGrow => buffer.extend([Opcode::MemoryGrow as u8, 0x00]),
+		Instruction::I32Const(value) => {
+			buffer.push(Opcode::I32Const as u8);
+			write_i64(buffer, *value as i64);
+		},
+		Instruction::I64Const(value) => {
+			buffer.push(Opcode::I64Const as u8);
+			write_i64(buffer, *value);
+		},
+		Instruction::F32Const(value) => {
+			buffer.push(Opcode::F32Const as u8);
+			buffer.extend_from_slice(&value.to_le_bytes());
+		},
+		Instruction::F64Const(value) => {
+			buffer.push(Opcode::F64Const as u8);
+			buffer.extend_from_slice(&value.to_le_bytes());
+		},
+		Instruction::I32TruncSatF32S => write_prefixed(buffer, Opcode::Extension, ExtensionOpcode::I32TruncSatF32S as u32),
+		Instruction::I32TruncSatF32U => write_prefixed(buffer, Opcode::Extension, ExtensionOpcode::I32TruncSatF32U as u32),
+		Instruction::I32TruncSatF64S => write_prefixed(buffer, Opcode::Extension, ExtensionOpcode::I32TruncSatF64S as u32),
+		Instruction::I32TruncSatF64U => write_prefixed(buffer, Opcode::Extension, ExtensionOpcode::I32TruncSatF64U as u32),
+		Instruction::I64TruncSatF32S => write_prefixed(buffer, Opcode::Extension, ExtensionOpcode::I64TruncSatF32S as u32),
+		Instruction::I64TruncSatF32U => write_prefixed(buffer, Opcode::Extension, ExtensionOpcode::I64TruncSatF32U as u32),
+		Instruction::I64TruncSatF64S => write_prefixed(buffer, Opcode::Extension, ExtensionOpcode::I64TruncSatF64S as u32),
+		Instruction::I64TruncSatF64U => write_prefixed(buffer, Opcode::Extension, ExtensionOpcode::I64TruncSatF64U as u32),
+		Instruction::MemoryInit(data_index) => {
+			write_prefixed(buffer, Opcode::Extension, ExtensionOpcode::MemoryInit as u32);
+			write_u32(buffer, *data_index);
+			buffer.push(0x00);
+		},
+		Instruction::DataDrop(data_index) => {
+			write_prefixed(buffer, Opcode::Extension, ExtensionOpcode::DataDrop as u32);
+			write_u32(buffer, *data_index);
+		},
+		Instruction::MemoryCopy => {
+			write_prefixed(buffer, Opcode::Extension, ExtensionOpcode::MemoryCopy as u32);
+			buffer.extend([0x00, 0x00]);
+		},
+		Instruction::MemoryFill => {
+			write_prefixed(buffer, Opcode::Extension, ExtensionOpcode::MemoryFill as u32);
+			buffer.push(0x00);
+		},
+		Instruction::TableInit { table_index, element_index } => {
+			write_prefixed(buffer, Opcode::Extension, ExtensionOpcode::TableInit as u32);
+			write_u32(buffer, *element_index);
+			write_u32(buffer, *table_index);
+		},
+		Instruction::ElemDrop(index) => {
+			write_prefixed(buffer, Opcode::Extension, ExtensionOpcode::ElemDrop as u32);
+			write_u32(buffer, *index);
+		},
+		Instruction::TableCopy { dst_table_index, src_table_index } => {
+			write_prefixed(buffer, Opcode::Extension, ExtensionOpcode::TableCopy as u32);
+			write_u32(buffer, *dst_table_index);
+			write_u32(buffer, *src_table_index);
+		},
+		Instruction::TableGrow(index) => {
+			write_prefixed(buffer, Opcode::Extension, ExtensionOpcode::TableGrow as u32);
+			write_u32(buffer, *index);
+		},
+		Instruction::TableSize(index) => {
+			write_prefixed(buffer, Opcode::Extension, ExtensionOpcode::TableSize as u32);
+			write_u32(buffer, *index);
+		},
+		Instruction::TableFill(index) => {
+			write_prefixed(buffer, Opcode::Extension, ExtensionOpcode::TableFill as u32);
+			write_u32(buffer, *index);
+		},
+		Instruction::V128Load(mem_arg) => {
+			write_prefixed(buffer, Opcode::Simd, SimdOpcode::V128Load as u32);
+			write_memarg(buffer, mem_arg);
+		},
+		Instruction::V128Store(mem_arg) => {
+			write_prefixed(buffer, Opcode::Simd, SimdOpcode::V128Store as u32);
+			write_memarg(buffer, mem_arg);
+		},
+		Instruction::V128Const(bytes) => {
+			write_prefixed(buffer, Opcode::Simd, SimdOpcode::V128Const as u32);
+			buffer.extend_from_slice(bytes);
+		},
+		Instruction::I8x16Shuffle(lanes) => {
+			write_prefixed(buffer, Opcode::Simd, SimdOpcode::I8x16Shuffle as u32);
+			buffer.extend_from_slice(lanes);
+		},
+		Instruction::I8x16ExtractLaneS(lane) => write_lane_instruction(buffer, SimdOpcode::I8x16ExtractLaneS, *lane),
+		Instruction::I8x16ExtractLaneU(lane) => write_lane_instruction(buffer, SimdOpcode::I8x16ExtractLaneU, *lane),
+		Instruction::I8x16ReplaceLane(lane) => write_lane_instruction(buffer, SimdOpcode::I8x16ReplaceLane, *lane),
+		Instruction::I16x8ExtractLaneS(lane) => write_lane_instruction(buffer, SimdOpcode::I16x8ExtractLaneS, *lane),
+		Instruction::I16x8ExtractLaneU(lane) => write_lane_instruction(buffer, SimdOpcode::I16x8ExtractLaneU, *lane),
+		Instruction::I16x8ReplaceLane(lane) => write_lane_instruction(buffer, SimdOpcode::I16x8ReplaceLane, *lane),
+		Instruction::I32x4ExtractLane(lane) => write_lane_instruction(buffer, SimdOpcode::I32x4ExtractLane, *lane),
+		Instruction::I32x4ReplaceLane(lane) => write_lane_instruction(buffer, SimdOpcode::I32x4ReplaceLane, *lane),
+		Instruction::I64x2ExtractLane(lane) => write_lane_instruction(buffer, SimdOpcode::I64x2ExtractLane, *lane),
+		Instruction::I64x2ReplaceLane(lane) => write_lane_instruction(buffer, SimdOpcode::I64x2ReplaceLane, *lane),
+		Instruction::F32x4ExtractLane(lane) => write_lane_instruction(buffer, SimdOpcode::F32x4ExtractLane, *lane),
+		Instruction::F32x4ReplaceLane(lane) => write_lane_instruction(buffer, SimdOpcode::F32x4ReplaceLane, *lane),
+		Instruction::F64x2ExtractLane(lane) => write_lane_instruction(buffer, SimdOpcode::F64x2ExtractLane, *lane),
+		Instruction::F64x2ReplaceLane(lane) => write_lane_instruction(buffer, SimdOpcode::F64x2ReplaceLane, *lane),
+		Instruction::MemoryAtomicNotify(mem_arg) => write_atomic_instruction(buffer, AtomicOpcode::MemoryAtomicNotify as u32, mem_arg),
+		Instruction::MemoryAtomicWait32(mem_arg) => write_atomic_instruction(buffer, AtomicOpcode::MemoryAtomicWait32 as u32, mem_arg),
+		Instruction::MemoryAtomicWait64(mem_arg) => write_atomic_instruction(buffer, AtomicOpcode::MemoryAtomicWait64 as u32, mem_arg),
+		Instruction::AtomicFence => {
+			write_prefixed(buffer, Opcode::Atomic, AtomicOpcode::AtomicFence as u32);
+			// Reserved byte for the memory ordering
+			buffer.push(0x00);
+		},
+		// The sub-opcodes of each kind of atomic access are ordered like the variants of AtomicWidth, and the ones of
+		// the read-modify-write operations like the variants of AtomicRmwOp
+		Instruction::AtomicLoad(width, mem_arg) => {
+			write_atomic_instruction(buffer, AtomicOpcode::I32AtomicLoad as u32 + *width as u32, mem_arg)
+		},
+		Instruction::AtomicStore(width, mem_arg) => {
+			write_atomic_instruction(buffer, AtomicOpcode::I32AtomicStore as u32 + *width as u32, mem_arg)
+		},
+		Instruction::AtomicRmw(op, width, mem_arg) => {
+			let opcode = AtomicOpcode::I32AtomicRmwAdd as u32 + *op as u32 * 7 + *width as u32;
+			write_atomic_instruction(buffer, opcode, mem_arg)
+		},
+		Instruction::AtomicCmpxchg(width, mem_arg) => {
+			write_atomic_instruction(buffer, AtomicOpcode::I32AtomicRmwCmpxchg as u32 + *width as u32, mem_arg)
+		},
+		other => match simd_opcode(other) {
+			Some(opcode) => write_prefixed(buffer, Opcode::Simd, opcode as u32),
+			None => buffer.push(plain_opcode(other) as u8),
+		},
+	}
+}
+
+fn write_indexed(buffer: &mut Vec<u8>, opcode: Opcode, index: usize) {
+	buffer.push(opcode as u8);
+	write_u32(buffer, index);
+}
+
+fn write_memory_instruction(buffer: &mut Vec<u8>, opcode: Opcode, mem_arg: &MemArg) {
+	buffer.push(opcode as u8);
+	write_memarg(buffer, mem_arg);
+}
+
+fn write_lane_instruction(buffer: &mut Vec<u8>, opcode: SimdOpcode, lane: u8) {
+	write_prefixed(buffer, Opcode::Simd, opcode as u32);
+	buffer.push(lane);
+}
+
+fn write_atomic_instruction(buffer: &mut Vec<u8>, opcode: u32, mem_arg: &MemArg) {
+	write_prefixed(buffer, Opcode::Atomic, opcode);
+	write_memarg(buffer, mem_arg);
+}
+
+/// The sub-opcode of a vector instruction without immediates.
+fn simd_opcode(instruction: &Instruction) -> Option<SimdOpcode> {
+	let opcode = match instruction {
+		Instruction::I8x16Swizzle => SimdOpcode::I8x16Swizzle,
+		Instruction::I8x16Splat => SimdOpcode::I8x16Splat,
+		Instruction::I16x8Splat => SimdOpcode::I16x8Splat,
+		Instruction::I32x4Splat => SimdOpcode::I32x4Splat,
+		Instruction::I64x2Splat => SimdOpcode::I64x2Splat,
+		Instruction::F32x4Splat => SimdOpcode::F32x4Splat,
+		Instruction::F64x2Splat => SimdOpcode::F64x2Splat,
+		Instruction::I8x16Eq => SimdOpcode::I8x16Eq,
+		Instruction::I16x8Eq => SimdOpcode::I16x8Eq,
+		Instruction::I32x4Eq => SimdOpcode::I32x4Eq,
+		Instruction::V128Not => SimdOpcode::V128Not,
+		Instruction::V128And => SimdOpcode::V128And,
+		Instruction::V128AndNot => SimdOpcode::V128AndNot,
+		Instruction::V128Or => SimdOpcode::V128Or,
+		Instruction::V128Xor => SimdOpcode::V128Xor,
+		Instruction::V128Bitselect => SimdOpcode::V128Bitselect,
+		Instruction::V128AnyTrue => SimdOpcode::V128AnyTrue,
+		Instruction::I8x16AllTrue => SimdOpcode::I8x16AllTrue,
+		Instruction::I8x16Bitmask => SimdOpcode::I8x16Bitmask,
+		Instruction::I8x16Add => SimdOpcode::I8x16Add,
+		Instruction::I8x16Sub => SimdOpcode::I8x16Sub,
+		Instruction::I16x8Add => SimdOpcode::I16x8Add,
+		Instruction::I16x8Sub => SimdOpcode::I16x8Sub,
+		Instruction::I16x8Mul => SimdOpcode::I16x8Mul,
+		Instruction::I32x4Add => SimdOpcode::I32x4Add,
+		Instruction::I32x4Sub => SimdOpcode::I32x4Sub,
+		Instruction::I32x4Mul => SimdOpcode::I32x4Mul,
+		Instruction::I64x2Add => SimdOpcode::I64x2Add,
+		Instruction::I64x2Sub => SimdOpcode::I64x2Sub,
+		Instruction::I64x2Mul => SimdOpcode::I64x2Mul,
+		_ => return None,
+	};
+	Some(opcode)
+}
+
+/// The opcode of an instruction without immediates.
+fn plain_opcode(instruction: &Instruction) -> Opcode {
+	match instruction {
+		Instruction::Unreachable => Opcode::Unreachable,
+		Instruction::Nop => Opcode::Nop,
+		Instruction::Return => Opcode::Return,
+		Instruction::I32Eqz => Opcode::I32Eqz,
+		Instruction::I32Eq => Opcode::I32Eq,
+		Instruction::I32Ne => Opcode::I32Ne,
+		Instruction::I32LtS => Opcode::I32LtS,
+		Instruction::I32LtU => Opcode::I32LtU,
+		Instruction::I32GtS => Opcode::I32GtS,
+		Instruction::I32GtU => Opcode::I32GtU,
+		Instruction::I32LeS => Opcode::I32LeS,
+		Instruction::I32LeU => Opcode::I32LeU,
+		Instruction::I32GeS => Opcode::I32GeS,
+		Instruction::I32GeU => Opcode::I32GeU,
+		Instruction::I64Eqz => Opcode::I64Eqz,
+		Instruction::I64Eq => Opcode::I64Eq,
+		Instruction::I64Ne => Opcode::I64Ne,
+		Instruction::I64LtS => Opcode::I64LtS,
+		Instruction::I64LtU => Opcode::I64LtU,
+		Instruction::I64GtS => Opcode::I64GtS,
+		Instruction::I64GtU => Opcode::I64GtU,
+		Instruction::I64LeS => Opcode::I64LeS,
+		Instruction::I64LeU => Opcode::I64LeU,
+		Instruction::I64GeS => Opcode::I64GeS,
+		Instruction::I64GeU => Opcode::I64GeU,
+		Instruction::F32Eq => Opcode::F32Eq,
+		Instruction::F32Ne => Opcode::F32Ne,
+		Instruction::F32Lt => Opcode::F32Lt,
+		Instruction::F32Gt => Opcode::F32Gt,
+		Instruction::F32Le => Opcode::F32Le,
+		Instruction::F32Ge => Opcode::F32Ge,
+		Instruction::F64Eq => Opcode::F64Eq,
+		Instruction::F64Ne => Opcode::F64Ne,
+		Instruction::F64Lt => Opcode::F64Lt,
+		Instruction::F64Gt => Opcode::F64Gt,
+		Instruction::F64Le => Opcode::F64Le,
+		Instruction::F64Ge => Opcode::F64Ge,
+		Instruction::I32Clz => Opcode::I32Clz,
+		Instruction::I32Ctz => Opcode::I32Ctz,
+		Instruction::I32Popcnt => Opcode::I32Popcnt,
+		Instruction::I32Add => Opcode::I32Add,
+		Instruction::I32Sub => Opcode::I32Sub,
+		Instruction::I32Mul => Opcode::I32Mul,
+		Instruction::I32DivS => Opcode::I32DivS,
+		Instruction::I32DivU => Opcode::I32DivU,
+		Instruction::I32RemS => Opcode::I32RemS,
+		Instruction::I32RemU => Opcode::I32RemU,
+		Instruction::I32And => Opcode::I32And,
+		Instruction::I32Or => Opcode::I32Or,
+		Instruction::I32Xor => Opcode::I32Xor,
+		Instruction::I32Shl => Opcode::I32Shl,
+		Instruction::I32ShrS => Opcode::I32ShrS,
+		Instruction::I32ShrU => Opcode::I32ShrU,
+		Instruction::I32Rotl => Opcode::I32Rotl,
+		Instruction::I32Rotr => Opcode::I32Rotr,
+		Instruction::I64Clz => Opcode::I64Clz,
+		Instruction::I64Ctz => Opcode::I64Ctz,
+		Instruction::I64Popcnt => Opcode::I64Popcnt,
+		Instruction::I64Add => Opcode::I64Add,
+		Instruction::I64Sub => Opcode::I64Sub,
+		Instruction::I64Mul => Opcode::I64Mul,
+		Instruction::I64DivS => Opcode::I64DivS,
+		Instruction::I64DivU => Opcode::I64DivU,
+		Instruction::I64RemS => Opcode::I64RemS,
+		Instruction::I64RemU => Opcode::I64RemU,
+		Instruction::I64And => Opcode::I64And,
+		Instruction::I64Or => Opcode::I64Or,
+		Instruction::I64Xor => Opcode::I64Xor,
+		Instruction::I64Shl => Opcode::I64Shl,
+		Instruction::I64ShrS => Opcode::I64ShrS,
+		Instruction::I64ShrU => Opcode::I64ShrU,
+		Instruction::I64Rotl => Opcode::I64Rotl,
+		Instruction::I64Rotr => Opcode::I64Rotr,
+		Instruction::F32Abs => Opcode::F32Abs,
+		Instruction::F32Neg => Opcode::F32Neg,
+		Instruction::F32Ceil => Opcode::F32Ceil,
+		Instruction::F32Floor => Opcode::F32Floor,
+		Instruction::F32Trunc => Opcode::F32Trunc,
+		Instruction::F32Nearest => Opcode::F32Nearest,
+		Instruction::F32Sqrt => Opcode::F32Sqrt,
+		Instruction::F32Add => Opcode::F32Add,
+		Instruction::F32Sub => Opcode::F32Sub,
+		Instruction::F32Mul => Opcode::F32Mul,
+		Instruction::F32Div => Opcode::F32Div,
+		Instruction::F32Min => Opcode::F32Min,
+		Instruction::F32Max => Opcode::F32Max,
+		Instruction::F32Copysign => Opcode::F32Copysign,
+		Instruction::F64Abs => Opcode::F64Abs,
+		Instruction::F64Neg => Opcode::F64Neg,
+		Instruction::F64Ceil => Opcode::F64Ceil,
+		Instruction::F64Floor => Opcode::F64Floor,
+		Instruction::F64Trunc => Opcode::F64Trunc,
+		Instruction::F64Nearest => Opcode::F64Nearest,
+		Instruction::F64Sqrt => Opcode::F64Sqrt,
+		Instruction::F64Add => Opcode::F64Add,
+		Instruction::F64Sub => Opcode::F64Sub,
+		Instruction::F64Mul => Opcode::F64Mul,
+		Instruction::F64Div => Opcode::F64Div,
+		Instruction::F64Min => Opcode::F64Min,
+		Instruction::F64Max => Opcode::F64Max,
+		Instruction::F64Copysign => Opcode::F64Copysign,
+		Instruction::I32WrapI64 => Opcode::I32WrapI64,
+		Instruction::I32TruncF32S => Opcode::I32TruncF32S,
+		Instruction::I32TruncF32U => Opcode::I32TruncF32U,
+		Instruction::I32TruncF64S => Opcode::I32TruncF64S,
+		Instruction::I32TruncF64U => Opcode::I32TruncF64U,
+		Instruction::I64ExtendI32S => Opcode::I64ExtendI32S,
+		Instruction::I64ExtendI32U => Opcode::I64ExtendI32U,
+		Instruction::I64TruncF32S => Opcode::I64TruncF32S,
+		Instruction::I64TruncF32U => Opcode::I64TruncF32U,
+		Instruction::I64TruncF64S => Opcode::I64TruncF64S,
+		Instruction::I64TruncF64U => Opcode::I64TruncF64U,
+		Instruction::F32ConvertI32S => Opcode::F32ConvertI32S,
+		Instruction::F32ConvertI32U => Opcode::F32ConvertI32U,
+		Instruction::F32ConvertI64S => Opcode::F32ConvertI64S,
+		Instruction::F32ConvertI64 => Opcode::F32ConvertI64,
+		Instruction::F32DemoteF64 => Opcode::F32DemoteF64,
+		Instruction::F64ConvertI32S => Opcode::F64ConvertI32S,
+		Instruction::F64ConvertI32U => Opcode::F64ConvertI32U,
+		Instruction::F64ConvertI64S => Opcode::F64ConvertI64S,
+		Instruction::F64ConvertI64U => Opcode::F64ConvertI64U,
+		Instruction::F64PromoteF32 => Opcode::F64PromoteF32,
+		Instruction::I32ReinterpretF32 => Opcode::I32ReinterpretF32,
+		Instruction::I64ReinterpretF64 => Opcode::I64ReinterpretF64,
+		Instruction::F32ReinterpretI32 => Opcode::F32ReinterpretI32,
+		Instruction::F64ReinterpretI64 => Opcode::F64ReinterpretI64,
+		Instruction::I32Extend8S => Opcode::I32Extend8S,
+		Instruction::I32Extend16S => Opcode::I32Extend16S,
+		Instruction::I64Extend8S => Opcode::I64Extend8S,
+		Instruction::I64Extend16S => Opcode::I64Extend16S,
+		Instruction::I64Extend32S => Opcode::I64Extend32S,
+		Instruction::Drop => Opcode::Drop,
+		Instruction::RefIsNull => Opcode::RefIsNull,
+		other => unreachable!("{:?} has immediates", other),
+	}
+}
\ No newline at end of file
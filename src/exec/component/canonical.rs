@@ -0,0 +1,260 @@
+//! The canonical ABI, which defines how component values are passed as core values and in the linear memory.
+//! <https://github.com/WebAssembly/component-model/blob/main/design/mvp/CanonicalABI.md>
+//!
+//! Besides components, the helpers can be used to exchange strings, lists and records with core modules following
+//! the canonical ABI, e.g. Rust guests exporting `cabi_realloc`, which is passed as `realloc` after looking it up
+//! with [`Instance::func`].
+
+use std::any;
+use crate::exec::{ComponentValue, Error, Func, Instance, InstanceRef, Mismatch, TrapKind, Value};
+use crate::parse::Type;
+use crate::parse::component::ValueType;
+
+/// Maximum number of core parameters, above which the parameters are passed in memory.
+pub(crate) const MAX_FLAT_PARAMS: usize = 16;
+/// Maximum number of core results, above which the results are passed in memory.
+pub(crate) const MAX_FLAT_RESULTS: usize = 1;
+
+/// The memory and functions of a guest, which is either an [Instance] or the [InstanceRef] passed to a host function.
+pub trait Guest {
+	fn memory(&mut self) -> Result<&mut [u8], Error>;
+	fn call(&mut self, func: &Func, args: Vec<Value>) -> Result<Vec<Value>, Error>;
+}
+
+impl Guest for Instance {
+	fn memory(&mut self) -> Result<&mut [u8], Error> {
+		self.memory_mut().map(|memory| &mut memory.data[..]).ok_or(Error::NoMemory)
+	}
+
+	fn call(&mut self, func: &Func, args: Vec<Value>) -> Result<Vec<Value>, Error> {
+		func.call(self, args)
+	}
+}
+
+impl Guest for InstanceRef<'_> {
+	fn memory(&mut self) -> Result<&mut [u8], Error> {
+		self.memory.as_mut().map(|memory| &mut memory.data[..]).ok_or(Error::NoMemory)
+	}
+
+	fn call(&mut self, func: &Func, args: Vec<Value>) -> Result<Vec<Value>, Error> {
+		func.call_from(self, args)
+	}
+}
+
+/// The core types values of `types` are passed as.
+pub fn flatten(types: impl IntoIterator<Item = ValueType>) -> Vec<Type> {
+	types.into_iter()
+		.flat_map(|ty| match ty {
+			ValueType::S64 | ValueType::U64 => &[Type::I64][..],
+			ValueType::F32 => &[Type::F32],
+			ValueType::F64 => &[Type::F64],
+			// Pointer and length
+			ValueType::String => &[Type::I32, Type::I32],
+			_ => &[Type::I32],
+		})
+		.copied()
+		.collect()
+}
+
+/// Size and alignment of a value of type `ty` in memory.
+pub fn size_and_align(ty: ValueType) -> (usize, usize) {
+	match ty {
+		ValueType::Bool | ValueType::S8 | ValueType::U8 => (1, 1),
+		ValueType::S16 | ValueType::U16 => (2, 2),
+		ValueType::S32 | ValueType::U32 | ValueType::F32 | ValueType::Char => (4, 4),
+		ValueType::S64 | ValueType::U64 | ValueType::F64 => (8, 8),
+		ValueType::String => (8, 4),
+	}
+}
+
+/// Size and alignment of a record with fields of `types` in memory.
+pub fn record_layout(types: &[ValueType]) -> (usize, usize) {
+	let (size, align) = types.iter().fold((0usize, 1usize), |(size, align), &ty| {
+		let (field_size, field_align) = size_and_align(ty);
+		(size.next_multiple_of(field_align) + field_size, align.max(field_align))
+	});
+	(size.next_multiple_of(align), align)
+}
+
+/// Checks that `values` have the types `types`.
+pub fn check_types(types: &[ValueType], values: &[ComponentValue]) -> Result<(), Error> {
+	if !values.iter().map(ComponentValue::ty).eq(types.iter().copied()) {
+		let mismatch = Mismatch { expected: types.to_vec(), got: values.to_vec() };
+		return Err(Error::ComponentValueMismatch(Box::new(mismatch)));
+	}
+	Ok(())
+}
+
+/// Converts the next core values of `values` into a value of type `ty`.
+pub fn lift_flat(guest: &mut impl Guest, ty: ValueType, values: &mut impl Iterator<Item = Value>) -> Result<ComponentValue, Error> {
+	Ok(match ty {
+		ValueType::Bool => ComponentValue::Bool(next::<i32>(values)? != 0),
+		ValueType::S8 => ComponentValue::S8(next::<i32>(values)? as i8),
+		ValueType::U8 => ComponentValue::U8(next::<i32>(values)? as u8),
+		ValueType::S16 => ComponentValue::S16(next::<i32>(values)? as i16),
+		ValueType::U16 => ComponentValue::U16(next::<i32>(values)? as u16),
+		ValueType::S32 => ComponentValue::S32(next(values)?),
+		ValueType::U32 => ComponentValue::U32(next::<i32>(values)? as u32),
+		ValueType::S64 => ComponentValue::S64(next(values)?),
+		ValueType::U64 => ComponentValue::U64(next::<i64>(values)? as u64),
+		ValueType::F32 => ComponentValue::F32(next(values)?),
+		ValueType::F64 => ComponentValue::F64(next(values)?),
+		ValueType::Char => ComponentValue::Char(lift_char(next::<i32>(values)? as u32)?),
+		ValueType::String => {
+			let ptr = next::<i32>(values)? as u32 as usize;
+			let len = next::<i32>(values)? as u32 as usize;
+			ComponentValue::String(lift_string(guest, ptr, len)?)
+		},
+	})
+}
+
+/// Converts `value` into core values appended to `values`. Strings are copied into memory allocated with `realloc`.
+pub fn lower_flat(guest: &mut impl Guest, realloc: Option<&Func>, value: &ComponentValue, values: &mut Vec<Value>) -> Result<(), Error> {
+	match *value {
+		ComponentValue::Bool(value) => values.push(Value::I32(value as i32)),
+		ComponentValue::S8(value) => values.push(Value::I32(value as i32)),
+		ComponentValue::U8(value) => values.push(Value::I32(value as i32)),
+		ComponentValue::S16(value) => values.push(Value::I32(value as i32)),
+		ComponentValue::U16(value) => values.push(Value::I32(value as i32)),
+		ComponentValue::S32(value) => values.push(Value::I32(value)),
+		ComponentValue::U32(value) => values.push(Value::I32(value as i32)),
+		ComponentValue::S64(value) => values.push(Value::I64(value)),
+		ComponentValue::U64(value) => values.push(Value::I64(value as i64)),
+		ComponentValue::F32(value) => values.push(Value::F32(value)),
+		ComponentValue::F64(value) => values.push(Value::F64(value)),
+		ComponentValue::Char(value) => values.push(Value::I32(value as i32)),
+		ComponentValue::String(ref string) => {
+			let realloc = realloc.ok_or(Error::Trap(TrapKind::Other("passing a string requires the realloc option")))?;
+			let (ptr, len) = lower_string(guest, realloc, string)?;
+			values.push(Value::I32(ptr as i32));
+			values.push(Value::I32(len as i32));
+		},
+	}
+	Ok(())
+}
+
+/// Reads the fields of `types` of the record at `addr`.
+pub fn lift_record(guest: &mut impl Guest, types: &[ValueType], mut addr: usize) -> Result<Vec<ComponentValue>, Error> {
+	let mut values = Vec::with_capacity(types.len());
+	for &ty in types {
+		let (size, align) = size_and_align(ty);
+		addr = addr.next_multiple_of(align);
+		let bytes = read(guest, addr, size)?;
+		values.push(match ty {
+			ValueType::String => {
+				let ptr = u32::from_le_bytes(bytes[0..4].try_into().unwrap()) as usize;
+				let len = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+				ComponentValue::String(lift_string(guest, ptr, len)?)
+			},
+			_ => {
+				let mut integer = [0; 8];
+				integer[..size].copy_from_slice(&bytes);
+				let core_value = match ty {
+					ValueType::S8 => Value::I32(bytes[0] as i8 as i32),
+					ValueType::S16 => Value::I32(i16::from_le_bytes(integer[..2].try_into().unwrap()) as i32),
+					ValueType::S64 | ValueType::U64 => Value::I64(i64::from_le_bytes(integer)),
+					ValueType::F32 => Value::F32(f32::from_le_bytes(integer[..4].try_into().unwrap())),
+					ValueType::F64 => Value::F64(f64::from_le_bytes(integer)),
+					_ => Value::I32(u32::from_le_bytes(integer[..4].try_into().unwrap()) as i32),
+				};
+				lift_flat(guest, ty, &mut [core_value].into_iter())?
+			},
+		});
+		addr += size;
+	}
+	Ok(values)
+}
+
+/// Writes `values` as the fields of a record to `addr`. Strings are copied into memory allocated with `realloc`.
+pub fn lower_record(guest: &mut impl Guest, realloc: Option<&Func>, values: &[ComponentValue], mut addr: usize) -> Result<(), Error> {
+	for value in values {
+		let (size, align) = size_and_align(value.ty());
+		addr = addr.next_multiple_of(align);
+		let mut core_values = Vec::new();
+		lower_flat(guest, realloc, value, &mut core_values)?;
+		let bytes: Vec<u8> = core_values.into_iter()
+			.flat_map(|core_value| match core_value {
+				Value::I64(value) => value.to_le_bytes().to_vec(),
+				Value::F32(value) => value.to_le_bytes().to_vec(),
+				Value::F64(value) => value.to_le_bytes().to_vec(),
+				core_value => i32::try_from(core_value).unwrap_or_default().to_le_bytes().to_vec(),
+			})
+			.collect();
+		// Values smaller than 4 bytes are stored truncated
+		write(guest, addr, &bytes[..size])?;
+		addr += size;
+	}
+	Ok(())
+}
+
+/// Reads the `len` elements of type `ty` of the list at `ptr`.
+pub fn lift_list(guest: &mut impl Guest, ty: ValueType, ptr: usize, len: usize) -> Result<Vec<ComponentValue>, Error> {
+	let (size, _) = size_and_align(ty);
+	(0..len)
+		.map(|index| Ok(lift_record(guest, &[ty], ptr + index * size)?.remove(0)))
+		.collect()
+}
+
+/// Copies `values`, which have to be of the same type, into a list allocated with `realloc` and returns its address
+/// and length.
+pub fn lower_list(guest: &mut impl Guest, realloc: &Func, values: &[ComponentValue]) -> Result<(usize, usize), Error> {
+	let Some(ty) = values.first().map(ComponentValue::ty) else {
+		return Ok((0, 0));
+	};
+	check_types(&vec![ty; values.len()], values)?;
+	let (size, align) = size_and_align(ty);
+	let ptr = allocate(guest, realloc, align, size * values.len())?;
+	lower_record(guest, Some(realloc), values, ptr)?;
+	Ok((ptr, values.len()))
+}
+
+/// Reads the UTF-8 string of `len` bytes at `ptr`.
+pub fn lift_string(guest: &mut impl Guest, ptr: usize, len: usize) -> Result<String, Error> {
+	String::from_utf8(read(guest, ptr, len)?).map_err(|_| Error::Trap(TrapKind::Other("invalid UTF-8 string")))
+}
+
+/// Copies `string` into memory allocated with `realloc` and returns its address and length in bytes.
+pub fn lower_string(guest: &mut impl Guest, realloc: &Func, string: &str) -> Result<(usize, usize), Error> {
+	let ptr = allocate(guest, realloc, 1, string.len())?;
+	write(guest, ptr, string.as_bytes())?;
+	Ok((ptr, string.len()))
+}
+
+/// Allocates `size` bytes aligned to `align` in the memory of the guest by calling `realloc`, which has the
+/// signature of `cabi_realloc`, and returns their address.
+pub fn allocate(guest: &mut impl Guest, realloc: &Func, align: usize, size: usize) -> Result<usize, Error> {
+	let args = vec![Value::I32(0), Value::I32(0), Value::I32(align as i32), Value::I32(size as i32)];
+	match guest.call(realloc, args)?[..] {
+		[Value::I32(ptr)] => Ok(ptr as u32 as usize),
+		_ => Err(Error::Trap(TrapKind::Other("realloc did not return a pointer"))),
+	}
+}
+
+fn lift_char(code_point: u32) -> Result<char, Error> {
+	char::from_u32(code_point).ok_or(Error::Trap(TrapKind::Other("invalid char")))
+}
+
+/// The next core value of `values` converted into a `T`.
+fn next<T: TryFrom<Value>>(values: &mut impl Iterator<Item = Value>) -> Result<T, Error> {
+	let value = values.next().ok_or(Error::Trap(TrapKind::Other("too few core values for the component type")))?;
+	T::try_from(value.clone())
+		.map_err(|_| Error::StackTypeError(Box::new(Mismatch { expected: any::type_name::<T>(), got: value })))
+}
+
+fn read(guest: &mut impl Guest, addr: usize, len: usize) -> Result<Vec<u8>, Error> {
+	let memory = guest.memory()?;
+	let range = addr..addr.saturating_add(len);
+	memory.get(range.clone())
+		.map(<[u8]>::to_vec)
+		.ok_or(Error::InvalidMemoryArea { addr: range, size: memory.len() })
+}
+
+fn write(guest: &mut impl Guest, addr: usize, bytes: &[u8]) -> Result<(), Error> {
+	let memory = guest.memory()?;
+	let size = memory.len();
+	let range = addr..addr.saturating_add(bytes.len());
+	memory.get_mut(range.clone())
+		.ok_or(Error::InvalidMemoryArea { addr: range, size })?
+		.copy_from_slice(bytes);
+	Ok(())
+}
\ No newline at end of file
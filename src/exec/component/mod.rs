@@ -0,0 +1,282 @@
+//! Instantiation of [components](Component), whose core instances are linked with each other and with the host by
+//! lifting and lowering functions with the canonical ABI.
+
+use std::collections::HashMap;
+use std::fmt;
+use crate::shared::{self, MaybeSendSync, Shared, SharedMut};
+use crate::exec::{Callable, Config, Error, Func, FunctionSignature, Identifier, Instance, InstanceRef, Linker, TrapKind, linker};
+use crate::parse::component::{CanonicalOptions, Component, ComponentFunc, ComponentFuncType, ComponentType, CoreFunc, CoreInstance, ValueType};
+
+pub mod canonical;
+
+use canonical::{MAX_FLAT_PARAMS, MAX_FLAT_RESULTS};
+
+/// A value of the component model, which is passed to and returned by component functions.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ComponentValue {
+	Bool(bool),
+	S8(i8),
+	U8(u8),
+	S16(i16),
+	U16(u16),
+	S32(i32),
+	U32(u32),
+	S64(i64),
+	U64(u64),
+	F32(f32),
+	F64(f64),
+	Char(char),
+	String(String),
+}
+
+impl ComponentValue {
+	/// The type of this value.
+	pub fn ty(&self) -> ValueType {
+		match self {
+			ComponentValue::Bool(_) => ValueType::Bool,
+			ComponentValue::S8(_) => ValueType::S8,
+			ComponentValue::U8(_) => ValueType::U8,
+			ComponentValue::S16(_) => ValueType::S16,
+			ComponentValue::U16(_) => ValueType::U16,
+			ComponentValue::S32(_) => ValueType::S32,
+			ComponentValue::U32(_) => ValueType::U32,
+			ComponentValue::S64(_) => ValueType::S64,
+			ComponentValue::U64(_) => ValueType::U64,
+			ComponentValue::F32(_) => ValueType::F32,
+			ComponentValue::F64(_) => ValueType::F64,
+			ComponentValue::Char(_) => ValueType::Char,
+			ComponentValue::String(_) => ValueType::String,
+		}
+	}
+}
+
+/// A host function imported by a component, which has to be `Send + Sync` with the `sync` feature.
+pub trait ComponentHostFunction: Fn(&[ComponentValue]) -> Result<Vec<ComponentValue>, Error> + MaybeSendSync {}
+
+impl<T: Fn(&[ComponentValue]) -> Result<Vec<ComponentValue>, Error> + MaybeSendSync> ComponentHostFunction for T {}
+
+/// Resolves the imports of a component by name.
+#[derive(Default)]
+pub struct ComponentLinker {
+	functions: HashMap<String, Shared<dyn ComponentHostFunction>>,
+}
+
+impl fmt::Debug for ComponentLinker {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("ComponentLinker")
+			.field("functions", &self.functions.keys().collect::<Vec<_>>())
+			.finish()
+	}
+}
+
+impl ComponentLinker {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Defines the host function `function` for the import `name`, replacing a previous definition.
+	/// The arguments and results are checked against the type of the import.
+	pub fn func(&mut self, name: &str, function: impl ComponentHostFunction + 'static) -> &mut Self {
+		self.functions.insert(name.to_owned(), Shared::new(function));
+		self
+	}
+}
+
+/// An instantiated [Component], whose exported functions can be called with [`ComponentInstance::call`].
+#[derive(Debug)]
+pub struct ComponentInstance {
+	component: Shared<Component>,
+	/// The instances of the core instances, which are [`None`] for the ones bundling exports.
+	instances: Vec<Option<SharedMut<Instance>>>,
+}
+
+impl Component {
+	/// Instantiates the core instances of this component in order, with the imported functions defined in `linker`.
+	///
+	/// Lifted and lowered functions use the memory of the core instance they are called in, which is the memory
+	/// passed as canonical option by common toolchains.
+	pub fn instantiate(&self, linker: &ComponentLinker) -> Result<ComponentInstance, Error> {
+		let mut instances: Vec<Option<SharedMut<Instance>>> = Vec::with_capacity(self.core_instances.len());
+		for core_instance in &self.core_instances {
+			let CoreInstance::Instantiate { module, args } = core_instance else {
+				instances.push(None);
+				continue;
+			};
+			let mut core_linker = Linker::new();
+			for (name, arg) in args {
+				match (self.core_instances.get(*arg), instances.get(*arg)) {
+					(Some(_), Some(Some(instance))) => { core_linker.instance(name, instance); },
+					(Some(CoreInstance::FromExports(exports)), Some(None)) => {
+						for (field, core_func) in exports {
+							let name = Identifier { module: name.clone(), field: field.clone() };
+							core_linker.func(self.core_callable(name, *core_func, &instances, linker)?);
+						}
+					},
+					_ => return Err(Error::UnsupportedComponent("instantiate arguments defined later")),
+				}
+			}
+			let module = self.modules.get(*module)
+				.ok_or(Error::UnsupportedComponent("instances of undefined modules"))?;
+			let instance = core_linker.instantiate(Shared::clone(module), Config::default())?;
+			instances.push(Some(shared::shared_mut(instance)));
+		}
+		Ok(ComponentInstance { component: Shared::new(self.clone()), instances })
+	}
+
+	/// The core function with index `core_func` as a host function named `name`.
+	fn core_callable(&self, name: Identifier, core_func: usize, instances: &[Option<SharedMut<Instance>>], linker: &ComponentLinker) -> Result<Callable, Error> {
+		match self.core_funcs.get(core_func) {
+			Some(CoreFunc::Alias(export)) => {
+				let Some(Some(instance)) = instances.get(export.instance) else {
+					return Err(Error::UnsupportedComponent("aliases of exports of undefined instances"));
+				};
+				let function = shared::try_lock(instance).expect("Instance is not executing").exported_functions()
+					.into_iter()
+					.find(|(field, ..)| *field == export.name);
+				let (_, function_index, signature) = function.ok_or_else(|| Error::UnknownExport(export.name.clone()))?;
+				Ok(linker::forward(name, instance, function_index, signature))
+			},
+			Some(CoreFunc::Lower { func, options }) => {
+				let Some(&ComponentFunc::Import(import)) = self.funcs.get(*func) else {
+					return Err(Error::UnsupportedComponent("lowering functions other than imports"));
+				};
+				let import = &self.imports[import];
+				let host_function = linker.functions.get(&import.name)
+					.ok_or_else(|| Error::UnresolvedComponentImport(import.name.clone()))?;
+				let ty = self.func_type(import.ty)?;
+				Ok(self.lower(name, ty.clone(), Shared::clone(host_function), options)?)
+			},
+			None => Err(Error::UnsupportedComponent("undefined core functions")),
+		}
+	}
+
+	/// Lowers `host_function` of type `ty` to a host function of the core instances, which converts the core values
+	/// of the guest into component values and back.
+	fn lower(&self, name: Identifier, ty: ComponentFuncType, host_function: Shared<dyn ComponentHostFunction>, options: &CanonicalOptions) -> Result<Callable, Error> {
+		let mut params = canonical::flatten(ty.params.iter().map(|(_, ty)| *ty));
+		let mut results = canonical::flatten(ty.results.iter().copied());
+		if params.len() > MAX_FLAT_PARAMS {
+			return Err(Error::UnsupportedComponent("functions with parameters passed in memory"));
+		}
+		// Results which do not fit into core results are written to memory provided by the caller
+		let results_in_memory = results.len() > MAX_FLAT_RESULTS;
+		if results_in_memory {
+			params.push(crate::parse::Type::I32);
+			results.clear();
+		}
+		let realloc = options.realloc.map(|realloc| self.export_name(realloc)).transpose()?;
+		let num_params = params.len();
+
+		let closure = move |caller: &mut InstanceRef| {
+			let mut core_args = caller.operand_stack.pop_n(num_params)?;
+			let retptr = match results_in_memory {
+				true => Some(i32::try_from(core_args.pop().expect("The return pointer is a parameter"))? as u32 as usize),
+				false => None,
+			};
+			let mut core_args = core_args.into_iter();
+			let args = ty.params.iter()
+				.map(|(_, ty)| canonical::lift_flat(caller, *ty, &mut core_args))
+				.collect::<Result<Vec<_>, _>>()?;
+			let results = host_function(&args)?;
+			canonical::check_types(&ty.results, &results)?;
+
+			let realloc = realloc.as_deref().and_then(|realloc| caller.func(realloc));
+			let mut core_results = Vec::new();
+			match retptr {
+				Some(retptr) => canonical::lower_record(caller, realloc.as_ref(), &results, retptr)?,
+				None => for result in &results {
+					canonical::lower_flat(caller, realloc.as_ref(), result, &mut core_results)?;
+				},
+			}
+			Ok(core_results)
+		};
+		Ok(Callable::RustClosure {
+			name,
+			signature: Shared::new(FunctionSignature { params, results }),
+			closure: Box::new(closure),
+		})
+	}
+
+	/// The function type with index `ty`.
+	fn func_type(&self, ty: usize) -> Result<&ComponentFuncType, Error> {
+		match self.types.get(ty) {
+			Some(ComponentType::Func(ty)) => Ok(ty),
+			_ => Err(Error::UnsupportedComponent("functions without function type")),
+		}
+	}
+
+	/// The name of the core function with index `core_func`, which has to be exported by a core instance.
+	fn export_name(&self, core_func: usize) -> Result<String, Error> {
+		match self.core_funcs.get(core_func) {
+			Some(CoreFunc::Alias(export)) => Ok(export.name.clone()),
+			_ => Err(Error::UnsupportedComponent("canonical options with functions not exported by a core instance")),
+		}
+	}
+}
+
+impl ComponentInstance {
+	/// Calls the exported function `name` with `args` and returns its results.
+	pub fn call(&mut self, name: &str, args: &[ComponentValue]) -> Result<Vec<ComponentValue>, Error> {
+		let component = &self.component;
+		let export = component.exports.iter()
+			.find(|export| export.name == name)
+			.ok_or_else(|| Error::UnknownExport(name.to_owned()))?;
+		let ComponentFunc::Lift { core_func, ty, options } = &component.funcs[export.func] else {
+			return Err(Error::UnsupportedComponent("exports of imported functions"));
+		};
+		let ty = component.func_type(*ty)?;
+		canonical::check_types(&ty.params.iter().map(|(_, ty)| *ty).collect::<Vec<_>>(), args)?;
+
+		let Some(CoreFunc::Alias(export)) = component.core_funcs.get(*core_func) else {
+			return Err(Error::UnsupportedComponent("lifting functions not exported by a core instance"));
+		};
+		let Some(Some(instance)) = self.instances.get(export.instance) else {
+			return Err(Error::UnsupportedComponent("aliases of exports of undefined instances"));
+		};
+		let mut instance = shared::try_lock(instance).ok_or(Error::Trap(TrapKind::Other("re-entrant call into a component instance")))?;
+		let instance = &mut *instance;
+		let func = instance.func(&export.name).ok_or_else(|| Error::UnknownExport(export.name.clone()))?;
+		let realloc = match options.realloc {
+			Some(realloc) => Some(component.instance_func(instance, realloc)?),
+			None => None,
+		};
+
+		let mut core_args = Vec::new();
+		for arg in args {
+			canonical::lower_flat(instance, realloc.as_ref(), arg, &mut core_args)?;
+		}
+		if core_args.len() > MAX_FLAT_PARAMS {
+			return Err(Error::UnsupportedComponent("functions with parameters passed in memory"));
+		}
+		let core_results = func.call(instance, core_args)?;
+		let results = match canonical::flatten(ty.results.iter().copied()).len() > MAX_FLAT_RESULTS {
+			true => {
+				let retptr = core_results.first().cloned().ok_or(Error::Trap(TrapKind::Other("missing return pointer")))?;
+				canonical::lift_record(instance, &ty.results, i32::try_from(retptr)? as u32 as usize)?
+			},
+			false => {
+				let mut core_results = core_results.clone().into_iter();
+				ty.results.iter()
+					.map(|ty| canonical::lift_flat(instance, *ty, &mut core_results))
+					.collect::<Result<Vec<_>, _>>()?
+			},
+		};
+		if let Some(post_return) = options.post_return {
+			component.instance_func(instance, post_return)?.call(instance, core_results)?;
+		}
+		Ok(results)
+	}
+
+	/// The core instances, which are [`None`] for the ones bundling exports.
+	pub fn instances(&self) -> &[Option<SharedMut<Instance>>] {
+		&self.instances
+	}
+}
+
+impl Component {
+	/// The core function with index `core_func` of `instance`, which is the instance of the called function.
+	fn instance_func(&self, instance: &Instance, core_func: usize) -> Result<Func, Error> {
+		let name = self.export_name(core_func)?;
+		instance.func(&name).ok_or(Error::UnknownExport(name))
+	}
+}
\ No newline at end of file
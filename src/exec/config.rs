@@ -0,0 +1,217 @@
+use std::path::PathBuf;
+use crate::exec::{EntropySource, Signatures, StoreLimits, TimeSource};
+use crate::parse::Features;
+use crate::shared::{self, Shared, SharedMut};
+
+/// Settings for executing an [`Instance`](crate::exec::Instance).
+///
+/// Use the builder methods to change the defaults:
+/// `Config::default().trace(true)`
+#[derive(Debug, Clone)]
+pub struct Config {
+	/// Log every executed instruction.
+	pub(crate) trace: bool,
+	/// Number of operand stack values shown per traced instruction.
+	pub(crate) trace_stack_depth: usize,
+	/// Collect per function statistics in a [`Profiler`](crate::exec::Profiler).
+	pub(crate) profile: bool,
+	/// Record the allocations of the guest in a [`HeapProfiler`](crate::exec::HeapProfiler).
+	pub(crate) heap_profile: bool,
+	/// Count the interpreted instructions per opcode in the [`Stats`](crate::exec::Stats).
+	pub(crate) opcode_stats: bool,
+	/// Skip unimplemented instructions instead of trapping.
+	pub(crate) lenient_mode: bool,
+	/// Keep calling the instance after a trap instead of poisoning it.
+	pub(crate) allow_resume_after_trap: bool,
+	/// Provide the `env.log` import in [`Instance::with_config`](crate::exec::Instance::with_config).
+	pub(crate) env_log: bool,
+	/// Where to write a coredump if the execution traps.
+	pub(crate) coredump_on_trap: Option<PathBuf>,
+	/// Make the execution only depend on the module and its inputs.
+	pub(crate) deterministic: bool,
+	/// Seed of the random numbers in deterministic mode.
+	pub(crate) random_seed: u64,
+	/// Clocks read by the guest outside of deterministic mode, the ones of the host if `None`.
+	pub(crate) time_source: Option<Shared<dyn TimeSource>>,
+	/// Seed of the random numbers outside of deterministic mode, chosen by the host if `None`.
+	pub(crate) entropy_source: Option<Shared<dyn EntropySource>>,
+	/// Allow the WASI socket functions.
+	pub(crate) allow_network: bool,
+	/// Budgets shared with other instances.
+	pub(crate) limits: Option<Shared<StoreLimits>>,
+	/// Proposals instantiated modules may use.
+	pub(crate) features: Features,
+	/// Ids of the function signatures, shared by the clones of the config so that the instances of an
+	/// [`Engine`](crate::exec::Engine) agree on them.
+	pub(crate) signatures: SharedMut<Signatures>,
+	/// Compile the supported functions to native code.
+	#[cfg(feature = "jit")]
+	pub(crate) jit: bool,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			trace: false,
+			trace_stack_depth: 4,
+			profile: false,
+			heap_profile: false,
+			opcode_stats: false,
+			lenient_mode: false,
+			allow_resume_after_trap: false,
+			env_log: false,
+			coredump_on_trap: None,
+			deterministic: false,
+			random_seed: 0,
+			time_source: None,
+			entropy_source: None,
+			allow_network: false,
+			limits: None,
+			features: Features::all(),
+			signatures: shared::shared_mut(Signatures::default()),
+			#[cfg(feature = "jit")]
+			jit: false,
+		}
+	}
+}
+
+impl Config {
+	/// Logs every executed instruction together with the current function, the top of the operand stack
+	/// and the locals changed by the instruction.
+	pub fn trace(mut self, enable: bool) -> Self {
+		self.trace = enable;
+		self
+	}
+
+	/// Number of operand stack values shown per traced instruction.
+	pub fn trace_stack_depth(mut self, depth: usize) -> Self {
+		self.trace_stack_depth = depth;
+		self
+	}
+
+	/// Counts executed instructions and measures wall time per function.
+	/// The results are available through [`Instance::profiler`](crate::exec::Instance::profiler).
+	pub fn profile(mut self, enable: bool) -> Self {
+		self.profile = enable;
+		self
+	}
+
+	/// Records the allocations of the guest by call site through the `malloc`, `calloc`, `realloc` and `free` or
+	/// `__rust_alloc`, `__rust_realloc` and `__rust_dealloc` functions it exports. The results are available
+	/// through [`Instance::heap_profiler`](crate::exec::Instance::heap_profiler).
+	pub fn heap_profile(mut self, enable: bool) -> Self {
+		self.heap_profile = enable;
+		self
+	}
+
+	/// Counts how often every opcode is interpreted. The histogram is available through
+	/// [`Stats::opcodes`](crate::exec::Stats::opcodes) and costs a hash map lookup per instruction.
+	pub fn opcode_stats(mut self, enable: bool) -> Self {
+		self.opcode_stats = enable;
+		self
+	}
+
+	/// Logs and skips instructions which the interpreter does not implement instead of trapping with
+	/// [`TrapKind::UnimplementedInstruction`](crate::exec::TrapKind::UnimplementedInstruction), e.g. to see how far
+	/// a module gets. The skipped instructions neither pop nor push operands, so the execution is likely wrong afterwards.
+	pub fn lenient_mode(mut self, enable: bool) -> Self {
+		self.lenient_mode = enable;
+		self
+	}
+
+	/// Allows calling an instance again after an execution trapped. The stacks of the aborted execution are discarded
+	/// when the next one starts, while the memory, tables and globals keep the state at the time of the trap, which
+	/// the guest may not expect, e.g. a held lock or a half written data structure.
+	///
+	/// By default, a trap poisons the instance and later calls fail with
+	/// [`Error::InstancePoisoned`](crate::exec::Error::InstancePoisoned), so that a faulty guest is isolated and
+	/// embedders create a new instance, e.g. with [`Instance::fork`](crate::exec::Instance::fork) from a template.
+	pub fn allow_resume_after_trap(mut self, enable: bool) -> Self {
+		self.allow_resume_after_trap = enable;
+		self
+	}
+
+	/// Provides the import `env.log(ptr, len, level)` of [`Linker::log`](crate::exec::Linker::log) to instances
+	/// created with [`Instance::with_config`](crate::exec::Instance::with_config), so that guests without WASI can
+	/// print. Add it to the linker when instantiating with [`Linker::instantiate`](crate::exec::Linker::instantiate).
+	pub fn env_log(mut self, enable: bool) -> Self {
+		self.env_log = enable;
+		self
+	}
+
+	/// Writes a coredump with the call stack, locals and memory to `path` if the execution traps, which can be
+	/// inspected with tools supporting the WebAssembly coredump format like `wasmgdb`.
+	pub fn coredump_on_trap(mut self, path: impl Into<PathBuf>) -> Self {
+		self.coredump_on_trap = Some(path.into());
+		self
+	}
+
+	/// Executes deterministically, so that the same module with the same inputs always produces the same memory and
+	/// output, e.g. for consensus or replay.
+	///
+	/// NaN results of float arithmetic are canonicalized, random numbers are generated from the
+	/// [seed](Config::random_seed), clocks return a logical time and [`Linker::instantiate`] refuses imports defined
+	/// as nondeterministic with [`Linker::nondeterministic`].
+	///
+	/// [`Linker::instantiate`]: crate::exec::Linker::instantiate
+	/// [`Linker::nondeterministic`]: crate::exec::Linker::nondeterministic
+	pub fn deterministic(mut self, enable: bool) -> Self {
+		self.deterministic = enable;
+		self
+	}
+
+	/// Seed of the random numbers returned by `random_get` in deterministic mode.
+	pub fn random_seed(mut self, seed: u64) -> Self {
+		self.random_seed = seed;
+		self
+	}
+
+	/// Reads the clocks of the guest from `time_source` instead of the host outside of deterministic mode, e.g. from
+	/// JavaScript on `wasm32-unknown-unknown`, which has no clocks. There, the clocks return a logical time like in
+	/// [deterministic](Config::deterministic) mode without a time source.
+	pub fn time_source(mut self, time_source: impl TimeSource + 'static) -> Self {
+		self.time_source = Some(Shared::new(time_source));
+		self
+	}
+
+	/// Seeds the random numbers of the guest with `entropy_source` instead of the host outside of deterministic mode.
+	pub fn entropy_source(mut self, entropy_source: impl EntropySource + 'static) -> Self {
+		self.entropy_source = Some(Shared::new(entropy_source));
+		self
+	}
+
+	/// Allows the WASI socket functions to accept, receive and send on the sockets passed to the instance with
+	/// [`WasiCtx::preopen_socket`](crate::exec::WasiCtx::preopen_socket). Otherwise, they fail with `ENOTCAPABLE`,
+	/// so that the instance stays sandboxed even if sockets are passed to it.
+	pub fn allow_network(mut self, enable: bool) -> Self {
+		self.allow_network = enable;
+		self
+	}
+
+	/// Accounts the memory, table elements and code of the instance in `limits`, which are shared with the other
+	/// instances configured with them.
+	pub fn limits(mut self, limits: Shared<StoreLimits>) -> Self {
+		self.limits = Some(limits);
+		self
+	}
+
+	/// Proposals instantiated modules may use. [`Linker::instantiate`] refuses modules using other ones, e.g. to
+	/// only run WebAssembly 1.0 with `Features::empty()`. All proposals are enabled by default.
+	///
+	/// [`Linker::instantiate`]: crate::exec::Linker::instantiate
+	pub fn features(mut self, features: Features) -> Self {
+		self.features = features;
+		self
+	}
+
+	/// Compiles functions which only compute with `i32` and `i64` values to native code with Cranelift when
+	/// instantiating a module. All other functions are interpreted.
+	///
+	/// Compiled functions are only executed if nothing observes single instructions, i.e. without tracing, profiling,
+	/// a debugger, hooks and [`Instance::run_steps`](crate::exec::Instance::run_steps).
+	#[cfg(feature = "jit")]
+	pub fn jit(mut self, enable: bool) -> Self {
+		self.jit = enable;
+		self
+	}
+}
\ No newline at end of file
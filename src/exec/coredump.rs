@@ -0,0 +1,97 @@
+//! Encoding of the state of a trapped instance in the
+//! [WebAssembly coredump format](https://github.com/WebAssembly/tool-conventions/blob/main/Coredump.md).
+
+use crate::encode::{write_name, write_section, write_u32};
+use crate::exec::{Callable, Frame, Memory, OperandStack, Value};
+use crate::parse::SectionId;
+
+/// Size of a memory page in the coredump, which follows the WebAssembly specification.
+const PAGE_SIZE: usize = 65536;
+
+/// Encodes the call stack, the locals and operands and a snapshot of the memory as a coredump module.
+///
+/// Since operands are not attributed to frames, the whole operand stack is stored in the innermost frame.
+pub(crate) fn encode(call_stack: &[Frame], operand_stack: &OperandStack, memory: Option<&Memory>) -> Vec<u8> {
+	let mut coredump = vec![0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00];
+
+	let mut process_info = Vec::new();
+	write_name(&mut process_info, "core");
+	process_info.push(0x00);
+	write_name(&mut process_info, "module");
+	write_section(&mut coredump, SectionId::Custom, &process_info);
+
+	let mut stack = Vec::new();
+	write_name(&mut stack, "corestack");
+	stack.push(0x00);
+	write_name(&mut stack, "main");
+	write_u32(&mut stack, call_stack.len());
+	for (depth, frame) in call_stack.iter().rev().enumerate() {
+		stack.push(0x00);
+		// Index of the instance
+		write_u32(&mut stack, 0);
+		write_u32(&mut stack, frame.function_index);
+		write_u32(&mut stack, code_offset(frame));
+		write_values(&mut stack, &frame.locals);
+		match depth {
+			0 => write_values(&mut stack, operand_stack.top(operand_stack.len())),
+			_ => write_values(&mut stack, &[]),
+		}
+	}
+	write_section(&mut coredump, SectionId::Custom, &stack);
+
+	if let Some(memory) = memory {
+		let data = memory.data();
+		let mut memory_section = Vec::new();
+		write_u32(&mut memory_section, 1);
+		// Limits with only a minimum
+		memory_section.push(0x00);
+		write_u32(&mut memory_section, data.len().div_ceil(PAGE_SIZE));
+		write_section(&mut coredump, SectionId::Memory, &memory_section);
+
+		let mut data_section = Vec::new();
+		write_u32(&mut data_section, 1);
+		// Active segment for memory 0 at offset `i32.const 0`
+		data_section.extend([0x00, 0x41, 0x00, 0x0B]);
+		write_u32(&mut data_section, data.len());
+		data_section.extend_from_slice(data);
+		write_section(&mut coredump, SectionId::Data, &data_section);
+	}
+
+	coredump
+}
+
+/// Offset of the instruction executed last in `frame`, relative to the start of the function body.
+fn code_offset(frame: &Frame) -> usize {
+	match frame.function() {
+		Callable::WasmFunction(function) => function.offsets.get(frame.offset)
+			.map(|offset| offset - function.body_offset)
+			.unwrap_or(0),
+		_ => 0,
+	}
+}
+
+/// Writes `values`, of which only numbers are represented in coredumps. Others are marked as missing.
+fn write_values(buffer: &mut Vec<u8>, values: &[Value]) {
+	write_u32(buffer, values.len());
+	for value in values {
+		match value {
+			Value::I32(value) => {
+				buffer.push(0x7F);
+				leb128::write::signed(buffer, *value as i64).expect("Writing to a Vec does not fail");
+			},
+			Value::I64(value) => {
+				buffer.push(0x7E);
+				leb128::write::signed(buffer, *value).expect("Writing to a Vec does not fail");
+			},
+			Value::F32(value) => {
+				buffer.push(0x7D);
+				buffer.extend_from_slice(&value.to_le_bytes());
+			},
+			Value::F64(value) => {
+				buffer.push(0x7C);
+				buffer.extend_from_slice(&value.to_le_bytes());
+			},
+			_ => buffer.push(0x01),
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,174 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::Range;
+use crate::exec::{Frame, Instruction, Memory, OperandStack};
+use crate::shared::MaybeSendSync;
+
+/// A position in the code of a function where execution should pause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Breakpoint {
+	/// Index of the function in the instance.
+	pub function: usize,
+	/// Index of the instruction in the function body, counting nested instructions in the order they
+	/// appear in the binary. See [`Instruction::nested_len`].
+	pub offset: usize,
+}
+
+/// What the [`Debugger`] should do after being paused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugAction {
+	/// Run until the next breakpoint.
+	Continue,
+	/// Pause again before the next instruction.
+	Step,
+	/// Stop the execution with [`Error::DebuggerAbort`](crate::exec::Error::DebuggerAbort).
+	Abort,
+}
+
+/// The state of the paused instance, handed to the [`Debugger`] handler.
+pub struct DebugContext<'a> {
+	/// Index of the current function in the instance.
+	pub function_index: usize,
+	/// Offset of the next instruction inside the current function.
+	pub offset: usize,
+	/// The instruction that will be executed next.
+	pub instruction: &'a Instruction,
+	/// The call stack with the current function as last frame.
+	pub call_stack: &'a [Frame],
+	pub operand_stack: &'a OperandStack,
+	pub memory: Option<&'a Memory>,
+	/// The breakpoints of the debugger, which can be modified while paused.
+	pub breakpoints: &'a mut BTreeSet<Breakpoint>,
+	/// The watched memory ranges of the debugger, which can be modified while paused.
+	pub watchpoints: &'a mut Vec<Range<usize>>,
+	/// The addresses written by the previous instruction, if the execution paused because they overlap a watchpoint.
+	pub watchpoint_hit: Option<Range<usize>>,
+}
+
+impl<'a> DebugContext<'a> {
+	/// The currently executing frame.
+	pub fn frame(&self) -> &Frame {
+		self.call_stack.last().expect("Instructions are only executed inside a function")
+	}
+
+	/// The position of the next instruction.
+	pub fn position(&self) -> Breakpoint {
+		Breakpoint { function: self.function_index, offset: self.offset }
+	}
+}
+
+/// The handler of a [`Debugger`], which has to be `Send + Sync` with the `sync` feature.
+trait DebugHandler: FnMut(&mut DebugContext) -> DebugAction + MaybeSendSync {}
+
+impl<T: FnMut(&mut DebugContext) -> DebugAction + MaybeSendSync> DebugHandler for T {}
+
+/// Pauses the execution of an [`Instance`](crate::exec::Instance) at breakpoints or after every instruction
+/// and hands the paused state to a handler, which decides how to resume.
+///
+/// Watchpoints pause the execution after the guest stored to a watched memory range, before the next instruction.
+pub struct Debugger {
+	breakpoints: BTreeSet<Breakpoint>,
+	watchpoints: Vec<Range<usize>>,
+	/// The written addresses which overlap a watchpoint, so that the execution pauses before the next instruction.
+	watchpoint_hit: Option<Range<usize>>,
+	/// Pause before the next instruction regardless of breakpoints.
+	stepping: bool,
+	handler: Box<dyn DebugHandler>,
+}
+
+impl fmt::Debug for Debugger {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Debugger")
+			.field("breakpoints", &self.breakpoints)
+			.field("watchpoints", &self.watchpoints)
+			.field("stepping", &self.stepping)
+			.field("handler", &"<opaque>")
+			.finish()
+	}
+}
+
+impl Debugger {
+	/// Creates a debugger calling `handler` every time the execution is paused.
+	pub fn new(handler: impl FnMut(&mut DebugContext) -> DebugAction + MaybeSendSync + 'static) -> Self {
+		Self {
+			breakpoints: BTreeSet::new(),
+			watchpoints: Vec::new(),
+			watchpoint_hit: None,
+			stepping: false,
+			handler: Box::new(handler),
+		}
+	}
+
+	/// Pauses before the first instruction instead of running until the first breakpoint.
+	pub fn step_first(mut self) -> Self {
+		self.stepping = true;
+		self
+	}
+
+	pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+		self.breakpoints.insert(breakpoint);
+	}
+
+	pub fn remove_breakpoint(&mut self, breakpoint: &Breakpoint) {
+		self.breakpoints.remove(breakpoint);
+	}
+
+	pub fn breakpoints(&self) -> &BTreeSet<Breakpoint> {
+		&self.breakpoints
+	}
+
+	/// Pauses after the guest stores to any byte of `range`.
+	pub fn add_watchpoint(&mut self, range: Range<usize>) {
+		self.watchpoints.push(range);
+	}
+
+	/// Removes the watchpoints of exactly `range`.
+	pub fn remove_watchpoint(&mut self, range: &Range<usize>) {
+		self.watchpoints.retain(|watched| watched != range);
+	}
+
+	pub fn watchpoints(&self) -> &[Range<usize>] {
+		&self.watchpoints
+	}
+
+	/// Whether the execution should pause before the instruction at `offset` in function `function_index`.
+	pub(crate) fn should_pause(&self, function_index: usize, offset: usize) -> bool {
+		self.stepping || self.watchpoint_hit.is_some()
+			|| self.breakpoints.contains(&Breakpoint { function: function_index, offset })
+	}
+
+	/// Remembers a store of the guest to `written`, if it overlaps a watchpoint.
+	pub(crate) fn memory_written(&mut self, written: Range<usize>) {
+		let watched = self.watchpoints.iter()
+			.any(|range| range.start < written.end && written.start < range.end);
+		if watched {
+			self.watchpoint_hit = Some(written);
+		}
+	}
+
+	/// Calls the handler with the paused state and remembers whether to step.
+	pub(crate) fn pause(
+		&mut self,
+		function_index: usize,
+		offset: usize,
+		instruction: &Instruction,
+		call_stack: &[Frame],
+		operand_stack: &OperandStack,
+		memory: Option<&Memory>,
+	) -> DebugAction {
+		let mut context = DebugContext {
+			function_index,
+			offset,
+			instruction,
+			call_stack,
+			operand_stack,
+			memory,
+			breakpoints: &mut self.breakpoints,
+			watchpoints: &mut self.watchpoints,
+			watchpoint_hit: self.watchpoint_hit.take(),
+		};
+		let action = (self.handler)(&mut context);
+		self.stepping = action == DebugAction::Step;
+		action
+	}
+}
\ No newline at end of file
@@ -0,0 +1,83 @@
+use std::fmt;
+use std::path::PathBuf;
+use gimli::{Dwarf, EndianSlice, LittleEndian, SectionId};
+use crate::exec::{Breakpoint, FuncKind};
+use crate::parse::{Module, SectionId as WasmSectionId};
+
+/// A line in the source code the module was compiled from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+	pub file: PathBuf,
+	pub line: u64,
+}
+
+impl fmt::Display for SourceLocation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}:{}", self.file.display(), self.line)
+	}
+}
+
+/// Maps instructions to source code lines using the DWARF `.debug_*` custom sections emitted by rustc and clang.
+///
+/// Addresses in DWARF for WebAssembly are offsets relative to the start of the code section.
+#[derive(Debug, Default, Clone)]
+pub struct SourceMap {
+	/// Rows of the line programs sorted by address.
+	rows: Vec<(u64, SourceLocation)>,
+}
+
+impl SourceMap {
+	/// Reads the line programs of `module`. A module without debug info results in an empty map.
+	pub fn new(module: &Module) -> Result<Self, gimli::Error> {
+		let dwarf = Dwarf::load(|id: SectionId| -> Result<_, gimli::Error> {
+			let data = module.custom_section(id.name()).map(|section| &section.data[..]).unwrap_or(&[]);
+			Ok(EndianSlice::new(data, LittleEndian))
+		})?;
+
+		let mut rows = Vec::new();
+		let mut units = dwarf.units();
+		while let Some(header) = units.next()? {
+			let unit = dwarf.unit(header)?;
+			let Some(program) = unit.line_program.clone() else {
+				continue;
+			};
+			let mut program_rows = program.rows();
+			while let Some((header, row)) = program_rows.next_row()? {
+				if row.end_sequence() {
+					continue;
+				}
+				let (Some(line), Some(file)) = (row.line(), row.file(header)) else {
+					continue;
+				};
+				let mut path = PathBuf::new();
+				if let Some(directory) = file.directory(header) {
+					path.push(dwarf.attr_string(&unit, directory)?.to_string_lossy().as_ref());
+				}
+				path.push(dwarf.attr_string(&unit, file.path_name())?.to_string_lossy().as_ref());
+				rows.push((row.address(), SourceLocation { file: path, line: line.get() }));
+			}
+		}
+		rows.sort_by_key(|(address, _)| *address);
+		Ok(Self { rows })
+	}
+
+	/// The source line containing the code section offset `address`.
+	pub fn lookup(&self, address: u64) -> Option<&SourceLocation> {
+		let index = self.rows.partition_point(|(row_address, _)| *row_address <= address);
+		self.rows[..index].last().map(|(_, location)| location)
+	}
+
+	/// The source line of the instruction at `position` in `module`, e.g. [`Instance::last_position`] after
+	/// a trap or [`DebugContext::position`] in a debugger.
+	///
+	/// [`Instance::last_position`]: crate::exec::Instance::last_position
+	/// [`DebugContext::position`]: crate::exec::DebugContext::position
+	pub fn locate(&self, module: &Module, position: Breakpoint) -> Option<&SourceLocation> {
+		let code_section = module.sections.iter().find(|section| section.id == WasmSectionId::Code)?;
+		let Some(FuncKind::Local(function)) = module.functions.get(position.function) else {
+			return None;
+		};
+		let instruction_offset = *function.offsets.get(position.offset)?;
+		self.lookup((instruction_offset - code_section.offset) as u64)
+	}
+}
\ No newline at end of file
@@ -0,0 +1,144 @@
+//! The `env` imports of the Emscripten JavaScript runtime which simple C programs compiled with Emscripten need
+//! besides WASI, such as the notifications of memory growth and the syscalls musl does not map to WASI.
+//!
+//! Syscalls return a negated errno, whose values are the ones of WASI for Emscripten.
+
+use crate::exec::{Clock, Error, HostResult, TrapKind, Value, MEMORY_PAGE_SIZE};
+use crate::exec::instance::InstanceRef;
+use crate::parse::Type;
+
+/// A host function implementing an import of the Emscripten runtime.
+type HostFunction = fn(&mut InstanceRef) -> HostResult;
+
+/// Field, parameters, results and implementation of all implemented functions of the module `env`.
+pub(crate) const FUNCTIONS: &[(&str, &[Type], &[Type], HostFunction)] = &[
+	("emscripten_notify_memory_growth", &[Type::I32], &[], notify_memory_growth),
+	("emscripten_resize_heap", &[Type::I32], &[Type::I32], resize_heap),
+	("emscripten_get_heap_max", &[], &[Type::I32], get_heap_max),
+	("emscripten_memcpy_big", &[Type::I32; 3], &[Type::I32], memcpy_big),
+	("emscripten_memcpy_js", &[Type::I32; 3], &[], memcpy_js),
+	("emscripten_date_now", &[], &[Type::F64], date_now),
+	("emscripten_get_now", &[], &[Type::F64], get_now),
+	("abort", &[], &[], abort),
+	("_abort_js", &[], &[], abort),
+	("__syscall_getcwd", &[Type::I32; 2], &[Type::I32], syscall_getcwd),
+	("__syscall_ioctl", &[Type::I32; 3], &[Type::I32], syscall_ioctl),
+	("__syscall_fcntl64", &[Type::I32; 3], &[Type::I32], syscall_fcntl64),
+];
+
+/// Bad file descriptor.
+const ERRNO_BADF: i32 = 8;
+/// Inappropriate I/O control operation.
+const ERRNO_NOTTY: i32 = 59;
+/// Result too large.
+const ERRNO_RANGE: i32 = 68;
+
+/// The largest heap Emscripten can address with 32 bit pointers.
+const HEAP_MAX: usize = u32::MAX as usize;
+
+/// `emscripten_notify_memory_growth(memory_index)`, which is only of interest to JavaScript views of the memory.
+fn notify_memory_growth(instance: &mut InstanceRef) -> HostResult {
+	let _memory_index = instance.operand_stack.pop::<i32>()?;
+	Ok(Vec::new())
+}
+
+/// `emscripten_resize_heap(requested_size) -> bool` grows the memory to at least `requested_size` bytes.
+fn resize_heap(instance: &mut InstanceRef) -> HostResult {
+	let requested_size = instance.operand_stack.pop::<i32>()? as u32 as usize;
+
+	let pages = instance.memory.as_ref().ok_or(Error::NoMemory)?.page_size();
+	let delta = requested_size.div_ceil(MEMORY_PAGE_SIZE).saturating_sub(pages);
+	let resized = delta == 0 || instance.grow_memory(delta)?.is_some();
+	Ok(vec![Value::I32(resized as i32)])
+}
+
+/// `emscripten_get_heap_max() -> size` in bytes.
+fn get_heap_max(instance: &mut InstanceRef) -> HostResult {
+	let mem = instance.memory.as_ref().ok_or(Error::NoMemory)?;
+	let heap_max = mem.page_limit.end.saturating_mul(MEMORY_PAGE_SIZE).min(HEAP_MAX);
+	Ok(vec![Value::I32(heap_max as u32 as i32)])
+}
+
+/// `emscripten_memcpy_big(dest, src, num) -> dest` of older Emscripten versions.
+fn memcpy_big(instance: &mut InstanceRef) -> HostResult {
+	let dest = memcpy(instance)?;
+	Ok(vec![Value::I32(dest as i32)])
+}
+
+/// `emscripten_memcpy_js(dest, src, num)`.
+fn memcpy_js(instance: &mut InstanceRef) -> HostResult {
+	memcpy(instance)?;
+	Ok(Vec::new())
+}
+
+/// Pops `dest`, `src` and `num`, copies `num` bytes from `src` to `dest` like `memory.copy` and returns `dest`.
+fn memcpy(instance: &mut InstanceRef) -> Result<usize, Error> {
+	let num = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let src = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let dest = instance.operand_stack.pop::<i32>()? as u32 as usize;
+
+	let mem = instance.memory.as_mut().ok_or(Error::NoMemory)?;
+	if src.max(dest) + num > mem.data.len() {
+		return Err(Error::Trap(TrapKind::MemoryOutOfBounds));
+	}
+	mem.data.copy_within(src..src + num, dest);
+	Ok(dest)
+}
+
+/// `emscripten_date_now() -> f64` in milliseconds since the Unix epoch.
+fn date_now(instance: &mut InstanceRef) -> HostResult {
+	let now = instance.environment.now(Clock::Realtime);
+	Ok(vec![Value::F64(now as f64 / 1e6)])
+}
+
+/// `emscripten_get_now() -> f64` in milliseconds of the monotonic clock.
+fn get_now(instance: &mut InstanceRef) -> HostResult {
+	let now = instance.environment.now(Clock::Monotonic);
+	Ok(vec![Value::F64(now as f64 / 1e6)])
+}
+
+fn abort(_instance: &mut InstanceRef) -> HostResult {
+	Err(Error::Trap(TrapKind::Other("abort")))
+}
+
+/// `getcwd(buf, size) -> len`, which is always the root, as paths are resolved against the preopened directories.
+fn syscall_getcwd(instance: &mut InstanceRef) -> HostResult {
+	let size = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let buf = instance.operand_stack.pop::<i32>()? as u32 as usize;
+
+	const CWD: &[u8] = b"/\0";
+	if size < CWD.len() {
+		return Ok(vec![Value::I32(-ERRNO_RANGE)]);
+	}
+	let mem = instance.memory.as_mut().ok_or(Error::NoMemory)?;
+	let size = mem.data.len();
+	mem.data.get_mut(buf..buf + CWD.len())
+		.ok_or(Error::InvalidMemoryArea { addr: buf..buf + CWD.len(), size })?
+		.copy_from_slice(CWD);
+	Ok(vec![Value::I32(CWD.len() as i32)])
+}
+
+/// `ioctl(fd, op, varargs)`, for which no file descriptor is a terminal, so that e.g. `isatty` returns false.
+fn syscall_ioctl(instance: &mut InstanceRef) -> HostResult {
+	let _varargs = instance.operand_stack.pop::<i32>()?;
+	let _op = instance.operand_stack.pop::<i32>()?;
+	let fd = instance.operand_stack.pop::<i32>()?;
+
+	let errno = if is_open(instance, fd) { ERRNO_NOTTY } else { ERRNO_BADF };
+	Ok(vec![Value::I32(-errno)])
+}
+
+/// `fcntl64(fd, cmd, varargs)`, which succeeds without changing the flags of open file descriptors.
+fn syscall_fcntl64(instance: &mut InstanceRef) -> HostResult {
+	let _varargs = instance.operand_stack.pop::<i32>()?;
+	let _cmd = instance.operand_stack.pop::<i32>()?;
+	let fd = instance.operand_stack.pop::<i32>()?;
+
+	let result = if is_open(instance, fd) { 0 } else { -ERRNO_BADF };
+	Ok(vec![Value::I32(result)])
+}
+
+/// Whether `fd` is a standard stream or opened by the WASI layer.
+fn is_open(instance: &InstanceRef, fd: i32) -> bool {
+	(0..3).contains(&fd) || instance.wasi.get(fd as u32).is_some()
+}
\ No newline at end of file
@@ -0,0 +1,76 @@
+use crate::shared::{self, Shared, SharedMut};
+use crate::exec::{Config, StoreLimits};
+use crate::parse::{Features, Module, ModuleCache, ParsingError};
+
+/// Owns the [`Config`] and the caches shared by the modules and instances created with it, see [`Module::new`] and
+/// [`Instance::new`](crate::exec::Instance::new).
+///
+/// Cloning an engine is cheap, and the clones share the caches. With the `sync` feature, it is `Send + Sync`, so
+/// that the threads of a service can share one engine.
+#[derive(Debug, Clone, Default)]
+pub struct Engine {
+	config: Shared<Config>,
+	/// Modules by the digest of their binary, if the engine caches them.
+	cache: Option<SharedMut<ModuleCache>>,
+}
+
+impl Engine {
+	/// An engine creating modules and instances with `config`.
+	pub fn new(config: Config) -> Self {
+		Engine { config: Shared::new(config), ..Engine::default() }
+	}
+
+	/// Loads modules through `cache`, so that each binary is only parsed once.
+	pub fn with_cache(mut self, cache: ModuleCache) -> Self {
+		self.cache = Some(shared::shared_mut(cache));
+		self
+	}
+
+	/// The config of the instances created with this engine.
+	pub fn config(&self) -> &Config {
+		&self.config
+	}
+
+	/// The proposals the modules created with this engine may use.
+	pub fn features(&self) -> Features {
+		self.config.features
+	}
+
+	/// The budgets shared by the instances created with this engine, see [`Config::limits`].
+	pub fn limits(&self) -> Option<&Shared<StoreLimits>> {
+		self.config.limits.as_ref()
+	}
+
+	/// Number of distinct function signatures of the modules created so far.
+	pub fn num_signatures(&self) -> usize {
+		shared::lock(&self.config.signatures).len()
+	}
+
+	/// Parses `bytes` or takes the module from the cache, and fails with [`ParsingError::FeatureNotEnabled`] if it
+	/// uses a proposal not in the [features](Engine::features).
+	pub(crate) fn load(&self, bytes: Shared<[u8]>) -> Result<Shared<Module>, ParsingError> {
+		let module = match &self.cache {
+			Some(cache) => shared::lock(cache).load_with(bytes, |module| self.intern_signatures(module))?,
+			None => {
+				let mut module = Module::from_bytes_with_features(bytes, self.features())?;
+				self.intern_signatures(&mut module);
+				Shared::new(module)
+			},
+		};
+		let disabled = module.features().difference(self.features());
+		match disabled.is_empty() {
+			true => Ok(module),
+			false => Err(ParsingError::FeatureNotEnabled(disabled)),
+		}
+	}
+
+	/// Replaces the signatures of `module` by the equal ones of the modules created before, so that modules share
+	/// them instead of each having its own copy, and assigns ids to the new ones.
+	fn intern_signatures(&self, module: &mut Module) {
+		let mut signatures = shared::lock(&self.config.signatures);
+		let all = module.types.iter_mut().chain(module.tags.iter_mut()).chain(module.functions.signatures_mut());
+		for signature in all {
+			*signature = signatures.intern(signature).1;
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,115 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use crate::exec::Config;
+use crate::shared::{MaybeSendSync, Shared};
+
+/// The sources of nondeterminism available to host functions, i.e. random numbers and clocks.
+///
+/// In [deterministic](Config::deterministic) mode, random numbers are generated from the configured seed and all
+/// clocks return a logical time, so that an execution only depends on the module and its inputs. Otherwise, they
+/// come from the [`TimeSource`] and [`EntropySource`] of the config, which default to the ones of the host.
+#[derive(Debug, Clone)]
+pub struct Environment {
+	deterministic: bool,
+	/// State of the SplitMix64 generator.
+	random_state: u64,
+	/// Nanoseconds returned by the clocks in deterministic mode or without a time source.
+	logical_time: u64,
+	time_source: Option<Shared<dyn TimeSource>>,
+}
+
+/// The clocks of WASI, identified by their id.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Clock {
+	Realtime,
+	Monotonic,
+}
+
+/// The clocks of the host, which the guest reads through [`Environment::now`], see [`Config::time_source`].
+pub trait TimeSource: fmt::Debug + MaybeSendSync {
+	/// The time of `clock` in nanoseconds, since the Unix epoch for [`Clock::Realtime`] and since an arbitrary
+	/// start for [`Clock::Monotonic`].
+	fn now(&self, clock: Clock) -> u64;
+}
+
+/// The seed of the random numbers of the guest, see [`Config::entropy_source`].
+pub trait EntropySource: fmt::Debug + MaybeSendSync {
+	fn seed(&self) -> u64;
+}
+
+/// The clocks of the operating system. They are not available on `wasm32-unknown-unknown`, where the embedder
+/// provides a [`TimeSource`], e.g. one reading the clock of JavaScript.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+#[derive(Debug)]
+struct SystemClocks {
+	/// Start of the monotonic clock.
+	started: std::time::Instant,
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl TimeSource for SystemClocks {
+	fn now(&self, clock: Clock) -> u64 {
+		use std::time::{SystemTime, UNIX_EPOCH};
+		match clock {
+			Clock::Realtime => SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64),
+			Clock::Monotonic => self.started.elapsed().as_nanos() as u64,
+		}
+	}
+}
+
+/// The clocks of the host, if the target has any.
+fn host_time_source() -> Option<Shared<dyn TimeSource>> {
+	#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+	return Some(Shared::new(SystemClocks { started: std::time::Instant::now() }));
+	#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+	return None;
+}
+
+impl Environment {
+	pub(crate) fn new(config: &Config) -> Self {
+		let random_state = match (config.deterministic, &config.entropy_source) {
+			(true, _) => config.random_seed,
+			(false, Some(entropy_source)) => entropy_source.seed(),
+			// The keys of the std hasher are randomly chosen by the operating system
+			(false, None) => RandomState::new().build_hasher().finish(),
+		};
+		Self {
+			deterministic: config.deterministic,
+			random_state,
+			logical_time: 0,
+			time_source: config.time_source.clone().or_else(host_time_source),
+		}
+	}
+
+	/// Fills `buffer` with random bytes, which are not suitable for cryptography.
+	pub fn fill_random(&mut self, buffer: &mut [u8]) {
+		for chunk in buffer.chunks_mut(8) {
+			let random = self.next_random().to_le_bytes();
+			chunk.copy_from_slice(&random[..chunk.len()]);
+		}
+	}
+
+	/// The next number of the SplitMix64 generator.
+	fn next_random(&mut self) -> u64 {
+		self.random_state = self.random_state.wrapping_add(0x9E3779B97F4A7C15);
+		let mut z = self.random_state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+		z ^ (z >> 31)
+	}
+
+	/// The time of `clock` in nanoseconds.
+	///
+	/// In deterministic mode, or on targets without clocks if the config has no time source, this is the logical
+	/// time, which starts at zero and advances by one nanosecond with every read of any clock.
+	pub fn now(&mut self, clock: Clock) -> u64 {
+		match &self.time_source {
+			Some(time_source) if !self.deterministic => time_source.now(clock),
+			_ => {
+				self.logical_time += 1;
+				self.logical_time
+			},
+		}
+	}
+}
@@ -1,8 +1,9 @@
 use std::io;
 use std::ops::Range;
 use thiserror::Error;
-use crate::exec::Value;
-use crate::parse::Type;
+use crate::exec::{ComponentValue, Exception, FunctionSignature, Identifier, Protection, Value};
+use crate::parse::{Features, ParsingError, Type};
+use crate::parse::component::ValueType;
 
 /// Execution errors.
 #[derive(Debug, Error)]
@@ -18,6 +19,42 @@ pub enum Error {
 		size: usize,
 	},
 
+	/// The memory cannot grow to a number of pages outside of its page limit.
+	#[error("The memory cannot grow to {pages} pages outside of its limit {limit:?}")]
+	MemoryPagesOutOfLimit {
+		pages: usize,
+		limit: Range<usize>,
+	},
+
+	/// The host cannot allocate the bytes of the memory.
+	#[error("Allocating {bytes} bytes for the memory failed")]
+	MemoryAllocationFailed {
+		bytes: usize,
+		#[source]
+		source: io::Error,
+	},
+
+	/// An active data segment does not fit into the memory when instantiating the module.
+	#[error("The data segment at {addr:?} does not fit into the memory of size {size}")]
+	DataSegmentOutOfBounds {
+		addr: Range<usize>,
+		size: usize,
+	},
+
+	/// An active element segment does not fit into its table when instantiating the module.
+	#[error("The element segment at {offset:?} does not fit into the table of size {size}")]
+	ElementSegmentOutOfBounds {
+		offset: Range<usize>,
+		size: usize,
+	},
+
+	/// The guest accessed a range of the memory protected by the host.
+	#[error("Access to {addr:?} violates the memory protection {protection:?}")]
+	ProtectedMemoryAccess {
+		addr: Range<usize>,
+		protection: Protection,
+	},
+
 	/// Function index out of bounds for length.
 	#[error("Function index {index} out of bounds for length {len}")]
 	FunctionIndexOutOfBounds {
@@ -25,22 +62,350 @@ pub enum Error {
 		len: usize,
 	},
 
+	/// Global index out of bounds for length.
+	#[error("Global index {index} out of bounds for length {len}")]
+	GlobalIndexOutOfBounds {
+		index: usize,
+		len: usize,
+	},
+
+	/// Type index out of bounds for length.
+	#[error("Type index {index} out of bounds for length {len}")]
+	TypeIndexOutOfBounds {
+		index: usize,
+		len: usize,
+	},
+
+	/// Table index out of bounds for length.
+	#[error("Table index {index} out of bounds for length {len}")]
+	TableIndexOutOfBounds {
+		index: usize,
+		len: usize,
+	},
+
+	/// Accessed elements of table with size.
+	#[error("Accessed elements {range:?} of table {table} with size {size}")]
+	InvalidTableAccess {
+		table: usize,
+		range: Range<usize>,
+		size: usize,
+	},
+
+	/// Element segment index out of bounds for length.
+	#[error("Element segment index {index} out of bounds for length {len}")]
+	ElementSegmentIndexOutOfBounds {
+		index: usize,
+		len: usize,
+	},
+
+	/// Accessed elements of element segment with size.
+	#[error("Accessed elements {range:?} of element segment {segment} with size {size}")]
+	InvalidElementSegmentAccess {
+		segment: usize,
+		range: Range<usize>,
+		size: usize,
+	},
+
+	/// Data segment index out of bounds for length.
+	#[error("Data segment index {index} out of bounds for length {len}")]
+	DataSegmentIndexOutOfBounds {
+		index: usize,
+		len: usize,
+	},
+
+	/// Accessed bytes of data segment with size.
+	#[error("Accessed bytes {range:?} of data segment {segment} with size {size}")]
+	InvalidDataSegmentAccess {
+		segment: usize,
+		range: Range<usize>,
+		size: usize,
+	},
+
+	/// Atomic access to an address which is not a multiple of the access size.
+	#[error("Atomic access to address {addr} which is not aligned to {align} bytes")]
+	UnalignedAtomicAccess {
+		addr: usize,
+		align: usize,
+	},
+
+	/// A branch targets a label which does not exist.
+	#[error("Branch to label {0} which does not exist")]
+	LabelIndexOutOfBounds(u32),
+
+	/// No definition was provided for an import.
+	#[error("Unresolved import {0}")]
+	UnresolvedImport(Identifier),
+
+	/// The definition of an import has a different signature than the import.
+	#[error("Import {} expects signature {:?}, but is defined with {:?}", .0.name, .0.expected, .0.got)]
+	ImportSignatureMismatch(Box<ImportMismatch>),
+
+	/// The definition of an imported global, memory or table does not match the type of the import.
+	#[error("Import {name} is incompatible with its definition: {reason}")]
+	IncompatibleImport {
+		name: Identifier,
+		reason: &'static str,
+	},
+
+	/// The definition of an import depends on the host environment, which is refused in deterministic mode.
+	#[error("Import {0} is nondeterministic")]
+	NondeterministicImport(Identifier),
+
+	/// The module uses proposals which are not enabled by [`Config::features`](crate::exec::Config::features).
+	#[error("Feature {0} not enabled")]
+	FeatureNotEnabled(Features),
+
+	/// A host function popped a different number of values than the parameters declared by its signature.
+	#[error("Host function {function} left the operand stack at height {got} instead of {expected}")]
+	HostFunctionStackMismatch {
+		function: String,
+		expected: usize,
+		got: usize,
+	},
+
+	/// An earlier execution of the instance trapped with the message, so the memory and globals may be inconsistent.
+	/// See [`Config::allow_resume_after_trap`](crate::exec::Config::allow_resume_after_trap).
+	#[error("The instance is poisoned by an earlier trap: {0}")]
+	InstancePoisoned(String),
+
+	/// A call from the host left a different number of values on the operand stack than the results of the function.
+	#[error("The call left {got} values on the operand stack instead of its {expected} results")]
+	UnbalancedOperandStack {
+		expected: usize,
+		got: usize,
+	},
+
+	/// A host function returned a different number of results than declared by its signature.
+	#[error("Host function {function} returned {got} results instead of {expected}")]
+	HostResultCountMismatch {
+		function: String,
+		expected: usize,
+		got: usize,
+	},
+
+	/// Instantiating a module would exceed the [`StoreLimits`](crate::exec::StoreLimits) of the instance.
+	#[error("Instantiating exceeds the limit of {limit} {resource}")]
+	LimitExceeded {
+		resource: &'static str,
+		limit: usize,
+	},
+
+	/// A host call does not match the next call of the replayed [`EventLog`](crate::exec::EventLog).
+	#[error("Replaying the call of host function {function} diverged from the event log: {reason}")]
+	ReplayDiverged {
+		function: String,
+		reason: &'static str,
+	},
+
+	/// Local index out of bounds for length.
+	#[error("Local index {index} out of bounds for length {len}")]
+	LocalIndexOutOfBounds {
+		index: usize,
+		len: usize,
+	},
+
 	/// Pop was called on an empty operand stack.
 	#[error("Pop was called on an empty operand stack")]
 	PopOnEmptyOperandStack,
 
-	/// Expected on stack, got instead
-	#[error("Expected {expected} on stack, got {got:?} instead")]
-	StackTypeError {
-		expected: &'static str,
-		got: Value,
+	/// A function tried to pop a value which belongs to its caller.
+	#[error("Operand stack underflow in function {function}")]
+	OperandStackUnderflow {
+		function: String,
 	},
 
-	/// Trap because of...
+	/// Expected on stack, got instead
+	#[error("Expected {} on stack, got {:?} instead", .0.expected, .0.got)]
+	StackTypeError(Box<Mismatch<&'static str, Value>>),
+
+	/// The execution trapped for the reason of the [TrapKind].
 	#[error("Trap because of {0}")]
-	Trap(&'static str),
+	Trap(TrapKind),
+
+	/// A thrown exception was not caught by the guest.
+	#[error("Uncaught exception with values {:?}", .0.values())]
+	Exception(Exception),
+
+	/// Tag index out of bounds for length.
+	#[error("Tag index {index} out of bounds for length {len}")]
+	TagIndexOutOfBounds {
+		index: usize,
+		len: usize,
+	},
+
+	/// The module does not export a function with this name.
+	#[error("The module does not export a function `{0}`")]
+	UnknownExport(String),
+
+	/// The host tried to set an immutable global.
+	#[error("Global {index} is immutable")]
+	ImmutableGlobal {
+		index: usize,
+	},
+
+	/// The host tried to set a global to a value of another type.
+	#[error("Expected a global value of type {:?}, got {:?}", .0.expected, .0.got)]
+	GlobalTypeMismatch(Box<Mismatch<Type, Value>>),
+
+	/// The host called a function with arguments not matching its parameters.
+	#[error("Expected arguments of types {:?}, got {:?}", .0.expected, .0.got)]
+	ArgumentMismatch(Box<Mismatch<Vec<Type>, Vec<Value>>>),
+
+	/// The component imports a function which the component linker does not define.
+	#[error("The component import `{0}` is not defined")]
+	UnresolvedComponentImport(String),
+
+	/// Component values do not have the types of the parameters or results of a component function.
+	#[error("Expected component values of types {:?}, got {:?}", .0.expected, .0.got)]
+	ComponentValueMismatch(Box<Mismatch<Vec<ValueType>, Vec<ComponentValue>>>),
+
+	/// The component uses a feature which cannot be instantiated or called yet.
+	#[error("Components with {0} are not supported")]
+	UnsupportedComponent(&'static str),
+
+	/// The guest trapped after printing the panic message of Rust, see [`guest::panic_message`].
+	///
+	/// [`guest::panic_message`]: crate::exec::guest::panic_message
+	#[error("{error}, after the guest panicked at {message}")]
+	GuestPanic {
+		error: Box<Error>,
+		message: String,
+	},
+
+	/// The module loaded as side module has no `dylink.0` section.
+	#[error("The module is not a side module, as it has no dylink.0 section")]
+	NotASideModule,
+
+	/// The `dylink.0` section of a side module is malformed.
+	#[error("Invalid dylink.0 section: {0}")]
+	InvalidDylinkSection(#[source] ParsingError),
+
+	/// The execution was aborted by the debugger.
+	#[error("The execution was aborted by the debugger")]
+	DebuggerAbort,
 
 	/// Underlying IoError
 	#[error("IoError: {0}")]
 	IoError(#[from] io::Error),
+}
+
+/// The signatures of an import whose definition does not match, see [`Error::ImportSignatureMismatch`].
+#[derive(Debug, Clone)]
+pub struct ImportMismatch {
+	pub name: Identifier,
+	pub expected: FunctionSignature,
+	pub got: FunctionSignature,
+}
+
+/// The expected type and the value which did not have it, boxed in the variants of [`Error`] to keep it small.
+#[derive(Debug, Clone)]
+pub struct Mismatch<E, G> {
+	pub expected: E,
+	pub got: G,
+}
+
+/// An error of a host function, which is passed through the guest to the embedder unchanged.
+pub type HostError = Box<dyn std::error::Error + Send + Sync>;
+
+impl Error {
+	/// Wraps the `error` of a host function into a [`TrapKind::HostError`], e.g. with `.map_err(Error::host)?`.
+	///
+	/// The error is returned unchanged from the call into the guest, where it can be recovered with
+	/// [`Error::downcast_ref`] or [`Error::into_host_error`].
+	pub fn host(error: impl Into<HostError>) -> Self {
+		Error::Trap(TrapKind::HostError(error.into()))
+	}
+
+	/// The error of the host function which caused the trap, if it is an `E`.
+	pub fn downcast_ref<E: std::error::Error + 'static>(&self) -> Option<&E> {
+		match self {
+			Error::Trap(TrapKind::HostError(error)) => error.downcast_ref(),
+			Error::GuestPanic { error, .. } => error.downcast_ref(),
+			_ => None,
+		}
+	}
+
+	/// The error of the host function which caused the trap, or the error itself if it was not caused by one.
+	pub fn into_host_error(self) -> Result<HostError, Self> {
+		match self {
+			Error::Trap(TrapKind::HostError(error)) => Ok(error),
+			Error::GuestPanic { error, message } => error.into_host_error()
+				.map_err(|error| Error::GuestPanic { error: Box::new(error), message }),
+			error => Err(error),
+		}
+	}
+
+	/// The kind of the trap, if the error is one.
+	pub fn trap_kind(&self) -> Option<&TrapKind> {
+		match self {
+			Error::Trap(kind) => Some(kind),
+			Error::GuestPanic { error, .. } => error.trap_kind(),
+			_ => None,
+		}
+	}
+
+	/// The panic message the guest printed before it trapped, if it is a Rust guest which panicked.
+	pub fn panic_message(&self) -> Option<&str> {
+		match self {
+			Error::GuestPanic { message, .. } => Some(message),
+			_ => None,
+		}
+	}
+}
+
+/// Reason of an [Error::Trap], so that embedders can decide per kind how to continue.
+#[derive(Debug, Error)]
+pub enum TrapKind {
+	/// The guest executed an `unreachable` instruction.
+	#[error("an unreachable instruction")]
+	Unreachable,
+
+	/// The guest accessed memory outside of the addressable range.
+	#[error("an out of bounds memory access")]
+	MemoryOutOfBounds,
+
+	/// The guest divided an integer by zero.
+	#[error("an integer divide by zero")]
+	DivisionByZero,
+
+	/// The result of an integer division or conversion is not representable.
+	#[error("an integer overflow")]
+	IntegerOverflow,
+
+	/// The guest converted a NaN to an integer with a trapping truncation.
+	#[error("an invalid conversion to integer")]
+	InvalidConversionToInteger,
+
+	/// The guest called a null function reference.
+	#[error("a null function reference")]
+	NullReference,
+
+	/// The call stack of the guest grew too deep for the host.
+	#[error("an exhausted call stack")]
+	StackExhausted,
+
+	/// The execution used up its fuel.
+	#[error("running out of fuel")]
+	OutOfFuel,
+
+	/// The guest executed an instruction which the interpreter does not implement, named like
+	/// [`Instruction::name`](crate::exec::Instruction::name). See
+	/// [`Config::lenient_mode`](crate::exec::Config::lenient_mode).
+	#[error("the unimplemented instruction {0}")]
+	UnimplementedInstruction(String),
+
+	/// A host function failed.
+	#[error("a host error: {0}")]
+	HostError(HostError),
+
+	/// Any other fault, described by the message.
+	#[error("{0}")]
+	Other(&'static str),
+}
+
+impl TrapKind {
+	/// Whether the trap was caused by a host function instead of the guest.
+	pub fn is_host_error(&self) -> bool {
+		matches!(self, TrapKind::HostError(_))
+	}
 }
\ No newline at end of file
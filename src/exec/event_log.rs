@@ -0,0 +1,101 @@
+use crate::exec::Value;
+
+/// A call of a host function recorded by an [`EventLog`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostCall {
+	/// Name of the called host function.
+	pub function: String,
+	/// The parameters the host function popped off the operand stack.
+	pub args: Vec<Value>,
+	/// The results the host function returned.
+	pub results: Vec<Value>,
+	/// The regions of the memory which changed during the call, as start address and new contents.
+	pub writes: Vec<(usize, Vec<u8>)>,
+	/// Size of the memory in pages after the call.
+	pub memory_pages: usize,
+}
+
+/// Records the calls of host functions of an [`Instance`](crate::exec::Instance), or replays recorded calls instead
+/// of performing them, so that a guest run depending on I/O, clocks or randomness can be re-executed
+/// deterministically, e.g. to reproduce a bug.
+///
+/// Attached with [`Instance::set_event_log`](crate::exec::Instance::set_event_log).
+///
+/// The writes are found by comparing the memory before and after each call, as host functions access it directly.
+/// For the same reason, the regions a host function read cannot be recorded. If a host function calls back into the
+/// guest, only the outer call is recorded and the effects of the callback on the memory are part of its writes.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct EventLog {
+	calls: Vec<HostCall>,
+	/// Index of the next call to replay, or `None` if the calls are recorded.
+	replayed: Option<usize>,
+	/// Number of host calls in progress, of which only the outermost one is recorded.
+	depth: usize,
+}
+
+impl EventLog {
+	/// An empty log which records the host calls.
+	pub fn record() -> Self {
+		Self::default()
+	}
+
+	/// A log which feeds the results and writes of the recorded `calls` back to the guest instead of calling the host
+	/// functions.
+	pub fn replay(calls: Vec<HostCall>) -> Self {
+		Self { calls, replayed: Some(0), depth: 0 }
+	}
+
+	/// The recorded calls, or the ones to replay.
+	pub fn calls(&self) -> &[HostCall] {
+		&self.calls
+	}
+
+	pub fn into_calls(self) -> Vec<HostCall> {
+		self.calls
+	}
+
+	pub fn is_replaying(&self) -> bool {
+		self.replayed.is_some()
+	}
+
+	/// Returns the next call to replay and advances past it, or `None` if all calls were replayed or the calls are
+	/// recorded.
+	pub(crate) fn next_replayed(&mut self) -> Option<&HostCall> {
+		let index = self.replayed.as_mut()?;
+		let call = self.calls.get(*index)?;
+		*index += 1;
+		Some(call)
+	}
+
+	/// Enters a host call and returns whether it is the outermost one, which is recorded.
+	pub(crate) fn enter(&mut self) -> bool {
+		self.depth += 1;
+		self.depth == 1
+	}
+
+	pub(crate) fn exit(&mut self) {
+		self.depth -= 1;
+	}
+
+	pub(crate) fn push(&mut self, call: HostCall) {
+		self.calls.push(call);
+	}
+}
+
+/// The regions in which `after` differs from `before`, where bytes beyond the end of `before` count as zero, as a
+/// grown memory is zeroed.
+pub(crate) fn changed_regions(before: &[u8], after: &[u8]) -> Vec<(usize, Vec<u8>)> {
+	let mut regions: Vec<(usize, Vec<u8>)> = Vec::new();
+	let mut current: Option<(usize, Vec<u8>)> = None;
+	for (addr, &byte) in after.iter().enumerate() {
+		let changed = before.get(addr).copied().unwrap_or(0) != byte;
+		match (&mut current, changed) {
+			(Some((_, bytes)), true) => bytes.push(byte),
+			(None, true) => current = Some((addr, vec![byte])),
+			(Some(_), false) => regions.extend(current.take()),
+			(None, false) => (),
+		}
+	}
+	regions.extend(current);
+	regions
+}
\ No newline at end of file
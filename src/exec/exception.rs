@@ -0,0 +1,51 @@
+use crate::shared::Shared;
+use crate::exec::{FunctionSignature, Value};
+
+/// A tag of the exception handling proposal, which identifies the kind of an [Exception].
+///
+/// Every instance creates its own tags, so exceptions are only caught by `catch` clauses of the instance which
+/// declared the tag, and by `catch_all` clauses of every instance.
+#[derive(Debug)]
+pub struct Tag {
+	signature: Shared<FunctionSignature>,
+}
+
+impl Tag {
+	pub fn new(signature: Shared<FunctionSignature>) -> Self {
+		Self { signature }
+	}
+
+	/// The parameters of the signature are the types of the values of exceptions with this tag.
+	pub fn signature(&self) -> &Shared<FunctionSignature> {
+		&self.signature
+	}
+}
+
+/// An exception thrown by `throw`, which unwinds the guest until a `catch` clause handles it.
+///
+/// If no clause handles it, the call into the guest returns it as [`Error::Exception`](crate::exec::Error::Exception).
+#[derive(Debug, Clone)]
+pub struct Exception {
+	tag: Shared<Tag>,
+	values: Vec<Value>,
+}
+
+impl Exception {
+	pub fn new(tag: Shared<Tag>, values: Vec<Value>) -> Self {
+		Self { tag, values }
+	}
+
+	pub fn tag(&self) -> &Shared<Tag> {
+		&self.tag
+	}
+
+	/// The values passed to `throw`, which a `catch` clause pushes onto the operand stack.
+	pub fn values(&self) -> &[Value] {
+		&self.values
+	}
+
+	/// Whether the exception has the tag `tag`, i.e. the very same one and not an equal one of another instance.
+	pub fn is(&self, tag: &Shared<Tag>) -> bool {
+		Shared::ptr_eq(&self.tag, tag)
+	}
+}
\ No newline at end of file
@@ -0,0 +1,87 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use crate::exec::ExternRef;
+use crate::shared::MaybeSendSync;
+
+/// A host object, which has to be `Send + Sync` with the `sync` feature.
+trait AnyObject: Any + MaybeSendSync {}
+
+impl<T: Any + MaybeSendSync> AnyObject for T {}
+
+/// A host object with the number of handles the host holds to it.
+struct StoredObject {
+	object: Box<dyn AnyObject>,
+	ref_count: usize,
+}
+
+/// Host objects which are passed to the guest as opaque `externref` handles.
+///
+/// The guest can copy handles freely, so the store cannot know when the guest drops them.
+/// Instead, the host counts its handles with [`ExternRefStore::retain`] and [`ExternRefStore::release`],
+/// and the object is removed once the count drops to zero.
+#[derive(Default)]
+pub struct ExternRefStore {
+	objects: HashMap<usize, StoredObject>,
+	next_id: usize,
+}
+
+impl fmt::Debug for ExternRefStore {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("ExternRefStore")
+			.field("len", &self.objects.len())
+			.finish()
+	}
+}
+
+impl ExternRefStore {
+	/// Stores `object` with a reference count of one and returns its handle.
+	pub fn insert<T: Any + MaybeSendSync>(&mut self, object: T) -> ExternRef {
+		let id = self.next_id;
+		self.next_id += 1;
+		self.objects.insert(id, StoredObject { object: Box::new(object), ref_count: 1 });
+		ExternRef(id)
+	}
+
+	/// The object behind `reference`, if it is still stored and of type `T`.
+	pub fn get<T: Any>(&self, reference: ExternRef) -> Option<&T> {
+		let object: &dyn Any = self.objects.get(&reference.0)?.object.as_ref();
+		object.downcast_ref()
+	}
+
+	/// The object behind `reference`, if it is still stored and of type `T`.
+	pub fn get_mut<T: Any>(&mut self, reference: ExternRef) -> Option<&mut T> {
+		let object: &mut dyn Any = self.objects.get_mut(&reference.0)?.object.as_mut();
+		object.downcast_mut()
+	}
+
+	/// Increments the reference count and returns whether the object is still stored.
+	pub fn retain(&mut self, reference: ExternRef) -> bool {
+		match self.objects.get_mut(&reference.0) {
+			Some(stored) => {
+				stored.ref_count += 1;
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// Decrements the reference count and returns the object if this was the last reference.
+	pub fn release(&mut self, reference: ExternRef) -> Option<Box<dyn Any>> {
+		let stored = self.objects.get_mut(&reference.0)?;
+		stored.ref_count -= 1;
+		if stored.ref_count > 0 {
+			return None;
+		}
+		self.objects.remove(&reference.0).map(|stored| stored.object as Box<dyn Any>)
+	}
+
+	/// Number of stored objects.
+	pub fn len(&self) -> usize {
+		self.objects.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.objects.is_empty()
+	}
+}
\ No newline at end of file
@@ -0,0 +1,47 @@
+use std::fmt;
+use crate::shared::Shared;
+use crate::exec::{Callable, Value};
+
+/// An activation of a function on the call stack.
+#[derive(Debug)]
+pub struct Frame {
+	/// Index of the function in the instance.
+	pub(crate) function_index: usize,
+	pub(crate) function: Shared<Callable>,
+	/// Parameters followed by the declared locals of the function.
+	pub(crate) locals: Vec<Value>,
+	/// Offset of the instruction executed last in this frame, see [`Breakpoint::offset`](crate::exec::Breakpoint::offset).
+	pub(crate) offset: usize,
+}
+
+impl Frame {
+	pub fn new(function_index: usize, function: Shared<Callable>, locals: Vec<Value>) -> Self {
+		Self { function_index, function, locals, offset: 0 }
+	}
+
+	/// Index of the function in the instance.
+	pub fn function_index(&self) -> usize {
+		self.function_index
+	}
+
+	/// The function executed by this frame.
+	pub fn function(&self) -> &Callable {
+		&self.function
+	}
+
+	/// Parameters followed by the declared locals of the function.
+	pub fn locals(&self) -> &[Value] {
+		&self.locals
+	}
+
+	/// Offset of the instruction executed last in this frame.
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+}
+
+impl fmt::Display for Frame {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.function.fmt(f)
+	}
+}
\ No newline at end of file
@@ -0,0 +1,53 @@
+use crate::shared::Shared;
+use crate::exec::{Error, FunctionSignature, Mismatch, Instance, InstanceRef, Value};
+
+/// A handle to a function of an [Instance], which the host can call repeatedly.
+///
+/// The handle only stores the function index and does not borrow the instance, so it can be cloned into host
+/// functions. Call it with [`Func::call`] from outside of the instance, or with [`Func::call_from`] from inside a
+/// host function while the guest is executing, which re-enters the guest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Func {
+	index: usize,
+	signature: Shared<FunctionSignature>,
+}
+
+impl Func {
+	pub(crate) fn new(index: usize, signature: Shared<FunctionSignature>) -> Self {
+		Self { index, signature }
+	}
+
+	/// Index of the function in the instance, counting the imported functions first.
+	pub fn index(&self) -> usize {
+		self.index
+	}
+
+	pub fn signature(&self) -> &Shared<FunctionSignature> {
+		&self.signature
+	}
+
+	/// Calls the function in `instance` with `args` and returns its results.
+	pub fn call(&self, instance: &mut Instance, args: Vec<Value>) -> Result<Vec<Value>, Error> {
+		self.check_args(&args)?;
+		instance.invoke(self.index, args)
+	}
+
+	/// Calls the function from inside a host function with `caller` as the instance, and returns its results.
+	///
+	/// The call runs on top of the stacks of the guest which called the host function, so a trap unwinds the
+	/// guest as well when the host function returns it.
+	pub fn call_from(&self, caller: &mut InstanceRef, args: Vec<Value>) -> Result<Vec<Value>, Error> {
+		self.check_args(&args)?;
+		caller.call(self.index, args)
+	}
+
+	fn check_args(&self, args: &[Value]) -> Result<(), Error> {
+		if !args.iter().map(Value::ty).eq(self.signature.params.iter().copied()) {
+			return Err(Error::ArgumentMismatch(Box::new(Mismatch {
+				expected: self.signature.params.clone(),
+				got: args.to_vec(),
+			})));
+		}
+		Ok(())
+	}
+}
\ No newline at end of file
@@ -0,0 +1,51 @@
+use crate::exec::{Error, Instance, Mismatch, Value};
+use crate::parse::Type;
+
+/// A handle to an exported global of an [Instance], e.g. configuration which the host changes between calls.
+///
+/// Like [`Func`](crate::exec::Func), the handle does not borrow the instance, which is passed to [`GlobalHandle::get`]
+/// and [`GlobalHandle::set`] instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobalHandle {
+	index: usize,
+	ty: Type,
+	mutable: bool,
+}
+
+impl GlobalHandle {
+	pub(crate) fn new(index: usize, ty: Type, mutable: bool) -> Self {
+		Self { index, ty, mutable }
+	}
+
+	/// Index of the global in the instance, counting the imported globals first.
+	pub fn index(&self) -> usize {
+		self.index
+	}
+
+	pub fn ty(&self) -> Type {
+		self.ty
+	}
+
+	/// Whether the global may be changed with [`GlobalHandle::set`].
+	pub fn is_mutable(&self) -> bool {
+		self.mutable
+	}
+
+	/// The current value of the global in `instance`.
+	pub fn get(&self, instance: &Instance) -> Value {
+		instance.globals()[self.index].clone()
+	}
+
+	/// Sets the global in `instance` to `value`, which the guest reads in its next call. Fails if the global is
+	/// immutable or `value` has another type.
+	pub fn set(&self, instance: &mut Instance, value: Value) -> Result<(), Error> {
+		if !self.mutable {
+			return Err(Error::ImmutableGlobal { index: self.index });
+		}
+		if value.ty() != self.ty {
+			return Err(Error::GlobalTypeMismatch(Box::new(Mismatch { expected: self.ty, got: value })));
+		}
+		instance.globals_mut()[self.index] = value;
+		Ok(())
+	}
+}
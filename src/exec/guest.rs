@@ -0,0 +1,141 @@
+//! Conveniences for calling guests, which pass byte buffers through the memory allocated by the exported `alloc`
+//! and released by the exported `dealloc` function of the guest.
+//!
+//! Rust guests provide them with `#[no_mangle] extern "C" fn alloc(len: usize) -> *mut u8` and
+//! `#[no_mangle] extern "C" fn dealloc(ptr: *mut u8, len: usize)`.
+
+use crate::exec::{Error, Instance, TrapKind, Value};
+
+/// Prefix of the panic message which the Rust standard library prints to stderr.
+const PANICKED_AT: &str = "panicked at ";
+
+/// Name of the exported function `(len: i32) -> i32` allocating `len` bytes and returning their address.
+pub const ALLOC: &str = "alloc";
+/// Name of the exported function `(ptr: i32, len: i32) -> ()` releasing the `len` bytes at `ptr`.
+pub const DEALLOC: &str = "dealloc";
+
+/// Extracts the location and message of the last Rust panic from the `stderr` output of a guest, which the standard
+/// library prints as `thread 'main' panicked at` followed by them before aborting.
+///
+/// Traps of [`Instance::start`], [`Instance::invoke`] and [`Func::call`](crate::exec::Func::call) after such a panic
+/// are returned as [`Error::GuestPanic`] with this message.
+pub fn panic_message(stderr: &[u8]) -> Option<String> {
+	let stderr = String::from_utf8_lossy(stderr);
+	let (_, panic) = stderr.rsplit_once(PANICKED_AT)?;
+	let message: Vec<&str> = panic.lines()
+		.take_while(|line| !line.starts_with("note: "))
+		.collect();
+	Some(message.join("\n").trim_end().to_owned())
+}
+
+/// A Rust type which is passed as a single WebAssembly value.
+pub trait WasmType: Into<Value> + TryFrom<Value, Error = Error> {}
+
+impl WasmType for i32 {}
+impl WasmType for u32 {}
+impl WasmType for i64 {}
+impl WasmType for u64 {}
+impl WasmType for f32 {}
+impl WasmType for f64 {}
+impl WasmType for [u8; 16] {}
+
+/// Arguments of [`Instance::call`], which are a single [WasmType], a tuple of them or `()`.
+pub trait WasmArgs {
+	fn into_values(self) -> Vec<Value>;
+}
+
+/// Results of [`Instance::call`], which are a single [WasmType], a tuple of them or `()`.
+pub trait WasmResults: Sized {
+	fn from_values(values: Vec<Value>) -> Result<Self, Error>;
+}
+
+impl<T: WasmType> WasmArgs for T {
+	fn into_values(self) -> Vec<Value> {
+		vec![self.into()]
+	}
+}
+
+impl<T: WasmType> WasmResults for T {
+	fn from_values(values: Vec<Value>) -> Result<Self, Error> {
+		let [value] = <[Value; 1]>::try_from(values).map_err(|_| Error::Trap(TrapKind::Other("expected one result")))?;
+		T::try_from(value)
+	}
+}
+
+macro_rules! impl_tuple {
+	($($name:ident),*) => {
+		impl<$($name: WasmType),*> WasmArgs for ($($name,)*) {
+			#[allow(non_snake_case)]
+			fn into_values(self) -> Vec<Value> {
+				let ($($name,)*) = self;
+				vec![$($name.into()),*]
+			}
+		}
+
+		impl<$($name: WasmType),*> WasmResults for ($($name,)*) {
+			#[allow(non_snake_case, unused_mut, unused_variables)]
+			fn from_values(values: Vec<Value>) -> Result<Self, Error> {
+				let names: &[&str] = &[$(stringify!($name)),*];
+				if values.len() != names.len() {
+					return Err(Error::Trap(TrapKind::Other("unexpected number of results")));
+				}
+				let mut values = values.into_iter();
+				Ok(($($name::try_from(values.next().expect("The length was checked"))?,)*))
+			}
+		}
+	};
+}
+
+impl_tuple!();
+impl_tuple!(A, B);
+impl_tuple!(A, B, C);
+impl_tuple!(A, B, C, D);
+impl_tuple!(A, B, C, D, E);
+impl_tuple!(A, B, C, D, E, F);
+
+impl Instance {
+	/// Calls the exported function `name` with `args` and converts its results into `R`.
+	///
+	/// For example, `let sum: i32 = instance.call("add", (1, 2))?;`
+	pub fn call<R: WasmResults>(&mut self, name: &str, args: impl WasmArgs) -> Result<R, Error> {
+		let func = self.get_func(name).ok_or_else(|| Error::UnknownExport(name.to_owned()))?;
+		R::from_values(func.call(self, args.into_values())?)
+	}
+
+	/// Copies `bytes` into memory allocated with the exported `alloc` function of the guest and returns its address
+	/// and length, which can be passed to [`Instance::call`]. The guest or [`Instance::free_buf`] releases it.
+	pub fn write_buf(&mut self, bytes: &[u8]) -> Result<(u32, u32), Error> {
+		let len = bytes.len() as u32;
+		let ptr: u32 = self.call(ALLOC, len)?;
+		let addr = ptr as usize..ptr as usize + bytes.len();
+		let memory = self.memory_mut().ok_or(Error::NoMemory)?;
+		let size = memory.data.len();
+		memory.data.get_mut(addr.clone())
+			.ok_or(Error::InvalidMemoryArea { addr, size })?
+			.copy_from_slice(bytes);
+		Ok((ptr, len))
+	}
+
+	/// Like [`Instance::write_buf`] for the UTF-8 bytes of `string`.
+	pub fn write_str(&mut self, string: &str) -> Result<(u32, u32), Error> {
+		self.write_buf(string.as_bytes())
+	}
+
+	/// The `len` bytes at `ptr`, e.g. a buffer returned by the guest.
+	pub fn read_buf(&self, ptr: u32, len: u32) -> Result<&[u8], Error> {
+		let memory = self.memory().as_ref().ok_or(Error::NoMemory)?;
+		let addr = ptr as usize..ptr as usize + len as usize;
+		memory.data().get(addr.clone())
+			.ok_or(Error::InvalidMemoryArea { addr, size: memory.data().len() })
+	}
+
+	/// The UTF-8 string of `len` bytes at `ptr`.
+	pub fn read_str(&self, ptr: u32, len: u32) -> Result<&str, Error> {
+		std::str::from_utf8(self.read_buf(ptr, len)?).map_err(|_| Error::Trap(TrapKind::Other("invalid UTF-8 string")))
+	}
+
+	/// Releases the buffer of `len` bytes at `ptr` with the exported `dealloc` function of the guest.
+	pub fn free_buf(&mut self, ptr: u32, len: u32) -> Result<(), Error> {
+		self.call(DEALLOC, (ptr, len))
+	}
+}
\ No newline at end of file
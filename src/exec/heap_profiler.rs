@@ -0,0 +1,219 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::Write;
+use crate::exec::{Frame, Value};
+use crate::parse::ExportMap;
+
+/// Allocations of a single call site collected by the [`HeapProfiler`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AllocationSite {
+	/// How often the call site allocated, including reallocations.
+	pub allocations: u64,
+	/// Bytes allocated in total.
+	pub allocated_bytes: u64,
+	/// Allocations which were not freed yet.
+	pub live_allocations: u64,
+	/// Bytes of the allocations which were not freed yet.
+	pub live_bytes: u64,
+}
+
+/// The signature of an allocator function exported by the guest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Allocator {
+	/// `malloc(size) -> ptr` or `__rust_alloc(size, align) -> ptr`.
+	Alloc,
+	/// `calloc(count, size) -> ptr`.
+	Calloc,
+	/// `realloc(ptr, size) -> ptr`.
+	Realloc,
+	/// `__rust_realloc(ptr, old_size, align, new_size) -> ptr`.
+	RustRealloc,
+	/// `free(ptr)` or `__rust_dealloc(ptr, size, align)`.
+	Free,
+}
+
+/// Names of the intercepted exports.
+const ALLOCATORS: &[(&str, Allocator)] = &[
+	("malloc", Allocator::Alloc),
+	("calloc", Allocator::Calloc),
+	("realloc", Allocator::Realloc),
+	("free", Allocator::Free),
+	("__rust_alloc", Allocator::Alloc),
+	("__rust_alloc_zeroed", Allocator::Alloc),
+	("__rust_realloc", Allocator::RustRealloc),
+	("__rust_dealloc", Allocator::Free),
+];
+
+/// A memory block allocated by the guest.
+#[derive(Debug)]
+struct Allocation {
+	size: u64,
+	site: String,
+}
+
+/// A call of an allocator in progress.
+#[derive(Debug)]
+struct AllocatorCall {
+	/// Height of the call stack including the allocator.
+	depth: usize,
+	size: u64,
+	site: String,
+}
+
+/// Records the allocations of the guest by call site, by intercepting the calls of the allocator functions it exports,
+/// e.g. `malloc` and `free` or `__rust_alloc` and `__rust_dealloc`.
+///
+/// A call site is the call stack of the allocation in the folded stacks format of the [`Profiler`], so that memory
+/// blowups can be attributed without recompiling the module. Calls of the allocator from inside the allocator, e.g.
+/// `malloc` by `realloc`, are not recorded separately.
+///
+/// Enabled with [`Config::heap_profile`](crate::exec::Config::heap_profile).
+///
+/// [`Profiler`]: crate::exec::Profiler
+#[derive(Debug, Default)]
+pub struct HeapProfiler {
+	/// The allocators by function index.
+	allocators: HashMap<usize, Allocator>,
+	sites: HashMap<String, AllocationSite>,
+	/// The live allocations by address.
+	live: HashMap<u32, Allocation>,
+	call: Option<AllocatorCall>,
+	live_bytes: u64,
+	peak_bytes: u64,
+}
+
+impl HeapProfiler {
+	pub(crate) fn new(exports: &ExportMap) -> Self {
+		let allocators = ALLOCATORS.iter()
+			.filter_map(|&(name, allocator)| Some((exports.function(name)?, allocator)))
+			.collect();
+		Self { allocators, ..Self::default() }
+	}
+
+	/// Records the call of the function with `function_index`, whose frame with the arguments is on top of
+	/// `call_stack`.
+	pub(crate) fn enter(&mut self, function_index: usize, call_stack: &[Frame]) {
+		let Some(&allocator) = self.allocators.get(&function_index) else {
+			return;
+		};
+		// A call which did not return because of a trap or an exception is abandoned
+		if self.call.as_ref().is_some_and(|call| call.depth >= call_stack.len()) {
+			self.call = None;
+		}
+		if self.call.is_some() {
+			return;
+		}
+
+		let Some(frame) = call_stack.last() else {
+			return;
+		};
+		let num_params = frame.function().signature().params.len();
+		let args: Vec<u32> = frame.locals()[..num_params].iter()
+			.map(|arg| match arg {
+				Value::I32(arg) => *arg as u32,
+				_ => 0,
+			})
+			.collect();
+		let size = match (allocator, &args[..]) {
+			(Allocator::Alloc, [size, ..]) => *size as u64,
+			(Allocator::Calloc, [count, size]) => *count as u64 * *size as u64,
+			(Allocator::Realloc, [ptr, size]) => {
+				self.free(*ptr);
+				*size as u64
+			},
+			(Allocator::RustRealloc, [ptr, _, _, new_size]) => {
+				self.free(*ptr);
+				*new_size as u64
+			},
+			(Allocator::Free, [ptr, ..]) => {
+				self.free(*ptr);
+				return;
+			},
+			_ => return,
+		};
+		let site = call_stack[..call_stack.len() - 1].iter()
+			.map(ToString::to_string)
+			.collect::<Vec<_>>()
+			.join(";");
+		self.call = Some(AllocatorCall { depth: call_stack.len(), size, site });
+	}
+
+	/// Records the return of the function with `function_index` at `depth` of the call stack with `results`.
+	pub(crate) fn exit(&mut self, function_index: usize, depth: usize, results: &[Value]) {
+		if !self.allocators.contains_key(&function_index) || self.call.as_ref().map(|call| call.depth) != Some(depth) {
+			return;
+		}
+		let Some(call) = self.call.take() else {
+			return;
+		};
+		let ptr = match results {
+			[Value::I32(ptr)] if *ptr != 0 => *ptr as u32,
+			// Allocating failed
+			_ => return,
+		};
+
+		let site = self.sites.entry(call.site.clone()).or_default();
+		site.allocations += 1;
+		site.allocated_bytes += call.size;
+		site.live_allocations += 1;
+		site.live_bytes += call.size;
+		self.live_bytes += call.size;
+		self.peak_bytes = self.peak_bytes.max(self.live_bytes);
+		self.live.insert(ptr, Allocation { size: call.size, site: call.site });
+	}
+
+	fn free(&mut self, ptr: u32) {
+		let Some(allocation) = self.live.remove(&ptr) else {
+			return;
+		};
+		if let Some(site) = self.sites.get_mut(&allocation.site) {
+			site.live_allocations -= 1;
+			site.live_bytes -= allocation.size;
+		}
+		self.live_bytes -= allocation.size;
+	}
+
+	/// Allocations per call site.
+	pub fn sites(&self) -> &HashMap<String, AllocationSite> {
+		&self.sites
+	}
+
+	/// Bytes of the allocations which were not freed yet.
+	pub fn live_bytes(&self) -> u64 {
+		self.live_bytes
+	}
+
+	/// The maximum of [`live_bytes`](Self::live_bytes) so far.
+	pub fn peak_bytes(&self) -> u64 {
+		self.peak_bytes
+	}
+
+	/// A table of all call sites, sorted descending by their live bytes and then by their allocated bytes.
+	pub fn summary(&self) -> String {
+		let mut sites = self.sites.iter().collect::<Vec<_>>();
+		sites.sort_by_key(|(_, site)| Reverse((site.live_bytes, site.allocated_bytes)));
+
+		let mut summary = format!("live {} bytes, peak {} bytes\n", self.live_bytes, self.peak_bytes);
+		let _ = writeln!(
+			summary, "{:>12} {:>14} {:>10} {:>14}  site",
+			"allocations", "allocated", "live", "live bytes"
+		);
+		for (stack, site) in sites {
+			let _ = writeln!(
+				summary, "{:>12} {:>14} {:>10} {:>14}  {}",
+				site.allocations, site.allocated_bytes, site.live_allocations, site.live_bytes, stack
+			);
+		}
+		summary
+	}
+
+	/// Live bytes per call site in the folded stacks format, which can be rendered by flamegraph tools like
+	/// `inferno-flamegraph`.
+	pub fn folded_stacks(&self) -> String {
+		let mut sites = self.sites.iter().filter(|(_, site)| site.live_bytes > 0).collect::<Vec<_>>();
+		sites.sort_by_key(|(a, _)| *a);
+		sites.into_iter()
+			.map(|(stack, site)| format!("{} {}\n", stack, site.live_bytes))
+			.collect()
+	}
+}
\ No newline at end of file
@@ -0,0 +1,37 @@
+use std::fmt;
+use std::ops::Range;
+use crate::exec::{Callable, Error};
+use crate::shared::MaybeSendSync;
+
+/// Callbacks invoked by the interpreter, which can be registered with
+/// [`Instance::set_hooks`](crate::exec::Instance::set_hooks) to build tracers, coverage tools or similar
+/// instrumentation.
+///
+/// All methods do nothing by default, so implementors only need to override the events they are interested in.
+pub trait Hooks: MaybeSendSync {
+	/// Called when entering the function with `function_index`.
+	fn on_call(&mut self, _function_index: usize, _function: &Callable) {}
+
+	/// Called when the function with `function_index` returns normally.
+	fn on_return(&mut self, _function_index: usize, _function: &Callable) {}
+
+	/// Called when the execution is stopped by `error`.
+	fn on_trap(&mut self, _error: &Error) {}
+
+	/// Called after the memory was grown from `old_pages` to `new_pages`.
+	fn on_memory_grow(&mut self, _old_pages: usize, _new_pages: usize) {}
+
+	/// Called after the guest wrote `data` to `addr`, if the write overlaps one of the [`Hooks::watched_memory`] ranges.
+	fn on_memory_write(&mut self, _addr: usize, _data: &[u8]) {}
+
+	/// Memory ranges for which [`Hooks::on_memory_write`] is called.
+	fn watched_memory(&self) -> &[Range<usize>] {
+		&[]
+	}
+}
+
+impl fmt::Debug for dyn Hooks {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "<hooks>")
+	}
+}
\ No newline at end of file
@@ -1,72 +1,599 @@
-use std::ops::{BitAnd, BitOr, BitXor, Deref, Shl, Shr};
-use std::rc::Rc;
-use crate::exec::memory::Memory;
-use crate::exec::{Callable, Instruction, Value, ExecutionResult, wasi};
-use crate::exec::error::Error;
-use crate::exec::OperandStack;
-use crate::parse::Module;
+use std::fs;
+use std::ops::{BitAnd, BitOr, BitXor, Deref, Range};
+use crate::shared::{self, Shared};
+use crate::exec::memory::{Memory, MEMORY_PAGE_SIZE};
+use crate::exec::operand_stack::CachedTop;
+use crate::exec::limits::Reservation;
+use crate::exec::{AtomicWidth, BlockType, Callable, Catch, Exception, MemArg, Tag, FunctionSignature, Instruction, Value, ExecutionResult, WasmFunction};
+use crate::exec::error::{Error, TrapKind};
+use crate::exec::{Breakpoint, Config, DebugAction, Debugger, Engine, Frame, Hooks, EventLog, HeapProfiler, HostCall, Linker, OperandStack, Profiler, Stats, Table, table, ExternRefStore, Environment, Func, GlobalHandle, Mismatch, SignatureId, WasiCtx};
+use crate::exec::{coredump, event_log, guest, simd};
+use crate::exec::linker::Externs;
+#[cfg(feature = "jit")]
+use crate::exec::jit::{CompiledFunction, Jit};
+use crate::parse::{ElementMode, ExportKind, ExportMap, Module, SectionId, SharedBytes};
 
 
+/// How the execution continues after executing a sequence of instructions.
+enum ControlFlow {
+	/// The end of the sequence was reached.
+	Continue,
+	/// Branch to the label with this relative index, where 0 refers to the innermost enclosing block.
+	Branch(u32),
+	/// Return from the current function, leaving all enclosing blocks.
+	Return,
+	/// The steps of [`Instance::run_steps`] are used up. The position was saved to [`Instance::suspended`].
+	Yield,
+	/// Rethrow the exception of the catch clause with this relative label index.
+	Rethrow(u32),
+	/// Throw the exception as if by an instruction inside the label with this relative index, see `delegate`.
+	Delegate(u32, Exception),
+	/// Return from the current function and call the function with this index instead, see `return_call`.
+	TailCall(usize),
+}
+
+/// What a branch to the label of a block does, i.e. which instruction the block belongs to.
+#[derive(Clone, Copy)]
+enum LabelKind<'e> {
+	Block,
+	Loop,
+	/// A catch clause handling the exception, of which `pushed` values were pushed onto the operand stack.
+	Catch { exception: &'e Exception, pushed: usize },
+}
+
+/// The position of one level of the interpreter at which the execution yielded.
+///
+/// While yielding, every level pushes its position, so that the innermost level is at the bottom. When resuming,
+/// the levels are re-entered from the outermost one, each popping its position.
+#[derive(Debug, Clone)]
+enum Suspended {
+	/// The instruction with this index in its sequence was not executed yet.
+	Instruction(usize),
+	/// The block or call at `index` in its sequence yielded. For an `if`, `else_branch` is the taken branch.
+	/// For a `try`, `else_branch` is whether a catch clause yielded instead of the body.
+	Nested { index: usize, else_branch: bool },
+	/// A block or function body with the operand stack height at its start.
+	Label { height: usize },
+	/// The catch clause with this index of a `try` handling the exception. This is followed by the `try`.
+	Caught { clause: usize, exception: Exception },
+	/// The function with this index executes instead of the called one, because of tail calls or because it was
+	/// called indirectly. This follows its label.
+	Callee { function_index: usize },
+}
+
+/// The bytes of the memory, which the loop of [`InstanceRef::execute_instructions`] holds across instructions, so
+/// that a load or store only has to compare its end with `len` instead of looking up the memory and its protections.
+///
+/// The bytes may move or be shared again when the memory grows or is cloned, so the view is dropped before every
+/// instruction which could do that.
+#[derive(Clone, Copy)]
+struct MemoryView {
+	ptr: *mut u8,
+	len: usize,
+}
+
+impl MemoryView {
+	/// The start of an access of `N` bytes at the dynamic address `base`, or [`None`] if it is out of bounds.
+	fn address<const N: usize>(&self, mem_arg: &MemArg, base: u32) -> Option<usize> {
+		// The 33 bit address plus `N` can not overflow
+		let start = base as u64 + mem_arg.offset as u64;
+		(start + N as u64 <= self.len as u64).then_some(start as usize)
+	}
+
+	fn load<const N: usize>(&self, mem_arg: &MemArg, base: u32) -> Option<[u8; N]> {
+		let addr = self.address::<N>(mem_arg, base)?;
+		// SAFETY: The `N` bytes at `addr` lie within the `len` bytes at `ptr`
+		Some(unsafe { self.ptr.add(addr).cast::<[u8; N]>().read_unaligned() })
+	}
+
+	/// Stores `bytes` and returns their address.
+	fn store<const N: usize>(&self, mem_arg: &MemArg, base: u32, bytes: [u8; N]) -> Option<usize> {
+		let addr = self.address::<N>(mem_arg, base)?;
+		// SAFETY: The `N` bytes at `addr` lie within the `len` bytes at `ptr`
+		unsafe { self.ptr.add(addr).cast::<[u8; N]>().write_unaligned(bytes) };
+		Some(addr)
+	}
+}
+
+/// The result of [`Instance::run_steps`].
+#[derive(Debug)]
+pub enum StepOutcome {
+	/// `_start` returned.
+	Completed,
+	/// The execution stopped because of an error.
+	TrapOccurred(Error),
+	/// All steps were executed. The next call to [`Instance::run_steps`] resumes the execution.
+	Yielded,
+}
+
+// Instances can be sent to other threads with the `sync` feature
+#[cfg(feature = "sync")]
+const _: fn() = || {
+	fn assert_send_sync<T: Send + Sync>() {}
+	assert_send_sync::<Instance>();
+};
+
 /// A module in execution.
 #[derive(Debug)]
 pub struct Instance {
-	functions: Vec<Rc<Callable>>,
+	/// The module this is an instance of, which is shared between all its instances.
+	module: Shared<Module>,
+	functions: Vec<Shared<Callable>>,
+	/// The ids of the signatures of the types of the module, by type index.
+	type_ids: Vec<SignatureId>,
+	/// The ids of the signatures of the functions, by function index.
+	signature_ids: Vec<SignatureId>,
 	memory: Option<Memory>,
+	tables: Vec<Table>,
+	/// The element segments, of which dropped ones are empty.
+	elements: Vec<Vec<Value>>,
+	/// The bytes of the data segments, of which dropped and active ones are empty.
+	data: Vec<SharedBytes>,
+	/// The current values of the globals.
+	globals: Vec<Value>,
+	/// The tags of the exceptions thrown and caught by the module.
+	tags: Vec<Shared<Tag>>,
+	/// Host objects passed to the guest as `externref`.
+	extern_refs: ExternRefStore,
 	/// The stack for working with values and instructions.
 	operand_stack: OperandStack,
 	/// The function call stack, usually starting with `_start`.
 	///
 	/// You may visualize this using:
 	/// `self.call_stack.iter().map(ToString::to_string).collect::<Vec<_>>()`
-	call_stack: Vec<Rc<Callable>>,
+	call_stack: Vec<Frame>,
+	/// Where the execution yielded in [`Instance::run_steps`], empty if it is not suspended.
+	suspended: Vec<Suspended>,
+	/// Remaining instructions to execute before yielding, unlimited if `None`.
+	steps_left: Option<u64>,
+	/// Position of the instruction executed last.
+	position: Option<Breakpoint>,
+	/// The message of the trap which aborted an execution, after which the instance cannot be called again unless
+	/// [`Config::allow_resume_after_trap`] is set.
+	poisoned: Option<String>,
+	config: Config,
+	profiler: Option<Profiler>,
+	heap_profiler: Option<HeapProfiler>,
+	stats: Stats,
+	debugger: Option<Debugger>,
+	hooks: Option<Box<dyn Hooks>>,
+	event_log: Option<EventLog>,
+	environment: Environment,
+	wasi: WasiCtx,
+	/// The resources accounted in the [`StoreLimits`](crate::exec::StoreLimits) of the config.
+	reservation: Option<Reservation>,
+	/// The functions compiled to native code.
+	#[cfg(feature = "jit")]
+	jit: Option<Shared<Jit>>,
 }
 
 impl Instance {
-	pub fn new(module: Module) -> Self {
-		/*let wasi = {
-			let mut wasi: HashMap<Identifier, Callable> = HashMap::new();
-			wasi.insert(
-				Identifier {
-					module: "wasi_snapshot_preview1".to_owned(),
-					field: "fd_write".to_owned()
-				},
-				Callable::RustClosure(Box::new(|| println!("fd_write called")))
-			);
-			wasi
-		};
+	/// Instantiates `module` like [`Instance::with_config`] with the config of `engine`.
+	pub fn new(engine: &Engine, module: impl Into<Shared<Module>>) -> Result<Self, Error> {
+		Self::with_config(module, engine.config().clone())
+	}
 
-		for import in module.functions.imports {
-			wasi[import.name]
-		}*/
+	/// Instantiates `module` with the imports provided by [`Linker::new`], and [`Linker::log`] if
+	/// [`Config::env_log`] is enabled.
+	///
+	/// Calling an import which the linker does not define fails with [`Error::UnresolvedImport`], calling one
+	/// defined with another signature with [`Error::ImportSignatureMismatch`]. Use [`Linker::instantiate`] to
+	/// detect these upfront. Imported globals get the default value of their type and imported memories and tables
+	/// are created with their minimum size, as [`Linker::new`] does not define any.
+	///
+	/// Fails with [`Error::MemoryAllocationFailed`] if the memory cannot be allocated, and with
+	/// [`Error::DataSegmentOutOfBounds`] or [`Error::ElementSegmentOutOfBounds`] if a segment does not fit. The
+	/// function of the start section runs as part of the instantiation, whose trap is returned as well.
+	pub fn with_config(module: impl Into<Shared<Module>>, config: Config) -> Result<Self, Error> {
+		let module = module.into();
+		let mut linker = Linker::new();
+		if config.env_log {
+			linker.log();
+		}
+		let imports = module.functions.imports()
+			.map(|import| linker.resolve(import).unwrap_or_else(|error| {
+				let name = import.name.clone();
+				let mismatch = match error {
+					Error::ImportSignatureMismatch(mismatch) => Some(mismatch),
+					_ => None,
+				};
+				Shared::new(Callable::RustClosure {
+					name: name.clone(),
+					signature: Shared::clone(&import.signature),
+					closure: Box::new(move |_| Err(match &mismatch {
+						Some(mismatch) => Error::ImportSignatureMismatch(mismatch.clone()),
+						None => Error::UnresolvedImport(name.clone()),
+					})),
+				})
+			}))
+			.collect();
+		let externs = Externs::placeholders(&module)?;
+		let mut instance = Self::with_imports(module, config, imports, externs)?;
+		instance.reserve(true)?;
+		instance.run_start_section()?;
+		Ok(instance)
+	}
 
-		let mut functions: Vec<Rc<Callable>> = vec![
-			Rc::new(Callable::RustFunction {
-				name: ("wasi_snapshot_preview1", "fd_write").into(),
-				function: wasi::fd_write
-			})
-		];
+	/// Instantiates `module` with `imports` as the definitions of the imported functions, in the same order, and
+	/// `externs` as the definitions of the other imports.
+	///
+	/// Only the runtime state like memory, tables and stacks is created, while function bodies and types are
+	/// shared with `module`. Thus, the same module can be instantiated many times cheaply.
+	///
+	/// Fails with [`Error::DataSegmentOutOfBounds`] or [`Error::ElementSegmentOutOfBounds`] if the offset of a data or
+	/// element segment, which may depend on the imported globals, exceeds the memory or its table.
+	#[tracing::instrument(name = "instantiate", skip_all)]
+	pub(crate) fn with_imports(
+		module: Shared<Module>, config: Config, imports: Vec<Shared<Callable>>, externs: Externs,
+	) -> Result<Self, Error> {
+		let mut functions = imports;
 		functions.extend(
-			module.functions.wasm.into_iter()
-				.map(|wasm_func| Rc::new(Callable::WasmFunction(wasm_func)))
+			module.functions.local()
+				.map(|wasm_func| Shared::new(Callable::WasmFunction(Shared::clone(wasm_func))))
 		);
+		// Ids are assigned once here, so that `call_indirect` compares them instead of the signatures
+		let (type_ids, signature_ids) = {
+			let mut signatures = shared::lock(&config.signatures);
+			let type_ids = module.types.iter().map(|signature| signatures.intern(signature).0).collect();
+			let signature_ids = functions.iter().map(|function| signatures.intern(function.signature()).0).collect();
+			(type_ids, signature_ids)
+		};
+
+		let mut imported_globals = externs.globals.into_iter();
+		let mut globals = Vec::with_capacity(module.globals.len());
+		for global in &module.globals {
+			let value = match global.import {
+				Some(_) => imported_globals.next().expect("Each imported global is defined"),
+				None => global.init.eval(&globals).expect("The parser checked that the initializer evaluates"),
+			};
+			globals.push(value);
+		}
+		// An imported memory is the memory 0 of the module, into which its data segments are copied. Their offsets
+		// may read the imported globals, e.g. the base address of position independent code.
+		let memories = match (&module.memory_blueprint, externs.memory) {
+			(Some(blueprint), memory) => {
+				let mut memory = match memory {
+					Some(memory) => memory,
+					None => Memory::without_segments(blueprint)?,
+				};
+				memory.copy_segments(&blueprint.init, &globals)?;
+				Some(memory)
+			},
+			(None, _) => None,
+		};
+		let tags = module.tags.iter()
+			.map(|signature| Shared::new(Tag::new(Shared::clone(signature))))
+			.collect();
+
+		let mut imported_tables = externs.tables.into_iter();
+		let mut tables: Vec<Table> = module.table_blueprints.iter()
+			.map(|blueprint| match blueprint.import {
+				Some(_) => imported_tables.next().expect("Each imported table is defined"),
+				None => Table::from(blueprint),
+			})
+			.collect();
+		// Copy active element segments into their tables. Only passive segments remain available to `table.init`,
+		// the other ones behave as if they were dropped.
+		let mut elements = Vec::with_capacity(module.elements.len());
+		for element_segment in &module.elements {
+			let values = table::element_values(element_segment);
+			match element_segment.mode {
+				ElementMode::Active { table, .. } => {
+					let offset = element_segment.mode.resolve_offset(&globals)
+						.expect("The parser checked that the offset evaluates to an i32");
+					let table_slice = offset..offset.saturating_add(values.len());
+					let size = tables[table].size();
+					if table_slice.end > size {
+						return Err(Error::ElementSegmentOutOfBounds { offset: table_slice, size });
+					}
+					tables[table].elements.splice(table_slice, values);
+					elements.push(Vec::new());
+				},
+				ElementMode::Passive => elements.push(values),
+				ElementMode::Declarative => elements.push(Vec::new()),
+			}
+		}
+		// Likewise, active data segments were copied into the memory and behave as if they were dropped
+		let data = module.passive_data.iter().map(|data| data.clone().unwrap_or_default()).collect();
 
-		let memories = module.memory_blueprint.map(Memory::from);
+		#[cfg(feature = "jit")]
+		let jit = config.jit.then(|| Jit::compile(&module)).flatten().map(Shared::new);
+		let heap_profiler = config.heap_profile.then(|| HeapProfiler::new(&module.export_map));
+
+		Ok(Self {
+			module,
+			functions,
+			type_ids,
+			signature_ids,
+			memory: memories,
+			tables,
+			elements,
+			data,
+			globals,
+			tags,
+			extern_refs: ExternRefStore::default(),
+			operand_stack: OperandStack::default(),
+			call_stack: Vec::new(),
+			suspended: Vec::new(),
+			steps_left: None,
+			position: None,
+			poisoned: None,
+			profiler: config.profile.then(Profiler::default),
+			heap_profiler,
+			stats: Stats::default(),
+			debugger: None,
+			hooks: None,
+			event_log: None,
+			environment: Environment::new(&config),
+			wasi: WasiCtx::default(),
+			reservation: None,
+			#[cfg(feature = "jit")]
+			jit,
+			config,
+		})
+	}
+
+	/// Accounts the memory, tables and code of this new instance in the [`StoreLimits`](crate::exec::StoreLimits) of
+	/// its config. Exceeding them fails with [`Error::LimitExceeded`], unless `force` is set, e.g. for infallible
+	/// constructors.
+	pub(crate) fn reserve(&mut self, force: bool) -> Result<(), Error> {
+		let Some(limits) = &self.config.limits else {
+			return Ok(());
+		};
+		let memory_bytes = self.memory.as_ref().map_or(0, |memory| memory.data.len());
+		let table_elements = self.tables.iter().map(Table::size).sum();
+		let code_size = self.module.sections.iter()
+			.filter(|section| section.id == SectionId::Code)
+			.map(|section| section.size as usize)
+			.sum();
+		self.reservation = Some(match force {
+			true => Reservation::force(limits, memory_bytes, table_elements, code_size),
+			false => Reservation::new(limits, memory_bytes, table_elements, code_size)?,
+		});
+		Ok(())
+	}
+
+	/// The current values of the globals.
+	pub(crate) fn globals(&self) -> &[Value] {
+		&self.globals
+	}
 
+	pub(crate) fn globals_mut(&mut self) -> &mut [Value] {
+		&mut self.globals
+	}
 
-		Self { functions, memory: memories, operand_stack: OperandStack::default(), call_stack: Vec::new() }
+	/// Creates a new instance with the current memory, tables and globals of this one, e.g. to initialize a template
+	/// instance once and fork it for every request.
+	///
+	/// The module and the imported functions are shared. The memory is copied on write, so forking is cheap and the
+	/// first store of either instance copies it, unless the `mmap` feature is enabled, which copies it right away.
+	/// The fork starts without a suspended execution, profile, debugger or hooks, and without the host objects of
+	/// this instance, so `externref`s to them cannot be resolved in the fork.
+	/// A fork of a [poisoned](Instance::is_poisoned) instance is poisoned as well.
+	pub fn fork(&self) -> Instance {
+		Self {
+			module: Shared::clone(&self.module),
+			functions: self.functions.clone(),
+			type_ids: self.type_ids.clone(),
+			signature_ids: self.signature_ids.clone(),
+			memory: self.memory.clone(),
+			tables: self.tables.clone(),
+			elements: self.elements.clone(),
+			data: self.data.clone(),
+			globals: self.globals.clone(),
+			tags: self.tags.clone(),
+			extern_refs: ExternRefStore::default(),
+			operand_stack: OperandStack::default(),
+			call_stack: Vec::new(),
+			suspended: Vec::new(),
+			steps_left: None,
+			position: None,
+			poisoned: self.poisoned.clone(),
+			profiler: self.config.profile.then(Profiler::default),
+			heap_profiler: self.config.heap_profile.then(|| HeapProfiler::new(&self.module.export_map)),
+			stats: Stats::default(),
+			debugger: None,
+			hooks: None,
+			event_log: None,
+			environment: self.environment.clone(),
+			wasi: WasiCtx::default(),
+			reservation: self.reservation.as_ref().map(Reservation::duplicate),
+			#[cfg(feature = "jit")]
+			jit: self.jit.clone(),
+			config: self.config.clone(),
+		}
 	}
 
-	fn as_ref(&mut self) -> InstanceRef {
+	fn as_ref(&mut self) -> InstanceRef<'_> {
 		InstanceRef {
 			functions: &self.functions,
+			types: &self.module.types,
+			type_ids: &self.type_ids,
+			signature_ids: &self.signature_ids,
+			exports: &self.module.export_map,
 			memory: &mut self.memory,
+			tables: &mut self.tables,
+			elements: &mut self.elements,
+			data: &mut self.data,
+			globals: &mut self.globals,
+			tags: &self.tags,
+			extern_refs: &mut self.extern_refs,
 			operand_stack: &mut self.operand_stack,
 			call_stack: &mut self.call_stack,
+			suspended: &mut self.suspended,
+			steps_left: &mut self.steps_left,
+			position: &mut self.position,
+			config: &self.config,
+			profiler: &mut self.profiler,
+			heap_profiler: &mut self.heap_profiler,
+			stats: &mut self.stats,
+			debugger: &mut self.debugger,
+			hooks: &mut self.hooks,
+			event_log: &mut self.event_log,
+			environment: &mut self.environment,
+			wasi: &mut self.wasi,
+			reservation: &mut self.reservation,
+			#[cfg(feature = "jit")]
+			jit: self.jit.as_deref(),
+		}
+	}
+
+	/// Runs the function of the start section of the module, if it has one, after the memory and tables were
+	/// initialized.
+	pub(crate) fn run_start_section(&mut self) -> Result<(), Error> {
+		match self.module.start {
+			Some(function_index) => self.invoke(function_index, Vec::new()).map(|_| ()),
+			None => Ok(()),
 		}
 	}
 
+	/// Executes the exported function `_start`, failing with [`Error::UnknownExport`] if the module has none.
+	#[tracing::instrument(skip_all)]
 	pub fn start(&mut self) -> Result<(), Error> {
+		self.check_poisoned()?;
+		self.abandon_suspended();
 		self.as_ref().exec_start()
+			.map(|_| ())
+			.map_err(|error| self.trapped(error))
+	}
+
+	/// Executes at most `steps` instructions of `_start` and returns whether it completed, trapped or yielded.
+	///
+	/// After [`StepOutcome::Yielded`], the next call resumes where the execution stopped, so that an embedder can
+	/// interleave many instances on a single thread. Otherwise, the next call starts `_start` again, unless the
+	/// instance is [poisoned](Instance::is_poisoned) by a trap.
+	/// Host functions are not interrupted and count as a single step.
+	pub fn run_steps(&mut self, steps: u64) -> StepOutcome {
+		if let Err(error) = self.check_poisoned() {
+			return StepOutcome::TrapOccurred(error);
+		}
+		self.steps_left = Some(steps);
+		let result = if self.suspended.is_empty() {
+			self.call_stack.clear();
+			self.as_ref().exec_start()
+		} else {
+			let function_index = self.call_stack[0].function_index;
+			self.as_ref().exec_function(function_index)
+		};
+		self.steps_left = None;
+		match result {
+			Ok(ControlFlow::Yield) => StepOutcome::Yielded,
+			Ok(_) => StepOutcome::Completed,
+			Err(error) => {
+				let error = self.trapped(error);
+				self.suspended.clear();
+				StepOutcome::TrapOccurred(error)
+			},
+		}
+	}
+
+	/// Discards the state of an execution which yielded in [`Instance::run_steps`] or trapped, so that the next one
+	/// starts on empty stacks.
+	fn abandon_suspended(&mut self) {
+		self.suspended.clear();
+		self.call_stack.clear();
+		if !self.operand_stack.is_empty() {
+			tracing::debug!(len = self.operand_stack.len(), "Discarding the operand stack of an aborted execution");
+			self.operand_stack.clear();
+		}
+	}
+
+	/// Whether an execution trapped, after which the instance cannot be called again, see
+	/// [`Config::allow_resume_after_trap`].
+	pub fn is_poisoned(&self) -> bool {
+		self.poisoned.is_some()
+	}
+
+	/// Fails with [`Error::InstancePoisoned`] if an execution trapped before.
+	fn check_poisoned(&self) -> Result<(), Error> {
+		match &self.poisoned {
+			Some(trap) => Err(Error::InstancePoisoned(trap.clone())),
+			None => Ok(()),
+		}
+	}
+
+	/// Notifies the hooks about a trap and writes a coredump if [`Config::coredump_on_trap`] is set.
+	/// The call stack is still the one at the time of the trap.
+	///
+	/// The floor of the operand stack is reset, as the trapped functions did not restore the floors of their callers.
+	/// Unless [`Config::allow_resume_after_trap`] is set, the instance is poisoned.
+	/// A trap after the guest printed a Rust panic to stderr is returned as [`Error::GuestPanic`].
+	fn trapped(&mut self, error: Error) -> Error {
+		self.operand_stack.set_floor(0);
+		if !self.config.allow_resume_after_trap {
+			self.poisoned = Some(error.to_string());
+		}
+		if let Some(hooks) = self.hooks.as_mut() {
+			hooks.on_trap(&error);
+		}
+		if let Some(path) = &self.config.coredump_on_trap {
+			let coredump = coredump::encode(&self.call_stack, &self.operand_stack, self.memory.as_ref());
+			if let Err(io_error) = fs::write(path, coredump) {
+				tracing::error!(path = %path.display(), error = %io_error, "Failed to write coredump");
+			}
+		}
+		match guest::panic_message(&self.wasi.take_stderr_tail()) {
+			Some(message) if error.trap_kind().is_some() => Error::GuestPanic { error: Box::new(error), message },
+			_ => error,
+		}
+	}
+
+	/// Calls the function with `function_index` with `args` and returns its results.
+	///
+	/// An instance can be invoked any number of times, e.g. as a long-lived plugin: every call starts on empty
+	/// operand and call stacks, while the memory, tables and globals keep the changes of previous calls. After a
+	/// trap, calls fail with [`Error::InstancePoisoned`], unless [`Config::allow_resume_after_trap`] is set.
+	#[tracing::instrument(skip(self, args))]
+	pub fn invoke(&mut self, function_index: usize, args: Vec<Value>) -> Result<Vec<Value>, Error> {
+		self.check_poisoned()?;
+		self.abandon_suspended();
+		let num_results = self.functions.get(function_index)
+			.ok_or(Error::FunctionIndexOutOfBounds { index: function_index, len: self.functions.len() })?
+			.signature().results.len();
+		for arg in args {
+			self.operand_stack.push(arg);
+		}
+		self.as_ref().exec_function(function_index)
+			.map_err(|error| self.trapped(error))?;
+		if self.operand_stack.len() != num_results {
+			let got = self.operand_stack.len();
+			self.operand_stack.clear();
+			return Err(Error::UnbalancedOperandStack { expected: num_results, got });
+		}
+		self.operand_stack.pop_n(num_results)
+	}
+
+	/// A handle to the exported function `name`, which can be called repeatedly, also from inside host functions.
+	///
+	/// Looks `name` up in the [`ExportMap`](crate::parse::ExportMap) of the module built while parsing.
+	pub fn get_func(&self, name: &str) -> Option<Func> {
+		let index = self.module.export_map.function(name)?;
+		match self.functions.get(index)?.deref() {
+			Callable::WasmFunction(function) => Some(Func::new(index, Shared::clone(&function.signature))),
+			_ => None,
+		}
+	}
+
+	/// Same as [`Instance::get_func`].
+	pub fn func(&self, name: &str) -> Option<Func> {
+		self.get_func(name)
+	}
+
+	/// Name, index and signature of all exported WebAssembly functions.
+	pub(crate) fn exported_functions(&self) -> Vec<(String, usize, Shared<FunctionSignature>)> {
+		self.functions.iter()
+			.enumerate()
+			.filter_map(|(index, function)| match function.deref() {
+				Callable::WasmFunction(function) => function.export_name.clone()
+					.map(|name| (name, index, Shared::clone(&function.signature))),
+				_ => None,
+			})
+			.collect()
+	}
+
+	/// The module this is an instance of.
+	pub fn module(&self) -> &Shared<Module> {
+		&self.module
 	}
 
 	pub fn operand_stack(&self) -> &OperandStack {
@@ -76,267 +603,2562 @@ impl Instance {
 	pub fn memory(&self) -> &Option<Memory> {
 		&self.memory
 	}
+
+	/// Mutable access to the memory, e.g. to write configuration for the guest and [protect](Memory::protect) it.
+	pub fn memory_mut(&mut self) -> Option<&mut Memory> {
+		self.memory.as_mut()
+	}
+
+	pub fn tables(&self) -> &[Table] {
+		&self.tables
+	}
+
+	/// A handle to read and, if it is mutable, change the exported global `name` between calls.
+	pub fn global(&self, name: &str) -> Option<GlobalHandle> {
+		let export = self.module.export(name).filter(|export| export.kind == ExportKind::Global)?;
+		let global = self.module.globals.get(export.index)?;
+		Some(GlobalHandle::new(export.index, global.ty, global.mutable))
+	}
+
+	/// Position of the instruction which started executing last. If the execution failed, this is the
+	/// instruction which trapped.
+	pub fn last_position(&self) -> Option<Breakpoint> {
+		self.position
+	}
+
+	/// Host objects passed to the guest as `externref`.
+	/// Insert objects here to obtain handles which can be passed to the guest.
+	pub fn extern_refs(&mut self) -> &mut ExternRefStore {
+		&mut self.extern_refs
+	}
+
+	/// Attaches a debugger which is consulted before every executed instruction.
+	pub fn set_debugger(&mut self, debugger: Debugger) {
+		self.debugger = Some(debugger);
+	}
+
+	pub fn debugger(&mut self) -> Option<&mut Debugger> {
+		self.debugger.as_mut()
+	}
+
+	/// Registers callbacks invoked by the interpreter on function calls, traps and memory changes.
+	pub fn set_hooks(&mut self, hooks: impl Hooks + 'static) {
+		self.hooks = Some(Box::new(hooks));
+	}
+
+	/// Records the calls of host functions into `event_log` or replays the recorded ones, see [`EventLog`].
+	pub fn set_event_log(&mut self, event_log: EventLog) {
+		self.event_log = Some(event_log);
+	}
+
+	pub fn event_log(&self) -> Option<&EventLog> {
+		self.event_log.as_ref()
+	}
+
+	/// Detaches the event log, e.g. to store the recorded calls after a run.
+	pub fn take_event_log(&mut self) -> Option<EventLog> {
+		self.event_log.take()
+	}
+
+	/// Provides the host resources in `wasi` to the WASI functions, e.g. sockets.
+	pub fn set_wasi(&mut self, wasi: WasiCtx) {
+		self.wasi = wasi;
+	}
+
+	/// The collected profile if [`Config::profile`] is enabled.
+	pub fn profiler(&self) -> Option<&Profiler> {
+		self.profiler.as_ref()
+	}
+
+	/// The recorded allocations of the guest if [`Config::heap_profile`] is enabled.
+	pub fn heap_profiler(&self) -> Option<&HeapProfiler> {
+		self.heap_profiler.as_ref()
+	}
+
+	/// Statistics about the executions so far, like the maximum call depth and operand stack height.
+	pub fn stats(&self) -> Stats {
+		Stats {
+			max_operand_stack: self.operand_stack.max_len(),
+			max_memory_pages: self.memory.as_ref().map_or(0, Memory::page_size),
+			..self.stats.clone()
+		}
+	}
+
+	/// Starts collecting the [`Stats`] from scratch, e.g. before measuring a single invocation.
+	pub fn reset_stats(&mut self) {
+		self.stats = Stats::default();
+		self.operand_stack.reset_max_len();
+	}
 }
 
 #[derive(Debug)]
 pub struct InstanceRef<'a> {
-	functions: &'a Vec<Rc<Callable>>,
+	functions: &'a Vec<Shared<Callable>>,
+	types: &'a [Shared<FunctionSignature>],
+	type_ids: &'a [SignatureId],
+	signature_ids: &'a [SignatureId],
+	exports: &'a ExportMap,
 	pub memory: &'a mut Option<Memory>,
+	pub tables: &'a mut Vec<Table>,
+	elements: &'a mut Vec<Vec<Value>>,
+	data: &'a mut Vec<SharedBytes>,
+	globals: &'a mut Vec<Value>,
+	tags: &'a [Shared<Tag>],
+	/// Host objects passed to the guest as `externref`.
+	pub extern_refs: &'a mut ExternRefStore,
 	pub operand_stack: &'a mut OperandStack,
-	call_stack: &'a mut Vec<Rc<Callable>>,
+	call_stack: &'a mut Vec<Frame>,
+	suspended: &'a mut Vec<Suspended>,
+	steps_left: &'a mut Option<u64>,
+	position: &'a mut Option<Breakpoint>,
+	pub(crate) config: &'a Config,
+	profiler: &'a mut Option<Profiler>,
+	heap_profiler: &'a mut Option<HeapProfiler>,
+	stats: &'a mut Stats,
+	debugger: &'a mut Option<Debugger>,
+	hooks: &'a mut Option<Box<dyn Hooks>>,
+	event_log: &'a mut Option<EventLog>,
+	/// Random numbers and clocks for host functions.
+	pub environment: &'a mut Environment,
+	/// Host resources like sockets for the WASI functions.
+	pub wasi: &'a mut WasiCtx,
+	reservation: &'a mut Option<Reservation>,
+	#[cfg(feature = "jit")]
+	jit: Option<&'a Jit>,
 }
 
 impl<'a> InstanceRef<'a> {
-	pub fn exec_start(&mut self) -> ExecutionResult {
-		// Search start function
-		let (index, _function) = self.functions.iter()
-			.enumerate()
-			.find(|(_, func)| {
-				match func.deref().deref() {
-					Callable::WasmFunction(func) => {
-						func.export_name.as_ref()
-							.map(|export_name| export_name == "_start")
-							.unwrap_or(false)
-					},
-					_ => false
-				}
-			}).expect("No start function");
-		self.exec_function(index)
+	/// Calls the function with `function_index` with `args` on top of the current execution and returns its results.
+	///
+	/// This allows host functions to call back into the guest. The nested call is not limited by the steps of
+	/// [`Instance::run_steps`], as a host function counts as a single step and cannot be suspended.
+	pub fn call(&mut self, function_index: usize, args: Vec<Value>) -> Result<Vec<Value>, Error> {
+		let num_results = self.functions.get(function_index)
+			.ok_or(Error::FunctionIndexOutOfBounds { index: function_index, len: self.functions.len() })?
+			.signature().results.len();
+		for arg in args {
+			self.operand_stack.push(arg);
+		}
+		let steps_left = self.steps_left.take();
+		let result = self.exec_function(function_index);
+		*self.steps_left = steps_left;
+		result?;
+		self.operand_stack.pop_n(num_results)
 	}
 
-	#[tracing::instrument(skip(self))]
-	fn exec_function(&mut self, function_index: usize) -> ExecutionResult {
+	/// A handle to the function referenced by the `funcref` `reference`, e.g. a callback passed to a host function.
+	pub fn func_ref(&self, reference: &Value) -> Result<Func, Error> {
+		let function_index = match reference {
+			Value::FuncRef(Some(function_index)) => *function_index,
+			Value::FuncRef(None) => return Err(Error::Trap(TrapKind::NullReference)),
+			_ => return Err(Error::Trap(TrapKind::Other("not a function reference"))),
+		};
 		let function = self.functions.get(function_index)
-			.ok_or(Error::FunctionIndexOutOfBounds {
-				index: function_index,
-				len: self.functions.len()
-			})?;
-
-		self.call_stack.push(Rc::clone(&function));
-		tracing::trace!(callstack = ?self.call_stack.iter().map(ToString::to_string).collect::<Vec<_>>());
-
-		// Execute function body
-		match function.deref().deref() {
-			Callable::RustFunction { function, .. } => function(self)?,
-			Callable::RustClosure { closure, .. } => closure(self)?,
-			Callable::WasmFunction(function) => {
-				self.execute_instructions(&function.body)?;
-			},
+			.ok_or(Error::FunctionIndexOutOfBounds { index: function_index, len: self.functions.len() })?;
+		Ok(Func::new(function_index, Shared::clone(function.signature())))
+	}
+
+	/// A handle to the function at `index` of the table with index `table`, e.g. a function pointer passed to a
+	/// host function by a guest compiled from C or Rust, which index into table 0.
+	pub fn table_func(&self, table: usize, index: usize) -> Result<Func, Error> {
+		let table_ref = self.tables.get(table)
+			.ok_or(Error::TableIndexOutOfBounds { index: table, len: self.tables.len() })?;
+		let reference = table_ref.get(index)
+			.ok_or(Error::InvalidTableAccess { table, range: index..index + 1, size: table_ref.size() })?;
+		self.func_ref(reference)
+	}
+
+	/// A handle to the exported function `name` of the executing instance, e.g. to call back into the guest.
+	pub fn func(&self, name: &str) -> Option<Func> {
+		let index = self.exports.function(name)?;
+		match self.functions.get(index)?.deref() {
+			Callable::WasmFunction(function) => Some(Func::new(index, Shared::clone(&function.signature))),
+			_ => None,
 		}
+	}
 
-		self.call_stack.pop();
-		Ok(())
+	fn exec_start(&mut self) -> Result<ControlFlow, Error> {
+		let index = self.exports.function("_start").ok_or_else(|| Error::UnknownExport("_start".to_owned()))?;
+		self.exec_function(index)
 	}
 
-	fn execute_instructions<'iter>(&mut self, instructions: impl IntoIterator<Item=&'iter Instruction>) -> ExecutionResult {
-		for instruction in instructions {
-			let span = tracing::trace_span!("execute_instruction", ?instruction);
-			let _span_enter = span.enter();
-			match instruction {
-				Instruction::Unreachable => return Err(Error::Trap("Instruction::Unreachable")),
-				Instruction::Nop => (),
-				Instruction::Block { block_type, instructions } => {
-					self.execute_instructions(instructions)?;
-				},
-				Instruction::Loop { block_type, instructions } => {
-					self.execute_instructions(instructions)?;
+	#[cfg_attr(feature = "trace-hot-paths", tracing::instrument(skip(self)))]
+	fn exec_function(&mut self, called_index: usize) -> Result<ControlFlow, Error> {
+		// A resumed function may have been replaced by tail calls
+		let mut function_index = match self.suspended.last() {
+			Some(&Suspended::Callee { function_index }) => {
+				self.suspended.pop();
+				function_index
+			},
+			_ => called_index,
+		};
+		// When resuming, the frame is still on the call stack
+		let mut resumed_height = self.resumed_label();
+
+		loop {
+			let function = self.functions.get(function_index)
+				.ok_or(Error::FunctionIndexOutOfBounds {
+					index: function_index,
+					len: self.functions.len()
+				})?;
+
+			let resumed_height = resumed_height.take();
+			if resumed_height.is_none() {
+				if stack_exhausted() {
+					return Err(Error::Trap(TrapKind::StackExhausted));
+				}
+				let locals = match function.deref() {
+					Callable::WasmFunction(function) => self.init_locals(function)?,
+					_ => Vec::new(),
+				};
+				self.call_stack.push(Frame::new(function_index, Shared::clone(function), locals));
+				self.stats.max_call_depth = self.stats.max_call_depth.max(self.call_stack.len());
+				hot_trace!(callstack = ?self.call_stack.iter().map(ToString::to_string).collect::<Vec<_>>());
+				if let Some(profiler) = self.profiler.as_mut() {
+					profiler.enter(function.to_string());
+				}
+				if let Some(heap_profiler) = self.heap_profiler.as_mut() {
+					heap_profiler.enter(function_index, self.call_stack);
+				}
+				if let Some(hooks) = self.hooks.as_mut() {
+					hooks.on_call(function_index, function);
+				}
+			}
+
+			// Execute function body
+			let mut tail_call = None;
+			match function.deref() {
+				Callable::RustFunction { .. } | Callable::RustClosure { .. } => self.call_host(function)?,
+				#[cfg(feature = "jit")]
+				Callable::WasmFunction(function) if self.compiled(function_index, resumed_height).is_some() => {
+					let compiled = self.compiled(function_index, resumed_height).expect("Checked by the match guard");
+					let frame = self.call_stack.last().expect("The frame of the function was pushed");
+					if let Some(result) = compiled.call(&frame.locals[..function.signature.params.len()]) {
+						self.operand_stack.push(result);
+					}
 				},
-				Instruction::If { block_type, if_instructions, else_instructions } => {
-					let condition = self.operand_stack.pop::<i32>()?;
-					if condition != 0 {
-						self.execute_instructions(if_instructions)?;
-					} else {
-						self.execute_instructions(else_instructions)?;
+				Callable::WasmFunction(function) => {
+					let height = resumed_height.unwrap_or(self.operand_stack.len());
+					self.operand_stack.reserve(function.metadata.max_stack_height);
+					// The function must not pop the values of its callers
+					let caller_floor = self.operand_stack.set_floor(height);
+					let control_flow = self.execute_instructions(&function.body, 0)
+						.map_err(|error| match error {
+							Error::PopOnEmptyOperandStack => Error::OperandStackUnderflow {
+								function: self.functions[function_index].to_string(),
+							},
+							error => error,
+						})?;
+					self.operand_stack.set_floor(caller_floor);
+					match control_flow {
+						// A branch to the outermost label returns from the function
+						ControlFlow::Continue | ControlFlow::Branch(0) | ControlFlow::Return => {
+							self.operand_stack.unwind(height, function.signature.results.len())?
+						},
+						ControlFlow::Branch(depth) | ControlFlow::Rethrow(depth) => {
+							return Err(Error::LabelIndexOutOfBounds(depth))
+						},
+						// Delegating to the function body throws to the caller
+						ControlFlow::Delegate(_, exception) => return Err(Error::Exception(exception)),
+						ControlFlow::TailCall(callee) => {
+							// The arguments of the callee replace the values of the returning function
+							let num_params = self.functions.get(callee)
+								.ok_or(Error::FunctionIndexOutOfBounds { index: callee, len: self.functions.len() })?
+								.signature().params.len();
+							self.operand_stack.unwind(height, num_params)?;
+							tail_call = Some(callee);
+						},
+						ControlFlow::Yield => {
+							self.suspended.push(Suspended::Label { height });
+							if function_index != called_index {
+								self.suspended.push(Suspended::Callee { function_index });
+							}
+							return Ok(ControlFlow::Yield);
+						},
 					}
 				},
-				Instruction::Return => break,
-				Instruction::I32Const(val) => self.operand_stack.push(Value::I32(*val)),
-				Instruction::I32Store(mem_arg) => {
-					let val = self.operand_stack.pop::<i32>()?;
-					// Convert value to little endian, because memory is in little endian
-					let val = val.to_le_bytes();
+			}
+
+			if let Some(profiler) = self.profiler.as_mut() {
+				profiler.exit();
+			}
+			if let Some(heap_profiler) = self.heap_profiler.as_mut() {
+				let results = self.operand_stack.top(function.signature().results.len());
+				heap_profiler.exit(function_index, self.call_stack.len(), results);
+			}
+			if let Some(hooks) = self.hooks.as_mut() {
+				hooks.on_return(function_index, function);
+			}
+			self.call_stack.pop();
+			match tail_call {
+				// The callee runs in this loop instead of a nested call, so tail calls do not grow the host stack
+				Some(callee) => function_index = callee,
+				None => return Ok(ControlFlow::Continue),
+			}
+		}
+	}
 
-					let addr = self.operand_stack.pop::<i32>()?;
-					let addr = addr as usize + mem_arg.offset;
-					let addr = addr..addr+4;
+	/// The native code of the function with `function_index`, if it was compiled and nothing observes the execution of
+	/// single instructions. A resumed function keeps being interpreted, as native code cannot be suspended.
+	#[cfg(feature = "jit")]
+	fn compiled(&self, function_index: usize, resumed_height: Option<usize>) -> Option<CompiledFunction> {
+		let observed = resumed_height.is_some() || self.steps_left.is_some() || self.debugger.is_some()
+			|| self.hooks.is_some() || self.profiler.is_some() || self.config.trace;
+		match observed {
+			true => None,
+			false => self.jit?.function(function_index),
+		}
+	}
 
-					tracing::trace!("mem[{:?}] <- {:?}", addr, val);
-					let mem = self.memory.as_mut()
-						.ok_or(Error::NoMemory)?;
-					let mem_data_len = mem.data.len(); // Has to fetched in advance for borrow checker
-					let mem_slice = mem.data.get_mut(addr.clone())
-						.ok_or(Error::InvalidMemoryArea { addr, size: mem_data_len })?;
-					mem_slice.copy_from_slice(&val);
+	/// Calls the host `function`, recording the call or replaying a recorded one if an [`EventLog`] is attached.
+	fn call_host(&mut self, function: &Callable) -> ExecutionResult {
+		if self.event_log.as_ref().is_some_and(EventLog::is_replaying) {
+			return self.replay_host_call(function);
+		}
+		let recorded = self.event_log.as_mut().is_some_and(EventLog::enter);
+		let signature = function.signature();
+		let args = self.operand_stack.top(signature.params.len()).to_vec();
+		let memory_before = match recorded {
+			true => self.memory.as_ref().map(|memory| memory.data.clone()),
+			false => None,
+		};
+
+		let height = self.operand_stack.len();
+		let result = match function {
+			Callable::RustFunction { function, .. } => function(self),
+			Callable::RustClosure { closure, .. } => closure(self),
+			Callable::WasmFunction(_) => unreachable!("Only host functions are called"),
+		};
+		if let Some(event_log) = self.event_log.as_mut() {
+			event_log.exit();
+		}
+		let results = result?;
+		self.check_host_stack(function, height)?;
+		if results.len() != signature.results.len() {
+			return Err(Error::HostResultCountMismatch {
+				function: function.to_string(),
+				expected: signature.results.len(),
+				got: results.len(),
+			});
+		}
+
+		if recorded {
+			let memory = self.memory.as_ref();
+			let call = HostCall {
+				function: function.to_string(),
+				args,
+				results: results.clone(),
+				writes: match (memory_before, memory) {
+					(Some(before), Some(after)) => event_log::changed_regions(&before, after.data()),
+					_ => Vec::new(),
 				},
-				Instruction::Call { function_index } => self.exec_function(*function_index)?,
-				Instruction::Drop => { self.operand_stack.pop::<Value>()?; },
-				Instruction::I32Eqz => {
-					let a = self.operand_stack.pop::<i32>()?;
-					let result = if a == 0 { 1 } else { 0 };
-					self.operand_stack.push(Value::I32(result));
+				memory_pages: memory.map_or(0, Memory::page_size),
+			};
+			self.event_log.as_mut().expect("The call was recorded").push(call);
+		}
+		for result in results {
+			self.operand_stack.push(result);
+		}
+		Ok(())
+	}
+
+	/// Applies the effects of the next recorded call instead of calling the host `function`.
+	fn replay_host_call(&mut self, function: &Callable) -> ExecutionResult {
+		let name = function.to_string();
+		let diverged = |reason| Error::ReplayDiverged { function: name.clone(), reason };
+		let args = self.operand_stack.pop_n(function.signature().params.len())?;
+		let call = self.event_log.as_mut()
+			.and_then(EventLog::next_replayed)
+			.ok_or_else(|| diverged("all recorded calls were replayed"))?;
+		if call.function != name {
+			return Err(diverged("another function was called"));
+		}
+		if call.args != args {
+			return Err(diverged("the arguments differ"));
+		}
+
+		if let Some(memory) = self.memory.as_mut() {
+			if memory.page_size() < call.memory_pages {
+				if call.memory_pages > memory.page_limit.end {
+					return Err(diverged("the memory cannot grow to the recorded size"));
 				}
-				Instruction::I32Eq => {
-					let lhs = self.operand_stack.pop::<i32>()?;
-					let rhs = self.operand_stack.pop::<i32>()?;
-					let result = if lhs == rhs { 1 } else  { 0 };
-					self.operand_stack.push(Value::I32(result));
-				},
-				Instruction::I32Add => {
-					let lhs = self.operand_stack.pop::<i32>()?;
-					let rhs = self.operand_stack.pop::<i32>()?;
-					let result = i32::wrapping_add(lhs, rhs);
-					self.operand_stack.push(Value::I32(result));
-				},
-				Instruction::I32Add => {
-					let lhs = self.operand_stack.pop::<i32>()?;
-					let rhs = self.operand_stack.pop::<i32>()?;
-					let result = i32::wrapping_sub(lhs, rhs);
-					self.operand_stack.push(Value::I32(result));
-				},
-				Instruction::I32Mul => {
-					let lhs = self.operand_stack.pop::<i32>()?;
-					let rhs = self.operand_stack.pop::<i32>()?;
-					let result = i32::wrapping_mul(lhs, rhs);
-					self.operand_stack.push(Value::I32(result));
-				},
-				Instruction::I32DivU => {
-					let lhs = self.operand_stack.pop::<u32>()?;
-					let rhs = self.operand_stack.pop::<u32>()?;
-					let result = u32::wrapping_div(lhs, rhs);
-					self.operand_stack.push(Value::I32(result as i32));
-				},
-				Instruction::I32DivS => {
-					let lhs = self.operand_stack.pop::<i32>()?;
-					let rhs = self.operand_stack.pop::<i32>()?;
-					let result = i32::wrapping_div(lhs, rhs);
-					self.operand_stack.push(Value::I32(result));
-				},
-				Instruction::I32RemU => {
-					let lhs = self.operand_stack.pop::<u32>()?;
-					let rhs = self.operand_stack.pop::<u32>()?;
-					let result = u32::wrapping_rem(lhs, rhs);
-					self.operand_stack.push(Value::I32(result as i32));
-				},
-				Instruction::I32RemS => {
-					let lhs = self.operand_stack.pop::<i32>()?;
-					let rhs = self.operand_stack.pop::<i32>()?;
-					let result = i32::wrapping_rem(lhs, rhs);
-					self.operand_stack.push(Value::I32(result));
-				},
-				Instruction::I32And => {
-					let lhs = self.operand_stack.pop::<i32>()?;
-					let rhs = self.operand_stack.pop::<i32>()?;
-					let result = i32::bitand(lhs, rhs);
-					self.operand_stack.push(Value::I32(result));
-				},
-				Instruction::I32Or => {
-					let lhs = self.operand_stack.pop::<i32>()?;
-					let rhs = self.operand_stack.pop::<i32>()?;
-					let result = i32::bitor(lhs, rhs);
-					self.operand_stack.push(Value::I32(result));
-				},
-				Instruction::I32Xor => {
-					let lhs = self.operand_stack.pop::<i32>()?;
-					let rhs = self.operand_stack.pop::<i32>()?;
-					let result = i32::bitxor(lhs, rhs);
-					self.operand_stack.push(Value::I32(result));
-				},
-				Instruction::I32Shl => {
-					let lhs = self.operand_stack.pop::<i32>()?;
-					let rhs = self.operand_stack.pop::<i32>()?;
-					let result = i32::shl(lhs, rhs);
-					self.operand_stack.push(Value::I32(result));
-				},
-				Instruction::I32ShrU => {
-					let lhs = self.operand_stack.pop::<i32>()?;
+				memory.grow(call.memory_pages)
+					.map_err(|_| diverged("the memory cannot grow to the recorded size"))?;
+			}
+			for (addr, bytes) in &call.writes {
+				memory.data.get_mut(*addr..*addr + bytes.len())
+					.ok_or_else(|| diverged("a recorded write is outside of the memory"))?
+					.copy_from_slice(bytes);
+			}
+		}
+		for result in &call.results {
+			self.operand_stack.push(result.clone());
+		}
+		Ok(())
+	}
+
+	/// Checks in debug builds that the host `function` called at operand stack `height` popped exactly its
+	/// parameters.
+	fn check_host_stack(&self, function: &Callable, height: usize) -> ExecutionResult {
+		if !cfg!(debug_assertions) {
+			return Ok(());
+		}
+		let expected = height.saturating_sub(function.signature().params.len());
+		if self.operand_stack.len() != expected {
+			return Err(Error::HostFunctionStackMismatch {
+				function: function.to_string(),
+				expected,
+				got: self.operand_stack.len(),
+			});
+		}
+		Ok(())
+	}
+
+	/// Pops the height of a block or function body which is resumed.
+	fn resumed_label(&mut self) -> Option<usize> {
+		match self.suspended.last() {
+			Some(&Suspended::Label { height }) => {
+				self.suspended.pop();
+				Some(height)
+			},
+			_ => None,
+		}
+	}
+
+	/// Pops the index into the table `table_index` and returns the index of the referenced function, which has to have
+	/// the signature with `type_index`.
+	fn indirect_callee(&mut self, table_index: usize, type_index: usize) -> Result<usize, Error> {
+		let index = self.operand_stack.pop::<u32>()? as usize;
+		let callee = self.table_func(table_index, index)?;
+		let expected = self.type_ids.get(type_index)
+			.ok_or(Error::TypeIndexOutOfBounds { index: type_index, len: self.type_ids.len() })?;
+		if self.signature_ids[callee.index()] != *expected {
+			return Err(Error::Trap(TrapKind::Other("indirect call type mismatch")));
+		}
+		Ok(callee.index())
+	}
+
+	/// Handles the control flow of the block or call at `index`, returning it if the enclosing sequence has to be left.
+	fn leave_nested(&mut self, index: usize, else_branch: bool, control_flow: ControlFlow) -> Option<ControlFlow> {
+		match control_flow {
+			ControlFlow::Continue => None,
+			ControlFlow::Yield => {
+				self.suspended.push(Suspended::Nested { index, else_branch });
+				Some(ControlFlow::Yield)
+			},
+			branch => Some(branch),
+		}
+	}
+
+	/// Pops the parameters of `function` off the operand stack and appends the zero-initialized declared locals.
+	fn init_locals(&mut self, function: &WasmFunction) -> Result<Vec<Value>, Error> {
+		let mut locals = Vec::with_capacity(function.metadata.num_locals);
+		for _ in &function.signature.params {
+			locals.push(self.operand_stack.pop::<Value>()?);
+		}
+		// Parameters were popped in reverse order
+		locals.reverse();
+		locals.extend(function.locals.iter().map(Value::default_for));
+		Ok(locals)
+	}
+
+	/// Locals of the currently executing function.
+	fn locals(&mut self) -> &mut Vec<Value> {
+		&mut self.call_stack.last_mut()
+			.expect("Instructions are only executed inside a function")
+			.locals
+	}
+
+	fn local(&mut self, index: usize) -> Result<&mut Value, Error> {
+		let locals = self.locals();
+		let len = locals.len();
+		locals.get_mut(index).ok_or(Error::LocalIndexOutOfBounds { index, len })
+	}
+
+	fn global(&mut self, index: usize) -> Result<&mut Value, Error> {
+		let len = self.globals.len();
+		self.globals.get_mut(index).ok_or(Error::GlobalIndexOutOfBounds { index, len })
+	}
+
+	/// Logs `instruction` with the current function, the top of the operand stack and the locals changed since
+	/// `locals_before`.
+	fn trace_instruction(&mut self, offset: usize, instruction: &Instruction, locals_before: &[Value]) {
+		let function = self.call_stack.last().map(ToString::to_string).unwrap_or_default();
+		let stack = self.operand_stack.top(self.config.trace_stack_depth);
+		let locals_diff = self.call_stack.last().map(|frame| {
+			frame.locals.iter().enumerate()
+				.filter(|(index, value)| locals_before.get(*index) != Some(value))
+				.map(|(index, value)| (index, value.clone()))
+				.collect::<Vec<_>>()
+		}).unwrap_or_default();
+		tracing::info!(
+			target: "rust_wasm_runtime::trace",
+			function = %function,
+			offset,
+			instruction = ?instruction,
+			stack = ?stack,
+			locals_diff = ?locals_diff,
+		);
+	}
+
+	fn table(&mut self, table: usize) -> Result<&mut Table, Error> {
+		let len = self.tables.len();
+		self.tables.get_mut(table)
+			.ok_or(Error::TableIndexOutOfBounds { index: table, len })
+	}
+
+	/// The element at `index` of the table with index `table`.
+	fn table_element(&mut self, table: usize, index: usize) -> Result<&mut Value, Error> {
+		Ok(&mut self.table_elements(table, index..index + 1)?[0])
+	}
+
+	/// The elements in `range` of the table with index `table`.
+	fn table_elements(&mut self, table: usize, range: Range<usize>) -> Result<&mut [Value], Error> {
+		let table_ref = self.table(table)?;
+		let size = table_ref.size();
+		table_ref.elements.get_mut(range.clone())
+			.ok_or(Error::InvalidTableAccess { table, range, size })
+	}
+
+	/// The elements in `range` of the element segment with index `segment`.
+	fn element_segment_elements(&self, segment: usize, range: Range<usize>) -> Result<&[Value], Error> {
+		let elements = self.elements.get(segment)
+			.ok_or(Error::ElementSegmentIndexOutOfBounds { index: segment, len: self.elements.len() })?;
+		elements.get(range.clone())
+			.ok_or(Error::InvalidElementSegmentAccess { segment, range, size: elements.len() })
+	}
+
+	/// The bytes in `range` of the data segment with index `segment`.
+	fn data_segment_bytes(&self, segment: usize, range: Range<usize>) -> Result<&[u8], Error> {
+		let data = self.data.get(segment)
+			.ok_or(Error::DataSegmentIndexOutOfBounds { index: segment, len: self.data.len() })?;
+		data.get(range.clone())
+			.ok_or(Error::InvalidDataSegmentAccess { segment, range, size: data.len() })
+	}
+
+	/// The bytes in `addr` of the linear memory, which the guest loads.
+	fn memory_bytes(&self, addr: Range<usize>) -> Result<&[u8], Error> {
+		let mem = self.memory.as_ref()
+			.ok_or(Error::NoMemory)?;
+		if let Some(protection) = mem.violated_protection(&addr, false) {
+			return Err(Error::ProtectedMemoryAccess { addr, protection });
+		}
+		mem.data.get(addr.clone())
+			.ok_or(Error::InvalidMemoryArea { addr, size: mem.data.len() })
+	}
+
+	/// The bytes in `addr` of the linear memory, which the guest stores.
+	fn memory_slice(&mut self, addr: Range<usize>) -> Result<&mut [u8], Error> {
+		let mem = self.memory.as_mut()
+			.ok_or(Error::NoMemory)?;
+		if let Some(protection) = mem.violated_protection(&addr, true) {
+			return Err(Error::ProtectedMemoryAccess { addr, protection });
+		}
+		let mem_data_len = mem.data.len(); // Has to fetched in advance for borrow checker
+		mem.data.get_mut(addr.clone())
+			.ok_or(Error::InvalidMemoryArea { addr, size: mem_data_len })
+	}
+
+	/// Grows the memory by `delta` pages like `memory.grow` and returns the previous number of pages, or [`None`] if
+	/// it would exceed its maximum or the [`StoreLimits`](crate::exec::StoreLimits).
+	pub(crate) fn grow_memory(&mut self, delta: usize) -> Result<Option<usize>, Error> {
+		self.stats.memory_grows += 1;
+		let mem = self.memory.as_mut()
+			.ok_or(Error::NoMemory)?;
+		let old_pages = mem.page_size();
+		let new_pages = match old_pages.checked_add(delta) {
+			Some(new_pages) if new_pages <= mem.page_limit.end => new_pages,
+			_ => return Ok(None),
+		};
+		let bytes = delta.saturating_mul(MEMORY_PAGE_SIZE);
+		let within_limits = |reservation: &mut Option<Reservation>| reservation.as_mut()
+			.is_none_or(|reservation| reservation.grow_memory(bytes));
+		if !within_limits(self.reservation) {
+			return Ok(None);
+		}
+		if let Err(error) = mem.grow(new_pages) {
+			// The host is out of memory, which the guest sees like exceeding the maximum
+			tracing::debug!("memory.grow failed: {}", error);
+			if let Some(reservation) = self.reservation.as_mut() {
+				reservation.shrink_memory(bytes);
+			}
+			return Ok(None);
+		}
+		if let Some(hooks) = self.hooks.as_mut() {
+			hooks.on_memory_grow(old_pages, new_pages);
+		}
+		Ok(Some(old_pages))
+	}
+
+	/// Pops the base address of an access of `bytes` bytes and adds the offset of `mem_arg`.
+	fn effective_address(&mut self, mem_arg: &MemArg, bytes: usize) -> Result<Range<usize>, Error> {
+		let base = self.operand_stack.pop::<u32>()?;
+		mem_arg.effective_address(base, bytes)
+			.ok_or(Error::Trap(TrapKind::MemoryOutOfBounds))
+	}
+
+	/// A [`MemoryView`] of the memory, or [`None`] if there is no memory or its protections have to be checked.
+	fn memory_view(&mut self) -> Option<MemoryView> {
+		let mem = self.memory.as_mut()?;
+		if !mem.protections().is_empty() {
+			return None;
+		}
+		// Mutable access copies bytes shared with a clone, so that stores through the view do not change the clone
+		let data: &mut [u8] = &mut mem.data;
+		Some(MemoryView { ptr: data.as_mut_ptr(), len: data.len() })
+	}
+
+	/// Pops the base address of a load of `N` bytes and loads them through `view`, which is created if missing.
+	/// Accesses which the view does not allow take the checked path, which reports why they fail.
+	fn load<const N: usize>(&mut self, view: &mut Option<MemoryView>, mem_arg: &MemArg) -> Result<[u8; N], Error> {
+		let base = self.operand_stack.pop::<u32>()?;
+		if view.is_none() {
+			*view = self.memory_view();
+		}
+		if let Some(bytes) = view.and_then(|view| view.load(mem_arg, base)) {
+			return Ok(bytes);
+		}
+		let addr = mem_arg.effective_address(base, N)
+			.ok_or(Error::Trap(TrapKind::MemoryOutOfBounds))?;
+		Ok(self.memory_bytes(addr)?.try_into().expect("The range has a length of N"))
+	}
+
+	/// Pops the base address of a store of `bytes` like [`load`](Self::load) and returns the address of the bytes.
+	fn store<const N: usize>(
+		&mut self, view: &mut Option<MemoryView>, mem_arg: &MemArg, bytes: [u8; N]
+	) -> Result<usize, Error> {
+		let base = self.operand_stack.pop::<u32>()?;
+		if view.is_none() {
+			*view = self.memory_view();
+		}
+		if let Some(addr) = view.and_then(|view| view.store(mem_arg, base, bytes)) {
+			return Ok(addr);
+		}
+		let addr = mem_arg.effective_address(base, N)
+			.ok_or(Error::Trap(TrapKind::MemoryOutOfBounds))?;
+		self.memory_slice(addr.clone())?.copy_from_slice(&bytes);
+		Ok(addr.start)
+	}
+
+	/// Stores `bytes` like [`store`](Self::store) and notifies the hooks and the debugger about the write.
+	fn store_bytes<const N: usize>(
+		&mut self, view: &mut Option<MemoryView>, mem_arg: &MemArg, bytes: [u8; N]
+	) -> ExecutionResult {
+		let addr = self.store(view, mem_arg, bytes)?;
+		hot_trace!(address = ?(addr..addr + N), value = ?bytes, "Memory write");
+		self.memory_written(addr, &bytes);
+		Ok(())
+	}
+
+	/// Pops the address of an atomic access of `bytes` bytes and checks that it is naturally aligned.
+	fn atomic_address(&mut self, mem_arg: &MemArg, bytes: usize) -> Result<Range<usize>, Error> {
+		let addr = self.effective_address(mem_arg, bytes)?;
+		if addr.start % bytes != 0 {
+			return Err(Error::UnalignedAtomicAccess { addr: addr.start, align: bytes });
+		}
+		Ok(addr)
+	}
+
+	/// Reads the little endian integer at `addr`, zero-extended to 64 bit.
+	fn atomic_read(&mut self, addr: Range<usize>) -> Result<u64, Error> {
+		let mut bytes = [0u8; 8];
+		bytes[..addr.len()].copy_from_slice(self.memory_bytes(addr)?);
+		Ok(u64::from_le_bytes(bytes))
+	}
+
+	/// Writes the lower bytes of `value` to `addr`, wrapping it to the length of `addr`.
+	fn atomic_write(&mut self, addr: Range<usize>, value: u64) -> ExecutionResult {
+		let bytes = value.to_le_bytes();
+		let bytes = &bytes[..addr.len()];
+		hot_trace!(address = ?addr, value = ?bytes, "Memory write");
+		self.memory_slice(addr.clone())?.copy_from_slice(bytes);
+		self.memory_written(addr.start, bytes);
+		Ok(())
+	}
+
+	/// Pops the operand of an atomic instruction, zero-extended to 64 bit.
+	fn pop_atomic_operand(&mut self, width: AtomicWidth) -> Result<u64, Error> {
+		if width.is_i64() {
+			self.operand_stack.pop::<u64>()
+		} else {
+			Ok(self.operand_stack.pop::<u32>()? as u64)
+		}
+	}
+
+	/// Pushes the result of an atomic instruction as i32 or i64 depending on `width`.
+	fn push_atomic_result(&mut self, width: AtomicWidth, value: u64) {
+		if width.is_i64() {
+			self.operand_stack.push(value);
+		} else {
+			self.operand_stack.push(value as u32);
+		}
+	}
+
+	/// Implements `memory.atomic.wait32` and `memory.atomic.wait64`.
+	///
+	/// As there is only a single thread, nobody could notify the waiter. Returns "not-equal" (1) if the loaded value
+	/// differs from the expected one and otherwise sleeps for the timeout and returns "timed-out" (2).
+	fn atomic_wait(&mut self, mem_arg: &MemArg, bytes: usize) -> ExecutionResult {
+		let timeout = self.operand_stack.pop::<i64>()?;
+		let expected = if bytes == 8 {
+			self.operand_stack.pop::<u64>()?
+		} else {
+			self.operand_stack.pop::<u32>()? as u64
+		};
+		let addr = self.atomic_address(mem_arg, bytes)?;
+		if !self.memory.as_ref().ok_or(Error::NoMemory)?.shared {
+			return Err(Error::Trap(TrapKind::Other("atomic wait on unshared memory")));
+		}
+		if self.atomic_read(addr)? != expected {
+			self.operand_stack.push(1i32);
+			return Ok(());
+		}
+		if timeout < 0 {
+			return Err(Error::Trap(TrapKind::Other("atomic wait without timeout would block the only thread forever")));
+		}
+		std::thread::sleep(std::time::Duration::from_nanos(timeout as u64));
+		self.operand_stack.push(2i32);
+		Ok(())
+	}
+
+	/// Pops two vectors, applies `op` and pushes the result.
+	fn simd_binary(&mut self, op: impl Fn([u8; 16], [u8; 16]) -> [u8; 16]) -> ExecutionResult {
+		let rhs = self.operand_stack.pop::<[u8; 16]>()?;
+		let lhs = self.operand_stack.pop::<[u8; 16]>()?;
+		self.operand_stack.push(op(lhs, rhs));
+		Ok(())
+	}
+
+	/// Notifies the hooks and the debugger about a write of `data` to `addr`, if it overlaps with a watched memory
+	/// range.
+	fn memory_written(&mut self, addr: usize, data: &[u8]) {
+		let written = addr..addr + data.len();
+		if let Some(debugger) = self.debugger.as_mut() {
+			debugger.memory_written(written.clone());
+		}
+		let Some(hooks) = self.hooks.as_mut() else {
+			return;
+		};
+		let watched = hooks.watched_memory().iter()
+			.any(|range| range.start < written.end && written.start < range.end);
+		if watched {
+			hooks.on_memory_write(addr, data);
+		}
+	}
+
+	/// Hands the paused state to the debugger if it wants to pause before `instruction`.
+	fn debug_pause(&mut self, offset: usize, instruction: &Instruction) -> ExecutionResult {
+		let Some(debugger) = self.debugger.as_mut() else {
+			return Ok(());
+		};
+		let function_index = self.call_stack.last()
+			.expect("Instructions are only executed inside a function")
+			.function_index;
+		if !debugger.should_pause(function_index, offset) {
+			return Ok(());
+		}
+
+		let action = debugger.pause(
+			function_index, offset, instruction, self.call_stack, self.operand_stack, self.memory.as_ref()
+		);
+		match action {
+			DebugAction::Abort => Err(Error::DebuggerAbort),
+			DebugAction::Continue | DebugAction::Step => Ok(()),
+		}
+	}
+
+	/// Executes the body of a block, loop, if, try or catch clause and handles branches targeting its label.
+	///
+	/// Reaching the end of the body or a branch to a block continues after the block with the block results on top
+	/// of the stack, discarding all other values pushed by the block.
+	/// A branch to a loop restarts the loop with the loop parameters on top of the stack.
+	fn execute_block(&mut self, block_type: &BlockType, instructions: &[Instruction], offset: usize, kind: LabelKind) -> Result<ControlFlow, Error> {
+		let (params, results) = block_type.arity(self.types)?;
+		let pushed = match kind {
+			LabelKind::Catch { pushed, .. } => pushed,
+			LabelKind::Block | LabelKind::Loop => params,
+		};
+		let height = match self.resumed_label() {
+			Some(height) => height,
+			None => self.operand_stack.len().checked_sub(pushed)
+				.ok_or(Error::PopOnEmptyOperandStack)?,
+		};
+		loop {
+			match self.execute_instructions(instructions, offset)? {
+				ControlFlow::Return => return Ok(ControlFlow::Return),
+				ControlFlow::TailCall(callee) => return Ok(ControlFlow::TailCall(callee)),
+				ControlFlow::Yield => {
+					self.suspended.push(Suspended::Label { height });
+					return Ok(ControlFlow::Yield);
+				},
+				ControlFlow::Branch(0) if matches!(kind, LabelKind::Loop) => self.operand_stack.unwind(height, params)?,
+				ControlFlow::Continue | ControlFlow::Branch(0) => {
+					self.operand_stack.unwind(height, results)?;
+					return Ok(ControlFlow::Continue);
+				},
+				ControlFlow::Branch(depth) => return Ok(ControlFlow::Branch(depth - 1)),
+				ControlFlow::Rethrow(0) => match kind {
+					LabelKind::Catch { exception, .. } => return Err(Error::Exception(exception.clone())),
+					LabelKind::Block | LabelKind::Loop => return Err(Error::LabelIndexOutOfBounds(0)),
+				},
+				ControlFlow::Rethrow(depth) => return Ok(ControlFlow::Rethrow(depth - 1)),
+				ControlFlow::Delegate(0, exception) => return Err(Error::Exception(exception)),
+				ControlFlow::Delegate(depth, exception) => return Ok(ControlFlow::Delegate(depth - 1, exception)),
+			}
+		}
+	}
+
+	/// Executes a `try` instruction, whose body starts at `offset`, and the first catch clause matching an exception
+	/// thrown by the body. Returns the control flow and whether it left a catch clause instead of the body.
+	///
+	/// Catching an exception unwinds the calls and operand stack values above the `try`.
+	fn execute_try(
+		&mut self, block_type: &BlockType, instructions: &[Instruction], catches: &[Catch], delegate: Option<u32>,
+		offset: usize, resumed_clause: bool,
+	) -> Result<(ControlFlow, bool), Error> {
+		let (clause, exception) = if resumed_clause {
+			match self.suspended.pop() {
+				Some(Suspended::Caught { clause, exception }) => (clause, exception),
+				other => unreachable!("A resumed catch clause is followed by its exception, got {:?}", other),
+			}
+		} else {
+			let (params, _) = block_type.arity(self.types)?;
+			// A resumed body already saved its height in its label
+			let height = match self.suspended.last() {
+				Some(&Suspended::Label { height }) => height,
+				_ => self.operand_stack.len().checked_sub(params).ok_or(Error::PopOnEmptyOperandStack)?,
+			};
+			let frames = self.call_stack.len();
+			let floor = self.operand_stack.floor();
+			let exception = match self.execute_block(block_type, instructions, offset, LabelKind::Block) {
+				Err(Error::Exception(exception)) => exception,
+				result => return Ok((result?, false)),
+			};
+
+			for _ in frames..self.call_stack.len() {
+				if let Some(profiler) = self.profiler.as_mut() {
+					profiler.exit();
+				}
+			}
+			self.call_stack.truncate(frames);
+			self.operand_stack.set_floor(floor);
+			self.operand_stack.unwind(height, 0)?;
+			if let Some(label_index) = delegate {
+				return Ok((ControlFlow::Delegate(label_index, exception), false));
+			}
+
+			let clause = catches.iter().position(|catch| match catch.tag {
+				Some(tag) => self.tags.get(tag).is_some_and(|tag| exception.is(tag)),
+				None => true,
+			});
+			let Some(clause) = clause else {
+				return Err(Error::Exception(exception));
+			};
+			if catches[clause].tag.is_some() {
+				for value in exception.values() {
+					self.operand_stack.push(value.clone());
+				}
+			}
+			(clause, exception)
+		};
+
+		let catch = &catches[clause];
+		let pushed = match catch.tag {
+			Some(_) => exception.values().len(),
+			None => 0,
+		};
+		let clause_offset = offset + instructions.iter()
+			.chain(catches[..clause].iter().flat_map(|catch| catch.instructions.iter()))
+			.map(Instruction::nested_len)
+			.sum::<usize>();
+		let kind = LabelKind::Catch { exception: &exception, pushed };
+		let control_flow = self.execute_block(block_type, &catch.instructions, clause_offset, kind)?;
+		if let ControlFlow::Yield = control_flow {
+			self.suspended.push(Suspended::Caught { clause, exception });
+		}
+		Ok((control_flow, true))
+	}
+
+	/// Executes `instruction` on the operands cached in `top` if it is straight-line i32 code, and returns whether it
+	/// did. Otherwise, `top` has to be flushed before `instruction` is executed on the operand stack.
+	#[inline(always)]
+	fn execute_cached(&mut self, instruction: &Instruction, top: &mut CachedTop) -> Result<bool, Error> {
+		match instruction {
+			Instruction::I32Const(value) => top.push(self.operand_stack, *value),
+			Instruction::LocalGet(index) => match *self.local(*index)? {
+				Value::I32(value) => top.push(self.operand_stack, value),
+				_ => return Ok(false),
+			},
+			Instruction::LocalSet(index) if top.peek().is_some() => {
+				let value = top.pop(self.operand_stack)?;
+				*self.local(*index)? = Value::I32(value);
+			},
+			Instruction::LocalTee(index) => match top.peek() {
+				Some(value) => *self.local(*index)? = Value::I32(value),
+				None => return Ok(false),
+			},
+			Instruction::Drop if top.peek().is_some() => {
+				top.pop(self.operand_stack)?;
+			},
+			Instruction::I32Eqz => {
+				let operand = top.pop(self.operand_stack)?;
+				top.push(self.operand_stack, (operand == 0) as i32);
+			},
+			Instruction::I32Add => return self.cached_binary(top, i32::wrapping_add),
+			Instruction::I32Sub => return self.cached_binary(top, i32::wrapping_sub),
+			Instruction::I32Mul => return self.cached_binary(top, i32::wrapping_mul),
+			Instruction::I32And => return self.cached_binary(top, |lhs, rhs| lhs & rhs),
+			Instruction::I32Or => return self.cached_binary(top, |lhs, rhs| lhs | rhs),
+			Instruction::I32Xor => return self.cached_binary(top, |lhs, rhs| lhs ^ rhs),
+			Instruction::I32Shl => return self.cached_binary(top, |lhs, rhs| lhs.wrapping_shl(rhs as u32)),
+			Instruction::I32ShrS => return self.cached_binary(top, |lhs, rhs| lhs.wrapping_shr(rhs as u32)),
+			Instruction::I32ShrU => {
+				return self.cached_binary(top, |lhs, rhs| (lhs as u32).wrapping_shr(rhs as u32) as i32);
+			},
+			Instruction::I32Eq => return self.cached_binary(top, |lhs, rhs| (lhs == rhs) as i32),
+			Instruction::I32Ne => return self.cached_binary(top, |lhs, rhs| (lhs != rhs) as i32),
+			Instruction::I32LtS => return self.cached_binary(top, |lhs, rhs| (lhs < rhs) as i32),
+			Instruction::I32LtU => return self.cached_binary(top, |lhs, rhs| ((lhs as u32) < rhs as u32) as i32),
+			Instruction::I32GtS => return self.cached_binary(top, |lhs, rhs| (lhs > rhs) as i32),
+			Instruction::I32GtU => return self.cached_binary(top, |lhs, rhs| (lhs as u32 > rhs as u32) as i32),
+			Instruction::I32LeS => return self.cached_binary(top, |lhs, rhs| (lhs <= rhs) as i32),
+			Instruction::I32LeU => return self.cached_binary(top, |lhs, rhs| (lhs as u32 <= rhs as u32) as i32),
+			Instruction::I32GeS => return self.cached_binary(top, |lhs, rhs| (lhs >= rhs) as i32),
+			Instruction::I32GeU => return self.cached_binary(top, |lhs, rhs| (lhs as u32 >= rhs as u32) as i32),
+			_ => return Ok(false),
+		}
+		Ok(true)
+	}
+
+	/// Replaces the two topmost i32 operands with `op(lhs, rhs)` like [`OperandStack::binary`], but on `top`.
+	#[inline(always)]
+	fn cached_binary(&mut self, top: &mut CachedTop, op: impl FnOnce(i32, i32) -> i32) -> Result<bool, Error> {
+		let rhs = top.pop(self.operand_stack)?;
+		let lhs = top.pop(self.operand_stack)?;
+		top.push(self.operand_stack, op(lhs, rhs));
+		Ok(true)
+	}
+
+	/// Executes `instructions`, of which the first one is located at `offset` in the current function.
+	fn execute_instructions(&mut self, instructions: &[Instruction], offset: usize) -> Result<ControlFlow, Error> {
+		// Skip to the instruction at which the execution yielded
+		let (start_index, resumed) = match self.suspended.last().cloned() {
+			Some(Suspended::Instruction(index)) => {
+				self.suspended.pop();
+				(index, None)
+			},
+			Some(Suspended::Nested { index, else_branch }) => {
+				self.suspended.pop();
+				(index, Some(else_branch))
+			},
+			_ => (0, None),
+		};
+
+		// Nothing may observe the operand stack between the instructions while operands are cached in locals
+		let cache_top = self.debugger.is_none() && !self.config.trace;
+		let mut top = CachedTop::default();
+		let mut view = None;
+		let mut next_offset = offset;
+		for (index, instruction) in instructions.iter().enumerate() {
+			let offset = next_offset;
+			next_offset += instruction.nested_len();
+			if index < start_index {
+				continue;
+			}
+			// A resumed block or call was already started, so it is neither counted nor paused at again
+			let resumed = resumed.filter(|_| index == start_index);
+			let frame = self.call_stack.last_mut().expect("Instructions are only executed inside a function");
+			frame.offset = offset;
+			*self.position = Some(Breakpoint { function: frame.function_index, offset });
+			#[cfg(feature = "trace-hot-paths")]
+			let _span = tracing::trace_span!("execute_instruction", offset, ?instruction).entered();
+			if resumed.is_none() {
+				if let Some(steps_left) = self.steps_left.as_mut() {
+					if *steps_left == 0 {
+						top.flush(self.operand_stack);
+						self.suspended.push(Suspended::Instruction(index));
+						return Ok(ControlFlow::Yield);
+					}
+					*steps_left -= 1;
+				}
+				self.debug_pause(offset, instruction)?;
+				self.stats.instructions += 1;
+				if self.config.opcode_stats {
+					self.stats.opcodes.record(instruction);
+				}
+				if let Some(profiler) = self.profiler.as_mut() {
+					profiler.instruction();
+				}
+			}
+			// Nested blocks and calls may run host functions, which could clone or protect the memory, and so may the
+			// host while the debugger pauses
+			let replaces_memory = matches!(instruction, Instruction::Block { .. } | Instruction::Loop { .. }
+				| Instruction::If { .. } | Instruction::Try { .. } | Instruction::Call { .. }
+				| Instruction::CallIndirect { .. } | Instruction::MemoryGrow);
+			// Writes which borrow the bytes mutably instead of going through the view invalidate its pointer
+			let writes_memory = matches!(instruction, Instruction::MemoryInit(_) | Instruction::MemoryCopy
+				| Instruction::MemoryFill | Instruction::AtomicStore(..) | Instruction::AtomicRmw(..)
+				| Instruction::AtomicCmpxchg(..));
+			if replaces_memory || writes_memory || self.debugger.is_some() {
+				view = None;
+			}
+			let locals_before = match self.config.trace {
+				true => Some(self.locals().clone()),
+				false => None,
+			};
+			if cache_top {
+				match self.execute_cached(instruction, &mut top) {
+					Ok(true) => continue,
+					Ok(false) => top.flush(self.operand_stack),
+					Err(error) => {
+						top.flush(self.operand_stack);
+						return Err(error);
+					},
+				}
+			}
+			match instruction {
+				Instruction::Unreachable => return Err(Error::Trap(TrapKind::Unreachable)),
+				Instruction::Nop => (),
+				Instruction::Block { block_type, instructions } => {
+					let control_flow = self.execute_block(block_type, instructions, offset + 1, LabelKind::Block)?;
+					if let Some(control_flow) = self.leave_nested(index, false, control_flow) {
+						return Ok(control_flow);
+					}
+				},
+				Instruction::Loop { block_type, instructions } => {
+					let control_flow = self.execute_block(block_type, instructions, offset + 1, LabelKind::Loop)?;
+					if let Some(control_flow) = self.leave_nested(index, false, control_flow) {
+						return Ok(control_flow);
+					}
+				},
+				Instruction::If { block_type, if_instructions, else_instructions } => {
+					let else_branch = match resumed {
+						Some(else_branch) => else_branch,
+						None => self.operand_stack.pop::<i32>()? == 0,
+					};
+					let control_flow = if !else_branch {
+						self.execute_block(block_type, if_instructions, offset + 1, LabelKind::Block)?
+					} else {
+						let else_offset = offset + 1 + if_instructions.iter().map(Instruction::nested_len).sum::<usize>();
+						self.execute_block(block_type, else_instructions, else_offset, LabelKind::Block)?
+					};
+					if let Some(control_flow) = self.leave_nested(index, else_branch, control_flow) {
+						return Ok(control_flow);
+					}
+				},
+				Instruction::Try { block_type, instructions, catches, delegate } => {
+					let resumed_clause = resumed.unwrap_or(false);
+					let (control_flow, in_clause) = self.execute_try(
+						block_type, instructions, catches, *delegate, offset + 1, resumed_clause
+					)?;
+					if let Some(control_flow) = self.leave_nested(index, in_clause, control_flow) {
+						return Ok(control_flow);
+					}
+				},
+				Instruction::Throw(tag_index) => {
+					let tag = self.tags.get(*tag_index)
+						.ok_or(Error::TagIndexOutOfBounds { index: *tag_index, len: self.tags.len() })?;
+					let values = self.operand_stack.pop_n(tag.signature().params.len())?;
+					return Err(Error::Exception(Exception::new(Shared::clone(tag), values)));
+				},
+				Instruction::Rethrow(label_index) => return Ok(ControlFlow::Rethrow(*label_index)),
+				Instruction::Br { label_index } => return Ok(ControlFlow::Branch(*label_index)),
+				Instruction::BrIf { label_index } => {
+					let condition = self.operand_stack.pop::<i32>()?;
+					if condition != 0 {
+						return Ok(ControlFlow::Branch(*label_index));
+					}
+				},
+				Instruction::BrTable { label_indexes, default } => {
+					let index = self.operand_stack.pop::<i32>()? as u32 as usize;
+					return Ok(ControlFlow::Branch(*label_indexes.get(index).unwrap_or(default)));
+				},
+				Instruction::Return => return Ok(ControlFlow::Return),
+				Instruction::ReturnCall { function_index } => return Ok(ControlFlow::TailCall(*function_index)),
+				Instruction::ReturnCallIndirect { table_index, type_index } => {
+					let function_index = self.indirect_callee(*table_index, *type_index)?;
+					return Ok(ControlFlow::TailCall(function_index));
+				},
+				Instruction::LocalGet(index) => {
+					let value = self.local(*index)?.clone();
+					self.operand_stack.push(value);
+				},
+				Instruction::LocalSet(index) => {
+					let value = self.operand_stack.pop::<Value>()?;
+					*self.local(*index)? = value;
+				},
+				Instruction::LocalTee(index) => {
+					let value = self.operand_stack.peek::<Value>()?;
+					*self.local(*index)? = value;
+				},
+				Instruction::GlobalGet(index) => {
+					let value = self.global(*index)?.clone();
+					self.operand_stack.push(value);
+				},
+				Instruction::GlobalSet(index) => {
+					let value = self.operand_stack.pop::<Value>()?;
+					*self.global(*index)? = value;
+				},
+				Instruction::I32Const(val) => self.operand_stack.push(Value::I32(*val)),
+				Instruction::I64Const(val) => self.operand_stack.push(Value::I64(*val)),
+				Instruction::F32Const(val) => self.operand_stack.push(Value::F32(*val)),
+				Instruction::F64Const(val) => self.operand_stack.push(Value::F64(*val)),
+				// Memory is little endian, so loads and stores convert from and to little endian bytes
+				Instruction::I32Load(mem_arg) => {
+					let val = i32::from_le_bytes(self.load(&mut view, mem_arg)?);
+					self.operand_stack.push(val);
+				},
+				Instruction::I64Load(mem_arg) => {
+					let val = i64::from_le_bytes(self.load(&mut view, mem_arg)?);
+					self.operand_stack.push(val);
+				},
+				Instruction::F32Load(mem_arg) => {
+					let val = f32::from_le_bytes(self.load(&mut view, mem_arg)?);
+					self.operand_stack.push(val);
+				},
+				Instruction::F64Load(mem_arg) => {
+					let val = f64::from_le_bytes(self.load(&mut view, mem_arg)?);
+					self.operand_stack.push(val);
+				},
+				Instruction::I32Load8s(mem_arg) => {
+					let val = i8::from_le_bytes(self.load(&mut view, mem_arg)?);
+					self.operand_stack.push(val as i32);
+				},
+				Instruction::I32Load8u(mem_arg) => {
+					let val = u8::from_le_bytes(self.load(&mut view, mem_arg)?);
+					self.operand_stack.push(val as i32);
+				},
+				Instruction::I32Load16s(mem_arg) => {
+					let val = i16::from_le_bytes(self.load(&mut view, mem_arg)?);
+					self.operand_stack.push(val as i32);
+				},
+				Instruction::I32Load16u(mem_arg) => {
+					let val = u16::from_le_bytes(self.load(&mut view, mem_arg)?);
+					self.operand_stack.push(val as i32);
+				},
+				Instruction::I64Load8s(mem_arg) => {
+					let val = i8::from_le_bytes(self.load(&mut view, mem_arg)?);
+					self.operand_stack.push(val as i64);
+				},
+				Instruction::I64Load8u(mem_arg) => {
+					let val = u8::from_le_bytes(self.load(&mut view, mem_arg)?);
+					self.operand_stack.push(val as i64);
+				},
+				Instruction::I64Load16s(mem_arg) => {
+					let val = i16::from_le_bytes(self.load(&mut view, mem_arg)?);
+					self.operand_stack.push(val as i64);
+				},
+				Instruction::I64Load16u(mem_arg) => {
+					let val = u16::from_le_bytes(self.load(&mut view, mem_arg)?);
+					self.operand_stack.push(val as i64);
+				},
+				Instruction::I64Load32s(mem_arg) => {
+					let val = i32::from_le_bytes(self.load(&mut view, mem_arg)?);
+					self.operand_stack.push(val as i64);
+				},
+				Instruction::I64Load32u(mem_arg) => {
+					let val = u32::from_le_bytes(self.load(&mut view, mem_arg)?);
+					self.operand_stack.push(val as i64);
+				},
+				Instruction::I32Store(mem_arg) => {
+					let val = self.operand_stack.pop::<i32>()?;
+					self.store_bytes(&mut view, mem_arg, val.to_le_bytes())?;
+				},
+				Instruction::I64Store(mem_arg) => {
+					let val = self.operand_stack.pop::<i64>()?;
+					self.store_bytes(&mut view, mem_arg, val.to_le_bytes())?;
+				},
+				Instruction::F32Store(mem_arg) => {
+					let val = self.operand_stack.pop::<f32>()?;
+					self.store_bytes(&mut view, mem_arg, val.to_le_bytes())?;
+				},
+				Instruction::F64Store(mem_arg) => {
+					let val = self.operand_stack.pop::<f64>()?;
+					self.store_bytes(&mut view, mem_arg, val.to_le_bytes())?;
+				},
+				// Narrow stores wrap the value to their width
+				Instruction::I32Store8(mem_arg) => {
+					let val = self.operand_stack.pop::<i32>()?;
+					self.store_bytes(&mut view, mem_arg, (val as u8).to_le_bytes())?;
+				},
+				Instruction::I32Store16(mem_arg) => {
+					let val = self.operand_stack.pop::<i32>()?;
+					self.store_bytes(&mut view, mem_arg, (val as u16).to_le_bytes())?;
+				},
+				Instruction::I64Store8(mem_arg) => {
+					let val = self.operand_stack.pop::<i64>()?;
+					self.store_bytes(&mut view, mem_arg, (val as u8).to_le_bytes())?;
+				},
+				Instruction::I64Store16(mem_arg) => {
+					let val = self.operand_stack.pop::<i64>()?;
+					self.store_bytes(&mut view, mem_arg, (val as u16).to_le_bytes())?;
+				},
+				Instruction::I64Store32(mem_arg) => {
+					let val = self.operand_stack.pop::<i64>()?;
+					self.store_bytes(&mut view, mem_arg, (val as u32).to_le_bytes())?;
+				},
+				Instruction::MemorySize => {
+					let mem = self.memory.as_ref()
+						.ok_or(Error::NoMemory)?;
+					self.operand_stack.push(Value::I32(mem.page_size() as i32));
+				},
+				Instruction::MemoryGrow => {
+					let delta = self.operand_stack.pop::<u32>()? as usize;
+					// Growing fails without trapping
+					let old_pages = self.grow_memory(delta)?.map_or(-1, |old_pages| old_pages as i32);
+					self.operand_stack.push(Value::I32(old_pages));
+				},
+				Instruction::Call { function_index } => {
+					let control_flow = self.exec_function(*function_index)?;
+					if let Some(control_flow) = self.leave_nested(index, false, control_flow) {
+						return Ok(control_flow);
+					}
+				},
+				Instruction::CallIndirect { table_index, type_index } => {
+					let callee = match (resumed, self.suspended.last()) {
+						// A resumed call already popped its table index, but saved the callee
+						(Some(_), Some(&Suspended::Callee { function_index })) => function_index,
+						_ => self.indirect_callee(*table_index, *type_index)?,
+					};
+					let control_flow = self.exec_function(callee)?;
+					let callee_saved = matches!(self.suspended.last(), Some(Suspended::Callee { .. }));
+					if matches!(control_flow, ControlFlow::Yield) && !callee_saved {
+						self.suspended.push(Suspended::Callee { function_index: callee });
+					}
+					if let Some(control_flow) = self.leave_nested(index, false, control_flow) {
+						return Ok(control_flow);
+					}
+				},
+				Instruction::Drop => { self.operand_stack.pop::<Value>()?; },
+				Instruction::Select | Instruction::SelectValueType => {
+					let condition = self.operand_stack.pop::<i32>()?;
+					let second = self.operand_stack.pop::<Value>()?;
+					let first = self.operand_stack.pop::<Value>()?;
+					self.operand_stack.push(if condition != 0 { first } else { second });
+				},
+				Instruction::RefNull(ty) => self.operand_stack.push(Value::default_for(ty)),
+				Instruction::RefIsNull => {
+					let value = self.operand_stack.pop::<Value>()?;
+					let is_null = value.is_null()
+						.ok_or(Error::StackTypeError(Box::new(Mismatch { expected: "reference", got: value })))?;
+					self.operand_stack.push(Value::I32(is_null as i32));
+				},
+				Instruction::RefFunc(function_index) => self.operand_stack.push(Value::FuncRef(Some(*function_index))),
+				Instruction::TableGet(table) => {
+					let index = self.operand_stack.pop::<u32>()? as usize;
+					let value = self.table_element(*table, index)?.clone();
+					self.operand_stack.push(value);
+				},
+				Instruction::TableSet(table) => {
+					let value = self.operand_stack.pop::<Value>()?;
+					let index = self.operand_stack.pop::<u32>()? as usize;
+					*self.table_element(*table, index)? = value;
+				},
+				Instruction::MemoryInit(data_index) => {
+					let len = self.operand_stack.pop::<u32>()? as usize;
+					let src = self.operand_stack.pop::<u32>()? as usize;
+					let dst = self.operand_stack.pop::<u32>()? as usize;
+					// Both ranges are checked before copying, so that nothing is written if one is out of bounds
+					let data = self.data_segment_bytes(*data_index, src..src + len)?.to_vec();
+					self.memory_slice(dst..dst + len)?.copy_from_slice(&data);
+					self.memory_written(dst, &data);
+				},
+				Instruction::MemoryCopy => {
+					let len = self.operand_stack.pop::<u32>()? as usize;
+					let src = self.operand_stack.pop::<u32>()? as usize;
+					let dst = self.operand_stack.pop::<u32>()? as usize;
+					// Copy via a temporary, because the ranges may overlap
+					let data = self.memory_bytes(src..src + len)?.to_vec();
+					self.memory_slice(dst..dst + len)?.copy_from_slice(&data);
+					self.memory_written(dst, &data);
+				},
+				Instruction::MemoryFill => {
+					let len = self.operand_stack.pop::<u32>()? as usize;
+					// Only the lowest byte of the value is used
+					let value = self.operand_stack.pop::<i32>()? as u8;
+					let dst = self.operand_stack.pop::<u32>()? as usize;
+					let data = vec![value; len];
+					self.memory_slice(dst..dst + len)?.copy_from_slice(&data);
+					self.memory_written(dst, &data);
+				},
+				Instruction::DataDrop(data_index) => {
+					let len = self.data.len();
+					let data = self.data.get_mut(*data_index)
+						.ok_or(Error::DataSegmentIndexOutOfBounds { index: *data_index, len })?;
+					*data = SharedBytes::default();
+				},
+				Instruction::TableInit { table_index, element_index } => {
+					let len = self.operand_stack.pop::<u32>()? as usize;
+					let src = self.operand_stack.pop::<u32>()? as usize;
+					let dst = self.operand_stack.pop::<u32>()? as usize;
+					let values = self.element_segment_elements(*element_index, src..src + len)?.to_vec();
+					self.table_elements(*table_index, dst..dst + len)?.clone_from_slice(&values);
+				},
+				Instruction::ElemDrop(element_index) => {
+					let len = self.elements.len();
+					let elements = self.elements.get_mut(*element_index)
+						.ok_or(Error::ElementSegmentIndexOutOfBounds { index: *element_index, len })?;
+					*elements = Vec::new();
+				},
+				Instruction::TableCopy { dst_table_index, src_table_index } => {
+					let len = self.operand_stack.pop::<u32>()? as usize;
+					let src = self.operand_stack.pop::<u32>()? as usize;
+					let dst = self.operand_stack.pop::<u32>()? as usize;
+					// Copy via a temporary, because the ranges may overlap in the same table
+					let values = self.table_elements(*src_table_index, src..src + len)?.to_vec();
+					self.table_elements(*dst_table_index, dst..dst + len)?.clone_from_slice(&values);
+				},
+				Instruction::TableGrow(table) => {
+					let delta = self.operand_stack.pop::<u32>()? as usize;
+					let init = self.operand_stack.pop::<Value>()?;
+					// Growing fails without trapping
+					let reserved = self.reservation.as_mut().is_none_or(|reservation| reservation.grow_table(delta));
+					let result = match reserved {
+						true => self.table(*table)?.grow(delta, init),
+						false => None,
+					};
+					if let (true, None, Some(reservation)) = (reserved, result, self.reservation.as_mut()) {
+						reservation.shrink_table(delta);
+					}
+					self.operand_stack.push(Value::I32(result.map_or(-1, |old_size| old_size as i32)));
+				},
+				Instruction::TableSize(table) => {
+					let size = self.table(*table)?.size();
+					self.operand_stack.push(Value::I32(size as i32));
+				},
+				Instruction::TableFill(table) => {
+					let len = self.operand_stack.pop::<u32>()? as usize;
+					let value = self.operand_stack.pop::<Value>()?;
+					let dst = self.operand_stack.pop::<u32>()? as usize;
+					self.table_elements(*table, dst..dst + len)?.fill(value);
+				},
+				Instruction::I32Eqz => {
+					self.operand_stack.unary(|a: i32| Value::I32(if a == 0 { 1 } else { 0 }))?;
+				}
+				Instruction::I32Eq => {
+					self.operand_stack.binary(|lhs: i32, rhs: i32| Value::I32(if lhs == rhs { 1 } else  { 0 }))?;
+				},
+				Instruction::I32Add => {
+					self.operand_stack.binary(|lhs: i32, rhs: i32| Value::I32(i32::wrapping_add(lhs, rhs)))?;
+				},
+				Instruction::I32Sub => {
+					self.operand_stack.binary(|lhs: i32, rhs: i32| Value::I32(i32::wrapping_sub(lhs, rhs)))?;
+				},
+				Instruction::I32Mul => {
+					self.operand_stack.binary(|lhs: i32, rhs: i32| Value::I32(i32::wrapping_mul(lhs, rhs)))?;
+				},
+				Instruction::I32DivU => {
+					let rhs = self.operand_stack.pop::<u32>()?;
+					let lhs = self.operand_stack.pop::<u32>()?;
+					let result = lhs / nonzero_divisor(rhs)?;
+					self.operand_stack.push(Value::I32(result as i32));
+				},
+				Instruction::I32DivS => {
 					let rhs = self.operand_stack.pop::<i32>()?;
-					let result = i32::shr(lhs, rhs); // TODO: Possibly not correct sign extension?
+					let lhs = self.operand_stack.pop::<i32>()?;
+					let result = lhs.checked_div(nonzero_divisor(rhs)?).ok_or(Error::Trap(TrapKind::IntegerOverflow))?;
 					self.operand_stack.push(Value::I32(result));
 				},
-				Instruction::I32ShrS => {
-					let lhs = self.operand_stack.pop::<i32>()?;
-					let rhs = self.operand_stack.pop::<i32>()?;
-					let result = i32::shr(lhs, rhs); // TODO: Possibly not correct sign extension?
-					self.operand_stack.push(Value::I32(result));
+				Instruction::I32RemU => {
+					let rhs = self.operand_stack.pop::<u32>()?;
+					let lhs = self.operand_stack.pop::<u32>()?;
+					let result = lhs % nonzero_divisor(rhs)?;
+					self.operand_stack.push(Value::I32(result as i32));
+				},
+				Instruction::I32RemS => {
+					let rhs = self.operand_stack.pop::<i32>()?;
+					let lhs = self.operand_stack.pop::<i32>()?;
+					// `i32::MIN % -1` is 0 instead of an overflow
+					let result = lhs.wrapping_rem(nonzero_divisor(rhs)?);
+					self.operand_stack.push(Value::I32(result));
+				},
+				Instruction::I64DivU => {
+					let rhs = self.operand_stack.pop::<u64>()?;
+					let lhs = self.operand_stack.pop::<u64>()?;
+					let result = lhs / nonzero_divisor(rhs)?;
+					self.operand_stack.push(Value::I64(result as i64));
+				},
+				Instruction::I64DivS => {
+					let rhs = self.operand_stack.pop::<i64>()?;
+					let lhs = self.operand_stack.pop::<i64>()?;
+					let result = lhs.checked_div(nonzero_divisor(rhs)?).ok_or(Error::Trap(TrapKind::IntegerOverflow))?;
+					self.operand_stack.push(Value::I64(result));
+				},
+				Instruction::I64RemU => {
+					let rhs = self.operand_stack.pop::<u64>()?;
+					let lhs = self.operand_stack.pop::<u64>()?;
+					let result = lhs % nonzero_divisor(rhs)?;
+					self.operand_stack.push(Value::I64(result as i64));
+				},
+				Instruction::I64RemS => {
+					let rhs = self.operand_stack.pop::<i64>()?;
+					let lhs = self.operand_stack.pop::<i64>()?;
+					let result = lhs.wrapping_rem(nonzero_divisor(rhs)?);
+					self.operand_stack.push(Value::I64(result));
+				},
+				Instruction::I32And => {
+					self.operand_stack.binary(|lhs: i32, rhs: i32| Value::I32(i32::bitand(lhs, rhs)))?;
+				},
+				Instruction::I32Or => {
+					self.operand_stack.binary(|lhs: i32, rhs: i32| Value::I32(i32::bitor(lhs, rhs)))?;
+				},
+				Instruction::I32Xor => {
+					self.operand_stack.binary(|lhs: i32, rhs: i32| Value::I32(i32::bitxor(lhs, rhs)))?;
+				},
+				Instruction::I32Shl => {
+					// The shift count is taken modulo the bit width
+					self.operand_stack.binary(|lhs: i32, rhs: u32| Value::I32(i32::wrapping_shl(lhs, rhs)))?;
+				},
+				Instruction::I32ShrS => {
+					self.operand_stack.binary(|lhs: i32, rhs: u32| Value::I32(i32::wrapping_shr(lhs, rhs)))?;
+				},
+				Instruction::I32ShrU => {
+					// Shifting the unsigned representation fills with zeros
+					self.operand_stack.binary(|lhs: u32, rhs: u32| Value::I32(u32::wrapping_shr(lhs, rhs) as i32))?;
+				},
+				Instruction::I32Rotl => {
+					self.operand_stack.binary(|lhs: i32, rhs: u32| Value::I32(i32::rotate_left(lhs, rhs)))?;
+				},
+				Instruction::I32Rotr => {
+					self.operand_stack.binary(|lhs: i32, rhs: u32| Value::I32(i32::rotate_right(lhs, rhs)))?;
+				},
+				Instruction::I64Shl => {
+					// The shift count is taken modulo the bit width
+					self.operand_stack.binary(|lhs: i64, rhs: u64| Value::I64(i64::wrapping_shl(lhs, rhs as u32)))?;
+				},
+				Instruction::I64ShrS => {
+					self.operand_stack.binary(|lhs: i64, rhs: u64| Value::I64(i64::wrapping_shr(lhs, rhs as u32)))?;
+				},
+				Instruction::I64ShrU => {
+					// Shifting the unsigned representation fills with zeros
+					self.operand_stack.binary(|lhs: u64, rhs: u64| Value::I64(u64::wrapping_shr(lhs, rhs as u32) as i64))?;
+				},
+				Instruction::I64Rotl => {
+					self.operand_stack.binary(|lhs: i64, rhs: u64| Value::I64(i64::rotate_left(lhs, rhs as u32)))?;
+				},
+				Instruction::I64Rotr => {
+					self.operand_stack.binary(|lhs: i64, rhs: u64| Value::I64(i64::rotate_right(lhs, rhs as u32)))?;
+				},
+				Instruction::I32Clz => {
+					self.operand_stack.unary(|operand: i32| Value::I32(operand.leading_zeros() as i32))?;
+				},
+				Instruction::I32Ctz => {
+					self.operand_stack.unary(|operand: i32| Value::I32(operand.trailing_zeros() as i32))?;
+				},
+				Instruction::I32Popcnt => {
+					self.operand_stack.unary(|operand: i32| Value::I32(operand.count_ones() as i32))?;
+				},
+				Instruction::I32Ne => {
+					self.operand_stack.binary(|lhs: i32, rhs: i32| Value::I32(if lhs != rhs { 1 } else { 0 }))?;
+				},
+				Instruction::I32LtU => {
+					self.operand_stack.binary(|lhs: u32, rhs: u32| Value::I32(if lhs < rhs { 1 } else { 0 }))?;
+				},
+				Instruction::I32LtS => {
+					self.operand_stack.binary(|lhs: i32, rhs: i32| Value::I32(if lhs < rhs { 1 } else { 0 }))?;
+				},
+				Instruction::I32GtU => {
+					self.operand_stack.binary(|lhs: u32, rhs: u32| Value::I32(if lhs > rhs { 1 } else { 0 }))?;
+				},
+				Instruction::I32GtS => {
+					self.operand_stack.binary(|lhs: i32, rhs: i32| Value::I32(if lhs > rhs { 1 } else { 0 }))?;
+				},
+				Instruction::I32LeU => {
+					self.operand_stack.binary(|lhs: u32, rhs: u32| Value::I32(if lhs <= rhs { 1 } else { 0 }))?;
+				},
+				Instruction::I32LeS => {
+					self.operand_stack.binary(|lhs: i32, rhs: i32| Value::I32(if lhs <= rhs { 1 } else { 0 }))?;
+				},
+				Instruction::I32GeU => {
+					self.operand_stack.binary(|lhs: u32, rhs: u32| Value::I32(if lhs >= rhs { 1 } else { 0 }))?;
+				},
+				Instruction::I32GeS => {
+					self.operand_stack.binary(|lhs: i32, rhs: i32| Value::I32(if lhs >= rhs { 1 } else { 0 }))?;
+				},
+				Instruction::I64Eqz => {
+					self.operand_stack.unary(|operand: i64| Value::I32((operand == 0) as i32))?;
+				},
+				Instruction::I64Eq => {
+					self.operand_stack.binary(|lhs: i64, rhs: i64| Value::I32((lhs == rhs) as i32))?;
+				},
+				Instruction::I64Ne => {
+					self.operand_stack.binary(|lhs: i64, rhs: i64| Value::I32((lhs != rhs) as i32))?;
+				},
+				Instruction::I64LtS => {
+					self.operand_stack.binary(|lhs: i64, rhs: i64| Value::I32((lhs < rhs) as i32))?;
+				},
+				Instruction::I64LtU => {
+					self.operand_stack.binary(|lhs: u64, rhs: u64| Value::I32((lhs < rhs) as i32))?;
+				},
+				Instruction::I64GtS => {
+					self.operand_stack.binary(|lhs: i64, rhs: i64| Value::I32((lhs > rhs) as i32))?;
+				},
+				Instruction::I64GtU => {
+					self.operand_stack.binary(|lhs: u64, rhs: u64| Value::I32((lhs > rhs) as i32))?;
+				},
+				Instruction::I64LeS => {
+					self.operand_stack.binary(|lhs: i64, rhs: i64| Value::I32((lhs <= rhs) as i32))?;
+				},
+				Instruction::I64LeU => {
+					self.operand_stack.binary(|lhs: u64, rhs: u64| Value::I32((lhs <= rhs) as i32))?;
+				},
+				Instruction::I64GeS => {
+					self.operand_stack.binary(|lhs: i64, rhs: i64| Value::I32((lhs >= rhs) as i32))?;
+				},
+				Instruction::I64GeU => {
+					self.operand_stack.binary(|lhs: u64, rhs: u64| Value::I32((lhs >= rhs) as i32))?;
+				},
+				Instruction::I64Clz => {
+					self.operand_stack.unary(|operand: i64| Value::I64(operand.leading_zeros() as i64))?;
+				},
+				Instruction::I64Ctz => {
+					self.operand_stack.unary(|operand: i64| Value::I64(operand.trailing_zeros() as i64))?;
+				},
+				Instruction::I64Popcnt => {
+					self.operand_stack.unary(|operand: i64| Value::I64(operand.count_ones() as i64))?;
+				},
+				Instruction::I64Add => {
+					self.operand_stack.binary(|lhs: i64, rhs: i64| Value::I64(i64::wrapping_add(lhs, rhs)))?;
 				},
-				Instruction::I32Rotr => {
-					let lhs = self.operand_stack.pop::<i32>()?;
-					let rhs = self.operand_stack.pop::<i32>()?;
-					let result = i32::rotate_right(lhs, rhs as u32);
-					self.operand_stack.push(Value::I32(result));
+				Instruction::I64Sub => {
+					self.operand_stack.binary(|lhs: i64, rhs: i64| Value::I64(i64::wrapping_sub(lhs, rhs)))?;
 				},
-				Instruction::I32Clz => {
-					let operand = self.operand_stack.pop::<i32>()?;
-					let result = operand.leading_zeros();
-					self.operand_stack.push(Value::I32(result as i32));
+				Instruction::I64Mul => {
+					self.operand_stack.binary(|lhs: i64, rhs: i64| Value::I64(i64::wrapping_mul(lhs, rhs)))?;
 				},
-				Instruction::I32Ctz => {
-					let operand = self.operand_stack.pop::<i32>()?;
-					let result = operand.trailing_zeros();
-					self.operand_stack.push(Value::I32(result as i32));
+				Instruction::I64And => {
+					self.operand_stack.binary(|lhs: i64, rhs: i64| Value::I64(i64::bitand(lhs, rhs)))?;
 				},
-				Instruction::I32Popcnt => {
-					let operand = self.operand_stack.pop::<i32>()?;
-					let result = operand.count_ones();
-					self.operand_stack.push(Value::I32(result as i32));
+				Instruction::I64Or => {
+					self.operand_stack.binary(|lhs: i64, rhs: i64| Value::I64(i64::bitor(lhs, rhs)))?;
 				},
-				Instruction::I32Ne => {
-					let lhs = self.operand_stack.pop::<i32>()?;
-					let rhs = self.operand_stack.pop::<i32>()?;
-					let result = if lhs != rhs { 1 } else { 0 };
-					self.operand_stack.push(Value::I32(result as i32));
+				Instruction::I64Xor => {
+					self.operand_stack.binary(|lhs: i64, rhs: i64| Value::I64(i64::bitxor(lhs, rhs)))?;
 				},
-				Instruction::I32LtU => {
-					let lhs = self.operand_stack.pop::<u32>()?;
-					let rhs = self.operand_stack.pop::<u32>()?;
-					let result = if lhs < rhs { 1 } else { 0 };
-					self.operand_stack.push(Value::I32(result as i32));
+				// Comparisons with NaN are false, except for `ne`
+				Instruction::F32Eq => self.operand_stack.binary(|lhs: f32, rhs: f32| Value::I32((lhs == rhs) as i32))?,
+				Instruction::F32Ne => self.operand_stack.binary(|lhs: f32, rhs: f32| Value::I32((lhs != rhs) as i32))?,
+				Instruction::F32Lt => self.operand_stack.binary(|lhs: f32, rhs: f32| Value::I32((lhs < rhs) as i32))?,
+				Instruction::F32Gt => self.operand_stack.binary(|lhs: f32, rhs: f32| Value::I32((lhs > rhs) as i32))?,
+				Instruction::F32Le => self.operand_stack.binary(|lhs: f32, rhs: f32| Value::I32((lhs <= rhs) as i32))?,
+				Instruction::F32Ge => self.operand_stack.binary(|lhs: f32, rhs: f32| Value::I32((lhs >= rhs) as i32))?,
+				Instruction::F64Eq => self.operand_stack.binary(|lhs: f64, rhs: f64| Value::I32((lhs == rhs) as i32))?,
+				Instruction::F64Ne => self.operand_stack.binary(|lhs: f64, rhs: f64| Value::I32((lhs != rhs) as i32))?,
+				Instruction::F64Lt => self.operand_stack.binary(|lhs: f64, rhs: f64| Value::I32((lhs < rhs) as i32))?,
+				Instruction::F64Gt => self.operand_stack.binary(|lhs: f64, rhs: f64| Value::I32((lhs > rhs) as i32))?,
+				Instruction::F64Le => self.operand_stack.binary(|lhs: f64, rhs: f64| Value::I32((lhs <= rhs) as i32))?,
+				Instruction::F64Ge => self.operand_stack.binary(|lhs: f64, rhs: f64| Value::I32((lhs >= rhs) as i32))?,
+				// `abs`, `neg` and `copysign` only change the sign bit, also of NaNs
+				Instruction::F32Abs => self.operand_stack.unary(|operand: f32| Value::F32(operand.abs()))?,
+				Instruction::F32Neg => self.operand_stack.unary(|operand: f32| Value::F32(-operand))?,
+				Instruction::F32Ceil => self.operand_stack.unary(|operand: f32| Value::F32(operand.ceil()))?,
+				Instruction::F32Floor => self.operand_stack.unary(|operand: f32| Value::F32(operand.floor()))?,
+				Instruction::F32Trunc => self.operand_stack.unary(|operand: f32| Value::F32(operand.trunc()))?,
+				Instruction::F32Nearest => {
+					self.operand_stack.unary(|operand: f32| Value::F32(operand.round_ties_even()))?;
 				},
-				Instruction::I32LtS => {
-					let lhs = self.operand_stack.pop::<i32>()?;
-					let rhs = self.operand_stack.pop::<i32>()?;
-					let result = if lhs < rhs { 1 } else { 0 };
-					self.operand_stack.push(Value::I32(result as i32));
+				Instruction::F32Sqrt => self.operand_stack.unary(|operand: f32| Value::F32(operand.sqrt()))?,
+				Instruction::F32Add => self.operand_stack.binary(|lhs: f32, rhs: f32| Value::F32(lhs + rhs))?,
+				Instruction::F32Sub => self.operand_stack.binary(|lhs: f32, rhs: f32| Value::F32(lhs - rhs))?,
+				Instruction::F32Mul => self.operand_stack.binary(|lhs: f32, rhs: f32| Value::F32(lhs * rhs))?,
+				Instruction::F32Div => self.operand_stack.binary(|lhs: f32, rhs: f32| Value::F32(lhs / rhs))?,
+				// Every f32 is exactly representable as f64, so the result is the same as computed on f32
+				Instruction::F32Min => {
+					self.operand_stack.binary(|lhs: f32, rhs: f32| {
+						Value::F32(float_min(lhs as f64, rhs as f64) as f32)
+					})?;
 				},
-				Instruction::I32GtU => {
-					let lhs = self.operand_stack.pop::<u32>()?;
-					let rhs = self.operand_stack.pop::<u32>()?;
-					let result = if lhs > rhs { 1 } else { 0 };
-					self.operand_stack.push(Value::I32(result as i32));
+				Instruction::F32Max => {
+					self.operand_stack.binary(|lhs: f32, rhs: f32| {
+						Value::F32(float_max(lhs as f64, rhs as f64) as f32)
+					})?;
 				},
-				Instruction::I32GtS => {
-					let lhs = self.operand_stack.pop::<i32>()?;
-					let rhs = self.operand_stack.pop::<i32>()?;
-					let result = if lhs > rhs { 1 } else { 0 };
-					self.operand_stack.push(Value::I32(result as i32));
+				Instruction::F32Copysign => {
+					self.operand_stack.binary(|lhs: f32, rhs: f32| Value::F32(lhs.copysign(rhs)))?;
 				},
-				Instruction::I32LeU => {
-					let lhs = self.operand_stack.pop::<u32>()?;
-					let rhs = self.operand_stack.pop::<u32>()?;
-					let result = if lhs <= rhs { 1 } else { 0 };
-					self.operand_stack.push(Value::I32(result as i32));
+				Instruction::F64Abs => self.operand_stack.unary(|operand: f64| Value::F64(operand.abs()))?,
+				Instruction::F64Neg => self.operand_stack.unary(|operand: f64| Value::F64(-operand))?,
+				Instruction::F64Ceil => self.operand_stack.unary(|operand: f64| Value::F64(operand.ceil()))?,
+				Instruction::F64Floor => self.operand_stack.unary(|operand: f64| Value::F64(operand.floor()))?,
+				Instruction::F64Trunc => self.operand_stack.unary(|operand: f64| Value::F64(operand.trunc()))?,
+				Instruction::F64Nearest => {
+					self.operand_stack.unary(|operand: f64| Value::F64(operand.round_ties_even()))?;
 				},
-				Instruction::I32GtS => {
-					let lhs = self.operand_stack.pop::<i32>()?;
-					let rhs = self.operand_stack.pop::<i32>()?;
-					let result = if lhs <= rhs { 1 } else { 0 };
-					self.operand_stack.push(Value::I32(result as i32));
+				Instruction::F64Sqrt => self.operand_stack.unary(|operand: f64| Value::F64(operand.sqrt()))?,
+				Instruction::F64Add => self.operand_stack.binary(|lhs: f64, rhs: f64| Value::F64(lhs + rhs))?,
+				Instruction::F64Sub => self.operand_stack.binary(|lhs: f64, rhs: f64| Value::F64(lhs - rhs))?,
+				Instruction::F64Mul => self.operand_stack.binary(|lhs: f64, rhs: f64| Value::F64(lhs * rhs))?,
+				Instruction::F64Div => self.operand_stack.binary(|lhs: f64, rhs: f64| Value::F64(lhs / rhs))?,
+				Instruction::F64Min => self.operand_stack.binary(|lhs: f64, rhs: f64| Value::F64(float_min(lhs, rhs)))?,
+				Instruction::F64Max => self.operand_stack.binary(|lhs: f64, rhs: f64| Value::F64(float_max(lhs, rhs)))?,
+				Instruction::F64Copysign => {
+					self.operand_stack.binary(|lhs: f64, rhs: f64| Value::F64(lhs.copysign(rhs)))?;
 				},
-				Instruction::I32GeS => {
-					let lhs = self.operand_stack.pop::<u32>()?;
-					let rhs = self.operand_stack.pop::<u32>()?;
-					let result = if lhs >= rhs { 1 } else { 0 };
-					self.operand_stack.push(Value::I32(result as i32));
+				Instruction::I32WrapI64 => self.operand_stack.unary(|operand: i64| Value::I32(operand as i32))?,
+				Instruction::I64ExtendI32S => self.operand_stack.unary(|operand: i32| Value::I64(operand as i64))?,
+				Instruction::I64ExtendI32U => self.operand_stack.unary(|operand: u32| Value::I64(operand as i64))?,
+				// The trapping truncations fail on NaN and on values out of the range of the integer type
+				Instruction::I32TruncF32S => {
+					let operand = self.operand_stack.pop::<f32>()?;
+					self.operand_stack.push(truncate(operand as f64, I32_RANGE)? as i32);
 				},
-				Instruction::I32GeU => {
-					let lhs = self.operand_stack.pop::<i32>()?;
-					let rhs = self.operand_stack.pop::<i32>()?;
-					let result = if lhs >= rhs { 1 } else { 0 };
-					self.operand_stack.push(Value::I32(result as i32));
+				Instruction::I32TruncF32U => {
+					let operand = self.operand_stack.pop::<f32>()?;
+					self.operand_stack.push(truncate(operand as f64, U32_RANGE)? as u32);
+				},
+				Instruction::I32TruncF64S => {
+					let operand = self.operand_stack.pop::<f64>()?;
+					self.operand_stack.push(truncate(operand, I32_RANGE)? as i32);
+				},
+				Instruction::I32TruncF64U => {
+					let operand = self.operand_stack.pop::<f64>()?;
+					self.operand_stack.push(truncate(operand, U32_RANGE)? as u32);
+				},
+				Instruction::I64TruncF32S => {
+					let operand = self.operand_stack.pop::<f32>()?;
+					self.operand_stack.push(truncate(operand as f64, I64_RANGE)? as i64);
+				},
+				Instruction::I64TruncF32U => {
+					let operand = self.operand_stack.pop::<f32>()?;
+					self.operand_stack.push(truncate(operand as f64, U64_RANGE)? as u64);
+				},
+				Instruction::I64TruncF64S => {
+					let operand = self.operand_stack.pop::<f64>()?;
+					self.operand_stack.push(truncate(operand, I64_RANGE)? as i64);
+				},
+				Instruction::I64TruncF64U => {
+					let operand = self.operand_stack.pop::<f64>()?;
+					self.operand_stack.push(truncate(operand, U64_RANGE)? as u64);
+				},
+				// Int to float casts with `as` round to nearest, ties to even
+				Instruction::F32ConvertI32S => self.operand_stack.unary(|operand: i32| Value::F32(operand as f32))?,
+				Instruction::F32ConvertI32U => self.operand_stack.unary(|operand: u32| Value::F32(operand as f32))?,
+				Instruction::F32ConvertI64S => self.operand_stack.unary(|operand: i64| Value::F32(operand as f32))?,
+				Instruction::F32ConvertI64 => self.operand_stack.unary(|operand: u64| Value::F32(operand as f32))?,
+				Instruction::F32DemoteF64 => self.operand_stack.unary(|operand: f64| Value::F32(operand as f32))?,
+				Instruction::F64ConvertI32S => self.operand_stack.unary(|operand: i32| Value::F64(operand as f64))?,
+				Instruction::F64ConvertI32U => self.operand_stack.unary(|operand: u32| Value::F64(operand as f64))?,
+				Instruction::F64ConvertI64S => self.operand_stack.unary(|operand: i64| Value::F64(operand as f64))?,
+				Instruction::F64ConvertI64U => self.operand_stack.unary(|operand: u64| Value::F64(operand as f64))?,
+				Instruction::F64PromoteF32 => self.operand_stack.unary(|operand: f32| Value::F64(operand as f64))?,
+				Instruction::I32ReinterpretF32 => {
+					self.operand_stack.unary(|operand: f32| Value::I32(operand.to_bits() as i32))?;
+				},
+				Instruction::I64ReinterpretF64 => {
+					self.operand_stack.unary(|operand: f64| Value::I64(operand.to_bits() as i64))?;
+				},
+				Instruction::F32ReinterpretI32 => {
+					self.operand_stack.unary(|operand: u32| Value::F32(f32::from_bits(operand)))?;
+				},
+				Instruction::F64ReinterpretI64 => {
+					self.operand_stack.unary(|operand: u64| Value::F64(f64::from_bits(operand)))?;
+				},
+				Instruction::I32Extend8S => self.operand_stack.unary(|operand: i32| Value::I32(operand as i8 as i32))?,
+				Instruction::I32Extend16S => {
+					self.operand_stack.unary(|operand: i32| Value::I32(operand as i16 as i32))?;
+				},
+				Instruction::I64Extend8S => self.operand_stack.unary(|operand: i64| Value::I64(operand as i8 as i64))?,
+				Instruction::I64Extend16S => {
+					self.operand_stack.unary(|operand: i64| Value::I64(operand as i16 as i64))?;
+				},
+				Instruction::I64Extend32S => {
+					self.operand_stack.unary(|operand: i64| Value::I64(operand as i32 as i64))?;
+				},
+				// Float to int casts with `as` saturate and map NaN to 0, as required by the trunc_sat instructions
+				Instruction::I32TruncSatF32S => {
+					let operand = self.operand_stack.pop::<f32>()?;
+					self.operand_stack.push(operand as i32);
 				},
-				_ => tracing::error!("unimplemented executing Instruction::{:?}", instruction),
+				Instruction::I32TruncSatF32U => {
+					let operand = self.operand_stack.pop::<f32>()?;
+					self.operand_stack.push(operand as u32);
+				},
+				Instruction::I32TruncSatF64S => {
+					let operand = self.operand_stack.pop::<f64>()?;
+					self.operand_stack.push(operand as i32);
+				},
+				Instruction::I32TruncSatF64U => {
+					let operand = self.operand_stack.pop::<f64>()?;
+					self.operand_stack.push(operand as u32);
+				},
+				Instruction::I64TruncSatF32S => {
+					let operand = self.operand_stack.pop::<f32>()?;
+					self.operand_stack.push(operand as i64);
+				},
+				Instruction::I64TruncSatF32U => {
+					let operand = self.operand_stack.pop::<f32>()?;
+					self.operand_stack.push(operand as u64);
+				},
+				Instruction::I64TruncSatF64S => {
+					let operand = self.operand_stack.pop::<f64>()?;
+					self.operand_stack.push(operand as i64);
+				},
+				Instruction::I64TruncSatF64U => {
+					let operand = self.operand_stack.pop::<f64>()?;
+					self.operand_stack.push(operand as u64);
+				},
+				Instruction::V128Load(mem_arg) => {
+					let val = self.load::<16>(&mut view, mem_arg)?;
+					self.operand_stack.push(val);
+				},
+				Instruction::V128Store(mem_arg) => {
+					let val = self.operand_stack.pop::<[u8; 16]>()?;
+					self.store_bytes(&mut view, mem_arg, val)?;
+				},
+				Instruction::V128Const(val) => self.operand_stack.push(*val),
+				Instruction::I8x16Shuffle(lanes) => {
+					let rhs = self.operand_stack.pop::<[u8; 16]>()?;
+					let lhs = self.operand_stack.pop::<[u8; 16]>()?;
+					let result = lanes.map(|lane| match lane {
+						0..=15 => lhs[lane as usize],
+						_ => rhs[lane as usize - 16],
+					});
+					self.operand_stack.push(result);
+				},
+				Instruction::I8x16Swizzle => {
+					let indices = self.operand_stack.pop::<[u8; 16]>()?;
+					let vector = self.operand_stack.pop::<[u8; 16]>()?;
+					// Out of range indices select 0
+					let result = indices.map(|index| vector.get(index as usize).copied().unwrap_or(0));
+					self.operand_stack.push(result);
+				},
+				Instruction::I8x16Splat => {
+					let operand = self.operand_stack.pop::<u32>()?;
+					self.operand_stack.push(simd::splat(operand as u8));
+				},
+				Instruction::I16x8Splat => {
+					let operand = self.operand_stack.pop::<u32>()?;
+					self.operand_stack.push(simd::splat(operand as u16));
+				},
+				Instruction::I32x4Splat => {
+					let operand = self.operand_stack.pop::<u32>()?;
+					self.operand_stack.push(simd::splat(operand));
+				},
+				Instruction::I64x2Splat => {
+					let operand = self.operand_stack.pop::<u64>()?;
+					self.operand_stack.push(simd::splat(operand));
+				},
+				Instruction::F32x4Splat => {
+					let operand = self.operand_stack.pop::<f32>()?;
+					self.operand_stack.push(simd::splat(operand));
+				},
+				Instruction::F64x2Splat => {
+					let operand = self.operand_stack.pop::<f64>()?;
+					self.operand_stack.push(simd::splat(operand));
+				},
+				Instruction::I8x16ExtractLaneS(lane) => {
+					let vector = self.operand_stack.pop::<[u8; 16]>()?;
+					self.operand_stack.push(<u8 as simd::Lane>::read(&vector, *lane as usize) as i8 as i32);
+				},
+				Instruction::I8x16ExtractLaneU(lane) => {
+					let vector = self.operand_stack.pop::<[u8; 16]>()?;
+					self.operand_stack.push(<u8 as simd::Lane>::read(&vector, *lane as usize) as u32);
+				},
+				Instruction::I16x8ExtractLaneS(lane) => {
+					let vector = self.operand_stack.pop::<[u8; 16]>()?;
+					self.operand_stack.push(<u16 as simd::Lane>::read(&vector, *lane as usize) as i16 as i32);
+				},
+				Instruction::I16x8ExtractLaneU(lane) => {
+					let vector = self.operand_stack.pop::<[u8; 16]>()?;
+					self.operand_stack.push(<u16 as simd::Lane>::read(&vector, *lane as usize) as u32);
+				},
+				Instruction::I32x4ExtractLane(lane) => {
+					let vector = self.operand_stack.pop::<[u8; 16]>()?;
+					self.operand_stack.push(<u32 as simd::Lane>::read(&vector, *lane as usize));
+				},
+				Instruction::I64x2ExtractLane(lane) => {
+					let vector = self.operand_stack.pop::<[u8; 16]>()?;
+					self.operand_stack.push(<u64 as simd::Lane>::read(&vector, *lane as usize));
+				},
+				Instruction::F32x4ExtractLane(lane) => {
+					let vector = self.operand_stack.pop::<[u8; 16]>()?;
+					self.operand_stack.push(<f32 as simd::Lane>::read(&vector, *lane as usize));
+				},
+				Instruction::F64x2ExtractLane(lane) => {
+					let vector = self.operand_stack.pop::<[u8; 16]>()?;
+					self.operand_stack.push(<f64 as simd::Lane>::read(&vector, *lane as usize));
+				},
+				Instruction::I8x16ReplaceLane(lane) => {
+					let operand = self.operand_stack.pop::<u32>()?;
+					let vector = self.operand_stack.pop::<[u8; 16]>()?;
+					self.operand_stack.push(simd::replace_lane(vector, *lane as usize, operand as u8));
+				},
+				Instruction::I16x8ReplaceLane(lane) => {
+					let operand = self.operand_stack.pop::<u32>()?;
+					let vector = self.operand_stack.pop::<[u8; 16]>()?;
+					self.operand_stack.push(simd::replace_lane(vector, *lane as usize, operand as u16));
+				},
+				Instruction::I32x4ReplaceLane(lane) => {
+					let operand = self.operand_stack.pop::<u32>()?;
+					let vector = self.operand_stack.pop::<[u8; 16]>()?;
+					self.operand_stack.push(simd::replace_lane(vector, *lane as usize, operand));
+				},
+				Instruction::I64x2ReplaceLane(lane) => {
+					let operand = self.operand_stack.pop::<u64>()?;
+					let vector = self.operand_stack.pop::<[u8; 16]>()?;
+					self.operand_stack.push(simd::replace_lane(vector, *lane as usize, operand));
+				},
+				Instruction::F32x4ReplaceLane(lane) => {
+					let operand = self.operand_stack.pop::<f32>()?;
+					let vector = self.operand_stack.pop::<[u8; 16]>()?;
+					self.operand_stack.push(simd::replace_lane(vector, *lane as usize, operand));
+				},
+				Instruction::F64x2ReplaceLane(lane) => {
+					let operand = self.operand_stack.pop::<f64>()?;
+					let vector = self.operand_stack.pop::<[u8; 16]>()?;
+					self.operand_stack.push(simd::replace_lane(vector, *lane as usize, operand));
+				},
+				Instruction::I8x16Eq => self.simd_binary(|lhs, rhs| simd::compare::<u8>(lhs, rhs, |a, b| a == b))?,
+				Instruction::I16x8Eq => self.simd_binary(|lhs, rhs| simd::compare::<u16>(lhs, rhs, |a, b| a == b))?,
+				Instruction::I32x4Eq => self.simd_binary(|lhs, rhs| simd::compare::<u32>(lhs, rhs, |a, b| a == b))?,
+				Instruction::V128Not => {
+					let operand = u128::from_le_bytes(self.operand_stack.pop::<[u8; 16]>()?);
+					self.operand_stack.push((!operand).to_le_bytes());
+				},
+				Instruction::V128And => self.simd_binary(|lhs, rhs| simd::map2::<u64>(lhs, rhs, u64::bitand))?,
+				Instruction::V128AndNot => self.simd_binary(|lhs, rhs| simd::map2::<u64>(lhs, rhs, |a, b| a & !b))?,
+				Instruction::V128Or => self.simd_binary(|lhs, rhs| simd::map2::<u64>(lhs, rhs, u64::bitor))?,
+				Instruction::V128Xor => self.simd_binary(|lhs, rhs| simd::map2::<u64>(lhs, rhs, u64::bitxor))?,
+				Instruction::V128Bitselect => {
+					let mask = u128::from_le_bytes(self.operand_stack.pop::<[u8; 16]>()?);
+					let rhs = u128::from_le_bytes(self.operand_stack.pop::<[u8; 16]>()?);
+					let lhs = u128::from_le_bytes(self.operand_stack.pop::<[u8; 16]>()?);
+					self.operand_stack.push(((lhs & mask) | (rhs & !mask)).to_le_bytes());
+				},
+				Instruction::V128AnyTrue => {
+					let operand = self.operand_stack.pop::<[u8; 16]>()?;
+					self.operand_stack.push(operand.iter().any(|&byte| byte != 0) as i32);
+				},
+				Instruction::I8x16AllTrue => {
+					let operand = self.operand_stack.pop::<[u8; 16]>()?;
+					self.operand_stack.push(operand.iter().all(|&byte| byte != 0) as i32);
+				},
+				Instruction::I8x16Bitmask => {
+					let operand = self.operand_stack.pop::<[u8; 16]>()?;
+					let mask = operand.iter().enumerate()
+						.fold(0u32, |mask, (lane, &byte)| mask | (((byte >> 7) as u32) << lane));
+					self.operand_stack.push(mask);
+				},
+				Instruction::I8x16Add => self.simd_binary(|lhs, rhs| simd::map2(lhs, rhs, u8::wrapping_add))?,
+				Instruction::I8x16Sub => self.simd_binary(|lhs, rhs| simd::map2(lhs, rhs, u8::wrapping_sub))?,
+				Instruction::I16x8Add => self.simd_binary(|lhs, rhs| simd::map2(lhs, rhs, u16::wrapping_add))?,
+				Instruction::I16x8Sub => self.simd_binary(|lhs, rhs| simd::map2(lhs, rhs, u16::wrapping_sub))?,
+				Instruction::I16x8Mul => self.simd_binary(|lhs, rhs| simd::map2(lhs, rhs, u16::wrapping_mul))?,
+				Instruction::I32x4Add => self.simd_binary(|lhs, rhs| simd::map2(lhs, rhs, u32::wrapping_add))?,
+				Instruction::I32x4Sub => self.simd_binary(|lhs, rhs| simd::map2(lhs, rhs, u32::wrapping_sub))?,
+				Instruction::I32x4Mul => self.simd_binary(|lhs, rhs| simd::map2(lhs, rhs, u32::wrapping_mul))?,
+				Instruction::I64x2Add => self.simd_binary(|lhs, rhs| simd::map2(lhs, rhs, u64::wrapping_add))?,
+				Instruction::I64x2Sub => self.simd_binary(|lhs, rhs| simd::map2(lhs, rhs, u64::wrapping_sub))?,
+				Instruction::I64x2Mul => self.simd_binary(|lhs, rhs| simd::map2(lhs, rhs, u64::wrapping_mul))?,
+				Instruction::MemoryAtomicNotify(mem_arg) => {
+					let _count = self.operand_stack.pop::<u32>()?;
+					let addr = self.atomic_address(mem_arg, 4)?;
+					self.memory_bytes(addr)?;
+					// There are no other threads which could wait
+					self.operand_stack.push(0i32);
+				},
+				Instruction::MemoryAtomicWait32(mem_arg) => self.atomic_wait(mem_arg, 4)?,
+				Instruction::MemoryAtomicWait64(mem_arg) => self.atomic_wait(mem_arg, 8)?,
+				// Every access is sequentially consistent with only a single thread
+				Instruction::AtomicFence => {},
+				Instruction::AtomicLoad(width, mem_arg) => {
+					let addr = self.atomic_address(mem_arg, width.bytes())?;
+					let value = self.atomic_read(addr)?;
+					self.push_atomic_result(*width, value);
+				},
+				Instruction::AtomicStore(width, mem_arg) => {
+					let value = self.pop_atomic_operand(*width)?;
+					let addr = self.atomic_address(mem_arg, width.bytes())?;
+					self.atomic_write(addr, value)?;
+				},
+				Instruction::AtomicRmw(op, width, mem_arg) => {
+					let operand = self.pop_atomic_operand(*width)?;
+					let addr = self.atomic_address(mem_arg, width.bytes())?;
+					let old = self.atomic_read(addr.clone())?;
+					self.atomic_write(addr, op.apply(old, operand))?;
+					self.push_atomic_result(*width, old);
+				},
+				Instruction::AtomicCmpxchg(width, mem_arg) => {
+					let replacement = self.pop_atomic_operand(*width)?;
+					let expected = self.pop_atomic_operand(*width)?;
+					let addr = self.atomic_address(mem_arg, width.bytes())?;
+					let old = self.atomic_read(addr.clone())?;
+					// The expected value is wrapped to the access width before comparing
+					let mask = u64::MAX >> (64 - 8 * width.bytes());
+					if old == expected & mask {
+						self.atomic_write(addr, replacement)?;
+					}
+					self.push_atomic_result(*width, old);
+				},
+				_ if self.config.lenient_mode => {
+					tracing::error!(?instruction, "Skipping the instruction, as executing it is not implemented");
+				},
+				_ => return Err(Error::Trap(TrapKind::UnimplementedInstruction(instruction.name()))),
+			}
+			if self.config.deterministic && instruction.is_float_arithmetic() {
+				self.operand_stack.canonicalize_nan();
+			}
+			if let Some(locals_before) = locals_before {
+				self.trace_instruction(offset, instruction, &locals_before);
 			}
 		}
-		Ok(())
+		top.flush(self.operand_stack);
+		Ok(ControlFlow::Continue)
+	}
+}
+
+
+/// Bytes of the host stack which must be left when a function of the guest is entered. The interpreter recurses for
+/// every call of the guest, so deep recursion would otherwise overflow the stack of the host and abort the process.
+const STACK_RED_ZONE: usize = 256 * 1024;
+
+/// Whether the host stack is too small to enter another function. On WebAssembly hosts, the remaining stack cannot be
+/// determined, so this is never the case.
+fn stack_exhausted() -> bool {
+	#[cfg(not(target_family = "wasm"))]
+	return stacker::remaining_stack().is_some_and(|remaining| remaining < STACK_RED_ZONE);
+	#[cfg(target_family = "wasm")]
+	return false;
+}
+
+/// The values of the integer types as floats, which are exact, as both bounds are powers of two.
+const I32_RANGE: Range<f64> = -2147483648.0..2147483648.0;
+const U32_RANGE: Range<f64> = 0.0..4294967296.0;
+const I64_RANGE: Range<f64> = -9223372036854775808.0..9223372036854775808.0;
+const U64_RANGE: Range<f64> = 0.0..18446744073709551616.0;
+
+/// Truncates `operand` towards zero for the trapping conversion to an integer type whose values are in `range`.
+fn truncate(operand: f64, range: Range<f64>) -> Result<f64, Error> {
+	if operand.is_nan() {
+		return Err(Error::Trap(TrapKind::InvalidConversionToInteger));
+	}
+	let truncated = operand.trunc();
+	match range.contains(&truncated) {
+		true => Ok(truncated),
+		false => Err(Error::Trap(TrapKind::IntegerOverflow)),
+	}
+}
+
+/// The `min` of WebAssembly, which unlike [`f64::min`] is NaN if an operand is NaN, and -0 for -0 and +0.
+fn float_min(lhs: f64, rhs: f64) -> f64 {
+	match (lhs.is_nan() || rhs.is_nan(), lhs == rhs) {
+		(true, _) => f64::NAN,
+		// Equal values only differ in the sign of zeros, of which the negative one is the minimum
+		(false, true) => f64::from_bits(lhs.to_bits() | rhs.to_bits()),
+		(false, false) => lhs.min(rhs),
+	}
+}
+
+/// The `max` of WebAssembly, which is NaN if an operand is NaN, and +0 for -0 and +0.
+fn float_max(lhs: f64, rhs: f64) -> f64 {
+	match (lhs.is_nan() || rhs.is_nan(), lhs == rhs) {
+		(true, _) => f64::NAN,
+		(false, true) => f64::from_bits(lhs.to_bits() & rhs.to_bits()),
+		(false, false) => lhs.max(rhs),
+	}
+}
+
+/// Traps if the divisor `rhs` of an integer division or remainder is zero.
+fn nonzero_divisor<T: Default + PartialEq>(rhs: T) -> Result<T, Error> {
+	match rhs == T::default() {
+		true => Err(Error::Trap(TrapKind::DivisionByZero)),
+		false => Ok(rhs),
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::exec::MAX_MEMORY_PAGES;
+	use crate::parse::{ModuleBuilder, ParsingError, Type};
+
+	/// Calls a function with `args` as parameters and the results `results`, whose body is `body`.
+	fn call(args: Vec<Value>, results: Vec<Type>, body: Vec<Instruction>) -> Result<Vec<Value>, Error> {
+		let mut builder = ModuleBuilder::new();
+		let signature = builder.signature(args.iter().map(Value::ty).collect(), results);
+		let function = builder.function(signature, vec![], body);
+		let mut instance = Instance::with_config(builder.build(), Config::default()).expect("The module instantiates");
+		let results = instance.invoke(function, args);
+		assert!(instance.operand_stack().is_empty(), "{:?}", instance.operand_stack());
+		results
+	}
+
+	/// Applies `instruction` to the operands `lhs` and `rhs`, whose type is also the one of the result.
+	fn binary(instruction: Instruction, lhs: Value, rhs: Value) -> Value {
+		let ty = lhs.ty();
+		let body = vec![Instruction::LocalGet(0), Instruction::LocalGet(1), instruction];
+		call(vec![lhs, rhs], vec![ty], body).unwrap().remove(0)
+	}
+
+	fn i32_binary(instruction: Instruction, vectors: &[(u32, u32, u32)]) {
+		for &(lhs, rhs, expected) in vectors {
+			let result = binary(instruction.clone(), Value::I32(lhs as i32), Value::I32(rhs as i32));
+			assert_eq!(result, Value::I32(expected as i32), "{:?} {:#x} {:#x}", instruction, lhs, rhs);
+		}
+	}
+
+	fn i64_binary(instruction: Instruction, vectors: &[(u64, u64, u64)]) {
+		for &(lhs, rhs, expected) in vectors {
+			let result = binary(instruction.clone(), Value::I64(lhs as i64), Value::I64(rhs as i64));
+			assert_eq!(result, Value::I64(expected as i64), "{:?} {:#x} {:#x}", instruction, lhs, rhs);
+		}
+	}
+
+	// The vectors are taken from i32.wast and i64.wast of the spec test suite. Shift counts of the bit width or more
+	// and negative ones are taken modulo the bit width, and the bit patterns of NaNs are shifted like any integer.
+
+	#[test]
+	fn i32_shl() {
+		i32_binary(Instruction::I32Shl, &[
+			(1, 1, 2), (1, 0, 1), (0x7fffffff, 1, 0xfffffffe), (0xffffffff, 1, 0xfffffffe),
+			(0x80000000, 1, 0), (0x40000000, 1, 0x80000000), (1, 31, 0x80000000), (1, 32, 1), (1, 33, 2),
+			(1, 0xffffffff, 0x80000000), (1, 0x7fffffff, 0x80000000), (0x7fc00000, 1, 0xff800000),
+		]);
+	}
+
+	#[test]
+	fn i32_shr_s() {
+		i32_binary(Instruction::I32ShrS, &[
+			(1, 1, 0), (1, 0, 1), (0xffffffff, 1, 0xffffffff), (0x7fffffff, 1, 0x3fffffff),
+			(0x80000000, 1, 0xc0000000), (0x40000000, 1, 0x20000000), (1, 32, 1), (1, 33, 0), (1, 0xffffffff, 0),
+			(1, 0x7fffffff, 0), (1, 0x80000000, 1), (0x80000000, 31, 0xffffffff), (0xffffffff, 32, 0xffffffff),
+			(0xffffffff, 33, 0xffffffff), (0xffffffff, 0xffffffff, 0xffffffff), (0xffc00000, 22, 0xffffffff),
+		]);
+	}
+
+	#[test]
+	fn i32_shr_u() {
+		i32_binary(Instruction::I32ShrU, &[
+			(1, 1, 0), (1, 0, 1), (0xffffffff, 1, 0x7fffffff), (0x7fffffff, 1, 0x3fffffff),
+			(0x80000000, 1, 0x40000000), (0x40000000, 1, 0x20000000), (1, 32, 1), (1, 33, 0), (1, 0xffffffff, 0),
+			(1, 0x7fffffff, 0), (1, 0x80000000, 1), (0x80000000, 31, 1), (0xffffffff, 32, 0xffffffff),
+			(0xffffffff, 33, 0x7fffffff), (0xffffffff, 0xffffffff, 1), (0xffc00000, 22, 0x3ff),
+		]);
+	}
+
+	#[test]
+	fn i32_rotl() {
+		i32_binary(Instruction::I32Rotl, &[
+			(1, 1, 2), (1, 0, 1), (0xffffffff, 1, 0xffffffff), (1, 32, 1), (0xabcd9876, 1, 0x579b30ed),
+			(0xfe00dc00, 4, 0xe00dc00f), (0xb0c1d2e3, 5, 0x183a5c76), (0x00008000, 37, 0x00100000),
+			(0xb0c1d2e3, 0xff05, 0x183a5c76), (0x769abcdf, 0xffffffed, 0x579beed3), (1, 31, 0x80000000),
+			(0x80000000, 1, 1), (0x7fc00000, 9, 0x800000ff),
+		]);
+	}
+
+	#[test]
+	fn i32_rotr() {
+		i32_binary(Instruction::I32Rotr, &[
+			(1, 1, 0x80000000), (1, 0, 1), (0xffffffff, 1, 0xffffffff), (1, 32, 1), (0xff00cc00, 1, 0x7f806600),
+			(0x00080000, 4, 0x00008000), (0xb0c1d2e3, 5, 0x1d860e97), (0x00008000, 37, 0x00000400),
+			(0xb0c1d2e3, 0xff05, 0x1d860e97), (0x769abcdf, 0xffffffed, 0xe6fbb4d5), (1, 31, 2),
+			(0x80000000, 31, 1), (1, 0xffffffff, 2),
+		]);
+	}
+
+	#[test]
+	fn i64_shifts() {
+		i64_binary(Instruction::I64Shl, &[
+			(1, 1, 2), (0x7fffffffffffffff, 1, 0xfffffffffffffffe), (0x8000000000000000, 1, 0),
+			(1, 63, 0x8000000000000000), (1, 64, 1), (1, 65, 2), (1, u64::MAX, 0x8000000000000000),
+		]);
+		i64_binary(Instruction::I64ShrS, &[
+			(u64::MAX, 1, u64::MAX), (0x8000000000000000, 1, 0xc000000000000000), (1, 64, 1), (1, 65, 0),
+			(1, u64::MAX, 0), (0x8000000000000000, 63, u64::MAX), (u64::MAX, 64, u64::MAX),
+		]);
+		i64_binary(Instruction::I64ShrU, &[
+			(u64::MAX, 1, 0x7fffffffffffffff), (0x8000000000000000, 1, 0x4000000000000000), (1, 64, 1),
+			(1, 65, 0), (1, u64::MAX, 0), (0x8000000000000000, 63, 1), (u64::MAX, 64, u64::MAX),
+			(u64::MAX, u64::MAX, 1),
+		]);
+	}
+
+	#[test]
+	fn i64_rotates() {
+		i64_binary(Instruction::I64Rotl, &[
+			(1, 1, 2), (1, 0, 1), (u64::MAX, 1, u64::MAX), (1, 64, 1), (0xabcd987602468ace, 1, 0x579b30ec048d159d),
+			(0xfe000000dc000000, 4, 0xe000000dc000000f), (0xabcd1234ef567809, 53, 0x013579a2469deacf),
+			(0xabd1234ef567809c, 63, 0x55e891a77ab3c04e), (1, 63, 0x8000000000000000), (0x8000000000000000, 1, 1),
+		]);
+		i64_binary(Instruction::I64Rotr, &[
+			(1, 1, 0x8000000000000000), (1, 0, 1), (u64::MAX, 1, u64::MAX), (1, 64, 1),
+			(0xabcd1234ef567809, 53, 0x6891a77ab3c04d5e), (0xabd1234ef567809c, 63, 0x57a2469deacf0139),
+			(1, 63, 2), (0x8000000000000000, 63, 1),
+		]);
+	}
+
+	/// A block returning an i32.
+	fn block(instructions: Vec<Instruction>) -> Instruction {
+		Instruction::Block { block_type: BlockType::Value(Type::I32), instructions }
+	}
+
+	#[test]
+	fn br_to_outer_label() {
+		use Instruction::*;
+		// The branch leaves the two inner blocks and discards the value below its operand
+		let body = vec![block(vec![
+			Block { block_type: BlockType::Empty, instructions: vec![
+				Block { block_type: BlockType::Empty, instructions: vec![
+					I32Const(7), I32Const(42), Br { label_index: 2 },
+				] },
+				Unreachable,
+			] },
+			Unreachable,
+		])];
+		assert_eq!(call(vec![], vec![Type::I32], body).unwrap(), vec![Value::I32(42)]);
+	}
+
+	#[test]
+	fn br_if_out_of_loop() {
+		use Instruction::*;
+		// Counts the parameter down to zero and returns how many iterations it took
+		let body = vec![
+			I32Const(0),
+			LocalSet(1),
+			Block { block_type: BlockType::Empty, instructions: vec![
+				Loop { block_type: BlockType::Empty, instructions: vec![
+					LocalGet(0), I32Eqz, BrIf { label_index: 1 },
+					LocalGet(0), I32Const(-1), I32Add, LocalSet(0),
+					LocalGet(1), I32Const(1), I32Add, LocalSet(1),
+					Br { label_index: 0 },
+				] },
+			] },
+			LocalGet(1),
+		];
+		let mut builder = ModuleBuilder::new();
+		let signature = builder.signature(vec![Type::I32], vec![Type::I32]);
+		let function = builder.function(signature, vec![Type::I32], body);
+		let mut instance = Instance::with_config(builder.build(), Config::default()).expect("The module instantiates");
+		assert_eq!(instance.invoke(function, vec![Value::I32(10)]).unwrap(), vec![Value::I32(10)]);
+		assert!(instance.operand_stack().is_empty());
+	}
+
+	#[test]
+	fn return_from_nested_blocks() {
+		use Instruction::*;
+		// Without returning from the whole function, the loop would run forever
+		let body = vec![
+			I32Const(5),
+			Block { block_type: BlockType::Empty, instructions: vec![
+				Loop { block_type: BlockType::Empty, instructions: vec![
+					I32Const(1),
+					If { block_type: BlockType::Empty, if_instructions: vec![
+						block(vec![I32Const(9), I32Const(42), Return]),
+						Drop,
+					], else_instructions: vec![] },
+					Br { label_index: 0 },
+				] },
+			] },
+			Drop,
+			I32Const(0),
+		];
+		assert_eq!(call(vec![], vec![Type::I32], body).unwrap(), vec![Value::I32(42)]);
+	}
+
+	#[test]
+	fn return_keeps_stack_of_caller() {
+		use Instruction::*;
+		let mut builder = ModuleBuilder::new();
+		let signature = builder.signature(vec![], vec![Type::I32]);
+		let callee = builder.function(signature, vec![], vec![
+			I32Const(1),
+			block(vec![block(vec![I32Const(2), I32Const(3), Return])]),
+		]);
+		// The value pushed by the caller before the call is still below the result
+		let caller = builder.function(signature, vec![], vec![I32Const(100), Call { function_index: callee }, I32Add]);
+		let mut instance = Instance::with_config(builder.build(), Config::default()).expect("The module instantiates");
+		assert_eq!(instance.invoke(caller, vec![]).unwrap(), vec![Value::I32(103)]);
+		assert!(instance.operand_stack().is_empty());
+	}
+
+	#[test]
+	fn i32_sub() {
+		i32_binary(Instruction::I32Sub, &[
+			(1, 1, 0), (1, 0, 1), (0, 1, 0xffffffff), (0x7fffffff, 0xffffffff, 0x80000000),
+			(0x80000000, 1, 0x7fffffff), (0x80000000, 0x80000000, 0), (0x3fffffff, 0xbfffffff, 0x80000000),
+		]);
+	}
+
+	// Operands which compare differently as signed and unsigned, and lhs and rhs swapped to catch reversed operands
+	const COMPARED: [(u32, u32); 6] = [
+		(0, 0), (1, 0), (0, 1), (0xffffffff, 1), (1, 0xffffffff), (0x80000000, 0x7fffffff),
+	];
+
+	fn i32_compare(instruction: Instruction, expected: [u32; 6]) {
+		let vectors: Vec<_> = COMPARED.iter().zip(expected)
+			.map(|(&(lhs, rhs), expected)| (lhs, rhs, expected))
+			.collect();
+		i32_binary(instruction, &vectors);
+	}
+
+	#[test]
+	fn i32_lt_s() {
+		i32_compare(Instruction::I32LtS, [0, 0, 1, 1, 0, 1]);
+	}
+
+	#[test]
+	fn i32_lt_u() {
+		i32_compare(Instruction::I32LtU, [0, 0, 1, 0, 1, 0]);
+	}
+
+	#[test]
+	fn i32_gt_s() {
+		i32_compare(Instruction::I32GtS, [0, 1, 0, 0, 1, 0]);
+	}
+
+	#[test]
+	fn i32_gt_u() {
+		i32_compare(Instruction::I32GtU, [0, 1, 0, 1, 0, 1]);
+	}
+
+	#[test]
+	fn i32_le_s() {
+		i32_compare(Instruction::I32LeS, [1, 0, 1, 1, 0, 1]);
+	}
+
+	#[test]
+	fn i32_le_u() {
+		i32_compare(Instruction::I32LeU, [1, 0, 1, 0, 1, 0]);
+	}
+
+	#[test]
+	fn i32_ge_s() {
+		i32_compare(Instruction::I32GeS, [1, 1, 0, 0, 1, 0]);
+	}
+
+	#[test]
+	fn i32_ge_u() {
+		i32_compare(Instruction::I32GeU, [1, 1, 0, 1, 0, 1]);
+	}
+
+	#[test]
+	fn cached_operands_keep_their_order() {
+		// The constants spill out of the cache, which is flushed before the i64 instructions
+		let body = vec![
+			Instruction::I32Const(1), Instruction::I32Const(2), Instruction::I32Const(3), Instruction::I32Const(4),
+			Instruction::I64Const(5), Instruction::Drop,
+			Instruction::I32Sub, Instruction::I32Sub, Instruction::I32Sub,
+		];
+		assert_eq!(call(vec![], vec![Type::I32], body).unwrap(), vec![Value::I32(-2)]);
+	}
+
+	#[test]
+	fn cached_operands_survive_yields() {
+		let mut builder = ModuleBuilder::new();
+		builder.memory(1, Some(1));
+		let signature = builder.signature(vec![], vec![]);
+		let body = vec![
+			Instruction::I32Const(0),
+			Instruction::I32Const(7), Instruction::I32Const(2), Instruction::I32Sub, Instruction::I32Const(3),
+			Instruction::I32Mul,
+			Instruction::I32Store(MemArg { align: 2, offset: 0 }),
+		];
+		let function = builder.function(signature, vec![], body);
+		builder.export_function("_start", function);
+		let mut instance = Instance::with_config(builder.build(), Config::default()).expect("The module instantiates");
+		while let StepOutcome::Yielded = instance.run_steps(1) {}
+		assert_eq!(instance.memory().as_ref().unwrap().data()[..4], 15i32.to_le_bytes());
+	}
+
+	#[test]
+	fn i64_compares_signed_and_unsigned() {
+		let compare = |instruction| {
+			let body = vec![Instruction::LocalGet(0), Instruction::LocalGet(1), instruction];
+			call(vec![Value::I64(-1), Value::I64(1)], vec![Type::I32], body).unwrap().remove(0)
+		};
+		assert_eq!(compare(Instruction::I64LtS), Value::I32(1));
+		assert_eq!(compare(Instruction::I64LtU), Value::I32(0));
+		assert_eq!(compare(Instruction::I64GeU), Value::I32(1));
+		assert_eq!(compare(Instruction::I64Ne), Value::I32(1));
+	}
+
+	#[test]
+	fn float_min_max_order_zeros_and_propagate_nan() {
+		let min = binary(Instruction::F64Min, Value::F64(0.0), Value::F64(-0.0));
+		assert!(matches!(min, Value::F64(value) if value == 0.0 && value.is_sign_negative()));
+		let max = binary(Instruction::F32Max, Value::F32(-0.0), Value::F32(0.0));
+		assert!(matches!(max, Value::F32(value) if value == 0.0 && value.is_sign_positive()));
+		let nan = binary(Instruction::F32Min, Value::F32(f32::NAN), Value::F32(1.0));
+		assert!(matches!(nan, Value::F32(value) if value.is_nan()));
+		assert_eq!(binary(Instruction::F64Max, Value::F64(-1.5), Value::F64(2.0)), Value::F64(2.0));
+	}
+
+	#[test]
+	fn nearest_rounds_ties_to_even() {
+		let nearest = |operand| call(vec![Value::F64(operand)], vec![Type::F64], vec![
+			Instruction::LocalGet(0), Instruction::F64Nearest,
+		]).unwrap().remove(0);
+		assert_eq!(nearest(2.5), Value::F64(2.0));
+		assert_eq!(nearest(3.5), Value::F64(4.0));
+		assert!(matches!(nearest(-0.5), Value::F64(value) if value == 0.0 && value.is_sign_negative()));
+	}
+
+	#[test]
+	fn trapping_truncations() {
+		let truncate = |instruction, operand| {
+			call(vec![operand], vec![Type::I32], vec![Instruction::LocalGet(0), instruction])
+		};
+		assert_eq!(truncate(Instruction::I32TruncF32S, Value::F32(-2147483648.0)).unwrap(), vec![Value::I32(i32::MIN)]);
+		assert_eq!(truncate(Instruction::I32TruncF64U, Value::F64(-0.9)).unwrap(), vec![Value::I32(0)]);
+		assert_eq!(truncate(Instruction::I32TruncF64S, Value::F64(-2147483648.9)).unwrap(), vec![Value::I32(i32::MIN)]);
+		assert!(matches!(
+			truncate(Instruction::I32TruncF32S, Value::F32(2147483648.0)),
+			Err(Error::Trap(TrapKind::IntegerOverflow)),
+		));
+		assert!(matches!(
+			truncate(Instruction::I32TruncF64U, Value::F64(-1.0)),
+			Err(Error::Trap(TrapKind::IntegerOverflow)),
+		));
+		assert!(matches!(
+			truncate(Instruction::I32TruncF32U, Value::F32(f32::NAN)),
+			Err(Error::Trap(TrapKind::InvalidConversionToInteger)),
+		));
+	}
+
+	#[test]
+	fn integer_divisions_trap() {
+		let divide = |instruction, lhs: Value, rhs| {
+			let ty = lhs.ty();
+			call(vec![lhs, rhs], vec![ty], vec![Instruction::LocalGet(0), Instruction::LocalGet(1), instruction])
+		};
+		for instruction in [Instruction::I32DivS, Instruction::I32DivU, Instruction::I32RemS, Instruction::I32RemU] {
+			let result = divide(instruction.clone(), Value::I32(7), Value::I32(0));
+			assert!(matches!(result, Err(Error::Trap(TrapKind::DivisionByZero))), "{:?}", instruction);
+		}
+		for instruction in [Instruction::I64DivS, Instruction::I64DivU, Instruction::I64RemS, Instruction::I64RemU] {
+			let result = divide(instruction.clone(), Value::I64(7), Value::I64(0));
+			assert!(matches!(result, Err(Error::Trap(TrapKind::DivisionByZero))), "{:?}", instruction);
+		}
+		let overflow = divide(Instruction::I32DivS, Value::I32(i32::MIN), Value::I32(-1));
+		assert!(matches!(overflow, Err(Error::Trap(TrapKind::IntegerOverflow))));
+		let overflow = divide(Instruction::I64DivS, Value::I64(i64::MIN), Value::I64(-1));
+		assert!(matches!(overflow, Err(Error::Trap(TrapKind::IntegerOverflow))));
+		// The remainder of the same operands is representable
+		assert_eq!(divide(Instruction::I32RemS, Value::I32(i32::MIN), Value::I32(-1)).unwrap(), vec![Value::I32(0)]);
+		assert_eq!(divide(Instruction::I64RemS, Value::I64(i64::MIN), Value::I64(-1)).unwrap(), vec![Value::I64(0)]);
+	}
+
+	/// An instance with an active data segment with index 0 at 0x100 and a passive one with index 1, whose functions
+	/// 0 and 1 call `memory.init` with the parameters `dst`, `src` and `len` on them and function 2 drops segment 1.
+	/// The module is encoded and parsed again, so that the data section is parsed.
+	fn data_segments() -> Instance {
+		let mut builder = ModuleBuilder::new();
+		builder.memory(1, Some(1)).data(0x100, vec![1, 2, 3, 4]);
+		let passive = builder.passive_data(vec![5, 6, 7, 8]);
+		let init_signature = builder.signature(vec![Type::I32; 3], vec![]);
+		for data_index in [0, passive] {
+			let mut body: Vec<_> = (0..3).map(Instruction::LocalGet).collect();
+			body.push(Instruction::MemoryInit(data_index));
+			builder.function(init_signature, vec![], body);
+		}
+		let drop_signature = builder.signature(vec![], vec![]);
+		builder.function(drop_signature, vec![], vec![Instruction::DataDrop(passive)]);
+		let module = Module::from_bytes(builder.build().encode()).unwrap();
+		let passive_data: Vec<_> = module.passive_data.iter().map(|data| data.as_deref()).collect();
+		assert_eq!(passive_data, vec![None, Some(&[5, 6, 7, 8][..])]);
+		Instance::with_config(module, Config::default().allow_resume_after_trap(true)).expect("The module instantiates")
+	}
+
+	fn memory_init(instance: &mut Instance, data_index: usize, dst: i32, src: i32, len: i32) -> Result<(), Error> {
+		instance.invoke(data_index, vec![Value::I32(dst), Value::I32(src), Value::I32(len)]).map(|_| ())
+	}
+
+	fn memory_at(instance: &Instance, addr: usize) -> [u8; 4] {
+		instance.memory().as_ref().unwrap().data()[addr..addr + 4].try_into().unwrap()
+	}
+
+	#[test]
+	fn memory_init_copies_passive_segments() {
+		let mut instance = data_segments();
+		memory_init(&mut instance, 1, 0x10, 1, 3).unwrap();
+		assert_eq!(memory_at(&instance, 0x10), [6, 7, 8, 0]);
+		assert_eq!(memory_at(&instance, 0x100), [1, 2, 3, 4]);
+		// Copying nothing at the end of the segment and the memory is in bounds
+		memory_init(&mut instance, 1, MEMORY_PAGE_SIZE as i32, 4, 0).unwrap();
+	}
+
+	#[test]
+	fn memory_init_checks_both_ranges_before_copying() {
+		let mut instance = data_segments();
+		assert!(matches!(memory_init(&mut instance, 1, 0x10, 2, 3), Err(Error::InvalidDataSegmentAccess { .. })));
+		assert!(matches!(
+			memory_init(&mut instance, 1, MEMORY_PAGE_SIZE as i32 - 2, 0, 4),
+			Err(Error::InvalidMemoryArea { .. }),
+		));
+		assert!(matches!(memory_init(&mut instance, 1, 0x10, 5, 0), Err(Error::InvalidDataSegmentAccess { .. })));
+		assert_eq!(memory_at(&instance, 0x10), [0; 4]);
+		assert_eq!(memory_at(&instance, MEMORY_PAGE_SIZE - 4), [0; 4]);
+	}
+
+	#[test]
+	fn dropped_and_active_segments_are_empty() {
+		let mut instance = data_segments();
+		assert!(matches!(memory_init(&mut instance, 0, 0x10, 0, 1), Err(Error::InvalidDataSegmentAccess { .. })));
+		memory_init(&mut instance, 0, 0x10, 0, 0).unwrap();
+		instance.invoke(2, vec![]).unwrap();
+		assert!(matches!(memory_init(&mut instance, 1, 0x10, 0, 1), Err(Error::InvalidDataSegmentAccess { .. })));
+		memory_init(&mut instance, 1, 0x10, 0, 0).unwrap();
+		assert_eq!(memory_at(&instance, 0x10), [0; 4]);
+	}
+
+	/// An instance whose function 0 calls `memory.copy` and function 1 `memory.fill` with their three parameters.
+	/// The module is encoded and parsed again, so that both instructions are parsed.
+	fn bulk_memory() -> Instance {
+		let mut builder = ModuleBuilder::new();
+		builder.memory(1, Some(1)).data(0, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+		let signature = builder.signature(vec![Type::I32; 3], vec![]);
+		for instruction in [Instruction::MemoryCopy, Instruction::MemoryFill] {
+			let mut body: Vec<_> = (0..3).map(Instruction::LocalGet).collect();
+			body.push(instruction);
+			builder.function(signature, vec![], body);
+		}
+		let module = Module::from_bytes(builder.build().encode()).unwrap();
+		Instance::with_config(module, Config::default().allow_resume_after_trap(true)).expect("The module instantiates")
+	}
+
+	#[test]
+	fn memory_copy_handles_overlapping_ranges() {
+		let mut instance = bulk_memory();
+		instance.invoke(0, vec![Value::I32(2), Value::I32(0), Value::I32(4)]).unwrap();
+		assert_eq!(memory_at(&instance, 0), [1, 2, 1, 2]);
+		assert_eq!(memory_at(&instance, 4), [3, 4, 7, 8]);
+		instance.invoke(0, vec![Value::I32(0), Value::I32(1), Value::I32(4)]).unwrap();
+		assert_eq!(memory_at(&instance, 0), [2, 1, 2, 3]);
+		let end = MEMORY_PAGE_SIZE as i32;
+		let out_of_bounds = instance.invoke(0, vec![Value::I32(0), Value::I32(end - 2), Value::I32(4)]);
+		assert!(matches!(out_of_bounds, Err(Error::InvalidMemoryArea { .. })));
+		assert_eq!(memory_at(&instance, 0), [2, 1, 2, 3]);
+	}
+
+	#[test]
+	fn memory_fill_uses_the_lowest_byte() {
+		let mut instance = bulk_memory();
+		instance.invoke(1, vec![Value::I32(1), Value::I32(0x1ab), Value::I32(2)]).unwrap();
+		assert_eq!(memory_at(&instance, 0), [1, 0xab, 0xab, 4]);
+		let end = MEMORY_PAGE_SIZE as i32;
+		let out_of_bounds = instance.invoke(1, vec![Value::I32(end - 1), Value::I32(0), Value::I32(2)]);
+		assert!(matches!(out_of_bounds, Err(Error::InvalidMemoryArea { .. })));
+	}
+
+	#[test]
+	fn start_section_runs_at_instantiation() {
+		let mut builder = ModuleBuilder::new();
+		builder.memory(1, Some(1));
+		let signature = builder.signature(vec![], vec![]);
+		let mem_arg = MemArg { align: 0, offset: 0 };
+		let body = vec![Instruction::I32Const(0), Instruction::I32Const(42), Instruction::I32Store8(mem_arg)];
+		let function = builder.function(signature, vec![], body);
+		builder.start(function);
+		let mut instance = Instance::with_config(builder.build(), Config::default()).expect("The module instantiates");
+		assert_eq!(memory_at(&instance, 0), [42, 0, 0, 0]);
+		// Without a `_start` export, there is nothing to run afterwards
+		assert!(matches!(instance.start(), Err(Error::UnknownExport(name)) if name == "_start"));
+	}
+
+	#[test]
+	fn start_section_traps_fail_the_instantiation() {
+		let mut builder = ModuleBuilder::new();
+		let signature = builder.signature(vec![], vec![]);
+		let function = builder.function(signature, vec![], vec![Instruction::Unreachable]);
+		builder.start(function);
+		let instance = Instance::with_config(builder.build(), Config::default());
+		assert!(matches!(instance, Err(Error::Trap(TrapKind::Unreachable))));
+	}
+
+	/// Grows a memory with the limit `min..max` by each of `deltas` and returns the results of `memory.grow`.
+	fn grow(min: usize, max: Option<usize>, deltas: &[u32]) -> Vec<i32> {
+		let mut builder = ModuleBuilder::new();
+		builder.memory(min, max);
+		let signature = builder.signature(vec![Type::I32], vec![Type::I32]);
+		let function = builder.function(signature, vec![], vec![Instruction::LocalGet(0), Instruction::MemoryGrow]);
+		let mut instance = Instance::with_config(builder.build(), Config::default()).expect("The module instantiates");
+		deltas.iter()
+			.map(|&delta| match instance.invoke(function, vec![Value::I32(delta as i32)]).unwrap()[..] {
+				[Value::I32(result)] => result,
+				ref results => panic!("memory.grow returned {:?}", results),
+			})
+			.collect()
+	}
+
+	#[test]
+	fn memory_grow_beyond_maximum() {
+		assert_eq!(grow(1, Some(3), &[1, 2, 1, 0]), vec![1, -1, 2, 3]);
+	}
+
+	#[test]
+	fn memory_grow_without_maximum() {
+		assert_eq!(grow(0, None, &[u32::MAX, MAX_MEMORY_PAGES as u32 + 1, 2, 0]), vec![-1, -1, 0, 2]);
+	}
+
+	/// Calls `body` like [`call`] in an instance whose memory starts with `data`.
+	fn call_with_memory(data: &[u8], args: Vec<Value>, result: Type, body: Vec<Instruction>) -> Result<Value, Error> {
+		let mut builder = ModuleBuilder::new();
+		builder.memory(1, Some(1)).data(0, data.to_vec());
+		let signature = builder.signature(args.iter().map(Value::ty).collect(), vec![result]);
+		let function = builder.function(signature, vec![], body);
+		let mut instance = Instance::with_config(builder.build(), Config::default()).expect("The module instantiates");
+		instance.invoke(function, args).map(|mut results| results.remove(0))
+	}
+
+	fn load(data: &[u8], instruction: fn(MemArg) -> Instruction, result: Type) -> Value {
+		let body = vec![Instruction::I32Const(0), instruction(MemArg { align: 0, offset: 0 })];
+		call_with_memory(data, vec![], result, body).unwrap()
+	}
+
+	#[test]
+	fn loads_extend_narrow_values() {
+		let data = [0x80, 0xff, 0xff, 0x7f, 0x01, 0, 0, 0x80];
+		assert_eq!(load(&data, Instruction::I32Load8s, Type::I32), Value::I32(-128));
+		assert_eq!(load(&data, Instruction::I32Load8u, Type::I32), Value::I32(0x80));
+		assert_eq!(load(&data, Instruction::I32Load16s, Type::I32), Value::I32(-128));
+		assert_eq!(load(&data, Instruction::I32Load16u, Type::I32), Value::I32(0xff80));
+		assert_eq!(load(&data, Instruction::I32Load, Type::I32), Value::I32(0x7fffff80));
+		assert_eq!(load(&data, Instruction::I64Load8s, Type::I64), Value::I64(-128));
+		assert_eq!(load(&data, Instruction::I64Load16u, Type::I64), Value::I64(0xff80));
+		assert_eq!(load(&data, Instruction::I64Load32s, Type::I64), Value::I64(0x7fffff80));
+		assert_eq!(load(&data, Instruction::I64Load, Type::I64), Value::I64(0x8000_0001_7fff_ff80u64 as i64));
+		// The bits are a NaN, whose payload is kept
+		let nan = load(&data, Instruction::F32Load, Type::F32);
+		assert!(matches!(nan, Value::F32(value) if value.to_bits() == 0x7fffff80));
+	}
+
+	#[test]
+	fn effective_addresses_do_not_wrap_around() {
+		// The base 0xffffffff plus the offset 4 is beyond 4 GiB instead of the address 3, which 32 bit hosts cannot
+		// even represent
+		let body = vec![Instruction::I32Const(-1), Instruction::I32Load8u(MemArg { align: 0, offset: 4 })];
+		let result = call_with_memory(&[1, 2, 3, 4], vec![], Type::I32, body);
+		assert!(
+			matches!(result, Err(Error::InvalidMemoryArea { ref addr, .. }) if addr.start as u64 == 0x1_0000_0003)
+				|| matches!(result, Err(Error::Trap(TrapKind::MemoryOutOfBounds))),
+			"{:?}", result,
+		);
+	}
+
+	#[test]
+	fn alignment_hints_may_not_exceed_the_natural_alignment() {
+		let module = |align| {
+			let mut builder = ModuleBuilder::new();
+			builder.memory(1, None);
+			let signature = builder.signature(vec![], vec![Type::I32]);
+			let body = vec![Instruction::I32Const(0), Instruction::I32Load(MemArg { align, offset: 0 })];
+			builder.function(signature, vec![], body);
+			builder.build()
+		};
+		assert!(module(2).validate_alignment().is_ok());
+		let invalid = module(3).validate_alignment();
+		assert!(matches!(invalid, Err(ParsingError::InvalidAlignment { align: 3, .. })), "{:?}", invalid);
+	}
+
+	#[test]
+	fn stores_wrap_to_their_width() {
+		let mem_arg = MemArg { align: 0, offset: 4 };
+		let body = vec![
+			Instruction::I32Const(0), Instruction::I64Const(0x1234_5678_9abc), Instruction::I64Store16(mem_arg.clone()),
+			Instruction::I32Const(0), Instruction::I64Load(mem_arg),
+		];
+		let result = call_with_memory(&[0xff; 12], vec![], Type::I64, body).unwrap();
+		assert_eq!(result, Value::I64(0xffff_ffff_ffff_9abcu64 as i64));
+	}
+
+}
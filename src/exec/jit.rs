@@ -0,0 +1,418 @@
+//! Compilation of functions to native code with [Cranelift](https://cranelift.dev), enabled by the `jit` feature.
+//!
+//! Only functions computing exclusively with `i32` and `i64` values in their locals and on the operand stack, using
+//! structured control flow, are compiled. All other functions, e.g. ones accessing the memory, calling other
+//! functions or containing instructions which may trap, are left to the interpreter.
+
+use std::{fmt, mem};
+use cranelift_codegen::ir::{self, condcodes::IntCC, types, AbiParam, InstBuilder, MemFlags};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_frontend::{FuncInstBuilder, FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::Module as _;
+use crate::exec::{BlockType, Instruction, Value, WasmFunction};
+use crate::parse::{Module, Type};
+
+/// Native code taking a pointer to the parameters and returning the result, both extended to 64 bits.
+type NativeFunction = extern "C" fn(*const i64) -> i64;
+
+/// The native code of a function.
+#[derive(Clone, Copy)]
+pub(crate) struct CompiledFunction {
+	code: NativeFunction,
+	result: Option<Type>,
+}
+
+impl CompiledFunction {
+	/// Executes the function with `params`, which have the types of its parameters, and returns its result.
+	pub(crate) fn call(&self, params: &[Value]) -> Option<Value> {
+		let params: Vec<i64> = params.iter()
+			.map(|param| match param {
+				Value::I32(value) => *value as i64,
+				Value::I64(value) => *value,
+				_ => unreachable!("Only functions with integer parameters are compiled"),
+			})
+			.collect();
+		let result = (self.code)(params.as_ptr());
+		self.result.map(|ty| match ty {
+			Type::I32 => Value::I32(result as i32),
+			_ => Value::I64(result),
+		})
+	}
+}
+
+/// The compiled functions of a module.
+pub(crate) struct Jit {
+	/// Owns the memory containing the native code.
+	_module: JITModule,
+	/// Indexed by function index, `None` for the functions left to the interpreter.
+	functions: Vec<Option<CompiledFunction>>,
+}
+
+// The module is not modified after the code was finalized, and the code itself only accesses its own stack
+unsafe impl Send for Jit {}
+unsafe impl Sync for Jit {}
+
+impl fmt::Debug for Jit {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Jit")
+			.field("compiled", &self.functions.iter().flatten().count())
+			.finish()
+	}
+}
+
+impl Jit {
+	/// Compiles the supported functions of `module`, or returns `None` if Cranelift does not support the host.
+	pub(crate) fn compile(module: &Module) -> Option<Self> {
+		let mut flags = settings::builder();
+		flags.set("opt_level", "speed").ok()?;
+		let isa = cranelift_native::builder().ok()?.finish(settings::Flags::new(flags)).ok()?;
+		let pointer_type = isa.pointer_type();
+		let mut jit_module = JITModule::new(JITBuilder::with_isa(isa, cranelift_module::default_libcall_names()));
+
+		let mut context = jit_module.make_context();
+		let mut builder_context = FunctionBuilderContext::new();
+		let mut defined = Vec::with_capacity(module.functions.num_local());
+		for function in module.functions.local() {
+			jit_module.clear_context(&mut context);
+			context.func.signature.params.push(AbiParam::new(pointer_type));
+			context.func.signature.returns.push(AbiParam::new(types::I64));
+			let id = translate(function, &mut context.func, &mut builder_context)
+				.and_then(|()| jit_module.declare_anonymous_function(&context.func.signature).ok())
+				.filter(|id| jit_module.define_function(*id, &mut context).is_ok());
+			if id.is_none() {
+				tracing::debug!(function = function.index, "Function is interpreted");
+			}
+			defined.push(id.map(|id| (id, function.signature.results.first().copied())));
+		}
+		jit_module.finalize_definitions().ok()?;
+
+		let mut functions = vec![None; module.functions.num_imports()];
+		functions.extend(defined.into_iter().map(|compiled| {
+			let (id, result) = compiled?;
+			let code = jit_module.get_finalized_function(id);
+			// The code was generated with the signature of `NativeFunction`
+			let code = unsafe { mem::transmute::<*const u8, NativeFunction>(code) };
+			Some(CompiledFunction { code, result })
+		}));
+		Some(Self { _module: jit_module, functions })
+	}
+
+	/// The compiled function with `function_index`, if it is supported.
+	pub(crate) fn function(&self, function_index: usize) -> Option<CompiledFunction> {
+		self.functions.get(function_index).copied().flatten()
+	}
+}
+
+/// The Cranelift type of an integer value.
+fn integer_type(ty: &Type) -> Option<ir::Type> {
+	match ty {
+		Type::I32 => Some(types::I32),
+		Type::I64 => Some(types::I64),
+		_ => None,
+	}
+}
+
+/// The type of the result of a block with `block_type`, or `None` if the block type is not supported.
+fn result_type(block_type: &BlockType) -> Option<Option<ir::Type>> {
+	match block_type {
+		BlockType::Empty => Some(None),
+		BlockType::Value(ty) => Some(Some(integer_type(ty)?)),
+		BlockType::TypeIndex(_) => None,
+	}
+}
+
+/// Translates `function` into `func`, whose signature is the one of [`NativeFunction`], or returns `None` if the
+/// function is not supported.
+fn translate(function: &WasmFunction, func: &mut ir::Function, builder_context: &mut FunctionBuilderContext) -> Option<()> {
+	// Neither the memory nor globals are supported, so the body does not need to be translated
+	if function.metadata.uses_memory || function.metadata.uses_globals {
+		return None;
+	}
+	let signature = &function.signature;
+	let num_params = signature.params.len();
+	let result = match &signature.results[..] {
+		[] => None,
+		[result] => Some(integer_type(result)?),
+		_ => return None,
+	};
+	let locals = signature.params.iter().chain(&function.locals)
+		.map(integer_type)
+		.collect::<Option<Vec<_>>>()?;
+
+	let mut builder = FunctionBuilder::new(func, builder_context);
+	let entry = builder.create_block();
+	builder.append_block_params_for_function_params(entry);
+	builder.switch_to_block(entry);
+	builder.seal_block(entry);
+	let params = builder.block_params(entry)[0];
+	for (index, ty) in locals.iter().enumerate() {
+		let variable = Variable::new(index);
+		builder.declare_var(variable, *ty);
+		let value = match index < num_params {
+			true => {
+				let param = builder.ins().load(types::I64, MemFlags::trusted(), params, (index * 8) as i32);
+				match *ty == types::I32 {
+					true => builder.ins().ireduce(types::I32, param),
+					false => param,
+				}
+			},
+			false => builder.ins().iconst(*ty, 0),
+		};
+		builder.def_var(variable, value);
+	}
+
+	// A branch to the outermost label returns from the function
+	let exit = builder.create_block();
+	if let Some(result) = result {
+		builder.append_block_param(exit, result);
+	}
+	let mut translator = Translator { builder, locals, stack: Vec::new(), labels: vec![(exit, result.is_some() as usize)] };
+	if translator.translate(&function.body)? {
+		let results = translator.pop_n(result.is_some() as usize)?;
+		translator.builder.ins().jump(exit, &results);
+	}
+
+	let mut builder = translator.builder;
+	builder.switch_to_block(exit);
+	builder.seal_block(exit);
+	let result = match builder.block_params(exit).first().copied() {
+		Some(result) if builder.func.dfg.value_type(result) == types::I32 => builder.ins().uextend(types::I64, result),
+		Some(result) => result,
+		None => builder.ins().iconst(types::I64, 0),
+	};
+	builder.ins().return_(&[result]);
+	builder.finalize();
+	Some(())
+}
+
+/// Translates instructions into the function being built.
+struct Translator<'f> {
+	builder: FunctionBuilder<'f>,
+	/// Types of the parameters and declared locals.
+	locals: Vec<ir::Type>,
+	/// The values computed by the translated instructions, like the operand stack during execution.
+	stack: Vec<ir::Value>,
+	/// The blocks which branches to the enclosing labels jump to together with their number of arguments,
+	/// the innermost label last.
+	labels: Vec<(ir::Block, usize)>,
+}
+
+impl<'f> Translator<'f> {
+	/// Translates `instructions` and returns whether the end of them is reachable, or `None` if one of them is not
+	/// supported.
+	fn translate(&mut self, instructions: &[Instruction]) -> Option<bool> {
+		for instruction in instructions {
+			match instruction {
+				Instruction::Nop => (),
+				Instruction::Drop => { self.pop()?; },
+				Instruction::Select => {
+					let condition = self.pop()?;
+					let [lhs, rhs] = self.pop_array()?;
+					let value = self.builder.ins().select(condition, lhs, rhs);
+					self.stack.push(value);
+				},
+				Instruction::I32Const(value) => {
+					let value = self.builder.ins().iconst(types::I32, *value as u32 as i64);
+					self.stack.push(value);
+				},
+				Instruction::I64Const(value) => {
+					let value = self.builder.ins().iconst(types::I64, *value);
+					self.stack.push(value);
+				},
+				Instruction::LocalGet(index) => {
+					self.locals.get(*index)?;
+					let value = self.builder.use_var(Variable::new(*index));
+					self.stack.push(value);
+				},
+				Instruction::LocalSet(index) => {
+					let value = self.pop()?;
+					self.set_local(*index, value)?;
+				},
+				Instruction::LocalTee(index) => {
+					let value = *self.stack.last()?;
+					self.set_local(*index, value)?;
+				},
+				Instruction::Block { block_type, instructions } => {
+					let exit = self.create_block(block_type)?;
+					let height = self.stack.len();
+					self.labels.push((exit, self.builder.block_params(exit).len()));
+					let reachable = self.translate(instructions)?;
+					self.labels.pop();
+					self.leave(exit, height, reachable)?;
+				},
+				Instruction::Loop { block_type, instructions } => {
+					// Branches to a loop continue with the next iteration, which takes no arguments
+					result_type(block_type)?;
+					let header = self.builder.create_block();
+					self.builder.ins().jump(header, &[]);
+					self.builder.switch_to_block(header);
+					self.labels.push((header, 0));
+					let reachable = self.translate(instructions)?;
+					self.labels.pop();
+					self.builder.seal_block(header);
+					if !reachable {
+						return Some(false);
+					}
+				},
+				Instruction::If { block_type, if_instructions, else_instructions } => {
+					let condition = self.pop()?;
+					let exit = self.create_block(block_type)?;
+					let (then_block, else_block) = (self.builder.create_block(), self.builder.create_block());
+					self.builder.ins().brif(condition, then_block, &[], else_block, &[]);
+					self.builder.seal_block(then_block);
+					self.builder.seal_block(else_block);
+
+					let height = self.stack.len();
+					self.labels.push((exit, self.builder.block_params(exit).len()));
+					for (block, instructions) in [(then_block, if_instructions), (else_block, else_instructions)] {
+						self.builder.switch_to_block(block);
+						if self.translate(instructions)? {
+							let args = self.pop_n(self.builder.block_params(exit).len())?;
+							self.builder.ins().jump(exit, &args);
+						}
+						self.stack.truncate(height);
+					}
+					self.labels.pop();
+					self.builder.switch_to_block(exit);
+					self.builder.seal_block(exit);
+					self.stack.extend_from_slice(self.builder.block_params(exit));
+				},
+				Instruction::Br { label_index } => {
+					let (target, args) = self.branch_target(*label_index)?;
+					self.builder.ins().jump(target, &args);
+					return Some(false);
+				},
+				Instruction::BrIf { label_index } => {
+					let condition = self.pop()?;
+					let (target, args) = self.branch_target(*label_index)?;
+					let next = self.builder.create_block();
+					self.builder.ins().brif(condition, target, &args, next, &[]);
+					self.builder.switch_to_block(next);
+					self.builder.seal_block(next);
+				},
+				Instruction::Return => {
+					let (target, args) = self.branch_target(self.labels.len() as u32 - 1)?;
+					self.builder.ins().jump(target, &args);
+					return Some(false);
+				},
+
+				Instruction::I32Eqz | Instruction::I64Eqz => {
+					let operand = self.pop()?;
+					let condition = self.builder.ins().icmp_imm(IntCC::Equal, operand, 0);
+					let value = self.builder.ins().uextend(types::I32, condition);
+					self.stack.push(value);
+				},
+				Instruction::I32Eq | Instruction::I64Eq => self.compare(IntCC::Equal)?,
+				Instruction::I32Ne | Instruction::I64Ne => self.compare(IntCC::NotEqual)?,
+				Instruction::I32LtS | Instruction::I64LtS => self.compare(IntCC::SignedLessThan)?,
+				Instruction::I32LtU | Instruction::I64LtU => self.compare(IntCC::UnsignedLessThan)?,
+				Instruction::I32GtS | Instruction::I64GtS => self.compare(IntCC::SignedGreaterThan)?,
+				Instruction::I32GtU | Instruction::I64GtU => self.compare(IntCC::UnsignedGreaterThan)?,
+				Instruction::I32LeS | Instruction::I64LeS => self.compare(IntCC::SignedLessThanOrEqual)?,
+				Instruction::I32LeU | Instruction::I64LeU => self.compare(IntCC::UnsignedLessThanOrEqual)?,
+				Instruction::I32GeS | Instruction::I64GeS => self.compare(IntCC::SignedGreaterThanOrEqual)?,
+				Instruction::I32GeU | Instruction::I64GeU => self.compare(IntCC::UnsignedGreaterThanOrEqual)?,
+
+				Instruction::I32Clz | Instruction::I64Clz => self.unary(|ins, operand| ins.clz(operand))?,
+				Instruction::I32Ctz | Instruction::I64Ctz => self.unary(|ins, operand| ins.ctz(operand))?,
+				Instruction::I32Popcnt | Instruction::I64Popcnt => self.unary(|ins, operand| ins.popcnt(operand))?,
+				Instruction::I32WrapI64 => self.unary(|ins, operand| ins.ireduce(types::I32, operand))?,
+				Instruction::I64ExtendI32S => self.unary(|ins, operand| ins.sextend(types::I64, operand))?,
+				Instruction::I64ExtendI32U => self.unary(|ins, operand| ins.uextend(types::I64, operand))?,
+
+				// Divisions are left to the interpreter, as they trap on a zero divisor
+				Instruction::I32Add | Instruction::I64Add => self.binary(|ins, lhs, rhs| ins.iadd(lhs, rhs))?,
+				Instruction::I32Sub | Instruction::I64Sub => self.binary(|ins, lhs, rhs| ins.isub(lhs, rhs))?,
+				Instruction::I32Mul | Instruction::I64Mul => self.binary(|ins, lhs, rhs| ins.imul(lhs, rhs))?,
+				Instruction::I32And | Instruction::I64And => self.binary(|ins, lhs, rhs| ins.band(lhs, rhs))?,
+				Instruction::I32Or | Instruction::I64Or => self.binary(|ins, lhs, rhs| ins.bor(lhs, rhs))?,
+				Instruction::I32Xor | Instruction::I64Xor => self.binary(|ins, lhs, rhs| ins.bxor(lhs, rhs))?,
+				// Cranelift takes shift counts modulo the bit width like WebAssembly
+				Instruction::I32Shl | Instruction::I64Shl => self.binary(|ins, lhs, rhs| ins.ishl(lhs, rhs))?,
+				Instruction::I32ShrS | Instruction::I64ShrS => self.binary(|ins, lhs, rhs| ins.sshr(lhs, rhs))?,
+				Instruction::I32ShrU | Instruction::I64ShrU => self.binary(|ins, lhs, rhs| ins.ushr(lhs, rhs))?,
+				Instruction::I32Rotl | Instruction::I64Rotl => self.binary(|ins, lhs, rhs| ins.rotl(lhs, rhs))?,
+				Instruction::I32Rotr | Instruction::I64Rotr => self.binary(|ins, lhs, rhs| ins.rotr(lhs, rhs))?,
+				_ => return None,
+			}
+		}
+		Some(true)
+	}
+
+	fn pop(&mut self) -> Option<ir::Value> {
+		self.stack.pop()
+	}
+
+	fn pop_array<const N: usize>(&mut self) -> Option<[ir::Value; N]> {
+		self.pop_n(N)?.try_into().ok()
+	}
+
+	/// Pops the topmost `n` values, keeping their order.
+	fn pop_n(&mut self, n: usize) -> Option<Vec<ir::Value>> {
+		let start = self.stack.len().checked_sub(n)?;
+		Some(self.stack.split_off(start))
+	}
+
+	fn set_local(&mut self, index: usize, value: ir::Value) -> Option<()> {
+		// Defining a variable with a value of another type panics
+		if self.locals.get(index) != Some(&self.builder.func.dfg.value_type(value)) {
+			return None;
+		}
+		self.builder.def_var(Variable::new(index), value);
+		Some(())
+	}
+
+	/// Creates the block following a block with `block_type`, which takes its results as parameters.
+	fn create_block(&mut self, block_type: &BlockType) -> Option<ir::Block> {
+		let result = result_type(block_type)?;
+		let block = self.builder.create_block();
+		if let Some(result) = result {
+			self.builder.append_block_param(block, result);
+		}
+		Some(block)
+	}
+
+	/// Continues with `exit` after a block which started at the stack `height`.
+	fn leave(&mut self, exit: ir::Block, height: usize, reachable: bool) -> Option<()> {
+		if reachable {
+			let args = self.pop_n(self.builder.block_params(exit).len())?;
+			self.builder.ins().jump(exit, &args);
+		}
+		self.stack.truncate(height);
+		self.builder.switch_to_block(exit);
+		self.builder.seal_block(exit);
+		self.stack.extend_from_slice(self.builder.block_params(exit));
+		Some(())
+	}
+
+	/// The block a branch to `label_index` jumps to together with the values passed to it.
+	fn branch_target(&self, label_index: u32) -> Option<(ir::Block, Vec<ir::Value>)> {
+		let (block, arity) = *self.labels.iter().rev().nth(label_index as usize)?;
+		let args = self.stack.get(self.stack.len().checked_sub(arity)?..)?.to_vec();
+		Some((block, args))
+	}
+
+	fn unary(&mut self, op: impl FnOnce(FuncInstBuilder<'_, 'f>, ir::Value) -> ir::Value) -> Option<()> {
+		let operand = self.pop()?;
+		let value = op(self.builder.ins(), operand);
+		self.stack.push(value);
+		Some(())
+	}
+
+	fn binary(&mut self, op: impl FnOnce(FuncInstBuilder<'_, 'f>, ir::Value, ir::Value) -> ir::Value) -> Option<()> {
+		let [lhs, rhs] = self.pop_array()?;
+		let value = op(self.builder.ins(), lhs, rhs);
+		self.stack.push(value);
+		Some(())
+	}
+
+	/// Compares the two topmost values, pushing 1 if `condition` holds and 0 otherwise.
+	fn compare(&mut self, condition: IntCC) -> Option<()> {
+		let [lhs, rhs] = self.pop_array()?;
+		let result = self.builder.ins().icmp(condition, lhs, rhs);
+		let value = self.builder.ins().uextend(types::I32, result);
+		self.stack.push(value);
+		Some(())
+	}
+}
\ No newline at end of file
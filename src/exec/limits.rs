@@ -0,0 +1,166 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::exec::Error;
+use crate::shared::Shared;
+
+/// Budgets for the resources of all instances created with the same limits in their [`Config`], e.g. to host many
+/// tenants in one process.
+///
+/// [`Linker::instantiate`] fails with [`Error::LimitExceeded`] if an instance would exceed a budget, and
+/// `memory.grow` and `table.grow` fail like at their maximum size. The resources of an instance are released when it
+/// is dropped.
+///
+/// Share the limits between instances with [`Config::limits`]:
+/// `Config::default().limits(Shared::new(StoreLimits::default().memory_bytes(1 << 30)))`
+///
+/// [`Config`]: crate::exec::Config
+/// [`Config::limits`]: crate::exec::Config::limits
+/// [`Linker::instantiate`]: crate::exec::Linker::instantiate
+#[derive(Debug, Default)]
+pub struct StoreLimits {
+	memory_bytes: Budget,
+	table_elements: Budget,
+	code_size: Budget,
+}
+
+/// The amount of a resource in use and the maximum amount.
+#[derive(Debug, Default)]
+struct Budget {
+	used: AtomicUsize,
+	/// Unlimited if `None`.
+	max: Option<usize>,
+}
+
+impl Budget {
+	/// Adds `amount` to the used amount if it stays within the maximum, and returns whether it did.
+	fn reserve(&self, amount: usize) -> bool {
+		self.used.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+			used.checked_add(amount).filter(|used| self.max.is_none_or(|max| *used <= max))
+		}).is_ok()
+	}
+
+	/// Adds `amount` to the used amount, even if it exceeds the maximum.
+	fn force(&self, amount: usize) {
+		self.used.fetch_add(amount, Ordering::Relaxed);
+	}
+
+	fn release(&self, amount: usize) {
+		self.used.fetch_sub(amount, Ordering::Relaxed);
+	}
+}
+
+impl StoreLimits {
+	/// Limits the total size of the linear memories in bytes.
+	pub fn memory_bytes(mut self, max: usize) -> Self {
+		self.memory_bytes.max = Some(max);
+		self
+	}
+
+	/// Limits the total number of table elements.
+	pub fn table_elements(mut self, max: usize) -> Self {
+		self.table_elements.max = Some(max);
+		self
+	}
+
+	/// Limits the total size of the code sections of the instantiated modules in bytes, which is counted once per
+	/// instance.
+	pub fn code_size(mut self, max: usize) -> Self {
+		self.code_size.max = Some(max);
+		self
+	}
+
+	/// Bytes of linear memory of all living instances.
+	pub fn used_memory_bytes(&self) -> usize {
+		self.memory_bytes.used.load(Ordering::Relaxed)
+	}
+
+	/// Table elements of all living instances.
+	pub fn used_table_elements(&self) -> usize {
+		self.table_elements.used.load(Ordering::Relaxed)
+	}
+
+	/// Code size of all living instances in bytes.
+	pub fn used_code_size(&self) -> usize {
+		self.code_size.used.load(Ordering::Relaxed)
+	}
+}
+
+/// The resources used by a single instance, which are released when it is dropped.
+#[derive(Debug)]
+pub(crate) struct Reservation {
+	limits: Shared<StoreLimits>,
+	memory_bytes: usize,
+	table_elements: usize,
+	code_size: usize,
+}
+
+impl Reservation {
+	/// Reserves the resources of a new instance, or fails with [`Error::LimitExceeded`] naming the exceeded budget.
+	pub(crate) fn new(limits: &Shared<StoreLimits>, memory_bytes: usize, table_elements: usize, code_size: usize) -> Result<Self, Error> {
+		// Dropping the reservation on an error releases the resources reserved so far
+		let mut reservation = Reservation { limits: Shared::clone(limits), memory_bytes: 0, table_elements: 0, code_size: 0 };
+		reservation.memory_bytes = reserve("memory bytes", &limits.memory_bytes, memory_bytes)?;
+		reservation.table_elements = reserve("table elements", &limits.table_elements, table_elements)?;
+		reservation.code_size = reserve("code size", &limits.code_size, code_size)?;
+		Ok(reservation)
+	}
+
+	/// Reserves the resources of a new instance even if they exceed the limits, e.g. for an instance created by an
+	/// infallible constructor.
+	pub(crate) fn force(limits: &Shared<StoreLimits>, memory_bytes: usize, table_elements: usize, code_size: usize) -> Self {
+		limits.memory_bytes.force(memory_bytes);
+		limits.table_elements.force(table_elements);
+		limits.code_size.force(code_size);
+		Reservation { limits: Shared::clone(limits), memory_bytes, table_elements, code_size }
+	}
+
+	/// Reserves the same resources again for a copy of the instance.
+	pub(crate) fn duplicate(&self) -> Self {
+		Self::force(&self.limits, self.memory_bytes, self.table_elements, self.code_size)
+	}
+
+	/// Reserves `bytes` more bytes of memory and returns whether they are within the limit.
+	pub(crate) fn grow_memory(&mut self, bytes: usize) -> bool {
+		let reserved = self.limits.memory_bytes.reserve(bytes);
+		if reserved {
+			self.memory_bytes += bytes;
+		}
+		reserved
+	}
+
+	/// Releases `bytes` bytes of memory, e.g. if growing the memory failed after reserving them.
+	pub(crate) fn shrink_memory(&mut self, bytes: usize) {
+		self.limits.memory_bytes.release(bytes);
+		self.memory_bytes -= bytes;
+	}
+
+	/// Reserves `elements` more table elements and returns whether they are within the limit.
+	pub(crate) fn grow_table(&mut self, elements: usize) -> bool {
+		let reserved = self.limits.table_elements.reserve(elements);
+		if reserved {
+			self.table_elements += elements;
+		}
+		reserved
+	}
+
+	/// Releases `elements` table elements, e.g. if growing the table failed after reserving them.
+	pub(crate) fn shrink_table(&mut self, elements: usize) {
+		self.limits.table_elements.release(elements);
+		self.table_elements -= elements;
+	}
+}
+
+/// Reserves `amount` of the `resource` with `budget` and returns it, or fails with [`Error::LimitExceeded`].
+fn reserve(resource: &'static str, budget: &Budget, amount: usize) -> Result<usize, Error> {
+	match budget.reserve(amount) {
+		true => Ok(amount),
+		false => Err(Error::LimitExceeded { resource, limit: budget.max.unwrap_or(usize::MAX) }),
+	}
+}
+
+impl Drop for Reservation {
+	fn drop(&mut self) {
+		self.limits.memory_bytes.release(self.memory_bytes);
+		self.limits.table_elements.release(self.table_elements);
+		self.limits.code_size.release(self.code_size);
+	}
+}
\ No newline at end of file
@@ -0,0 +1,485 @@
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
+use std::iter;
+use crate::shared::{self, Shared, SharedMut};
+use crate::exec::{Callable, Config, Error, HostResult, TrapKind, ExternFunction, FunctionSignature, Identifier, ImportMismatch, Instance, Memory, Table, Value, WasiVersion, wasi};
+use crate::exec::memory::MEMORY_PAGE_SIZE;
+use crate::exec::instance::InstanceRef;
+use crate::exec::log;
+#[cfg(feature = "emscripten")]
+use crate::exec::emscripten;
+use crate::parse::{DylinkInfo, GlobalBlueprint, MemoryBlueprint, Module, TableBlueprint, Type};
+
+/// A host function implementing a WASI import.
+type HostFunction = fn(&mut InstanceRef) -> HostResult;
+
+/// Resolves the imports of a module by their [`Identifier`].
+///
+/// Besides host functions, the exported functions of other instances can be registered with [`Linker::instance`],
+/// so that one module can call into another one. Imported globals, memories and tables are defined with
+/// [`Linker::global`], [`Linker::memory`] and [`Linker::table`].
+///
+/// Modules built by older or nonstandard toolchains, which import the same definitions under other names, are linked
+/// with [`Linker::alias`] and [`Linker::alias_module`].
+#[derive(Debug)]
+pub struct Linker {
+	definitions: HashMap<Identifier, Shared<Callable>>,
+	globals: HashMap<Identifier, Value>,
+	memories: HashMap<Identifier, Memory>,
+	tables: HashMap<Identifier, Table>,
+	/// Definitions which depend on the host environment.
+	nondeterministic: HashSet<Identifier>,
+	/// The names imports are resolved with instead of their own, by the name of the import.
+	aliases: HashMap<Identifier, Identifier>,
+	/// The module names imports are resolved with instead of their own, by the module name of the import.
+	module_aliases: HashMap<String, String>,
+	/// End of the memory and table space of the side modules loaded so far, see [`Linker::side_module`].
+	memory_base: usize,
+	table_base: usize,
+}
+
+impl Default for Linker {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Linker {
+	/// A linker which provides the implemented WASI functions of preview 1.
+	pub fn new() -> Self {
+		Self::with_wasi(WasiVersion::Preview1)
+	}
+
+	/// A linker which provides the implemented WASI functions of `version`.
+	///
+	/// For preview 1, the module `wasi_unstable` of the snapshot before is an alias of `wasi_snapshot_preview1`. Only
+	/// the functions whose signature and layouts did not change between both snapshots behave as expected, which are
+	/// all except `fd_seek`, `fd_filestat_get`, `path_filestat_get` and `poll_oneoff`.
+	///
+	/// With the `emscripten` feature, preview 1 also provides the `env` imports of the Emscripten runtime, such as
+	/// `emscripten_notify_memory_growth` and the syscalls which musl does not map to WASI.
+	pub fn with_wasi(version: WasiVersion) -> Self {
+		let mut linker = Self {
+			definitions: HashMap::new(),
+			globals: HashMap::new(),
+			memories: HashMap::new(),
+			tables: HashMap::new(),
+			nondeterministic: HashSet::new(),
+			aliases: HashMap::new(),
+			module_aliases: HashMap::new(),
+			memory_base: 0,
+			table_base: 0,
+		};
+		match version {
+			WasiVersion::Preview1 => {
+				linker.define_wasi_preview1();
+				#[cfg(feature = "emscripten")]
+				linker.define_emscripten();
+			},
+			WasiVersion::Preview2 => linker.define_wasi_preview2(),
+		}
+		linker
+	}
+
+	fn define_wasi_preview1(&mut self) {
+		self.alias_module("wasi_unstable", "wasi_snapshot_preview1");
+		self.wasi_func("fd_write", vec![Type::I32; 4], wasi::fd_write);
+		// Stdin is an input of the execution, and files are only opened by nondeterministic functions or the host
+		self.wasi_func("fd_read", vec![Type::I32; 4], wasi::fd_read);
+		self.wasi_func("fd_fdstat_set_flags", vec![Type::I32; 2], wasi::fd_fdstat_set_flags);
+		self.wasi_func("args_sizes_get", vec![Type::I32; 2], wasi::args_sizes_get);
+		self.wasi_func("args_get", vec![Type::I32; 2], wasi::args_get);
+		self.wasi_func("environ_sizes_get", vec![Type::I32; 2], wasi::environ_sizes_get);
+		self.wasi_func("environ_get", vec![Type::I32; 2], wasi::environ_get);
+		self.wasi_func("random_get", vec![Type::I32; 2], wasi::random_get);
+		self.wasi_func("clock_time_get", vec![Type::I32, Type::I64, Type::I32], wasi::clock_time_get);
+		self.wasi_func("fd_prestat_get", vec![Type::I32; 2], wasi::fd_prestat_get);
+		self.wasi_func("fd_prestat_dir_name", vec![Type::I32; 3], wasi::fd_prestat_dir_name);
+		self.wasi_func("poll_oneoff", vec![Type::I32; 4], wasi::poll_oneoff);
+		self.nondeterministic(("wasi_snapshot_preview1", "poll_oneoff"));
+		let sockets: [(&str, usize, HostFunction); 4] = [
+			("sock_accept", 3, wasi::sock_accept),
+			("sock_recv", 6, wasi::sock_recv),
+			("sock_send", 5, wasi::sock_send),
+			("sock_shutdown", 2, wasi::sock_shutdown),
+		];
+		let paths: [(&str, usize, HostFunction); 5] = [
+			("path_filestat_get", 5, wasi::path_filestat_get),
+			("path_create_directory", 3, wasi::path_create_directory),
+			("path_unlink_file", 3, wasi::path_unlink_file),
+			("path_rename", 6, wasi::path_rename),
+			("path_readlink", 6, wasi::path_readlink),
+		];
+		for (field, num_params, function) in sockets.into_iter().chain(paths) {
+			self.wasi_func(field, vec![Type::I32; num_params], function);
+			self.nondeterministic(("wasi_snapshot_preview1", field));
+		}
+		let files: [(&str, Vec<Type>, HostFunction); 9] = [
+			("path_open", [vec![Type::I32; 5], vec![Type::I64; 2], vec![Type::I32; 2]].concat(), wasi::path_open),
+			("fd_readdir", vec![Type::I32, Type::I32, Type::I32, Type::I64, Type::I32], wasi::fd_readdir),
+			("fd_seek", vec![Type::I32, Type::I64, Type::I32, Type::I32], wasi::fd_seek),
+			("fd_close", vec![Type::I32], wasi::fd_close),
+			("fd_filestat_get", vec![Type::I32; 2], wasi::fd_filestat_get),
+			("fd_filestat_set_size", vec![Type::I32, Type::I64], wasi::fd_filestat_set_size),
+			("fd_tell", vec![Type::I32; 2], wasi::fd_tell),
+			("fd_pread", vec![Type::I32, Type::I32, Type::I32, Type::I64, Type::I32], wasi::fd_pread),
+			("fd_pwrite", vec![Type::I32, Type::I32, Type::I32, Type::I64, Type::I32], wasi::fd_pwrite),
+		];
+		for (field, params, function) in files {
+			self.wasi_func(field, params, function);
+			self.nondeterministic(("wasi_snapshot_preview1", field));
+		}
+	}
+
+	/// Defines the WASI preview 1 function `field` returning an errno, which is not performed if the
+	/// [`WasiPolicy`](crate::exec::WasiPolicy) of the calling instance restricts it.
+	fn wasi_func(&mut self, field: &'static str, params: Vec<Type>, function: fn(&mut InstanceRef) -> HostResult) {
+		let num_params = params.len();
+		self.func(Callable::RustClosure {
+			name: ("wasi_snapshot_preview1", field).into(),
+			signature: Shared::new(FunctionSignature { params, results: vec![Type::I32] }),
+			closure: Box::new(move |instance| match instance.wasi.policy().restriction(field) {
+				Some(errno) => {
+					instance.operand_stack.pop_n(num_params)?;
+					Ok(vec![Value::I32(errno)])
+				},
+				None => function(instance),
+			}),
+		});
+	}
+
+	fn define_wasi_preview2(&mut self) {
+		for &(module, field, params, results, function) in wasi::preview2::FUNCTIONS {
+			self.func(Callable::RustFunction {
+				name: (module, field).into(),
+				signature: Shared::new(FunctionSignature { params: params.to_vec(), results: results.to_vec() }),
+				function,
+			});
+		}
+	}
+
+	#[cfg(feature = "emscripten")]
+	fn define_emscripten(&mut self) {
+		for &(field, params, results, function) in emscripten::FUNCTIONS {
+			self.func(Callable::RustFunction {
+				name: ("env", field).into(),
+				signature: Shared::new(FunctionSignature { params: params.to_vec(), results: results.to_vec() }),
+				function,
+			});
+		}
+	}
+
+	/// Defines the import `env.log(ptr, len, level)`, with which guests not using WASI print the UTF-8 string at `ptr`
+	/// through `tracing` with the target `guest`, e.g. in teaching examples like `examples/log.wat`.
+	pub fn log(&mut self) -> &mut Self {
+		self.func(Callable::RustFunction {
+			name: log::NAME.into(),
+			signature: Shared::new(FunctionSignature { params: log::PARAMS.to_vec(), results: Vec::new() }),
+			function: log::log,
+		})
+	}
+
+	/// Defines the host function `callable` under its name, replacing a previous definition.
+	pub fn func(&mut self, callable: Callable) -> &mut Self {
+		let name = match &callable {
+			Callable::RustFunction { name, .. } | Callable::RustClosure { name, .. } => name.clone(),
+			Callable::WasmFunction(function) => Identifier {
+				module: String::new(),
+				field: function.export_name.clone().unwrap_or_else(|| function.index.to_string()),
+			},
+		};
+		self.definitions.insert(name, Shared::new(callable));
+		self
+	}
+
+	/// Defines the global `name` with the initial `value`.
+	pub fn global(&mut self, name: impl Into<Identifier>, value: Value) -> &mut Self {
+		self.globals.insert(name.into(), value);
+		self
+	}
+
+	/// Defines the memory `name`, e.g. for modules produced by wasm-bindgen which import their memory.
+	///
+	/// Each instance importing it gets a copy of `memory`, into which its data segments are copied.
+	pub fn memory(&mut self, name: impl Into<Identifier>, memory: Memory) -> &mut Self {
+		self.memories.insert(name.into(), memory);
+		self
+	}
+
+	/// Defines the table `name`, of which each instance importing it gets a copy.
+	pub fn table(&mut self, name: impl Into<Identifier>, table: Table) -> &mut Self {
+		self.tables.insert(name.into(), table);
+		self
+	}
+
+	/// Marks the definition `name` as depending on the host environment, e.g. because it reads files or the network,
+	/// so that [`Linker::instantiate`] refuses it as import in [deterministic](Config::deterministic) mode.
+	pub fn nondeterministic(&mut self, name: impl Into<Identifier>) -> &mut Self {
+		self.nondeterministic.insert(name.into());
+		self
+	}
+
+	/// Resolves the import `alias` with the definition `target`, e.g. `env.print` with a host function defined under
+	/// another name. A definition of `alias` itself takes precedence.
+	pub fn alias(&mut self, alias: impl Into<Identifier>, target: impl Into<Identifier>) -> &mut Self {
+		self.aliases.insert(alias.into(), target.into());
+		self
+	}
+
+	/// Resolves the imports from the module `alias` with the definitions of the same fields in the module `target`,
+	/// e.g. `wasi_unstable` with `wasi_snapshot_preview1`. Definitions in `alias` itself and the ones of
+	/// [`Linker::alias`] take precedence.
+	pub fn alias_module(&mut self, alias: impl Into<String>, target: impl Into<String>) -> &mut Self {
+		self.module_aliases.insert(alias.into(), target.into());
+		self
+	}
+
+	/// The name under which `definitions` defines the import `name`, which is tried as is, then its
+	/// [alias](Linker::alias) and then in its [aliased module](Linker::alias_module). Returns `name` if none of
+	/// them is defined.
+	fn canonical<T>(&self, definitions: &HashMap<Identifier, T>, name: &Identifier) -> Identifier {
+		let module_alias = self.module_aliases.get(&name.module)
+			.map(|module| Identifier { module: module.clone(), field: name.field.clone() });
+		iter::once(name).chain(self.aliases.get(name)).chain(module_alias.as_ref())
+			.find(|candidate| definitions.contains_key(candidate))
+			.unwrap_or(name)
+			.clone()
+	}
+
+	/// Defines all exported functions of `instance` under the module name `name`.
+	///
+	/// Calls are forwarded to `instance`, which executes them with its own memory and tables.
+	pub fn instance(&mut self, name: &str, instance: &SharedMut<Instance>) -> &mut Self {
+		let exports = shared::try_lock(instance).expect("Instance is not executing").exported_functions();
+		for (field, function_index, signature) in exports {
+			let identifier = Identifier { module: name.to_owned(), field };
+			self.definitions.insert(identifier.clone(), Shared::new(forward(identifier, instance, function_index, signature)));
+		}
+		self
+	}
+
+	/// Loads `module` as side module of the dynamic linking conventions and defines its exported functions under the
+	/// module name `name`, like [`Linker::instance`]. Fails with [`Error::NotASideModule`] if it has no `dylink.0`
+	/// section.
+	///
+	/// The memory and table space which the section declares is allocated after the one of the side modules loaded
+	/// before, and its start is defined as `env.__memory_base` and `env.__table_base`. The imported memory and first
+	/// table are defined or grown to contain the space. After instantiating the module, `__wasm_apply_data_relocs`
+	/// and `__wasm_call_ctors` are called if it exports them. Then the imported memory is defined with the data of
+	/// the module, and its exported `i32` globals, which are addresses relative to `__memory_base`, as the `GOT.mem`
+	/// imports of the modules loaded later.
+	///
+	/// The modules the section declares as needed have to be loaded before. As the instance gets a copy of the
+	/// memory, the modules do not see the stores of each other, and `GOT.func` imports are not defined.
+	pub fn side_module(
+		&mut self, name: &str, module: impl Into<Shared<Module>>, config: Config,
+	) -> Result<SharedMut<Instance>, Error> {
+		let module = module.into();
+		let dylink = module.dylink().map_err(Error::InvalidDylinkSection)?.ok_or(Error::NotASideModule)?;
+		let memory_base = self.memory_base.next_multiple_of(1 << dylink.memory_alignment.min(usize::BITS - 1));
+		let table_base = self.table_base.next_multiple_of(1 << dylink.table_alignment.min(usize::BITS - 1));
+		let memory_end = memory_base + dylink.memory_size;
+		let table_end = table_base + dylink.table_size;
+		self.global(("env", "__memory_base"), Value::I32(memory_base as i32));
+		self.global(("env", "__table_base"), Value::I32(table_base as i32));
+
+		let memory_import = module.memory_blueprint.as_ref()
+			.and_then(|blueprint| Some((blueprint, blueprint.import.as_ref()?)));
+		let memory_name = match memory_import {
+			Some((blueprint, import)) => {
+				let name = self.canonical(&self.memories, import);
+				let memory = match self.memories.entry(name.clone()) {
+					Entry::Occupied(entry) => entry.into_mut(),
+					Entry::Vacant(entry) => entry.insert(Memory::without_segments(blueprint)?),
+				};
+				let pages = memory_end.div_ceil(MEMORY_PAGE_SIZE);
+				if pages > memory.page_limit.end {
+					return Err(Error::IncompatibleImport { name, reason: "memory too small for the side module" });
+				}
+				memory.grow(pages.max(memory.page_size()))?;
+				Some(name)
+			},
+			None => None,
+		};
+		let table_import = module.table_blueprints.first()
+			.and_then(|blueprint| Some((blueprint, blueprint.import.as_ref()?)));
+		if let Some((blueprint, import)) = table_import {
+			let name = self.canonical(&self.tables, import);
+			let table = self.tables.entry(name.clone()).or_insert_with(|| Table::from(blueprint));
+			let init = Value::default_for(&table.element_type);
+			table.grow(table_end.saturating_sub(table.size()), init)
+				.ok_or(Error::IncompatibleImport { name, reason: "table too small for the side module" })?;
+		}
+
+		let mut instance = self.instantiate(Shared::clone(&module), config)?;
+		for function in ["__wasm_apply_data_relocs", "__wasm_call_ctors"] {
+			if let Some(function_index) = module.export_map.function(function) {
+				instance.invoke(function_index, Vec::new())?;
+			}
+		}
+		if let (Some(name), Some(memory)) = (memory_name, instance.memory()) {
+			self.memories.insert(name, memory.clone());
+		}
+		for (global, value) in module.globals.iter().zip(instance.globals()) {
+			match (&global.export_name, value) {
+				(Some(symbol), Value::I32(addr)) if dylink.export_flags(symbol) & DylinkInfo::SYMBOL_TLS == 0 => {
+					let name = Identifier { module: "GOT.mem".to_owned(), field: symbol.clone() };
+					self.globals.insert(name, Value::I32(addr.wrapping_add(memory_base as i32)));
+				},
+				_ => {},
+			}
+		}
+		self.memory_base = memory_end;
+		self.table_base = table_end;
+
+		let instance = shared::shared_mut(instance);
+		self.instance(name, &instance);
+		Ok(instance)
+	}
+
+	/// The definition for the import `name`, which may be defined under an alias.
+	pub fn get(&self, name: &Identifier) -> Option<Shared<Callable>> {
+		self.definitions.get(&self.canonical(&self.definitions, name)).cloned()
+	}
+
+	/// The definition for `import`, which has to have the same signature as the import.
+	pub fn resolve(&self, import: &ExternFunction) -> Result<Shared<Callable>, Error> {
+		let definition = self.get(&import.name)
+			.ok_or_else(|| Error::UnresolvedImport(import.name.clone()))?;
+		if definition.signature() != &import.signature {
+			return Err(Error::ImportSignatureMismatch(Box::new(ImportMismatch {
+				name: import.name.clone(),
+				expected: FunctionSignature::clone(&import.signature),
+				got: FunctionSignature::clone(definition.signature()),
+			})));
+		}
+		Ok(definition)
+	}
+
+	/// The definition for the imported `global`, which has to have the type of the import.
+	pub(crate) fn resolve_global(&self, global: &GlobalBlueprint, name: &Identifier) -> Result<Value, Error> {
+		let value = self.globals.get(&self.canonical(&self.globals, name)).ok_or_else(|| Error::UnresolvedImport(name.clone()))?;
+		match value.ty() == global.ty {
+			true => Ok(value.clone()),
+			false => Err(Error::IncompatibleImport { name: name.clone(), reason: "global of another type" }),
+		}
+	}
+
+	/// The definition for the imported `memory`, which has to have at least its minimum and at most its maximum size.
+	pub(crate) fn resolve_memory(&self, memory: &MemoryBlueprint, name: &Identifier) -> Result<Memory, Error> {
+		let definition = self.memories.get(&self.canonical(&self.memories, name)).ok_or_else(|| Error::UnresolvedImport(name.clone()))?;
+		if definition.page_size() < memory.page_limit.start || definition.page_limit.end > memory.page_limit.end {
+			return Err(Error::IncompatibleImport { name: name.clone(), reason: "memory size out of the limits" });
+		}
+		Ok(definition.clone())
+	}
+
+	/// The definition for the imported `table`, which has to have its element type and fit into its limits.
+	pub(crate) fn resolve_table(&self, table: &TableBlueprint, name: &Identifier) -> Result<Table, Error> {
+		let definition = self.tables.get(&self.canonical(&self.tables, name)).ok_or_else(|| Error::UnresolvedImport(name.clone()))?;
+		if definition.element_type != table.element_type {
+			return Err(Error::IncompatibleImport { name: name.clone(), reason: "table of another element type" });
+		}
+		if definition.size() < table.limit.start || definition.limit.end > table.limit.end {
+			return Err(Error::IncompatibleImport { name: name.clone(), reason: "table size out of the limits" });
+		}
+		Ok(definition.clone())
+	}
+
+	/// Instantiates `module`, failing with [`Error::UnresolvedImport`] if an import is not defined or with
+	/// [`Error::ImportSignatureMismatch`] if its definition has a different signature. Imported globals, memories
+	/// and tables of another type fail with [`Error::IncompatibleImport`].
+	///
+	/// In deterministic mode, imports marked as [nondeterministic](Linker::nondeterministic) fail with
+	/// [`Error::NondeterministicImport`]. A trap of the function of the start section, which runs once the instance
+	/// is initialized, is returned as well.
+	pub fn instantiate(&self, module: impl Into<Shared<Module>>, config: Config) -> Result<Instance, Error> {
+		let module = module.into();
+		let disabled = module.features().difference(config.features);
+		if !disabled.is_empty() {
+			return Err(Error::FeatureNotEnabled(disabled));
+		}
+		// Definitions are marked under their own name, not the one of an alias
+		let check_determinism = |name: &Identifier, canonical: Identifier| {
+			match config.deterministic && self.nondeterministic.contains(&canonical) {
+				true => Err(Error::NondeterministicImport(name.clone())),
+				false => Ok(()),
+			}
+		};
+		let imports = module.functions.imports()
+			.map(|import| {
+				check_determinism(&import.name, self.canonical(&self.definitions, &import.name))?;
+				self.resolve(import)
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let mut externs = Externs::default();
+		for global in &module.globals {
+			if let Some(name) = &global.import {
+				check_determinism(name, self.canonical(&self.globals, name))?;
+				externs.globals.push(self.resolve_global(global, name)?);
+			}
+		}
+		if let Some(memory) = &module.memory_blueprint {
+			if let Some(name) = &memory.import {
+				check_determinism(name, self.canonical(&self.memories, name))?;
+				externs.memory = Some(self.resolve_memory(memory, name)?);
+			}
+		}
+		for table in &module.table_blueprints {
+			if let Some(name) = &table.import {
+				check_determinism(name, self.canonical(&self.tables, name))?;
+				externs.tables.push(self.resolve_table(table, name)?);
+			}
+		}
+		let mut instance = Instance::with_imports(module, config, imports, externs)?;
+		instance.reserve(false)?;
+		instance.run_start_section()?;
+		Ok(instance)
+	}
+}
+
+/// The definitions of the imported globals, memory and tables of a module, in the order of their indices.
+#[derive(Debug, Default)]
+pub(crate) struct Externs {
+	pub globals: Vec<Value>,
+	pub memory: Option<Memory>,
+	pub tables: Vec<Table>,
+}
+
+impl Externs {
+	/// Globals with default values and empty memory and tables of the minimum size, for when no linker defines them.
+	pub(crate) fn placeholders(module: &Module) -> Result<Self, Error> {
+		Ok(Self {
+			globals: module.globals.iter()
+				.filter(|global| global.import.is_some())
+				.map(|global| Value::default_for(&global.ty))
+				.collect(),
+			memory: module.memory_blueprint.as_ref()
+				.filter(|memory| memory.import.is_some())
+				.map(Memory::without_segments)
+				.transpose()?,
+			tables: module.table_blueprints.iter()
+				.filter(|table| table.import.is_some())
+				.map(Table::from)
+				.collect(),
+		})
+	}
+}
+
+/// A host function named `name` which forwards calls to the function with `function_index` of `instance`.
+pub(crate) fn forward(name: Identifier, instance: &SharedMut<Instance>, function_index: usize, signature: Shared<FunctionSignature>) -> Callable {
+	let callee = Shared::clone(instance);
+	let num_params = signature.params.len();
+	let closure = move |caller: &mut InstanceRef| {
+		let mut callee = shared::try_lock(&callee)
+			.ok_or(Error::Trap(TrapKind::Other("re-entrant call into a linked instance")))?;
+		let args = caller.operand_stack.pop_n(num_params)?;
+		callee.invoke(function_index, args)
+	};
+	Callable::RustClosure {
+		name,
+		signature,
+		closure: Box::new(closure),
+	}
+}
\ No newline at end of file
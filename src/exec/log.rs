@@ -0,0 +1,35 @@
+//! The `env.log(ptr, len, level)` import, which lets guests without WASI print messages through `tracing`, see
+//! [`Linker::log`](crate::exec::Linker::log).
+
+use crate::exec::{Error, HostResult};
+use crate::exec::instance::InstanceRef;
+use crate::parse::Type;
+
+/// Module and field of the import.
+pub(crate) const NAME: (&str, &str) = ("env", "log");
+
+/// Parameters of the import, which has no results.
+pub(crate) const PARAMS: [Type; 3] = [Type::I32; 3];
+
+/// `log(ptr, len, level)` emits the UTF-8 message of `len` bytes at `ptr` as `tracing` event with the target `guest`.
+///
+/// The level is 0 or less for error, 1 for warn, 2 for info, 3 for debug and 4 or more for trace. Invalid UTF-8 is
+/// replaced by the replacement character, so that a wrong length does not hide the message.
+pub(crate) fn log(instance: &mut InstanceRef) -> HostResult {
+	let level = instance.operand_stack.pop::<i32>()?;
+	let len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+
+	let mem = instance.memory.as_ref().ok_or(Error::NoMemory)?;
+	let bytes = mem.data().get(ptr..ptr + len)
+		.ok_or(Error::InvalidMemoryArea { addr: ptr..ptr + len, size: mem.data().len() })?;
+	let message = String::from_utf8_lossy(bytes);
+	match level {
+		i32::MIN..=0 => tracing::error!(target: "guest", "{}", message),
+		1 => tracing::warn!(target: "guest", "{}", message),
+		2 => tracing::info!(target: "guest", "{}", message),
+		3 => tracing::debug!(target: "guest", "{}", message),
+		_ => tracing::trace!(target: "guest", "{}", message),
+	}
+	Ok(Vec::new())
+}
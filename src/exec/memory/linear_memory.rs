@@ -0,0 +1,186 @@
+use std::fmt;
+
+#[cfg(not(all(feature = "mmap", unix, target_pointer_width = "64")))]
+pub use vec::LinearMemory;
+#[cfg(all(feature = "mmap", unix, target_pointer_width = "64"))]
+pub use mapped::LinearMemory;
+
+#[cfg(not(all(feature = "mmap", unix, target_pointer_width = "64")))]
+mod vec {
+	use std::io;
+	use std::ops::{Deref, DerefMut};
+	use crate::shared::Shared;
+
+	/// The bytes of a [`Memory`](crate::exec::Memory), which are stored in a [`Vec`] that reallocates when growing.
+	///
+	/// Clones share the bytes until one of them is written, which copies them.
+	/// With the `mmap` feature on 64 bit Unix, the address space for the maximum size is reserved upfront instead.
+	#[derive(Default, PartialEq, Eq, Clone)]
+	pub struct LinearMemory {
+		bytes: Shared<Vec<u8>>,
+	}
+
+	impl LinearMemory {
+		/// Empty bytes, which can grow up to `max_len` bytes.
+		pub(crate) fn new(_max_len: usize) -> io::Result<Self> {
+			Ok(Self::default())
+		}
+
+		/// Grows or shrinks to `len` bytes, or fails if the bytes cannot be allocated.
+		pub(crate) fn resize(&mut self, len: usize) -> io::Result<()> {
+			let bytes = Shared::make_mut(&mut self.bytes);
+			if let Some(additional) = len.checked_sub(bytes.len()) {
+				bytes.try_reserve_exact(additional)
+					.map_err(|error| io::Error::new(io::ErrorKind::OutOfMemory, error))?;
+			}
+			bytes.resize(len, 0);
+			Ok(())
+		}
+	}
+
+	impl Deref for LinearMemory {
+		type Target = [u8];
+
+		fn deref(&self) -> &[u8] {
+			&self.bytes
+		}
+	}
+
+	impl DerefMut for LinearMemory {
+		fn deref_mut(&mut self) -> &mut [u8] {
+			Shared::make_mut(&mut self.bytes).as_mut_slice()
+		}
+	}
+}
+
+#[cfg(all(feature = "mmap", unix, target_pointer_width = "64"))]
+mod mapped {
+	use std::ops::{Deref, DerefMut};
+	use std::ptr::NonNull;
+	use std::{io, ptr, slice};
+
+	/// The bytes of a [`Memory`](crate::exec::Memory) in a range of the address space reserved for the maximum size,
+	/// of which only the first `len` bytes are accessible. Thus, growing is O(1) and the bytes never move, so pointers
+	/// to them stay valid.
+	pub struct LinearMemory {
+		ptr: NonNull<u8>,
+		len: usize,
+		/// Size of the reserved range.
+		reserved: usize,
+		/// Number of bytes at the start which were made accessible, a multiple of the page size of the system.
+		committed: usize,
+	}
+
+	// The bytes are owned like the ones of a `Vec`
+	unsafe impl Send for LinearMemory {}
+	unsafe impl Sync for LinearMemory {}
+
+	impl LinearMemory {
+		/// Reserves the address space for `max_len` bytes, which are inaccessible until growing, or fails if the
+		/// address space cannot be reserved.
+		pub(crate) fn new(max_len: usize) -> io::Result<Self> {
+			// Addresses of WebAssembly are 32 bit, so more cannot be accessed
+			let reserved = max_len.min(u32::MAX as usize + 1);
+			if reserved == 0 {
+				return Ok(Self::default());
+			}
+			// SAFETY: Mapping new anonymous pages does not affect existing memory
+			let ptr = unsafe {
+				libc::mmap(
+					ptr::null_mut(), reserved, libc::PROT_NONE,
+					libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_NORESERVE, -1, 0,
+				)
+			};
+			if ptr == libc::MAP_FAILED {
+				return Err(io::Error::last_os_error());
+			}
+			let ptr = NonNull::new(ptr.cast()).expect("mmap does not return null on success");
+			Ok(Self { ptr, len: 0, reserved, committed: 0 })
+		}
+
+		/// Grows or shrinks to `len` bytes, or fails if `len` exceeds the reserved size or the pages cannot be made
+		/// accessible.
+		pub(crate) fn resize(&mut self, len: usize) -> io::Result<()> {
+			if len > self.reserved {
+				let message = format!("{} bytes exceed the reserved {} bytes", len, self.reserved);
+				return Err(io::Error::new(io::ErrorKind::OutOfMemory, message));
+			}
+			if len > self.committed {
+				// SAFETY: The system page size is always positive
+				let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+				let committed = usize::min(len.div_ceil(page_size) * page_size, self.reserved);
+				// SAFETY: The range lies in the reserved mapping, which starts at a page boundary
+				let result = unsafe {
+					libc::mprotect(self.ptr.as_ptr().cast(), committed, libc::PROT_READ | libc::PROT_WRITE)
+				};
+				if result != 0 {
+					return Err(io::Error::last_os_error());
+				}
+				self.committed = committed;
+			}
+			if len < self.len {
+				// Zero the bytes which are dropped, so that they are zeroed when growing again like the fresh pages
+				// SAFETY: The bytes are committed
+				unsafe { ptr::write_bytes(self.ptr.as_ptr().add(len), 0, self.len - len) };
+			}
+			self.len = len;
+			Ok(())
+		}
+	}
+
+	impl Default for LinearMemory {
+		fn default() -> Self {
+			Self { ptr: NonNull::dangling(), len: 0, reserved: 0, committed: 0 }
+		}
+	}
+
+	impl Drop for LinearMemory {
+		fn drop(&mut self) {
+			if self.reserved > 0 {
+				// SAFETY: The mapping is owned by this and not referenced anymore
+				unsafe { libc::munmap(self.ptr.as_ptr().cast(), self.reserved) };
+			}
+		}
+	}
+
+	impl Deref for LinearMemory {
+		type Target = [u8];
+
+		fn deref(&self) -> &[u8] {
+			// SAFETY: The first `len` bytes are committed and zero-initialized by the system
+			unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+		}
+	}
+
+	impl DerefMut for LinearMemory {
+		fn deref_mut(&mut self) -> &mut [u8] {
+			// SAFETY: The first `len` bytes are committed and zero-initialized by the system
+			unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+		}
+	}
+
+	/// Copies the bytes into a new reservation of the same size.
+	impl Clone for LinearMemory {
+		fn clone(&self) -> Self {
+			let mut clone = Self::new(self.reserved).expect("The address space of the clone can be reserved");
+			clone.resize(self.len).expect("The clone reserves as many bytes as the original");
+			clone.copy_from_slice(self);
+			clone
+		}
+	}
+
+	impl PartialEq for LinearMemory {
+		fn eq(&self, other: &Self) -> bool {
+			**self == **other
+		}
+	}
+
+	impl Eq for LinearMemory {}
+}
+
+impl fmt::Debug for LinearMemory {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		// Do not print the bytes, which may be very large
+		write!(f, "LinearMemory({} bytes)", self.len())
+	}
+}
\ No newline at end of file
@@ -1,37 +1,72 @@
-use std::{fmt, usize};
+use std::fmt;
 use std::ops::Range;
-use crate::parse::MemoryBlueprint;
+use crate::exec::{Error, Value};
+use crate::parse::{DataSegment, MemoryBlueprint};
 pub use mem_object::MemObject;
+pub use linear_memory::LinearMemory;
+pub use snapshot::MemorySnapshot;
 
 mod mem_object;
+mod linear_memory;
+mod snapshot;
 
+/// Size of a page of the memory in bytes, the unit of the limits of memories and `memory.grow`.
+pub const MEMORY_PAGE_SIZE: usize = 65536;
 
-pub const MEMORY_PAGE_SIZE: usize = 4096;
+/// Maximum number of pages of a memory, which is addressed with 32 bit.
+pub const MAX_MEMORY_PAGES: usize = 65536;
 
-#[derive(Default, PartialEq, Eq)]
+#[derive(Default, PartialEq, Eq, Clone)]
 pub struct Memory {
-	pub data: Vec<u8>,
+	pub data: LinearMemory,
 	/// Minimum and maximum page limit.
 	pub page_limit: Range<usize>,
+	/// Whether the memory is shared between threads.
+	pub shared: bool,
 	pub name: Option<String>,
+	/// Ranges which the guest may not write or access at all.
+	protections: Vec<(Range<usize>, Protection)>,
 }
 
-impl From<MemoryBlueprint> for Memory {
-	fn from(blueprint: MemoryBlueprint) -> Self {
+/// Restriction of the accesses of the guest to a range of the memory, see [`Memory::protect`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Protection {
+	/// Loads are allowed, but stores trap.
+	ReadOnly,
+	/// Loads and stores trap, e.g. for guard regions.
+	NoAccess,
+}
+
+impl TryFrom<&MemoryBlueprint> for Memory {
+	type Error = Error;
+
+	/// A memory with the data segments of `blueprint`, except the ones whose offset reads imported globals.
+	fn try_from(blueprint: &MemoryBlueprint) -> Result<Self, Error> {
+		let mut memory = Memory::without_segments(blueprint)?;
+		let segments = blueprint.init.iter().filter(|segment| segment.offset.is_none());
+		memory.copy_segments(segments, &[])?;
+		Ok(memory)
+	}
+}
+
+impl Memory {
+	/// A memory of the minimum size of `blueprint` without its data segments.
+	///
+	/// The maximum is clamped to [`MAX_MEMORY_PAGES`], which also applies to memories without a maximum.
+	pub(crate) fn without_segments(blueprint: &MemoryBlueprint) -> Result<Self, Error> {
+		let max_pages = blueprint.page_limit.end.min(MAX_MEMORY_PAGES);
+		// The maximum of 4 GiB does not fit into the address space of 32 bit hosts
+		let bytes = MEMORY_PAGE_SIZE.saturating_mul(max_pages);
 		let mut memory = Memory {
-			data: Vec::new(),
-			page_limit: blueprint.page_limit.clone(),
-			name: blueprint.export_name
+			data: LinearMemory::new(bytes).map_err(|source| Error::MemoryAllocationFailed { bytes, source })?,
+			page_limit: blueprint.page_limit.start..max_pages,
+			shared: blueprint.shared,
+			name: blueprint.export_name.clone(),
+			protections: Vec::new(),
 		};
 		// Set initial page size
-		memory.grow(blueprint.page_limit.start);
-
-		// Copy init data from data section into memory
-		for init_segment in blueprint.init {
-			let memory_slice_addr = init_segment.addr..init_segment.addr+init_segment.data.len();
-			memory.data[memory_slice_addr].copy_from_slice(&init_segment.data);
-		}
-		memory
+		memory.grow(blueprint.page_limit.start)?;
+		Ok(memory)
 	}
 }
 
@@ -40,20 +75,40 @@ impl fmt::Debug for Memory {
 		// Do not print self.data because it is very large
 		f.debug_struct("Memory")
 			.field("limit", &self.page_limit)
+			.field("shared", &self.shared)
 			.field("name", &self.name)
 			.finish()
 	}
 }
 
 impl Memory {
-	/// Grow the memory to `new_page_size` * [`MEMORY_PAGE_SIZE`] bytes.
-	#[tracing::instrument(skip(self))]
-	pub fn grow(&mut self, new_page_size: usize) {
-		assert!(new_page_size >= self.page_limit.start, "Memory grow too small");
-		assert!(new_page_size <= self.page_limit.end, "Memory grow too large");
+	/// Copies the data segments of a module into this memory, which happens during instantiation, with `globals` as
+	/// the values of the globals of the instance.
+	pub(crate) fn copy_segments<'s>(
+		&mut self, segments: impl IntoIterator<Item = &'s DataSegment>, globals: &[Value],
+	) -> Result<(), Error> {
+		for init_segment in segments {
+			let addr = init_segment.resolve_addr(globals)
+				.expect("The parser checked that the offset evaluates to an i32");
+			let memory_slice_addr = addr..addr.saturating_add(init_segment.data.len());
+			let size = self.data.len();
+			self.data.get_mut(memory_slice_addr.clone())
+				.ok_or(Error::DataSegmentOutOfBounds { addr: memory_slice_addr, size })?
+				.copy_from_slice(&init_segment.data);
+		}
+		Ok(())
+	}
 
-		let new_byte_size = MEMORY_PAGE_SIZE * new_page_size;
-		self.data.resize(new_byte_size, 0);
+	/// Grow the memory to `new_page_size` * [`MEMORY_PAGE_SIZE`] bytes, or fail if this is outside of the page limit
+	/// or the bytes cannot be allocated.
+	#[tracing::instrument(skip(self))]
+	pub fn grow(&mut self, new_page_size: usize) -> Result<(), Error> {
+		if new_page_size < self.page_limit.start || new_page_size > self.page_limit.end {
+			return Err(Error::MemoryPagesOutOfLimit { pages: new_page_size, limit: self.page_limit.clone() });
+		}
+		let bytes = MEMORY_PAGE_SIZE.saturating_mul(new_page_size);
+		self.data.resize(bytes)
+			.map_err(|source| Error::MemoryAllocationFailed { bytes, source })
 	}
 
 	/// Get the current page size.
@@ -66,13 +121,64 @@ impl Memory {
 		&self.data
 	}
 
+	/// Copies the bytes of `range`, cut off at the end of the memory, to compare them with a later state of the memory
+	/// with [`MemorySnapshot::changes`].
+	pub fn snapshot_region(&self, range: Range<usize>) -> MemorySnapshot {
+		let len = self.data.len();
+		let range = range.start.min(len)..range.end.clamp(range.start.min(len), len);
+		MemorySnapshot { start: range.start, data: self.data[range].to_vec() }
+	}
+
+	/// Formats the bytes of `range`, cut off at the end of the memory, as rows of 16 bytes with their offset, hex
+	/// values and ASCII characters, where unprintable bytes are shown as `.`.
+	pub fn hexdump(&self, range: Range<usize>) -> String {
+		let len = self.data.len();
+		let range = range.start.min(len)..range.end.clamp(range.start.min(len), len);
+		let mut dump = String::new();
+		for (row, bytes) in self.data[range.clone()].chunks(16).enumerate() {
+			let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+			let ascii: String = bytes.iter()
+				.map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })
+				.collect();
+			dump += &format!("{:08x}  {:<47}  |{}|\n", range.start + 16 * row, hex.join(" "), ascii);
+		}
+		dump
+	}
+
 	/// Read a [`MemObject`] from an address in memory.
 	pub fn read<T: MemObject>(&self, addr: usize) -> T {
-		T::read_from_mem(&self, addr)
+		T::read_from_mem(self, addr)
 	}
 
 	/// Write a [`MemObject`] to an address in memory.
 	pub fn write<T: MemObject>(&mut self, mem_object: &T, addr: usize) {
 		mem_object.write_to_mem(self, addr)
 	}
+
+	/// Restricts the loads and stores of the guest to `range`, e.g. to protect configuration written by the host
+	/// from being clobbered. Accesses violating the protection trap with [`Error::ProtectedMemoryAccess`], while the
+	/// host can still access the range through [`Memory::data`].
+	///
+	/// [`Error::ProtectedMemoryAccess`]: crate::exec::Error::ProtectedMemoryAccess
+	pub fn protect(&mut self, range: Range<usize>, protection: Protection) {
+		self.protections.push((range, protection));
+	}
+
+	/// Removes the protections of exactly `range`.
+	pub fn unprotect(&mut self, range: Range<usize>) {
+		self.protections.retain(|(protected, _)| *protected != range);
+	}
+
+	/// The protected ranges in the order they were added.
+	pub fn protections(&self) -> &[(Range<usize>, Protection)] {
+		&self.protections
+	}
+
+	/// The protection which a load or, if `write` is set, a store of the guest to `addr` violates.
+	pub(crate) fn violated_protection(&self, addr: &Range<usize>, write: bool) -> Option<Protection> {
+		self.protections.iter()
+			.filter(|(protected, _)| protected.start < addr.end && addr.start < protected.end)
+			.map(|(_, protection)| *protection)
+			.find(|protection| write || *protection == Protection::NoAccess)
+	}
 }
\ No newline at end of file
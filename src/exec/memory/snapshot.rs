@@ -0,0 +1,42 @@
+use std::ops::Range;
+use super::Memory;
+
+/// A copy of a region of a [`Memory`] taken with [`Memory::snapshot_region`], which reports the addresses that changed
+/// between two points of the execution, e.g. before and after a call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemorySnapshot {
+	/// Address of the first byte of the region.
+	pub(super) start: usize,
+	pub(super) data: Vec<u8>,
+}
+
+impl MemorySnapshot {
+	/// The addresses of the region.
+	pub fn range(&self) -> Range<usize> {
+		self.start..self.start + self.data.len()
+	}
+
+	/// The bytes of the region at the time of the snapshot.
+	pub fn data(&self) -> &[u8] {
+		&self.data
+	}
+
+	/// The address ranges of the region whose bytes differ in `memory` now, in ascending order and with adjacent
+	/// changed bytes merged into one range. Bytes beyond the end of `memory` count as changed.
+	pub fn changes(&self, memory: &Memory) -> Vec<Range<usize>> {
+		let len = memory.data().len();
+		let current = &memory.data()[self.start.min(len)..self.range().end.min(len)];
+		let mut changes: Vec<Range<usize>> = Vec::new();
+		for (offset, byte) in self.data.iter().enumerate() {
+			if current.get(offset) == Some(byte) {
+				continue;
+			}
+			let addr = self.start + offset;
+			match changes.last_mut() {
+				Some(last) if last.end == addr => last.end += 1,
+				_ => changes.push(addr..addr + 1),
+			}
+		}
+		changes
+	}
+}
\ No newline at end of file
@@ -1,12 +1,67 @@
-pub mod types;
-pub mod memory;
+pub(crate) mod types;
+pub(crate) mod memory;
 mod instance;
 mod error;
 mod wasi;
 mod operand_stack;
+mod config;
+mod frame;
+mod profiler;
+mod heap_profiler;
+mod stats;
+mod debugger;
+mod hooks;
+mod event_log;
+mod table;
+mod extern_ref_store;
+mod simd;
+mod linker;
+mod log;
+mod engine;
+mod signatures;
+mod store;
+mod limits;
+mod coredump;
+mod environment;
+mod func;
+mod global;
+mod exception;
+mod component;
+pub mod guest;
+#[cfg(feature = "dwarf")]
+mod dwarf;
+#[cfg(feature = "jit")]
+mod jit;
+#[cfg(feature = "emscripten")]
+mod emscripten;
 
 pub use types::*;
-pub use memory::Memory;
-pub use instance::Instance;
+pub use memory::{LinearMemory, MemObject, Memory, MemorySnapshot, Protection, MAX_MEMORY_PAGES, MEMORY_PAGE_SIZE};
+pub use instance::{Instance, InstanceRef, StepOutcome};
 pub use operand_stack::OperandStack;
-pub use error::Error;
\ No newline at end of file
+pub use error::{Error, HostError, ImportMismatch, Mismatch, TrapKind};
+pub use config::Config;
+pub use frame::Frame;
+pub use profiler::{Profiler, FunctionProfile};
+pub use heap_profiler::{HeapProfiler, AllocationSite};
+pub use stats::{Stats, OpcodeStats};
+pub use debugger::{Debugger, DebugContext, DebugAction, Breakpoint};
+pub use hooks::Hooks;
+pub use event_log::{EventLog, HostCall};
+pub use table::Table;
+pub use extern_ref_store::ExternRefStore;
+pub use linker::Linker;
+pub use engine::Engine;
+pub(crate) use signatures::{SignatureId, Signatures};
+pub use store::Store;
+pub use limits::StoreLimits;
+pub use environment::{Environment, Clock, TimeSource, EntropySource};
+pub use func::Func;
+pub use global::GlobalHandle;
+pub use exception::{Exception, Tag};
+pub use guest::{WasmArgs, WasmResults, WasmType};
+pub use component::{ComponentValue, ComponentHostFunction, ComponentLinker, ComponentInstance, canonical};
+pub use wasi::{WasiCtx, WasiPolicy, WasiVersion};
+pub use wasi::{WasiDir, WasiFile, OpenFlags, FileStat, FileType, RealDir, MemDir, MemFile};
+#[cfg(feature = "dwarf")]
+pub use dwarf::{SourceMap, SourceLocation};
\ No newline at end of file
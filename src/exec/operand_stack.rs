@@ -1,28 +1,209 @@
-use crate::exec::error::Error;
+use crate::exec::error::{Error, Mismatch};
 use crate::exec::types;
 
 /// The stack for working with values and instructions.
 ///
 /// WebAssembly is a stack-based language, so values are pushed onto the operand stack,
 /// and instructions pop values off the stack and the result onto the stack.
+///
+/// The values below the floor belong to the callers of the current function and cannot be popped.
 #[derive(Default, PartialEq, Debug, Clone)]
-pub struct OperandStack(Vec<types::Value>);
+pub struct OperandStack {
+	values: Vec<types::Value>,
+	/// Height of the operand stack when the current function was entered.
+	floor: usize,
+	/// Maximum number of values since the stack was created or [`OperandStack::reset_max_len`] was called.
+	max_len: usize,
+}
 
 impl OperandStack {
 	/// Converts `value` into a [`Value`](types::Value) and pushes it onto the operand stack.
 	pub fn push<T: Into<types::Value>>(&mut self, value: T) {
-		self.0.push(value.into());
+		self.values.push(value.into());
+		self.max_len = self.max_len.max(self.values.len());
 	}
 
 	/// Pops a [`Value`](types::Value) off the operand stack and tries to convert in into a `T`.
 	///
-	/// If the stack is empty, an [`Error::PopOnEmptyOperandStack`] is returned.
+	/// If the stack is empty down to the floor, an [`Error::PopOnEmptyOperandStack`] is returned.
 	/// If the conversion fails, an [`Error::StackTypeError`] is returned.
 	pub fn pop<T: TryFrom<types::Value>>(&mut self) -> Result<T, Error> {
-		let value = self.0.pop().ok_or(Error::PopOnEmptyOperandStack)?;
-		T::try_from(value.clone()).map_err(|_| Error::StackTypeError {
+		if self.values.len() <= self.floor {
+			return Err(Error::PopOnEmptyOperandStack);
+		}
+		let value = self.values.pop().expect("The stack is higher than the floor");
+		T::try_from(value.clone()).map_err(|_| Error::StackTypeError(Box::new(Mismatch {
 			got: value,
 			expected: std::any::type_name::<T>(),
-		})
+		})))
+	}
+
+	/// Converts the topmost value into a `T` without popping it, failing like [`OperandStack::pop`].
+	pub fn peek<T: TryFrom<types::Value>>(&mut self) -> Result<T, Error> {
+		convert(self.top_slot()?)
+	}
+
+	/// Replaces the topmost value `operand` with `op(operand)` in place.
+	///
+	/// Unlike popping the operand and pushing the result, the length of the stack is not touched at all, which
+	/// keeps the top of the stack in its slot like in a register.
+	pub(crate) fn unary<T: TryFrom<types::Value>>(&mut self, op: impl FnOnce(T) -> types::Value) -> Result<(), Error> {
+		let slot = self.top_slot()?;
+		*slot = op(convert(slot)?);
+		Ok(())
+	}
+
+	/// Pops `rhs` and replaces `lhs` below it with `op(lhs, rhs)` in place, saving a pop and push compared to
+	/// popping both operands and pushing the result.
+	pub(crate) fn binary<L, R>(&mut self, op: impl FnOnce(L, R) -> types::Value) -> Result<(), Error>
+	where
+		L: TryFrom<types::Value>,
+		R: TryFrom<types::Value>,
+	{
+		let rhs = self.pop::<R>()?;
+		let slot = self.top_slot()?;
+		*slot = op(convert(slot)?, rhs);
+		Ok(())
+	}
+
+	/// The topmost value, if it is above the floor.
+	fn top_slot(&mut self) -> Result<&mut types::Value, Error> {
+		match self.values.len() > self.floor {
+			true => Ok(self.values.last_mut().expect("The stack is higher than the floor")),
+			false => Err(Error::PopOnEmptyOperandStack),
+		}
+	}
+
+	/// Pops the topmost `n` values off the operand stack, keeping their order.
+	pub fn pop_n(&mut self, n: usize) -> Result<Vec<types::Value>, Error> {
+		let start = self.values.len().checked_sub(n)
+			.filter(|start| *start >= self.floor)
+			.ok_or(Error::PopOnEmptyOperandStack)?;
+		Ok(self.values.split_off(start))
+	}
+
+	/// The topmost `n` values, or fewer if the stack is not that high. The top of the stack is the last element.
+	pub fn top(&self, n: usize) -> &[types::Value] {
+		&self.values[self.values.len().saturating_sub(n)..]
+	}
+
+	/// Reserves capacity for at least `additional` more values, so that pushing them does not reallocate.
+	pub(crate) fn reserve(&mut self, additional: usize) {
+		self.values.reserve(additional);
+	}
+
+	/// Number of values on the operand stack.
+	pub fn len(&self) -> usize {
+		self.values.len()
+	}
+
+	/// Maximum number of values on the operand stack so far.
+	pub fn max_len(&self) -> usize {
+		self.max_len
+	}
+
+	/// Starts tracking the maximum number of values from the current one.
+	pub(crate) fn reset_max_len(&mut self) {
+		self.max_len = self.values.len();
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.values.is_empty()
+	}
+
+	/// Replaces a NaN on top of the stack with the canonical NaN, whose bit pattern does not depend on the platform.
+	pub(crate) fn canonicalize_nan(&mut self) {
+		match self.values.last_mut() {
+			Some(types::Value::F32(value)) if value.is_nan() => *value = f32::from_bits(0x7FC0_0000),
+			Some(types::Value::F64(value)) if value.is_nan() => *value = f64::from_bits(0x7FF8_0000_0000_0000),
+			_ => (),
+		}
+	}
+
+	/// Removes all values, e.g. the ones left by an execution which trapped.
+	pub(crate) fn clear(&mut self) {
+		self.values.clear();
+		self.floor = 0;
+	}
+
+	/// Height below which values belong to the callers of the current function.
+	pub(crate) fn floor(&self) -> usize {
+		self.floor
+	}
+
+	/// Protects the values below `floor` from being popped and returns the previous floor.
+	pub(crate) fn set_floor(&mut self, floor: usize) -> usize {
+		std::mem::replace(&mut self.floor, floor)
+	}
+
+	/// Removes all values above `height` except the topmost `arity` values, which are moved down to `height`.
+	///
+	/// This is used when exiting a block, where the block results are kept, but all other values pushed
+	/// by the block are discarded.
+	pub fn unwind(&mut self, height: usize, arity: usize) -> Result<(), Error> {
+		let kept_start = self.values.len().checked_sub(arity)
+			.filter(|kept_start| *kept_start >= height && height >= self.floor)
+			.ok_or(Error::PopOnEmptyOperandStack)?;
+		self.values.drain(height..kept_start);
+		Ok(())
+	}
+}
+
+/// Converts a copy of `value` into a `T`, failing with an [`Error::StackTypeError`].
+fn convert<T: TryFrom<types::Value>>(value: &types::Value) -> Result<T, Error> {
+	T::try_from(value.clone()).map_err(|_| Error::StackTypeError(Box::new(Mismatch {
+		got: value.clone(),
+		expected: std::any::type_name::<T>(),
+	})))
+}
+
+/// The topmost i32 operands of straight-line integer code, which the interpreter keeps in locals instead of on the
+/// [`OperandStack`] while nothing observes the stack, so that most of these instructions neither push nor pop.
+///
+/// The first `len` slots are on top of the operand stack in this order.
+#[derive(Default)]
+pub(crate) struct CachedTop {
+	slots: [i32; 2],
+	len: usize,
+}
+
+impl CachedTop {
+	/// Pushes `value`, spilling the lowest cached value onto `stack` if all slots are taken.
+	#[inline(always)]
+	pub(crate) fn push(&mut self, stack: &mut OperandStack, value: i32) {
+		if self.len == self.slots.len() {
+			stack.push(types::Value::I32(self.slots[0]));
+			self.slots = [self.slots[1], value];
+		} else {
+			self.slots[self.len] = value;
+			self.len += 1;
+		}
+	}
+
+	/// Pops the topmost value, which comes from `stack` if none is cached.
+	#[inline(always)]
+	pub(crate) fn pop(&mut self, stack: &mut OperandStack) -> Result<i32, Error> {
+		match self.len {
+			0 => stack.pop::<i32>(),
+			_ => {
+				self.len -= 1;
+				Ok(self.slots[self.len])
+			},
+		}
+	}
+
+	/// The topmost value, if it is cached.
+	#[inline(always)]
+	pub(crate) fn peek(&self) -> Option<i32> {
+		self.len.checked_sub(1).map(|top| self.slots[top])
+	}
+
+	/// Moves the cached values onto `stack`, which has to happen before anything else accesses it.
+	#[inline(always)]
+	pub(crate) fn flush(&mut self, stack: &mut OperandStack) {
+		for &value in &self.slots[..self.len] {
+			stack.push(types::Value::I32(value));
+		}
+		self.len = 0;
 	}
-}
\ No newline at end of file
+}
@@ -0,0 +1,89 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// Statistics of a single function collected by the [`Profiler`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FunctionProfile {
+	/// How often the function was called.
+	pub calls: u64,
+	/// Instructions executed by the function itself, excluding callees.
+	pub instructions: u64,
+	/// Wall time spent in the function including callees.
+	pub time: Duration,
+}
+
+/// A function call in progress.
+#[derive(Debug)]
+struct ProfileFrame {
+	name: String,
+	start: Instant,
+	instructions: u64,
+}
+
+/// Counts executed instructions and measures wall time per function.
+///
+/// Enabled with [`Config::profile`](crate::exec::Config::profile).
+#[derive(Debug, Default)]
+pub struct Profiler {
+	functions: HashMap<String, FunctionProfile>,
+	/// Self instruction count per call stack, keyed by the function names joined with `;`.
+	stacks: HashMap<String, u64>,
+	frames: Vec<ProfileFrame>,
+}
+
+impl Profiler {
+	pub(crate) fn enter(&mut self, name: String) {
+		self.frames.push(ProfileFrame { name, start: Instant::now(), instructions: 0 });
+	}
+
+	pub(crate) fn instruction(&mut self) {
+		if let Some(frame) = self.frames.last_mut() {
+			frame.instructions += 1;
+		}
+	}
+
+	pub(crate) fn exit(&mut self) {
+		let stack = self.frames.iter().map(|frame| frame.name.as_str()).collect::<Vec<_>>().join(";");
+		let Some(frame) = self.frames.pop() else {
+			return;
+		};
+		*self.stacks.entry(stack).or_default() += frame.instructions;
+
+		let profile = self.functions.entry(frame.name).or_default();
+		profile.calls += 1;
+		profile.instructions += frame.instructions;
+		profile.time += frame.start.elapsed();
+	}
+
+	/// Statistics per function name.
+	pub fn functions(&self) -> &HashMap<String, FunctionProfile> {
+		&self.functions
+	}
+
+	/// A table of all called functions, sorted descending by their self instruction count.
+	pub fn summary(&self) -> String {
+		let mut functions = self.functions.iter().collect::<Vec<_>>();
+		functions.sort_by_key(|(_, profile)| Reverse(profile.instructions));
+
+		let mut summary = format!("{:<40} {:>10} {:>14} {:>14}\n", "function", "calls", "instructions", "time");
+		for (name, profile) in functions {
+			let _ = writeln!(
+				summary, "{:<40} {:>10} {:>14} {:>14?}",
+				name, profile.calls, profile.instructions, profile.time
+			);
+		}
+		summary
+	}
+
+	/// Self instruction counts in the folded stacks format, which can be rendered by flamegraph tools like
+	/// `inferno-flamegraph`.
+	pub fn folded_stacks(&self) -> String {
+		let mut stacks = self.stacks.iter().collect::<Vec<_>>();
+		stacks.sort();
+		stacks.into_iter()
+			.map(|(stack, instructions)| format!("{} {}\n", stack, instructions))
+			.collect()
+	}
+}
\ No newline at end of file
@@ -0,0 +1,33 @@
+use std::collections::HashMap;
+use crate::shared::Shared;
+use crate::exec::FunctionSignature;
+
+/// Identifies a [`FunctionSignature`] interned in [`Signatures`], so that equal signatures have equal ids.
+///
+/// Comparing ids is a single integer comparison instead of comparing the parameters and results, e.g. to check the
+/// type of the callee of `call_indirect`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct SignatureId(u32);
+
+/// One instance of every distinct function signature, each with its [`SignatureId`].
+#[derive(Debug, Default)]
+pub(crate) struct Signatures {
+	ids: HashMap<Shared<FunctionSignature>, SignatureId>,
+}
+
+impl Signatures {
+	/// The id of `signature`, and the instance equal to it which was interned first and should replace it.
+	pub(crate) fn intern(&mut self, signature: &Shared<FunctionSignature>) -> (SignatureId, Shared<FunctionSignature>) {
+		if let Some((interned, &id)) = self.ids.get_key_value(&**signature) {
+			return (id, Shared::clone(interned));
+		}
+		let id = SignatureId(u32::try_from(self.ids.len()).expect("Less than 2^32 distinct signatures"));
+		self.ids.insert(Shared::clone(signature), id);
+		(id, Shared::clone(signature))
+	}
+
+	/// Number of distinct signatures.
+	pub(crate) fn len(&self) -> usize {
+		self.ids.len()
+	}
+}
@@ -0,0 +1,76 @@
+//! Lane-wise helpers for the 128 bit vector instructions.
+//!
+//! Vectors are represented as 16 bytes in little endian order, which matches their layout in memory.
+
+/// A scalar which can be stored in a lane of a vector.
+pub(crate) trait Lane: Copy {
+	/// Size in bytes.
+	const WIDTH: usize;
+
+	/// Reads the lane with index `lane` from `vector`.
+	fn read(vector: &[u8; 16], lane: usize) -> Self;
+
+	/// Writes this value to the lane with index `lane` of `vector`.
+	fn write(self, vector: &mut [u8; 16], lane: usize);
+}
+
+macro_rules! impl_lane {
+	($($ty:ty),*) => {
+		$(
+			impl Lane for $ty {
+				const WIDTH: usize = std::mem::size_of::<$ty>();
+
+				fn read(vector: &[u8; 16], lane: usize) -> Self {
+					let mut bytes = [0u8; std::mem::size_of::<$ty>()];
+					bytes.copy_from_slice(&vector[lane * Self::WIDTH..(lane + 1) * Self::WIDTH]);
+					<$ty>::from_le_bytes(bytes)
+				}
+
+				fn write(self, vector: &mut [u8; 16], lane: usize) {
+					vector[lane * Self::WIDTH..(lane + 1) * Self::WIDTH].copy_from_slice(&self.to_le_bytes());
+				}
+			}
+		)*
+	};
+}
+
+impl_lane!(u8, u16, u32, u64, f32, f64);
+
+/// Number of lanes of type `T` in a vector.
+pub(crate) fn lane_count<T: Lane>() -> usize {
+	16 / T::WIDTH
+}
+
+/// A vector with all lanes set to `value`.
+pub(crate) fn splat<T: Lane>(value: T) -> [u8; 16] {
+	let mut vector = [0u8; 16];
+	for lane in 0..lane_count::<T>() {
+		value.write(&mut vector, lane);
+	}
+	vector
+}
+
+/// `vector` with lane `lane` replaced by `value`.
+pub(crate) fn replace_lane<T: Lane>(mut vector: [u8; 16], lane: usize, value: T) -> [u8; 16] {
+	value.write(&mut vector, lane);
+	vector
+}
+
+/// Combines the lanes of `a` and `b` pairwise with `op`.
+pub(crate) fn map2<T: Lane>(a: [u8; 16], b: [u8; 16], op: impl Fn(T, T) -> T) -> [u8; 16] {
+	let mut result = [0u8; 16];
+	for lane in 0..lane_count::<T>() {
+		op(T::read(&a, lane), T::read(&b, lane)).write(&mut result, lane);
+	}
+	result
+}
+
+/// Compares the lanes of `a` and `b` pairwise with `op`, resulting in lanes with all bits set if `op` returns true.
+pub(crate) fn compare<T: Lane + PartialEq>(a: [u8; 16], b: [u8; 16], op: impl Fn(T, T) -> bool) -> [u8; 16] {
+	let mut result = [0u8; 16];
+	for lane in 0..lane_count::<T>() {
+		let mask = if op(T::read(&a, lane), T::read(&b, lane)) { 0xFF } else { 0x00 };
+		result[lane * T::WIDTH..(lane + 1) * T::WIDTH].fill(mask);
+	}
+	result
+}
\ No newline at end of file
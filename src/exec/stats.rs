@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::mem::{self, Discriminant};
+use crate::exec::Instruction;
+
+/// Statistics about the executions of an [`Instance`](crate::exec::Instance), e.g. for choosing the limits of an
+/// embedding before running it in production.
+///
+/// The statistics accumulate over all executions until [`Instance::reset_stats`](crate::exec::Instance::reset_stats).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Stats {
+	/// Maximum number of frames on the call stack, including the frames of host functions.
+	pub max_call_depth: usize,
+	/// Maximum number of values on the operand stack.
+	pub max_operand_stack: usize,
+	/// Number of interpreted instructions. Host functions and functions compiled by the JIT are not counted.
+	pub instructions: u64,
+	/// Maximum size of the memory in pages, which is its current size, as a memory never shrinks.
+	pub max_memory_pages: usize,
+	/// Number of executed `memory.grow` instructions, including the ones which failed.
+	pub memory_grows: u64,
+	/// Number of interpreted instructions per opcode, empty unless
+	/// [`Config::opcode_stats`](crate::exec::Config::opcode_stats) is enabled.
+	pub opcodes: OpcodeStats,
+}
+
+/// Number of interpreted instructions per opcode, e.g. to find the instructions worth optimizing for a workload.
+#[derive(Default, Clone, PartialEq, Eq)]
+pub struct OpcodeStats {
+	/// The name of the opcode and its count by the variant of the instruction.
+	counts: HashMap<Discriminant<Instruction>, (String, u64)>,
+}
+
+impl OpcodeStats {
+	pub(crate) fn record(&mut self, instruction: &Instruction) {
+		let (_, count) = self.counts.entry(mem::discriminant(instruction))
+			.or_insert_with(|| (instruction.name(), 0));
+		*count += 1;
+	}
+
+	/// Whether no instruction was counted.
+	pub fn is_empty(&self) -> bool {
+		self.counts.is_empty()
+	}
+
+	/// The opcodes with their counts, the most frequent first and ties sorted by name.
+	pub fn histogram(&self) -> Vec<(&str, u64)> {
+		let mut histogram = self.counts.values()
+			.map(|(name, count)| (name.as_str(), *count))
+			.collect::<Vec<_>>();
+		histogram.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then_with(|| a_name.cmp(b_name)));
+		histogram
+	}
+
+	/// The histogram as a table with the share of every opcode in all counted instructions.
+	pub fn summary(&self) -> String {
+		let histogram = self.histogram();
+		let total = histogram.iter().map(|(_, count)| count).sum::<u64>();
+		let mut summary = format!("{:<24} {:>14} {:>8}\n", "opcode", "count", "share");
+		for (name, count) in histogram {
+			let share = count as f64 / total as f64 * 100.0;
+			let _ = writeln!(summary, "{:<24} {:>14} {:>7.2}%", name, count, share);
+		}
+		summary
+	}
+}
+
+impl fmt::Debug for OpcodeStats {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_map().entries(self.histogram()).finish()
+	}
+}
\ No newline at end of file
@@ -0,0 +1,25 @@
+use crate::shared::{self, Shared, SharedMut};
+use crate::exec::{Config, Error, Instance, Linker};
+use crate::parse::Module;
+
+/// Owns instances, so that the exports of one instance can satisfy the imports of another one.
+///
+/// Instances are shared as [`SharedMut`], because a [`Linker`] forwards calls to them.
+#[derive(Debug, Default)]
+pub struct Store {
+	instances: Vec<SharedMut<Instance>>,
+}
+
+impl Store {
+	/// Instantiates `module` with the imports defined in `linker` and adds the instance to the store.
+	pub fn instantiate(&mut self, linker: &Linker, module: impl Into<Shared<Module>>, config: Config) -> Result<SharedMut<Instance>, Error> {
+		let instance = shared::shared_mut(linker.instantiate(module, config)?);
+		self.instances.push(Shared::clone(&instance));
+		Ok(instance)
+	}
+
+	/// All instances in the order they were created.
+	pub fn instances(&self) -> &[SharedMut<Instance>] {
+		&self.instances
+	}
+}
\ No newline at end of file
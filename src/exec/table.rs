@@ -0,0 +1,62 @@
+use std::ops::Range;
+use crate::exec::Value;
+use crate::parse::{ElementSegment, TableBlueprint, Type};
+
+/// A vector of references, accessed by the table instructions and `call_indirect`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Table {
+	pub elements: Vec<Value>,
+	/// Minimum and maximum number of elements.
+	pub limit: Range<usize>,
+	/// Either [`Type::FuncRef`] or [`Type::ExternRef`].
+	pub element_type: Type,
+	pub name: Option<String>,
+}
+
+impl From<&TableBlueprint> for Table {
+	fn from(blueprint: &TableBlueprint) -> Self {
+		Table {
+			elements: vec![Value::default_for(&blueprint.element_type); blueprint.limit.start],
+			limit: blueprint.limit.clone(),
+			element_type: blueprint.element_type,
+			name: blueprint.export_name.clone(),
+		}
+	}
+}
+
+impl Table {
+	/// Current number of elements.
+	pub fn size(&self) -> usize {
+		self.elements.len()
+	}
+
+	pub fn get(&self, index: usize) -> Option<&Value> {
+		self.elements.get(index)
+	}
+
+	pub fn get_mut(&mut self, index: usize) -> Option<&mut Value> {
+		self.elements.get_mut(index)
+	}
+
+	/// Appends `delta` copies of `init` and returns the previous size,
+	/// or [`None`] if the table would exceed its maximum size.
+	pub fn grow(&mut self, delta: usize, init: Value) -> Option<usize> {
+		let old_size = self.size();
+		let new_size = old_size.checked_add(delta)?;
+		if new_size > self.limit.end {
+			return None;
+		}
+		self.elements.resize(new_size, init);
+		Some(old_size)
+	}
+}
+
+/// The reference values of an element segment.
+pub(crate) fn element_values(segment: &ElementSegment) -> Vec<Value> {
+	segment.init.iter()
+		.map(|&function_index| match segment.element_type {
+			Type::ExternRef => Value::ExternRef(None),
+			_ => Value::FuncRef(function_index),
+		})
+		.collect()
+}
\ No newline at end of file
@@ -0,0 +1,56 @@
+/// Value type and access size of an atomic memory instruction.
+///
+/// Narrow accesses are zero-extended to the value type when loaded and wrapped when stored.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AtomicWidth {
+	I32,
+	I64,
+	I32U8,
+	I32U16,
+	I64U8,
+	I64U16,
+	I64U32,
+}
+
+impl AtomicWidth {
+	/// Number of bytes accessed in memory.
+	pub fn bytes(&self) -> usize {
+		match self {
+			AtomicWidth::I32U8 | AtomicWidth::I64U8 => 1,
+			AtomicWidth::I32U16 | AtomicWidth::I64U16 => 2,
+			AtomicWidth::I32 | AtomicWidth::I64U32 => 4,
+			AtomicWidth::I64 => 8,
+		}
+	}
+
+	/// Whether the value on the operand stack is an i64.
+	pub fn is_i64(&self) -> bool {
+		matches!(self, AtomicWidth::I64 | AtomicWidth::I64U8 | AtomicWidth::I64U16 | AtomicWidth::I64U32)
+	}
+}
+
+/// Operation of an atomic read-modify-write instruction.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AtomicRmwOp {
+	Add,
+	Sub,
+	And,
+	Or,
+	Xor,
+	/// Exchange, i.e. store the operand and return the old value.
+	Xchg,
+}
+
+impl AtomicRmwOp {
+	/// Combines the `old` value in memory with `operand`.
+	pub fn apply(&self, old: u64, operand: u64) -> u64 {
+		match self {
+			AtomicRmwOp::Add => old.wrapping_add(operand),
+			AtomicRmwOp::Sub => old.wrapping_sub(operand),
+			AtomicRmwOp::And => old & operand,
+			AtomicRmwOp::Or => old | operand,
+			AtomicRmwOp::Xor => old ^ operand,
+			AtomicRmwOp::Xchg => operand,
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,32 @@
+use crate::shared::Shared;
+use crate::exec::error::Error;
+use crate::exec::types::*;
+use crate::parse::Type;
+
+/// The type of a block, loop or if instruction.
+///
+/// <https://webassembly.github.io/spec/core/binary/instructions.html#control-instructions>
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum BlockType {
+	/// No parameters and no results.
+	Empty,
+	/// No parameters and a single result.
+	Value(Type),
+	/// Parameters and results as declared by the signature with this index in the type section.
+	TypeIndex(usize),
+}
+
+impl BlockType {
+	/// Number of parameters and number of results of the block.
+	pub fn arity(&self, types: &[Shared<FunctionSignature>]) -> Result<(usize, usize), Error> {
+		match self {
+			BlockType::Empty => Ok((0, 0)),
+			BlockType::Value(_) => Ok((0, 1)),
+			BlockType::TypeIndex(index) => {
+				let signature = types.get(*index)
+					.ok_or(Error::TypeIndexOutOfBounds { index: *index, len: types.len() })?;
+				Ok((signature.params.len(), signature.results.len()))
+			},
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,84 @@
+use crate::exec::types::*;
+use crate::parse::Type;
+
+/// A constant expression, which initializes a global or computes the offset of a segment.
+///
+/// Besides constants, `ref.null`, `ref.func` and `global.get`, the extended constant expressions proposal allows
+/// `add`, `sub` and `mul` of `i32` and `i64` values.
+///
+/// <https://github.com/WebAssembly/extended-const>
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct ConstExpr {
+	pub instructions: Vec<Instruction>,
+}
+
+impl ConstExpr {
+	pub fn new(instructions: Vec<Instruction>) -> Self {
+		Self { instructions }
+	}
+
+	/// Evaluates the expression with `globals` as the values of the globals it may refer to.
+	///
+	/// Returns `None` if the expression contains a non-constant instruction, refers to a global outside of `globals`,
+	/// has operands of the wrong type or does not produce exactly one value.
+	pub fn eval(&self, globals: &[Value]) -> Option<Value> {
+		let mut stack = Vec::new();
+		for instruction in &self.instructions {
+			let value = match instruction {
+				Instruction::I32Const(value) => Value::I32(*value),
+				Instruction::I64Const(value) => Value::I64(*value),
+				Instruction::F32Const(value) => Value::F32(*value),
+				Instruction::F64Const(value) => Value::F64(*value),
+				Instruction::V128Const(value) => Value::V128(*value),
+				Instruction::RefNull(ty) => Value::default_for(ty),
+				Instruction::RefFunc(function_index) => Value::FuncRef(Some(*function_index)),
+				Instruction::GlobalGet(index) => globals.get(*index)?.clone(),
+				Instruction::I32Add | Instruction::I32Sub | Instruction::I32Mul => {
+					let rhs = i32::try_from(stack.pop()?).ok()?;
+					let lhs = i32::try_from(stack.pop()?).ok()?;
+					Value::I32(match instruction {
+						Instruction::I32Add => lhs.wrapping_add(rhs),
+						Instruction::I32Sub => lhs.wrapping_sub(rhs),
+						_ => lhs.wrapping_mul(rhs),
+					})
+				},
+				Instruction::I64Add | Instruction::I64Sub | Instruction::I64Mul => {
+					let rhs = i64::try_from(stack.pop()?).ok()?;
+					let lhs = i64::try_from(stack.pop()?).ok()?;
+					Value::I64(match instruction {
+						Instruction::I64Add => lhs.wrapping_add(rhs),
+						Instruction::I64Sub => lhs.wrapping_sub(rhs),
+						_ => lhs.wrapping_mul(rhs),
+					})
+				},
+				_ => return None,
+			};
+			stack.push(value);
+		}
+		match stack.len() {
+			1 => stack.pop(),
+			_ => None,
+		}
+	}
+}
+
+impl TryFrom<Value> for ConstExpr {
+	/// References to host objects have no constant expression and are returned as error.
+	type Error = Value;
+
+	/// The expression producing `value`, e.g. `i32.const 42` for `Value::I32(42)`.
+	fn try_from(value: Value) -> Result<Self, Value> {
+		let instruction = match value {
+			Value::I32(value) => Instruction::I32Const(value),
+			Value::I64(value) => Instruction::I64Const(value),
+			Value::F32(value) => Instruction::F32Const(value),
+			Value::F64(value) => Instruction::F64Const(value),
+			Value::V128(value) => Instruction::V128Const(value),
+			Value::FuncRef(Some(function_index)) => Instruction::RefFunc(function_index),
+			Value::FuncRef(None) => Instruction::RefNull(Type::FuncRef),
+			Value::ExternRef(None) => Instruction::RefNull(Type::ExternRef),
+			value => return Err(value),
+		};
+		Ok(Self::new(vec![instruction]))
+	}
+}
\ No newline at end of file
@@ -0,0 +1,150 @@
+use crate::exec::{BlockType, Instruction, WasmFunction};
+use crate::parse::Module;
+
+/// Properties of a [`WasmFunction`] derived from its body, which the interpreter uses to prepare calls of it.
+///
+/// They are computed when parsing or building a module and have to be recomputed with
+/// [`Module::update_function_metadata`] after changing a body. Instructions after an unconditional branch are never
+/// executed and not taken into account.
+#[derive(PartialEq, Eq, Debug, Default, Clone, Copy)]
+pub struct FunctionMetadata {
+	/// Upper bound of the height of the operand stack above the height when the function was entered, with which the
+	/// stack is reserved so that pushing values never reallocates. It is not exact, as the module is not validated.
+	pub max_stack_height: usize,
+	/// Number of parameters and declared locals.
+	pub num_locals: usize,
+	/// Whether the function loads, stores, grows or queries the size of the memory itself, not counting the
+	/// functions it calls.
+	pub uses_memory: bool,
+	/// Whether the function gets or sets globals itself, not counting the functions it calls.
+	pub uses_globals: bool,
+}
+
+impl FunctionMetadata {
+	/// The metadata of `function` of `module`.
+	pub fn new(module: &Module, function: &WasmFunction) -> Self {
+		let mut analysis = Analysis { module, height: 0, metadata: FunctionMetadata::default() };
+		analysis.sequence(&function.body);
+		FunctionMetadata {
+			num_locals: function.signature.params.len() + function.locals.len(),
+			..analysis.metadata
+		}
+	}
+}
+
+struct Analysis<'m> {
+	module: &'m Module,
+	height: usize,
+	metadata: FunctionMetadata,
+}
+
+impl Analysis<'_> {
+	/// Analyzes `instructions` up to the first unconditional branch.
+	fn sequence(&mut self, instructions: &[Instruction]) {
+		for instruction in instructions {
+			self.metadata.uses_memory |= instruction.memory_access().is_some()
+				|| matches!(instruction, Instruction::MemorySize | Instruction::MemoryGrow | Instruction::MemoryInit(_)
+					| Instruction::MemoryCopy | Instruction::MemoryFill);
+			self.metadata.uses_globals |= matches!(instruction, Instruction::GlobalGet(_) | Instruction::GlobalSet(_));
+			let (pops, pushes) = match instruction {
+				Instruction::Block { block_type, instructions } | Instruction::Loop { block_type, instructions } => {
+					self.block(block_type, &[instructions]);
+					continue;
+				},
+				Instruction::If { block_type, if_instructions, else_instructions } => {
+					self.pop(1);
+					self.block(block_type, &[if_instructions, else_instructions]);
+					continue;
+				},
+				Instruction::Try { block_type, instructions, catches, .. } => {
+					// A catch clause starts with the values of the exception instead of the parameters
+					let (params, _) = self.arity(block_type);
+					for catch in catches {
+						let values = catch.tag
+							.and_then(|tag| self.module.tags.get(tag))
+							.map_or(0, |signature| signature.params.len());
+						let height = self.height;
+						self.height = self.height.saturating_sub(params);
+						self.push(values);
+						self.sequence(&catch.instructions);
+						self.height = height;
+					}
+					self.block(block_type, &[instructions]);
+					continue;
+				},
+				Instruction::Unreachable | Instruction::Br { .. } | Instruction::BrTable { .. }
+				| Instruction::Return | Instruction::ReturnCall { .. } | Instruction::ReturnCallIndirect { .. }
+				| Instruction::Throw(_) | Instruction::Rethrow(_) => return,
+				Instruction::Call { function_index } => match self.module.functions.get(*function_index) {
+					Some(function) => (function.signature().params.len(), function.signature().results.len()),
+					None => (0, 0),
+				},
+				Instruction::CallIndirect { type_index, .. } => match self.module.types.get(*type_index) {
+					// The table index is popped before the arguments
+					Some(signature) => (signature.params.len() + 1, signature.results.len()),
+					None => (1, 0),
+				},
+				instruction => operands(instruction),
+			};
+			self.pop(pops);
+			self.push(pushes);
+		}
+	}
+
+	/// Analyzes a block whose body is one of `bodies`, after which its results replace its parameters.
+	fn block(&mut self, block_type: &BlockType, bodies: &[&Vec<Instruction>]) {
+		let (params, results) = self.arity(block_type);
+		let height = self.height;
+		for body in bodies {
+			self.height = height;
+			self.sequence(body);
+		}
+		self.height = height.saturating_sub(params);
+		self.push(results);
+	}
+
+	fn arity(&self, block_type: &BlockType) -> (usize, usize) {
+		block_type.arity(&self.module.types).unwrap_or((0, 0))
+	}
+
+	fn pop(&mut self, values: usize) {
+		self.height = self.height.saturating_sub(values);
+	}
+
+	fn push(&mut self, values: usize) {
+		self.height += values;
+		self.metadata.max_stack_height = self.metadata.max_stack_height.max(self.height);
+	}
+}
+
+/// Number of values popped and pushed by an instruction without nested instructions or signatures.
+fn operands(instruction: &Instruction) -> (usize, usize) {
+	use Instruction::*;
+	match instruction {
+		Nop | DataDrop(_) | ElemDrop(_) | AtomicFence | Extension => (0, 0),
+		LocalGet(_) | GlobalGet(_) | MemorySize | TableSize(_) | RefNull(_) | RefFunc(_)
+		| I32Const(_) | I64Const(_) | F32Const(_) | F64Const(_) | V128Const(_) => (0, 1),
+		Drop | BrIf { .. } | LocalSet(_) | GlobalSet(_) => (1, 0),
+		TableSet(_) | I32Store(_) | I64Store(_) | F32Store(_) | F64Store(_) | I32Store8(_) | I32Store16(_)
+		| I64Store8(_) | I64Store16(_) | I64Store32(_) | V128Store(_) | AtomicStore(..) => (2, 0),
+		MemoryInit(_) | MemoryCopy | MemoryFill | TableInit { .. } | TableCopy { .. } | TableFill(_) => (3, 0),
+		I32Eq | I32Ne | I32LtS | I32LtU | I32GtS | I32GtU | I32LeS | I32LeU | I32GeS | I32GeU
+		| I64Eq | I64Ne | I64LtS | I64LtU | I64GtS | I64GtU | I64LeS | I64LeU | I64GeS | I64GeU
+		| F32Eq | F32Ne | F32Lt | F32Gt | F32Le | F32Ge | F64Eq | F64Ne | F64Lt | F64Gt | F64Le | F64Ge
+		| I32Add | I32Sub | I32Mul | I32DivS | I32DivU | I32RemS | I32RemU | I32And | I32Or | I32Xor
+		| I32Shl | I32ShrS | I32ShrU | I32Rotl | I32Rotr
+		| I64Add | I64Sub | I64Mul | I64DivS | I64DivU | I64RemS | I64RemU | I64And | I64Or | I64Xor
+		| I64Shl | I64ShrS | I64ShrU | I64Rotl | I64Rotr
+		| F32Add | F32Sub | F32Mul | F32Div | F32Min | F32Max | F32Copysign
+		| F64Add | F64Sub | F64Mul | F64Div | F64Min | F64Max | F64Copysign
+		| TableGrow(_) | I8x16Shuffle(_) | I8x16Swizzle | I8x16ReplaceLane(_) | I16x8ReplaceLane(_)
+		| I32x4ReplaceLane(_) | I64x2ReplaceLane(_) | F32x4ReplaceLane(_) | F64x2ReplaceLane(_)
+		| I8x16Eq | I16x8Eq | I32x4Eq | V128And | V128AndNot | V128Or | V128Xor
+		| I8x16Add | I8x16Sub | I16x8Add | I16x8Sub | I16x8Mul | I32x4Add | I32x4Sub | I32x4Mul
+		| I64x2Add | I64x2Sub | I64x2Mul | MemoryAtomicNotify(_) | AtomicRmw(..) => (2, 1),
+		Select | SelectValueType | V128Bitselect | MemoryAtomicWait32(_) | MemoryAtomicWait64(_)
+		| AtomicCmpxchg(..) => (3, 1),
+		// Loads, unary operators and conversions replace their operand
+		_ => (1, 1),
+	}
+}
\ No newline at end of file
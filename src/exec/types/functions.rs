@@ -1,28 +1,140 @@
 use std::fmt;
-use std::rc::Rc;
+use crate::shared::Shared;
 use crate::exec::instance::InstanceRef;
 use crate::exec::types::*;
 use crate::parse::{ParsingError, Type};
+use crate::shared::MaybeSendSync;
 
+/// The function index space of a module: the imported functions followed by the ones defined by the module, in
+/// the order in which calls, exports and element segments index them.
 #[derive(Default, Debug, PartialEq)]
 pub struct Functions {
-	pub imports: Vec<ExternFunction>,
-	pub wasm: Vec<WasmFunction>,
+	entries: Vec<FuncKind>,
+	num_imports: usize,
+}
+
+/// An entry of the function index space.
+#[derive(Debug, PartialEq)]
+pub enum FuncKind {
+	Imported(ExternFunction),
+	/// Shared with the instances of the module, so that instantiating does not copy function bodies.
+	Local(Shared<WasmFunction>),
+}
+
+impl FuncKind {
+	pub fn signature(&self) -> &Shared<FunctionSignature> {
+		match self {
+			FuncKind::Imported(function) => &function.signature,
+			FuncKind::Local(function) => &function.signature,
+		}
+	}
 }
 
 impl Functions {
-	pub fn get_wasm_function(&mut self, function_index: usize) -> Result<&mut WasmFunction, ParsingError> {
-		let wasm_len = self.wasm.len();
-		let imports_len = self.imports.len();
-		let total_len = wasm_len + imports_len;
+	/// Number of functions, counting imported ones.
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	pub fn num_imports(&self) -> usize {
+		self.num_imports
+	}
+
+	pub fn num_local(&self) -> usize {
+		self.entries.len() - self.num_imports
+	}
+
+	/// The function with `index`.
+	pub fn get(&self, index: usize) -> Option<&FuncKind> {
+		self.entries.get(index)
+	}
+
+	/// All functions in the order of their indices.
+	pub fn iter(&self) -> std::slice::Iter<'_, FuncKind> {
+		self.entries.iter()
+	}
+
+	/// The imported functions, whose indices start at 0.
+	pub fn imports(&self) -> impl Iterator<Item = &ExternFunction> + Clone {
+		self.entries[..self.num_imports].iter().filter_map(|function| match function {
+			FuncKind::Imported(function) => Some(function),
+			FuncKind::Local(_) => None,
+		})
+	}
+
+	/// The functions defined by the module, whose indices start at [`Functions::num_imports`].
+	pub fn local(&self) -> impl Iterator<Item = &Shared<WasmFunction>> + Clone {
+		self.entries[self.num_imports..].iter().filter_map(|function| match function {
+			FuncKind::Local(function) => Some(function),
+			FuncKind::Imported(_) => None,
+		})
+	}
+
+	pub fn local_mut(&mut self) -> impl Iterator<Item = &mut Shared<WasmFunction>> {
+		self.entries[self.num_imports..].iter_mut().filter_map(|function| match function {
+			FuncKind::Local(function) => Some(function),
+			FuncKind::Imported(_) => None,
+		})
+	}
+
+	/// The signatures of all functions in the order of their indices.
+	pub(crate) fn signatures_mut(&mut self) -> impl Iterator<Item = &mut Shared<FunctionSignature>> {
+		self.entries.iter_mut().map(|function| match function {
+			FuncKind::Imported(function) => &mut function.signature,
+			FuncKind::Local(function) => &mut Shared::make_mut(function).signature,
+		})
+	}
+
+	/// The defined function with `index`, which fails if `index` refers to an imported function or is out of bounds.
+	pub fn get_local_mut(&mut self, index: usize) -> Result<&mut WasmFunction, ParsingError> {
+		let len = self.entries.len();
+		match self.entries.get_mut(index) {
+			Some(FuncKind::Local(function)) => Ok(Shared::make_mut(function)),
+			Some(FuncKind::Imported(_)) => Err(ParsingError::ImportedFunction(index)),
+			None => Err(ParsingError::FunctionIndexOutOfBounds { index, len }),
+		}
+	}
+
+	/// Adds an imported function after the existing imports and returns its index.
+	///
+	/// The indices of all defined functions are shifted by one, references to them have to be adjusted by the caller.
+	pub fn push_import(&mut self, function: ExternFunction) -> usize {
+		let index = self.num_imports;
+		self.entries.insert(index, FuncKind::Imported(function));
+		self.num_imports += 1;
+		for function in self.local_mut() {
+			Shared::make_mut(function).index += 1;
+		}
+		index
+	}
 
-		let function_index = function_index.checked_sub(self.imports.len())
-			.ok_or(ParsingError::WasmFunctionOutOfRange { index: function_index, wasm_len, imports_len, total_len })?;
-		self.wasm.get_mut(function_index)
-			.ok_or(ParsingError::WasmFunctionOutOfRange { index: function_index,  wasm_len, imports_len, total_len })
+	/// Adds a defined function at the end of the index space, assigns it its index and returns the index.
+	pub fn push_local(&mut self, mut function: WasmFunction) -> usize {
+		let index = self.entries.len();
+		function.index = index;
+		self.entries.push(FuncKind::Local(Shared::new(function)));
+		index
 	}
 }
 
+impl<'a> IntoIterator for &'a Functions {
+	type Item = &'a FuncKind;
+	type IntoIter = std::slice::Iter<'a, FuncKind>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
+
+/// A host function implemented as closure, which has to be `Send + Sync` with the `sync` feature.
+pub trait HostClosure: Fn(&mut InstanceRef) -> HostResult + MaybeSendSync {}
+
+impl<T: Fn(&mut InstanceRef) -> HostResult + MaybeSendSync> HostClosure for T {}
 
 /// Something that can be called inside the context of a runtime. This is either a WebAssembly function or a
 /// Rust function (used for extern functions like WASI).
@@ -30,16 +142,20 @@ impl Functions {
 /// Other ideas that would avoid this enum are:
 /// * Implementing `Fn` for WebAssembly functions. However, implementing `Fn` for custom types is not stable yet.
 /// * Creating a closure for all WebAssembly functions, which saves their instructions. This implies that every function
-/// has to be `box`ed, which is inefficient.
+///   has to be `box`ed, which is inefficient.
 pub enum Callable {
-	WasmFunction(WasmFunction),
+	WasmFunction(Shared<WasmFunction>),
 	RustClosure {
 		name: Identifier,
-		closure: Box<dyn Fn(&mut InstanceRef) -> ExecutionResult>
+		/// The parameters the closure pops off the operand stack and the results it returns.
+		signature: Shared<FunctionSignature>,
+		closure: Box<dyn HostClosure>
 	},
 	RustFunction {
 		name: Identifier,
-		function: fn(&mut InstanceRef) -> ExecutionResult
+		/// The parameters the function pops off the operand stack and the results it returns.
+		signature: Shared<FunctionSignature>,
+		function: fn(&mut InstanceRef) -> HostResult
 	},
 }
 
@@ -47,15 +163,17 @@ impl fmt::Debug for Callable {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		match self {
 			Callable::WasmFunction(function) => function.fmt(f),
-			Callable::RustFunction { name, .. } => {
+			Callable::RustFunction { name, signature, .. } => {
 				f.debug_struct("RustFunction")
 					.field("name", name)
+					.field("signature", signature)
 					.field("function", &"<opaque>")
 					.finish()
 			},
-			Callable::RustClosure { name, .. } => {
+			Callable::RustClosure { name, signature, .. } => {
 				f.debug_struct("RustClosure")
 					.field("name", name)
+					.field("signature", signature)
 					.field("closure", &"<opaque>")
 					.finish()
 			},
@@ -64,6 +182,13 @@ impl fmt::Debug for Callable {
 }
 
 impl Callable {
+	pub fn signature(&self) -> &Shared<FunctionSignature> {
+		match self {
+			Callable::WasmFunction(function) => &function.signature,
+			Callable::RustFunction { signature, .. } | Callable::RustClosure { signature, .. } => signature,
+		}
+	}
+
 	fn name(&self) -> String {
 		match self {
 			Callable::WasmFunction(function) => {
@@ -87,14 +212,22 @@ impl fmt::Display for Callable {
 #[derive(Debug, PartialEq, Eq)]
 pub struct ExternFunction {
 	pub name: Identifier,
-	pub signature: Rc<FunctionSignature>,
+	pub signature: Shared<FunctionSignature>,
 }
 
 #[derive(PartialEq, Debug, Default, Clone)]
 pub struct WasmFunction {
 	pub index: usize,
 	pub export_name: Option<String>,
-	pub signature: Rc<FunctionSignature>,
+	pub signature: Shared<FunctionSignature>,
 	pub locals: Vec<Type>,
 	pub body: Vec<Instruction>,
+	/// Position of the function body, starting with the declaration of the locals, in the binary.
+	pub body_offset: usize,
+	/// Position in the binary of every instruction in `body`, indexed like [`Breakpoint::offset`].
+	///
+	/// [`Breakpoint::offset`]: crate::exec::Breakpoint::offset
+	pub offsets: Vec<usize>,
+	/// Properties derived from `body`.
+	pub metadata: FunctionMetadata,
 }
\ No newline at end of file
@@ -1,8 +1,6 @@
 use std::fmt;
-use std::rc::Rc;
-use crate::exec::types::*;
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct Identifier {
 	pub module: String,
 	pub field: String,
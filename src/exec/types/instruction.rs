@@ -1,23 +1,38 @@
-use std::rc::Rc;
 use crate::exec::types::*;
+use crate::parse::{Features, Type};
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Instruction {
 	Unreachable,
 	Nop,
-	Block { block_type: u8, instructions: Vec<Instruction> },
-	Loop { block_type: u8, instructions: Vec<Instruction> },
-	If { block_type: u8, if_instructions: Vec<Instruction>, else_instructions: Vec<Instruction> },
-	Br { label_index: u8 },
-	BrIf { label_index: u8 },
-	BrTable { label_indexes: Vec<u8> },
+	Block { block_type: BlockType, instructions: Vec<Instruction> },
+	Loop { block_type: BlockType, instructions: Vec<Instruction> },
+	If { block_type: BlockType, if_instructions: Vec<Instruction>, else_instructions: Vec<Instruction> },
+	Br { label_index: u32 },
+	BrIf { label_index: u32 },
+	/// Branches to the label at the index popped from the stack, or to `default` if it is out of bounds.
+	BrTable { label_indexes: Vec<u32>, default: u32 },
 	Return,
 	Call { function_index: usize },
 	CallIndirect { table_index: usize, type_index: usize },
+	/// `return_call` of the tail call proposal, which replaces the frame of the caller with the one of the callee.
+	ReturnCall { function_index: usize },
+	/// `return_call_indirect` of the tail call proposal.
+	ReturnCallIndirect { table_index: usize, type_index: usize },
 
-	RefNull,
+	/// `try` of the exception handling proposal. Exceptions thrown by `instructions` are handled by the first
+	/// matching clause of `catches`, or rethrown at the label `delegate`.
+	Try { block_type: BlockType, instructions: Vec<Instruction>, catches: Vec<Catch>, delegate: Option<u32> },
+	/// `throw` with the tag index.
+	Throw(usize),
+	/// `rethrow` with the label index of the catch clause, whose exception is thrown again.
+	Rethrow(u32),
+
+	/// `ref.null` with the reference type.
+	RefNull(Type),
 	RefIsNull,
-	RefFunc,
+	/// `ref.func` with the function index.
+	RefFunc(usize),
 
 	Drop,
 	Select,
@@ -45,7 +60,7 @@ pub enum Instruction {
 	I64Load8s(MemArg),
 	I64Load8u(MemArg),
 	I64Load16s(MemArg),
-	I66Load16u(MemArg),
+	I64Load16u(MemArg),
 	I64Load32s(MemArg),
 	I64Load32u(MemArg),
 	I32Store(MemArg),
@@ -57,6 +72,8 @@ pub enum Instruction {
 	I64Store8(MemArg),
 	I64Store16(MemArg),
 	I64Store32(MemArg),
+	MemorySize,
+	MemoryGrow,
 
 
 	I32Const(i32),
@@ -200,4 +217,204 @@ pub enum Instruction {
 	I64Extend8S,
 	I64Extend16S,
 	I64Extend32S,
+
+	I32TruncSatF32S,
+	I32TruncSatF32U,
+	I32TruncSatF64S,
+	I32TruncSatF64U,
+	I64TruncSatF32S,
+	I64TruncSatF32U,
+	I64TruncSatF64S,
+	I64TruncSatF64U,
+
+	/// `memory.init` with the data segment index.
+	MemoryInit(usize),
+	/// `data.drop` with the data segment index.
+	DataDrop(usize),
+	MemoryCopy,
+	MemoryFill,
+	TableInit { table_index: usize, element_index: usize },
+	ElemDrop(usize),
+	TableCopy { dst_table_index: usize, src_table_index: usize },
+	TableGrow(usize),
+	TableSize(usize),
+	TableFill(usize),
+
+	V128Load(MemArg),
+	V128Store(MemArg),
+	V128Const([u8; 16]),
+	/// Lane indices into the concatenation of both operands.
+	I8x16Shuffle([u8; 16]),
+	I8x16Swizzle,
+	I8x16Splat,
+	I16x8Splat,
+	I32x4Splat,
+	I64x2Splat,
+	F32x4Splat,
+	F64x2Splat,
+	I8x16ExtractLaneS(u8),
+	I8x16ExtractLaneU(u8),
+	I8x16ReplaceLane(u8),
+	I16x8ExtractLaneS(u8),
+	I16x8ExtractLaneU(u8),
+	I16x8ReplaceLane(u8),
+	I32x4ExtractLane(u8),
+	I32x4ReplaceLane(u8),
+	I64x2ExtractLane(u8),
+	I64x2ReplaceLane(u8),
+	F32x4ExtractLane(u8),
+	F32x4ReplaceLane(u8),
+	F64x2ExtractLane(u8),
+	F64x2ReplaceLane(u8),
+	I8x16Eq,
+	I16x8Eq,
+	I32x4Eq,
+	V128Not,
+	V128And,
+	V128AndNot,
+	V128Or,
+	V128Xor,
+	V128Bitselect,
+	V128AnyTrue,
+	I8x16AllTrue,
+	I8x16Bitmask,
+	I8x16Add,
+	I8x16Sub,
+	I16x8Add,
+	I16x8Sub,
+	I16x8Mul,
+	I32x4Add,
+	I32x4Sub,
+	I32x4Mul,
+	I64x2Add,
+	I64x2Sub,
+	I64x2Mul,
+
+	MemoryAtomicNotify(MemArg),
+	MemoryAtomicWait32(MemArg),
+	MemoryAtomicWait64(MemArg),
+	AtomicFence,
+	AtomicLoad(AtomicWidth, MemArg),
+	AtomicStore(AtomicWidth, MemArg),
+	AtomicRmw(AtomicRmwOp, AtomicWidth, MemArg),
+	AtomicCmpxchg(AtomicWidth, MemArg),
+}
+
+/// A `catch` clause of a [`Instruction::Try`], which handles the exceptions with the tag index `tag`, or all
+/// exceptions for a `catch_all` without a tag.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Catch {
+	pub tag: Option<usize>,
+	pub instructions: Vec<Instruction>,
+}
+
+impl Instruction {
+	/// Number of instructions including this one and all nested ones, i.e. how many instructions this instruction
+	/// spans in a function body when counted in the order they appear in the binary.
+	pub fn nested_len(&self) -> usize {
+		let nested: &[&[Instruction]] = match self {
+			Instruction::Block { instructions, .. } => &[instructions],
+			Instruction::Loop { instructions, .. } => &[instructions],
+			Instruction::If { if_instructions, else_instructions, .. } => &[if_instructions, else_instructions],
+			Instruction::Try { instructions, catches, .. } => {
+				let catches_len = catches.iter()
+					.flat_map(|catch| catch.instructions.iter())
+					.map(Instruction::nested_len)
+					.sum::<usize>();
+				return 1 + catches_len + instructions.iter().map(Instruction::nested_len).sum::<usize>();
+			},
+			_ => &[],
+		};
+		1 + nested.iter()
+			.flat_map(|instructions| instructions.iter())
+			.map(Instruction::nested_len)
+			.sum::<usize>()
+	}
+
+	/// The name of the variant of this instruction without its immediates, e.g. `I32Add` or `Block`.
+	pub fn name(&self) -> String {
+		let debug = format!("{:?}", self);
+		let end = debug.find(|c: char| !c.is_alphanumeric()).unwrap_or(debug.len());
+		debug[..end].to_owned()
+	}
+
+	/// Whether this is a float instruction whose NaN results have a nondeterministic bit pattern according to the
+	/// specification. Other float instructions like `f32.neg` or `f32.reinterpret_i32` only operate on the bits.
+	pub fn is_float_arithmetic(&self) -> bool {
+		matches!(self,
+			Instruction::F32Ceil | Instruction::F32Floor | Instruction::F32Trunc | Instruction::F32Nearest
+			| Instruction::F32Sqrt | Instruction::F32Add | Instruction::F32Sub | Instruction::F32Mul | Instruction::F32Div
+			| Instruction::F32Min | Instruction::F32Max | Instruction::F32DemoteF64
+			| Instruction::F64Ceil | Instruction::F64Floor | Instruction::F64Trunc | Instruction::F64Nearest
+			| Instruction::F64Sqrt | Instruction::F64Add | Instruction::F64Sub | Instruction::F64Mul | Instruction::F64Div
+			| Instruction::F64Min | Instruction::F64Max | Instruction::F64PromoteF32
+		)
+	}
+
+	/// The proposals this instruction requires on its own, not including the ones of nested instructions.
+	pub fn required_features(&self) -> Features {
+		match self {
+			Instruction::Block { block_type: BlockType::TypeIndex(_), .. }
+			| Instruction::Loop { block_type: BlockType::TypeIndex(_), .. }
+			| Instruction::If { block_type: BlockType::TypeIndex(_), .. }
+			| Instruction::Try { block_type: BlockType::TypeIndex(_), .. } => Features::MULTI_VALUE,
+			Instruction::Block { block_type: BlockType::Value(Type::V128), .. }
+			| Instruction::Loop { block_type: BlockType::Value(Type::V128), .. }
+			| Instruction::If { block_type: BlockType::Value(Type::V128), .. }
+			| Instruction::Try { block_type: BlockType::Value(Type::V128), .. } => Features::SIMD,
+			Instruction::ReturnCall { .. } | Instruction::ReturnCallIndirect { .. } => Features::TAIL_CALL,
+			Instruction::CallIndirect { table_index, .. } if *table_index != 0 => Features::REFERENCE_TYPES,
+			Instruction::RefNull(_) | Instruction::RefIsNull | Instruction::RefFunc(_) | Instruction::SelectValueType
+			| Instruction::TableGet(_) | Instruction::TableSet(_) | Instruction::TableGrow(_) | Instruction::TableSize(_)
+			| Instruction::TableFill(_) => Features::REFERENCE_TYPES,
+			Instruction::MemoryInit(_) | Instruction::DataDrop(_) | Instruction::MemoryCopy | Instruction::MemoryFill
+			| Instruction::TableInit { .. }
+			| Instruction::ElemDrop(_) | Instruction::TableCopy { .. } => Features::BULK_MEMORY,
+			Instruction::MemoryAtomicNotify(_) | Instruction::MemoryAtomicWait32(_) | Instruction::MemoryAtomicWait64(_)
+			| Instruction::AtomicFence | Instruction::AtomicLoad(..) | Instruction::AtomicStore(..)
+			| Instruction::AtomicRmw(..) | Instruction::AtomicCmpxchg(..) => Features::THREADS,
+			Instruction::V128Load(_) | Instruction::V128Store(_) | Instruction::V128Const(_) | Instruction::I8x16Shuffle(_)
+			| Instruction::I8x16Swizzle | Instruction::I8x16Splat | Instruction::I16x8Splat | Instruction::I32x4Splat
+			| Instruction::I64x2Splat | Instruction::F32x4Splat | Instruction::F64x2Splat
+			| Instruction::I8x16ExtractLaneS(_) | Instruction::I8x16ExtractLaneU(_) | Instruction::I8x16ReplaceLane(_)
+			| Instruction::I16x8ExtractLaneS(_) | Instruction::I16x8ExtractLaneU(_) | Instruction::I16x8ReplaceLane(_)
+			| Instruction::I32x4ExtractLane(_) | Instruction::I32x4ReplaceLane(_)
+			| Instruction::I64x2ExtractLane(_) | Instruction::I64x2ReplaceLane(_)
+			| Instruction::F32x4ExtractLane(_) | Instruction::F32x4ReplaceLane(_)
+			| Instruction::F64x2ExtractLane(_) | Instruction::F64x2ReplaceLane(_)
+			| Instruction::I8x16Eq | Instruction::I16x8Eq | Instruction::I32x4Eq
+			| Instruction::V128Not | Instruction::V128And | Instruction::V128AndNot | Instruction::V128Or
+			| Instruction::V128Xor | Instruction::V128Bitselect | Instruction::V128AnyTrue
+			| Instruction::I8x16AllTrue | Instruction::I8x16Bitmask
+			| Instruction::I8x16Add | Instruction::I8x16Sub | Instruction::I16x8Add | Instruction::I16x8Sub
+			| Instruction::I16x8Mul | Instruction::I32x4Add | Instruction::I32x4Sub | Instruction::I32x4Mul
+			| Instruction::I64x2Add | Instruction::I64x2Sub | Instruction::I64x2Mul => Features::SIMD,
+			_ => Features::empty(),
+		}
+	}
+
+	/// The memory argument and the number of accessed bytes if this instruction accesses the linear memory.
+	pub fn memory_access(&self) -> Option<(&MemArg, usize)> {
+		match self {
+			Instruction::I32Load8s(mem_arg) | Instruction::I32Load8u(mem_arg)
+			| Instruction::I64Load8s(mem_arg) | Instruction::I64Load8u(mem_arg)
+			| Instruction::I32Store8(mem_arg) | Instruction::I64Store8(mem_arg) => Some((mem_arg, 1)),
+			Instruction::I32Load16s(mem_arg) | Instruction::I32Load16u(mem_arg)
+			| Instruction::I64Load16s(mem_arg) | Instruction::I64Load16u(mem_arg)
+			| Instruction::I32Store16(mem_arg) | Instruction::I64Store16(mem_arg) => Some((mem_arg, 2)),
+			Instruction::I32Load(mem_arg) | Instruction::F32Load(mem_arg)
+			| Instruction::I64Load32s(mem_arg) | Instruction::I64Load32u(mem_arg)
+			| Instruction::I32Store(mem_arg) | Instruction::F32Store(mem_arg) | Instruction::I64Store32(mem_arg)
+			| Instruction::MemoryAtomicNotify(mem_arg) | Instruction::MemoryAtomicWait32(mem_arg) => Some((mem_arg, 4)),
+			Instruction::I64Load(mem_arg) | Instruction::F64Load(mem_arg)
+			| Instruction::I64Store(mem_arg) | Instruction::F64Store(mem_arg)
+			| Instruction::MemoryAtomicWait64(mem_arg) => Some((mem_arg, 8)),
+			Instruction::V128Load(mem_arg) | Instruction::V128Store(mem_arg) => Some((mem_arg, 16)),
+			Instruction::AtomicLoad(width, mem_arg) | Instruction::AtomicStore(width, mem_arg)
+			| Instruction::AtomicRmw(_, width, mem_arg) | Instruction::AtomicCmpxchg(width, mem_arg) => {
+				Some((mem_arg, width.bytes()))
+			},
+			_ => None,
+		}
+	}
 }
\ No newline at end of file
@@ -1,5 +1,20 @@
+use std::ops::Range;
+
 #[derive(Default, Debug, PartialEq, Eq, Clone)]
 pub struct MemArg {
-	pub align: usize,
-	pub offset: usize,
+	/// The alignment hint as exponent of two.
+	pub align: u32,
+	pub offset: u32,
+}
+
+impl MemArg {
+	/// The bytes accessed by an access of `bytes` bytes at the dynamic address `base`, or `None` if they are not
+	/// addressable on this platform.
+	///
+	/// The offset is added with 33 bit precision like in the specification, so the address never wraps around.
+	pub fn effective_address(&self, base: u32, bytes: usize) -> Option<Range<usize>> {
+		let start = base as u64 + self.offset as u64;
+		let end = start + bytes as u64;
+		Some(usize::try_from(start).ok()?..usize::try_from(end).ok()?)
+	}
 }
\ No newline at end of file
@@ -1,20 +1,32 @@
+mod atomic;
+mod block_type;
+mod const_expr;
 mod function_signature;
 mod functions;
+mod function_metadata;
 mod identifier;
 mod instruction;
 mod mem_arg;
 mod value;
 
+pub use atomic::{AtomicWidth, AtomicRmwOp};
+pub use block_type::BlockType;
+pub use const_expr::ConstExpr;
 pub use function_signature::{FunctionSignature};
-pub use functions::{Callable, ExternFunction, WasmFunction, Functions};
+pub use functions::{Callable, ExternFunction, FuncKind, WasmFunction, Functions};
+pub use function_metadata::FunctionMetadata;
 pub use identifier::Identifier;
-pub use instruction::Instruction;
+pub use instruction::{Catch, Instruction};
 pub use mem_arg::MemArg;
-pub use value::Value;
+pub use value::{Value, ExternRef};
 use crate::exec::error::Error;
 
 pub type ExecutionResult = Result<(), Error>;
 
+/// The results of a host function, which the interpreter pushes onto the operand stack after checking their number
+/// against the signature of the function.
+pub type HostResult = Result<Vec<Value>, Error>;
+
 
 
 
@@ -1,6 +1,9 @@
-use std::rc::Rc;
-use crate::exec::error::Error;
-use crate::exec::types::*;
+use crate::exec::error::{Error, Mismatch};
+use crate::parse::Type;
+
+/// An opaque reference to a host object, passed to the guest as `externref`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub struct ExternRef(pub usize);
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Value {
@@ -8,24 +11,71 @@ pub enum Value {
 	I64(i64),
 	F32(f32),
 	F64(f64),
-	V128,
-	FuncRef,
-	ExternRef,
+	/// A 128 bit vector as little endian bytes.
+	V128([u8; 16]),
+	/// A reference to the function with the index, or `ref.null func`.
+	FuncRef(Option<usize>),
+	/// A reference to a host object, or `ref.null extern`.
+	ExternRef(Option<ExternRef>),
 	Function,
 	Const,
 	Var
 }
 
+impl Value {
+	/// The zero value of `ty`, which is the initial value of locals.
+	pub fn default_for(ty: &Type) -> Value {
+		match ty {
+			Type::I32 => Value::I32(0),
+			Type::I64 => Value::I64(0),
+			Type::F32 => Value::F32(0.0),
+			Type::F64 => Value::F64(0.0),
+			Type::V128 => Value::V128([0; 16]),
+			Type::FuncRef => Value::FuncRef(None),
+			Type::ExternRef => Value::ExternRef(None),
+			Type::Function => Value::Function,
+			Type::Const => Value::Const,
+			Type::Var => Value::Var,
+		}
+	}
+
+	/// The type of this value.
+	pub fn ty(&self) -> Type {
+		match self {
+			Value::I32(_) => Type::I32,
+			Value::I64(_) => Type::I64,
+			Value::F32(_) => Type::F32,
+			Value::F64(_) => Type::F64,
+			Value::V128(_) => Type::V128,
+			Value::FuncRef(_) => Type::FuncRef,
+			Value::ExternRef(_) => Type::ExternRef,
+			Value::Function => Type::Function,
+			Value::Const => Type::Const,
+			Value::Var => Type::Var,
+		}
+	}
+
+	/// Whether this is a null reference.
+	/// Returns [`None`] for non-reference values.
+	pub fn is_null(&self) -> Option<bool> {
+		match self {
+			Value::FuncRef(reference) => Some(reference.is_none()),
+			Value::ExternRef(reference) => Some(reference.is_none()),
+			_ => None,
+		}
+	}
+}
+
 impl TryFrom<Value> for i32 {
 	type Error = Error;
 
 	fn try_from(value: Value) -> Result<Self, Self::Error> {
 		match value {
 			Value::I32(val) => Ok(val),
-			got => Err(Error::StackTypeError {
+			got => Err(Error::StackTypeError(Box::new(Mismatch {
 				got,
 				expected: "i32",
-			}),
+			}))),
 		}
 	}
 }
@@ -36,10 +86,10 @@ impl TryFrom<Value> for u32 {
 	fn try_from(value: Value) -> Result<Self, Self::Error> {
 		match value {
 			Value::I32(val) => Ok(val as u32),
-			got => Err(Error::StackTypeError {
+			got => Err(Error::StackTypeError(Box::new(Mismatch {
 				got,
 				expected: "i32(u32)",
-			}),
+			}))),
 		}
 	}
 }
@@ -50,10 +100,10 @@ impl TryFrom<Value> for i64 {
 	fn try_from(value: Value) -> Result<Self, Self::Error> {
 		match value {
 			Value::I64(val) => Ok(val),
-			got => Err(Error::StackTypeError {
+			got => Err(Error::StackTypeError(Box::new(Mismatch {
 				got,
 				expected: "i64",
-			}),
+			}))),
 		}
 	}
 }
@@ -64,54 +114,124 @@ impl TryFrom<Value> for u64 {
 	fn try_from(value: Value) -> Result<Self, Self::Error> {
 		match value {
 			Value::I64(val) => Ok(val as u64),
-			got => Err(Error::StackTypeError {
+			got => Err(Error::StackTypeError(Box::new(Mismatch {
 				got,
 				expected: "i64(u64)",
-			}),
+			}))),
 		}
 	}
 }
 
-impl TryFrom<Value> for usize {
+impl TryFrom<Value> for f32 {
+	type Error = Error;
+
+	fn try_from(value: Value) -> Result<Self, Self::Error> {
+		match value {
+			Value::F32(val) => Ok(val),
+			got => Err(Error::StackTypeError(Box::new(Mismatch {
+				got,
+				expected: "f32",
+			}))),
+		}
+	}
+}
+
+impl TryFrom<Value> for f64 {
+	type Error = Error;
+
+	fn try_from(value: Value) -> Result<Self, Self::Error> {
+		match value {
+			Value::F64(val) => Ok(val),
+			got => Err(Error::StackTypeError(Box::new(Mismatch {
+				got,
+				expected: "f64",
+			}))),
+		}
+	}
+}
+
+impl TryFrom<Value> for [u8; 16] {
 	type Error = Error;
 
 	fn try_from(value: Value) -> Result<Self, Self::Error> {
 		match value {
-			Value::I64(val) => Ok(val as usize),
-			got => Err(Error::StackTypeError {
+			Value::V128(val) => Ok(val),
+			got => Err(Error::StackTypeError(Box::new(Mismatch {
 				got,
-				expected: "usize",
-			}),
+				expected: "v128",
+			}))),
 		}
 	}
 }
 
-impl Into<Value> for i32 {
-	fn into(self) -> Value {
-		Value::I32(self)
+/// Accepts an unsigned i32 like a 32 bit pointer, or an unsigned i64 if it fits into the pointer size of the host.
+impl TryFrom<Value> for usize {
+	type Error = Error;
+
+	fn try_from(value: Value) -> Result<Self, Self::Error> {
+		let converted = match &value {
+			Value::I32(val) => usize::try_from(*val as u32).ok(),
+			Value::I64(val) => usize::try_from(*val as u64).ok(),
+			_ => None,
+		};
+		converted.ok_or(Error::StackTypeError(Box::new(Mismatch {
+			got: value,
+			expected: "usize",
+		})))
+	}
+}
+
+impl From<i32> for Value {
+	fn from(val: i32) -> Self {
+		Value::I32(val)
+	}
+}
+
+impl From<u32> for Value {
+	fn from(val: u32) -> Self {
+		Value::I32(val as i32)
+	}
+}
+
+impl From<i64> for Value {
+	fn from(val: i64) -> Self {
+		Value::I64(val)
+	}
+}
+
+impl From<u64> for Value {
+	fn from(val: u64) -> Self {
+		Value::I64(val as i64)
 	}
 }
 
-impl Into<Value> for u32 {
-	fn into(self) -> Value {
-		Value::I32(self as i32)
+impl From<f32> for Value {
+	fn from(val: f32) -> Self {
+		Value::F32(val)
 	}
 }
 
-impl Into<Value> for i64 {
-	fn into(self) -> Value {
-		Value::I64(self)
+impl From<f64> for Value {
+	fn from(val: f64) -> Self {
+		Value::F64(val)
 	}
 }
 
-impl Into<Value> for u64 {
-	fn into(self) -> Value {
-		Value::I64(self as i64)
+impl From<[u8; 16]> for Value {
+	fn from(val: [u8; 16]) -> Self {
+		Value::V128(val)
 	}
 }
 
-impl Into<Value> for usize {
-	fn into(self) -> Value {
-		Value::I64(self as i64)
+/// Becomes an i32 on hosts with 32 bit pointers and an i64 otherwise.
+impl From<usize> for Value {
+	#[cfg(target_pointer_width = "32")]
+	fn from(val: usize) -> Self {
+		Value::I32(val as i32)
+	}
+
+	#[cfg(not(target_pointer_width = "32"))]
+	fn from(val: usize) -> Self {
+		Value::I64(val as i64)
 	}
 }
\ No newline at end of file
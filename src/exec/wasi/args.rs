@@ -0,0 +1,89 @@
+//! The functions of WASI preview 1 for the command line arguments and environment variables passed in with
+//! [`WasiCtx::set_args`](crate::exec::WasiCtx::set_args) and [`WasiCtx::set_env`](crate::exec::WasiCtx::set_env).
+//!
+//! The sizes reported by `args_sizes_get` and `environ_sizes_get` are exactly the ones written by `args_get` and
+//! `environ_get`, so that the guest can allocate the buffers from them. Exceeding the limit of the [`WasiPolicy`]
+//! fails both with the errno `2big` instead of truncating.
+
+use crate::exec::{HostResult, Value};
+use crate::exec::instance::InstanceRef;
+use super::*;
+
+/// `args_sizes_get(argc_ptr, argv_buf_size_ptr) -> errno`
+pub fn args_sizes_get(instance: &mut InstanceRef) -> HostResult {
+	let buf_size_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let count_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+
+	let args = instance.wasi.args();
+	let size = instance.wasi.policy().limit_args(size(args));
+	let mem = instance.memory.as_mut().unwrap();
+	Ok(vec![Value::I32(sizes_get(&mut mem.data, args, size, count_ptr, buf_size_ptr))])
+}
+
+/// `args_get(argv_ptr, argv_buf_ptr) -> errno`
+pub fn args_get(instance: &mut InstanceRef) -> HostResult {
+	let buf_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let ptrs_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+
+	let args = instance.wasi.args();
+	let size = instance.wasi.policy().limit_args(size(args));
+	let mem = instance.memory.as_mut().unwrap();
+	Ok(vec![Value::I32(get(&mut mem.data, args, size, ptrs_ptr, buf_ptr))])
+}
+
+/// `environ_sizes_get(environc_ptr, environ_buf_size_ptr) -> errno`
+pub fn environ_sizes_get(instance: &mut InstanceRef) -> HostResult {
+	let buf_size_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let count_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+
+	let environ = instance.wasi.environ();
+	let size = instance.wasi.policy().limit_environ(size(environ));
+	let mem = instance.memory.as_mut().unwrap();
+	Ok(vec![Value::I32(sizes_get(&mut mem.data, environ, size, count_ptr, buf_size_ptr))])
+}
+
+/// `environ_get(environ_ptr, environ_buf_ptr) -> errno`
+///
+/// Writes the variables as `KEY=VALUE`.
+pub fn environ_get(instance: &mut InstanceRef) -> HostResult {
+	let buf_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let ptrs_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+
+	let environ = instance.wasi.environ();
+	let size = instance.wasi.policy().limit_environ(size(environ));
+	let mem = instance.memory.as_mut().unwrap();
+	Ok(vec![Value::I32(get(&mut mem.data, environ, size, ptrs_ptr, buf_ptr))])
+}
+
+/// Size of `strings` with their terminating zeros, which may not exceed the 32 bit sizes of the guest.
+fn size(strings: &[String]) -> Option<u32> {
+	strings.iter().try_fold(0u32, |size, string| size.checked_add(u32::try_from(string.len()).ok()?)?.checked_add(1))
+}
+
+/// Writes the number of `strings` and their `size`.
+fn sizes_get(data: &mut [u8], strings: &[String], size: Result<u32, i32>, count_ptr: usize, size_ptr: usize) -> i32 {
+	match size {
+		Ok(size) => write_bytes(data, count_ptr, &(strings.len() as u32).to_le_bytes())
+			.and_then(|_| write_bytes(data, size_ptr, &size.to_le_bytes()))
+			.map_or(ERRNO_FAULT, |_| 0),
+		Err(errno) => errno,
+	}
+}
+
+/// Writes `strings` with terminating zeros one after another to `buf_ptr`, and a pointer to each of them to
+/// `ptrs_ptr`.
+fn get(data: &mut [u8], strings: &[String], size: Result<u32, i32>, ptrs_ptr: usize, buf_ptr: usize) -> i32 {
+	if let Err(errno) = size {
+		return errno;
+	}
+	let mut ptrs = Vec::with_capacity(strings.len() * 4);
+	let mut buf = Vec::new();
+	for string in strings {
+		ptrs.extend_from_slice(&((buf_ptr + buf.len()) as u32).to_le_bytes());
+		buf.extend_from_slice(string.as_bytes());
+		buf.push(0);
+	}
+	write_bytes(data, buf_ptr, &buf)
+		.and_then(|_| write_bytes(data, ptrs_ptr, &ptrs))
+		.map_or(ERRNO_FAULT, |_| 0)
+}
\ No newline at end of file
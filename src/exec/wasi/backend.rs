@@ -0,0 +1,121 @@
+//! The storage behind the filesystem functions of WASI, so that hosts can back the files of the guest with the
+//! filesystem of the host ([`RealDir`](crate::exec::RealDir)), memory ([`MemDir`](crate::exec::MemDir)) or their
+//! own storage like archives or databases.
+
+use std::fmt;
+use std::io::{self, SeekFrom};
+use std::path::{Path, PathBuf};
+use crate::shared::MaybeSendSync;
+
+/// A file opened by the guest or passed to it with [`WasiCtx::preopen_file`](crate::exec::WasiCtx::preopen_file).
+///
+/// Only the positioned accesses, seeking, resizing and the metadata have to be implemented, as reading and writing
+/// at the current position is based on them.
+pub trait WasiFile: fmt::Debug + MaybeSendSync {
+	/// Reads into `buf` starting at `offset`, without using or changing the current position.
+	fn read_at(&mut self, buf: &mut [u8], offset: u64) -> io::Result<usize>;
+
+	/// Writes `buf` starting at `offset`, without using or changing the current position.
+	fn write_at(&mut self, buf: &[u8], offset: u64) -> io::Result<usize>;
+
+	/// Changes the current position and returns the new one.
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64>;
+
+	/// Truncates the file or extends it with zeros to `len` bytes.
+	fn set_len(&mut self, len: u64) -> io::Result<()>;
+
+	fn stat(&self) -> io::Result<FileStat>;
+
+	/// Reads into `buf` at the current position and advances it.
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let position = self.seek(SeekFrom::Current(0))?;
+		let read = self.read_at(buf, position)?;
+		self.seek(SeekFrom::Start(position + read as u64))?;
+		Ok(read)
+	}
+
+	/// Writes `buf` at the current position and advances it.
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let position = self.seek(SeekFrom::Current(0))?;
+		let written = self.write_at(buf, position)?;
+		self.seek(SeekFrom::Start(position + written as u64))?;
+		Ok(written)
+	}
+}
+
+/// A directory the guest accesses files in, e.g. one passed to it with
+/// [`WasiCtx::preopen`](crate::exec::WasiCtx::preopen).
+///
+/// Paths are relative to the directory and only consist of normal components, i.e. neither `.`, `..` nor a root,
+/// which the WASI functions already resolved. An empty path refers to the directory itself. Implementations have to
+/// make sure that the guest cannot leave the directory in other ways, e.g. through symlinks.
+pub trait WasiDir: fmt::Debug + MaybeSendSync {
+	/// Opens the file at `path`, which is created according to `flags`.
+	fn open_file(&self, path: &Path, flags: OpenFlags) -> io::Result<Box<dyn WasiFile>>;
+
+	/// Opens the directory at `path`, which the guest can access paths relative to.
+	fn open_dir(&self, path: &Path) -> io::Result<Box<dyn WasiDir>>;
+
+	/// The metadata of the file or directory at `path`, or of the target if it is a symlink and `follow` is set.
+	fn stat(&self, path: &Path, follow: bool) -> io::Result<FileStat>;
+
+	/// The names and types of the entries of the directory at `path`.
+	fn read_dir(&self, path: &Path) -> io::Result<Vec<(String, FileType)>>;
+
+	fn create_dir(&self, path: &Path) -> io::Result<()>;
+
+	/// Removes the file or symlink at `path`, but not a directory.
+	fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+	/// Moves the file or directory at `from` to `to`, replacing a file there.
+	fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+	/// The target of the symlink at `path`.
+	fn read_link(&self, path: &Path) -> io::Result<PathBuf>;
+}
+
+/// How [`WasiDir::open_file`] opens a file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OpenFlags {
+	pub read: bool,
+	pub write: bool,
+	/// Create the file if it does not exist.
+	pub create: bool,
+	/// Fail if the file already exists, together with `create`.
+	pub exclusive: bool,
+	/// Truncate the file to zero bytes.
+	pub truncate: bool,
+}
+
+/// The metadata of a file, which the WASI functions return to the guest as `filestat`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FileStat {
+	/// Device containing the file.
+	pub dev: u64,
+	/// Number of the file on its device.
+	pub ino: u64,
+	pub file_type: FileType,
+	/// Number of hard links to the file.
+	pub nlink: u64,
+	/// Size in bytes.
+	pub size: u64,
+	/// Times of the last access, modification and status change in nanoseconds since the Unix epoch.
+	pub atim: u64,
+	pub mtim: u64,
+	pub ctim: u64,
+}
+
+/// The `filetype` of WASI.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FileType {
+	#[default]
+	Unknown = 0,
+	BlockDevice = 1,
+	CharacterDevice = 2,
+	Directory = 3,
+	RegularFile = 4,
+	SocketDgram = 5,
+	SocketStream = 6,
+	SymbolicLink = 7,
+}
\ No newline at end of file
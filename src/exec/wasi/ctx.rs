@@ -0,0 +1,182 @@
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use crate::shared::MaybeSendSync;
+use super::{Output, RealDir, Stdin, WasiDir, WasiFile, WasiPolicy};
+
+/// The host resources available to the WASI functions of an instance, see
+/// [`Instance::set_wasi`](crate::exec::Instance::set_wasi).
+///
+/// The file descriptors 0, 1 and 2 are stdin, stdout and stderr of the host, except for the ones piped with
+/// [`set_stdin`](Self::set_stdin), [`set_stdout`](Self::set_stdout) and [`set_stderr`](Self::set_stderr).
+#[derive(Debug, Default)]
+pub struct WasiCtx {
+	/// The file descriptors opened by the host or the guest, starting at 3.
+	descriptors: BTreeMap<u32, Descriptor>,
+	args: Vec<String>,
+	/// The environment variables as `KEY=VALUE`.
+	environ: Vec<String>,
+	stdin: Stdin,
+	stdout: Output,
+	stderr: Output,
+	policy: WasiPolicy,
+	/// The last bytes written to stderr, at most [`STDERR_TAIL_LEN`].
+	stderr_tail: Vec<u8>,
+}
+
+/// Number of bytes of stderr kept for [`WasiCtx::stderr_tail`], which fits the message and location of a panic.
+const STDERR_TAIL_LEN: usize = 4096;
+
+/// A resource the guest refers to by file descriptor.
+#[derive(Debug)]
+pub(crate) enum Descriptor {
+	/// A socket accepting connections with `sock_accept`.
+	Listener(TcpListener),
+	/// A connection accepted from a listener.
+	Stream(TcpStream),
+	/// A file opened by the host or the guest.
+	File(Box<dyn WasiFile>),
+	/// A directory the guest may access files in. The guest finds the directories preopened by the host with
+	/// `fd_prestat_get` under their `preopen` path.
+	Dir {
+		dir: Box<dyn WasiDir>,
+		preopen: Option<String>,
+	},
+}
+
+impl WasiCtx {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Passes `listener` to the guest and returns its file descriptor, e.g. for servers which accept connections
+	/// with `sock_accept`. The socket calls only succeed if [`Config::allow_network`] is enabled.
+	///
+	/// [`Config::allow_network`]: crate::exec::Config::allow_network
+	pub fn preopen_socket(&mut self, listener: TcpListener) -> u32 {
+		self.insert(Descriptor::Listener(listener))
+	}
+
+	/// Passes the directory `host_path` of the host to the guest, which sees it as `guest_path`, e.g. `"."` or
+	/// `"/data"`, and returns its file descriptor. The guest can only access files inside the preopened directories.
+	pub fn preopen_dir(&mut self, host_path: impl Into<PathBuf>, guest_path: impl Into<String>) -> u32 {
+		self.preopen(RealDir::new(host_path), guest_path)
+	}
+
+	/// Passes `dir` to the guest like [`preopen_dir`](Self::preopen_dir), e.g. a [`MemDir`](crate::exec::MemDir)
+	/// or a directory of custom storage.
+	pub fn preopen(&mut self, dir: impl WasiDir + 'static, guest_path: impl Into<String>) -> u32 {
+		self.insert(Descriptor::Dir { dir: Box::new(dir), preopen: Some(guest_path.into()) })
+	}
+
+	/// Passes the opened `file` to the guest and returns its file descriptor, e.g. a database the guest reads and
+	/// writes with `fd_pread` and `fd_pwrite`.
+	pub fn preopen_file(&mut self, file: impl WasiFile + 'static) -> u32 {
+		self.insert(Descriptor::File(Box::new(file)))
+	}
+
+	/// The directory preopened as `fd`.
+	pub fn preopened_dir(&self, fd: u32) -> Option<&dyn WasiDir> {
+		match self.descriptors.get(&fd)? {
+			Descriptor::Dir { dir, preopen: Some(_) } => Some(dir.as_ref()),
+			_ => None,
+		}
+	}
+
+	/// Passes the command line arguments `args` to the guest, which usually start with the program name.
+	pub fn set_args(&mut self, args: impl IntoIterator<Item = impl Into<String>>) {
+		self.args = args.into_iter().map(Into::into).collect();
+	}
+
+	/// Passes the environment variables `vars` to the guest, which does not see the ones of the host.
+	pub fn set_env(&mut self, vars: impl IntoIterator<Item = (impl AsRef<str>, impl AsRef<str>)>) {
+		self.environ = vars.into_iter().map(|(key, value)| format!("{}={}", key.as_ref(), value.as_ref())).collect();
+	}
+
+	pub fn args(&self) -> &[String] {
+		&self.args
+	}
+
+	/// The environment variables as `KEY=VALUE`.
+	pub fn environ(&self) -> &[String] {
+		&self.environ
+	}
+
+	/// Pipes `reader` into the stdin of the guest instead of the stdin of the host, e.g. a file or a byte slice.
+	pub fn set_stdin(&mut self, reader: impl Read + MaybeSendSync + 'static) {
+		self.stdin = Stdin::piped(reader);
+	}
+
+	pub(crate) fn stdin_mut(&mut self) -> &mut Stdin {
+		&mut self.stdin
+	}
+
+	/// Pipes the stdout of the guest into `writer` instead of the stdout of the host, e.g. to capture it in a buffer
+	/// on targets without a stdout like the browser.
+	pub fn set_stdout(&mut self, writer: impl Write + MaybeSendSync + 'static) {
+		self.stdout = Output::piped(writer);
+	}
+
+	/// Pipes the stderr of the guest into `writer` like [`set_stdout`](Self::set_stdout). The
+	/// [`stderr_tail`](Self::stderr_tail) is kept either way.
+	pub fn set_stderr(&mut self, writer: impl Write + MaybeSendSync + 'static) {
+		self.stderr = Output::piped(writer);
+	}
+
+	pub(crate) fn stdout_mut(&mut self) -> &mut Output {
+		&mut self.stdout
+	}
+
+	pub(crate) fn stderr_mut(&mut self) -> &mut Output {
+		&mut self.stderr
+	}
+
+	/// The last bytes the guest wrote to stderr, e.g. the message of a panic before the guest aborted.
+	pub fn stderr_tail(&self) -> &[u8] {
+		&self.stderr_tail
+	}
+
+	/// Appends `bytes` written to stderr to the [`stderr_tail`](Self::stderr_tail).
+	pub(crate) fn capture_stderr(&mut self, bytes: &[u8]) {
+		self.stderr_tail.extend_from_slice(bytes);
+		let excess = self.stderr_tail.len().saturating_sub(STDERR_TAIL_LEN);
+		self.stderr_tail.drain(..excess);
+	}
+
+	pub(crate) fn take_stderr_tail(&mut self) -> Vec<u8> {
+		std::mem::take(&mut self.stderr_tail)
+	}
+
+	/// Restricts the WASI functions the instance may call to `policy`.
+	pub fn set_policy(&mut self, policy: WasiPolicy) {
+		self.policy = policy;
+	}
+
+	pub fn policy(&self) -> &WasiPolicy {
+		&self.policy
+	}
+
+	pub(crate) fn policy_mut(&mut self) -> &mut WasiPolicy {
+		&mut self.policy
+	}
+
+	/// Adds `descriptor` with the lowest free file descriptor, which is returned.
+	pub(crate) fn insert(&mut self, descriptor: Descriptor) -> u32 {
+		let fd = (3..).find(|fd| !self.descriptors.contains_key(fd)).expect("File descriptors are exhausted");
+		self.descriptors.insert(fd, descriptor);
+		fd
+	}
+
+	pub(crate) fn get(&self, fd: u32) -> Option<&Descriptor> {
+		self.descriptors.get(&fd)
+	}
+
+	pub(crate) fn get_mut(&mut self, fd: u32) -> Option<&mut Descriptor> {
+		self.descriptors.get_mut(&fd)
+	}
+
+	pub(crate) fn remove(&mut self, fd: u32) -> Option<Descriptor> {
+		self.descriptors.remove(&fd)
+	}
+}
\ No newline at end of file
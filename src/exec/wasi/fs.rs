@@ -0,0 +1,442 @@
+//! The filesystem functions of WASI preview 1, which operate on the directories and files passed in with
+//! [`WasiCtx::preopen`](crate::exec::WasiCtx::preopen) and
+//! [`WasiCtx::preopen_file`](crate::exec::WasiCtx::preopen_file) and the ones opened from them, through the
+//! [`WasiDir`] and [`WasiFile`] backends.
+
+use std::io::SeekFrom;
+use std::path::{Component, Path, PathBuf};
+use crate::exec::{HostResult, Value};
+use crate::exec::instance::InstanceRef;
+use super::*;
+
+/// Tag of the `prestat` union for a preopened directory.
+const PREOPENTYPE_DIR: u8 = 0;
+/// Flag of the `lookupflags` to follow a symlink in the last component of a path.
+const LOOKUPFLAGS_SYMLINK_FOLLOW: i32 = 1;
+/// Flags of the `oflags` of `path_open`.
+const OFLAGS_CREAT: i32 = 1;
+const OFLAGS_DIRECTORY: i32 = 2;
+const OFLAGS_EXCL: i32 = 4;
+const OFLAGS_TRUNC: i32 = 8;
+/// Rights of `path_open` to read and write the opened file.
+const RIGHTS_FD_READ: i64 = 1 << 1;
+const RIGHTS_FD_WRITE: i64 = 1 << 6;
+/// Size of a `filestat`.
+const FILESTAT_SIZE: usize = 64;
+/// Size of a `dirent` without the name following it.
+const DIRENT_SIZE: usize = 24;
+
+/// `fd_prestat_get(fd, prestat_ptr) -> errno`
+///
+/// Writes the `prestat` of a preopened directory, i.e. the tag [`PREOPENTYPE_DIR`] followed by the length of its
+/// guest path at offset 4. Other file descriptors fail with `badf`, at which the guest stops enumerating preopens.
+pub fn fd_prestat_get(instance: &mut InstanceRef) -> HostResult {
+	let prestat_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let mem = instance.memory.as_mut().unwrap();
+	let errno = match instance.wasi.get(fd) {
+		Some(Descriptor::Dir { preopen: Some(guest_path), .. }) => {
+			let mut prestat = [0u8; 8];
+			prestat[0] = PREOPENTYPE_DIR;
+			prestat[4..8].copy_from_slice(&(guest_path.len() as u32).to_le_bytes());
+			write_bytes(&mut mem.data, prestat_ptr, &prestat).map_or(ERRNO_FAULT, |_| 0)
+		},
+		_ => ERRNO_BADF,
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `fd_prestat_dir_name(fd, path_ptr, path_len) -> errno`
+///
+/// Writes the guest path of a preopened directory without a terminating zero.
+pub fn fd_prestat_dir_name(instance: &mut InstanceRef) -> HostResult {
+	let path_len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let path_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let mem = instance.memory.as_mut().unwrap();
+	let errno = match instance.wasi.get(fd) {
+		Some(Descriptor::Dir { preopen: Some(guest_path), .. }) if guest_path.len() > path_len => ERRNO_NAMETOOLONG,
+		Some(Descriptor::Dir { preopen: Some(guest_path), .. }) => {
+			write_bytes(&mut mem.data, path_ptr, guest_path.as_bytes()).map_or(ERRNO_FAULT, |_| 0)
+		},
+		_ => ERRNO_BADF,
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `path_filestat_get(fd, flags, path_ptr, path_len, filestat_ptr) -> errno`
+pub fn path_filestat_get(instance: &mut InstanceRef) -> HostResult {
+	let filestat_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let path_len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let path_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let flags = instance.operand_stack.pop::<i32>()?;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let mem = instance.memory.as_mut().unwrap();
+	let follow = flags & LOOKUPFLAGS_SYMLINK_FOLLOW != 0;
+	let stat = read_path(&mem.data, path_ptr, path_len)
+		.and_then(|path| dir(instance.wasi, fd)?.stat(&path, follow).map_err(|error| errno(&error)));
+	let errno = match stat {
+		Ok(stat) => write_bytes(&mut mem.data, filestat_ptr, &filestat(&stat)).map_or(ERRNO_FAULT, |_| 0),
+		Err(errno) => errno,
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `path_create_directory(fd, path_ptr, path_len) -> errno`
+pub fn path_create_directory(instance: &mut InstanceRef) -> HostResult {
+	let path_len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let path_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let mem = instance.memory.as_ref().unwrap();
+	let errno = read_path(&mem.data, path_ptr, path_len)
+		.and_then(|path| dir(instance.wasi, fd)?.create_dir(&path).map_err(|error| errno(&error)))
+		.map_or_else(|errno| errno, |_| 0);
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `path_unlink_file(fd, path_ptr, path_len) -> errno`
+///
+/// Removes a file or symlink, but not a directory.
+pub fn path_unlink_file(instance: &mut InstanceRef) -> HostResult {
+	let path_len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let path_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let mem = instance.memory.as_ref().unwrap();
+	let errno = read_path(&mem.data, path_ptr, path_len)
+		.and_then(|path| {
+			let dir = dir(instance.wasi, fd)?;
+			match dir.stat(&path, false) {
+				Ok(stat) if stat.file_type == FileType::Directory => Err(ERRNO_ISDIR),
+				_ => dir.remove_file(&path).map_err(|error| errno(&error)),
+			}
+		})
+		.map_or_else(|errno| errno, |_| 0);
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `path_rename(fd, old_path_ptr, old_path_len, new_fd, new_path_ptr, new_path_len) -> errno`
+///
+/// Both paths have to be in the same directory, as the directories may have different backends.
+pub fn path_rename(instance: &mut InstanceRef) -> HostResult {
+	let new_path_len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let new_path_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let new_fd = instance.operand_stack.pop::<i32>()? as u32;
+	let old_path_len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let old_path_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let mem = instance.memory.as_ref().unwrap();
+	let paths = read_path(&mem.data, old_path_ptr, old_path_len)
+		.and_then(|old_path| Ok((old_path, read_path(&mem.data, new_path_ptr, new_path_len)?)));
+	let errno = match (dir(instance.wasi, fd), dir(instance.wasi, new_fd), paths) {
+		(Err(errno), _, _) | (_, Err(errno), _) | (_, _, Err(errno)) => errno,
+		_ if fd != new_fd => ERRNO_XDEV,
+		(Ok(dir), _, Ok((old_path, new_path))) => dir.rename(&old_path, &new_path).map_or_else(|error| errno(&error), |_| 0),
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `path_readlink(fd, path_ptr, path_len, buf_ptr, buf_len, bufused_ptr) -> errno`
+///
+/// Writes the target of a symlink, truncated to `buf_len` bytes, without a terminating zero.
+pub fn path_readlink(instance: &mut InstanceRef) -> HostResult {
+	let bufused_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let buf_len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let buf_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let path_len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let path_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let mem = instance.memory.as_mut().unwrap();
+	let target = read_path(&mem.data, path_ptr, path_len)
+		.and_then(|path| dir(instance.wasi, fd)?.read_link(&path).map_err(|error| errno(&error)))
+		.and_then(|target| target.into_os_string().into_string().map_err(|_| ERRNO_ILSEQ));
+	let errno = match target {
+		Ok(target) => {
+			let target = &target.as_bytes()[..target.len().min(buf_len)];
+			write_bytes(&mut mem.data, buf_ptr, target)
+				.and_then(|_| write_bytes(&mut mem.data, bufused_ptr, &(target.len() as u32).to_le_bytes()))
+				.map_or(ERRNO_FAULT, |_| 0)
+		},
+		Err(errno) => errno,
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `path_open(fd, dirflags, path_ptr, path_len, oflags, fs_rights_base, fs_rights_inheriting, fdflags, fd_ptr)
+/// -> errno`
+///
+/// Opens a directory if `oflags` require one or the path refers to one, and a file otherwise, which is readable and
+/// writable according to `fs_rights_base`. The `fdflags` are ignored.
+pub fn path_open(instance: &mut InstanceRef) -> HostResult {
+	let fd_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let _fdflags = instance.operand_stack.pop::<i32>()?;
+	let _fs_rights_inheriting = instance.operand_stack.pop::<i64>()?;
+	let fs_rights_base = instance.operand_stack.pop::<i64>()?;
+	let oflags = instance.operand_stack.pop::<i32>()?;
+	let path_len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let path_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let dirflags = instance.operand_stack.pop::<i32>()?;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let mem = instance.memory.as_mut().unwrap();
+	let follow = dirflags & LOOKUPFLAGS_SYMLINK_FOLLOW != 0;
+	let flags = OpenFlags {
+		read: fs_rights_base & RIGHTS_FD_READ != 0,
+		write: fs_rights_base & RIGHTS_FD_WRITE != 0,
+		create: oflags & OFLAGS_CREAT != 0,
+		exclusive: oflags & OFLAGS_EXCL != 0,
+		truncate: oflags & OFLAGS_TRUNC != 0,
+	};
+	let opened = read_path(&mem.data, path_ptr, path_len).and_then(|path| {
+		let dir = dir(instance.wasi, fd)?;
+		let is_dir = || dir.stat(&path, follow).is_ok_and(|stat| stat.file_type == FileType::Directory);
+		let opened = match oflags & OFLAGS_DIRECTORY != 0 || !flags.create && is_dir() {
+			true => dir.open_dir(&path).map(|dir| Descriptor::Dir { dir, preopen: None }),
+			false => dir.open_file(&path, flags).map(Descriptor::File),
+		};
+		opened.map_err(|error| errno(&error))
+	});
+	let errno = match opened {
+		// The descriptor is only inserted if the guest can learn about it
+		Ok(descriptor) => match mem.data.get_mut(fd_ptr..fd_ptr.saturating_add(4)) {
+			Some(result_fd) => {
+				result_fd.copy_from_slice(&instance.wasi.insert(descriptor).to_le_bytes());
+				0
+			},
+			None => ERRNO_FAULT,
+		},
+		Err(errno) => errno,
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `fd_readdir(fd, buf_ptr, buf_len, cookie, bufused_ptr) -> errno`
+///
+/// Writes the `dirent`s of the directory starting at the index `cookie`, each followed by its name. The last one is
+/// truncated if the buffer is full, which the guest detects by `bufused` being `buf_len`.
+pub fn fd_readdir(instance: &mut InstanceRef) -> HostResult {
+	let bufused_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let cookie = instance.operand_stack.pop::<i64>()? as u64;
+	let buf_len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let buf_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let mem = instance.memory.as_mut().unwrap();
+	let entries = dir(instance.wasi, fd).and_then(|dir| dir.read_dir(Path::new("")).map_err(|error| errno(&error)));
+	let errno = match entries {
+		Ok(entries) => {
+			let mut buf = Vec::new();
+			for (index, (name, file_type)) in entries.iter().enumerate().skip(cookie.try_into().unwrap_or(usize::MAX)) {
+				if buf.len() >= buf_len {
+					break;
+				}
+				let mut dirent = [0u8; DIRENT_SIZE];
+				dirent[0..8].copy_from_slice(&(index as u64 + 1).to_le_bytes()); // Cookie of the next entry
+				dirent[16..20].copy_from_slice(&(name.len() as u32).to_le_bytes());
+				dirent[20] = *file_type as u8;
+				buf.extend_from_slice(&dirent);
+				buf.extend_from_slice(name.as_bytes());
+			}
+			buf.truncate(buf_len);
+			write_bytes(&mut mem.data, buf_ptr, &buf)
+				.and_then(|_| write_bytes(&mut mem.data, bufused_ptr, &(buf.len() as u32).to_le_bytes()))
+				.map_or(ERRNO_FAULT, |_| 0)
+		},
+		Err(errno) => errno,
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `fd_seek(fd, offset, whence, newoffset_ptr) -> errno`
+pub fn fd_seek(instance: &mut InstanceRef) -> HostResult {
+	let newoffset_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let whence = instance.operand_stack.pop::<i32>()?;
+	let offset = instance.operand_stack.pop::<i64>()?;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let pos = match whence {
+		0 => Ok(SeekFrom::Start(offset as u64)),
+		1 => Ok(SeekFrom::Current(offset)),
+		2 => Ok(SeekFrom::End(offset)),
+		_ => Err(ERRNO_INVAL),
+	};
+	let mem = instance.memory.as_mut().unwrap();
+	let errno = match pos.and_then(|pos| file(instance.wasi, fd)?.seek(pos).map_err(|error| errno(&error))) {
+		Ok(offset) => write_bytes(&mut mem.data, newoffset_ptr, &offset.to_le_bytes()).map_or(ERRNO_FAULT, |_| 0),
+		Err(errno) => errno,
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `fd_close(fd) -> errno`
+///
+/// Closes a file, directory or socket. The file descriptors of stdin, stdout and stderr are never open in the
+/// [`WasiCtx`] and fail with `badf`.
+pub fn fd_close(instance: &mut InstanceRef) -> HostResult {
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let errno = instance.wasi.remove(fd).map_or(ERRNO_BADF, |_| 0);
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `fd_filestat_get(fd, filestat_ptr) -> errno`
+///
+/// Only files and directories have attributes besides their type.
+pub fn fd_filestat_get(instance: &mut InstanceRef) -> HostResult {
+	let filestat_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let with_type = |file_type| Ok(FileStat { file_type, ..FileStat::default() });
+	let stat = match instance.wasi.get(fd) {
+		Some(Descriptor::File(file)) => file.stat().map_err(|error| errno(&error)),
+		Some(Descriptor::Dir { dir, .. }) => dir.stat(Path::new(""), true).map_err(|error| errno(&error)),
+		Some(Descriptor::Listener(_) | Descriptor::Stream(_)) => with_type(FileType::SocketStream),
+		None if fd < 3 => with_type(FileType::CharacterDevice),
+		None => Err(ERRNO_BADF),
+	};
+	let mem = instance.memory.as_mut().unwrap();
+	let errno = match stat {
+		Ok(stat) => write_bytes(&mut mem.data, filestat_ptr, &filestat(&stat)).map_or(ERRNO_FAULT, |_| 0),
+		Err(errno) => errno,
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `fd_filestat_set_size(fd, size) -> errno`
+///
+/// Truncates or extends the file with zeros.
+pub fn fd_filestat_set_size(instance: &mut InstanceRef) -> HostResult {
+	let size = instance.operand_stack.pop::<i64>()? as u64;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let errno = file(instance.wasi, fd)
+		.and_then(|file| file.set_len(size).map_err(|error| errno(&error)))
+		.map_or_else(|errno| errno, |_| 0);
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `fd_tell(fd, offset_ptr) -> errno`
+pub fn fd_tell(instance: &mut InstanceRef) -> HostResult {
+	let offset_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let mem = instance.memory.as_mut().unwrap();
+	let offset = file(instance.wasi, fd).and_then(|file| file.seek(SeekFrom::Current(0)).map_err(|error| errno(&error)));
+	let errno = match offset {
+		Ok(offset) => write_bytes(&mut mem.data, offset_ptr, &offset.to_le_bytes()).map_or(ERRNO_FAULT, |_| 0),
+		Err(errno) => errno,
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `fd_pread(fd, iovs_ptr, iovs_len, offset, nread_ptr) -> errno`
+///
+/// Reads at `offset` without using or changing the position of the file descriptor.
+pub fn fd_pread(instance: &mut InstanceRef) -> HostResult {
+	let nread_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let offset = instance.operand_stack.pop::<i64>()? as u64;
+	let iovs_len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let iovs_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let mem = instance.memory.as_mut().unwrap();
+	let errno = match (file(instance.wasi, fd), iovecs(&mem.data, iovs_ptr, iovs_len)) {
+		(Err(errno), _) => errno,
+		(_, None) => ERRNO_FAULT,
+		(Ok(file), Some(buffers)) => {
+			let mut read = vec![0; buffers.iter().map(Range::len).sum()];
+			match file.read_at(&mut read, offset) {
+				Ok(len) => {
+					scatter(&mut mem.data, buffers, &read[..len]);
+					write_bytes(&mut mem.data, nread_ptr, &(len as u32).to_le_bytes()).map_or(ERRNO_FAULT, |_| 0)
+				},
+				Err(error) => errno(&error),
+			}
+		},
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `fd_pwrite(fd, iovs_ptr, iovs_len, offset, nwritten_ptr) -> errno`
+///
+/// Writes at `offset` without using or changing the position of the file descriptor.
+pub fn fd_pwrite(instance: &mut InstanceRef) -> HostResult {
+	let nwritten_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let offset = instance.operand_stack.pop::<i64>()? as u64;
+	let iovs_len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let iovs_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let mem = instance.memory.as_mut().unwrap();
+	let errno = match (file(instance.wasi, fd), iovecs(&mem.data, iovs_ptr, iovs_len)) {
+		(Err(errno), _) => errno,
+		(_, None) => ERRNO_FAULT,
+		(Ok(file), Some(buffers)) => {
+			let data: Vec<u8> = buffers.into_iter().flat_map(|buffer| mem.data[buffer].to_vec()).collect();
+			match file.write_at(&data, offset) {
+				Ok(len) => write_bytes(&mut mem.data, nwritten_ptr, &(len as u32).to_le_bytes()).map_or(ERRNO_FAULT, |_| 0),
+				Err(error) => errno(&error),
+			}
+		},
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+/// The file `fd` refers to, or the errno if it is not a file.
+pub(super) fn file(wasi: &mut WasiCtx, fd: u32) -> Result<&mut dyn WasiFile, i32> {
+	match wasi.get_mut(fd) {
+		Some(Descriptor::File(file)) => Ok(file.as_mut()),
+		Some(Descriptor::Dir { .. }) => Err(ERRNO_ISDIR),
+		Some(_) => Err(ERRNO_INVAL),
+		None if fd < 3 => Err(ERRNO_INVAL),
+		None => Err(ERRNO_BADF),
+	}
+}
+
+/// The directory `fd` refers to, or the errno if it is not a directory.
+fn dir(wasi: &WasiCtx, fd: u32) -> Result<&dyn WasiDir, i32> {
+	match wasi.get(fd) {
+		Some(Descriptor::Dir { dir, .. }) => Ok(dir.as_ref()),
+		Some(_) => Err(ERRNO_NOTDIR),
+		None => Err(ERRNO_BADF),
+	}
+}
+
+/// The path of `len` bytes at `ptr` of the memory `data`, with `.` and `..` resolved, or the errno if `..` goes
+/// above the directory the path is relative to or the path is absolute.
+fn read_path(data: &[u8], ptr: usize, len: usize) -> Result<PathBuf, i32> {
+	let bytes = data.get(ptr..ptr.checked_add(len).ok_or(ERRNO_FAULT)?).ok_or(ERRNO_FAULT)?;
+	let path = std::str::from_utf8(bytes).map_err(|_| ERRNO_ILSEQ)?;
+	let mut components = Vec::new();
+	for component in Path::new(path).components() {
+		match component {
+			Component::Normal(name) => components.push(name),
+			Component::CurDir => (),
+			Component::ParentDir => {
+				components.pop().ok_or(ERRNO_NOTCAPABLE)?;
+			},
+			Component::RootDir | Component::Prefix(_) => return Err(ERRNO_NOTCAPABLE),
+		}
+	}
+	Ok(components.iter().collect())
+}
+
+/// The `filestat` of `stat`.
+fn filestat(stat: &FileStat) -> [u8; FILESTAT_SIZE] {
+	let mut filestat = [0u8; FILESTAT_SIZE];
+	filestat[0..8].copy_from_slice(&stat.dev.to_le_bytes());
+	filestat[8..16].copy_from_slice(&stat.ino.to_le_bytes());
+	filestat[16] = stat.file_type as u8;
+	filestat[24..32].copy_from_slice(&stat.nlink.to_le_bytes());
+	filestat[32..40].copy_from_slice(&stat.size.to_le_bytes());
+	filestat[40..48].copy_from_slice(&stat.atim.to_le_bytes());
+	filestat[48..56].copy_from_slice(&stat.mtim.to_le_bytes());
+	filestat[56..64].copy_from_slice(&stat.ctim.to_le_bytes());
+	filestat
+}
\ No newline at end of file
@@ -0,0 +1,278 @@
+//! The backend of the WASI filesystem functions in memory, e.g. for tests or sandboxes without access to the host.
+
+use std::collections::BTreeMap;
+use std::io::{self, SeekFrom};
+use std::ops::DerefMut;
+use std::path::{Component, Path, PathBuf};
+use crate::shared::{self, SharedMut};
+use super::{FileStat, FileType, OpenFlags, WasiDir, WasiFile};
+
+/// A directory in memory without symlinks, whose files have no timestamps so that reading them is deterministic.
+///
+/// Clones share the files, so the host can prepare them with [`insert_file`](Self::insert_file) before and inspect
+/// them with [`read_file`](Self::read_file) after running the guest.
+#[derive(Debug, Clone)]
+pub struct MemDir {
+	root: SharedMut<Node>,
+	/// The path of this directory below `root`.
+	path: PathBuf,
+}
+
+#[derive(Debug)]
+enum Node {
+	File(SharedMut<Vec<u8>>),
+	Dir(BTreeMap<String, Node>),
+}
+
+/// A file of a [`MemDir`], with its own position.
+#[derive(Debug)]
+pub struct MemFile {
+	data: SharedMut<Vec<u8>>,
+	position: u64,
+}
+
+impl MemDir {
+	pub fn new() -> Self {
+		Self { root: shared::shared_mut(Node::Dir(BTreeMap::new())), path: PathBuf::new() }
+	}
+
+	/// Creates or replaces the file at `path` with `contents`. Missing parent directories are created.
+	pub fn insert_file(&self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) -> io::Result<()> {
+		let path = self.path.join(path);
+		let (parent, name) = split(&path)?;
+		let mut root = lock(&self.root)?;
+		let mut node = root.deref_mut();
+		for component in names(parent)? {
+			node = match node {
+				Node::Dir(entries) => entries.entry(component.to_owned()).or_insert_with(|| Node::Dir(BTreeMap::new())),
+				Node::File(_) => return Err(io::ErrorKind::NotADirectory.into()),
+			};
+		}
+		let Node::Dir(entries) = node else {
+			return Err(io::ErrorKind::NotADirectory.into());
+		};
+		match entries.get(name) {
+			Some(Node::Dir(_)) => Err(io::ErrorKind::IsADirectory.into()),
+			_ => {
+				entries.insert(name.to_owned(), Node::File(shared::shared_mut(contents.into())));
+				Ok(())
+			},
+		}
+	}
+
+	/// The contents of the file at `path`.
+	pub fn read_file(&self, path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+		let path = self.path.join(path);
+		let root = lock(&self.root)?;
+		match lookup(&root, &path)? {
+			Node::File(data) => Ok(lock(data)?.clone()),
+			Node::Dir(_) => Err(io::ErrorKind::IsADirectory.into()),
+		}
+	}
+
+	/// The entries of the directory at `path`, which is relative to this directory.
+	fn with_entries<T>(&self, path: &Path, f: impl FnOnce(&mut BTreeMap<String, Node>) -> io::Result<T>) -> io::Result<T> {
+		let mut root = lock(&self.root)?;
+		match lookup_mut(&mut root, &self.path.join(path))? {
+			Node::Dir(entries) => f(entries),
+			Node::File(_) => Err(io::ErrorKind::NotADirectory.into()),
+		}
+	}
+}
+
+impl Default for MemDir {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl WasiDir for MemDir {
+	fn open_file(&self, path: &Path, flags: OpenFlags) -> io::Result<Box<dyn WasiFile>> {
+		let (parent, name) = split(path)?;
+		let data = self.with_entries(parent, |entries| match entries.get(name) {
+			Some(_) if flags.create && flags.exclusive => Err(io::ErrorKind::AlreadyExists.into()),
+			Some(Node::File(data)) => Ok(data.clone()),
+			Some(Node::Dir(_)) => Err(io::ErrorKind::IsADirectory.into()),
+			None if flags.create => {
+				let data = shared::shared_mut(Vec::new());
+				entries.insert(name.to_owned(), Node::File(data.clone()));
+				Ok(data)
+			},
+			None => Err(io::ErrorKind::NotFound.into()),
+		})?;
+		if flags.truncate {
+			lock(&data)?.clear();
+		}
+		Ok(Box::new(MemFile { data, position: 0 }))
+	}
+
+	fn open_dir(&self, path: &Path) -> io::Result<Box<dyn WasiDir>> {
+		self.with_entries(path, |_| Ok(()))?;
+		Ok(Box::new(MemDir { root: self.root.clone(), path: self.path.join(path) }))
+	}
+
+	fn stat(&self, path: &Path, _follow: bool) -> io::Result<FileStat> {
+		let root = lock(&self.root)?;
+		Ok(match lookup(&root, &self.path.join(path))? {
+			Node::File(data) => file_stat(lock(data)?.len()),
+			Node::Dir(_) => FileStat { file_type: FileType::Directory, nlink: 1, ..FileStat::default() },
+		})
+	}
+
+	fn read_dir(&self, path: &Path) -> io::Result<Vec<(String, FileType)>> {
+		self.with_entries(path, |entries| Ok(entries.iter()
+			.map(|(name, node)| {
+				let file_type = match node {
+					Node::File(_) => FileType::RegularFile,
+					Node::Dir(_) => FileType::Directory,
+				};
+				(name.clone(), file_type)
+			})
+			.collect()))
+	}
+
+	fn create_dir(&self, path: &Path) -> io::Result<()> {
+		let (parent, name) = split(path)?;
+		self.with_entries(parent, |entries| match entries.contains_key(name) {
+			true => Err(io::ErrorKind::AlreadyExists.into()),
+			false => {
+				entries.insert(name.to_owned(), Node::Dir(BTreeMap::new()));
+				Ok(())
+			},
+		})
+	}
+
+	fn remove_file(&self, path: &Path) -> io::Result<()> {
+		let (parent, name) = split(path)?;
+		self.with_entries(parent, |entries| match entries.get(name) {
+			Some(Node::File(_)) => {
+				entries.remove(name);
+				Ok(())
+			},
+			Some(Node::Dir(_)) => Err(io::ErrorKind::IsADirectory.into()),
+			None => Err(io::ErrorKind::NotFound.into()),
+		})
+	}
+
+	fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+		let (from_parent, from_name) = split(from)?;
+		let (to_parent, to_name) = split(to)?;
+		if to.starts_with(from) && to != from {
+			// A directory can not be moved into itself
+			return Err(io::ErrorKind::InvalidInput.into());
+		}
+		let node = self.with_entries(from_parent, |entries| entries.remove(from_name).ok_or(io::ErrorKind::NotFound.into()))?;
+		let is_dir = matches!(node, Node::Dir(_));
+		let mut node = Some(node);
+		let moved = self.with_entries(to_parent, |entries| match (entries.get(to_name), is_dir) {
+			(Some(Node::Dir(_)), false) => Err(io::ErrorKind::IsADirectory.into()),
+			(Some(Node::File(_)), true) => Err(io::ErrorKind::NotADirectory.into()),
+			(Some(Node::Dir(existing)), true) if !existing.is_empty() => Err(io::ErrorKind::DirectoryNotEmpty.into()),
+			_ => {
+				entries.insert(to_name.to_owned(), node.take().unwrap());
+				Ok(())
+			},
+		});
+		if let Some(node) = node {
+			// Restore the source if the destination rejected it
+			self.with_entries(from_parent, |entries| {
+				entries.insert(from_name.to_owned(), node);
+				Ok(())
+			})?;
+		}
+		moved
+	}
+
+	fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+		// Every existing path is not a symlink
+		self.stat(path, false)?;
+		Err(io::ErrorKind::InvalidInput.into())
+	}
+}
+
+impl WasiFile for MemFile {
+	fn read_at(&mut self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+		let data = lock(&self.data)?;
+		let start = usize::try_from(offset).unwrap_or(usize::MAX).min(data.len());
+		let len = buf.len().min(data.len() - start);
+		buf[..len].copy_from_slice(&data[start..start + len]);
+		Ok(len)
+	}
+
+	fn write_at(&mut self, buf: &[u8], offset: u64) -> io::Result<usize> {
+		let mut data = lock(&self.data)?;
+		let start = usize::try_from(offset).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+		let end = start.checked_add(buf.len()).ok_or(io::Error::from(io::ErrorKind::InvalidInput))?;
+		if data.len() < end {
+			data.resize(end, 0);
+		}
+		data[start..end].copy_from_slice(buf);
+		Ok(buf.len())
+	}
+
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		let position = match pos {
+			SeekFrom::Start(offset) => Some(offset),
+			SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+			SeekFrom::End(offset) => (lock(&self.data)?.len() as u64).checked_add_signed(offset),
+		};
+		self.position = position.ok_or(io::Error::from(io::ErrorKind::InvalidInput))?;
+		Ok(self.position)
+	}
+
+	fn set_len(&mut self, len: u64) -> io::Result<()> {
+		let len = usize::try_from(len).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+		lock(&self.data)?.resize(len, 0);
+		Ok(())
+	}
+
+	fn stat(&self) -> io::Result<FileStat> {
+		Ok(file_stat(lock(&self.data)?.len()))
+	}
+}
+
+fn file_stat(size: usize) -> FileStat {
+	FileStat { file_type: FileType::RegularFile, nlink: 1, size: size as u64, ..FileStat::default() }
+}
+
+/// Exclusive access to `shared`, which fails if another descriptor of the guest or the host is accessing it.
+fn lock<T>(shared: &SharedMut<T>) -> io::Result<impl DerefMut<Target = T> + '_> {
+	shared::try_lock(shared).ok_or(io::Error::from(io::ErrorKind::WouldBlock))
+}
+
+/// The names of the components of `path`, which may only consist of normal components.
+fn names(path: &Path) -> io::Result<Vec<&str>> {
+	path.components()
+		.map(|component| match component {
+			Component::Normal(name) => name.to_str().ok_or(io::Error::from(io::ErrorKind::InvalidInput)),
+			_ => Err(io::ErrorKind::InvalidInput.into()),
+		})
+		.collect()
+}
+
+/// The parent and the name of the last component of `path`.
+fn split(path: &Path) -> io::Result<(&Path, &str)> {
+	let name = path.file_name().and_then(|name| name.to_str()).ok_or(io::Error::from(io::ErrorKind::InvalidInput))?;
+	Ok((path.parent().unwrap_or(Path::new("")), name))
+}
+
+/// The node at `path` below `node`.
+fn lookup<'a>(mut node: &'a Node, path: &Path) -> io::Result<&'a Node> {
+	for name in names(path)? {
+		node = match node {
+			Node::Dir(entries) => entries.get(name).ok_or(io::Error::from(io::ErrorKind::NotFound))?,
+			Node::File(_) => return Err(io::ErrorKind::NotADirectory.into()),
+		};
+	}
+	Ok(node)
+}
+
+fn lookup_mut<'a>(mut node: &'a mut Node, path: &Path) -> io::Result<&'a mut Node> {
+	for name in names(path)? {
+		node = match node {
+			Node::Dir(entries) => entries.get_mut(name).ok_or(io::Error::from(io::ErrorKind::NotFound))?,
+			Node::File(_) => return Err(io::ErrorKind::NotADirectory.into()),
+		};
+	}
+	Ok(node)
+}
\ No newline at end of file
@@ -0,0 +1,285 @@
+use std::{io};
+use std::ops::Range;
+
+use std::io::IoSlice;
+use crate::exec::{Clock, HostResult, Value};
+use crate::exec::instance::InstanceRef;
+
+// Only contains WasiCtx, so re-export it in this module.
+mod ctx;
+mod sockets;
+mod fs;
+mod backend;
+mod real_fs;
+mod mem_fs;
+mod policy;
+mod args;
+mod stdin;
+mod output;
+mod poll;
+pub(crate) mod preview2;
+
+pub use ctx::WasiCtx;
+pub use policy::WasiPolicy;
+pub use backend::{FileStat, FileType, OpenFlags, WasiDir, WasiFile};
+pub use real_fs::RealDir;
+pub use mem_fs::{MemDir, MemFile};
+pub(crate) use ctx::Descriptor;
+pub(crate) use stdin::Stdin;
+pub(crate) use output::Output;
+pub use sockets::{sock_accept, sock_recv, sock_send, sock_shutdown};
+pub use fs::{
+	fd_close, fd_filestat_get, fd_filestat_set_size, fd_pread, fd_prestat_dir_name, fd_prestat_get, fd_pwrite,
+	fd_readdir, fd_seek, fd_tell, path_create_directory, path_filestat_get, path_open, path_readlink, path_rename,
+	path_unlink_file,
+};
+pub use poll::poll_oneoff;
+pub use args::{args_get, args_sizes_get, environ_get, environ_sizes_get};
+
+/// Flag of `sock_accept` and `fd_fdstat_set_flags` for non-blocking I/O.
+const FDFLAGS_NONBLOCK: i32 = 4;
+
+/// `fd_write(fd, iovs_ptr, iovs_len, nwritten_ptr) -> errno`
+///
+/// Writes to stdout, stderr or a file at its position, shortened to the quota of the [`WasiPolicy`].
+pub fn fd_write(instance: &mut InstanceRef) -> HostResult {
+	let nwritten_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let iovs_len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let iovs_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let mem = instance.memory.as_mut().unwrap();
+	let Some(buffers) = iovecs(&mem.data, iovs_ptr, iovs_len) else {
+		return Ok(vec![Value::I32(ERRNO_FAULT)]);
+	};
+	let requested = buffers.iter().map(Range::len).sum();
+	let mut allowed = match instance.wasi.policy().limit_write(requested) {
+		Ok(allowed) => allowed,
+		Err(errno) => {
+			let errno = write_bytes(&mut mem.data, nwritten_ptr, &0u32.to_le_bytes()).map_or(ERRNO_FAULT, |_| errno);
+			return Ok(vec![Value::I32(errno)]);
+		},
+	};
+
+	let io_slices: Vec<IoSlice> = buffers.into_iter()
+		.map(|buffer| {
+			// A write exceeding the quota of the policy is shortened
+			let len = usize::min(buffer.len(), allowed);
+			allowed -= len;
+			IoSlice::new(&mem.data[buffer.start..buffer.start + len])
+		})
+		.collect();
+
+	let written = match fd {
+		1 => instance.wasi.stdout_mut().write_vectored(io::stdout(), &io_slices).map_err(|error| errno(&error)),
+		2 => instance.wasi.stderr_mut().write_vectored(io::stderr(), &io_slices).map_err(|error| errno(&error)),
+		_ => fs::file(instance.wasi, fd).and_then(|file| {
+			let bytes: Vec<u8> = io_slices.iter().flat_map(|slice| slice.iter()).copied().collect();
+			file.write(&bytes).map_err(|error| errno(&error))
+		}),
+	};
+	let (bytes_written, errno) = match written {
+		Ok(bytes_written) => {
+			instance.wasi.policy_mut().wrote(bytes_written);
+			if fd == 2 {
+				let bytes = io_slices.iter().flat_map(|slice| slice.iter()).take(bytes_written);
+				instance.wasi.capture_stderr(&bytes.copied().collect::<Vec<u8>>());
+			}
+			(bytes_written, 0)
+		},
+		Err(errno) => (0, errno),
+	};
+	let errno = write_bytes(&mut mem.data, nwritten_ptr, &(bytes_written as u32).to_le_bytes())
+		.map_or(ERRNO_FAULT, |_| errno);
+
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `fd_read(fd, iovs_ptr, iovs_len, nread_ptr) -> errno`
+///
+/// Reads from stdin or from a file at its position.
+pub fn fd_read(instance: &mut InstanceRef) -> HostResult {
+	let nread_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let iovs_len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let iovs_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let mem = instance.memory.as_mut().unwrap();
+	let Some(buffers) = iovecs(&mem.data, iovs_ptr, iovs_len) else {
+		return Ok(vec![Value::I32(ERRNO_FAULT)]);
+	};
+	let mut read = vec![0; buffers.iter().map(Range::len).sum()];
+	let len = match fd {
+		0 => instance.wasi.stdin_mut().read(&mut read).map_err(|error| errno(&error)),
+		_ => fs::file(instance.wasi, fd).and_then(|file| file.read(&mut read).map_err(|error| errno(&error))),
+	};
+	let errno = match len {
+		Ok(len) => {
+			scatter(&mut mem.data, buffers, &read[..len]);
+			write_bytes(&mut mem.data, nread_ptr, &(len as u32).to_le_bytes()).map_or(ERRNO_FAULT, |_| 0)
+		},
+		Err(errno) => errno,
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `fd_fdstat_set_flags(fd, flags) -> errno`
+///
+/// Only the flag for non-blocking I/O of stdin and sockets is supported, the other flags are ignored.
+pub fn fd_fdstat_set_flags(instance: &mut InstanceRef) -> HostResult {
+	let flags = instance.operand_stack.pop::<i32>()?;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let nonblocking = flags & FDFLAGS_NONBLOCK != 0;
+	let result = match instance.wasi.get(fd) {
+		_ if fd == 0 => {
+			instance.wasi.stdin_mut().nonblocking = nonblocking;
+			Ok(())
+		},
+		Some(Descriptor::Listener(listener)) => listener.set_nonblocking(nonblocking),
+		Some(Descriptor::Stream(stream)) => stream.set_nonblocking(nonblocking),
+		Some(_) => Ok(()),
+		None if fd < 3 => Ok(()),
+		None => return Ok(vec![Value::I32(ERRNO_BADF)]),
+	};
+	Ok(vec![Value::I32(result.map_or_else(|error| errno(&error), |_| 0))])
+}
+
+/// Errno of WASI for arguments or environment variables which exceed their limit.
+const ERRNO_2BIG: i32 = 1;
+/// Errno of WASI for a missing permission of the host.
+const ERRNO_ACCES: i32 = 2;
+/// Errno of WASI for a resource which is temporarily unavailable, e.g. a non-blocking socket without data.
+const ERRNO_AGAIN: i32 = 6;
+/// Errno of WASI for a file descriptor which is not open.
+const ERRNO_BADF: i32 = 8;
+/// Errno of WASI for a connection reset by the peer.
+const ERRNO_CONNRESET: i32 = 15;
+/// Errno of WASI for an exhausted quota.
+const ERRNO_DQUOT: i32 = 19;
+/// Errno of WASI for a file which already exists.
+const ERRNO_EXIST: i32 = 20;
+/// Errno of WASI for a pointer outside of the memory.
+const ERRNO_FAULT: i32 = 21;
+/// Errno of WASI for a path which is not valid UTF-8.
+const ERRNO_ILSEQ: i32 = 25;
+/// Errno of WASI for an invalid argument.
+const ERRNO_INVAL: i32 = 28;
+/// Errno of WASI for an I/O error without a more specific errno.
+const ERRNO_IO: i32 = 29;
+/// Errno of WASI for a directory where a file is required.
+const ERRNO_ISDIR: i32 = 31;
+/// Errno of WASI for a path or buffer for a name which is too long.
+const ERRNO_NAMETOOLONG: i32 = 37;
+/// Errno of WASI for a file which does not exist.
+const ERRNO_NOENT: i32 = 44;
+/// Errno of WASI for a socket which is not connected.
+const ERRNO_NOTCONN: i32 = 53;
+/// Errno of WASI for a file where a directory is required.
+const ERRNO_NOTDIR: i32 = 54;
+/// Errno of WASI for removing a directory which is not empty.
+const ERRNO_NOTEMPTY: i32 = 55;
+/// Errno of WASI for a file descriptor which is not a socket.
+const ERRNO_NOTSOCK: i32 = 57;
+/// Errno of WASI for an operation the backend does not support, e.g. writing to a read-only filesystem.
+const ERRNO_NOTSUP: i32 = 58;
+/// Errno of WASI for a broken connection.
+const ERRNO_PIPE: i32 = 64;
+/// Errno of WASI for renaming a file between different directories.
+const ERRNO_XDEV: i32 = 75;
+/// Errno of WASI for an operation the instance is not allowed to perform.
+const ERRNO_NOTCAPABLE: i32 = 76;
+
+/// The version of the WASI functions defined by a [`Linker`](crate::exec::Linker).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum WasiVersion {
+	/// The functions of `wasi_snapshot_preview1`, which most toolchains target.
+	Preview1,
+	/// The interfaces of the preview 2 world like `wasi:io/streams`, lowered to core module imports.
+	Preview2,
+}
+
+/// The WASI errno for `error`.
+fn errno(error: &io::Error) -> i32 {
+	match error.kind() {
+		io::ErrorKind::WouldBlock => ERRNO_AGAIN,
+		io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted => ERRNO_CONNRESET,
+		io::ErrorKind::NotConnected => ERRNO_NOTCONN,
+		io::ErrorKind::BrokenPipe => ERRNO_PIPE,
+		io::ErrorKind::InvalidInput => ERRNO_INVAL,
+		io::ErrorKind::NotFound => ERRNO_NOENT,
+		io::ErrorKind::AlreadyExists => ERRNO_EXIST,
+		io::ErrorKind::PermissionDenied => ERRNO_ACCES,
+		io::ErrorKind::NotADirectory => ERRNO_NOTDIR,
+		io::ErrorKind::IsADirectory => ERRNO_ISDIR,
+		io::ErrorKind::DirectoryNotEmpty => ERRNO_NOTEMPTY,
+		io::ErrorKind::Unsupported => ERRNO_NOTSUP,
+		_ => ERRNO_IO,
+	}
+}
+
+pub fn random_get(instance: &mut InstanceRef) -> HostResult {
+	let buf_len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let buf_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+
+	let mem = instance.memory.as_mut().unwrap();
+	let errno = match mem.data.get_mut(buf_ptr..buf_ptr.saturating_add(buf_len)) {
+		Some(buf) => {
+			instance.environment.fill_random(buf);
+			0
+		},
+		None => ERRNO_FAULT,
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+pub fn clock_time_get(instance: &mut InstanceRef) -> HostResult {
+	let time_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let _precision = instance.operand_stack.pop::<i64>()?;
+	let clock = match instance.operand_stack.pop::<i32>()? {
+		0 => Some(Clock::Realtime),
+		1 => Some(Clock::Monotonic),
+		// The CPU time clocks are not supported
+		_ => None,
+	};
+
+	let mem = instance.memory.as_mut().unwrap();
+	let errno = match (clock, mem.data.get_mut(time_ptr..time_ptr.saturating_add(8))) {
+		(Some(clock), Some(time)) => {
+			time.copy_from_slice(&instance.environment.now(clock).to_le_bytes());
+			0
+		},
+		(None, _) => ERRNO_INVAL,
+		(_, None) => ERRNO_FAULT,
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+/// Copies `bytes` to `addr` of the memory `data`, or returns [`None`] if it is outside of the memory.
+fn write_bytes(data: &mut [u8], addr: usize, bytes: &[u8]) -> Option<()> {
+	data.get_mut(addr..addr.checked_add(bytes.len())?)?.copy_from_slice(bytes);
+	Some(())
+}
+
+/// Copies `bytes` into the `buffers` of the memory `data` one after another.
+fn scatter(data: &mut [u8], buffers: Vec<Range<usize>>, mut bytes: &[u8]) {
+	for buffer in buffers {
+		let (chunk, rest) = bytes.split_at(buffer.len().min(bytes.len()));
+		data[buffer.start..buffer.start + chunk.len()].copy_from_slice(chunk);
+		bytes = rest;
+	}
+}
+
+/// The buffers of the `len` iovecs at `ptr`, or [`None`] if one of them is outside of the memory.
+fn iovecs(data: &[u8], ptr: usize, len: usize) -> Option<Vec<Range<usize>>> {
+	const IOVEC_SIZE: usize = 8;
+	(0..len)
+		.map(|index| {
+			let iovec = data.get(ptr + index * IOVEC_SIZE..ptr + (index + 1) * IOVEC_SIZE)?;
+			let buf_ptr = u32::from_le_bytes(iovec[0..4].try_into().unwrap()) as usize;
+			let buf_len = u32::from_le_bytes(iovec[4..8].try_into().unwrap()) as usize;
+			let buffer = buf_ptr..buf_ptr.checked_add(buf_len)?;
+			data.get(buffer.clone()).map(|_| buffer)
+		})
+		.collect()
+}
\ No newline at end of file
@@ -0,0 +1,50 @@
+//! The stdout and stderr of the guest, i.e. file descriptors 1 and 2, which are the ones of the host or piped out by
+//! the host with [`WasiCtx::set_stdout`](crate::exec::WasiCtx::set_stdout) and
+//! [`WasiCtx::set_stderr`](crate::exec::WasiCtx::set_stderr).
+
+use std::fmt;
+use std::io::{self, IoSlice, Write};
+use crate::shared::MaybeSendSync;
+
+/// A writer stdout or stderr is piped into.
+trait Sink: Write + MaybeSendSync {}
+
+impl<T: Write + MaybeSendSync> Sink for T {}
+
+#[derive(Default)]
+pub(crate) struct Output {
+	/// The piped writer, or [`None`] for the stream of the host.
+	writer: Option<Box<dyn Sink>>,
+}
+
+impl Output {
+	pub(crate) fn piped(writer: impl Write + MaybeSendSync + 'static) -> Self {
+		Self { writer: Some(Box::new(writer)) }
+	}
+
+	/// Writes `bufs` to the piped writer, or to `host` if none is piped.
+	pub(crate) fn write_vectored(&mut self, mut host: impl Write, bufs: &[IoSlice]) -> io::Result<usize> {
+		match &mut self.writer {
+			Some(writer) => writer.write_vectored(bufs),
+			None => host.write_vectored(bufs),
+		}
+	}
+
+	/// Writes all of `buf` to the piped writer, or to `host` if none is piped, and flushes it.
+	pub(crate) fn write_all(&mut self, mut host: impl Write, buf: &[u8]) -> io::Result<()> {
+		let writer: &mut dyn Write = match &mut self.writer {
+			Some(writer) => writer,
+			None => &mut host,
+		};
+		writer.write_all(buf)?;
+		writer.flush()
+	}
+}
+
+impl fmt::Debug for Output {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Output")
+			.field("piped", &self.writer.is_some())
+			.finish()
+	}
+}
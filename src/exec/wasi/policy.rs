@@ -0,0 +1,105 @@
+use std::collections::{HashMap, HashSet};
+use super::{ERRNO_2BIG, ERRNO_DQUOT, ERRNO_NOTCAPABLE};
+
+/// Restrictions of the WASI preview 1 functions an instance may call, see [`WasiCtx::set_policy`].
+///
+/// A restricted function is not performed, but pops its parameters and returns an errno to the guest, so that the
+/// guest can handle it like an error of the host system instead of trapping.
+///
+/// [`WasiCtx::set_policy`]: crate::exec::WasiCtx::set_policy
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WasiPolicy {
+	/// The only functions which are performed, or `None` if all functions which are not stubbed are performed.
+	allowed: Option<HashSet<String>>,
+	/// Functions which return the errno instead of being performed.
+	stubbed: HashMap<String, i32>,
+	/// Remaining bytes `fd_write` may write, unlimited if `None`.
+	write_quota: Option<usize>,
+	/// Maximum bytes of the arguments and environment variables, unlimited if `None`.
+	args_limit: Option<u32>,
+	environ_limit: Option<u32>,
+}
+
+impl WasiPolicy {
+	/// Makes the function `name`, e.g. `"path_open"`, fail with the errno `notcapable`.
+	pub fn deny(self, name: &str) -> Self {
+		self.stub(name, ERRNO_NOTCAPABLE)
+	}
+
+	/// Makes the function `name` return `errno` without being performed, e.g. `random_get` with the errno `nosys`.
+	pub fn stub(mut self, name: &str, errno: i32) -> Self {
+		self.stubbed.insert(name.to_owned(), errno);
+		self
+	}
+
+	/// Denies all functions except the ones in `names`.
+	pub fn allow_only<'n>(mut self, names: impl IntoIterator<Item = &'n str>) -> Self {
+		self.allowed = Some(names.into_iter().map(str::to_owned).collect());
+		self
+	}
+
+	/// Limits the total number of bytes written with `fd_write`. A write exceeding the remaining quota is shortened,
+	/// and once it is used up, `fd_write` fails with the errno `dquot`.
+	pub fn write_quota(mut self, bytes: usize) -> Self {
+		self.write_quota = Some(bytes);
+		self
+	}
+
+	/// Limits the total size of the arguments including their terminating zeros. If they are larger,
+	/// `args_sizes_get` and `args_get` fail with the errno `2big`.
+	pub fn args_limit(mut self, bytes: u32) -> Self {
+		self.args_limit = Some(bytes);
+		self
+	}
+
+	/// Limits the total size of the environment variables like [`args_limit`](Self::args_limit) does for the
+	/// arguments.
+	pub fn environ_limit(mut self, bytes: u32) -> Self {
+		self.environ_limit = Some(bytes);
+		self
+	}
+
+	/// The errno the function `name` returns instead of being performed, or `None` if it is allowed.
+	pub(crate) fn restriction(&self, name: &str) -> Option<i32> {
+		if let Some(&errno) = self.stubbed.get(name) {
+			return Some(errno);
+		}
+		match &self.allowed {
+			Some(allowed) if !allowed.contains(name) => Some(ERRNO_NOTCAPABLE),
+			_ => None,
+		}
+	}
+
+	/// Limits a write of `len` bytes to the remaining quota, or fails with the errno `dquot` if it is used up.
+	pub(crate) fn limit_write(&self, len: usize) -> Result<usize, i32> {
+		match self.write_quota {
+			Some(0) if len > 0 => Err(ERRNO_DQUOT),
+			Some(quota) => Ok(len.min(quota)),
+			None => Ok(len),
+		}
+	}
+
+	/// The `size` of the arguments, or the errno `2big` if it exceeds the limit or is unknown as it overflows.
+	pub(crate) fn limit_args(&self, size: Option<u32>) -> Result<u32, i32> {
+		limit(size, self.args_limit)
+	}
+
+	pub(crate) fn limit_environ(&self, size: Option<u32>) -> Result<u32, i32> {
+		limit(size, self.environ_limit)
+	}
+
+	/// Subtracts the `written` bytes from the remaining quota.
+	pub(crate) fn wrote(&mut self, written: usize) {
+		if let Some(quota) = self.write_quota.as_mut() {
+			*quota = quota.saturating_sub(written);
+		}
+	}
+}
+
+fn limit(size: Option<u32>, limit: Option<u32>) -> Result<u32, i32> {
+	match (size, limit) {
+		(Some(size), Some(limit)) if size > limit => Err(ERRNO_2BIG),
+		(Some(size), _) => Ok(size),
+		(None, _) => Err(ERRNO_2BIG),
+	}
+}
\ No newline at end of file
@@ -0,0 +1,138 @@
+//! `poll_oneoff` of WASI preview 1, which waits for clocks and for stdin to become readable.
+
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::exec::{Clock, HostResult, Value};
+use crate::exec::instance::InstanceRef;
+use super::*;
+
+/// Size of a `subscription`.
+const SUBSCRIPTION_SIZE: usize = 48;
+/// Size of an `event`.
+const EVENT_SIZE: usize = 32;
+/// Values of `eventtype`.
+const EVENTTYPE_CLOCK: u8 = 0;
+const EVENTTYPE_FD_READ: u8 = 1;
+const EVENTTYPE_FD_WRITE: u8 = 2;
+/// Flag of `subclockflags` for a timeout which is an absolute time of the clock.
+const SUBCLOCKFLAGS_ABSTIME: u16 = 1;
+/// Flag of `eventrwflags` for the end of a stream.
+const EVENTRWFLAGS_HANGUP: u16 = 1;
+
+/// The condition a subscription waits for.
+enum Condition {
+	Clock(Instant),
+	Read(u32),
+	Write(u32),
+	/// An invalid subscription, which is reported immediately with this errno.
+	Invalid(u8, i32),
+}
+
+/// `poll_oneoff(in_ptr, out_ptr, nsubscriptions, nevents_ptr) -> errno`
+///
+/// Waits until at least one subscription is ready and writes an event for each ready one. Only stdin of the host
+/// may not be ready, the other file descriptors are always ready for reading and writing.
+pub fn poll_oneoff(instance: &mut InstanceRef) -> HostResult {
+	let nevents_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let nsubscriptions = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let out_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let in_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+
+	if nsubscriptions == 0 {
+		return Ok(vec![Value::I32(ERRNO_INVAL)]);
+	}
+	let mem = instance.memory.as_ref().unwrap();
+	let Some(subscriptions) = nsubscriptions.checked_mul(SUBSCRIPTION_SIZE)
+		.and_then(|len| mem.data.get(in_ptr..in_ptr.checked_add(len)?)) else {
+		return Ok(vec![Value::I32(ERRNO_FAULT)]);
+	};
+	let started = Instant::now();
+	let subscriptions: Vec<(u64, Condition)> = subscriptions.chunks_exact(SUBSCRIPTION_SIZE)
+		.map(|subscription| {
+			let read_u64 = |offset: usize| u64::from_le_bytes(subscription[offset..offset + 8].try_into().unwrap());
+			let fd = u32::from_le_bytes(subscription[16..20].try_into().unwrap());
+			let condition = match subscription[8] {
+				EVENTTYPE_CLOCK => {
+					let clock = match fd {
+						0 => Some(Clock::Realtime),
+						1 => Some(Clock::Monotonic),
+						_ => None,
+					};
+					let timeout = read_u64(24);
+					let flags = u16::from_le_bytes(subscription[40..42].try_into().unwrap());
+					match clock {
+						Some(clock) if flags & SUBCLOCKFLAGS_ABSTIME != 0 => {
+							let remaining = timeout.saturating_sub(instance.environment.now(clock));
+							Condition::Clock(started + Duration::from_nanos(remaining))
+						},
+						Some(_) => Condition::Clock(started + Duration::from_nanos(timeout)),
+						None => Condition::Invalid(EVENTTYPE_CLOCK, ERRNO_INVAL),
+					}
+				},
+				EVENTTYPE_FD_READ => Condition::Read(fd),
+				EVENTTYPE_FD_WRITE => Condition::Write(fd),
+				tag => Condition::Invalid(tag, ERRNO_INVAL),
+			};
+			(read_u64(0), condition)
+		})
+		.collect();
+
+	let events = loop {
+		let events: Vec<[u8; EVENT_SIZE]> = subscriptions.iter()
+			.filter_map(|(userdata, condition)| {
+				let (eventtype, errno, nbytes, hangup) = match *condition {
+					Condition::Clock(deadline) if Instant::now() >= deadline => (EVENTTYPE_CLOCK, 0, 0, false),
+					Condition::Clock(_) => return None,
+					Condition::Read(0) => {
+						let (nbytes, eof) = instance.wasi.stdin_mut().wait(Some(Duration::ZERO))?;
+						(EVENTTYPE_FD_READ, 0, nbytes, eof)
+					},
+					Condition::Read(fd) | Condition::Write(fd) => {
+						let eventtype = match condition {
+							Condition::Read(_) => EVENTTYPE_FD_READ,
+							_ => EVENTTYPE_FD_WRITE,
+						};
+						match instance.wasi.get(fd) {
+							Some(_) => (eventtype, 0, 0, false),
+							None if fd < 3 => (eventtype, 0, 0, false),
+							None => (eventtype, ERRNO_BADF, 0, false),
+						}
+					},
+					Condition::Invalid(eventtype, errno) => (eventtype, errno, 0, false),
+				};
+				let mut event = [0u8; EVENT_SIZE];
+				event[0..8].copy_from_slice(&userdata.to_le_bytes());
+				event[8..10].copy_from_slice(&(errno as u16).to_le_bytes());
+				event[10] = eventtype;
+				event[16..24].copy_from_slice(&(nbytes as u64).to_le_bytes());
+				if hangup {
+					event[24..26].copy_from_slice(&EVENTRWFLAGS_HANGUP.to_le_bytes());
+				}
+				Some(event)
+			})
+			.collect();
+		if !events.is_empty() {
+			break events;
+		}
+		// Only clocks and stdin can be pending
+		let deadline = subscriptions.iter()
+			.filter_map(|(_, condition)| match condition {
+				Condition::Clock(deadline) => Some(*deadline),
+				_ => None,
+			})
+			.min();
+		let timeout = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+		match subscriptions.iter().any(|(_, condition)| matches!(condition, Condition::Read(0))) {
+			true => {
+				instance.wasi.stdin_mut().wait(timeout);
+			},
+			false => thread::sleep(timeout.unwrap_or_default()),
+		}
+	};
+
+	let mem = instance.memory.as_mut().unwrap();
+	let errno = write_bytes(&mut mem.data, out_ptr, &events.concat())
+		.and_then(|_| write_bytes(&mut mem.data, nevents_ptr, &(events.len() as u32).to_le_bytes()))
+		.map_or(ERRNO_FAULT, |_| 0);
+	Ok(vec![Value::I32(errno)])
+}
\ No newline at end of file
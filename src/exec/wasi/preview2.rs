@@ -0,0 +1,228 @@
+//! The interfaces of the WASI preview 2 world lowered onto core module imports, as imported by modules built with
+//! newer toolchains or wrapped by the preview 1 adapter.
+//!
+//! Resources are referred to by handles, of which only the standard streams are implemented. Lists returned to the
+//! guest are allocated with its exported `cabi_realloc`, like the canonical ABI does.
+
+use std::io;
+use crate::exec::{Clock, Error, ExecutionResult, HostResult, TrapKind, Value};
+use crate::exec::instance::InstanceRef;
+use crate::parse::Type;
+use super::write_bytes;
+
+/// A host function implementing a lowered preview 2 function.
+type HostFunction = fn(&mut InstanceRef) -> HostResult;
+
+/// Module, field, parameters, results and implementation of a lowered function.
+type Function = (&'static str, &'static str, &'static [Type], &'static [Type], HostFunction);
+
+/// All implemented functions.
+pub(crate) const FUNCTIONS: &[Function] = &[
+	("wasi:cli/stdin@0.2.0", "get-stdin", &[], &[Type::I32], get_stdin),
+	("wasi:cli/stdout@0.2.0", "get-stdout", &[], &[Type::I32], get_stdout),
+	("wasi:cli/stderr@0.2.0", "get-stderr", &[], &[Type::I32], get_stderr),
+	("wasi:io/streams@0.2.0", "[method]input-stream.read", &[Type::I32, Type::I64, Type::I32], &[], input_stream_read),
+	("wasi:io/streams@0.2.0", "[method]input-stream.blocking-read", &[Type::I32, Type::I64, Type::I32], &[], input_stream_read),
+	("wasi:io/streams@0.2.0", "[method]output-stream.check-write", &[Type::I32; 2], &[], output_stream_check_write),
+	("wasi:io/streams@0.2.0", "[method]output-stream.write", &[Type::I32; 4], &[], output_stream_write),
+	("wasi:io/streams@0.2.0", "[method]output-stream.blocking-write-and-flush", &[Type::I32; 4], &[], output_stream_write),
+	("wasi:io/streams@0.2.0", "[method]output-stream.blocking-flush", &[Type::I32; 2], &[], output_stream_flush),
+	("wasi:io/streams@0.2.0", "[resource-drop]input-stream", &[Type::I32], &[], resource_drop),
+	("wasi:io/streams@0.2.0", "[resource-drop]output-stream", &[Type::I32], &[], resource_drop),
+	("wasi:clocks/wall-clock@0.2.0", "now", &[Type::I32], &[], wall_clock_now),
+	("wasi:clocks/monotonic-clock@0.2.0", "now", &[], &[Type::I64], monotonic_clock_now),
+	("wasi:random/random@0.2.0", "get-random-u64", &[], &[Type::I64], get_random_u64),
+	("wasi:random/random@0.2.0", "get-random-bytes", &[Type::I64, Type::I32], &[], get_random_bytes),
+	("wasi:filesystem/preopens@0.2.0", "get-directories", &[Type::I32], &[], get_directories),
+];
+
+/// Handles of the standard streams. Handle 0 is reserved by the canonical ABI.
+const STDIN: i32 = 1;
+const STDOUT: i32 = 2;
+const STDERR: i32 = 3;
+
+/// Number of bytes the guest may write to a stream at once, returned by `check-write`.
+const WRITE_BUDGET: u64 = 4096;
+/// Maximum number of bytes returned by a single read.
+const READ_LIMIT: u64 = 64 * 1024;
+
+/// The error of a stream operation, which is always `closed`, as the `last-operation-failed` error resource is not
+/// implemented.
+enum StreamError {
+	Closed,
+}
+
+fn get_stdin(_instance: &mut InstanceRef) -> HostResult {
+	Ok(vec![Value::I32(STDIN)])
+}
+
+fn get_stdout(_instance: &mut InstanceRef) -> HostResult {
+	Ok(vec![Value::I32(STDOUT)])
+}
+
+fn get_stderr(_instance: &mut InstanceRef) -> HostResult {
+	Ok(vec![Value::I32(STDERR)])
+}
+
+/// `read(this, len, retptr)` returning `result<list<u8>, stream-error>`. Reads block, as only stdin is implemented.
+fn input_stream_read(instance: &mut InstanceRef) -> HostResult {
+	let retptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let len = instance.operand_stack.pop::<i64>()? as u64;
+	let this = instance.operand_stack.pop::<i32>()?;
+
+	let mut buffer = vec![0; len.min(READ_LIMIT) as usize];
+	let read = match this {
+		// Reading nothing at the end of the stream closes it
+		STDIN => match instance.wasi.stdin_mut().read(&mut buffer) {
+			Ok(0) if !buffer.is_empty() => Err(StreamError::Closed),
+			Ok(read) => Ok(read),
+			Err(_) => Err(StreamError::Closed),
+		},
+		_ => Err(StreamError::Closed),
+	};
+	match read {
+		Ok(read) => {
+			let ptr = alloc(instance, read)?;
+			store(instance, ptr, &buffer[..read])?;
+			store(instance, retptr, &[0])?;
+			store(instance, retptr + 4, &(ptr as u32).to_le_bytes())?;
+			store(instance, retptr + 8, &(read as u32).to_le_bytes())?;
+		},
+		Err(error) => store_stream_error(instance, retptr, error)?,
+	}
+	Ok(Vec::new())
+}
+
+/// `check-write(this, retptr)` returning `result<u64, stream-error>`.
+fn output_stream_check_write(instance: &mut InstanceRef) -> HostResult {
+	let retptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let this = instance.operand_stack.pop::<i32>()?;
+
+	match this {
+		STDOUT | STDERR => {
+			store(instance, retptr, &[0])?;
+			store(instance, retptr + 8, &WRITE_BUDGET.to_le_bytes())?;
+		},
+		_ => store_stream_error(instance, retptr, StreamError::Closed)?,
+	}
+	Ok(Vec::new())
+}
+
+/// `write(this, contents_ptr, contents_len, retptr)` returning `result<_, stream-error>`. Writes are flushed
+/// immediately, so this also implements `blocking-write-and-flush`.
+fn output_stream_write(instance: &mut InstanceRef) -> HostResult {
+	let retptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let contents_len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let contents_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let this = instance.operand_stack.pop::<i32>()?;
+
+	let contents = load(instance, contents_ptr, contents_len)?;
+	let written = match this {
+		STDOUT => instance.wasi.stdout_mut().write_all(io::stdout(), &contents),
+		STDERR => instance.wasi.stderr_mut().write_all(io::stderr(), &contents),
+		_ => Err(io::ErrorKind::NotFound.into()),
+	};
+	match written {
+		Ok(()) => store(instance, retptr, &[0])?,
+		Err(_) => store_stream_error(instance, retptr, StreamError::Closed)?,
+	}
+	Ok(Vec::new())
+}
+
+/// `blocking-flush(this, retptr)` returning `result<_, stream-error>`.
+fn output_stream_flush(instance: &mut InstanceRef) -> HostResult {
+	let retptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let this = instance.operand_stack.pop::<i32>()?;
+
+	match this {
+		STDOUT | STDERR => store(instance, retptr, &[0])?,
+		_ => store_stream_error(instance, retptr, StreamError::Closed)?,
+	}
+	Ok(Vec::new())
+}
+
+/// Drops a stream, which is a no-op for the standard streams.
+fn resource_drop(instance: &mut InstanceRef) -> HostResult {
+	let _this = instance.operand_stack.pop::<i32>()?;
+	Ok(Vec::new())
+}
+
+/// `now(retptr)` returning the `datetime` record of seconds and nanoseconds.
+fn wall_clock_now(instance: &mut InstanceRef) -> HostResult {
+	let retptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+
+	let now = instance.environment.now(Clock::Realtime);
+	store(instance, retptr, &(now / 1_000_000_000).to_le_bytes())?;
+	store(instance, retptr + 8, &((now % 1_000_000_000) as u32).to_le_bytes())?;
+	Ok(Vec::new())
+}
+
+/// `now() -> instant` in nanoseconds.
+fn monotonic_clock_now(instance: &mut InstanceRef) -> HostResult {
+	let now = instance.environment.now(Clock::Monotonic);
+	Ok(vec![Value::I64(now as i64)])
+}
+
+fn get_random_u64(instance: &mut InstanceRef) -> HostResult {
+	let mut random = [0; 8];
+	instance.environment.fill_random(&mut random);
+	Ok(vec![Value::I64(i64::from_le_bytes(random))])
+}
+
+/// `get-random-bytes(len, retptr)` returning `list<u8>`.
+fn get_random_bytes(instance: &mut InstanceRef) -> HostResult {
+	let retptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let len = instance.operand_stack.pop::<i64>()? as u64 as usize;
+
+	let mut random = vec![0; len];
+	instance.environment.fill_random(&mut random);
+	let ptr = alloc(instance, len)?;
+	store(instance, ptr, &random)?;
+	store(instance, retptr, &(ptr as u32).to_le_bytes())?;
+	store(instance, retptr + 4, &(len as u32).to_le_bytes())?;
+	Ok(Vec::new())
+}
+
+/// `get-directories(retptr)` returning an empty list, as the filesystem is not implemented.
+fn get_directories(instance: &mut InstanceRef) -> HostResult {
+	let retptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	store(instance, retptr, &[0; 8])?;
+	Ok(Vec::new())
+}
+
+/// Writes the `err` case of a `result<_, stream-error>` to `retptr`.
+fn store_stream_error(instance: &mut InstanceRef, retptr: usize, error: StreamError) -> ExecutionResult {
+	let case = match error {
+		StreamError::Closed => 1u8,
+	};
+	store(instance, retptr, &[1])?;
+	store(instance, retptr + 4, &[case])
+}
+
+/// Allocates `len` bytes in the memory of the guest with its exported `cabi_realloc` and returns their address.
+fn alloc(instance: &mut InstanceRef, len: usize) -> Result<usize, Error> {
+	let realloc = instance.func("cabi_realloc")
+		.ok_or_else(|| Error::UnknownExport("cabi_realloc".to_owned()))?;
+	let args = vec![Value::I32(0), Value::I32(0), Value::I32(1), Value::I32(len as i32)];
+	match realloc.call_from(instance, args)?[..] {
+		[Value::I32(ptr)] => Ok(ptr as u32 as usize),
+		_ => Err(Error::Trap(TrapKind::Other("cabi_realloc did not return a pointer"))),
+	}
+}
+
+/// The `len` bytes at `addr` of the memory.
+fn load(instance: &InstanceRef, addr: usize, len: usize) -> Result<Vec<u8>, Error> {
+	let mem = instance.memory.as_ref().ok_or(Error::NoMemory)?;
+	let range = addr..addr.saturating_add(len);
+	mem.data.get(range.clone())
+		.map(<[u8]>::to_vec)
+		.ok_or(Error::InvalidMemoryArea { addr: range, size: mem.data.len() })
+}
+
+/// Copies `bytes` to `addr` of the memory.
+fn store(instance: &mut InstanceRef, addr: usize, bytes: &[u8]) -> ExecutionResult {
+	let mem = instance.memory.as_mut().ok_or(Error::NoMemory)?;
+	let size = mem.data.len();
+	write_bytes(&mut mem.data, addr, bytes)
+		.ok_or(Error::InvalidMemoryArea { addr: addr..addr.saturating_add(bytes.len()), size })
+}
\ No newline at end of file
@@ -0,0 +1,187 @@
+//! The backend of the WASI filesystem functions on the filesystem of the host.
+
+use std::fs::{self, File, Metadata, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use super::{FileStat, FileType, OpenFlags, WasiDir, WasiFile};
+
+/// A directory of the host, see [`WasiCtx::preopen_dir`](crate::exec::WasiCtx::preopen_dir).
+///
+/// Symlinks may only point into the directory, otherwise accessing them fails with
+/// [`PermissionDenied`](io::ErrorKind::PermissionDenied).
+#[derive(Debug, Clone)]
+pub struct RealDir {
+	root: PathBuf,
+}
+
+impl RealDir {
+	pub fn new(root: impl Into<PathBuf>) -> Self {
+		Self { root: root.into() }
+	}
+
+	/// The host path of `path`, whose parent directories, and the last component if `follow` is set, have to
+	/// resolve to paths inside the directory.
+	fn resolve(&self, path: &Path, follow: bool) -> io::Result<PathBuf> {
+		let root = self.root.canonicalize()?;
+		// The path refers to the directory itself
+		let Some(name) = path.file_name() else {
+			return Ok(root);
+		};
+		let parent = root.join(path.parent().unwrap_or(Path::new(""))).canonicalize()?;
+		if !parent.starts_with(&root) {
+			return Err(escape());
+		}
+		let resolved = parent.join(name);
+		if follow {
+			// A missing target is reported by the caller when accessing it
+			if let Ok(target) = resolved.canonicalize() {
+				return match target.starts_with(&root) {
+					true => Ok(target),
+					false => Err(escape()),
+				};
+			}
+		}
+		Ok(resolved)
+	}
+}
+
+/// The error for a path leaving the directory.
+fn escape() -> io::Error {
+	io::Error::new(io::ErrorKind::PermissionDenied, "Path leaves the directory")
+}
+
+impl WasiDir for RealDir {
+	fn open_file(&self, path: &Path, flags: OpenFlags) -> io::Result<Box<dyn WasiFile>> {
+		let file = OpenOptions::new()
+			.read(flags.read)
+			.write(flags.write)
+			.create(flags.create && !flags.exclusive)
+			.create_new(flags.create && flags.exclusive)
+			.truncate(flags.truncate)
+			.open(self.resolve(path, true)?)?;
+		Ok(Box::new(file))
+	}
+
+	fn open_dir(&self, path: &Path) -> io::Result<Box<dyn WasiDir>> {
+		let resolved = self.resolve(path, true)?;
+		match fs::metadata(&resolved)?.is_dir() {
+			true => Ok(Box::new(RealDir::new(resolved))),
+			false => Err(io::ErrorKind::NotADirectory.into()),
+		}
+	}
+
+	fn stat(&self, path: &Path, follow: bool) -> io::Result<FileStat> {
+		let resolved = self.resolve(path, follow)?;
+		let metadata = match follow {
+			true => fs::metadata(resolved)?,
+			false => fs::symlink_metadata(resolved)?,
+		};
+		Ok(file_stat(&metadata))
+	}
+
+	fn read_dir(&self, path: &Path) -> io::Result<Vec<(String, FileType)>> {
+		let mut entries = fs::read_dir(self.resolve(path, true)?)?
+			.map(|entry| {
+				let entry = entry?;
+				let file_type = file_type(&entry.file_type()?);
+				Ok((entry.file_name().to_string_lossy().into_owned(), file_type))
+			})
+			.collect::<io::Result<Vec<_>>>()?;
+		// The order of the host is arbitrary
+		entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+		Ok(entries)
+	}
+
+	fn create_dir(&self, path: &Path) -> io::Result<()> {
+		fs::create_dir(self.resolve(path, false)?)
+	}
+
+	fn remove_file(&self, path: &Path) -> io::Result<()> {
+		fs::remove_file(self.resolve(path, false)?)
+	}
+
+	fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+		fs::rename(self.resolve(from, false)?, self.resolve(to, false)?)
+	}
+
+	fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+		fs::read_link(self.resolve(path, false)?)
+	}
+}
+
+impl WasiFile for File {
+	#[cfg(unix)]
+	fn read_at(&mut self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+		std::os::unix::fs::FileExt::read_at(self, buf, offset)
+	}
+
+	#[cfg(windows)]
+	fn read_at(&mut self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+		std::os::windows::fs::FileExt::seek_read(self, buf, offset)
+	}
+
+	#[cfg(unix)]
+	fn write_at(&mut self, buf: &[u8], offset: u64) -> io::Result<usize> {
+		std::os::unix::fs::FileExt::write_at(self, buf, offset)
+	}
+
+	#[cfg(windows)]
+	fn write_at(&mut self, buf: &[u8], offset: u64) -> io::Result<usize> {
+		std::os::windows::fs::FileExt::seek_write(self, buf, offset)
+	}
+
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		Seek::seek(self, pos)
+	}
+
+	fn set_len(&mut self, len: u64) -> io::Result<()> {
+		File::set_len(self, len)
+	}
+
+	fn stat(&self) -> io::Result<FileStat> {
+		Ok(file_stat(&self.metadata()?))
+	}
+
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		Read::read(self, buf)
+	}
+
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		Write::write(self, buf)
+	}
+}
+
+fn file_type(file_type: &fs::FileType) -> FileType {
+	match () {
+		_ if file_type.is_dir() => FileType::Directory,
+		_ if file_type.is_file() => FileType::RegularFile,
+		_ if file_type.is_symlink() => FileType::SymbolicLink,
+		_ => FileType::Unknown,
+	}
+}
+
+fn file_stat(metadata: &Metadata) -> FileStat {
+	let nanos = |time: io::Result<SystemTime>| time.ok()
+		.and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+		.map_or(0, |duration| duration.as_nanos() as u64);
+	#[cfg(unix)]
+	let (dev, ino, nlink, ctim) = {
+		use std::os::unix::fs::MetadataExt;
+		let ctim = (metadata.ctime() as u64).wrapping_mul(1_000_000_000).wrapping_add(metadata.ctime_nsec() as u64);
+		(metadata.dev(), metadata.ino(), metadata.nlink(), ctim)
+	};
+	#[cfg(not(unix))]
+	let (dev, ino, nlink, ctim) = (0, 0, 1, nanos(metadata.modified()));
+
+	FileStat {
+		dev,
+		ino,
+		file_type: file_type(&metadata.file_type()),
+		nlink,
+		size: metadata.len(),
+		atim: nanos(metadata.accessed()),
+		mtim: nanos(metadata.modified()),
+		ctim,
+	}
+}
\ No newline at end of file
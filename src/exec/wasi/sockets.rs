@@ -0,0 +1,139 @@
+//! The socket functions of WASI preview 1, which operate on the listeners passed in with
+//! [`WasiCtx::preopen_socket`](crate::exec::WasiCtx::preopen_socket) and the connections accepted from them.
+
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::ops::Range;
+use crate::exec::{Config, HostResult, Value};
+use crate::exec::instance::InstanceRef;
+use super::*;
+
+/// Flag of `sock_recv` to return the data without removing it from the socket.
+const RIFLAGS_RECV_PEEK: i32 = 1;
+/// Flags of `sock_shutdown` for disabling receiving and sending.
+const SDFLAGS_RD: i32 = 1;
+const SDFLAGS_WR: i32 = 2;
+
+/// `sock_accept(fd, flags, result_fd_ptr) -> errno`
+pub fn sock_accept(instance: &mut InstanceRef) -> HostResult {
+	let result_fd_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let flags = instance.operand_stack.pop::<i32>()?;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let accepted = match instance.wasi.get(fd) {
+		_ if !instance.config.allow_network => Err(ERRNO_NOTCAPABLE),
+		Some(Descriptor::Listener(listener)) => listener.accept()
+			.and_then(|(stream, _)| stream.set_nonblocking(flags & FDFLAGS_NONBLOCK != 0).map(|_| stream))
+			.map_err(|error| errno(&error)),
+		Some(Descriptor::Stream(_)) => Err(ERRNO_INVAL),
+		Some(_) => Err(ERRNO_NOTSOCK),
+		None if fd < 3 => Err(ERRNO_NOTSOCK),
+		None => Err(ERRNO_BADF),
+	};
+	let mem = instance.memory.as_mut().unwrap();
+	let errno = match accepted {
+		Ok(stream) => match mem.data.get_mut(result_fd_ptr..result_fd_ptr.saturating_add(4)) {
+			Some(result_fd) => {
+				let fd = instance.wasi.insert(Descriptor::Stream(stream));
+				result_fd.copy_from_slice(&fd.to_le_bytes());
+				0
+			},
+			None => ERRNO_FAULT,
+		},
+		Err(errno) => errno,
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `sock_recv(fd, ri_data_ptr, ri_data_len, ri_flags, ro_datalen_ptr, ro_flags_ptr) -> errno`
+pub fn sock_recv(instance: &mut InstanceRef) -> HostResult {
+	let ro_flags_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let ro_datalen_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let ri_flags = instance.operand_stack.pop::<i32>()?;
+	let ri_data_len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let ri_data_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let mem = instance.memory.as_mut().unwrap();
+	let errno = match stream(instance.config, instance.wasi, fd) {
+		Err(errno) => errno,
+		Ok(mut stream) => match iovecs(&mem.data, ri_data_ptr, ri_data_len) {
+			None => ERRNO_FAULT,
+			Some(buffers) => {
+				// Receive into a single buffer, as a second read could block after the first one returned data
+				let mut received = vec![0; buffers.iter().map(Range::len).sum()];
+				let result = match ri_flags & RIFLAGS_RECV_PEEK != 0 {
+					true => stream.peek(&mut received),
+					false => stream.read(&mut received),
+				};
+				match result {
+					Ok(len) => {
+						scatter(&mut mem.data, buffers, &received[..len]);
+						write_bytes(&mut mem.data, ro_datalen_ptr, &(len as u32).to_le_bytes())
+							.and_then(|_| write_bytes(&mut mem.data, ro_flags_ptr, &0u16.to_le_bytes()))
+							.map_or(ERRNO_FAULT, |_| 0)
+					},
+					Err(error) => errno(&error),
+				}
+			},
+		},
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `sock_send(fd, si_data_ptr, si_data_len, si_flags, so_datalen_ptr) -> errno`
+pub fn sock_send(instance: &mut InstanceRef) -> HostResult {
+	let so_datalen_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let _si_flags = instance.operand_stack.pop::<i32>()?;
+	let si_data_len = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let si_data_ptr = instance.operand_stack.pop::<i32>()? as u32 as usize;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let mem = instance.memory.as_mut().unwrap();
+	let errno = match stream(instance.config, instance.wasi, fd) {
+		Err(errno) => errno,
+		Ok(mut stream) => match iovecs(&mem.data, si_data_ptr, si_data_len) {
+			None => ERRNO_FAULT,
+			Some(buffers) => {
+				let data: Vec<u8> = buffers.into_iter().flat_map(|buffer| mem.data[buffer].to_vec()).collect();
+				match stream.write(&data) {
+					Ok(len) => write_bytes(&mut mem.data, so_datalen_ptr, &(len as u32).to_le_bytes())
+						.map_or(ERRNO_FAULT, |_| 0),
+					Err(error) => errno(&error),
+				}
+			},
+		},
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+/// `sock_shutdown(fd, how) -> errno`
+pub fn sock_shutdown(instance: &mut InstanceRef) -> HostResult {
+	let how = instance.operand_stack.pop::<i32>()?;
+	let fd = instance.operand_stack.pop::<i32>()? as u32;
+
+	let how = match how {
+		SDFLAGS_RD => Some(Shutdown::Read),
+		SDFLAGS_WR => Some(Shutdown::Write),
+		how if how == SDFLAGS_RD | SDFLAGS_WR => Some(Shutdown::Both),
+		_ => None,
+	};
+	let errno = match (stream(instance.config, instance.wasi, fd), how) {
+		(Err(errno), _) => errno,
+		(Ok(_), None) => ERRNO_INVAL,
+		(Ok(stream), Some(how)) => stream.shutdown(how).map_or_else(|error| errno(&error), |_| 0),
+	};
+	Ok(vec![Value::I32(errno)])
+}
+
+/// The connection `fd` refers to, or the errno if it is not a connection or the network is not allowed.
+fn stream<'a>(config: &Config, wasi: &'a WasiCtx, fd: u32) -> Result<&'a TcpStream, i32> {
+	match wasi.get(fd) {
+		_ if !config.allow_network => Err(ERRNO_NOTCAPABLE),
+		Some(Descriptor::Stream(stream)) => Ok(stream),
+		Some(Descriptor::Listener(_)) => Err(ERRNO_NOTCONN),
+		Some(_) => Err(ERRNO_NOTSOCK),
+		None if fd < 3 => Err(ERRNO_NOTSOCK),
+		None => Err(ERRNO_BADF),
+	}
+}
\ No newline at end of file
@@ -0,0 +1,140 @@
+//! The stdin of the guest, i.e. file descriptor 0, which is either the stdin of the host or piped in by the host
+//! with [`WasiCtx::set_stdin`](crate::exec::WasiCtx::set_stdin).
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+use crate::shared::MaybeSendSync;
+
+/// Number of bytes the reader thread reads from the stdin of the host at once.
+const CHUNK_SIZE: usize = 4096;
+
+/// The stdin of the host, which is read by a thread once the guest reads without blocking or polls it, so that its
+/// readiness is known without blocking. Until then, reads block on the stdin of the host directly.
+static HOST_STDIN: HostStdin = HostStdin {
+	buffer: Mutex::new(HostBuffer { data: VecDeque::new(), eof: false, reading: false }),
+	changed: Condvar::new(),
+};
+
+struct HostStdin {
+	buffer: Mutex<HostBuffer>,
+	/// Notified when the reader thread added data or reached the end.
+	changed: Condvar,
+}
+
+struct HostBuffer {
+	/// Data read by the thread, which the guest did not read yet.
+	data: VecDeque<u8>,
+	eof: bool,
+	/// Whether the reader thread was started.
+	reading: bool,
+}
+
+/// A reader piped into stdin.
+trait Input: Read + MaybeSendSync {}
+
+impl<T: Read + MaybeSendSync> Input for T {}
+
+#[derive(Default)]
+pub(crate) struct Stdin {
+	/// The piped stdin, or [`None`] for the stdin of the host.
+	reader: Option<Box<dyn Input>>,
+	/// Whether reads fail with `again` instead of blocking, set with `fd_fdstat_set_flags`.
+	pub(crate) nonblocking: bool,
+}
+
+impl Stdin {
+	pub(crate) fn piped(reader: impl Read + MaybeSendSync + 'static) -> Self {
+		Self { reader: Some(Box::new(reader)), nonblocking: false }
+	}
+
+	/// Reads into `buf`, which fails with [`WouldBlock`](io::ErrorKind::WouldBlock) if no data is available and
+	/// the stdin is non-blocking. A piped stdin is always considered available.
+	pub(crate) fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if let Some(reader) = &mut self.reader {
+			return reader.read(buf);
+		}
+		let mut buffer = HOST_STDIN.lock();
+		if !buffer.reading && !self.nonblocking {
+			drop(buffer);
+			return io::stdin().read(buf);
+		}
+		buffer.start();
+		while buffer.data.is_empty() && !buffer.eof {
+			if self.nonblocking {
+				return Err(io::ErrorKind::WouldBlock.into());
+			}
+			buffer = HOST_STDIN.changed.wait(buffer).unwrap_or_else(|poisoned| poisoned.into_inner());
+		}
+		let len = buf.len().min(buffer.data.len());
+		for (byte, read) in buf.iter_mut().zip(buffer.data.drain(..len)) {
+			*byte = read;
+		}
+		Ok(len)
+	}
+
+	/// Waits up to `timeout`, or indefinitely if it is [`None`], until data is available or the end is reached,
+	/// and returns the number of available bytes and whether the end was reached.
+	pub(crate) fn wait(&self, timeout: Option<Duration>) -> Option<(usize, bool)> {
+		if self.reader.is_some() {
+			return Some((0, false));
+		}
+		let mut buffer = HOST_STDIN.lock();
+		buffer.start();
+		let ready = |buffer: &HostBuffer| !buffer.data.is_empty() || buffer.eof;
+		buffer = match timeout {
+			Some(timeout) => HOST_STDIN.changed.wait_timeout_while(buffer, timeout, |buffer| !ready(buffer))
+				.map_or_else(|poisoned| poisoned.into_inner().0, |(buffer, _)| buffer),
+			None => HOST_STDIN.changed.wait_while(buffer, |buffer| !ready(buffer))
+				.unwrap_or_else(|poisoned| poisoned.into_inner()),
+		};
+		ready(&buffer).then_some((buffer.data.len(), buffer.eof))
+	}
+}
+
+impl fmt::Debug for Stdin {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Stdin")
+			.field("piped", &self.reader.is_some())
+			.field("nonblocking", &self.nonblocking)
+			.finish()
+	}
+}
+
+impl HostStdin {
+	fn lock(&self) -> MutexGuard<'_, HostBuffer> {
+		// The buffer stays consistent even if a thread panicked while holding the lock
+		self.buffer.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+}
+
+impl HostBuffer {
+	/// Starts the reader thread, unless it is already running.
+	fn start(&mut self) {
+		if self.reading {
+			return;
+		}
+		self.reading = true;
+		thread::spawn(|| {
+			let mut chunk = [0; CHUNK_SIZE];
+			loop {
+				let read = io::stdin().read(&mut chunk);
+				let mut buffer = HOST_STDIN.lock();
+				match read {
+					Ok(0) => buffer.eof = true,
+					Ok(len) => buffer.data.extend(&chunk[..len]),
+					Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+					// The guest can not distinguish a failing stdin from a closed one
+					Err(_) => buffer.eof = true,
+				}
+				HOST_STDIN.changed.notify_all();
+				if buffer.eof {
+					return;
+				}
+			}
+		});
+	}
+}
\ No newline at end of file
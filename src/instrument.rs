@@ -0,0 +1,139 @@
+//! Transformations of a [Module] which insert instrumentation into the function bodies.
+//!
+//! The transformed module can be executed directly or [encoded](crate::encode) into a binary for other runtimes.
+
+use std::mem;
+use crate::shared::Shared;
+use crate::exec::{ExternFunction, FunctionSignature, Identifier, Instruction};
+use crate::parse::{Module, Type};
+
+/// Module name of the imported gas function.
+pub const GAS_MODULE: &str = "env";
+/// Field name of the imported gas function.
+pub const GAS_FIELD: &str = "gas";
+
+/// Rewrites the function bodies of `module` to call the imported function `env.gas` with the summed `cost` of the
+/// instructions of every basic block before the block is executed.
+///
+/// The gas function has the signature `(i32) -> ()`, where the amount is meant to be interpreted as unsigned. Unlike
+/// fuel, the metering is part of the module, so it is deterministic and the same for every runtime executing it.
+///
+/// A basic block ends after every branch and every block, loop or if, so that the instructions following them are
+/// only charged if they are reached. The instructions inserted for metering are not charged themselves.
+///
+/// If `module` does not already import `env.gas`, the import is added after the existing ones, which shifts the
+/// indices of all defined functions by one. Calls, `ref.func`, element segments, exports and the start function are
+/// adjusted accordingly. The positions of instructions in the original binary are discarded.
+pub fn inject_gas(module: &mut Module, cost: impl Fn(&Instruction) -> u32) {
+	let signature = FunctionSignature { params: vec![Type::I32], results: Vec::new() };
+	let existing_import = module.functions.imports().position(|import| {
+		import.name.module == GAS_MODULE && import.name.field == GAS_FIELD && *import.signature == signature
+	});
+	let (gas_index, shift) = match existing_import {
+		Some(index) => (index, 0),
+		None => {
+			let signature = match module.types.iter().find(|declared| ***declared == signature) {
+				Some(declared) => Shared::clone(declared),
+				None => {
+					let signature = Shared::new(signature);
+					module.types.push(Shared::clone(&signature));
+					signature
+				},
+			};
+			let index = module.functions.push_import(ExternFunction {
+				name: Identifier { module: GAS_MODULE.to_owned(), field: GAS_FIELD.to_owned() },
+				signature,
+			});
+			(index, 1)
+		},
+	};
+	let metering = Metering { gas_index, shift, cost };
+	for function in module.functions.local_mut() {
+		let function = Shared::make_mut(function);
+		function.body = metering.meter(mem::take(&mut function.body));
+		function.body_offset = 0;
+		function.offsets.clear();
+	}
+
+	for segment in &mut module.elements {
+		for function_index in segment.init.iter_mut().flatten() {
+			*function_index = metering.shifted(*function_index);
+		}
+	}
+	module.start = module.start.map(|start| metering.shifted(start));
+	for function_index in module.export_map.functions.values_mut() {
+		*function_index = metering.shifted(*function_index);
+	}
+	module.update_function_metadata();
+}
+
+struct Metering<F> {
+	/// Function index of the gas function.
+	gas_index: usize,
+	/// Number of imports added before the defined functions.
+	shift: usize,
+	cost: F,
+}
+
+impl<F: Fn(&Instruction) -> u32> Metering<F> {
+	/// The function index `function_index` after adding the gas import.
+	fn shifted(&self, function_index: usize) -> usize {
+		// An added gas import takes the index of the first defined function
+		match function_index >= self.gas_index {
+			true => function_index + self.shift,
+			false => function_index,
+		}
+	}
+
+	/// Inserts a call to the gas function at the start of every basic block in `instructions` and the nested ones.
+	fn meter(&self, instructions: Vec<Instruction>) -> Vec<Instruction> {
+		let mut metered = Vec::with_capacity(instructions.len() + 2);
+		let mut block = Vec::new();
+		let mut block_cost = 0u32;
+
+		for mut instruction in instructions {
+			block_cost = block_cost.saturating_add((self.cost)(&instruction));
+			match &mut instruction {
+				Instruction::Block { instructions, .. } | Instruction::Loop { instructions, .. } => {
+					*instructions = self.meter(mem::take(instructions));
+				},
+				Instruction::If { if_instructions, else_instructions, .. } => {
+					*if_instructions = self.meter(mem::take(if_instructions));
+					*else_instructions = self.meter(mem::take(else_instructions));
+				},
+				Instruction::Try { instructions, catches, .. } => {
+					*instructions = self.meter(mem::take(instructions));
+					for catch in catches {
+						catch.instructions = self.meter(mem::take(&mut catch.instructions));
+					}
+				},
+				Instruction::Call { function_index } | Instruction::ReturnCall { function_index }
+				| Instruction::RefFunc(function_index) => {
+					*function_index = self.shifted(*function_index);
+				},
+				_ => (),
+			}
+			let ends_block = matches!(instruction,
+				Instruction::Block { .. } | Instruction::Loop { .. } | Instruction::If { .. } | Instruction::Br { .. }
+				| Instruction::BrIf { .. } | Instruction::BrTable { .. } | Instruction::Return | Instruction::Unreachable
+				| Instruction::Try { .. } | Instruction::Throw(_) | Instruction::Rethrow(_)
+				| Instruction::ReturnCall { .. } | Instruction::ReturnCallIndirect { .. }
+			);
+			block.push(instruction);
+			if ends_block {
+				self.charge(&mut metered, mem::take(&mut block), mem::take(&mut block_cost));
+			}
+		}
+		self.charge(&mut metered, block, block_cost);
+		metered
+	}
+
+	/// Appends the call to the gas function with `cost` followed by `block` to `metered`.
+	fn charge(&self, metered: &mut Vec<Instruction>, block: Vec<Instruction>, cost: u32) {
+		if cost > 0 {
+			metered.push(Instruction::I32Const(cost as i32));
+			metered.push(Instruction::Call { function_index: self.gas_index });
+		}
+		metered.extend(block);
+	}
+}
\ No newline at end of file
@@ -1,4 +1,27 @@
+/// `tracing::trace!` in hot paths like the interpreter loop, which is compiled out without the `trace-hot-paths`
+/// feature.
+macro_rules! hot_trace {
+	($($arg:tt)*) => {
+		#[cfg(feature = "trace-hot-paths")]
+		tracing::trace!($($arg)*)
+	};
+}
+
 pub mod parse;
+pub mod encode;
+pub mod instrument;
+pub mod optimize;
+pub mod transpile;
+pub mod wizen;
 pub mod exec;
-// pub mod wasi;
+pub mod shared;
+pub mod prelude;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "web")]
+pub mod web;
 
+pub use parse::Module;
+pub use exec::{Config, Instance, Linker, Value};
+/// Why an execution trapped, as contained in [`exec::Error::Trap`].
+pub use exec::TrapKind as Trap;
@@ -1,29 +1,473 @@
 use rust_wasm_runtime::{
-    exec::Instance,
-    parse::Module,
+    exec::{Breakpoint, Config, DebugAction, DebugContext, Debugger, Instance, Value, WasiCtx},
+    parse::{ExportKind, Module, ModuleFunction, Type},
 };
 use std::error::Error;
-use std::fs;
+use std::ops::Range;
+use std::io::{self, BufRead, Write};
+use std::{env, fs};
 
 
 fn main() -> Result<(), Box<dyn Error>> {
     init_logger();
 
-    // let path = "target/wasm32-wasi/release/rust_wasm_runtime.wasm";
-    let path = "example.wasm";
-    // let path = "locals.wasm";
-    let code = fs::File::open(path)?;
-    let module = Module::new(code)?;
-    tracing::debug!("{:#?}", module);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (flags, positional): (Vec<&str>, Vec<&str>) = args.iter()
+        .map(String::as_str)
+        .partition(|arg| arg.starts_with("--"));
+    let mut config = Config::default()
+        .trace(flags.contains(&"--trace"))
+        .profile(flags.contains(&"--profile") || flags.iter().any(|flag| flag.starts_with("--profile-folded=")))
+        .heap_profile(flags.contains(&"--heap-profile"))
+        .opcode_stats(flags.contains(&"--opcode-stats"))
+        .lenient_mode(flags.contains(&"--lenient"))
+        .env_log(flags.contains(&"--env-log"))
+        .deterministic(flags.contains(&"--deterministic"));
+    if let Some(path) = flags.iter().find_map(|flag| flag.strip_prefix("--coredump=")) {
+        config = config.coredump_on_trap(path);
+    }
+    let mut wasi = WasiCtx::new();
+    // Without the flag, the guest reads the stdin of the host, e.g. a terminal or a pipe
+    if let Some(path) = flags.iter().find_map(|flag| flag.strip_prefix("--stdin=")) {
+        wasi.set_stdin(fs::File::open(path)?);
+    }
+    wasi.set_env(flags.iter().filter_map(|flag| flag.strip_prefix("--env=")?.split_once('=')));
+    // The host directory is visible to the guest under the same path, e.g. `--dir=.` for relative paths
+    for path in flags.iter().filter_map(|flag| flag.strip_prefix("--dir=")) {
+        wasi.preopen_dir(path, path);
+    }
+    let dump_memory = match flags.iter().find_map(|flag| flag.strip_prefix("--dump-memory=")) {
+        Some(range) => Some(parse_range(range).ok_or("Usage: --dump-memory=<start>..<end>")?),
+        None => None,
+    };
+    let options = RunOptions {
+        profile_folded_path: flags.iter().find_map(|flag| flag.strip_prefix("--profile-folded=")),
+        strict: flags.contains(&"--strict"),
+        optimize: flags.contains(&"--optimize"),
+        stats: flags.contains(&"--stats"),
+        dump_memory,
+        dump_memory_path: flags.iter().find_map(|flag| flag.strip_prefix("--dump-memory-to=")),
+    };
+
+    match positional.first().copied() {
+        Some("inspect") => {
+            let path = positional.get(1).ok_or("Usage: rust-wasm-runtime inspect <module.wasm>")?;
+            inspect(path)
+        },
+        Some("debug") => {
+            let path = positional.get(1).ok_or("Usage: rust-wasm-runtime debug <module.wasm>")?;
+            debug(path, config)
+        },
+        Some("repl") => {
+            let path = positional.get(1).ok_or("Usage: rust-wasm-runtime repl <module.wasm> [<args>]")?;
+            wasi.set_args(positional[1..].iter().copied());
+            // A trap only aborts the current command
+            repl(path, config.allow_resume_after_trap(true), wasi)
+        },
+        Some("compile") => {
+            let usage = "Usage: rust-wasm-runtime compile <module.wasm> -o <module.rs>";
+            let path = positional.get(1).ok_or(usage)?;
+            let output = match positional.get(2..) {
+                Some(["-o", output]) => output,
+                _ => return Err(usage.into()),
+            };
+            compile(path, output)
+        },
+        Some("run") => {
+            let path = positional.get(1).ok_or("Usage: rust-wasm-runtime run [--trace] [--profile] [--profile-folded=<file>] [--heap-profile] [--coredump=<file>] [--strict] [--lenient] [--env-log] [--deterministic] [--optimize] [--stats] [--opcode-stats] [--dump-memory=<start>..<end>] [--dump-memory-to=<file>] [--stdin=<file>] [--env=<key>=<value>] [--dir=<path>] <module.wasm> [<args>]")?;
+            // The module is the first argument of the guest like the program name of a native program
+            wasi.set_args(positional[1..].iter().copied());
+            run(path, config, wasi, options)
+        },
+        Some(path) => {
+            wasi.set_args(positional.iter().copied());
+            run(path, config, wasi, options)
+        },
+        // let path = "target/wasm32-wasi/release/rust_wasm_runtime.wasm";
+        // let path = "locals.wasm";
+        None => run("example.wasm", config, wasi, options),
+    }
+}
+
+/// The flags of the `run` command which are not part of the [`Config`] or [`WasiCtx`].
+struct RunOptions<'a> {
+    profile_folded_path: Option<&'a str>,
+    strict: bool,
+    optimize: bool,
+    stats: bool,
+    /// Range of the memory to print as hexdump after the run.
+    dump_memory: Option<Range<usize>>,
+    /// File to write the whole memory to after the run.
+    dump_memory_path: Option<&'a str>,
+}
+
+fn run(path: &str, config: Config, wasi: WasiCtx, options: RunOptions) -> Result<(), Box<dyn Error>> {
+    let mut module = Module::from_bytes(fs::read(path)?)?;
+    if options.strict {
+        module.validate_alignment()?;
+    }
+    if options.optimize {
+        rust_wasm_runtime::optimize::optimize(&mut module);
+    }
+    tracing::debug!(?module);
+
+    let mut instance = Instance::with_config(module, config)?;
+    instance.set_wasi(wasi);
+    if let Err(error) = instance.start() {
+        #[cfg(feature = "dwarf")]
+        print_trap_location(&instance);
+        return Err(error.into());
+    }
+    match (instance.memory(), options.dump_memory) {
+        (Some(memory), Some(range)) => print!("{}", memory.hexdump(range)),
+        (None, Some(_)) => println!("The module has no memory"),
+        (_, None) => (),
+    }
+    if let (Some(memory), Some(path)) = (instance.memory(), options.dump_memory_path) {
+        fs::write(path, memory.data())?;
+    }
+
+    if let Some(profiler) = instance.profiler() {
+        match options.profile_folded_path {
+            Some(profile_folded_path) => fs::write(profile_folded_path, profiler.folded_stacks())?,
+            None => print!("{}", profiler.summary()),
+        }
+    }
+    if let Some(heap_profiler) = instance.heap_profiler() {
+        print!("{}", heap_profiler.summary());
+    }
+    if options.stats {
+        println!("{:#?}", instance.stats());
+    }
+    // Only counted with `--opcode-stats`
+    let opcodes = instance.stats().opcodes;
+    if !opcodes.is_empty() {
+        print!("{}", opcodes.summary());
+    }
+
+    Ok(())
+}
+
+/// Prints the source line of the instruction which trapped, if the module contains debug info.
+#[cfg(feature = "dwarf")]
+fn print_trap_location(instance: &Instance) {
+    let Some(position) = instance.last_position() else {
+        return;
+    };
+    match rust_wasm_runtime::exec::SourceMap::new(instance.module()) {
+        Ok(source_map) => match source_map.locate(instance.module(), position) {
+            Some(location) => eprintln!("Trapped at {}", location),
+            None => eprintln!("Trapped in function {} at instruction {}", position.function, position.offset),
+        },
+        Err(error) => tracing::warn!(%error, "Failed to read debug info"),
+    }
+}
+
+/// Runs the module at `path` with an interactive debugger, pausing before the first instruction.
+fn debug(path: &str, config: Config) -> Result<(), Box<dyn Error>> {
+    let module = Module::from_bytes(fs::read(path)?)?;
 
-    let mut instance = Instance::new(module);
+    let mut instance = Instance::with_config(module, config)?;
+    instance.set_debugger(Debugger::new(debug_prompt).step_first());
+    println!("Type `help` for a list of commands");
     instance.start()?;
-    if let Some(mem) = instance.memory() {
-        tracing::info!("Memory dump: {:?}", &mem.data()[0..50]);
-    } else {
-        tracing::info!("no memory");
+    println!("Execution finished");
+    Ok(())
+}
+
+/// Reads debugger commands from stdin until one of them resumes the execution.
+fn debug_prompt(context: &mut DebugContext) -> DebugAction {
+    if let Some(written) = &context.watchpoint_hit {
+        println!("Watchpoint hit by a write to {:?}", written);
+    }
+    println!("[{}:{}] {:?}", context.function_index, context.offset, context.instruction);
+    let stdin = io::stdin();
+    loop {
+        print!("(debug) ");
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => return DebugAction::Abort,
+            Ok(_) => (),
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let numbers: Vec<usize> = words.iter().skip(1).filter_map(|word| word.parse().ok()).collect();
+        match words.first().copied() {
+            Some("s" | "step") | None => return DebugAction::Step,
+            Some("c" | "continue") => return DebugAction::Continue,
+            Some("q" | "quit") => return DebugAction::Abort,
+            Some("b" | "break") => match numbers[..] {
+                [function, offset] => { context.breakpoints.insert(Breakpoint { function, offset }); },
+                _ => println!("Usage: break <function> <offset>"),
+            },
+            Some("d" | "delete") => match numbers[..] {
+                [function, offset] => { context.breakpoints.remove(&Breakpoint { function, offset }); },
+                _ => println!("Usage: delete <function> <offset>"),
+            },
+            Some("w" | "watch") => match numbers[..] {
+                [addr] => context.watchpoints.push(addr..addr + 1),
+                [addr, len] => context.watchpoints.push(addr..addr + len),
+                _ => println!("Usage: watch <addr> [len]"),
+            },
+            Some("unwatch") => match numbers[..] {
+                [addr, ..] => context.watchpoints.retain(|range| !range.contains(&addr)),
+                _ => println!("Usage: unwatch <addr>"),
+            },
+            Some("i" | "info") => {
+                for breakpoint in context.breakpoints.iter() {
+                    println!("  {}:{}", breakpoint.function, breakpoint.offset);
+                }
+                for range in context.watchpoints.iter() {
+                    println!("  {:?}", range);
+                }
+            },
+            Some("l" | "locals") => {
+                for (index, value) in context.frame().locals().iter().enumerate() {
+                    println!("  [{}] {:?}", index, value);
+                }
+            },
+            Some("st" | "stack") => {
+                for value in context.operand_stack.top(context.operand_stack.len()).iter().rev() {
+                    println!("  {:?}", value);
+                }
+            },
+            Some("bt" | "backtrace") => {
+                for frame in context.call_stack.iter().rev() {
+                    println!("  [{}] {}", frame.function_index(), frame);
+                }
+            },
+            Some("m" | "memory") => match (context.memory, numbers.first()) {
+                (Some(memory), Some(&addr)) => {
+                    let len = numbers.get(1).copied().unwrap_or(16);
+                    let end = usize::min(addr.saturating_add(len), memory.data().len());
+                    println!("  {:?}", &memory.data()[addr.min(end)..end]);
+                },
+                (Some(_), None) => println!("Usage: memory <addr> [len]"),
+                (None, _) => println!("The module has no memory"),
+            },
+            Some("h" | "help") => println!(
+                "step | continue | break <function> <offset> | delete <function> <offset> | watch <addr> [len] | \
+                unwatch <addr> | info | locals | stack | backtrace | memory <addr> [len] | quit"
+            ),
+            Some(other) => println!("Unknown command `{}`", other),
+        }
+    }
+}
+
+/// Instantiates the module at `path` and calls its exports as typed in by the user, all on the same instance.
+fn repl(path: &str, config: Config, wasi: WasiCtx) -> Result<(), Box<dyn Error>> {
+    let module = Module::from_bytes(fs::read(path)?)?;
+    let mut instance = Instance::with_config(module, config)?;
+    instance.set_wasi(wasi);
+    println!("Type `help` for a list of commands");
+    print_exports(&instance);
+
+    let stdin = io::stdin();
+    loop {
+        print!("(repl) ");
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => return Ok(()),
+            Ok(_) => (),
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let numbers: Vec<usize> = words.iter().skip(1).filter_map(|word| parse_number(word)).collect();
+        match words[..] {
+            [] => (),
+            ["q" | "quit"] => return Ok(()),
+            ["h" | "help"] => println!(
+                "<function> [<args>] | call <function> [<args>] | exports | global <name> [value] | \
+                memory <addr> [len] | quit"
+            ),
+            ["e" | "exports"] => print_exports(&instance),
+            ["g" | "global", name] => match instance.global(name) {
+                Some(global) => println!("{:?}", global.get(&instance)),
+                None => println!("No exported global `{}`", name),
+            },
+            ["g" | "global", name, value] => match instance.global(name) {
+                Some(global) => match parse_value(&global.ty(), value) {
+                    Ok(value) => {
+                        if let Err(error) = global.set(&mut instance, value) {
+                            println!("Error: {}", error);
+                        }
+                    },
+                    Err(message) => println!("{}", message),
+                },
+                None => println!("No exported global `{}`", name),
+            },
+            ["m" | "memory", ..] => match (instance.memory(), numbers.first()) {
+                (Some(memory), Some(&addr)) => {
+                    let len = numbers.get(1).copied().unwrap_or(64);
+                    print!("{}", memory.hexdump(addr..addr.saturating_add(len)));
+                },
+                (Some(_), None) => println!("Usage: memory <addr> [len]"),
+                (None, _) => println!("The module has no memory"),
+            },
+            ["call", name, ref args @ ..] | [name, ref args @ ..] => call_export(&mut instance, name, args),
+        }
+    }
+}
+
+/// Prints the exports of `instance` with the signatures of the functions.
+fn print_exports(instance: &Instance) {
+    for export in instance.module().exports() {
+        match (&export.kind, instance.get_func(&export.name)) {
+            (ExportKind::Function, Some(func)) => {
+                println!("  {} {:?} -> {:?}", export.name, func.signature().params, func.signature().results);
+            },
+            (kind, _) => println!("  {} ({:?})", export.name, kind),
+        }
+    }
+}
+
+/// Calls the exported function `name` with `args` parsed as the types of its parameters and prints the results.
+fn call_export(instance: &mut Instance, name: &str, args: &[&str]) {
+    let Some(func) = instance.get_func(name) else {
+        println!("No exported function `{}`, see `exports`", name);
+        return;
+    };
+    let params = &func.signature().params;
+    if params.len() != args.len() {
+        println!("`{}` takes {} arguments of types {:?}", name, params.len(), params);
+        return;
+    }
+    let values: Result<Vec<Value>, String> = params.iter().zip(args).map(|(ty, arg)| parse_value(ty, arg)).collect();
+    let values = match values {
+        Ok(values) => values,
+        Err(message) => {
+            println!("{}", message);
+            return;
+        },
+    };
+    match func.call(instance, values) {
+        Ok(results) => {
+            for result in results {
+                println!("{:?}", result);
+            }
+        },
+        Err(error) => println!("Error: {}", error),
+    }
+}
+
+/// Parses `word` as a value of type `ty`. Integers may also be written in hex with a `0x` prefix.
+fn parse_value(ty: &Type, word: &str) -> Result<Value, String> {
+    let invalid = || format!("`{}` is not a valid {:?}", word, ty);
+    let integer = || match word.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).map(|value| value as i64).map_err(|_| invalid()),
+        None => word.parse::<i64>().map_err(|_| invalid()),
+    };
+    match ty {
+        Type::I32 => integer().map(|value| Value::I32(value as i32)),
+        Type::I64 => integer().map(Value::I64),
+        Type::F32 => word.parse().map(Value::F32).map_err(|_| invalid()),
+        Type::F64 => word.parse().map(Value::F64).map_err(|_| invalid()),
+        other => Err(format!("Arguments of type {:?} are not supported", other)),
+    }
+}
+
+/// Parses an address or length, which may be written in hex with a `0x` prefix.
+fn parse_number(word: &str) -> Option<usize> {
+    match word.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => word.parse().ok(),
+    }
+}
+
+/// Parses a range of addresses like `0x100..0x200`.
+fn parse_range(range: &str) -> Option<Range<usize>> {
+    let (start, end) = range.split_once("..")?;
+    Some(parse_number(start)?..parse_number(end)?)
+}
+
+/// Translates the module at `path` into Rust source and writes it to `output`.
+fn compile(path: &str, output: &str) -> Result<(), Box<dyn Error>> {
+    let module = Module::from_bytes(fs::read(path)?)?;
+    fs::write(output, rust_wasm_runtime::transpile::transpile(&module)?)?;
+    Ok(())
+}
+
+/// Prints a summary of the module at `path`.
+fn inspect(path: &str) -> Result<(), Box<dyn Error>> {
+    let module = Module::from_bytes(fs::read(path)?)?;
+
+    println!("Sections:");
+    for section in module.sections() {
+        println!("  {:<10} {:>8} bytes at {:#x}", format!("{:?}", section.id), section.size, section.offset);
+    }
+
+    println!("Types:");
+    for (index, signature) in module.types().iter().enumerate() {
+        println!("  [{}] {:?} -> {:?}", index, signature.params, signature.results);
+    }
+
+    println!("Imports:");
+    for (index, import) in module.imports().enumerate() {
+        println!("  [{}] {} {:?} -> {:?}", index, import.name, import.signature.params, import.signature.results);
+    }
+
+    println!("Functions:");
+    for function in module.functions().filter_map(|function| match function {
+        ModuleFunction::Defined(function) => Some(function),
+        ModuleFunction::Imported(_) => None,
+    }) {
+        println!(
+            "  [{}] {:?} -> {:?}, {} locals, {} instructions",
+            function.index, function.signature.params, function.signature.results,
+            function.locals.len(), function.body.len()
+        );
+    }
+
+    println!("Exports:");
+    for export in module.exports() {
+        println!("  {:?} `{}` -> {}", export.kind, export.name, export.index);
     }
 
+    println!("Memory:");
+    match module.memory() {
+        Some(memory) => {
+            let shared = if memory.shared { " (shared)" } else { "" };
+            let import = memory.import.as_ref().map(|name| format!(" imported from {}", name)).unwrap_or_default();
+            println!("  pages {:?}{}{}", memory.page_limit, shared, import);
+            for segment in &memory.init {
+                match &segment.offset {
+                    Some(offset) => println!("  data at {:?}, {:#x} bytes", offset.instructions, segment.data.len()),
+                    None => println!("  data {:#x}..{:#x}", segment.addr, segment.addr + segment.data.len()),
+                }
+            }
+        },
+        None => println!("  none"),
+    }
+
+    println!("Tables:");
+    for (index, table) in module.tables().iter().enumerate() {
+        let import = table.import.as_ref().map(|name| format!(" imported from {}", name)).unwrap_or_default();
+        println!("  [{}] {:?} elements {:?}{}", index, table.element_type, table.limit, import);
+    }
+    for segment in module.elements() {
+        println!("  elements {:?} {} entries", segment.mode, segment.init.len());
+    }
+
+    println!("Custom sections:");
+    for section in module.custom_sections() {
+        println!("  `{}` {} bytes", section.name, section.data.len());
+    }
+    if let Some(dylink) = module.dylink()? {
+        println!("Side module:");
+        println!("  memory {:#x} bytes aligned to 2^{}", dylink.memory_size, dylink.memory_alignment);
+        println!("  table {} entries aligned to 2^{}", dylink.table_size, dylink.table_alignment);
+        for needed in &dylink.needed {
+            println!("  needs {}", needed);
+        }
+    }
+
+    println!("Start:");
+    match module.start() {
+        Some(index) => println!("  {}", index),
+        None => println!("  none"),
+    }
 
     Ok(())
 }
@@ -0,0 +1,123 @@
+//! Optimizations of the function bodies of a [Module], which reduce the number of instructions the interpreter
+//! executes, e.g. for guests compiled in debug mode.
+//!
+//! Like the [instrumentation](crate::instrument), the optimized module can be executed directly or
+//! [encoded](crate::encode) into a binary again.
+
+use std::mem;
+use crate::shared::Shared;
+use crate::exec::{BlockType, Instruction};
+use crate::parse::Module;
+
+/// Rewrites the function bodies of `module` by folding constant `i32` and `i64` arithmetic, removing the
+/// instructions after an unconditional branch like `unreachable` or `br`, and dropping empty blocks.
+///
+/// Divisions and remainders are not folded, as they may trap. The positions of instructions in the original binary
+/// are discarded.
+#[tracing::instrument(skip_all)]
+pub fn optimize(module: &mut Module) {
+	for function in module.functions.local_mut() {
+		let function = Shared::make_mut(function);
+		function.body = optimize_sequence(mem::take(&mut function.body));
+		function.body_offset = 0;
+		function.offsets.clear();
+	}
+	module.update_function_metadata();
+}
+
+/// Optimizes `instructions` and the nested ones.
+fn optimize_sequence(instructions: Vec<Instruction>) -> Vec<Instruction> {
+	let mut optimized: Vec<Instruction> = Vec::with_capacity(instructions.len());
+	for mut instruction in instructions {
+		match &mut instruction {
+			Instruction::Block { instructions, .. } | Instruction::Loop { instructions, .. } => {
+				*instructions = optimize_sequence(mem::take(instructions));
+			},
+			Instruction::If { if_instructions, else_instructions, .. } => {
+				*if_instructions = optimize_sequence(mem::take(if_instructions));
+				*else_instructions = optimize_sequence(mem::take(else_instructions));
+			},
+			Instruction::Try { instructions, catches, .. } => {
+				*instructions = optimize_sequence(mem::take(instructions));
+				for catch in catches {
+					catch.instructions = optimize_sequence(mem::take(&mut catch.instructions));
+				}
+			},
+			_ => (),
+		}
+
+		match &instruction {
+			// No branch can target an empty block, so it can be removed without changing any label index
+			Instruction::Block { block_type: BlockType::Empty, instructions }
+			| Instruction::Loop { block_type: BlockType::Empty, instructions } if instructions.is_empty() => continue,
+			Instruction::If { block_type: BlockType::Empty, if_instructions, else_instructions }
+			if if_instructions.is_empty() && else_instructions.is_empty() => {
+				// The condition still has to be popped
+				optimized.push(Instruction::Drop);
+				continue;
+			},
+			_ => (),
+		}
+
+		if let [.., lhs, rhs] = &optimized[..] {
+			if let Some(folded) = fold(lhs, rhs, &instruction) {
+				optimized.truncate(optimized.len() - 2);
+				optimized.push(folded);
+				continue;
+			}
+		}
+
+		let ends_sequence = matches!(instruction,
+			Instruction::Unreachable | Instruction::Br { .. } | Instruction::BrTable { .. } | Instruction::Return
+			| Instruction::Throw(_) | Instruction::Rethrow(_)
+			| Instruction::ReturnCall { .. } | Instruction::ReturnCallIndirect { .. }
+		);
+		optimized.push(instruction);
+		if ends_sequence {
+			// The following instructions are unreachable
+			break;
+		}
+	}
+	optimized
+}
+
+/// The constant computed by the binary `instruction` from the constants `lhs` and `rhs`, if it can be folded.
+fn fold(lhs: &Instruction, rhs: &Instruction, instruction: &Instruction) -> Option<Instruction> {
+	match (lhs, rhs) {
+		(Instruction::I32Const(lhs), Instruction::I32Const(rhs)) => {
+			let (lhs, rhs) = (*lhs, *rhs);
+			Some(Instruction::I32Const(match instruction {
+				Instruction::I32Add => lhs.wrapping_add(rhs),
+				Instruction::I32Sub => lhs.wrapping_sub(rhs),
+				Instruction::I32Mul => lhs.wrapping_mul(rhs),
+				Instruction::I32And => lhs & rhs,
+				Instruction::I32Or => lhs | rhs,
+				Instruction::I32Xor => lhs ^ rhs,
+				Instruction::I32Shl => lhs.wrapping_shl(rhs as u32),
+				Instruction::I32ShrS => lhs.wrapping_shr(rhs as u32),
+				Instruction::I32ShrU => (lhs as u32).wrapping_shr(rhs as u32) as i32,
+				Instruction::I32Rotl => lhs.rotate_left(rhs as u32),
+				Instruction::I32Rotr => lhs.rotate_right(rhs as u32),
+				_ => return None,
+			}))
+		},
+		(Instruction::I64Const(lhs), Instruction::I64Const(rhs)) => {
+			let (lhs, rhs) = (*lhs, *rhs);
+			Some(Instruction::I64Const(match instruction {
+				Instruction::I64Add => lhs.wrapping_add(rhs),
+				Instruction::I64Sub => lhs.wrapping_sub(rhs),
+				Instruction::I64Mul => lhs.wrapping_mul(rhs),
+				Instruction::I64And => lhs & rhs,
+				Instruction::I64Or => lhs | rhs,
+				Instruction::I64Xor => lhs ^ rhs,
+				Instruction::I64Shl => lhs.wrapping_shl(rhs as u32),
+				Instruction::I64ShrS => lhs.wrapping_shr(rhs as u32),
+				Instruction::I64ShrU => (lhs as u64).wrapping_shr(rhs as u32) as i64,
+				Instruction::I64Rotl => lhs.rotate_left(rhs as u32),
+				Instruction::I64Rotr => lhs.rotate_right(rhs as u32),
+				_ => return None,
+			}))
+		},
+		_ => None,
+	}
+}
\ No newline at end of file
@@ -0,0 +1,123 @@
+use crate::shared::Shared;
+use crate::exec::{ExternFunction, FunctionSignature, Identifier, Instruction, WasmFunction};
+use crate::parse::{DataSegment, Export, ExportKind, MemoryBlueprint, Module, SharedBytes, Type};
+
+/// Constructs a [Module] in code instead of parsing a binary.
+///
+/// Signatures and functions are referenced by the indices returned when adding them:
+/// `builder.function(builder.signature(vec![], vec![Type::I32]), vec![], vec![Instruction::I32Const(42)])`
+#[derive(Debug, Default)]
+pub struct ModuleBuilder {
+	module: Module,
+}
+
+impl ModuleBuilder {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds the function signature to the type section, reusing an equal one, and returns its type index.
+	pub fn signature(&mut self, params: Vec<Type>, results: Vec<Type>) -> usize {
+		let signature = FunctionSignature { params, results };
+		match self.module.types.iter().position(|existing| **existing == signature) {
+			Some(index) => index,
+			None => {
+				self.module.types.push(Shared::new(signature));
+				self.module.types.len() - 1
+			},
+		}
+	}
+
+	/// Adds an imported function with the type `type_index` and returns its function index.
+	///
+	/// # Panics
+	/// If a function was already defined, since imported functions come first in the function index space.
+	/// Also if `type_index` is out of bounds.
+	pub fn import(&mut self, module: &str, field: &str, type_index: usize) -> usize {
+		assert_eq!(self.module.functions.num_local(), 0, "Imports have to be added before functions are defined");
+		self.module.functions.push_import(ExternFunction {
+			name: Identifier { module: module.to_owned(), field: field.to_owned() },
+			signature: Shared::clone(&self.module.types[type_index]),
+		})
+	}
+
+	/// Defines a function with the type `type_index`, the declared `locals` and the instructions `body` and
+	/// returns its function index.
+	///
+	/// # Panics
+	/// If `type_index` is out of bounds.
+	pub fn function(&mut self, type_index: usize, locals: Vec<Type>, body: Vec<Instruction>) -> usize {
+		self.module.functions.push_local(WasmFunction {
+			signature: Shared::clone(&self.module.types[type_index]),
+			locals,
+			body,
+			..WasmFunction::default()
+		})
+	}
+
+	/// Declares the memory with `min` pages and at most `max` pages.
+	pub fn memory(&mut self, min: usize, max: Option<usize>) -> &mut Self {
+		self.module.memory_blueprint = Some(MemoryBlueprint {
+			page_limit: min..max.unwrap_or(u32::MAX as usize),
+			..MemoryBlueprint::default()
+		});
+		self
+	}
+
+	/// Adds a data segment copying `data` to `addr` in the memory during instantiation.
+	///
+	/// # Panics
+	/// If no memory was declared.
+	pub fn data(&mut self, addr: usize, data: Vec<u8>) -> &mut Self {
+		let memory = self.module.memory_blueprint.as_mut().expect("A memory has to be declared before data segments");
+		memory.init.push(DataSegment { addr, offset: None, data: SharedBytes::from(data) });
+		self.module.passive_data.push(None);
+		self
+	}
+
+	/// Adds a passive data segment with `data`, which `memory.init` copies into the memory, and returns its data
+	/// index.
+	pub fn passive_data(&mut self, data: Vec<u8>) -> usize {
+		self.module.passive_data.push(Some(SharedBytes::from(data)));
+		self.module.passive_data.len() - 1
+	}
+
+	/// Exports the defined function with `function_index` as `name`.
+	///
+	/// # Panics
+	/// If `function_index` does not refer to a defined function.
+	pub fn export_function(&mut self, name: &str, function_index: usize) -> &mut Self {
+		self.module.functions.get_local_mut(function_index)
+			.expect("Only defined functions can be exported")
+			.export_name = Some(name.to_owned());
+		self.module.export_map.insert(Export { name: name.to_owned(), kind: ExportKind::Function, index: function_index });
+		self
+	}
+
+	/// Exports the memory as `name`.
+	///
+	/// # Panics
+	/// If no memory was declared.
+	pub fn export_memory(&mut self, name: &str) -> &mut Self {
+		self.module.memory_blueprint.as_mut()
+			.expect("A memory has to be declared before exporting it")
+			.export_name = Some(name.to_owned());
+		self.module.export_map.insert(Export { name: name.to_owned(), kind: ExportKind::Memory, index: 0 });
+		self
+	}
+
+	/// Calls the function with `function_index` during instantiation.
+	pub fn start(&mut self, function_index: usize) -> &mut Self {
+		self.module.start = Some(function_index);
+		self
+	}
+
+	pub fn build(mut self) -> Module {
+		// `memory.init` and `data.drop` require the data count section
+		if self.module.passive_data.iter().any(Option::is_some) {
+			self.module.data_count = Some(self.module.passive_data.len());
+		}
+		self.module.update_function_metadata();
+		self.module
+	}
+}
\ No newline at end of file
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use crate::parse::{Digest, Module, ParsingError};
+use crate::shared::Shared;
+
+/// Parsed modules by the [`Digest`] of their binary, so that services loading the same modules repeatedly parse each
+/// of them only once.
+///
+/// The modules are only kept in memory. There is no on-disk format for parsed modules, and storing the
+/// [encoded](Module::encode) module would not save parsing it again.
+#[derive(Debug, Default)]
+pub struct ModuleCache {
+	modules: HashMap<Digest, Shared<Module>>,
+}
+
+impl ModuleCache {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// The module parsed from `bytes`, which is only parsed if the cache does not contain it.
+	pub fn load(&mut self, bytes: impl Into<Shared<[u8]>>) -> Result<Shared<Module>, ParsingError> {
+		self.load_with(bytes.into(), |_| {})
+	}
+
+	/// Like [`ModuleCache::load`], but calls `prepare` with a module not in the cache yet before sharing it.
+	pub(crate) fn load_with(
+		&mut self, bytes: Shared<[u8]>, prepare: impl FnOnce(&mut Module),
+	) -> Result<Shared<Module>, ParsingError> {
+		let digest = Digest::of(&bytes);
+		if let Some(module) = self.modules.get(&digest) {
+			return Ok(Shared::clone(module));
+		}
+		let mut module = Module::from_bytes(bytes)?;
+		prepare(&mut module);
+		let module = Shared::new(module);
+		self.modules.insert(digest, Shared::clone(&module));
+		Ok(module)
+	}
+
+	/// The cached module parsed from a binary with `digest`.
+	pub fn get(&self, digest: &Digest) -> Option<Shared<Module>> {
+		self.modules.get(digest).cloned()
+	}
+
+	/// Number of cached modules.
+	pub fn len(&self) -> usize {
+		self.modules.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.modules.is_empty()
+	}
+
+	/// Removes all modules from the cache.
+	pub fn clear(&mut self) {
+		self.modules.clear();
+	}
+}
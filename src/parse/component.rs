@@ -0,0 +1,426 @@
+//! The binary format of the [component model](https://github.com/WebAssembly/component-model), which wraps core
+//! modules and describes their interface with higher-level types.
+//!
+//! Only simple components are supported: nested core modules, core instances, aliases of core exports, canonical
+//! lifting and lowering, and functions with scalar and string types. Other items like nested components, component
+//! instances and resources are reported as [`ParsingError::UnsupportedComponentItem`].
+
+use num_enum::TryFromPrimitive;
+use crate::shared::Shared;
+use crate::parse::{Module, ParsingError};
+
+/// The version and layer following the magic of a component, which distinguish it from a core module.
+pub(crate) const VERSION_AND_LAYER: [u8; 4] = [0x0d, 0x00, 0x01, 0x00];
+
+/// <https://github.com/WebAssembly/component-model/blob/main/design/mvp/Binary.md#component-definitions>
+#[derive(Eq, PartialEq, Debug, TryFromPrimitive, Clone, Copy)]
+#[repr(u8)]
+pub enum ComponentSectionId {
+	Custom = 0,
+	CoreModule = 1,
+	CoreInstance = 2,
+	CoreType = 3,
+	Component = 4,
+	Instance = 5,
+	Alias = 6,
+	Type = 7,
+	Canon = 8,
+	Start = 9,
+	Import = 10,
+	Export = 11,
+	Value = 12,
+}
+
+/// A parsed component, whose items are stored in their index spaces.
+#[derive(Debug, Default, Clone)]
+pub struct Component {
+	/// The nested core modules.
+	pub modules: Vec<Shared<Module>>,
+	pub core_instances: Vec<CoreInstance>,
+	/// The core function index space.
+	pub core_funcs: Vec<CoreFunc>,
+	/// The core memory index space, whose memories are exported by core instances.
+	pub core_memories: Vec<CoreExport>,
+	/// The type index space, of which only function types and primitive value types are supported.
+	pub types: Vec<ComponentType>,
+	/// The component function index space.
+	pub funcs: Vec<ComponentFunc>,
+	pub imports: Vec<ComponentImport>,
+	pub exports: Vec<ComponentExport>,
+}
+
+/// An instance of a core module, created when the component is instantiated.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CoreInstance {
+	/// Instantiates the module with index `module`. The imports of every module name are the exports of the core
+	/// instance it is paired with.
+	Instantiate { module: usize, args: Vec<(String, usize)> },
+	/// Bundles core functions by index under the given names, so that they can be passed to [`CoreInstance::Instantiate`].
+	FromExports(Vec<(String, usize)>),
+}
+
+/// The export `name` of the core instance with index `instance`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CoreExport {
+	pub instance: usize,
+	pub name: String,
+}
+
+/// A function in the core function index space.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CoreFunc {
+	/// A function exported by a core instance.
+	Alias(CoreExport),
+	/// The component function with index `func` lowered to a core function.
+	Lower { func: usize, options: CanonicalOptions },
+}
+
+/// Options of lifting and lowering, which refer to the core items used for passing strings.
+/// Strings are always encoded as UTF-8.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct CanonicalOptions {
+	/// Core memory index of the memory strings are passed in.
+	pub memory: Option<usize>,
+	/// Core function index of `cabi_realloc`, which allocates memory for strings passed to the guest.
+	pub realloc: Option<usize>,
+	/// Core function index of the function called after the results of a lifted function were read.
+	pub post_return: Option<usize>,
+}
+
+/// A type in the type index space.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ComponentType {
+	Func(ComponentFuncType),
+	Value(ValueType),
+}
+
+/// The signature of a component function.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct ComponentFuncType {
+	pub params: Vec<(String, ValueType)>,
+	pub results: Vec<ValueType>,
+}
+
+/// The supported value types of the component model.
+/// <https://github.com/WebAssembly/component-model/blob/main/design/mvp/Binary.md#type-definitions>
+#[derive(Eq, PartialEq, Debug, TryFromPrimitive, Clone, Copy)]
+#[repr(u8)]
+pub enum ValueType {
+	Bool = 0x7f,
+	S8 = 0x7e,
+	U8 = 0x7d,
+	S16 = 0x7c,
+	U16 = 0x7b,
+	S32 = 0x7a,
+	U32 = 0x79,
+	S64 = 0x78,
+	U64 = 0x77,
+	F32 = 0x76,
+	F64 = 0x75,
+	Char = 0x74,
+	String = 0x73,
+}
+
+/// A function in the component function index space.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ComponentFunc {
+	/// The import with this index.
+	Import(usize),
+	/// The core function with index `core_func` lifted to a component function with the type with index `ty`.
+	Lift { core_func: usize, ty: usize, options: CanonicalOptions },
+}
+
+/// An imported function with the function type with index `ty`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ComponentImport {
+	pub name: String,
+	pub ty: usize,
+}
+
+/// An exported component function.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ComponentExport {
+	pub name: String,
+	pub func: usize,
+}
+
+impl Component {
+	/// Parses the binary `bytes` into a [Component] or a [ParsingError].
+	pub fn from_bytes(bytes: &[u8]) -> Result<Component, ParsingError> {
+		let mut reader = Reader { bytes };
+		if reader.read_bytes(4)? != [0x00, 0x61, 0x73, 0x6D] {
+			return Err(ParsingError::NotAWasmModule);
+		}
+		let version = reader.read_bytes(4)?;
+		if version != VERSION_AND_LAYER {
+			return Err(ParsingError::IllegalVersion(version.try_into().unwrap()));
+		}
+
+		let mut component = Component::default();
+		while !reader.bytes.is_empty() {
+			let section_id = ComponentSectionId::try_from(reader.read_byte()?)?;
+			let section_size = reader.read_u32()? as usize;
+			let mut section = Reader { bytes: reader.read_bytes(section_size)? };
+			let _span = tracing::debug_span!("component_section", id = ?section_id, size = section_size).entered();
+			match section_id {
+				ComponentSectionId::Custom => (),
+				ComponentSectionId::CoreModule => {
+					component.modules.push(Shared::new(Module::from_bytes(section.bytes)?));
+				},
+				ComponentSectionId::CoreInstance => section.read_vec(|reader| component.parse_core_instance(reader))?,
+				ComponentSectionId::Alias => section.read_vec(|reader| component.parse_alias(reader))?,
+				ComponentSectionId::Type => section.read_vec(|reader| component.parse_type(reader))?,
+				ComponentSectionId::Canon => section.read_vec(|reader| component.parse_canon(reader))?,
+				ComponentSectionId::Import => section.read_vec(|reader| component.parse_import(reader))?,
+				ComponentSectionId::Export => section.read_vec(|reader| component.parse_export(reader))?,
+				other => return Err(ParsingError::UnsupportedComponentItem(section_name(other))),
+			}
+		}
+		Ok(component)
+	}
+
+	fn parse_core_instance(&mut self, reader: &mut Reader) -> Result<(), ParsingError> {
+		let instance = match reader.read_byte()? {
+			0x00 => {
+				let module = reader.read_u32()? as usize;
+				let mut args = Vec::new();
+				reader.read_vec(|reader| {
+					let name = reader.read_name()?;
+					reader.expect_byte(CORE_SORT_INSTANCE, "instantiate arguments other than instances")?;
+					args.push((name, reader.read_u32()? as usize));
+					Ok(())
+				})?;
+				CoreInstance::Instantiate { module, args }
+			},
+			0x01 => {
+				let mut exports = Vec::new();
+				reader.read_vec(|reader| {
+					let name = reader.read_name()?;
+					let sort = reader.read_byte()?;
+					let index = reader.read_u32()? as usize;
+					// Only functions can be imported by core modules of this runtime
+					if sort == CORE_SORT_FUNC {
+						exports.push((name, index));
+					}
+					Ok(())
+				})?;
+				CoreInstance::FromExports(exports)
+			},
+			_ => return Err(ParsingError::UnsupportedComponentItem("core instance")),
+		};
+		self.core_instances.push(instance);
+		Ok(())
+	}
+
+	fn parse_alias(&mut self, reader: &mut Reader) -> Result<(), ParsingError> {
+		if reader.read_byte()? != SORT_CORE {
+			return Err(ParsingError::UnsupportedComponentItem("aliases of component items"));
+		}
+		let core_sort = reader.read_byte()?;
+		reader.expect_byte(ALIAS_CORE_EXPORT, "aliases other than of core exports")?;
+		let export = CoreExport { instance: reader.read_u32()? as usize, name: reader.read_name()? };
+		match core_sort {
+			CORE_SORT_FUNC => self.core_funcs.push(CoreFunc::Alias(export)),
+			CORE_SORT_MEMORY => self.core_memories.push(export),
+			// Tables and globals are not passed between core instances
+			_ => (),
+		}
+		Ok(())
+	}
+
+	fn parse_type(&mut self, reader: &mut Reader) -> Result<(), ParsingError> {
+		let ty = match reader.peek_byte()? {
+			0x40 => {
+				reader.read_byte()?;
+				let mut ty = ComponentFuncType::default();
+				reader.read_vec(|reader| {
+					let name = reader.read_name()?;
+					ty.params.push((name, self.read_value_type(reader)?));
+					Ok(())
+				})?;
+				match reader.read_byte()? {
+					0x00 => ty.results.push(self.read_value_type(reader)?),
+					_ => reader.read_vec(|reader| {
+						reader.read_name()?;
+						ty.results.push(self.read_value_type(reader)?);
+						Ok(())
+					})?,
+				}
+				ComponentType::Func(ty)
+			},
+			byte => match ValueType::try_from(byte) {
+				Ok(value_type) => {
+					reader.read_byte()?;
+					ComponentType::Value(value_type)
+				},
+				Err(_) => return Err(ParsingError::UnsupportedComponentItem("types other than functions and primitives")),
+			},
+		};
+		self.types.push(ty);
+		Ok(())
+	}
+
+	/// Reads a primitive value type or the index of a primitive value type.
+	fn read_value_type(&self, reader: &mut Reader) -> Result<ValueType, ParsingError> {
+		if let Ok(value_type) = ValueType::try_from(reader.peek_byte()?) {
+			reader.read_byte()?;
+			return Ok(value_type);
+		}
+		match self.types.get(reader.read_u32()? as usize) {
+			Some(ComponentType::Value(value_type)) => Ok(*value_type),
+			_ => Err(ParsingError::UnsupportedComponentItem("value types other than primitives")),
+		}
+	}
+
+	fn parse_canon(&mut self, reader: &mut Reader) -> Result<(), ParsingError> {
+		match reader.read_byte()? {
+			0x00 => {
+				reader.expect_byte(0x00, "lifting other than functions")?;
+				let core_func = reader.read_u32()? as usize;
+				let options = read_canonical_options(reader)?;
+				let ty = reader.read_u32()? as usize;
+				self.funcs.push(ComponentFunc::Lift { core_func, ty, options });
+			},
+			0x01 => {
+				reader.expect_byte(0x00, "lowering other than functions")?;
+				let func = reader.read_u32()? as usize;
+				let options = read_canonical_options(reader)?;
+				self.core_funcs.push(CoreFunc::Lower { func, options });
+			},
+			_ => return Err(ParsingError::UnsupportedComponentItem("canonical functions for resources")),
+		}
+		Ok(())
+	}
+
+	fn parse_import(&mut self, reader: &mut Reader) -> Result<(), ParsingError> {
+		let name = reader.read_extern_name()?;
+		reader.expect_byte(SORT_FUNC, "imports other than functions")?;
+		let ty = reader.read_u32()? as usize;
+		self.funcs.push(ComponentFunc::Import(self.imports.len()));
+		self.imports.push(ComponentImport { name, ty });
+		Ok(())
+	}
+
+	fn parse_export(&mut self, reader: &mut Reader) -> Result<(), ParsingError> {
+		let name = reader.read_extern_name()?;
+		let sort = reader.read_byte()?;
+		let index = reader.read_u32()? as usize;
+		// The optional type ascription must be the type of the exported item, so it can be skipped
+		if reader.read_byte()? == 0x01 {
+			match reader.read_byte()? {
+				0x00 | 0x03 => { reader.read_byte()?; reader.read_u32()?; },
+				_ => { reader.read_u32()?; },
+			}
+		}
+		// Exports add a new index to the index space of their sort
+		match sort {
+			SORT_FUNC => {
+				let func = self.funcs.get(index).cloned()
+					.ok_or(ParsingError::UnsupportedComponentItem("exports of undefined functions"))?;
+				self.funcs.push(func);
+				self.exports.push(ComponentExport { name, func: self.funcs.len() - 1 });
+			},
+			SORT_TYPE => {
+				let ty = self.types.get(index).cloned()
+					.ok_or(ParsingError::UnsupportedComponentItem("exports of undefined types"))?;
+				self.types.push(ty);
+			},
+			_ => return Err(ParsingError::UnsupportedComponentItem("exports other than functions and types")),
+		}
+		Ok(())
+	}
+}
+
+const SORT_CORE: u8 = 0x00;
+const SORT_FUNC: u8 = 0x01;
+const SORT_TYPE: u8 = 0x03;
+const CORE_SORT_FUNC: u8 = 0x00;
+const CORE_SORT_MEMORY: u8 = 0x02;
+const CORE_SORT_INSTANCE: u8 = 0x12;
+const ALIAS_CORE_EXPORT: u8 = 0x01;
+
+fn read_canonical_options(reader: &mut Reader) -> Result<CanonicalOptions, ParsingError> {
+	let mut options = CanonicalOptions::default();
+	reader.read_vec(|reader| {
+		match reader.read_byte()? {
+			// UTF-8 string encoding
+			0x00 => (),
+			0x03 => options.memory = Some(reader.read_u32()? as usize),
+			0x04 => options.realloc = Some(reader.read_u32()? as usize),
+			0x05 => options.post_return = Some(reader.read_u32()? as usize),
+			_ => return Err(ParsingError::UnsupportedComponentItem("string encodings other than UTF-8")),
+		}
+		Ok(())
+	})?;
+	Ok(options)
+}
+
+fn section_name(id: ComponentSectionId) -> &'static str {
+	match id {
+		ComponentSectionId::CoreType => "core type section",
+		ComponentSectionId::Component => "nested components",
+		ComponentSectionId::Instance => "component instances",
+		ComponentSectionId::Start => "start section",
+		ComponentSectionId::Value => "value section",
+		_ => "section",
+	}
+}
+
+/// Reads the items of a component section.
+struct Reader<'b> {
+	bytes: &'b [u8],
+}
+
+impl<'b> Reader<'b> {
+	fn read_byte(&mut self) -> Result<u8, ParsingError> {
+		Ok(self.read_bytes(1)?[0])
+	}
+
+	fn peek_byte(&self) -> Result<u8, ParsingError> {
+		self.bytes.first().copied().ok_or_else(|| std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into())
+	}
+
+	fn expect_byte(&mut self, expected: u8, unsupported: &'static str) -> Result<(), ParsingError> {
+		match self.read_byte()? == expected {
+			true => Ok(()),
+			false => Err(ParsingError::UnsupportedComponentItem(unsupported)),
+		}
+	}
+
+	fn read_bytes(&mut self, len: usize) -> Result<&'b [u8], ParsingError> {
+		if len > self.bytes.len() {
+			return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+		}
+		let (bytes, rest) = self.bytes.split_at(len);
+		self.bytes = rest;
+		Ok(bytes)
+	}
+
+	fn read_u32(&mut self) -> Result<u32, ParsingError> {
+		let value = leb128::read::unsigned(&mut self.bytes)?;
+		u32::try_from(value).map_err(|_| ParsingError::Leb128Error(leb128::read::Error::Overflow))
+	}
+
+	fn read_name(&mut self) -> Result<String, ParsingError> {
+		let len = self.read_u32()? as usize;
+		Ok(String::from_utf8(self.read_bytes(len)?.to_vec())?)
+	}
+
+	/// Reads the name of an import or export, ignoring a version suffix.
+	fn read_extern_name(&mut self) -> Result<String, ParsingError> {
+		let kind = self.read_byte()?;
+		let name = self.read_name()?;
+		if kind == 0x01 {
+			self.read_name()?;
+		}
+		Ok(name)
+	}
+
+	/// Reads a vector by calling `read_item` for every item.
+	fn read_vec(&mut self, mut read_item: impl FnMut(&mut Self) -> Result<(), ParsingError>) -> Result<(), ParsingError> {
+		for _ in 0..self.read_u32()? {
+			read_item(self)?;
+		}
+		Ok(())
+	}
+}
\ No newline at end of file
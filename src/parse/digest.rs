@@ -0,0 +1,108 @@
+use std::fmt;
+
+/// SHA-256 hash of the binary of a module, which identifies it in a [`ModuleCache`](crate::parse::ModuleCache).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Digest(pub [u8; 32]);
+
+const ROUND_CONSTANTS: [u32; 64] = [
+	0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+	0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+	0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+	0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+	0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+	0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+	0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+	0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const INITIAL_STATE: [u32; 8] = [
+	0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+impl Digest {
+	/// The SHA-256 hash of `bytes`.
+	pub fn of(bytes: &[u8]) -> Self {
+		let mut state = INITIAL_STATE;
+		let mut blocks = bytes.chunks_exact(64);
+		for block in &mut blocks {
+			compress(&mut state, block);
+		}
+		// Pad the remainder with a one bit, zeros and the length in bits into one or two final blocks
+		let remainder = blocks.remainder();
+		let mut tail = [0u8; 128];
+		tail[..remainder.len()].copy_from_slice(remainder);
+		tail[remainder.len()] = 0x80;
+		let tail_len = if remainder.len() < 56 { 64 } else { 128 };
+		tail[tail_len - 8..tail_len].copy_from_slice(&(bytes.len() as u64 * 8).to_be_bytes());
+		for block in tail[..tail_len].chunks_exact(64) {
+			compress(&mut state, block);
+		}
+
+		let mut digest = [0u8; 32];
+		for (bytes, word) in digest.chunks_exact_mut(4).zip(state) {
+			bytes.copy_from_slice(&word.to_be_bytes());
+		}
+		Digest(digest)
+	}
+}
+
+/// Updates `state` with the 64 bytes of `block`.
+fn compress(state: &mut [u32; 8], block: &[u8]) {
+	let mut schedule = [0u32; 64];
+	for (word, bytes) in schedule.iter_mut().zip(block.chunks_exact(4)) {
+		*word = u32::from_be_bytes(bytes.try_into().unwrap());
+	}
+	for i in 16..64 {
+		let s0 = schedule[i - 15].rotate_right(7) ^ schedule[i - 15].rotate_right(18) ^ (schedule[i - 15] >> 3);
+		let s1 = schedule[i - 2].rotate_right(17) ^ schedule[i - 2].rotate_right(19) ^ (schedule[i - 2] >> 10);
+		schedule[i] = schedule[i - 16].wrapping_add(s0).wrapping_add(schedule[i - 7]).wrapping_add(s1);
+	}
+
+	let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+	for (constant, word) in ROUND_CONSTANTS.iter().zip(schedule) {
+		let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+		let choice = (e & f) ^ (!e & g);
+		let temp1 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add(*constant).wrapping_add(word);
+		let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+		let majority = (a & b) ^ (a & c) ^ (b & c);
+		let temp2 = s0.wrapping_add(majority);
+		h = g;
+		g = f;
+		f = e;
+		e = d.wrapping_add(temp1);
+		d = c;
+		c = b;
+		b = a;
+		a = temp1.wrapping_add(temp2);
+	}
+	for (word, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+		*word = word.wrapping_add(value);
+	}
+}
+
+impl fmt::Display for Digest {
+	/// Formats the digest as lowercase hex, like `sha256sum`.
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+	}
+}
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// The examples of FIPS 180-4 for SHA-256.
+	#[test]
+	fn known_answers() {
+		let cases: [(&[u8], &str); 3] = [
+			(b"", "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+			(b"abc", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+			(
+				b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+				"248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+			),
+		];
+		for (message, digest) in cases {
+			assert_eq!(Digest::of(message).to_string(), digest);
+		}
+	}
+}
@@ -0,0 +1,102 @@
+use std::io::{self, Read};
+use crate::parse::ParsingError;
+
+/// Name of the custom section which marks a module as side module of the dynamic linking conventions.
+pub const DYLINK_SECTION: &str = "dylink.0";
+
+/// The content of the `dylink.0` custom section of a side module, see [`Module::dylink`](crate::parse::Module::dylink).
+///
+/// <https://github.com/WebAssembly/tool-conventions/blob/main/DynamicLinking.md>
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DylinkInfo {
+	/// Bytes of memory the module needs for its data segments, which are placed at `__memory_base`.
+	pub memory_size: usize,
+	/// Alignment of `__memory_base` as power of two.
+	pub memory_alignment: u32,
+	/// Number of table slots the module needs for its element segments, which are placed at `__table_base`.
+	pub table_size: usize,
+	/// Alignment of `__table_base` as power of two.
+	pub table_alignment: u32,
+	/// Names of the side modules this one depends on, which have to be loaded before.
+	pub needed: Vec<String>,
+	/// Exported symbols with their flags like [`DylinkInfo::SYMBOL_TLS`], if they are not the default ones.
+	pub export_info: Vec<(String, u32)>,
+	/// Imported symbols by module and field name with their flags like [`DylinkInfo::SYMBOL_WEAK`], if they are not
+	/// the default ones.
+	pub import_info: Vec<(String, String, u32)>,
+}
+
+impl DylinkInfo {
+	/// Flag of a weak symbol, whose import may remain undefined.
+	pub const SYMBOL_WEAK: u32 = 0x1;
+	/// Flag of a thread local symbol, whose address is relative to `__tls_base`.
+	pub const SYMBOL_TLS: u32 = 0x100;
+
+	const MEM_INFO: u8 = 1;
+	const NEEDED: u8 = 2;
+	const EXPORT_INFO: u8 = 3;
+	const IMPORT_INFO: u8 = 4;
+
+	/// Parses the content of a `dylink.0` section, skipping unknown subsections.
+	pub fn parse(mut data: &[u8]) -> Result<Self, ParsingError> {
+		let mut info = DylinkInfo::default();
+		while !data.is_empty() {
+			let mut id = [0u8];
+			data.read_exact(&mut id)?;
+			let size = leb128::read::unsigned(&mut data)? as usize;
+			if size > data.len() {
+				return Err(ParsingError::CustomSectionTooSmall { size: size as u64 });
+			}
+			let (mut subsection, rest) = data.split_at(size);
+			data = rest;
+			match id[0] {
+				Self::MEM_INFO => {
+					info.memory_size = leb128::read::unsigned(&mut subsection)? as usize;
+					info.memory_alignment = leb128::read::unsigned(&mut subsection)? as u32;
+					info.table_size = leb128::read::unsigned(&mut subsection)? as usize;
+					info.table_alignment = leb128::read::unsigned(&mut subsection)? as u32;
+				},
+				Self::NEEDED => {
+					let count = leb128::read::unsigned(&mut subsection)?;
+					for _ in 0..count {
+						info.needed.push(read_string(&mut subsection)?);
+					}
+				},
+				Self::EXPORT_INFO => {
+					let count = leb128::read::unsigned(&mut subsection)?;
+					for _ in 0..count {
+						let name = read_string(&mut subsection)?;
+						let flags = leb128::read::unsigned(&mut subsection)? as u32;
+						info.export_info.push((name, flags));
+					}
+				},
+				Self::IMPORT_INFO => {
+					let count = leb128::read::unsigned(&mut subsection)?;
+					for _ in 0..count {
+						let module = read_string(&mut subsection)?;
+						let field = read_string(&mut subsection)?;
+						let flags = leb128::read::unsigned(&mut subsection)? as u32;
+						info.import_info.push((module, field, flags));
+					}
+				},
+				other => tracing::debug!(id = other, size, "Skipping unknown dylink subsection"),
+			}
+		}
+		Ok(info)
+	}
+
+	/// The flags of the exported symbol `name`.
+	pub fn export_flags(&self, name: &str) -> u32 {
+		self.export_info.iter().find(|(export, _)| export == name).map_or(0, |(_, flags)| *flags)
+	}
+}
+
+fn read_string(data: &mut &[u8]) -> Result<String, ParsingError> {
+	let length = leb128::read::unsigned(data)? as usize;
+	if length > data.len() {
+		return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+	}
+	let (string, rest) = data.split_at(length);
+	*data = rest;
+	Ok(String::from_utf8(string.to_vec())?)
+}
\ No newline at end of file
@@ -1,7 +1,10 @@
-use std::{io, string};
+use std::{fmt, io, string};
 use thiserror::Error;
 use num_enum::TryFromPrimitiveError;
 use crate::parse::types::*;
+use crate::parse::Features;
+use crate::parse::component::ComponentSectionId;
+use crate::exec::Instruction;
 
 #[derive(Debug, Error)]
 pub enum ParsingError {
@@ -11,6 +14,15 @@ pub enum ParsingError {
 	#[error("The version {0:?} is not supported")]
 	IllegalVersion([u8; 4]),
 
+	#[error("The binary is a component, which has to be parsed with Component::from_bytes")]
+	UnexpectedComponent,
+
+	#[error("Unknown component section id: {0}")]
+	UnknownComponentSectionId(#[from] TryFromPrimitiveError<ComponentSectionId>),
+
+	#[error("Components with {0} are not supported")]
+	UnsupportedComponentItem(&'static str),
+
 	#[error("Unknown section id: {0}")]
 	UnknownSectionId(#[from] TryFromPrimitiveError<SectionId>),
 
@@ -23,18 +35,114 @@ pub enum ParsingError {
 	#[error("Unknown opcode: {0}")]
 	UnknownOpcode(#[from] TryFromPrimitiveError<Opcode>),
 
+	#[error("Unknown extension opcode: {0}")]
+	UnknownExtensionOpcode(#[from] TryFromPrimitiveError<ExtensionOpcode>),
+
+	#[error("Unknown SIMD opcode: {0}")]
+	UnknownSimdOpcode(#[from] TryFromPrimitiveError<SimdOpcode>),
+
+	#[error("{source} at offset {offset:#x} in function {function_index}, starting with byte 0x{opcode:02X}:\n{context}")]
+	InvalidOpcode {
+		function_index: usize,
+		/// Position of the instruction in the binary.
+		offset: usize,
+		/// The first byte of the instruction, which is a prefix if the unknown opcode follows it.
+		opcode: u8,
+		/// The bytes around the instruction.
+		context: Hexdump,
+		source: Box<ParsingError>,
+	},
+
+	#[error("Lane index {lane} out of bounds for {lanes} lanes")]
+	InvalidLaneIndex {
+		lane: u8,
+		lanes: u8,
+	},
+
+	#[error("Unknown atomic opcode: {0}")]
+	UnknownAtomicOpcode(#[from] TryFromPrimitiveError<AtomicOpcode>),
+
+	#[error("Alignment 2^{align} exceeds the natural alignment of {natural} bytes")]
+	InvalidAlignment {
+		align: u32,
+		natural: usize,
+	},
+
+	#[error("Feature {0} not enabled")]
+	FeatureNotEnabled(Features),
+
+	#[error("Only memories can be shared")]
+	UnexpectedSharedLimit,
+
 	#[error("Unknown limit: {0}")]
 	UnknownLimit(#[from] TryFromPrimitiveError<LimitKind>),
 
 	#[error("Unknown data mode: {0}")]
 	UnknownDataMode(#[from] TryFromPrimitiveError<DataMode>),
 
-	#[error("Function access out of range. index={index} wasm_len={wasm_len} imports_len={imports_len} total_len={total_len}")]
-	WasmFunctionOutOfRange {
+	#[error("Unknown element segment kind {flags} at offset {offset:#x}")]
+	UnknownElementSegmentKind {
+		offset: usize,
+		flags: u64,
+	},
+
+	#[error("Data segment for memory {index} at offset {offset:#x}, but only memory 0 is supported")]
+	UnsupportedMemoryIndex {
+		offset: usize,
+		index: u64,
+	},
+
+	#[error("Active data segment at offset {offset:#x} without a memory")]
+	DataSegmentWithoutMemory {
+		offset: usize,
+	},
+
+	#[error("Unsupported element expression {expression:?} at offset {offset:#x}")]
+	UnsupportedElementExpression {
+		offset: usize,
+		expression: Vec<Instruction>,
+	},
+
+	#[error("Function index {index} out of bounds for {len} functions")]
+	FunctionIndexOutOfBounds {
 		index: usize,
-		wasm_len: usize,
-		imports_len: usize,
-		total_len: usize
+		len: usize,
+	},
+
+	#[error("{kind:?} index {index} of an export out of bounds for {len} definitions")]
+	ExportIndexOutOfBounds {
+		kind: ExportKind,
+		index: usize,
+		len: usize,
+	},
+
+	#[error("Function {0} is imported, but a function defined by the module is required")]
+	ImportedFunction(usize),
+
+	#[error("The data count section declares {declared} data segments, but the data section contains {actual}")]
+	DataCountMismatch {
+		declared: usize,
+		actual: usize,
+	},
+
+	#[error("{0} requires the data count section")]
+	MissingDataCount(&'static str),
+
+	#[error("Data segment index {index} out of bounds for {len} data segments")]
+	DataIndexOutOfBounds {
+		index: usize,
+		len: usize,
+	},
+
+	#[error("Name of custom section exceeds section size {size}")]
+	CustomSectionTooSmall {
+		size: u64,
+	},
+
+	#[error("Parsing custom section `{name}` failed: {source}")]
+	CustomSection {
+		name: String,
+		source: Box<dyn std::error::Error + Send + Sync>,
 	},
 
 	#[error("IoError: {0}")]
@@ -43,9 +151,49 @@ pub enum ParsingError {
 	#[error("Expected opcode {0:?}")]
 	ExpectedOpcode(Opcode),
 
+	#[error("Unsupported constant expression {0:?}")]
+	UnsupportedConstExpr(Vec<Instruction>),
+
 	#[error("Leb128Error: {0}")]
 	Leb128Error(#[from] leb128::read::Error),
 
 	#[error("Utf8Error: {0}")]
 	Utf8Error(#[from] string::FromUtf8Error),
+
+	#[cfg(feature = "frontend-wasmparser")]
+	#[error("wasmparser: {0}")]
+	Wasmparser(#[from] wasmparser::BinaryReaderError),
+
+	#[cfg(feature = "frontend-wasmparser")]
+	#[error("{0} are not supported")]
+	Unsupported(&'static str),
+
+	#[cfg(feature = "frontend-wasmparser")]
+	#[error("The instruction {operator} at offset {offset:#x} is not supported")]
+	UnsupportedOperator {
+		operator: String,
+		offset: usize,
+	},
+}
+
+/// Bytes of a binary starting at `offset`, displayed with their positions like by `xxd`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hexdump {
+	pub offset: usize,
+	pub bytes: Vec<u8>,
+}
+
+impl fmt::Display for Hexdump {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (i, line) in self.bytes.chunks(16).enumerate() {
+			if i != 0 {
+				writeln!(f)?;
+			}
+			write!(f, "{:08x}:", self.offset + 16 * i)?;
+			for byte in line {
+				write!(f, " {:02x}", byte)?;
+			}
+		}
+		Ok(())
+	}
 }
\ No newline at end of file
@@ -0,0 +1,98 @@
+use std::fmt;
+use std::ops::BitOr;
+
+/// Set of WebAssembly proposals, which a module may use and a [`Parser`](crate::parse::Parser) or
+/// [`Config`](crate::exec::Config) may accept.
+///
+/// Combine them with `|`, e.g. `Features::SIMD | Features::BULK_MEMORY`. The default enables all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Features(u32);
+
+impl Features {
+	/// 128 bit vector type and instructions.
+	pub const SIMD: Features = Features(1 << 0);
+	/// `memory.init`, `data.drop`, table copies and the data count section.
+	pub const BULK_MEMORY: Features = Features(1 << 1);
+	/// `externref`, multiple tables and the `table.*` and `ref.*` instructions.
+	pub const REFERENCE_TYPES: Features = Features(1 << 2);
+	/// Functions and blocks with multiple results, and blocks with parameters.
+	pub const MULTI_VALUE: Features = Features(1 << 3);
+	/// Shared memories and atomic instructions.
+	pub const THREADS: Features = Features(1 << 4);
+	/// `return_call` and `return_call_indirect`.
+	pub const TAIL_CALL: Features = Features(1 << 5);
+	/// Memories indexed with 64 bit addresses.
+	pub const MEMORY64: Features = Features(1 << 6);
+
+	const NAMES: [(Features, &'static str); 7] = [
+		(Features::SIMD, "simd"),
+		(Features::BULK_MEMORY, "bulk_memory"),
+		(Features::REFERENCE_TYPES, "reference_types"),
+		(Features::MULTI_VALUE, "multi_value"),
+		(Features::THREADS, "threads"),
+		(Features::TAIL_CALL, "tail_call"),
+		(Features::MEMORY64, "memory64"),
+	];
+
+	/// No proposals, i.e. only the WebAssembly 1.0 core specification.
+	pub const fn empty() -> Self {
+		Features(0)
+	}
+
+	/// All proposals.
+	pub const fn all() -> Self {
+		Features((1 << Self::NAMES.len()) - 1)
+	}
+
+	pub const fn is_empty(self) -> bool {
+		self.0 == 0
+	}
+
+	/// Whether all features of `other` are in this set.
+	pub const fn contains(self, other: Features) -> bool {
+		self.0 & other.0 == other.0
+	}
+
+	pub fn insert(&mut self, other: Features) {
+		self.0 |= other.0;
+	}
+
+	pub fn remove(&mut self, other: Features) {
+		self.0 &= !other.0;
+	}
+
+	/// The features of this set which are not in `other`.
+	pub const fn difference(self, other: Features) -> Self {
+		Features(self.0 & !other.0)
+	}
+}
+
+impl Default for Features {
+	fn default() -> Self {
+		Features::all()
+	}
+}
+
+impl BitOr for Features {
+	type Output = Features;
+
+	fn bitor(self, rhs: Features) -> Features {
+		Features(self.0 | rhs.0)
+	}
+}
+
+impl fmt::Display for Features {
+	/// Lists the names of the features, e.g. `simd, threads`.
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut names = Self::NAMES.iter()
+			.filter(|(feature, _)| self.contains(*feature))
+			.map(|(_, name)| *name);
+		if let Some(first) = names.next() {
+			write!(f, "{}", first)?;
+		}
+		for name in names {
+			write!(f, ", {}", name)?;
+		}
+		Ok(())
+	}
+}
\ No newline at end of file
@@ -0,0 +1,809 @@
+//! An alternative to the [`Parser`](super::Parser) of this crate, which decodes modules with the `wasmparser` crate.
+//!
+//! The binary is validated before it is converted, so that malformed modules are rejected instead of causing
+//! panics later on. As both frontends build the same [`Module`], they can be compared to test the native parser.
+
+use std::iter;
+use wasmparser::{
+	DataKind, ElementItems, ElementKind, Encoding, ExternalKind, FunctionBody, HeapType, Operator, OperatorsReader,
+	Payload, RefType, TypeRef, ValType, Validator, WasmFeatures,
+};
+use crate::shared::Shared;
+use crate::parse::{
+	error::ParsingError,
+	parser::{eval_known, eval_offset},
+	types::*,
+	Digest,
+};
+use crate::exec::types::*;
+
+impl Module {
+	/// Like [`Module::from_bytes`], but validates and decodes `bytes` with the `wasmparser` crate instead of the
+	/// parser of this crate. Custom sections are kept, but no custom section parsers are called.
+	pub fn from_bytes_wasmparser(bytes: impl Into<Shared<[u8]>>) -> Result<Module, ParsingError> {
+		Frontend::new(bytes.into()).parse()
+	}
+}
+
+/// The proposals the native parser supports.
+fn features() -> WasmFeatures {
+	WasmFeatures {
+		threads: true,
+		exceptions: true,
+		tail_call: true,
+		extended_const: true,
+		memory64: true,
+		..WasmFeatures::default()
+	}
+}
+
+struct Frontend {
+	/// The binary, which data segments and custom sections reference instead of copying.
+	bytes: Shared<[u8]>,
+	module: Module,
+	/// Number of code section entries converted so far.
+	num_bodies: usize,
+	/// Initial values of the globals converted so far, `None` for the ones only known at instantiation.
+	global_values: Vec<Option<Value>>,
+}
+
+impl Frontend {
+	fn new(bytes: Shared<[u8]>) -> Self {
+		let module = Module { digest: Some(Digest::of(&bytes)), ..Module::default() };
+		Frontend { bytes, module, num_bodies: 0, global_values: Vec::new() }
+	}
+
+	#[tracing::instrument(name = "parse_wasmparser", skip_all)]
+	fn parse(mut self) -> Result<Module, ParsingError> {
+		let bytes = Shared::clone(&self.bytes);
+		Validator::new_with_features(features()).validate_all(&bytes)?;
+
+		for payload in wasmparser::Parser::new(0).parse_all(&bytes) {
+			let payload = payload?;
+			if let Some((id, range)) = payload.as_section() {
+				let id = SectionId::try_from(id)?;
+				self.module.sections.push(SectionHeader { id, offset: range.start, size: range.len() as u64 });
+			}
+			match payload {
+				Payload::Version { encoding: Encoding::Component, .. } => return Err(ParsingError::UnexpectedComponent),
+				Payload::TypeSection(reader) => {
+					for function_type in reader.into_iter_err_on_gc_types() {
+						let function_type = function_type?;
+						let signature = FunctionSignature {
+							params: function_type.params().iter().copied().map(value_type).collect::<Result<_, _>>()?,
+							results: function_type.results().iter().copied().map(value_type).collect::<Result<_, _>>()?,
+						};
+						tracing::debug!(function_type = ?signature);
+						self.module.types.push(Shared::new(signature));
+					}
+				},
+				Payload::ImportSection(reader) => {
+					for import in reader {
+						self.import(import?)?;
+					}
+				},
+				Payload::FunctionSection(reader) => {
+					for type_index in reader {
+						let function = WasmFunction {
+							signature: Shared::clone(&self.module.types[type_index? as usize]),
+							..WasmFunction::default()
+						};
+						self.module.functions.push_local(function);
+					}
+				},
+				Payload::TableSection(reader) => {
+					for table in reader {
+						let table_blueprint = table_blueprint(table?.ty, None)?;
+						tracing::trace!(?table_blueprint);
+						self.module.table_blueprints.push(table_blueprint);
+					}
+				},
+				Payload::MemorySection(reader) => {
+					for memory in reader {
+						let memory_blueprint = memory_blueprint(memory?, None);
+						tracing::trace!(?memory_blueprint);
+						self.module.memory_blueprint = Some(memory_blueprint);
+					}
+				},
+				Payload::TagSection(reader) => {
+					for tag in reader {
+						self.module.tags.push(Shared::clone(&self.module.types[tag?.func_type_idx as usize]));
+					}
+				},
+				Payload::GlobalSection(reader) => {
+					for global in reader {
+						self.global(global?)?;
+					}
+				},
+				Payload::ExportSection(reader) => {
+					for export in reader {
+						self.export(export?)?;
+					}
+				},
+				Payload::StartSection { func, .. } => self.module.start = Some(func as usize),
+				Payload::ElementSection(reader) => {
+					for element in reader {
+						self.element(element?)?;
+					}
+				},
+				Payload::DataCountSection { count, .. } => self.module.data_count = Some(count as usize),
+				Payload::DataSection(reader) => {
+					for data in reader {
+						self.data(data?)?;
+					}
+				},
+				Payload::CodeSectionEntry(body) => {
+					// The code entries belong to the defined functions, which follow the imported ones
+					let function_index = self.module.functions.num_imports() + self.num_bodies;
+					self.num_bodies += 1;
+					self.function_code(function_index, body)?;
+				},
+				Payload::CustomSection(reader) => {
+					tracing::trace!(name = reader.name(), size = reader.data().len(), "Custom section");
+					let data = self.shared_bytes(reader.data());
+					self.module.custom_sections.push(CustomSection { name: reader.name().to_owned(), data });
+				},
+				_ => {},
+			}
+		}
+		self.module.update_function_metadata();
+		Ok(self.module)
+	}
+
+	/// References `slice` of the binary instead of copying it.
+	fn shared_bytes(&self, slice: &[u8]) -> SharedBytes {
+		let start = slice.as_ptr() as usize - self.bytes.as_ptr() as usize;
+		SharedBytes::new(Shared::clone(&self.bytes), start..start + slice.len())
+	}
+
+	fn import(&mut self, import: wasmparser::Import) -> Result<(), ParsingError> {
+		let name = Identifier { module: import.module.to_owned(), field: import.name.to_owned() };
+		match import.ty {
+			TypeRef::Func(type_index) => {
+				let extern_function = ExternFunction {
+					name,
+					signature: Shared::clone(&self.module.types[type_index as usize]),
+				};
+				tracing::debug!(import = ?extern_function);
+				self.module.functions.push_import(extern_function);
+			},
+			TypeRef::Table(table_type) => {
+				let table_blueprint = table_blueprint(table_type, Some(name))?;
+				tracing::debug!(import = ?table_blueprint);
+				self.module.table_blueprints.push(table_blueprint);
+			},
+			TypeRef::Memory(memory_type) => {
+				let memory_blueprint = memory_blueprint(memory_type, Some(name));
+				tracing::debug!(import = ?memory_blueprint);
+				self.module.memory_blueprint = Some(memory_blueprint);
+			},
+			TypeRef::Global(global_type) => {
+				let ty = value_type(global_type.content_type)?;
+				let init = ConstExpr::default();
+				let mutable = global_type.mutable;
+				let global = GlobalBlueprint { ty, mutable, init, export_name: None, import: Some(name) };
+				tracing::debug!(import = ?global);
+				self.module.globals.push(global);
+				self.global_values.push(None);
+			},
+			TypeRef::Tag(_) => return Err(ParsingError::Unsupported("Imported tags")),
+		}
+		Ok(())
+	}
+
+	fn global(&mut self, global: wasmparser::Global) -> Result<(), ParsingError> {
+		let init = ConstExpr::new(const_expr(&global.init_expr)?);
+		// Globals may only refer to the preceding ones
+		let value = eval_known(&self.module, &self.global_values, &init)
+			.ok_or_else(|| ParsingError::UnsupportedConstExpr(init.instructions.clone()))?;
+		self.global_values.push(value);
+		let ty = value_type(global.ty.content_type)?;
+		let global = GlobalBlueprint { ty, mutable: global.ty.mutable, init, export_name: None, import: None };
+		tracing::trace!(?global);
+		self.module.globals.push(global);
+		Ok(())
+	}
+
+	fn export(&mut self, export: wasmparser::Export) -> Result<(), ParsingError> {
+		let name = export.name.to_owned();
+		let index = export.index as usize;
+		let kind = match export.kind {
+			ExternalKind::Func => {
+				self.module.functions.get_local_mut(index)?.export_name = Some(name.clone());
+				ExportKind::Function
+			},
+			ExternalKind::Table => {
+				if let Some(table) = self.module.table_blueprints.get_mut(index) {
+					table.export_name = Some(name.clone());
+				}
+				ExportKind::Table
+			},
+			ExternalKind::Memory => {
+				if let Some(memory) = &mut self.module.memory_blueprint {
+					memory.export_name = Some(name.clone());
+				}
+				ExportKind::Memory
+			},
+			ExternalKind::Global => {
+				if let Some(global) = self.module.globals.get_mut(index) {
+					global.export_name = Some(name.clone());
+				}
+				ExportKind::Global
+			},
+			ExternalKind::Tag => return Err(ParsingError::Unsupported("Exported tags")),
+		};
+		tracing::debug!(index, %name, ?kind, "Export");
+		self.module.export_map.insert(Export { name, kind, index });
+		Ok(())
+	}
+
+	fn element(&mut self, element: wasmparser::Element) -> Result<(), ParsingError> {
+		let mode = match element.kind {
+			ElementKind::Active { table_index, offset_expr } => {
+				let expression = ConstExpr::new(const_expr(&offset_expr)?);
+				let (offset, offset_expr) = eval_offset(&self.module, &self.global_values, expression)?;
+				ElementMode::Active { table: table_index.unwrap_or(0) as usize, offset, offset_expr }
+			},
+			ElementKind::Passive => ElementMode::Passive,
+			ElementKind::Declared => ElementMode::Declarative,
+		};
+		let (element_type, init) = match element.items {
+			ElementItems::Functions(reader) => {
+				let init = reader.into_iter()
+					.map(|function_index| Ok(Some(function_index? as usize)))
+					.collect::<Result<_, ParsingError>>()?;
+				(Type::FuncRef, init)
+			},
+			ElementItems::Expressions(ref_type, reader) => {
+				let init = reader.into_iter()
+					.map(|expression| {
+						let expression = const_expr(&expression?)?;
+						let element = match expression[..] {
+							[Instruction::RefFunc(function_index)] => Some(Some(function_index)),
+							[Instruction::RefNull(_)] => Some(None),
+							_ => None,
+						};
+						element.ok_or(ParsingError::UnsupportedConstExpr(expression))
+					})
+					.collect::<Result<_, ParsingError>>()?;
+				(reference_type(ref_type)?, init)
+			},
+		};
+		let element_segment = ElementSegment { mode, element_type, init };
+		tracing::debug!(?element_segment);
+		self.module.elements.push(element_segment);
+		Ok(())
+	}
+
+	fn data(&mut self, data: wasmparser::Data) -> Result<(), ParsingError> {
+		let offset_expr = match data.kind {
+			DataKind::Active { memory_index: 0, offset_expr } => offset_expr,
+			DataKind::Active { .. } => return Err(ParsingError::Unsupported("Multiple memories")),
+			DataKind::Passive => {
+				self.module.passive_data.push(Some(self.shared_bytes(data.data)));
+				return Ok(());
+			},
+		};
+		let expression = ConstExpr::new(const_expr(&offset_expr)?);
+		let (addr, offset) = eval_offset(&self.module, &self.global_values, expression)?;
+		let data_segment = DataSegment { addr, offset, data: self.shared_bytes(data.data) };
+		tracing::debug!(?data_segment);
+		self.module.memory_blueprint.as_mut()
+			.ok_or(ParsingError::Unsupported("Data segments without memory"))?
+			.init.push(data_segment);
+		self.module.passive_data.push(None);
+		Ok(())
+	}
+
+	#[tracing::instrument(name = "function", skip(self, body))]
+	fn function_code(&mut self, function_index: usize, body: FunctionBody) -> Result<(), ParsingError> {
+		let mut locals = Vec::new();
+		for local in body.get_locals_reader()? {
+			let (count, local_type) = local?;
+			locals.extend(iter::repeat_n(value_type(local_type)?, count as usize));
+		}
+		let mut offsets = Vec::new();
+		let instructions = instructions(body.get_operators_reader()?, &mut offsets)?;
+		let function = self.module.functions.get_local_mut(function_index)?;
+		function.locals = locals;
+		function.body = instructions;
+		function.body_offset = body.range().start;
+		function.offsets = offsets;
+		Ok(())
+	}
+}
+
+fn value_type(value_type: ValType) -> Result<Type, ParsingError> {
+	Ok(match value_type {
+		ValType::I32 => Type::I32,
+		ValType::I64 => Type::I64,
+		ValType::F32 => Type::F32,
+		ValType::F64 => Type::F64,
+		ValType::V128 => Type::V128,
+		ValType::Ref(ref_type) => reference_type(ref_type)?,
+	})
+}
+
+fn reference_type(ref_type: RefType) -> Result<Type, ParsingError> {
+	match ref_type {
+		RefType::FUNCREF => Ok(Type::FuncRef),
+		RefType::EXTERNREF => Ok(Type::ExternRef),
+		_ => Err(ParsingError::Unsupported("Typed function references")),
+	}
+}
+
+fn table_blueprint(
+	table_type: wasmparser::TableType,
+	import: Option<Identifier>,
+) -> Result<TableBlueprint, ParsingError> {
+	let element_type = reference_type(table_type.element_type)?;
+	let limit = table_type.initial as usize..table_type.maximum.map_or(u32::MAX as usize, |maximum| maximum as usize);
+	Ok(TableBlueprint { element_type, limit, export_name: None, import })
+}
+
+fn memory_blueprint(memory_type: wasmparser::MemoryType, import: Option<Identifier>) -> MemoryBlueprint {
+	let maximum = memory_type.maximum.map_or(u32::MAX as usize, |maximum| maximum as usize);
+	let page_limit = memory_type.initial as usize..maximum;
+	MemoryBlueprint { page_limit, shared: memory_type.shared, export_name: None, import, init: Vec::new() }
+}
+
+fn block_type(block_type: wasmparser::BlockType) -> Result<BlockType, ParsingError> {
+	Ok(match block_type {
+		wasmparser::BlockType::Empty => BlockType::Empty,
+		wasmparser::BlockType::Type(value) => BlockType::Value(value_type(value)?),
+		wasmparser::BlockType::FuncType(type_index) => BlockType::TypeIndex(type_index as usize),
+	})
+}
+
+fn mem_arg(mem_arg: &wasmparser::MemArg) -> Result<MemArg, ParsingError> {
+	if mem_arg.memory != 0 {
+		return Err(ParsingError::Unsupported("Multiple memories"));
+	}
+	let offset = u32::try_from(mem_arg.offset).map_err(|_| ParsingError::Unsupported("64 bit memory offsets"))?;
+	Ok(MemArg { align: mem_arg.align as u32, offset })
+}
+
+fn const_expr(expression: &wasmparser::ConstExpr) -> Result<Vec<Instruction>, ParsingError> {
+	instructions(expression.get_operators_reader(), &mut Vec::new())
+}
+
+/// A block whose `end` was not read yet.
+struct OpenBlock {
+	kind: BlockKind,
+	block_type: BlockType,
+	instructions: Vec<Instruction>,
+	/// The instructions after the `else` of an `if`, once it was read.
+	else_instructions: Option<Vec<Instruction>>,
+	/// The clauses of a `try` read so far, of which the last one is being read.
+	catches: Vec<Catch>,
+}
+
+enum BlockKind {
+	Block,
+	Loop,
+	If,
+	Try,
+}
+
+impl OpenBlock {
+	fn new(kind: BlockKind, block_type: BlockType) -> Self {
+		OpenBlock { kind, block_type, instructions: Vec::new(), else_instructions: None, catches: Vec::new() }
+	}
+
+	/// The instructions which the next instruction belongs to.
+	fn current(&mut self) -> &mut Vec<Instruction> {
+		match (self.catches.last_mut(), &mut self.else_instructions) {
+			(Some(catch), _) => &mut catch.instructions,
+			(None, Some(else_instructions)) => else_instructions,
+			(None, None) => &mut self.instructions,
+		}
+	}
+
+	fn into_instruction(self, delegate: Option<u32>) -> Instruction {
+		let OpenBlock { block_type, instructions, .. } = self;
+		match self.kind {
+			BlockKind::Block => Instruction::Block { block_type, instructions },
+			BlockKind::Loop => Instruction::Loop { block_type, instructions },
+			BlockKind::If => Instruction::If {
+				block_type,
+				if_instructions: instructions,
+				else_instructions: self.else_instructions.unwrap_or_default(),
+			},
+			BlockKind::Try => Instruction::Try { block_type, instructions, catches: self.catches, delegate },
+		}
+	}
+}
+
+/// Nests the flat operators of `reader` up to and including the final `end` into instructions, and appends the
+/// positions of all except the terminators `end`, `else`, `catch`, `catch_all` and `delegate` to `offsets`.
+fn instructions(mut reader: OperatorsReader, offsets: &mut Vec<usize>) -> Result<Vec<Instruction>, ParsingError> {
+	// The outermost block is the function body or constant expression itself
+	let mut blocks = vec![OpenBlock::new(BlockKind::Block, BlockType::Empty)];
+	loop {
+		let (operator, offset) = reader.read_with_offset()?;
+		let is_terminator = matches!(
+			operator,
+			Operator::End | Operator::Else | Operator::Catch { .. } | Operator::CatchAll | Operator::Delegate { .. }
+		);
+		if !is_terminator {
+			offsets.push(offset);
+		}
+		// The binary is validated, so there is always an open block
+		let block = blocks.last_mut().expect("Operator after the final end");
+		match operator {
+			Operator::Block { blockty } => blocks.push(OpenBlock::new(BlockKind::Block, block_type(blockty)?)),
+			Operator::Loop { blockty } => blocks.push(OpenBlock::new(BlockKind::Loop, block_type(blockty)?)),
+			Operator::If { blockty } => blocks.push(OpenBlock::new(BlockKind::If, block_type(blockty)?)),
+			Operator::Try { blockty } => blocks.push(OpenBlock::new(BlockKind::Try, block_type(blockty)?)),
+			Operator::Else => block.else_instructions = Some(Vec::new()),
+			Operator::Catch { tag_index } => {
+				block.catches.push(Catch { tag: Some(tag_index as usize), instructions: Vec::new() });
+			},
+			Operator::CatchAll => block.catches.push(Catch { tag: None, instructions: Vec::new() }),
+			Operator::End | Operator::Delegate { .. } => {
+				let delegate = match operator {
+					Operator::Delegate { relative_depth } => Some(relative_depth),
+					_ => None,
+				};
+				let block = blocks.pop().expect("Operator after the final end");
+				match blocks.last_mut() {
+					Some(outer) => outer.current().push(block.into_instruction(delegate)),
+					None => return Ok(block.instructions),
+				}
+			},
+			operator => block.current().push(instruction(operator, offset)?),
+		}
+	}
+}
+
+/// Converts an operator which is not a structured control instruction or terminator.
+fn instruction(operator: Operator, offset: usize) -> Result<Instruction, ParsingError> {
+	let instruction = match operator {
+		Operator::Unreachable => Instruction::Unreachable,
+		Operator::Nop => Instruction::Nop,
+		Operator::Br { relative_depth } => Instruction::Br { label_index: relative_depth },
+		Operator::BrIf { relative_depth } => Instruction::BrIf { label_index: relative_depth },
+		Operator::BrTable { targets } => Instruction::BrTable {
+			label_indexes: targets.targets().collect::<Result<_, _>>()?,
+			default: targets.default(),
+		},
+		Operator::Return => Instruction::Return,
+		Operator::Call { function_index } => Instruction::Call { function_index: function_index as usize },
+		Operator::CallIndirect { type_index, table_index, .. } => Instruction::CallIndirect {
+			table_index: table_index as usize,
+			type_index: type_index as usize,
+		},
+		Operator::ReturnCall { function_index } => Instruction::ReturnCall { function_index: function_index as usize },
+		Operator::ReturnCallIndirect { type_index, table_index } => Instruction::ReturnCallIndirect {
+			table_index: table_index as usize,
+			type_index: type_index as usize,
+		},
+		Operator::Throw { tag_index } => Instruction::Throw(tag_index as usize),
+		Operator::Rethrow { relative_depth } => Instruction::Rethrow(relative_depth),
+		Operator::RefNull { hty: HeapType::Func } => Instruction::RefNull(Type::FuncRef),
+		Operator::RefNull { hty: HeapType::Extern } => Instruction::RefNull(Type::ExternRef),
+		Operator::RefIsNull => Instruction::RefIsNull,
+		Operator::RefFunc { function_index } => Instruction::RefFunc(function_index as usize),
+		Operator::Drop => Instruction::Drop,
+		Operator::Select => Instruction::Select,
+		Operator::TypedSelect { .. } => Instruction::SelectValueType,
+		Operator::LocalGet { local_index } => Instruction::LocalGet(local_index as usize),
+		Operator::LocalSet { local_index } => Instruction::LocalSet(local_index as usize),
+		Operator::LocalTee { local_index } => Instruction::LocalTee(local_index as usize),
+		Operator::GlobalGet { global_index } => Instruction::GlobalGet(global_index as usize),
+		Operator::GlobalSet { global_index } => Instruction::GlobalSet(global_index as usize),
+		Operator::TableGet { table } => Instruction::TableGet(table as usize),
+		Operator::TableSet { table } => Instruction::TableSet(table as usize),
+		Operator::I32Load { memarg } => Instruction::I32Load(mem_arg(&memarg)?),
+		Operator::I64Load { memarg } => Instruction::I64Load(mem_arg(&memarg)?),
+		Operator::F32Load { memarg } => Instruction::F32Load(mem_arg(&memarg)?),
+		Operator::F64Load { memarg } => Instruction::F64Load(mem_arg(&memarg)?),
+		Operator::I32Load8S { memarg } => Instruction::I32Load8s(mem_arg(&memarg)?),
+		Operator::I32Load8U { memarg } => Instruction::I32Load8u(mem_arg(&memarg)?),
+		Operator::I32Load16S { memarg } => Instruction::I32Load16s(mem_arg(&memarg)?),
+		Operator::I32Load16U { memarg } => Instruction::I32Load16u(mem_arg(&memarg)?),
+		Operator::I64Load8S { memarg } => Instruction::I64Load8s(mem_arg(&memarg)?),
+		Operator::I64Load8U { memarg } => Instruction::I64Load8u(mem_arg(&memarg)?),
+		Operator::I64Load16S { memarg } => Instruction::I64Load16s(mem_arg(&memarg)?),
+		Operator::I64Load16U { memarg } => Instruction::I64Load16u(mem_arg(&memarg)?),
+		Operator::I64Load32S { memarg } => Instruction::I64Load32s(mem_arg(&memarg)?),
+		Operator::I64Load32U { memarg } => Instruction::I64Load32u(mem_arg(&memarg)?),
+		Operator::I32Store { memarg } => Instruction::I32Store(mem_arg(&memarg)?),
+		Operator::I64Store { memarg } => Instruction::I64Store(mem_arg(&memarg)?),
+		Operator::F32Store { memarg } => Instruction::F32Store(mem_arg(&memarg)?),
+		Operator::F64Store { memarg } => Instruction::F64Store(mem_arg(&memarg)?),
+		Operator::I32Store8 { memarg } => Instruction::I32Store8(mem_arg(&memarg)?),
+		Operator::I32Store16 { memarg } => Instruction::I32Store16(mem_arg(&memarg)?),
+		Operator::I64Store8 { memarg } => Instruction::I64Store8(mem_arg(&memarg)?),
+		Operator::I64Store16 { memarg } => Instruction::I64Store16(mem_arg(&memarg)?),
+		Operator::I64Store32 { memarg } => Instruction::I64Store32(mem_arg(&memarg)?),
+		Operator::MemorySize { mem: 0, .. } => Instruction::MemorySize,
+		Operator::MemoryGrow { mem: 0, .. } => Instruction::MemoryGrow,
+		Operator::I32Const { value } => Instruction::I32Const(value),
+		Operator::I64Const { value } => Instruction::I64Const(value),
+		Operator::F32Const { value } => Instruction::F32Const(f32::from_bits(value.bits())),
+		Operator::F64Const { value } => Instruction::F64Const(f64::from_bits(value.bits())),
+		Operator::I32Eqz => Instruction::I32Eqz,
+		Operator::I32Eq => Instruction::I32Eq,
+		Operator::I32Ne => Instruction::I32Ne,
+		Operator::I32LtS => Instruction::I32LtS,
+		Operator::I32LtU => Instruction::I32LtU,
+		Operator::I32GtS => Instruction::I32GtS,
+		Operator::I32GtU => Instruction::I32GtU,
+		Operator::I32LeS => Instruction::I32LeS,
+		Operator::I32LeU => Instruction::I32LeU,
+		Operator::I32GeS => Instruction::I32GeS,
+		Operator::I32GeU => Instruction::I32GeU,
+		Operator::I64Eqz => Instruction::I64Eqz,
+		Operator::I64Eq => Instruction::I64Eq,
+		Operator::I64Ne => Instruction::I64Ne,
+		Operator::I64LtS => Instruction::I64LtS,
+		Operator::I64LtU => Instruction::I64LtU,
+		Operator::I64GtS => Instruction::I64GtS,
+		Operator::I64GtU => Instruction::I64GtU,
+		Operator::I64LeS => Instruction::I64LeS,
+		Operator::I64LeU => Instruction::I64LeU,
+		Operator::I64GeS => Instruction::I64GeS,
+		Operator::I64GeU => Instruction::I64GeU,
+		Operator::F32Eq => Instruction::F32Eq,
+		Operator::F32Ne => Instruction::F32Ne,
+		Operator::F32Lt => Instruction::F32Lt,
+		Operator::F32Gt => Instruction::F32Gt,
+		Operator::F32Le => Instruction::F32Le,
+		Operator::F32Ge => Instruction::F32Ge,
+		Operator::F64Eq => Instruction::F64Eq,
+		Operator::F64Ne => Instruction::F64Ne,
+		Operator::F64Lt => Instruction::F64Lt,
+		Operator::F64Gt => Instruction::F64Gt,
+		Operator::F64Le => Instruction::F64Le,
+		Operator::F64Ge => Instruction::F64Ge,
+		Operator::I32Clz => Instruction::I32Clz,
+		Operator::I32Ctz => Instruction::I32Ctz,
+		Operator::I32Popcnt => Instruction::I32Popcnt,
+		Operator::I32Add => Instruction::I32Add,
+		Operator::I32Sub => Instruction::I32Sub,
+		Operator::I32Mul => Instruction::I32Mul,
+		Operator::I32DivS => Instruction::I32DivS,
+		Operator::I32DivU => Instruction::I32DivU,
+		Operator::I32RemS => Instruction::I32RemS,
+		Operator::I32RemU => Instruction::I32RemU,
+		Operator::I32And => Instruction::I32And,
+		Operator::I32Or => Instruction::I32Or,
+		Operator::I32Xor => Instruction::I32Xor,
+		Operator::I32Shl => Instruction::I32Shl,
+		Operator::I32ShrS => Instruction::I32ShrS,
+		Operator::I32ShrU => Instruction::I32ShrU,
+		Operator::I32Rotl => Instruction::I32Rotl,
+		Operator::I32Rotr => Instruction::I32Rotr,
+		Operator::I64Clz => Instruction::I64Clz,
+		Operator::I64Ctz => Instruction::I64Ctz,
+		Operator::I64Popcnt => Instruction::I64Popcnt,
+		Operator::I64Add => Instruction::I64Add,
+		Operator::I64Sub => Instruction::I64Sub,
+		Operator::I64Mul => Instruction::I64Mul,
+		Operator::I64DivS => Instruction::I64DivS,
+		Operator::I64DivU => Instruction::I64DivU,
+		Operator::I64RemS => Instruction::I64RemS,
+		Operator::I64RemU => Instruction::I64RemU,
+		Operator::I64And => Instruction::I64And,
+		Operator::I64Or => Instruction::I64Or,
+		Operator::I64Xor => Instruction::I64Xor,
+		Operator::I64Shl => Instruction::I64Shl,
+		Operator::I64ShrS => Instruction::I64ShrS,
+		Operator::I64ShrU => Instruction::I64ShrU,
+		Operator::I64Rotl => Instruction::I64Rotl,
+		Operator::I64Rotr => Instruction::I64Rotr,
+		Operator::F32Abs => Instruction::F32Abs,
+		Operator::F32Neg => Instruction::F32Neg,
+		Operator::F32Ceil => Instruction::F32Ceil,
+		Operator::F32Floor => Instruction::F32Floor,
+		Operator::F32Trunc => Instruction::F32Trunc,
+		Operator::F32Nearest => Instruction::F32Nearest,
+		Operator::F32Sqrt => Instruction::F32Sqrt,
+		Operator::F32Add => Instruction::F32Add,
+		Operator::F32Sub => Instruction::F32Sub,
+		Operator::F32Mul => Instruction::F32Mul,
+		Operator::F32Div => Instruction::F32Div,
+		Operator::F32Min => Instruction::F32Min,
+		Operator::F32Max => Instruction::F32Max,
+		Operator::F32Copysign => Instruction::F32Copysign,
+		Operator::F64Abs => Instruction::F64Abs,
+		Operator::F64Neg => Instruction::F64Neg,
+		Operator::F64Ceil => Instruction::F64Ceil,
+		Operator::F64Floor => Instruction::F64Floor,
+		Operator::F64Trunc => Instruction::F64Trunc,
+		Operator::F64Nearest => Instruction::F64Nearest,
+		Operator::F64Sqrt => Instruction::F64Sqrt,
+		Operator::F64Add => Instruction::F64Add,
+		Operator::F64Sub => Instruction::F64Sub,
+		Operator::F64Mul => Instruction::F64Mul,
+		Operator::F64Div => Instruction::F64Div,
+		Operator::F64Min => Instruction::F64Min,
+		Operator::F64Max => Instruction::F64Max,
+		Operator::F64Copysign => Instruction::F64Copysign,
+		Operator::I32WrapI64 => Instruction::I32WrapI64,
+		Operator::I32TruncF32S => Instruction::I32TruncF32S,
+		Operator::I32TruncF32U => Instruction::I32TruncF32U,
+		Operator::I32TruncF64S => Instruction::I32TruncF64S,
+		Operator::I32TruncF64U => Instruction::I32TruncF64U,
+		Operator::I64ExtendI32S => Instruction::I64ExtendI32S,
+		Operator::I64ExtendI32U => Instruction::I64ExtendI32U,
+		Operator::I64TruncF32S => Instruction::I64TruncF32S,
+		Operator::I64TruncF32U => Instruction::I64TruncF32U,
+		Operator::I64TruncF64S => Instruction::I64TruncF64S,
+		Operator::I64TruncF64U => Instruction::I64TruncF64U,
+		Operator::F32ConvertI32S => Instruction::F32ConvertI32S,
+		Operator::F32ConvertI32U => Instruction::F32ConvertI32U,
+		Operator::F32ConvertI64S => Instruction::F32ConvertI64S,
+		Operator::F32ConvertI64U => Instruction::F32ConvertI64,
+		Operator::F32DemoteF64 => Instruction::F32DemoteF64,
+		Operator::F64ConvertI32S => Instruction::F64ConvertI32S,
+		Operator::F64ConvertI32U => Instruction::F64ConvertI32U,
+		Operator::F64ConvertI64S => Instruction::F64ConvertI64S,
+		Operator::F64ConvertI64U => Instruction::F64ConvertI64U,
+		Operator::F64PromoteF32 => Instruction::F64PromoteF32,
+		Operator::I32ReinterpretF32 => Instruction::I32ReinterpretF32,
+		Operator::I64ReinterpretF64 => Instruction::I64ReinterpretF64,
+		Operator::F32ReinterpretI32 => Instruction::F32ReinterpretI32,
+		Operator::F64ReinterpretI64 => Instruction::F64ReinterpretI64,
+		Operator::I32Extend8S => Instruction::I32Extend8S,
+		Operator::I32Extend16S => Instruction::I32Extend16S,
+		Operator::I64Extend8S => Instruction::I64Extend8S,
+		Operator::I64Extend16S => Instruction::I64Extend16S,
+		Operator::I64Extend32S => Instruction::I64Extend32S,
+		Operator::I32TruncSatF32S => Instruction::I32TruncSatF32S,
+		Operator::I32TruncSatF32U => Instruction::I32TruncSatF32U,
+		Operator::I32TruncSatF64S => Instruction::I32TruncSatF64S,
+		Operator::I32TruncSatF64U => Instruction::I32TruncSatF64U,
+		Operator::I64TruncSatF32S => Instruction::I64TruncSatF32S,
+		Operator::I64TruncSatF32U => Instruction::I64TruncSatF32U,
+		Operator::I64TruncSatF64S => Instruction::I64TruncSatF64S,
+		Operator::I64TruncSatF64U => Instruction::I64TruncSatF64U,
+		Operator::MemoryInit { data_index, mem: 0 } => Instruction::MemoryInit(data_index as usize),
+		Operator::DataDrop { data_index } => Instruction::DataDrop(data_index as usize),
+		Operator::MemoryCopy { dst_mem: 0, src_mem: 0 } => Instruction::MemoryCopy,
+		Operator::MemoryFill { mem: 0 } => Instruction::MemoryFill,
+		Operator::TableInit { elem_index, table } => Instruction::TableInit {
+			table_index: table as usize,
+			element_index: elem_index as usize,
+		},
+		Operator::ElemDrop { elem_index } => Instruction::ElemDrop(elem_index as usize),
+		Operator::TableCopy { dst_table, src_table } => Instruction::TableCopy {
+			dst_table_index: dst_table as usize,
+			src_table_index: src_table as usize,
+		},
+		Operator::TableGrow { table } => Instruction::TableGrow(table as usize),
+		Operator::TableSize { table } => Instruction::TableSize(table as usize),
+		Operator::TableFill { table } => Instruction::TableFill(table as usize),
+		Operator::V128Load { memarg } => Instruction::V128Load(mem_arg(&memarg)?),
+		Operator::V128Store { memarg } => Instruction::V128Store(mem_arg(&memarg)?),
+		Operator::V128Const { value } => Instruction::V128Const(*value.bytes()),
+		Operator::I8x16Shuffle { lanes } => Instruction::I8x16Shuffle(lanes),
+		Operator::I8x16Swizzle => Instruction::I8x16Swizzle,
+		Operator::I8x16Splat => Instruction::I8x16Splat,
+		Operator::I16x8Splat => Instruction::I16x8Splat,
+		Operator::I32x4Splat => Instruction::I32x4Splat,
+		Operator::I64x2Splat => Instruction::I64x2Splat,
+		Operator::F32x4Splat => Instruction::F32x4Splat,
+		Operator::F64x2Splat => Instruction::F64x2Splat,
+		Operator::I8x16ExtractLaneS { lane } => Instruction::I8x16ExtractLaneS(lane),
+		Operator::I8x16ExtractLaneU { lane } => Instruction::I8x16ExtractLaneU(lane),
+		Operator::I8x16ReplaceLane { lane } => Instruction::I8x16ReplaceLane(lane),
+		Operator::I16x8ExtractLaneS { lane } => Instruction::I16x8ExtractLaneS(lane),
+		Operator::I16x8ExtractLaneU { lane } => Instruction::I16x8ExtractLaneU(lane),
+		Operator::I16x8ReplaceLane { lane } => Instruction::I16x8ReplaceLane(lane),
+		Operator::I32x4ExtractLane { lane } => Instruction::I32x4ExtractLane(lane),
+		Operator::I32x4ReplaceLane { lane } => Instruction::I32x4ReplaceLane(lane),
+		Operator::I64x2ExtractLane { lane } => Instruction::I64x2ExtractLane(lane),
+		Operator::I64x2ReplaceLane { lane } => Instruction::I64x2ReplaceLane(lane),
+		Operator::F32x4ExtractLane { lane } => Instruction::F32x4ExtractLane(lane),
+		Operator::F32x4ReplaceLane { lane } => Instruction::F32x4ReplaceLane(lane),
+		Operator::F64x2ExtractLane { lane } => Instruction::F64x2ExtractLane(lane),
+		Operator::F64x2ReplaceLane { lane } => Instruction::F64x2ReplaceLane(lane),
+		Operator::I8x16Eq => Instruction::I8x16Eq,
+		Operator::I16x8Eq => Instruction::I16x8Eq,
+		Operator::I32x4Eq => Instruction::I32x4Eq,
+		Operator::V128Not => Instruction::V128Not,
+		Operator::V128And => Instruction::V128And,
+		Operator::V128AndNot => Instruction::V128AndNot,
+		Operator::V128Or => Instruction::V128Or,
+		Operator::V128Xor => Instruction::V128Xor,
+		Operator::V128Bitselect => Instruction::V128Bitselect,
+		Operator::V128AnyTrue => Instruction::V128AnyTrue,
+		Operator::I8x16AllTrue => Instruction::I8x16AllTrue,
+		Operator::I8x16Bitmask => Instruction::I8x16Bitmask,
+		Operator::I8x16Add => Instruction::I8x16Add,
+		Operator::I8x16Sub => Instruction::I8x16Sub,
+		Operator::I16x8Add => Instruction::I16x8Add,
+		Operator::I16x8Sub => Instruction::I16x8Sub,
+		Operator::I16x8Mul => Instruction::I16x8Mul,
+		Operator::I32x4Add => Instruction::I32x4Add,
+		Operator::I32x4Sub => Instruction::I32x4Sub,
+		Operator::I32x4Mul => Instruction::I32x4Mul,
+		Operator::I64x2Add => Instruction::I64x2Add,
+		Operator::I64x2Sub => Instruction::I64x2Sub,
+		Operator::I64x2Mul => Instruction::I64x2Mul,
+		Operator::MemoryAtomicNotify { memarg } => Instruction::MemoryAtomicNotify(mem_arg(&memarg)?),
+		Operator::MemoryAtomicWait32 { memarg } => Instruction::MemoryAtomicWait32(mem_arg(&memarg)?),
+		Operator::MemoryAtomicWait64 { memarg } => Instruction::MemoryAtomicWait64(mem_arg(&memarg)?),
+		Operator::AtomicFence { .. } => Instruction::AtomicFence,
+		operator => return atomic_instruction(operator, offset),
+	};
+	Ok(instruction)
+}
+
+/// Converts the atomic loads, stores and read-modify-write instructions of the threads proposal.
+fn atomic_instruction(operator: Operator, offset: usize) -> Result<Instruction, ParsingError> {
+	use AtomicRmwOp::*;
+	use AtomicWidth::*;
+
+	let instruction = match operator {
+		Operator::I32AtomicLoad { memarg } => Instruction::AtomicLoad(I32, mem_arg(&memarg)?),
+		Operator::I64AtomicLoad { memarg } => Instruction::AtomicLoad(I64, mem_arg(&memarg)?),
+		Operator::I32AtomicLoad8U { memarg } => Instruction::AtomicLoad(I32U8, mem_arg(&memarg)?),
+		Operator::I32AtomicLoad16U { memarg } => Instruction::AtomicLoad(I32U16, mem_arg(&memarg)?),
+		Operator::I64AtomicLoad8U { memarg } => Instruction::AtomicLoad(I64U8, mem_arg(&memarg)?),
+		Operator::I64AtomicLoad16U { memarg } => Instruction::AtomicLoad(I64U16, mem_arg(&memarg)?),
+		Operator::I64AtomicLoad32U { memarg } => Instruction::AtomicLoad(I64U32, mem_arg(&memarg)?),
+		Operator::I32AtomicStore { memarg } => Instruction::AtomicStore(I32, mem_arg(&memarg)?),
+		Operator::I64AtomicStore { memarg } => Instruction::AtomicStore(I64, mem_arg(&memarg)?),
+		Operator::I32AtomicStore8 { memarg } => Instruction::AtomicStore(I32U8, mem_arg(&memarg)?),
+		Operator::I32AtomicStore16 { memarg } => Instruction::AtomicStore(I32U16, mem_arg(&memarg)?),
+		Operator::I64AtomicStore8 { memarg } => Instruction::AtomicStore(I64U8, mem_arg(&memarg)?),
+		Operator::I64AtomicStore16 { memarg } => Instruction::AtomicStore(I64U16, mem_arg(&memarg)?),
+		Operator::I64AtomicStore32 { memarg } => Instruction::AtomicStore(I64U32, mem_arg(&memarg)?),
+		Operator::I32AtomicRmwAdd { memarg } => Instruction::AtomicRmw(Add, I32, mem_arg(&memarg)?),
+		Operator::I64AtomicRmwAdd { memarg } => Instruction::AtomicRmw(Add, I64, mem_arg(&memarg)?),
+		Operator::I32AtomicRmw8AddU { memarg } => Instruction::AtomicRmw(Add, I32U8, mem_arg(&memarg)?),
+		Operator::I32AtomicRmw16AddU { memarg } => Instruction::AtomicRmw(Add, I32U16, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw8AddU { memarg } => Instruction::AtomicRmw(Add, I64U8, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw16AddU { memarg } => Instruction::AtomicRmw(Add, I64U16, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw32AddU { memarg } => Instruction::AtomicRmw(Add, I64U32, mem_arg(&memarg)?),
+		Operator::I32AtomicRmwSub { memarg } => Instruction::AtomicRmw(Sub, I32, mem_arg(&memarg)?),
+		Operator::I64AtomicRmwSub { memarg } => Instruction::AtomicRmw(Sub, I64, mem_arg(&memarg)?),
+		Operator::I32AtomicRmw8SubU { memarg } => Instruction::AtomicRmw(Sub, I32U8, mem_arg(&memarg)?),
+		Operator::I32AtomicRmw16SubU { memarg } => Instruction::AtomicRmw(Sub, I32U16, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw8SubU { memarg } => Instruction::AtomicRmw(Sub, I64U8, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw16SubU { memarg } => Instruction::AtomicRmw(Sub, I64U16, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw32SubU { memarg } => Instruction::AtomicRmw(Sub, I64U32, mem_arg(&memarg)?),
+		Operator::I32AtomicRmwAnd { memarg } => Instruction::AtomicRmw(And, I32, mem_arg(&memarg)?),
+		Operator::I64AtomicRmwAnd { memarg } => Instruction::AtomicRmw(And, I64, mem_arg(&memarg)?),
+		Operator::I32AtomicRmw8AndU { memarg } => Instruction::AtomicRmw(And, I32U8, mem_arg(&memarg)?),
+		Operator::I32AtomicRmw16AndU { memarg } => Instruction::AtomicRmw(And, I32U16, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw8AndU { memarg } => Instruction::AtomicRmw(And, I64U8, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw16AndU { memarg } => Instruction::AtomicRmw(And, I64U16, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw32AndU { memarg } => Instruction::AtomicRmw(And, I64U32, mem_arg(&memarg)?),
+		Operator::I32AtomicRmwOr { memarg } => Instruction::AtomicRmw(Or, I32, mem_arg(&memarg)?),
+		Operator::I64AtomicRmwOr { memarg } => Instruction::AtomicRmw(Or, I64, mem_arg(&memarg)?),
+		Operator::I32AtomicRmw8OrU { memarg } => Instruction::AtomicRmw(Or, I32U8, mem_arg(&memarg)?),
+		Operator::I32AtomicRmw16OrU { memarg } => Instruction::AtomicRmw(Or, I32U16, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw8OrU { memarg } => Instruction::AtomicRmw(Or, I64U8, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw16OrU { memarg } => Instruction::AtomicRmw(Or, I64U16, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw32OrU { memarg } => Instruction::AtomicRmw(Or, I64U32, mem_arg(&memarg)?),
+		Operator::I32AtomicRmwXor { memarg } => Instruction::AtomicRmw(Xor, I32, mem_arg(&memarg)?),
+		Operator::I64AtomicRmwXor { memarg } => Instruction::AtomicRmw(Xor, I64, mem_arg(&memarg)?),
+		Operator::I32AtomicRmw8XorU { memarg } => Instruction::AtomicRmw(Xor, I32U8, mem_arg(&memarg)?),
+		Operator::I32AtomicRmw16XorU { memarg } => Instruction::AtomicRmw(Xor, I32U16, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw8XorU { memarg } => Instruction::AtomicRmw(Xor, I64U8, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw16XorU { memarg } => Instruction::AtomicRmw(Xor, I64U16, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw32XorU { memarg } => Instruction::AtomicRmw(Xor, I64U32, mem_arg(&memarg)?),
+		Operator::I32AtomicRmwXchg { memarg } => Instruction::AtomicRmw(Xchg, I32, mem_arg(&memarg)?),
+		Operator::I64AtomicRmwXchg { memarg } => Instruction::AtomicRmw(Xchg, I64, mem_arg(&memarg)?),
+		Operator::I32AtomicRmw8XchgU { memarg } => Instruction::AtomicRmw(Xchg, I32U8, mem_arg(&memarg)?),
+		Operator::I32AtomicRmw16XchgU { memarg } => Instruction::AtomicRmw(Xchg, I32U16, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw8XchgU { memarg } => Instruction::AtomicRmw(Xchg, I64U8, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw16XchgU { memarg } => Instruction::AtomicRmw(Xchg, I64U16, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw32XchgU { memarg } => Instruction::AtomicRmw(Xchg, I64U32, mem_arg(&memarg)?),
+		Operator::I32AtomicRmwCmpxchg { memarg } => Instruction::AtomicCmpxchg(I32, mem_arg(&memarg)?),
+		Operator::I64AtomicRmwCmpxchg { memarg } => Instruction::AtomicCmpxchg(I64, mem_arg(&memarg)?),
+		Operator::I32AtomicRmw8CmpxchgU { memarg } => Instruction::AtomicCmpxchg(I32U8, mem_arg(&memarg)?),
+		Operator::I32AtomicRmw16CmpxchgU { memarg } => Instruction::AtomicCmpxchg(I32U16, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw8CmpxchgU { memarg } => Instruction::AtomicCmpxchg(I64U8, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw16CmpxchgU { memarg } => Instruction::AtomicCmpxchg(I64U16, mem_arg(&memarg)?),
+		Operator::I64AtomicRmw32CmpxchgU { memarg } => Instruction::AtomicCmpxchg(I64U32, mem_arg(&memarg)?),
+		operator => {
+			return Err(ParsingError::UnsupportedOperator { operator: format!("{:?}", operator), offset });
+		},
+	};
+	Ok(instruction)
+}
\ No newline at end of file
@@ -3,7 +3,7 @@ use std::convert::TryFrom;
 use std::{fmt, io, iter};
 use std::fmt::{Formatter, Pointer};
 use std::ops::Range;
-use std::rc::Rc;
+use crate::shared::Shared;
 
 */
 
@@ -13,10 +13,30 @@ pub mod types;
 mod parser;
 // Only contains ParsingError, so re-export in this module.
 mod error;
+// Only contains ModuleBuilder, so re-export in this module.
+mod builder;
+// Only contains Features, so re-export it in this module.
+mod features;
+// Only contains DylinkInfo, so re-export it in this module.
+mod dylink;
+// Only contains Digest, so re-export it in this module.
+mod digest;
+// Only contains ModuleCache, so re-export it in this module.
+mod cache;
+// Not re-exported, as the names of the component model overlap with the ones of core modules.
+pub mod component;
+// Only adds Module::from_bytes_wasmparser, so there is nothing to re-export.
+#[cfg(feature = "frontend-wasmparser")]
+mod frontend;
 
 pub use types::*;
-pub use error::ParsingError;
+pub use error::{Hexdump, ParsingError};
 pub use parser::Parser;
+pub use builder::ModuleBuilder;
+pub use features::Features;
+pub use dylink::{DylinkInfo, DYLINK_SECTION};
+pub use digest::Digest;
+pub use cache::ModuleCache;
 
 /*#[cfg(test)]
 mod tests {
@@ -1,28 +1,145 @@
-use std::{io, iter};
-use std::rc::Rc;
+use std::{io, iter, mem};
+use std::io::Read;
+use std::ops::Range;
+use crate::shared::Shared;
 use crate::parse::{
+	component,
 	error::*,
 	types::*,
+	Digest,
+	Features,
 };
 use crate::exec::{types::*};
 
-pub struct Parser<ByteIter: io::Read> {
-	types: Vec<Rc<FunctionSignature>>,
+/// Number of bytes before and after an unknown opcode shown in [`ParsingError::InvalidOpcode`].
+const CONTEXT_LEN: usize = 16;
+
+/// Counts the bytes read from the underlying reader, so that the parser knows its position in the binary.
+struct CountingReader<R: io::Read> {
+	inner: R,
+	position: usize,
+	/// The last bytes read, indexed by their position modulo the length, for the context of errors.
+	recent: [u8; 2 * CONTEXT_LEN],
+}
+
+impl<R: io::Read> CountingReader<R> {
+	fn new(inner: R) -> Self {
+		CountingReader { inner, position: 0, recent: [0; 2 * CONTEXT_LEN] }
+	}
+
+	/// The bytes up to [`CONTEXT_LEN`] before and after `offset`. The preceding ones are taken from the recent
+	/// bytes, the following ones are read, as the parser does not continue after an error anyway.
+	fn context(&mut self, offset: usize) -> Hexdump {
+		let start = offset.saturating_sub(CONTEXT_LEN).max(self.position.saturating_sub(self.recent.len()));
+		let end = offset + 1 + CONTEXT_LEN;
+		let mut bytes: Vec<u8> = (start..self.position)
+			.map(|position| self.recent[position % self.recent.len()])
+			.collect();
+		let mut byte = [0u8; 1];
+		while self.position < end && io::Read::read_exact(self, &mut byte).is_ok() {
+			bytes.push(byte[0]);
+		}
+		Hexdump { offset: start, bytes }
+	}
+}
+
+impl<R: io::Read> io::Read for CountingReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let read = self.inner.read(buf)?;
+		let skipped = read.saturating_sub(self.recent.len());
+		for (i, byte) in buf[skipped..read].iter().enumerate() {
+			let len = self.recent.len();
+			self.recent[(self.position + skipped + i) % len] = *byte;
+		}
+		self.position += read;
+		Ok(read)
+	}
+}
+
+pub struct Parser<'p, ByteIter: io::Read> {
+	types: Vec<Shared<FunctionSignature>>,
 	module: Module,
-	bytecode: ByteIter,
+	bytecode: CountingReader<ByteIter>,
+	/// The complete binary if parsing from memory, which data segments reference instead of copying.
+	input: Option<Shared<[u8]>>,
+	custom_section_parsers: CustomSectionParsers<'p>,
+	/// Positions of the instructions of the function body being parsed, in the order they appear in the binary.
+	instruction_offsets: Vec<usize>,
+	/// Position of the instruction being parsed, which errors about unknown opcodes refer to.
+	instruction_start: usize,
+	/// Initial values of the globals parsed so far, which constant expressions of segment offsets may refer to.
+	/// `None` for imported globals and the ones initialized from them, which are only known at instantiation.
+	global_values: Vec<Option<Value>>,
+	/// The proposals the module may use.
+	features: Features,
 }
 
-impl<ByteIter: io::Read> Parser<ByteIter> {
-	#[tracing::instrument(skip_all)]
+impl<'p> Parser<'p, &[u8]> {
+	/// Parses the binary `bytes`, referencing it from data segments instead of copying them.
+	pub fn parse_bytes(bytes: Shared<[u8]>) -> Result<Module, ParsingError> {
+		Parser::parse_bytes_with(bytes, CustomSectionParsers::default())
+	}
+
+	/// Like [`Parser::parse_bytes`], but fails with [`ParsingError::FeatureNotEnabled`] if the module uses a
+	/// proposal not in `features`.
+	pub fn parse_bytes_with_features(bytes: Shared<[u8]>, features: Features) -> Result<Module, ParsingError> {
+		Parser::parse_bytes_internal(bytes, CustomSectionParsers::default(), features)
+	}
+
+	/// Like [`Parser::parse_bytes`], additionally calling `custom_section_parsers`.
+	pub fn parse_bytes_with(bytes: Shared<[u8]>, custom_section_parsers: CustomSectionParsers<'p>) -> Result<Module, ParsingError> {
+		Parser::parse_bytes_internal(bytes, custom_section_parsers, Features::all())
+	}
+
+	fn parse_bytes_internal(
+		bytes: Shared<[u8]>,
+		custom_section_parsers: CustomSectionParsers<'p>,
+		features: Features,
+	) -> Result<Module, ParsingError> {
+		let parser = Parser {
+			bytecode: CountingReader::new(&bytes[..]),
+			module: Module { digest: Some(Digest::of(&bytes)), ..Module::default() },
+			types: Vec::new(),
+			input: Some(Shared::clone(&bytes)),
+			custom_section_parsers,
+			instruction_offsets: Vec::new(),
+			instruction_start: 0,
+			global_values: Vec::new(),
+			features,
+		};
+		parser.parse_module_internal()
+	}
+}
+
+impl<'p, ByteIter: io::Read> Parser<'p, ByteIter> {
 	pub fn parse_module(bytecode: ByteIter) -> Result<Module, ParsingError> {
+		Parser::parse_module_with(bytecode, CustomSectionParsers::default())
+	}
+
+	/// Like [`Parser::parse_module`], additionally calling `custom_section_parsers`.
+	pub fn parse_module_with(bytecode: ByteIter, custom_section_parsers: CustomSectionParsers<'p>) -> Result<Module, ParsingError> {
 		let parser = Parser {
-			bytecode,
+			bytecode: CountingReader::new(bytecode),
 			module: Module::default(),
 			types: Vec::new(),
+			input: None,
+			custom_section_parsers,
+			instruction_offsets: Vec::new(),
+			instruction_start: 0,
+			global_values: Vec::new(),
+			features: Features::all(),
 		};
 		parser.parse_module_internal()
 	}
 
+	/// Fails if `feature` is not enabled.
+	fn require(&self, feature: Features) -> Result<(), ParsingError> {
+		match self.features.contains(feature) {
+			true => Ok(()),
+			false => Err(ParsingError::FeatureNotEnabled(feature)),
+		}
+	}
+
 	/// Reads one byte from [self.bytecode].
 	fn read_byte(&mut self) -> Result<u8, io::Error> {
 		let mut buf = [0u8; 1];
@@ -30,6 +147,12 @@ impl<ByteIter: io::Read> Parser<ByteIter> {
 		Ok(buf[0])
 	}
 
+	/// Reads an unsigned LEB128 integer which has to fit into 32 bit.
+	fn read_u32(&mut self) -> Result<u32, ParsingError> {
+		let value = leb128::read::unsigned(&mut self.bytecode)?;
+		u32::try_from(value).map_err(|_| ParsingError::Leb128Error(leb128::read::Error::Overflow))
+	}
+
 	fn parse_function_type(&mut self) -> Result<FunctionSignature, ParsingError> {
 		let mut function_type = FunctionSignature::default();
 		if Type::try_from(self.read_byte()?)? != Type::Function {
@@ -60,37 +183,53 @@ impl<ByteIter: io::Read> Parser<ByteIter> {
 		Ok(function_type)
 	}
 
-	#[tracing::instrument(skip_all)]
-	fn parse_type_section(&mut self) -> Result<Vec<Rc<FunctionSignature>>, ParsingError> {
+	fn parse_type_section(&mut self) -> Result<Vec<Shared<FunctionSignature>>, ParsingError> {
 		let num_types = leb128::read::unsigned(&mut self.bytecode)? as usize;
-		tracing::trace!("Parsing type section with {} types", num_types);
+		tracing::trace!(count = num_types, "Parsing the type section");
 		let mut types = Vec::with_capacity(num_types);
 		for _ in 0..num_types {
 			let function_type = self.parse_function_type()?;
-			tracing::debug!("{:?}", function_type);
-			types.push(Rc::new(function_type));
+			tracing::debug!(?function_type);
+			types.push(Shared::new(function_type));
 		}
 		Ok(types)
 	}
 
-	#[tracing::instrument(skip_all)]
 	fn parse_function_section(&mut self) -> Result<(), ParsingError> {
 		let num_functions = leb128::read::unsigned(&mut self.bytecode)? as usize;
-		tracing::trace!("Parsing function section with {} functions", num_functions);
+		tracing::trace!(count = num_functions, "Parsing the function section");
 
 		for _ in 0..num_functions {
 			let function_type_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
 			let function = WasmFunction {
-				index: self.module.functions.imports.len() + self.module.functions.wasm.len(),
-				export_name: None,
-				signature: Rc::clone(&self.types[function_type_index]),
+				signature: Shared::clone(&self.types[function_type_index]),
 				..WasmFunction::default()
 			};
-			self.module.functions.wasm.push(function);
+			self.module.functions.push_local(function);
 		}
 		Ok(())
 	}
 
+	/// Reads `len` bytes, which reference the input if parsing from memory and are copied otherwise.
+	fn read_shared_bytes(&mut self, len: usize) -> Result<SharedBytes, ParsingError> {
+		let start = self.bytecode.position;
+		match &self.input {
+			Some(input) => {
+				let input = Shared::clone(input);
+				let skipped = io::copy(&mut (&mut self.bytecode).take(len as u64), &mut io::sink())?;
+				if skipped < len as u64 {
+					return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+				}
+				Ok(SharedBytes::new(input, start..start + len))
+			},
+			None => {
+				let mut data = vec![0u8; len];
+				self.bytecode.read_exact(&mut data)?;
+				Ok(SharedBytes::from(data))
+			},
+		}
+	}
+
 	fn read_string(&mut self) -> Result<String, ParsingError> {
 		let length = leb128::read::unsigned(&mut self.bytecode)? as usize;
 		let mut string = vec![0u8; length];
@@ -106,24 +245,37 @@ impl<ByteIter: io::Read> Parser<ByteIter> {
 
 		match kind {
 			ExportKind::Function => {
-				tracing::debug!("Exporting function with index {} as `{}`", index, name);
-				self.module.functions.get_wasm_function(index)?.export_name = Some(name);
+				tracing::debug!(index, %name, "Exporting function");
+				self.module.functions.get_local_mut(index)?.export_name = Some(name.clone());
 			},
 			ExportKind::Memory => {
 				// TODO: Return error instead of unwrap
-				tracing::debug!("Exporting memory with index {} as `{}`", index, name);
-				self.module.memory_blueprint.as_mut().unwrap().export_name = Some(name);
+				tracing::debug!(index, %name, "Exporting memory");
+				self.module.memory_blueprint.as_mut().unwrap().export_name = Some(name.clone());
 			}
-			_ => unimplemented!()
+			ExportKind::Table => {
+				tracing::debug!(index, %name, "Exporting table");
+				let len = self.module.table_blueprints.len();
+				self.module.table_blueprints.get_mut(index)
+					.ok_or(ParsingError::ExportIndexOutOfBounds { kind: ExportKind::Table, index, len })?
+					.export_name = Some(name.clone());
+			},
+			ExportKind::Global => {
+				tracing::debug!(index, %name, "Exporting global");
+				let len = self.module.globals.len();
+				self.module.globals.get_mut(index)
+					.ok_or(ParsingError::ExportIndexOutOfBounds { kind: ExportKind::Global, index, len })?
+					.export_name = Some(name.clone());
+			},
 		}
+		self.module.export_map.insert(Export { name, kind, index });
 
 		Ok(())
 	}
 
-	#[tracing::instrument(skip_all)]
 	fn parse_export_section(&mut self) -> Result<(), ParsingError> {
 		let num_exports = leb128::read::unsigned(&mut self.bytecode)? as usize;
-		tracing::trace!("Parsing export section with {} functions", num_exports);
+		tracing::trace!(count = num_exports, "Parsing the export section");
 
 		for _ in 0..num_exports {
 			self.parse_export()?;
@@ -131,43 +283,136 @@ impl<ByteIter: io::Read> Parser<ByteIter> {
 		Ok(())
 	}
 
-	fn parse_block(&mut self) -> Result<Vec<Instruction>, ParsingError> {
-		let instructions = self.parse_instructions()?;
-		if Opcode::try_from(self.read_byte()?)? != Opcode::End {
-			return Err(ParsingError::ExpectedOpcode(Opcode::End));
+	/// Parses a block type, which is either empty, a single result type or an index into the type section.
+	fn parse_block_type(&mut self) -> Result<BlockType, ParsingError> {
+		let byte = self.read_byte()?;
+		if byte == 0x40 {
+			return Ok(BlockType::Empty);
+		}
+		match Type::try_from(byte) {
+			Ok(value_type @ (Type::I32 | Type::I64 | Type::F32 | Type::F64 | Type::V128 | Type::FuncRef | Type::ExternRef)) => {
+				Ok(BlockType::Value(value_type))
+			},
+			_ => {
+				// The byte already read is the start of a signed LEB128 encoded type index
+				let first_byte = [byte];
+				let mut type_index_bytes = first_byte.as_slice().chain(&mut self.bytecode);
+				let type_index = leb128::read::signed(&mut type_index_bytes)? as usize;
+				Ok(BlockType::TypeIndex(type_index))
+			},
 		}
-		Ok(instructions)
 	}
 
 	fn parse_memarg(&mut self) -> Result<MemArg, ParsingError> {
 		Ok(MemArg {
-			align: leb128::read::unsigned(&mut self.bytecode)? as usize,
-			offset: leb128::read::unsigned(&mut self.bytecode)? as usize,
+			align: self.read_u32()?,
+			offset: self.read_u32()?,
 		})
 	}
 
+	/// Parses instructions up to and including the terminating [`Opcode::End`].
 	fn parse_instructions(&mut self) -> Result<Vec<Instruction>, ParsingError> {
+		match self.parse_instruction_sequence()? {
+			(instructions, Opcode::End) => Ok(instructions),
+			_ => Err(ParsingError::ExpectedOpcode(Opcode::End)),
+		}
+	}
+
+	/// Parses instructions up to and including the terminating [`Opcode::End`], [`Opcode::Else`],
+	/// [`Opcode::Catch`], [`Opcode::CatchAll`] or [`Opcode::Delegate`], which is returned along with the instructions.
+	fn parse_instruction_sequence(&mut self) -> Result<(Vec<Instruction>, Opcode), ParsingError> {
 		let mut instructions = Vec::new();
-		loop {
+		let terminator = loop {
+			let position = self.bytecode.position;
+			self.instruction_start = position;
 			let opcode = Opcode::try_from(self.read_byte()?)?;
+			hot_trace!(offset = position, ?opcode);
+			let is_terminator = matches!(
+				opcode, Opcode::End | Opcode::Else | Opcode::Catch | Opcode::CatchAll | Opcode::Delegate
+			);
+			if !is_terminator {
+				self.instruction_offsets.push(position);
+			}
 			let instruction = match opcode {
 				Opcode::Unreachable => Instruction::Unreachable,
 				Opcode::Nop => Instruction::Nop,
 				Opcode::Block => Instruction::Block {
-					instructions: self.parse_block()?,
-					block_type: 0,
+					block_type: self.parse_block_type()?,
+					instructions: self.parse_instructions()?,
+				},
+				Opcode::Loop => Instruction::Loop {
+					block_type: self.parse_block_type()?,
+					instructions: self.parse_instructions()?,
+				},
+				Opcode::If => {
+					let block_type = self.parse_block_type()?;
+					let (if_instructions, terminator) = self.parse_instruction_sequence()?;
+					let else_instructions = match terminator {
+						Opcode::Else => self.parse_instructions()?,
+						_ => Vec::new(),
+					};
+					Instruction::If { block_type, if_instructions, else_instructions }
+				},
+				Opcode::Try => {
+					let block_type = self.parse_block_type()?;
+					let (instructions, mut terminator) = self.parse_instruction_sequence()?;
+					let mut catches = Vec::new();
+					let mut delegate = None;
+					loop {
+						let tag = match terminator {
+							Opcode::Catch => Some(leb128::read::unsigned(&mut self.bytecode)? as usize),
+							Opcode::CatchAll => None,
+							Opcode::Delegate => {
+								delegate = Some(leb128::read::unsigned(&mut self.bytecode)? as u32);
+								break;
+							},
+							Opcode::End => break,
+							_ => return Err(ParsingError::ExpectedOpcode(Opcode::End)),
+						};
+						let (instructions, next_terminator) = self.parse_instruction_sequence()?;
+						catches.push(Catch { tag, instructions });
+						terminator = next_terminator;
+					}
+					Instruction::Try { block_type, instructions, catches, delegate }
+				},
+				Opcode::Throw => Instruction::Throw(leb128::read::unsigned(&mut self.bytecode)? as usize),
+				Opcode::Rethrow => Instruction::Rethrow(leb128::read::unsigned(&mut self.bytecode)? as u32),
+				Opcode::End | Opcode::Else | Opcode::Catch | Opcode::CatchAll | Opcode::Delegate => break opcode,
+				Opcode::Br => {
+					let label_index = leb128::read::unsigned(&mut self.bytecode)? as u32;
+					Instruction::Br { label_index }
+				},
+				Opcode::BrIf => {
+					let label_index = leb128::read::unsigned(&mut self.bytecode)? as u32;
+					Instruction::BrIf { label_index }
+				},
+				Opcode::BrTable => {
+					let num_labels = leb128::read::unsigned(&mut self.bytecode)? as usize;
+					let label_indexes = (0..num_labels)
+						.map(|_| Ok(leb128::read::unsigned(&mut self.bytecode)? as u32))
+						.collect::<Result<Vec<u32>, ParsingError>>()?;
+					let default = leb128::read::unsigned(&mut self.bytecode)? as u32;
+					Instruction::BrTable { label_indexes, default }
 				},
-				Opcode::End => break,
 				Opcode::Return => Instruction::Return,
 				Opcode::Call => {
 					let function_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
 					Instruction::Call { function_index }
 				},
 				Opcode::CallIndirect => {
-					let table_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
 					let type_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
+					let table_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
 					Instruction::CallIndirect { table_index, type_index }
 				}
+				Opcode::ReturnCall => {
+					let function_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
+					Instruction::ReturnCall { function_index }
+				},
+				Opcode::ReturnCallIndirect => {
+					let type_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
+					let table_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
+					Instruction::ReturnCallIndirect { table_index, type_index }
+				},
 				// ...
 				Opcode::LocalGet => {
 					let index = leb128::read::unsigned(&mut self.bytecode)? as usize;
@@ -181,6 +426,14 @@ impl<ByteIter: io::Read> Parser<ByteIter> {
 					let index = leb128::read::unsigned(&mut self.bytecode)? as usize;
 					Instruction::LocalTee(index)
 				}
+				Opcode::GlobalGet => {
+					let index = leb128::read::unsigned(&mut self.bytecode)? as usize;
+					Instruction::GlobalGet(index)
+				},
+				Opcode::GlobalSet => {
+					let index = leb128::read::unsigned(&mut self.bytecode)? as usize;
+					Instruction::GlobalSet(index)
+				},
 				// ...
 				Opcode::I32Load => Instruction::I32Load(self.parse_memarg()?),
 				Opcode::I64Load => Instruction::I64Load(self.parse_memarg()?),
@@ -193,7 +446,7 @@ impl<ByteIter: io::Read> Parser<ByteIter> {
 				Opcode::I64Load8s => Instruction::I64Load8s(self.parse_memarg()?),
 				Opcode::I64Load8u => Instruction::I64Load8u(self.parse_memarg()?),
 				Opcode::I64Load16s => Instruction::I64Load16s(self.parse_memarg()?),
-				Opcode::I66Load16u => Instruction::I66Load16u(self.parse_memarg()?),
+				Opcode::I64Load16u => Instruction::I64Load16u(self.parse_memarg()?),
 				Opcode::I64Load32s => Instruction::I64Load32s(self.parse_memarg()?),
 				Opcode::I64Load32u => Instruction::I64Load32u(self.parse_memarg()?),
 				Opcode::I32Store => Instruction::I32Store(self.parse_memarg()?),
@@ -205,11 +458,19 @@ impl<ByteIter: io::Read> Parser<ByteIter> {
 				Opcode::I64Store8 => Instruction::I64Store8(self.parse_memarg()?),
 				Opcode::I64Store16 => Instruction::I64Store16(self.parse_memarg()?),
 				Opcode::I64Store32 => Instruction::I64Store32(self.parse_memarg()?),
+				Opcode::MemorySize => {
+					let _memory_index = self.read_byte()?;
+					Instruction::MemorySize
+				},
+				Opcode::MemoryGrow => {
+					let _memory_index = self.read_byte()?;
+					Instruction::MemoryGrow
+				},
 				Opcode::I32Const => {
-					Instruction::I32Const(leb128::read::unsigned(&mut self.bytecode)? as i32)
+					Instruction::I32Const(leb128::read::signed(&mut self.bytecode)? as i32)
 				},
 				Opcode::I64Const => {
-					Instruction::I32Const(leb128::read::unsigned(&mut self.bytecode)? as i32)
+					Instruction::I64Const(leb128::read::signed(&mut self.bytecode)?)
 				},
 				Opcode::F32Const => {
 					let mut float_bytes = [0u8; 4];
@@ -352,14 +613,255 @@ impl<ByteIter: io::Read> Parser<ByteIter> {
 				Opcode::I64Extend16S => Instruction::I64Extend16S,
 				Opcode::I64Extend32S => Instruction::I64Extend32S,
 				Opcode::Drop => Instruction::Drop,
-				other => {
-					tracing::error!("Unimplemented opcode {:?}", other);
-					continue
-				}
+				Opcode::Select => Instruction::Select,
+				Opcode::SelectValueType => {
+					// The types of the operands, which are only needed for validation
+					let num_types = leb128::read::unsigned(&mut self.bytecode)?;
+					for _ in 0..num_types {
+						Type::try_from(self.read_byte()?)?;
+					}
+					Instruction::SelectValueType
+				},
+				Opcode::RefNull => Instruction::RefNull(Type::try_from(self.read_byte()?)?),
+				Opcode::RefIsNull => Instruction::RefIsNull,
+				Opcode::RefFunc => {
+					let function_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
+					Instruction::RefFunc(function_index)
+				},
+				Opcode::TableGet => {
+					let table_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
+					Instruction::TableGet(table_index)
+				},
+				Opcode::TableSet => {
+					let table_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
+					Instruction::TableSet(table_index)
+				},
+				Opcode::Extension => self.parse_extension_instruction()?,
+				Opcode::Simd => {
+					self.require(Features::SIMD)?;
+					self.parse_simd_instruction()?
+				},
+				Opcode::Atomic => {
+					self.require(Features::THREADS)?;
+					self.parse_atomic_instruction()?
+				},
 			};
 			instructions.push(instruction);
+		};
+		Ok((instructions, terminator))
+	}
+
+	/// Parses the instruction following the [`Opcode::Extension`] prefix.
+	fn parse_extension_instruction(&mut self) -> Result<Instruction, ParsingError> {
+		let opcode = ExtensionOpcode::try_from(leb128::read::unsigned(&mut self.bytecode)? as u32)?;
+		let instruction = match opcode {
+			ExtensionOpcode::I32TruncSatF32S => Instruction::I32TruncSatF32S,
+			ExtensionOpcode::I32TruncSatF32U => Instruction::I32TruncSatF32U,
+			ExtensionOpcode::I32TruncSatF64S => Instruction::I32TruncSatF64S,
+			ExtensionOpcode::I32TruncSatF64U => Instruction::I32TruncSatF64U,
+			ExtensionOpcode::I64TruncSatF32S => Instruction::I64TruncSatF32S,
+			ExtensionOpcode::I64TruncSatF32U => Instruction::I64TruncSatF32U,
+			ExtensionOpcode::I64TruncSatF64S => Instruction::I64TruncSatF64S,
+			ExtensionOpcode::I64TruncSatF64U => Instruction::I64TruncSatF64U,
+			ExtensionOpcode::MemoryInit => {
+				let data_index = self.parse_data_index("memory.init")?;
+				// Reserved memory index
+				self.read_byte()?;
+				Instruction::MemoryInit(data_index)
+			},
+			ExtensionOpcode::DataDrop => Instruction::DataDrop(self.parse_data_index("data.drop")?),
+			ExtensionOpcode::MemoryCopy => {
+				// Reserved destination and source memory indices
+				self.read_byte()?;
+				self.read_byte()?;
+				Instruction::MemoryCopy
+			},
+			ExtensionOpcode::MemoryFill => {
+				// Reserved memory index
+				self.read_byte()?;
+				Instruction::MemoryFill
+			},
+			ExtensionOpcode::TableInit => {
+				let element_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
+				let table_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
+				Instruction::TableInit { table_index, element_index }
+			},
+			ExtensionOpcode::ElemDrop => Instruction::ElemDrop(leb128::read::unsigned(&mut self.bytecode)? as usize),
+			ExtensionOpcode::TableCopy => {
+				let dst_table_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
+				let src_table_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
+				Instruction::TableCopy { dst_table_index, src_table_index }
+			},
+			ExtensionOpcode::TableGrow => Instruction::TableGrow(leb128::read::unsigned(&mut self.bytecode)? as usize),
+			ExtensionOpcode::TableSize => Instruction::TableSize(leb128::read::unsigned(&mut self.bytecode)? as usize),
+			ExtensionOpcode::TableFill => Instruction::TableFill(leb128::read::unsigned(&mut self.bytecode)? as usize),
+		};
+		Ok(instruction)
+	}
+
+	/// Reads the data segment index of `instruction` and checks it against the data count section, which has to
+	/// precede the code section if data segments are referenced.
+	fn parse_data_index(&mut self, instruction: &'static str) -> Result<usize, ParsingError> {
+		let index = leb128::read::unsigned(&mut self.bytecode)? as usize;
+		let len = self.module.data_count.ok_or(ParsingError::MissingDataCount(instruction))?;
+		match index < len {
+			true => Ok(index),
+			false => Err(ParsingError::DataIndexOutOfBounds { index, len }),
 		}
-		Ok(instructions)
+	}
+
+	/// Reads a lane index and checks that it is smaller than `lanes`.
+	fn parse_lane_index(&mut self, lanes: u8) -> Result<u8, ParsingError> {
+		let lane = self.read_byte()?;
+		if lane >= lanes {
+			return Err(ParsingError::InvalidLaneIndex { lane, lanes });
+		}
+		Ok(lane)
+	}
+
+	/// Parses the instruction following the [`Opcode::Simd`] prefix.
+	fn parse_simd_instruction(&mut self) -> Result<Instruction, ParsingError> {
+		let opcode = SimdOpcode::try_from(leb128::read::unsigned(&mut self.bytecode)? as u32)?;
+		let instruction = match opcode {
+			SimdOpcode::V128Load => Instruction::V128Load(self.parse_memarg()?),
+			SimdOpcode::V128Store => Instruction::V128Store(self.parse_memarg()?),
+			SimdOpcode::V128Const => {
+				let mut bytes = [0u8; 16];
+				self.bytecode.read_exact(&mut bytes)?;
+				Instruction::V128Const(bytes)
+			},
+			SimdOpcode::I8x16Shuffle => {
+				let mut lanes = [0u8; 16];
+				for lane in &mut lanes {
+					*lane = self.parse_lane_index(32)?;
+				}
+				Instruction::I8x16Shuffle(lanes)
+			},
+			SimdOpcode::I8x16Swizzle => Instruction::I8x16Swizzle,
+			SimdOpcode::I8x16Splat => Instruction::I8x16Splat,
+			SimdOpcode::I16x8Splat => Instruction::I16x8Splat,
+			SimdOpcode::I32x4Splat => Instruction::I32x4Splat,
+			SimdOpcode::I64x2Splat => Instruction::I64x2Splat,
+			SimdOpcode::F32x4Splat => Instruction::F32x4Splat,
+			SimdOpcode::F64x2Splat => Instruction::F64x2Splat,
+			SimdOpcode::I8x16ExtractLaneS => Instruction::I8x16ExtractLaneS(self.parse_lane_index(16)?),
+			SimdOpcode::I8x16ExtractLaneU => Instruction::I8x16ExtractLaneU(self.parse_lane_index(16)?),
+			SimdOpcode::I8x16ReplaceLane => Instruction::I8x16ReplaceLane(self.parse_lane_index(16)?),
+			SimdOpcode::I16x8ExtractLaneS => Instruction::I16x8ExtractLaneS(self.parse_lane_index(8)?),
+			SimdOpcode::I16x8ExtractLaneU => Instruction::I16x8ExtractLaneU(self.parse_lane_index(8)?),
+			SimdOpcode::I16x8ReplaceLane => Instruction::I16x8ReplaceLane(self.parse_lane_index(8)?),
+			SimdOpcode::I32x4ExtractLane => Instruction::I32x4ExtractLane(self.parse_lane_index(4)?),
+			SimdOpcode::I32x4ReplaceLane => Instruction::I32x4ReplaceLane(self.parse_lane_index(4)?),
+			SimdOpcode::I64x2ExtractLane => Instruction::I64x2ExtractLane(self.parse_lane_index(2)?),
+			SimdOpcode::I64x2ReplaceLane => Instruction::I64x2ReplaceLane(self.parse_lane_index(2)?),
+			SimdOpcode::F32x4ExtractLane => Instruction::F32x4ExtractLane(self.parse_lane_index(4)?),
+			SimdOpcode::F32x4ReplaceLane => Instruction::F32x4ReplaceLane(self.parse_lane_index(4)?),
+			SimdOpcode::F64x2ExtractLane => Instruction::F64x2ExtractLane(self.parse_lane_index(2)?),
+			SimdOpcode::F64x2ReplaceLane => Instruction::F64x2ReplaceLane(self.parse_lane_index(2)?),
+			SimdOpcode::I8x16Eq => Instruction::I8x16Eq,
+			SimdOpcode::I16x8Eq => Instruction::I16x8Eq,
+			SimdOpcode::I32x4Eq => Instruction::I32x4Eq,
+			SimdOpcode::V128Not => Instruction::V128Not,
+			SimdOpcode::V128And => Instruction::V128And,
+			SimdOpcode::V128AndNot => Instruction::V128AndNot,
+			SimdOpcode::V128Or => Instruction::V128Or,
+			SimdOpcode::V128Xor => Instruction::V128Xor,
+			SimdOpcode::V128Bitselect => Instruction::V128Bitselect,
+			SimdOpcode::V128AnyTrue => Instruction::V128AnyTrue,
+			SimdOpcode::I8x16AllTrue => Instruction::I8x16AllTrue,
+			SimdOpcode::I8x16Bitmask => Instruction::I8x16Bitmask,
+			SimdOpcode::I8x16Add => Instruction::I8x16Add,
+			SimdOpcode::I8x16Sub => Instruction::I8x16Sub,
+			SimdOpcode::I16x8Add => Instruction::I16x8Add,
+			SimdOpcode::I16x8Sub => Instruction::I16x8Sub,
+			SimdOpcode::I16x8Mul => Instruction::I16x8Mul,
+			SimdOpcode::I32x4Add => Instruction::I32x4Add,
+			SimdOpcode::I32x4Sub => Instruction::I32x4Sub,
+			SimdOpcode::I32x4Mul => Instruction::I32x4Mul,
+			SimdOpcode::I64x2Add => Instruction::I64x2Add,
+			SimdOpcode::I64x2Sub => Instruction::I64x2Sub,
+			SimdOpcode::I64x2Mul => Instruction::I64x2Mul,
+		};
+		Ok(instruction)
+	}
+
+	/// Parses the instruction following the [`Opcode::Atomic`] prefix.
+	fn parse_atomic_instruction(&mut self) -> Result<Instruction, ParsingError> {
+		let opcode = AtomicOpcode::try_from(leb128::read::unsigned(&mut self.bytecode)? as u32)?;
+		let instruction = match opcode {
+			AtomicOpcode::MemoryAtomicNotify => Instruction::MemoryAtomicNotify(self.parse_memarg()?),
+			AtomicOpcode::MemoryAtomicWait32 => Instruction::MemoryAtomicWait32(self.parse_memarg()?),
+			AtomicOpcode::MemoryAtomicWait64 => Instruction::MemoryAtomicWait64(self.parse_memarg()?),
+			AtomicOpcode::AtomicFence => {
+				// Reserved byte for the memory ordering
+				self.read_byte()?;
+				Instruction::AtomicFence
+			},
+			AtomicOpcode::I32AtomicLoad => Instruction::AtomicLoad(AtomicWidth::I32, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicLoad => Instruction::AtomicLoad(AtomicWidth::I64, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicLoadU8 => Instruction::AtomicLoad(AtomicWidth::I32U8, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicLoadU16 => Instruction::AtomicLoad(AtomicWidth::I32U16, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicLoadU8 => Instruction::AtomicLoad(AtomicWidth::I64U8, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicLoadU16 => Instruction::AtomicLoad(AtomicWidth::I64U16, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicLoadU32 => Instruction::AtomicLoad(AtomicWidth::I64U32, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicStore => Instruction::AtomicStore(AtomicWidth::I32, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicStore => Instruction::AtomicStore(AtomicWidth::I64, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicStoreU8 => Instruction::AtomicStore(AtomicWidth::I32U8, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicStoreU16 => Instruction::AtomicStore(AtomicWidth::I32U16, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicStoreU8 => Instruction::AtomicStore(AtomicWidth::I64U8, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicStoreU16 => Instruction::AtomicStore(AtomicWidth::I64U16, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicStoreU32 => Instruction::AtomicStore(AtomicWidth::I64U32, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwAdd => Instruction::AtomicRmw(AtomicRmwOp::Add, AtomicWidth::I32, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwAdd => Instruction::AtomicRmw(AtomicRmwOp::Add, AtomicWidth::I64, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwAddU8 => Instruction::AtomicRmw(AtomicRmwOp::Add, AtomicWidth::I32U8, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwAddU16 => Instruction::AtomicRmw(AtomicRmwOp::Add, AtomicWidth::I32U16, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwAddU8 => Instruction::AtomicRmw(AtomicRmwOp::Add, AtomicWidth::I64U8, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwAddU16 => Instruction::AtomicRmw(AtomicRmwOp::Add, AtomicWidth::I64U16, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwAddU32 => Instruction::AtomicRmw(AtomicRmwOp::Add, AtomicWidth::I64U32, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwSub => Instruction::AtomicRmw(AtomicRmwOp::Sub, AtomicWidth::I32, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwSub => Instruction::AtomicRmw(AtomicRmwOp::Sub, AtomicWidth::I64, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwSubU8 => Instruction::AtomicRmw(AtomicRmwOp::Sub, AtomicWidth::I32U8, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwSubU16 => Instruction::AtomicRmw(AtomicRmwOp::Sub, AtomicWidth::I32U16, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwSubU8 => Instruction::AtomicRmw(AtomicRmwOp::Sub, AtomicWidth::I64U8, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwSubU16 => Instruction::AtomicRmw(AtomicRmwOp::Sub, AtomicWidth::I64U16, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwSubU32 => Instruction::AtomicRmw(AtomicRmwOp::Sub, AtomicWidth::I64U32, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwAnd => Instruction::AtomicRmw(AtomicRmwOp::And, AtomicWidth::I32, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwAnd => Instruction::AtomicRmw(AtomicRmwOp::And, AtomicWidth::I64, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwAndU8 => Instruction::AtomicRmw(AtomicRmwOp::And, AtomicWidth::I32U8, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwAndU16 => Instruction::AtomicRmw(AtomicRmwOp::And, AtomicWidth::I32U16, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwAndU8 => Instruction::AtomicRmw(AtomicRmwOp::And, AtomicWidth::I64U8, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwAndU16 => Instruction::AtomicRmw(AtomicRmwOp::And, AtomicWidth::I64U16, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwAndU32 => Instruction::AtomicRmw(AtomicRmwOp::And, AtomicWidth::I64U32, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwOr => Instruction::AtomicRmw(AtomicRmwOp::Or, AtomicWidth::I32, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwOr => Instruction::AtomicRmw(AtomicRmwOp::Or, AtomicWidth::I64, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwOrU8 => Instruction::AtomicRmw(AtomicRmwOp::Or, AtomicWidth::I32U8, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwOrU16 => Instruction::AtomicRmw(AtomicRmwOp::Or, AtomicWidth::I32U16, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwOrU8 => Instruction::AtomicRmw(AtomicRmwOp::Or, AtomicWidth::I64U8, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwOrU16 => Instruction::AtomicRmw(AtomicRmwOp::Or, AtomicWidth::I64U16, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwOrU32 => Instruction::AtomicRmw(AtomicRmwOp::Or, AtomicWidth::I64U32, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwXor => Instruction::AtomicRmw(AtomicRmwOp::Xor, AtomicWidth::I32, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwXor => Instruction::AtomicRmw(AtomicRmwOp::Xor, AtomicWidth::I64, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwXorU8 => Instruction::AtomicRmw(AtomicRmwOp::Xor, AtomicWidth::I32U8, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwXorU16 => Instruction::AtomicRmw(AtomicRmwOp::Xor, AtomicWidth::I32U16, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwXorU8 => Instruction::AtomicRmw(AtomicRmwOp::Xor, AtomicWidth::I64U8, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwXorU16 => Instruction::AtomicRmw(AtomicRmwOp::Xor, AtomicWidth::I64U16, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwXorU32 => Instruction::AtomicRmw(AtomicRmwOp::Xor, AtomicWidth::I64U32, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwXchg => Instruction::AtomicRmw(AtomicRmwOp::Xchg, AtomicWidth::I32, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwXchg => Instruction::AtomicRmw(AtomicRmwOp::Xchg, AtomicWidth::I64, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwXchgU8 => Instruction::AtomicRmw(AtomicRmwOp::Xchg, AtomicWidth::I32U8, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwXchgU16 => Instruction::AtomicRmw(AtomicRmwOp::Xchg, AtomicWidth::I32U16, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwXchgU8 => Instruction::AtomicRmw(AtomicRmwOp::Xchg, AtomicWidth::I64U8, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwXchgU16 => Instruction::AtomicRmw(AtomicRmwOp::Xchg, AtomicWidth::I64U16, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwXchgU32 => Instruction::AtomicRmw(AtomicRmwOp::Xchg, AtomicWidth::I64U32, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwCmpxchg => Instruction::AtomicCmpxchg(AtomicWidth::I32, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwCmpxchg => Instruction::AtomicCmpxchg(AtomicWidth::I64, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwCmpxchgU8 => Instruction::AtomicCmpxchg(AtomicWidth::I32U8, self.parse_memarg()?),
+			AtomicOpcode::I32AtomicRmwCmpxchgU16 => Instruction::AtomicCmpxchg(AtomicWidth::I32U16, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwCmpxchgU8 => Instruction::AtomicCmpxchg(AtomicWidth::I64U8, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwCmpxchgU16 => Instruction::AtomicCmpxchg(AtomicWidth::I64U16, self.parse_memarg()?),
+			AtomicOpcode::I64AtomicRmwCmpxchgU32 => Instruction::AtomicCmpxchg(AtomicWidth::I64U32, self.parse_memarg()?),
+		};
+		Ok(instruction)
 	}
 
 	fn parse_locals(&mut self, function_index: usize) -> Result<(), ParsingError> {
@@ -368,127 +870,361 @@ impl<ByteIter: io::Read> Parser<ByteIter> {
 			// A local declaration is a tuple of (local type count, local type)
 			let num_locals_of_type = leb128::read::unsigned(&mut self.bytecode)? as usize;
 			let local_type = Type::try_from(self.read_byte()?)?;
-			let locals_of_type = iter::repeat(local_type).take(num_locals_of_type);
-			self.module.functions.get_wasm_function(function_index)?.locals.extend(locals_of_type);
+			let locals_of_type = iter::repeat_n(local_type, num_locals_of_type);
+			self.module.functions.get_local_mut(function_index)?.locals.extend(locals_of_type);
 		}
 		Ok(())
 	}
 
+	#[tracing::instrument(name = "function", skip(self))]
 	fn parse_function_code(&mut self, function_index: usize) -> Result<(), ParsingError> {
 		let _code_size = leb128::read::unsigned(&mut self.bytecode)? as usize;
+		let body_offset = self.bytecode.position;
 		self.parse_locals(function_index)?;
-		self.module.functions.get_wasm_function(function_index)?.body = self.parse_instructions()?;
+		self.instruction_offsets.clear();
+		let body = self.parse_instructions().map_err(|error| self.invalid_opcode(function_index, error))?;
+		let function = self.module.functions.get_local_mut(function_index)?;
+		function.body = body;
+		function.body_offset = body_offset;
+		function.offsets = mem::take(&mut self.instruction_offsets);
 		Ok(())
 	}
 
-	#[tracing::instrument(skip_all)]
+	/// Adds the position, function and surrounding bytes to an error about an unknown opcode in the body of the
+	/// function `function_index`. Other errors are returned unchanged.
+	fn invalid_opcode(&mut self, function_index: usize, error: ParsingError) -> ParsingError {
+		match error {
+			ParsingError::UnknownOpcode(_) | ParsingError::UnknownExtensionOpcode(_)
+			| ParsingError::UnknownSimdOpcode(_) | ParsingError::UnknownAtomicOpcode(_) => {
+				let offset = self.instruction_start;
+				let context = self.bytecode.context(offset);
+				let opcode = context.bytes[offset - context.offset];
+				ParsingError::InvalidOpcode { function_index, offset, opcode, context, source: Box::new(error) }
+			},
+			error => error,
+		}
+	}
+
 	fn parse_code_section(&mut self) -> Result<(), ParsingError> {
 		let num_functions = leb128::read::unsigned(&mut self.bytecode)? as usize;
-		tracing::trace!("Parsing code section with {} functions", num_functions);
+		tracing::trace!(count = num_functions, "Parsing the code section");
 
 		for i in 0..num_functions {
-			// Skip extern functions when assigning code body to WASM functions
-			let function_index = self.module.functions.imports.len() + i;
+			// The code entries belong to the defined functions, which follow the imported ones
+			let function_index = self.module.functions.num_imports() + i;
 			self.parse_function_code(function_index)?;
 		}
 		Ok(())
 	}
 
-	#[tracing::instrument(skip_all)]
 	fn parse_import_section(&mut self) -> Result<(), ParsingError> {
 		let num_imports = leb128::read::unsigned(&mut self.bytecode)? as usize;
-		tracing::trace!("Parsing import section with {} imports", num_imports);
+		tracing::trace!(count = num_imports, "Parsing the import section");
 		for _ in 0..num_imports {
 			let module_name = self.read_string()?;
 			let field_name = self.read_string()?;
-			let import_kind = ExportKind::try_from(self.read_byte()?)?;
-			let signature_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
-			match import_kind {
+			let name = Identifier {
+				module: module_name,
+				field: field_name
+			};
+			match ExportKind::try_from(self.read_byte()?)? {
 				ExportKind::Function => {
+					let signature_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
 					let extern_function = ExternFunction {
-						name: Identifier {
-							module: module_name,
-							field: field_name
-						},
-						signature: Rc::clone(&self.types[signature_index]),
+						name,
+						signature: Shared::clone(&self.types[signature_index]),
 					};
-					tracing::debug!("Import {:?}", extern_function);
-					self.module.functions.imports.push(extern_function);
+					tracing::debug!(import = ?extern_function);
+					self.module.functions.push_import(extern_function);
+				},
+				ExportKind::Table => {
+					let element_type = Type::try_from(self.read_byte()?)?;
+					let limit = self.parse_limits()?;
+					let table_blueprint = TableBlueprint { element_type, limit, export_name: None, import: Some(name) };
+					tracing::debug!(import = ?table_blueprint);
+					self.module.table_blueprints.push(table_blueprint);
+				},
+				ExportKind::Memory => {
+					let memory_blueprint = self.parse_memory_type(Some(name))?;
+					tracing::debug!(import = ?memory_blueprint);
+					self.module.memory_blueprint = Some(memory_blueprint);
+				},
+				ExportKind::Global => {
+					let ty = Type::try_from(self.read_byte()?)?;
+					let mutable = Type::try_from(self.read_byte()?)? == Type::Var;
+					let init = ConstExpr::default();
+					let global = GlobalBlueprint { ty, mutable, init, export_name: None, import: Some(name) };
+					tracing::debug!(import = ?global);
+					self.module.globals.push(global);
+					self.global_values.push(None);
 				},
-				_ => unimplemented!(),
 			}
 		}
 		Ok(())
 	}
 
-	#[tracing::instrument(skip_all)]
+	/// Parses limits into a range of minimum and maximum size.
+	fn parse_limits(&mut self) -> Result<Range<usize>, ParsingError> {
+		match self.read_limit_kind()? {
+			LimitKind::SharedMinMax => Err(ParsingError::UnexpectedSharedLimit),
+			limit_kind => self.parse_limits_of_kind(limit_kind),
+		}
+	}
+
+	/// Reads the flags of limits, of which 64 bit indices are rejected unless [`Features::MEMORY64`] is enabled.
+	fn read_limit_kind(&mut self) -> Result<LimitKind, ParsingError> {
+		let flags = self.read_byte()?;
+		if flags & 0x04 != 0 {
+			self.require(Features::MEMORY64)?;
+		}
+		Ok(LimitKind::try_from(flags)?)
+	}
+
+	/// Parses the minimum and maximum following the already read `limit_kind`.
+	fn parse_limits_of_kind(&mut self, limit_kind: LimitKind) -> Result<Range<usize>, ParsingError> {
+		let limit = match limit_kind {
+			LimitKind::Min => {
+				let min = leb128::read::unsigned(&mut self.bytecode)? as usize;
+				min..(u32::MAX as usize)
+			},
+			LimitKind::MinMax | LimitKind::SharedMinMax => {
+				let min = leb128::read::unsigned(&mut self.bytecode)? as usize;
+				let max = leb128::read::unsigned(&mut self.bytecode)? as usize;
+				min..max
+			}
+		};
+		Ok(limit)
+	}
+
+	fn parse_table_section(&mut self) -> Result<(), ParsingError> {
+		let num_tables = leb128::read::unsigned(&mut self.bytecode)? as usize;
+		tracing::trace!(count = num_tables, "Parsing the table section");
+		for _ in 0..num_tables {
+			let element_type = Type::try_from(self.read_byte()?)?;
+			let limit = self.parse_limits()?;
+			let table_blueprint = TableBlueprint { element_type, limit, export_name: None, import: None };
+			tracing::trace!(?table_blueprint);
+			self.module.table_blueprints.push(table_blueprint);
+		}
+		Ok(())
+	}
+
+	/// Parses a constant expression like `i32.const 42` or `(i32.add (global.get 0) (i32.const 16))` into the `i32`
+	/// it evaluates to.
+	fn parse_offset_expression(&mut self) -> Result<(usize, Option<ConstExpr>), ParsingError> {
+		let expression = ConstExpr::new(self.parse_instructions()?);
+		eval_offset(&self.module, &self.global_values, expression)
+	}
+
+	/// Parses a vector of function indices as element initializers.
+	fn parse_element_function_indices(&mut self) -> Result<Vec<Option<usize>>, ParsingError> {
+		let num_elements = leb128::read::unsigned(&mut self.bytecode)? as usize;
+		let mut init = Vec::with_capacity(num_elements);
+		for _ in 0..num_elements {
+			init.push(Some(leb128::read::unsigned(&mut self.bytecode)? as usize));
+		}
+		Ok(init)
+	}
+
+	/// Parses a vector of `ref.func` or `ref.null` expressions as element initializers.
+	fn parse_element_expressions(&mut self) -> Result<Vec<Option<usize>>, ParsingError> {
+		let num_elements = leb128::read::unsigned(&mut self.bytecode)? as usize;
+		let mut init = Vec::with_capacity(num_elements);
+		for _ in 0..num_elements {
+			let offset = self.bytecode.position;
+			let expression = self.parse_instructions()?;
+			let element = match expression[..] {
+				[Instruction::RefFunc(function_index)] => Some(function_index),
+				[Instruction::RefNull(_)] => None,
+				_ => return Err(ParsingError::UnsupportedElementExpression { offset, expression }),
+			};
+			init.push(element);
+		}
+		Ok(init)
+	}
+
+	fn parse_element_section(&mut self) -> Result<(), ParsingError> {
+		let num_segments = leb128::read::unsigned(&mut self.bytecode)? as usize;
+		tracing::trace!(count = num_segments, "Parsing the element section");
+
+		for _ in 0..num_segments {
+			// The flags encode whether the segment is active with an implicit or explicit table index
+			// and whether the elements are function indices or expressions
+			let offset = self.bytecode.position;
+			let flags = leb128::read::unsigned(&mut self.bytecode)?;
+			let element_segment = match flags {
+				0 => {
+					let (offset, offset_expr) = self.parse_offset_expression()?;
+					let init = self.parse_element_function_indices()?;
+					let mode = ElementMode::Active { table: 0, offset, offset_expr };
+					ElementSegment { mode, element_type: Type::FuncRef, init }
+				},
+				1 | 3 => {
+					let mode = if flags == 1 { ElementMode::Passive } else { ElementMode::Declarative };
+					let _element_kind = self.read_byte()?;
+					let init = self.parse_element_function_indices()?;
+					ElementSegment { mode, element_type: Type::FuncRef, init }
+				},
+				2 => {
+					let table = leb128::read::unsigned(&mut self.bytecode)? as usize;
+					let (offset, offset_expr) = self.parse_offset_expression()?;
+					let _element_kind = self.read_byte()?;
+					let init = self.parse_element_function_indices()?;
+					let mode = ElementMode::Active { table, offset, offset_expr };
+					ElementSegment { mode, element_type: Type::FuncRef, init }
+				},
+				4 => {
+					let (offset, offset_expr) = self.parse_offset_expression()?;
+					let init = self.parse_element_expressions()?;
+					let mode = ElementMode::Active { table: 0, offset, offset_expr };
+					ElementSegment { mode, element_type: Type::FuncRef, init }
+				},
+				5 | 7 => {
+					let mode = if flags == 5 { ElementMode::Passive } else { ElementMode::Declarative };
+					let element_type = Type::try_from(self.read_byte()?)?;
+					let init = self.parse_element_expressions()?;
+					ElementSegment { mode, element_type, init }
+				},
+				6 => {
+					let table = leb128::read::unsigned(&mut self.bytecode)? as usize;
+					let (offset, offset_expr) = self.parse_offset_expression()?;
+					let element_type = Type::try_from(self.read_byte()?)?;
+					let init = self.parse_element_expressions()?;
+					ElementSegment { mode: ElementMode::Active { table, offset, offset_expr }, element_type, init }
+				},
+				flags => return Err(ParsingError::UnknownElementSegmentKind { offset, flags }),
+			};
+			tracing::debug!(?element_segment);
+			self.module.elements.push(element_segment);
+		}
+		Ok(())
+	}
+
 	fn parse_memory_section(&mut self) -> Result<(), ParsingError> {
 		let num_mems = leb128::read::unsigned(&mut self.bytecode)? as usize;
-		tracing::trace!("Parsing memory section with {} memories", num_mems);
+		tracing::trace!(count = num_mems, "Parsing the memory section");
 		// TODO: Error instead of panic / assert
 		assert!(num_mems <= 1);
 		for _ in 0..num_mems {
-			let memory_limit_kind = LimitKind::try_from(self.read_byte()?)?;
-			let page_limit = match memory_limit_kind {
-				LimitKind::Min => {
-					let min = leb128::read::unsigned(&mut self.bytecode)? as usize;
-					min..(u32::MAX as usize)
-				},
-				LimitKind::MinMax => {
-					let min = leb128::read::unsigned(&mut self.bytecode)? as usize;
-					let max = leb128::read::unsigned(&mut self.bytecode)? as usize;
-					min..max
-				}
-			};
-			let memory_blueprint = MemoryBlueprint { page_limit, export_name: None, init: Vec::new() };
-			tracing::trace!("{:?}", memory_blueprint);
+			let memory_blueprint = self.parse_memory_type(None)?;
+			tracing::trace!(?memory_blueprint);
 			self.module.memory_blueprint = Some(memory_blueprint);
 		}
 		Ok(())
 	}
 
-	#[tracing::instrument(skip_all)]
+	/// Parses the limits of a memory, which is imported from `import` if given.
+	fn parse_memory_type(&mut self, import: Option<Identifier>) -> Result<MemoryBlueprint, ParsingError> {
+		let limit_kind = self.read_limit_kind()?;
+		let shared = limit_kind == LimitKind::SharedMinMax;
+		let page_limit = self.parse_limits_of_kind(limit_kind)?;
+		Ok(MemoryBlueprint { page_limit, shared, export_name: None, import, init: Vec::new() })
+	}
+
 	fn parse_data_section(&mut self) -> Result<(), ParsingError> {
 		let num_segments = leb128::read::unsigned(&mut self.bytecode)? as usize;
-		tracing::trace!("Parsing data section with {} segments", num_segments);
+		tracing::trace!(count = num_segments, "Parsing the data section");
+		if let Some(declared) = self.module.data_count.filter(|declared| *declared != num_segments) {
+			return Err(ParsingError::DataCountMismatch { declared, actual: num_segments });
+		}
 
 		for _ in 0..num_segments {
+			let offset = self.bytecode.position;
 			let data_mode = DataMode::try_from(self.read_byte()?)?;
-			match data_mode {
-				DataMode::ActiveMemory0 => {
-					let segment_addr_expressions = self.parse_instructions()?;
-					let segment_addr = match segment_addr_expressions[0] {
-						Instruction::I32Const(val) => val as usize,
-						_ => unimplemented!("Unsupported data segment address expression"),
-					};
+			let memory_index = match data_mode {
+				DataMode::ActiveMemory0 => Some(0),
+				DataMode::Passive => None,
+				DataMode::Active => Some(leb128::read::unsigned(&mut self.bytecode)?),
+			};
+			let Some(memory_index) = memory_index else {
+				let segment_size = leb128::read::unsigned(&mut self.bytecode)? as usize;
+				let data = self.read_shared_bytes(segment_size)?;
+				tracing::debug!(len = data.len(), "Passive data segment");
+				self.module.passive_data.push(Some(data));
+				continue;
+			};
+			if memory_index != 0 {
+				return Err(ParsingError::UnsupportedMemoryIndex { offset, index: memory_index });
+			}
+			let expression = ConstExpr::new(self.parse_instructions()?);
+			let (addr, expression) = eval_offset(&self.module, &self.global_values, expression)?;
 
-					let segment_size = leb128::read::unsigned(&mut self.bytecode)? as usize;
+			let segment_size = leb128::read::unsigned(&mut self.bytecode)? as usize;
+			let data_segment = DataSegment {
+				addr,
+				offset: expression,
+				data: self.read_shared_bytes(segment_size)?,
+			};
+			tracing::debug!(?data_segment);
+			self.module.memory_blueprint.as_mut()
+				.ok_or(ParsingError::DataSegmentWithoutMemory { offset })?
+				.init.push(data_segment);
+			self.module.passive_data.push(None);
+		}
+		Ok(())
+	}
 
-					let mut segment_data = vec![0u8; segment_size];
-					self.bytecode.read_exact(&mut segment_data)?;
+	fn parse_global_section(&mut self) -> Result<(), ParsingError> {
+		let num_globals = leb128::read::unsigned(&mut self.bytecode)? as usize;
+		tracing::trace!(count = num_globals, "Parsing the global section");
 
-					let data_segment = DataSegment {
-						addr: segment_addr,
-						data: segment_data,
-					};
-					tracing::debug!("{:?}", data_segment);
-					self.module.memory_blueprint.as_mut().unwrap().init.push(data_segment);
-				},
-				DataMode::Passive => unimplemented!(),
-				DataMode::Active => unimplemented!(),
-			}
+		for _ in 0..num_globals {
+			let ty = Type::try_from(self.read_byte()?)?;
+			let mutable = Type::try_from(self.read_byte()?)? == Type::Var;
+			let init = ConstExpr::new(self.parse_instructions()?);
+			// Globals may only refer to the preceding ones
+			let value = eval_known(&self.module, &self.global_values, &init)
+				.ok_or_else(|| ParsingError::UnsupportedConstExpr(init.instructions.clone()))?;
+			self.global_values.push(value);
+			let global = GlobalBlueprint { ty, mutable, init, export_name: None, import: None };
+			tracing::trace!(?global);
+			self.module.globals.push(global);
+		}
+		Ok(())
+	}
+
+	fn parse_data_count_section(&mut self) -> Result<(), ParsingError> {
+		let data_count = leb128::read::unsigned(&mut self.bytecode)? as usize;
+		tracing::trace!(count = data_count, "Data count section");
+		self.module.data_count = Some(data_count);
+		Ok(())
+	}
+
+	fn parse_start_section(&mut self) -> Result<(), ParsingError> {
+		let function_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
+		tracing::trace!(function_index, "Start function");
+		self.module.start = Some(function_index);
+		Ok(())
+	}
+
+	/// Parses the tag section of the exception handling proposal, where each tag refers to a function signature
+	/// without results.
+	fn parse_tag_section(&mut self) -> Result<(), ParsingError> {
+		let num_tags = leb128::read::unsigned(&mut self.bytecode)? as usize;
+		tracing::trace!(count = num_tags, "Parsing the tag section");
+		for _ in 0..num_tags {
+			let _attribute = self.read_byte()?;
+			let type_index = leb128::read::unsigned(&mut self.bytecode)? as usize;
+			self.module.tags.push(Shared::clone(&self.types[type_index]));
 		}
 		Ok(())
 	}
 
-	#[tracing::instrument(skip_all)]
 	fn parse_custom_section(&mut self, section_size: u64) -> Result<(), ParsingError> {
-		let mut sink = vec![0u8; section_size as usize];
-		self.bytecode.read_exact(&mut sink)?;
-		tracing::trace!("Skipping custom section with {} bytes", section_size);
+		let start = self.bytecode.position;
+		let name = self.read_string()?;
+		let data_size = (section_size as usize).checked_sub(self.bytecode.position - start)
+			.ok_or(ParsingError::CustomSectionTooSmall { size: section_size })?;
+		let data = self.read_shared_bytes(data_size)?;
+		tracing::trace!(%name, size = data_size, "Custom section");
+		if let Some(parser) = self.custom_section_parsers.get_mut(&name) {
+			parser(&data).map_err(|source| ParsingError::CustomSection { name: name.clone(), source })?;
+		}
+		self.module.custom_sections.push(CustomSection { name, data });
 		Ok(())
 	}
 
+	#[tracing::instrument(name = "parse", skip_all)]
 	fn parse_module_internal(mut self) -> Result<Module, ParsingError> {
 		let mut magic = [0u8; 4];
 		self.bytecode.read_exact(&mut magic)?;
@@ -498,6 +1234,9 @@ impl<ByteIter: io::Read> Parser<ByteIter> {
 
 		let mut version = [0u8; 4];
 		self.bytecode.read_exact(&mut version)?;
+		if version == component::VERSION_AND_LAYER {
+			return Err(ParsingError::UnexpectedComponent);
+		}
 		if version != [0x01, 0x00, 0x00, 0x00] {
 			return Err(ParsingError::IllegalVersion(version));
 		}
@@ -505,22 +1244,74 @@ impl<ByteIter: io::Read> Parser<ByteIter> {
 		while let Ok(section_id) = self.read_byte() {
 			let section_id = SectionId::try_from(section_id)?;
 			let section_size = leb128::read::unsigned(&mut self.bytecode)?;
-			tracing::trace!("Section `{:?}` with size {:?} bytes", section_id, section_size);
+			let offset = self.bytecode.position;
+			let _span = tracing::debug_span!("section", id = ?section_id, offset, size = section_size).entered();
+			self.module.sections.push(SectionHeader { id: section_id, offset, size: section_size });
 			match section_id {
-				SectionId::Type => self.types = self.parse_type_section()?,
+				SectionId::Type => {
+					self.types = self.parse_type_section()?;
+					self.module.types = self.types.clone();
+				},
 				SectionId::Function => self.parse_function_section()?,
 				SectionId::Export => self.parse_export_section()?,
 				SectionId::Code => self.parse_code_section()?,
 				SectionId::Import => self.parse_import_section()?,
 				SectionId::Memory => self.parse_memory_section()?,
+				SectionId::Table => self.parse_table_section()?,
+				SectionId::Element => self.parse_element_section()?,
 				SectionId::Data => self.parse_data_section()?,
+				SectionId::DataCount => self.parse_data_count_section()?,
 				SectionId::Custom => self.parse_custom_section(section_size)?,
-				other => {
-					tracing::error!("Unknown section {:?}. Ending parsing with Ok", other);
-					break
-				},
+				SectionId::Start => self.parse_start_section()?,
+				SectionId::Tag => self.parse_tag_section()?,
+				SectionId::Global => self.parse_global_section()?,
 			}
 		}
+		// A data count section without a data section declares zero segments
+		let has_data_section = self.module.sections.iter().any(|section| section.id == SectionId::Data);
+		if let Some(declared) = self.module.data_count.filter(|declared| *declared != 0 && !has_data_section) {
+			return Err(ParsingError::DataCountMismatch { declared, actual: 0 });
+		}
+		let disabled = self.module.features().difference(self.features);
+		if !disabled.is_empty() {
+			return Err(ParsingError::FeatureNotEnabled(disabled));
+		}
+		self.module.update_function_metadata();
 		Ok(self.module)
 	}
+}
+
+/// Evaluates `expression` with `global_values`, the initial values of the globals of `module` parsed so far.
+///
+/// Returns `None` if it does not evaluate and `Some(None)` if it depends on a global only known at instantiation,
+/// in which case it is evaluated with the default value of that global to check that it evaluates at all.
+pub(super) fn eval_known(
+	module: &Module,
+	global_values: &[Option<Value>],
+	expression: &ConstExpr,
+) -> Option<Option<Value>> {
+	let globals: Vec<Value> = module.globals.iter().zip(global_values)
+		.map(|(global, value)| value.clone().unwrap_or_else(|| Value::default_for(&global.ty)))
+		.collect();
+	let value = expression.eval(&globals)?;
+	let known = expression.instructions.iter().all(|instruction| match instruction {
+		Instruction::GlobalGet(index) => matches!(global_values.get(*index), Some(Some(_))),
+		_ => true,
+	});
+	Some(known.then_some(value))
+}
+
+/// Evaluates the offset `expression` of a segment into the `i32` it evaluates to, see [`eval_known`]. If it reads
+/// imported globals, it is returned instead, to be evaluated when instantiating the module.
+pub(super) fn eval_offset(
+	module: &Module,
+	global_values: &[Option<Value>],
+	expression: ConstExpr,
+) -> Result<(usize, Option<ConstExpr>), ParsingError> {
+	let defaults: Vec<Value> = module.globals.iter().map(|global| Value::default_for(&global.ty)).collect();
+	match eval_known(module, global_values, &expression) {
+		Some(Some(Value::I32(offset))) => Ok((offset as u32 as usize, None)),
+		Some(None) if matches!(expression.eval(&defaults), Some(Value::I32(_))) => Ok((0, Some(expression))),
+		_ => Err(ParsingError::UnsupportedConstExpr(expression.instructions)),
+	}
 }
\ No newline at end of file
@@ -1,11 +1,13 @@
-use std::io;
-use std::ops::Range;
+use std::fmt;
+use std::collections::HashMap;
+use std::ops::{Deref, Range};
+use crate::shared::Shared;
 use num_enum::TryFromPrimitive;
-use crate::exec::Functions;
-use crate::parse::{Parser, ParsingError};
+use crate::exec::{ConstExpr, Engine, ExternFunction, FuncKind, FunctionMetadata, FunctionSignature, Functions, Identifier, Instruction, Value, WasmFunction};
+use crate::parse::{Digest, DylinkInfo, DYLINK_SECTION, Features, Parser, ParsingError};
 
 /// <https://webassembly.github.io/spec/core/binary/modules.html#sections>
-#[derive(Eq, PartialEq, Debug, TryFromPrimitive)]
+#[derive(Eq, PartialEq, Debug, TryFromPrimitive, Clone, Copy)]
 #[repr(u8)]
 pub enum SectionId {
 	Custom = 0,
@@ -21,6 +23,7 @@ pub enum SectionId {
 	Code = 10,
 	Data = 11,
 	DataCount = 12,
+	Tag = 13,
 }
 
 /// <https://webassembly.github.io/spec/core/binary/instructions.html>
@@ -32,6 +35,11 @@ pub enum Opcode {
 	Block                = 0x02,
 	Loop                 = 0x03,
 	If                   = 0x04,
+	Else                 = 0x05,
+	Try                  = 0x06,
+	Catch                = 0x07,
+	Throw                = 0x08,
+	Rethrow              = 0x09,
 	End                  = 0x0B,
 	Br                   = 0x0C,
 	BrIf                 = 0x0D,
@@ -39,6 +47,10 @@ pub enum Opcode {
 	Return               = 0x0F,
 	Call                 = 0x10,
 	CallIndirect         = 0x11,
+	ReturnCall           = 0x12,
+	ReturnCallIndirect   = 0x13,
+	Delegate             = 0x18,
+	CatchAll             = 0x19,
 	RefNull              = 0xD0,
 	RefIsNull            = 0xD1,
 	RefFunc              = 0xD2,
@@ -53,6 +65,8 @@ pub enum Opcode {
 	TableGet             = 0x25,
 	TableSet             = 0x26,
 	Extension            = 0xFC,
+	Simd                 = 0xFD,
+	Atomic               = 0xFE,
 	I32Load              = 0x28,
 	I64Load              = 0x29,
 	F32Load              = 0x2A,
@@ -64,7 +78,7 @@ pub enum Opcode {
 	I64Load8s            = 0x30,
 	I64Load8u            = 0x31,
 	I64Load16s           = 0x32,
-	I66Load16u           = 0x33,
+	I64Load16u           = 0x33,
 	I64Load32s           = 0x34,
 	I64Load32u           = 0x35,
 	I32Store             = 0x36,
@@ -212,8 +226,167 @@ pub enum Opcode {
 	I64Extend32S         = 0xC4,
 }
 
+/// Sub-opcodes following the [`Opcode::Extension`] prefix 0xFC.
+///
+/// <https://webassembly.github.io/spec/core/binary/instructions.html#numeric-instructions>
+#[derive(Eq, PartialEq, Debug, TryFromPrimitive, Clone, Copy)]
+#[repr(u32)]
+pub enum ExtensionOpcode {
+	I32TruncSatF32S      = 0,
+	I32TruncSatF32U      = 1,
+	I32TruncSatF64S      = 2,
+	I32TruncSatF64U      = 3,
+	I64TruncSatF32S      = 4,
+	I64TruncSatF32U      = 5,
+	I64TruncSatF64S      = 6,
+	I64TruncSatF64U      = 7,
+	MemoryInit           = 8,
+	DataDrop             = 9,
+	MemoryCopy           = 10,
+	MemoryFill           = 11,
+	TableInit            = 12,
+	ElemDrop             = 13,
+	TableCopy            = 14,
+	TableGrow            = 15,
+	TableSize            = 16,
+	TableFill            = 17,
+}
+
+/// Sub-opcodes following the [`Opcode::Simd`] prefix 0xFD.
+///
+/// <https://webassembly.github.io/spec/core/binary/instructions.html#vector-instructions>
+#[derive(Eq, PartialEq, Debug, TryFromPrimitive, Clone, Copy)]
+#[repr(u32)]
+pub enum SimdOpcode {
+	V128Load             = 0,
+	V128Store            = 11,
+	V128Const            = 12,
+	I8x16Shuffle         = 13,
+	I8x16Swizzle         = 14,
+	I8x16Splat           = 15,
+	I16x8Splat           = 16,
+	I32x4Splat           = 17,
+	I64x2Splat           = 18,
+	F32x4Splat           = 19,
+	F64x2Splat           = 20,
+	I8x16ExtractLaneS    = 21,
+	I8x16ExtractLaneU    = 22,
+	I8x16ReplaceLane     = 23,
+	I16x8ExtractLaneS    = 24,
+	I16x8ExtractLaneU    = 25,
+	I16x8ReplaceLane     = 26,
+	I32x4ExtractLane     = 27,
+	I32x4ReplaceLane     = 28,
+	I64x2ExtractLane     = 29,
+	I64x2ReplaceLane     = 30,
+	F32x4ExtractLane     = 31,
+	F32x4ReplaceLane     = 32,
+	F64x2ExtractLane     = 33,
+	F64x2ReplaceLane     = 34,
+	I8x16Eq              = 35,
+	I16x8Eq              = 45,
+	I32x4Eq              = 55,
+	V128Not              = 77,
+	V128And              = 78,
+	V128AndNot           = 79,
+	V128Or               = 80,
+	V128Xor              = 81,
+	V128Bitselect        = 82,
+	V128AnyTrue          = 83,
+	I8x16AllTrue         = 99,
+	I8x16Bitmask         = 100,
+	I8x16Add             = 110,
+	I8x16Sub             = 113,
+	I16x8Add             = 142,
+	I16x8Sub             = 145,
+	I16x8Mul             = 149,
+	I32x4Add             = 174,
+	I32x4Sub             = 177,
+	I32x4Mul             = 181,
+	I64x2Add             = 206,
+	I64x2Sub             = 209,
+	I64x2Mul             = 213,
+}
+
+/// Sub-opcodes following the [`Opcode::Atomic`] prefix 0xFE of the threads proposal.
+///
+/// Narrow accesses are named after their width, e.g. `I32AtomicLoadU8` is `i32.atomic.load8_u`.
+///
+/// <https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md>
+#[derive(Eq, PartialEq, Debug, TryFromPrimitive, Clone, Copy)]
+#[repr(u32)]
+pub enum AtomicOpcode {
+	MemoryAtomicNotify     = 0x00,
+	MemoryAtomicWait32     = 0x01,
+	MemoryAtomicWait64     = 0x02,
+	AtomicFence            = 0x03,
+	I32AtomicLoad          = 0x10,
+	I64AtomicLoad          = 0x11,
+	I32AtomicLoadU8        = 0x12,
+	I32AtomicLoadU16       = 0x13,
+	I64AtomicLoadU8        = 0x14,
+	I64AtomicLoadU16       = 0x15,
+	I64AtomicLoadU32       = 0x16,
+	I32AtomicStore         = 0x17,
+	I64AtomicStore         = 0x18,
+	I32AtomicStoreU8       = 0x19,
+	I32AtomicStoreU16      = 0x1A,
+	I64AtomicStoreU8       = 0x1B,
+	I64AtomicStoreU16      = 0x1C,
+	I64AtomicStoreU32      = 0x1D,
+	I32AtomicRmwAdd        = 0x1E,
+	I64AtomicRmwAdd        = 0x1F,
+	I32AtomicRmwAddU8      = 0x20,
+	I32AtomicRmwAddU16     = 0x21,
+	I64AtomicRmwAddU8      = 0x22,
+	I64AtomicRmwAddU16     = 0x23,
+	I64AtomicRmwAddU32     = 0x24,
+	I32AtomicRmwSub        = 0x25,
+	I64AtomicRmwSub        = 0x26,
+	I32AtomicRmwSubU8      = 0x27,
+	I32AtomicRmwSubU16     = 0x28,
+	I64AtomicRmwSubU8      = 0x29,
+	I64AtomicRmwSubU16     = 0x2A,
+	I64AtomicRmwSubU32     = 0x2B,
+	I32AtomicRmwAnd        = 0x2C,
+	I64AtomicRmwAnd        = 0x2D,
+	I32AtomicRmwAndU8      = 0x2E,
+	I32AtomicRmwAndU16     = 0x2F,
+	I64AtomicRmwAndU8      = 0x30,
+	I64AtomicRmwAndU16     = 0x31,
+	I64AtomicRmwAndU32     = 0x32,
+	I32AtomicRmwOr         = 0x33,
+	I64AtomicRmwOr         = 0x34,
+	I32AtomicRmwOrU8       = 0x35,
+	I32AtomicRmwOrU16      = 0x36,
+	I64AtomicRmwOrU8       = 0x37,
+	I64AtomicRmwOrU16      = 0x38,
+	I64AtomicRmwOrU32      = 0x39,
+	I32AtomicRmwXor        = 0x3A,
+	I64AtomicRmwXor        = 0x3B,
+	I32AtomicRmwXorU8      = 0x3C,
+	I32AtomicRmwXorU16     = 0x3D,
+	I64AtomicRmwXorU8      = 0x3E,
+	I64AtomicRmwXorU16     = 0x3F,
+	I64AtomicRmwXorU32     = 0x40,
+	I32AtomicRmwXchg       = 0x41,
+	I64AtomicRmwXchg       = 0x42,
+	I32AtomicRmwXchgU8     = 0x43,
+	I32AtomicRmwXchgU16    = 0x44,
+	I64AtomicRmwXchgU8     = 0x45,
+	I64AtomicRmwXchgU16    = 0x46,
+	I64AtomicRmwXchgU32    = 0x47,
+	I32AtomicRmwCmpxchg    = 0x48,
+	I64AtomicRmwCmpxchg    = 0x49,
+	I32AtomicRmwCmpxchgU8  = 0x4A,
+	I32AtomicRmwCmpxchgU16 = 0x4B,
+	I64AtomicRmwCmpxchgU8  = 0x4C,
+	I64AtomicRmwCmpxchgU16 = 0x4D,
+	I64AtomicRmwCmpxchgU32 = 0x4E,
+}
+
 /// <https://webassembly.github.io/spec/core/binary/types.html>
-#[derive(Eq, PartialEq, Debug, TryFromPrimitive, Clone)]
+#[derive(Eq, PartialEq, Hash, Debug, TryFromPrimitive, Clone, Copy)]
 #[repr(u8)]
 pub enum Type {
 	I32 = 0x7F,
@@ -234,6 +407,8 @@ pub enum Type {
 pub enum LimitKind {
 	Min = 0x00,
 	MinMax = 0x01,
+	/// Minimum and maximum of a shared memory.
+	SharedMinMax = 0x03,
 }
 
 /// <https://webassembly.github.io/spec/core/binary/modules.html#export-section>
@@ -255,30 +430,575 @@ pub enum DataMode {
 	Active = 0x02,
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Debug, Default, PartialEq)]
 pub struct MemoryBlueprint {
 	/// Minimum and maximum page limit.
 	pub page_limit: Range<usize>,
+	/// Whether the memory is shared between threads.
+	pub shared: bool,
 	pub export_name: Option<String>,
+	/// Where the memory is imported from, or `None` if the module defines it.
+	pub import: Option<Identifier>,
 	pub init: Vec<DataSegment>,
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
+/// A section as encountered in the binary, used for inspecting a [Module].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SectionHeader {
+	pub id: SectionId,
+	/// Position of the section content in the binary.
+	pub offset: usize,
+	/// Size of the section content in bytes.
+	pub size: u64,
+}
+
+/// An exported item of a [Module].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Export {
+	pub name: String,
+	pub kind: ExportKind,
+	pub index: usize,
+}
+
+/// The indices of the exports of a [Module] by name, one map per [ExportKind], for lookups in constant time.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExportMap {
+	pub functions: HashMap<String, usize>,
+	pub tables: HashMap<String, usize>,
+	pub memories: HashMap<String, usize>,
+	pub globals: HashMap<String, usize>,
+}
+
+impl ExportMap {
+	/// Adds `export`, replacing an export of the same kind and name.
+	pub fn insert(&mut self, export: Export) {
+		self.kind_mut(&export.kind).insert(export.name, export.index);
+	}
+
+	/// Removes the export of `kind` named `name` and returns its index.
+	pub fn remove(&mut self, kind: &ExportKind, name: &str) -> Option<usize> {
+		self.kind_mut(kind).remove(name)
+	}
+
+	/// The export named `name` of any kind.
+	pub fn get(&self, name: &str) -> Option<Export> {
+		[
+			(ExportKind::Function, &self.functions),
+			(ExportKind::Table, &self.tables),
+			(ExportKind::Memory, &self.memories),
+			(ExportKind::Global, &self.globals),
+		].into_iter()
+			.find_map(|(kind, map)| map.get(name).map(|&index| Export { name: name.to_owned(), kind, index }))
+	}
+
+	/// Index of the function exported as `name`.
+	pub fn function(&self, name: &str) -> Option<usize> {
+		self.functions.get(name).copied()
+	}
+
+	fn kind_mut(&mut self, kind: &ExportKind) -> &mut HashMap<String, usize> {
+		match kind {
+			ExportKind::Function => &mut self.functions,
+			ExportKind::Table => &mut self.tables,
+			ExportKind::Memory => &mut self.memories,
+			ExportKind::Global => &mut self.globals,
+		}
+	}
+}
+
+/// A function of a [Module], which is either imported or defined by the module itself.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ModuleFunction<'m> {
+	Imported(&'m ExternFunction),
+	Defined(&'m WasmFunction),
+}
+
+impl<'m> From<&'m FuncKind> for ModuleFunction<'m> {
+	fn from(function: &'m FuncKind) -> Self {
+		match function {
+			FuncKind::Imported(function) => ModuleFunction::Imported(function),
+			FuncKind::Local(function) => ModuleFunction::Defined(function),
+		}
+	}
+}
+
+impl<'m> ModuleFunction<'m> {
+	pub fn signature(&self) -> &'m FunctionSignature {
+		match self {
+			ModuleFunction::Imported(function) => &function.signature,
+			ModuleFunction::Defined(function) => &function.signature,
+		}
+	}
+
+	/// The name under which a defined function is exported.
+	pub fn export_name(&self) -> Option<&'m str> {
+		match self {
+			ModuleFunction::Imported(_) => None,
+			ModuleFunction::Defined(function) => function.export_name.as_deref(),
+		}
+	}
+}
+
+/// <https://webassembly.github.io/spec/core/binary/modules.html#global-section>
+#[derive(Debug, PartialEq, Clone)]
+pub struct GlobalBlueprint {
+	pub ty: Type,
+	pub mutable: bool,
+	/// Computes the initial value when instantiating the module, empty for imported globals.
+	pub init: ConstExpr,
+	pub export_name: Option<String>,
+	/// Where the global is imported from, or `None` if the module defines it.
+	pub import: Option<Identifier>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct TableBlueprint {
+	/// Either [`Type::FuncRef`] or [`Type::ExternRef`].
+	pub element_type: Type,
+	/// Minimum and maximum number of elements.
+	pub limit: Range<usize>,
+	pub export_name: Option<String>,
+	/// Where the table is imported from, or `None` if the module defines it.
+	pub import: Option<Identifier>,
+}
+
+/// <https://webassembly.github.io/spec/core/binary/modules.html#element-section>
+#[derive(Debug, PartialEq, Clone)]
+pub enum ElementMode {
+	/// Copied into the table with index `table` at `offset` during instantiation.
+	Active {
+		table: usize,
+		offset: usize,
+		/// The offset expression if it reads imported globals, e.g. the `__table_base` of position independent code,
+		/// which is evaluated when instantiating the module instead of using `offset`.
+		offset_expr: Option<ConstExpr>,
+	},
+	/// Only copied into a table by `table.init`.
+	Passive,
+	/// Only declares the functions referenced by `ref.func` and is not available at runtime.
+	Declarative,
+}
+
+impl ElementMode {
+	/// The offset an active segment is copied to with `globals` as the values of the globals of the instance, or
+	/// `None` if the segment is not active or its offset expression does not evaluate to an `i32` with them.
+	pub fn resolve_offset(&self, globals: &[Value]) -> Option<usize> {
+		match self {
+			ElementMode::Active { offset_expr: Some(offset_expr), .. } => match offset_expr.eval(globals)? {
+				Value::I32(offset) => Some(offset as u32 as usize),
+				_ => None,
+			},
+			ElementMode::Active { offset, .. } => Some(*offset),
+			ElementMode::Passive | ElementMode::Declarative => None,
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ElementSegment {
+	pub mode: ElementMode,
+	/// Either [`Type::FuncRef`] or [`Type::ExternRef`].
+	pub element_type: Type,
+	/// Function indices of the elements, or [`None`] for `ref.null`.
+	pub init: Vec<Option<usize>>,
+}
+
+#[derive(Debug, Default, PartialEq)]
 pub struct DataSegment {
+	/// Address the segment is copied to, unless it has an `offset` expression.
 	pub addr: usize,
-	pub data: Vec<u8>,
+	/// The offset expression if it reads imported globals, e.g. the `__memory_base` of position independent code,
+	/// which is evaluated when instantiating the module instead of using `addr`.
+	pub offset: Option<ConstExpr>,
+	pub data: SharedBytes,
+}
+
+impl DataSegment {
+	/// The address the segment is copied to with `globals` as the values of the globals of the instance, or `None`
+	/// if the offset expression does not evaluate to an `i32` with them.
+	pub fn resolve_addr(&self, globals: &[Value]) -> Option<usize> {
+		match &self.offset {
+			Some(offset) => match offset.eval(globals)? {
+				Value::I32(addr) => Some(addr as u32 as usize),
+				_ => None,
+			},
+			None => Some(self.addr),
+		}
+	}
+}
+
+/// Bytes which are a range of a shared buffer, e.g. the binary of a module parsed with [`Module::from_bytes`].
+#[derive(Default, Clone, PartialEq, Eq)]
+pub struct SharedBytes {
+	buffer: Shared<[u8]>,
+	range: Range<usize>,
+}
+
+impl SharedBytes {
+	/// The bytes in `range` of `buffer`.
+	///
+	/// # Panics
+	/// If `range` is out of bounds of `buffer`.
+	pub fn new(buffer: Shared<[u8]>, range: Range<usize>) -> Self {
+		assert!(range.start <= range.end && range.end <= buffer.len(), "Range out of bounds of buffer");
+		Self { buffer, range }
+	}
+
+	/// Position of the bytes in the buffer, i.e. in the binary for data segments of [`Module::from_bytes`].
+	pub fn range(&self) -> Range<usize> {
+		self.range.clone()
+	}
+}
+
+impl From<Vec<u8>> for SharedBytes {
+	fn from(bytes: Vec<u8>) -> Self {
+		let range = 0..bytes.len();
+		Self { buffer: bytes.into(), range }
+	}
+}
+
+impl Deref for SharedBytes {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		&self.buffer[self.range.clone()]
+	}
+}
+
+impl fmt::Debug for SharedBytes {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		// Do not print the buffer, which may be the whole binary
+		write!(f, "SharedBytes({} bytes at {:?})", self.range.len(), self.range)
+	}
+}
+
+/// A custom section with its name and content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomSection {
+	pub name: String,
+	pub data: SharedBytes,
+}
+
+/// Parsers for custom sections with specific names, which are called while parsing a module.
+///
+/// Pass them to [`Parser::parse_module_with`] or [`Parser::parse_bytes_with`]. The parsers may borrow state of the
+/// embedder, e.g. to collect the `producers` section or DWARF debug information.
+#[derive(Default)]
+pub struct CustomSectionParsers<'p> {
+	parsers: HashMap<String, Box<CustomSectionParser<'p>>>,
+}
+
+/// A parser of the content of a custom section.
+pub type CustomSectionParser<'p> = dyn FnMut(&[u8]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> + 'p;
+
+impl fmt::Debug for CustomSectionParsers<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_set().entries(self.parsers.keys()).finish()
+	}
+}
+
+impl<'p> CustomSectionParsers<'p> {
+	/// Calls `parser` with the content of every custom section named `name`.
+	pub fn register(
+		mut self,
+		name: impl Into<String>,
+		parser: impl FnMut(&[u8]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> + 'p,
+	) -> Self {
+		self.parsers.insert(name.into(), Box::new(parser));
+		self
+	}
+
+	/// The parser registered for the custom section `name`.
+	pub(crate) fn get_mut(&mut self, name: &str) -> Option<&mut Box<CustomSectionParser<'p>>> {
+		self.parsers.get_mut(name)
+	}
 }
 
 /// A parsed WebAssembly module.
 #[derive(Default, Debug)]
 pub struct Module {
 	pub functions: Functions,
+	/// The imported or defined memory.
 	pub memory_blueprint: Option<MemoryBlueprint>,
+	/// The imported tables followed by the ones of the table section, like table indices count them.
+	pub table_blueprints: Vec<TableBlueprint>,
+	/// The element section, which initializes tables.
+	pub elements: Vec<ElementSegment>,
+	/// The type section, i.e. all function signatures declared by the module.
+	pub types: Vec<Shared<FunctionSignature>>,
+	/// Index of the function from the start section.
+	pub start: Option<usize>,
+	/// Number of data segments declared by the data count section, which `memory.init` and `data.drop` require.
+	pub data_count: Option<usize>,
+	/// The bytes of the passive data segments by data index, which `memory.init` copies from, or [`None`] for the
+	/// active ones in the `init` of the memory blueprint, which behave as if they were dropped after instantiation.
+	pub passive_data: Vec<Option<SharedBytes>>,
+	/// The imported globals followed by the ones of the global section, like global indices count them.
+	pub globals: Vec<GlobalBlueprint>,
+	/// The tag section, i.e. the signatures of the exceptions thrown and caught by the module.
+	pub tags: Vec<Shared<FunctionSignature>>,
+	/// All sections in the order they appeared in the binary.
+	pub sections: Vec<SectionHeader>,
+	/// The custom sections in the order they appeared in the binary.
+	pub custom_sections: Vec<CustomSection>,
+	/// The indices of the exports by name.
+	pub export_map: ExportMap,
+	/// The SHA-256 hash of the binary if it was parsed from memory.
+	pub digest: Option<Digest>,
 }
 
 impl Module {
-	/// Parses `bytecode` into a [Module] or a [ParsingError].
-	pub fn new(bytecode: impl io::Read) -> Result<Module, ParsingError> {
-		Parser::parse_module(bytecode)
+	/// Parses the binary `bytes` with the features of `engine`, or takes the module from its cache.
+	///
+	/// The signatures of the module are shared with the other modules created with `engine`. Modules are also
+	/// parsed without an engine with [`Module::from_bytes`] or from a reader with [`Parser::parse_module`].
+	pub fn new(engine: &Engine, bytes: impl Into<Shared<[u8]>>) -> Result<Shared<Module>, ParsingError> {
+		engine.load(bytes.into())
+	}
+
+	/// Parses the binary `bytes` into a [Module] or a [ParsingError].
+	///
+	/// Unlike [`Parser::parse_module`], data segments are not copied but reference `bytes`, which is kept alive by
+	/// the module.
+	/// Pass an existing [`Shared`] buffer to avoid copying `bytes` at all.
+	pub fn from_bytes(bytes: impl Into<Shared<[u8]>>) -> Result<Module, ParsingError> {
+		Parser::parse_bytes(bytes.into())
+	}
+
+	/// Like [`Module::from_bytes`], but fails with [`ParsingError::FeatureNotEnabled`] if the module uses a proposal
+	/// not in `features`.
+	pub fn from_bytes_with_features(bytes: impl Into<Shared<[u8]>>, features: Features) -> Result<Module, ParsingError> {
+		Parser::parse_bytes_with_features(bytes.into(), features)
+	}
+
+	/// The SHA-256 hash of the binary this module was parsed from, or `None` if it was parsed from a reader with
+	/// [`Parser::parse_module`] or built with a [`ModuleBuilder`](crate::parse::ModuleBuilder).
+	pub fn digest(&self) -> Option<Digest> {
+		self.digest
+	}
+
+	/// Encodes this module as a binary, see [`encode`](crate::encode::encode).
+	pub fn encode(&self) -> Vec<u8> {
+		crate::encode::encode(self)
+	}
+
+	/// All sections in the order they appeared in the binary.
+	pub fn sections(&self) -> &[SectionHeader] {
+		&self.sections
+	}
+
+	/// The function signatures from the type section.
+	pub fn types(&self) -> &[Shared<FunctionSignature>] {
+		&self.types
+	}
+
+	/// The imported functions.
+	pub fn imports(&self) -> impl Iterator<Item = &ExternFunction> {
+		self.functions.imports()
+	}
+
+	/// Number of functions, counting imported ones first like function indices do.
+	pub fn num_functions(&self) -> usize {
+		self.functions.len()
+	}
+
+	/// The function with `index`, where imported functions come before the ones defined by the module.
+	pub fn function(&self, index: usize) -> Option<ModuleFunction<'_>> {
+		self.functions.get(index).map(ModuleFunction::from)
+	}
+
+	/// All functions in the order of their indices.
+	pub fn functions(&self) -> impl Iterator<Item = ModuleFunction<'_>> {
+		self.functions.iter().map(ModuleFunction::from)
+	}
+
+	/// All exports in the order functions, tables, memory and globals.
+	pub fn exports(&self) -> Vec<Export> {
+		let function_exports = self.functions.local()
+			.filter_map(|function| {
+				let name = function.export_name.clone()?;
+				Some(Export { name, kind: ExportKind::Function, index: function.index })
+			});
+		let table_exports = self.table_blueprints.iter().enumerate()
+			.filter_map(|(index, table)| {
+				let name = table.export_name.clone()?;
+				Some(Export { name, kind: ExportKind::Table, index })
+			});
+		let memory_export = self.memory_blueprint.iter()
+			.filter_map(|memory| {
+				let name = memory.export_name.clone()?;
+				Some(Export { name, kind: ExportKind::Memory, index: 0 })
+			});
+		let global_exports = self.globals.iter().enumerate()
+			.filter_map(|(index, global)| {
+				let name = global.export_name.clone()?;
+				Some(Export { name, kind: ExportKind::Global, index })
+			});
+		function_exports.chain(table_exports).chain(memory_export).chain(global_exports).collect()
+	}
+
+	/// The export named `name`.
+	pub fn export(&self, name: &str) -> Option<Export> {
+		self.export_map.get(name)
+	}
+
+	/// The memory imported or declared by this module, if any.
+	pub fn memory(&self) -> Option<&MemoryBlueprint> {
+		self.memory_blueprint.as_ref()
+	}
+
+	/// Minimum and maximum number of pages of the memory, if the module declares one.
+	pub fn memory_limits(&self) -> Option<Range<usize>> {
+		self.memory_blueprint.as_ref().map(|memory| memory.page_limit.clone())
+	}
+
+	/// The tables imported or declared by this module.
+	pub fn tables(&self) -> &[TableBlueprint] {
+		&self.table_blueprints
+	}
+
+	/// The element segments, which initialize tables.
+	pub fn elements(&self) -> &[ElementSegment] {
+		&self.elements
+	}
+
+	/// Index of the function from the start section.
+	pub fn start(&self) -> Option<usize> {
+		self.start
+	}
+
+	/// The globals imported or defined by this module.
+	pub fn globals(&self) -> &[GlobalBlueprint] {
+		&self.globals
+	}
+
+	/// The signatures of the tags, whose parameters are the values of the exceptions.
+	pub fn tags(&self) -> &[Shared<FunctionSignature>] {
+		&self.tags
+	}
+
+	/// The custom sections in the order they appeared in the binary.
+	pub fn custom_sections(&self) -> &[CustomSection] {
+		&self.custom_sections
+	}
+
+	/// The first custom section named `name`.
+	pub fn custom_section(&self, name: &str) -> Option<&CustomSection> {
+		self.custom_sections.iter().find(|section| section.name == name)
+	}
+
+	/// The content of the `dylink.0` custom section, or `None` if this is not a side module of the dynamic linking
+	/// conventions.
+	pub fn dylink(&self) -> Result<Option<DylinkInfo>, ParsingError> {
+		self.custom_section(DYLINK_SECTION).map(|section| DylinkInfo::parse(&section.data)).transpose()
+	}
+
+	/// Checks that the alignment hint of every memory instruction does not exceed the natural alignment of the
+	/// access, which is not required for executing the module but is by the specification.
+	pub fn validate_alignment(&self) -> Result<(), ParsingError> {
+		fn validate(instructions: &[Instruction]) -> Result<(), ParsingError> {
+			for instruction in instructions {
+				match instruction {
+					Instruction::Block { instructions, .. } | Instruction::Loop { instructions, .. } => validate(instructions)?,
+					Instruction::If { if_instructions, else_instructions, .. } => {
+						validate(if_instructions)?;
+						validate(else_instructions)?;
+					},
+					Instruction::Try { instructions, catches, .. } => {
+						validate(instructions)?;
+						for catch in catches {
+							validate(&catch.instructions)?;
+						}
+					},
+					_ => (),
+				}
+				if let Some((mem_arg, natural)) = instruction.memory_access() {
+					if 1u64.checked_shl(mem_arg.align).is_none_or(|align| align > natural as u64) {
+						return Err(ParsingError::InvalidAlignment { align: mem_arg.align, natural });
+					}
+				}
+			}
+			Ok(())
+		}
+
+		self.functions.local().try_for_each(|function| validate(&function.body))
+	}
+
+	/// Recomputes the [`FunctionMetadata`] of the defined functions, which is required after changing their bodies.
+	pub fn update_function_metadata(&mut self) {
+		let metadata: Vec<FunctionMetadata> = self.functions.local()
+			.map(|function| FunctionMetadata::new(self, function))
+			.collect();
+		for (function, metadata) in self.functions.local_mut().zip(metadata) {
+			// Functions shared with other modules are only copied if their metadata changed
+			if function.metadata != metadata {
+				Shared::make_mut(function).metadata = metadata;
+			}
+		}
+	}
+
+	/// The proposals this module uses in its types, memory, tables, globals and function bodies.
+	pub fn features(&self) -> Features {
+		fn value_type(ty: &Type) -> Features {
+			match ty {
+				Type::V128 => Features::SIMD,
+				Type::ExternRef => Features::REFERENCE_TYPES,
+				_ => Features::empty(),
+			}
+		}
+
+		fn collect(instructions: &[Instruction], features: &mut Features) {
+			for instruction in instructions {
+				features.insert(instruction.required_features());
+				match instruction {
+					Instruction::Block { instructions, .. } | Instruction::Loop { instructions, .. } => {
+						collect(instructions, features)
+					},
+					Instruction::If { if_instructions, else_instructions, .. } => {
+						collect(if_instructions, features);
+						collect(else_instructions, features);
+					},
+					Instruction::Try { instructions, catches, .. } => {
+						collect(instructions, features);
+						for catch in catches {
+							collect(&catch.instructions, features);
+						}
+					},
+					_ => (),
+				}
+			}
+		}
+
+		let mut features = Features::empty();
+		for signature in self.types.iter().chain(&self.tags) {
+			if signature.results.len() > 1 {
+				features.insert(Features::MULTI_VALUE);
+			}
+			for ty in signature.params.iter().chain(&signature.results) {
+				features.insert(value_type(ty));
+			}
+		}
+		if self.memory_blueprint.as_ref().is_some_and(|memory| memory.shared) {
+			features.insert(Features::THREADS);
+		}
+		if self.table_blueprints.len() > 1 {
+			features.insert(Features::REFERENCE_TYPES);
+		}
+		for table in &self.table_blueprints {
+			features.insert(value_type(&table.element_type));
+		}
+		for global in &self.globals {
+			features.insert(value_type(&global.ty));
+		}
+		if self.data_count.is_some() {
+			features.insert(Features::BULK_MEMORY);
+		}
+		for function in self.functions.local() {
+			for ty in &function.locals {
+				features.insert(value_type(ty));
+			}
+			collect(&function.body, &mut features);
+		}
+		features
 	}
 }
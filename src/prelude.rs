@@ -0,0 +1,6 @@
+//! The types needed to load, link and run a module, for a glob import:
+//! `use rust_wasm_runtime::prelude::*;`
+
+pub use crate::{Config, Instance, Linker, Module, Trap, Value};
+pub use crate::exec::{Error, WasmArgs, WasmResults, WasmType};
+pub use crate::parse::ParsingError;
\ No newline at end of file
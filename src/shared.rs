@@ -0,0 +1,56 @@
+//! Reference counting and bounds which switch to thread-safe variants with the `sync` feature.
+//!
+//! Without the feature, reference counting is cheaper and host closures do not need to be `Send + Sync`.
+//! With it, an [`Instance`](crate::exec::Instance) can be created on one thread and executed on another one.
+
+use std::ops::DerefMut;
+
+/// Shared ownership, i.e. [`Rc`](std::rc::Rc) or [`Arc`](std::sync::Arc) with the `sync` feature.
+#[cfg(not(feature = "sync"))]
+pub use std::rc::Rc as Shared;
+#[cfg(feature = "sync")]
+pub use std::sync::Arc as Shared;
+
+/// Shared ownership with interior mutability, i.e. [`RefCell`](std::cell::RefCell) or
+/// [`Mutex`](std::sync::Mutex) with the `sync` feature.
+#[cfg(not(feature = "sync"))]
+pub type SharedMut<T> = Shared<std::cell::RefCell<T>>;
+#[cfg(feature = "sync")]
+pub type SharedMut<T> = Shared<std::sync::Mutex<T>>;
+
+/// Wraps `value` into a [`SharedMut`].
+pub fn shared_mut<T>(value: T) -> SharedMut<T> {
+	#[cfg(not(feature = "sync"))]
+	return Shared::new(std::cell::RefCell::new(value));
+	#[cfg(feature = "sync")]
+	return Shared::new(std::sync::Mutex::new(value));
+}
+
+/// Exclusive access to the value, or `None` if it is already accessed.
+pub fn try_lock<T>(shared: &SharedMut<T>) -> Option<impl DerefMut<Target = T> + '_> {
+	#[cfg(not(feature = "sync"))]
+	return shared.try_borrow_mut().ok();
+	#[cfg(feature = "sync")]
+	return shared.try_lock().ok();
+}
+
+/// Exclusive access to the value, which waits for other threads with the `sync` feature. Without it, panics if the
+/// value is already accessed.
+pub fn lock<T>(shared: &SharedMut<T>) -> impl DerefMut<Target = T> + '_ {
+	#[cfg(not(feature = "sync"))]
+	return shared.borrow_mut();
+	#[cfg(feature = "sync")]
+	return shared.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+}
+
+/// `Send + Sync` with the `sync` feature, no bound otherwise.
+#[cfg(not(feature = "sync"))]
+pub trait MaybeSendSync {}
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> MaybeSendSync for T {}
+
+/// `Send + Sync` with the `sync` feature, no bound otherwise.
+#[cfg(feature = "sync")]
+pub trait MaybeSendSync: Send + Sync {}
+#[cfg(feature = "sync")]
+impl<T: Send + Sync + ?Sized> MaybeSendSync for T {}
\ No newline at end of file
@@ -0,0 +1,715 @@
+//! Ahead-of-time translation of a [Module] into Rust source, which embedders build into their binary to execute the
+//! guest at near-native speed without interpreting it or compiling it at runtime.
+//!
+//! The generated source defines a `Guest` struct holding the [`Memory`](crate::exec::Memory) and the globals of the
+//! module, with a method for every defined function and a public method for every exported one. Traps of the guest
+//! become panics.
+
+use std::collections::BTreeSet;
+use thiserror::Error;
+use crate::exec::{BlockType, Instruction, Value, WasmFunction};
+use crate::parse::{Module, ModuleFunction, Type};
+
+/// The reason why a [Module] cannot be translated into Rust source.
+#[derive(Error, Debug)]
+pub enum TranspileError {
+	#[error("Imported {0} are not supported, as the generated source has no linker to resolve them")]
+	Import(&'static str),
+	#[error("Function {function} contains the unsupported instruction {instruction:?}")]
+	UnsupportedInstruction { function: usize, instruction: Instruction },
+	#[error("Values of type {0:?} are not supported")]
+	UnsupportedType(Type),
+	#[error("Function {0} returns multiple values")]
+	MultipleResults(usize),
+	#[error("The initializer of global {0} is not constant")]
+	GlobalInit(usize),
+	#[error("Function {0} is not valid")]
+	InvalidFunction(usize),
+}
+
+/// Translates the functions of `module` into Rust source calling the memory API of this crate.
+///
+/// Only numeric instructions on `i32`, `i64`, `f32` and `f64`, the variable instructions, memory accesses, structured
+/// control flow and direct calls are supported. Modules with imports cannot be translated, as they would have to be
+/// resolved by the generated source.
+pub fn transpile(module: &Module) -> Result<String, TranspileError> {
+	if module.functions.num_imports() > 0 {
+		return Err(TranspileError::Import("functions"));
+	}
+	if module.memory_blueprint.as_ref().is_some_and(|memory| memory.import.is_some()) {
+		return Err(TranspileError::Import("memories"));
+	}
+	if module.globals.iter().any(|global| global.import.is_some()) {
+		return Err(TranspileError::Import("globals"));
+	}
+
+	let mut globals: Vec<Value> = Vec::with_capacity(module.globals.len());
+	for (index, global) in module.globals.iter().enumerate() {
+		rust_type(global.ty)?;
+		globals.push(global.init.eval(&globals).ok_or(TranspileError::GlobalInit(index))?);
+	}
+
+	let mut source = String::from(
+		"// Generated by `rust-wasm-runtime compile`. Traps of the guest panic.\n\
+		\n\
+		use rust_wasm_runtime::exec::Memory;\n\
+		use rust_wasm_runtime::parse::MemoryBlueprint;\n\
+		\n\
+		pub struct Guest {\n\
+		\tpub memory: Memory,\n"
+	);
+	for (index, global) in module.globals.iter().enumerate() {
+		source += &format!("\tpub g{}: {},\n", index, rust_type(global.ty)?);
+	}
+	source += "}\n\n";
+
+	source += "#[allow(dead_code, unused_mut, unused_variables, unused_assignments, unused_labels, unreachable_code, non_snake_case, clippy::all)]\n";
+	source += "impl Guest {\n";
+	let page_limit = module.memory_blueprint.as_ref().map_or(0..0, |memory| memory.page_limit.clone());
+	source += &format!(
+		"\tpub fn new() -> Self {{\n\
+		\t\tlet blueprint = MemoryBlueprint {{ page_limit: {}..{}, ..Default::default() }};\n\
+		\t\tlet mut memory = Memory::try_from(&blueprint).expect(\"The memory can be allocated\");\n",
+		page_limit.start, page_limit.end
+	);
+	for segment in module.memory_blueprint.iter().flat_map(|memory| &memory.init) {
+		let bytes: Vec<String> = segment.data.iter().map(u8::to_string).collect();
+		source += &format!(
+			"\t\tmemory.data[{}..{}].copy_from_slice(&[{}]);\n",
+			segment.addr, segment.addr + bytes.len(), bytes.join(", ")
+		);
+	}
+	source += "\t\tlet mut guest = Guest {\n\t\t\tmemory,\n";
+	for (index, value) in globals.iter().enumerate() {
+		source += &format!("\t\t\tg{}: {},\n", index, literal(value)?);
+	}
+	source += "\t\t};\n";
+	if let Some(start) = module.start {
+		source += &format!("\t\tguest.f{}();\n", start);
+	}
+	source += "\t\tguest\n\t}\n";
+	source += HELPERS;
+
+	for function in module.functions.local() {
+		source += "\n";
+		source += &FunctionTranspiler::new(module, function)?.transpile()?;
+	}
+
+	for function in module.functions.local() {
+		let Some(export_name) = &function.export_name else {
+			continue;
+		};
+		let params: Vec<String> = function.signature.params.iter().enumerate()
+			.map(|(index, ty)| Ok(format!(", l{}: {}", index, rust_type(*ty)?)))
+			.collect::<Result<_, TranspileError>>()?;
+		let args: Vec<String> = (0..params.len()).map(|index| format!("l{}", index)).collect();
+		source += &format!(
+			"\n\tpub fn {}(&mut self{}){} {{\n\t\tself.f{}({})\n\t}}\n",
+			identifier(export_name), params.concat(), return_type(function)?, function.index, args.join(", ")
+		);
+	}
+	source += "}\n";
+	Ok(source)
+}
+
+/// Methods of the generated `Guest` which the translated instructions call.
+const HELPERS: &str = "
+	fn load<const N: usize>(&self, addr: i32, offset: u32) -> [u8; N] {
+		let start = addr as u32 as usize + offset as usize;
+		self.memory.data()[start..start + N].try_into().expect(\"The range has N bytes\")
+	}
+
+	fn store<const N: usize>(&mut self, addr: i32, offset: u32, bytes: [u8; N]) {
+		let start = addr as u32 as usize + offset as usize;
+		self.memory.data[start..start + N].copy_from_slice(&bytes);
+	}
+
+	fn memory_grow(&mut self, delta: i32) -> i32 {
+		let old_pages = self.memory.page_size();
+		match old_pages.checked_add(delta as u32 as usize) {
+			Some(new_pages) if self.memory.grow(new_pages).is_ok() => old_pages as i32,
+			_ => -1,
+		}
+	}
+";
+
+/// The Rust type of values of the WebAssembly type `ty`.
+fn rust_type(ty: Type) -> Result<&'static str, TranspileError> {
+	match ty {
+		Type::I32 => Ok("i32"),
+		Type::I64 => Ok("i64"),
+		Type::F32 => Ok("f32"),
+		Type::F64 => Ok("f64"),
+		other => Err(TranspileError::UnsupportedType(other)),
+	}
+}
+
+/// A Rust expression evaluating to `value`, keeping the bit pattern of floats.
+fn literal(value: &Value) -> Result<String, TranspileError> {
+	match value {
+		Value::I32(value) => Ok(value.to_string()),
+		Value::I64(value) => Ok(value.to_string()),
+		Value::F32(value) => Ok(format!("f32::from_bits({:#x})", value.to_bits())),
+		Value::F64(value) => Ok(format!("f64::from_bits({:#x})", value.to_bits())),
+		other => Err(TranspileError::UnsupportedType(other.ty())),
+	}
+}
+
+/// The return type annotation of the method generated for `function`.
+fn return_type(function: &WasmFunction) -> Result<String, TranspileError> {
+	match function.signature.results[..] {
+		[] => Ok(String::new()),
+		[result] => Ok(format!(" -> {}", rust_type(result)?)),
+		_ => Err(TranspileError::MultipleResults(function.index)),
+	}
+}
+
+/// Replaces the characters of the export name `name` which are not allowed in a Rust identifier.
+fn identifier(name: &str) -> String {
+	let mut identifier: String = name.chars()
+		.map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+		.collect();
+	if !identifier.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+		identifier.insert(0, '_');
+	}
+	identifier
+}
+
+/// A block, loop or if as Rust label which branches target.
+struct Label {
+	/// Number of the label, named `'b{name}` in the generated source.
+	name: usize,
+	/// Whether branches continue the loop instead of breaking out of the block.
+	is_loop: bool,
+	/// Height of the operand stack below the parameters of the block.
+	height: usize,
+	/// Types of the values a branch passes to the label.
+	branch_types: Vec<Type>,
+}
+
+/// Translates the body of a single function.
+///
+/// Every slot of the operand stack is a local variable of the generated function named after its depth and type,
+/// e.g. `s2_i64`. As the height of the stack is the same for every path reaching an instruction, the results of a block
+/// are already in the right variables when it ends, and a branch only has to move its values down to the height of
+/// the target.
+struct FunctionTranspiler<'m> {
+	module: &'m Module,
+	function: &'m WasmFunction,
+	/// Types of the parameters followed by the declared locals.
+	locals: Vec<Type>,
+	/// Types of the values on the operand stack at the current instruction.
+	stack: Vec<Type>,
+	/// Depths and types of the stack slots used by the body, which are declared at the start of the function.
+	slots: BTreeSet<(usize, &'static str)>,
+	labels: Vec<Label>,
+	next_label: usize,
+	indent: usize,
+	code: String,
+}
+
+impl<'m> FunctionTranspiler<'m> {
+	fn new(module: &'m Module, function: &'m WasmFunction) -> Result<Self, TranspileError> {
+		return_type(function)?;
+		let locals: Vec<Type> = function.signature.params.iter().chain(&function.locals).copied().collect();
+		for ty in &locals {
+			rust_type(*ty)?;
+		}
+		Ok(FunctionTranspiler {
+			module,
+			function,
+			locals,
+			stack: Vec::new(),
+			slots: BTreeSet::new(),
+			labels: vec![Label { name: 0, is_loop: false, height: 0, branch_types: function.signature.results.clone() }],
+			next_label: 1,
+			indent: 3,
+			code: String::new(),
+		})
+	}
+
+	/// Returns the source of the method executing the function.
+	fn transpile(mut self) -> Result<String, TranspileError> {
+		let function = self.function;
+		self.sequence(&function.body)?;
+
+		let params: Vec<String> = self.function.signature.params.iter().enumerate()
+			.map(|(index, ty)| Ok(format!(", mut l{}: {}", index, rust_type(*ty)?)))
+			.collect::<Result<_, TranspileError>>()?;
+		let mut source = format!(
+			"\tfn f{}(&mut self{}){} {{\n",
+			self.function.index, params.concat(), return_type(self.function)?
+		);
+		for (index, ty) in self.locals.iter().enumerate().skip(params.len()) {
+			let ty = rust_type(*ty)?;
+			source += &format!("\t\tlet mut l{}: {} = {};\n", index, ty, zero(ty));
+		}
+		for (depth, ty) in &self.slots {
+			source += &format!("\t\tlet mut s{}_{}: {} = {};\n", depth, ty, ty, zero(ty));
+		}
+		source += "\t\t'b0: {\n";
+		source += &self.code;
+		source += "\t\t}\n";
+		if let [result] = self.function.signature.results[..] {
+			source += &format!("\t\t{}\n", slot(0, result)?);
+		}
+		source += "\t}\n";
+		Ok(source)
+	}
+
+	/// Translates `instructions` and returns whether the end of the sequence is reachable.
+	fn sequence(&mut self, instructions: &'m [Instruction]) -> Result<bool, TranspileError> {
+		for instruction in instructions {
+			if !self.instruction(instruction)? {
+				// Like in the optimizer, the following instructions are unreachable and not translated
+				return Ok(false);
+			}
+		}
+		Ok(true)
+	}
+
+	/// Translates `instruction` and returns whether the next instruction is reachable.
+	fn instruction(&mut self, instruction: &'m Instruction) -> Result<bool, TranspileError> {
+		if let Some((operands, result, expression)) = numeric(instruction) {
+			let mut expression = expression.to_owned();
+			if operands == 2 {
+				expression = expression.replace("{b}", &self.pop()?);
+			}
+			expression = expression.replace("{a}", &self.pop()?);
+			let result = self.push(result)?;
+			self.line(format!("{} = {};", result, expression));
+			return Ok(true);
+		}
+		if let Some((result, conversion)) = load(instruction) {
+			let (mem_arg, bytes) = instruction.memory_access().expect("Loads access the memory");
+			let addr = self.pop()?;
+			let bytes = format!("self.load::<{}>({}, {})", bytes, addr, mem_arg.offset);
+			let result = self.push(result)?;
+			self.line(format!("{} = {};", result, conversion.replace("{m}", &bytes)));
+			return Ok(true);
+		}
+		if let Some(conversion) = store(instruction) {
+			let (mem_arg, _) = instruction.memory_access().expect("Stores access the memory");
+			let value = self.pop()?;
+			let addr = self.pop()?;
+			self.line(format!("self.store({}, {}, {});", addr, mem_arg.offset, conversion.replace("{v}", &value)));
+			return Ok(true);
+		}
+
+		match instruction {
+			Instruction::Nop => (),
+			Instruction::Unreachable => {
+				self.line("panic!(\"unreachable\");".to_owned());
+				return Ok(false);
+			},
+			Instruction::Block { block_type, instructions } => {
+				let (params, results) = self.block_types(block_type)?;
+				self.block(false, params, results.clone(), results, |transpiler| transpiler.sequence(instructions).map(drop))?;
+			},
+			Instruction::Loop { block_type, instructions } => {
+				let (params, results) = self.block_types(block_type)?;
+				self.block(true, params.clone(), params, results, |transpiler| {
+					if transpiler.sequence(instructions)? {
+						let name = transpiler.labels.last().expect("The loop is a label").name;
+						transpiler.line(format!("break 'b{};", name));
+					}
+					Ok(())
+				})?;
+			},
+			Instruction::If { block_type, if_instructions, else_instructions } => {
+				let condition = self.pop()?;
+				let (params, results) = self.block_types(block_type)?;
+				self.block(false, params, results.clone(), results, |transpiler| {
+					let stack = transpiler.stack.clone();
+					transpiler.line(format!("if {} != 0 {{", condition));
+					transpiler.indent += 1;
+					transpiler.sequence(if_instructions)?;
+					transpiler.indent -= 1;
+					transpiler.stack = stack;
+					transpiler.line("} else {".to_owned());
+					transpiler.indent += 1;
+					transpiler.sequence(else_instructions)?;
+					transpiler.indent -= 1;
+					transpiler.line("}".to_owned());
+					Ok(())
+				})?;
+			},
+			Instruction::Br { label_index } => {
+				self.branch(*label_index as usize)?;
+				return Ok(false);
+			},
+			Instruction::BrIf { label_index } => {
+				let condition = self.pop()?;
+				self.line(format!("if {} != 0 {{", condition));
+				self.indent += 1;
+				self.branch(*label_index as usize)?;
+				self.indent -= 1;
+				self.line("}".to_owned());
+			},
+			Instruction::BrTable { label_indexes, default } => {
+				let index = self.pop()?;
+				self.line(format!("match {} as u32 {{", index));
+				self.indent += 1;
+				for (case, label_index) in label_indexes.iter().enumerate() {
+					self.line(format!("{} => {{", case));
+					self.indent += 1;
+					self.branch(*label_index as usize)?;
+					self.indent -= 1;
+					self.line("},".to_owned());
+				}
+				self.line("_ => {".to_owned());
+				self.indent += 1;
+				self.branch(*default as usize)?;
+				self.indent -= 1;
+				self.line("},".to_owned());
+				self.indent -= 1;
+				self.line("}".to_owned());
+				return Ok(false);
+			},
+			Instruction::Return => {
+				self.branch(self.labels.len() - 1)?;
+				return Ok(false);
+			},
+			Instruction::Call { function_index } => {
+				let Some(ModuleFunction::Defined(callee)) = self.module.function(*function_index) else {
+					return Err(TranspileError::InvalidFunction(self.function.index));
+				};
+				let mut args = Vec::with_capacity(callee.signature.params.len());
+				for _ in &callee.signature.params {
+					args.push(self.pop()?);
+				}
+				args.reverse();
+				let call = format!("self.f{}({})", callee.index, args.join(", "));
+				match callee.signature.results[..] {
+					[] => self.line(format!("{};", call)),
+					[result] => {
+						let result = self.push(result)?;
+						self.line(format!("{} = {};", result, call));
+					},
+					_ => return Err(TranspileError::MultipleResults(callee.index)),
+				}
+			},
+			Instruction::Drop => {
+				self.pop()?;
+			},
+			Instruction::Select | Instruction::SelectValueType => {
+				let condition = self.pop()?;
+				let rhs = self.pop()?;
+				let lhs = self.top()?;
+				self.line(format!("if {} == 0 {{ {} = {}; }}", condition, lhs, rhs));
+			},
+			Instruction::LocalGet(index) => {
+				let ty = self.local(*index)?;
+				let result = self.push(ty)?;
+				self.line(format!("{} = l{};", result, index));
+			},
+			Instruction::LocalSet(index) => {
+				self.local(*index)?;
+				let value = self.pop()?;
+				self.line(format!("l{} = {};", index, value));
+			},
+			Instruction::LocalTee(index) => {
+				self.local(*index)?;
+				let value = self.top()?;
+				self.line(format!("l{} = {};", index, value));
+			},
+			Instruction::GlobalGet(index) => {
+				let global = self.module.globals.get(*index).ok_or(TranspileError::InvalidFunction(self.function.index))?;
+				let result = self.push(global.ty)?;
+				self.line(format!("{} = self.g{};", result, index));
+			},
+			Instruction::GlobalSet(index) => {
+				self.module.globals.get(*index).ok_or(TranspileError::InvalidFunction(self.function.index))?;
+				let value = self.pop()?;
+				self.line(format!("self.g{} = {};", index, value));
+			},
+			Instruction::MemorySize => {
+				let result = self.push(Type::I32)?;
+				self.line(format!("{} = self.memory.page_size() as i32;", result));
+			},
+			Instruction::MemoryGrow => {
+				let delta = self.pop()?;
+				let result = self.push(Type::I32)?;
+				self.line(format!("{} = self.memory_grow({});", result, delta));
+			},
+			Instruction::I32Const(value) => self.constant(Type::I32, value.to_string())?,
+			Instruction::I64Const(value) => self.constant(Type::I64, value.to_string())?,
+			Instruction::F32Const(value) => self.constant(Type::F32, literal(&Value::F32(*value))?)?,
+			Instruction::F64Const(value) => self.constant(Type::F64, literal(&Value::F64(*value))?)?,
+			other => return Err(TranspileError::UnsupportedInstruction {
+				function: self.function.index,
+				instruction: other.clone(),
+			}),
+		}
+		Ok(true)
+	}
+
+	/// Emits a labeled block whose contents are translated by `body`, and leaves the `results` on the stack.
+	fn block(
+		&mut self,
+		is_loop: bool,
+		params: Vec<Type>,
+		branch_types: Vec<Type>,
+		results: Vec<Type>,
+		body: impl FnOnce(&mut Self) -> Result<(), TranspileError>,
+	) -> Result<(), TranspileError> {
+		let height = self.stack.len().checked_sub(params.len())
+			.ok_or(TranspileError::InvalidFunction(self.function.index))?;
+		let name = self.next_label;
+		self.next_label += 1;
+		self.line(format!("'b{}: {}{{", name, if is_loop { "loop " } else { "" }));
+		self.indent += 1;
+		self.labels.push(Label { name, is_loop, height, branch_types });
+		body(self)?;
+		self.labels.pop();
+		self.indent -= 1;
+		self.line("}".to_owned());
+
+		self.stack.truncate(height);
+		for ty in results {
+			self.push(ty)?;
+		}
+		Ok(())
+	}
+
+	/// Moves the values passed to the label `label_index` to its height and branches to it.
+	fn branch(&mut self, label_index: usize) -> Result<(), TranspileError> {
+		let label = self.labels.len().checked_sub(label_index + 1)
+			.and_then(|index| self.labels.get(index))
+			.ok_or(TranspileError::InvalidFunction(self.function.index))?;
+		let (name, is_loop, height, branch_types) = (label.name, label.is_loop, label.height, label.branch_types.clone());
+		let source_height = self.stack.len().checked_sub(branch_types.len())
+			.filter(|source_height| *source_height >= height)
+			.ok_or(TranspileError::InvalidFunction(self.function.index))?;
+		for (index, ty) in branch_types.into_iter().enumerate() {
+			let target = self.slot(height + index, ty)?;
+			let source = self.slot(source_height + index, ty)?;
+			if target != source {
+				self.line(format!("{} = {};", target, source));
+			}
+		}
+		self.line(format!("{} 'b{};", if is_loop { "continue" } else { "break" }, name));
+		Ok(())
+	}
+
+	/// The parameter and result types of a block with `block_type`.
+	fn block_types(&self, block_type: &BlockType) -> Result<(Vec<Type>, Vec<Type>), TranspileError> {
+		match block_type {
+			BlockType::Empty => Ok((Vec::new(), Vec::new())),
+			BlockType::Value(ty) => Ok((Vec::new(), vec![*ty])),
+			BlockType::TypeIndex(index) => self.module.types.get(*index)
+				.map(|signature| (signature.params.clone(), signature.results.clone()))
+				.ok_or(TranspileError::InvalidFunction(self.function.index)),
+		}
+	}
+
+	fn constant(&mut self, ty: Type, value: String) -> Result<(), TranspileError> {
+		let result = self.push(ty)?;
+		self.line(format!("{} = {};", result, value));
+		Ok(())
+	}
+
+	/// The type of the local `index`.
+	fn local(&self, index: usize) -> Result<Type, TranspileError> {
+		self.locals.get(index).copied().ok_or(TranspileError::InvalidFunction(self.function.index))
+	}
+
+	/// Pushes a value of type `ty` and returns the name of its slot.
+	fn push(&mut self, ty: Type) -> Result<String, TranspileError> {
+		let name = self.slot(self.stack.len(), ty)?;
+		self.stack.push(ty);
+		Ok(name)
+	}
+
+	/// Pops a value and returns the name of its slot.
+	fn pop(&mut self) -> Result<String, TranspileError> {
+		let name = self.top()?;
+		self.stack.pop();
+		Ok(name)
+	}
+
+	/// The name of the slot of the topmost value.
+	fn top(&mut self) -> Result<String, TranspileError> {
+		let ty = *self.stack.last().ok_or(TranspileError::InvalidFunction(self.function.index))?;
+		self.slot(self.stack.len() - 1, ty)
+	}
+
+	/// The name of the slot at `depth` for values of type `ty`, which is declared as it is used.
+	fn slot(&mut self, depth: usize, ty: Type) -> Result<String, TranspileError> {
+		self.slots.insert((depth, rust_type(ty)?));
+		slot(depth, ty)
+	}
+
+	fn line(&mut self, line: String) {
+		for _ in 0..self.indent {
+			self.code.push('\t');
+		}
+		self.code += &line;
+		self.code.push('\n');
+	}
+}
+
+/// The name of the stack slot at `depth` for values of type `ty`.
+fn slot(depth: usize, ty: Type) -> Result<String, TranspileError> {
+	Ok(format!("s{}_{}", depth, rust_type(ty)?))
+}
+
+/// The zero literal of the Rust type `ty`.
+fn zero(ty: &str) -> &'static str {
+	match ty {
+		"f32" | "f64" => "0.0",
+		_ => "0",
+	}
+}
+
+/// The number of operands, the result type and the Rust expression of a numeric instruction, in which `{a}` and `{b}`
+/// are replaced by the first and second operand.
+///
+/// Division by zero and signed overflow of `div_s` panic like the interpreter traps. Float to integer truncations,
+/// which trap on values out of range, as well as `min` and `max`, whose NaN and zero handling differs from Rust, are
+/// not supported.
+fn numeric(instruction: &Instruction) -> Option<(usize, Type, &'static str)> {
+	use Instruction::*;
+	Some(match instruction {
+		I32Eqz => (1, Type::I32, "({a} == 0) as i32"),
+		I32Eq | I64Eq | F32Eq | F64Eq => (2, Type::I32, "({a} == {b}) as i32"),
+		I32Ne | I64Ne | F32Ne | F64Ne => (2, Type::I32, "({a} != {b}) as i32"),
+		I32LtS | I64LtS | F32Lt | F64Lt => (2, Type::I32, "({a} < {b}) as i32"),
+		I32GtS | I64GtS | F32Gt | F64Gt => (2, Type::I32, "({a} > {b}) as i32"),
+		I32LeS | I64LeS | F32Le | F64Le => (2, Type::I32, "({a} <= {b}) as i32"),
+		I32GeS | I64GeS | F32Ge | F64Ge => (2, Type::I32, "({a} >= {b}) as i32"),
+		I32LtU => (2, Type::I32, "(({a} as u32) < ({b} as u32)) as i32"),
+		I32GtU => (2, Type::I32, "(({a} as u32) > ({b} as u32)) as i32"),
+		I32LeU => (2, Type::I32, "(({a} as u32) <= ({b} as u32)) as i32"),
+		I32GeU => (2, Type::I32, "(({a} as u32) >= ({b} as u32)) as i32"),
+		I64Eqz => (1, Type::I32, "({a} == 0) as i32"),
+		I64LtU => (2, Type::I32, "(({a} as u64) < ({b} as u64)) as i32"),
+		I64GtU => (2, Type::I32, "(({a} as u64) > ({b} as u64)) as i32"),
+		I64LeU => (2, Type::I32, "(({a} as u64) <= ({b} as u64)) as i32"),
+		I64GeU => (2, Type::I32, "(({a} as u64) >= ({b} as u64)) as i32"),
+
+		I32Clz => (1, Type::I32, "{a}.leading_zeros() as i32"),
+		I32Ctz => (1, Type::I32, "{a}.trailing_zeros() as i32"),
+		I32Popcnt => (1, Type::I32, "{a}.count_ones() as i32"),
+		I32Add => (2, Type::I32, "{a}.wrapping_add({b})"),
+		I32Sub => (2, Type::I32, "{a}.wrapping_sub({b})"),
+		I32Mul => (2, Type::I32, "{a}.wrapping_mul({b})"),
+		I32DivS => (2, Type::I32, "{a} / {b}"),
+		I32DivU => (2, Type::I32, "(({a} as u32) / ({b} as u32)) as i32"),
+		I32RemS => (2, Type::I32, "{a}.wrapping_rem({b})"),
+		I32RemU => (2, Type::I32, "(({a} as u32) % ({b} as u32)) as i32"),
+		I32And => (2, Type::I32, "{a} & {b}"),
+		I32Or => (2, Type::I32, "{a} | {b}"),
+		I32Xor => (2, Type::I32, "{a} ^ {b}"),
+		I32Shl => (2, Type::I32, "{a}.wrapping_shl({b} as u32)"),
+		I32ShrS => (2, Type::I32, "{a}.wrapping_shr({b} as u32)"),
+		I32ShrU => (2, Type::I32, "({a} as u32).wrapping_shr({b} as u32) as i32"),
+		I32Rotl => (2, Type::I32, "{a}.rotate_left({b} as u32)"),
+		I32Rotr => (2, Type::I32, "{a}.rotate_right({b} as u32)"),
+
+		I64Clz => (1, Type::I64, "{a}.leading_zeros() as i64"),
+		I64Ctz => (1, Type::I64, "{a}.trailing_zeros() as i64"),
+		I64Popcnt => (1, Type::I64, "{a}.count_ones() as i64"),
+		I64Add => (2, Type::I64, "{a}.wrapping_add({b})"),
+		I64Sub => (2, Type::I64, "{a}.wrapping_sub({b})"),
+		I64Mul => (2, Type::I64, "{a}.wrapping_mul({b})"),
+		I64DivS => (2, Type::I64, "{a} / {b}"),
+		I64DivU => (2, Type::I64, "(({a} as u64) / ({b} as u64)) as i64"),
+		I64RemS => (2, Type::I64, "{a}.wrapping_rem({b})"),
+		I64RemU => (2, Type::I64, "(({a} as u64) % ({b} as u64)) as i64"),
+		I64And => (2, Type::I64, "{a} & {b}"),
+		I64Or => (2, Type::I64, "{a} | {b}"),
+		I64Xor => (2, Type::I64, "{a} ^ {b}"),
+		I64Shl => (2, Type::I64, "{a}.wrapping_shl({b} as u32)"),
+		I64ShrS => (2, Type::I64, "{a}.wrapping_shr({b} as u32)"),
+		I64ShrU => (2, Type::I64, "({a} as u64).wrapping_shr({b} as u32) as i64"),
+		I64Rotl => (2, Type::I64, "{a}.rotate_left({b} as u32)"),
+		I64Rotr => (2, Type::I64, "{a}.rotate_right({b} as u32)"),
+
+		F32Abs => (1, Type::F32, "{a}.abs()"),
+		F32Neg => (1, Type::F32, "-{a}"),
+		F32Ceil => (1, Type::F32, "{a}.ceil()"),
+		F32Floor => (1, Type::F32, "{a}.floor()"),
+		F32Trunc => (1, Type::F32, "{a}.trunc()"),
+		F32Nearest => (1, Type::F32, "{a}.round_ties_even()"),
+		F32Sqrt => (1, Type::F32, "{a}.sqrt()"),
+		F32Add => (2, Type::F32, "{a} + {b}"),
+		F32Sub => (2, Type::F32, "{a} - {b}"),
+		F32Mul => (2, Type::F32, "{a} * {b}"),
+		F32Div => (2, Type::F32, "{a} / {b}"),
+		F32Copysign => (2, Type::F32, "{a}.copysign({b})"),
+
+		F64Abs => (1, Type::F64, "{a}.abs()"),
+		F64Neg => (1, Type::F64, "-{a}"),
+		F64Ceil => (1, Type::F64, "{a}.ceil()"),
+		F64Floor => (1, Type::F64, "{a}.floor()"),
+		F64Trunc => (1, Type::F64, "{a}.trunc()"),
+		F64Nearest => (1, Type::F64, "{a}.round_ties_even()"),
+		F64Sqrt => (1, Type::F64, "{a}.sqrt()"),
+		F64Add => (2, Type::F64, "{a} + {b}"),
+		F64Sub => (2, Type::F64, "{a} - {b}"),
+		F64Mul => (2, Type::F64, "{a} * {b}"),
+		F64Div => (2, Type::F64, "{a} / {b}"),
+		F64Copysign => (2, Type::F64, "{a}.copysign({b})"),
+
+		I32WrapI64 => (1, Type::I32, "{a} as i32"),
+		I64ExtendI32S => (1, Type::I64, "{a} as i64"),
+		I64ExtendI32U => (1, Type::I64, "{a} as u32 as i64"),
+		F32ConvertI32S | F32ConvertI64S | F32DemoteF64 => (1, Type::F32, "{a} as f32"),
+		F32ConvertI32U => (1, Type::F32, "{a} as u32 as f32"),
+		F32ConvertI64 => (1, Type::F32, "{a} as u64 as f32"),
+		F64ConvertI32S | F64ConvertI64S | F64PromoteF32 => (1, Type::F64, "{a} as f64"),
+		F64ConvertI32U => (1, Type::F64, "{a} as u32 as f64"),
+		F64ConvertI64U => (1, Type::F64, "{a} as u64 as f64"),
+		I32ReinterpretF32 => (1, Type::I32, "{a}.to_bits() as i32"),
+		I64ReinterpretF64 => (1, Type::I64, "{a}.to_bits() as i64"),
+		F32ReinterpretI32 => (1, Type::F32, "f32::from_bits({a} as u32)"),
+		F64ReinterpretI64 => (1, Type::F64, "f64::from_bits({a} as u64)"),
+
+		I32Extend8S => (1, Type::I32, "{a} as i8 as i32"),
+		I32Extend16S => (1, Type::I32, "{a} as i16 as i32"),
+		I64Extend8S => (1, Type::I64, "{a} as i8 as i64"),
+		I64Extend16S => (1, Type::I64, "{a} as i16 as i64"),
+		I64Extend32S => (1, Type::I64, "{a} as i32 as i64"),
+
+		// Casts of floats to integers saturate in Rust
+		I32TruncSatF32S | I32TruncSatF64S => (1, Type::I32, "{a} as i32"),
+		I32TruncSatF32U | I32TruncSatF64U => (1, Type::I32, "{a} as u32 as i32"),
+		I64TruncSatF32S | I64TruncSatF64S => (1, Type::I64, "{a} as i64"),
+		I64TruncSatF32U | I64TruncSatF64U => (1, Type::I64, "{a} as u64 as i64"),
+		_ => return None,
+	})
+}
+
+/// The result type and the Rust expression converting the loaded bytes `{m}` of a load instruction.
+fn load(instruction: &Instruction) -> Option<(Type, &'static str)> {
+	Some(match instruction {
+		Instruction::I32Load(_) => (Type::I32, "i32::from_le_bytes({m})"),
+		Instruction::I64Load(_) => (Type::I64, "i64::from_le_bytes({m})"),
+		Instruction::F32Load(_) => (Type::F32, "f32::from_le_bytes({m})"),
+		Instruction::F64Load(_) => (Type::F64, "f64::from_le_bytes({m})"),
+		Instruction::I32Load8s(_) => (Type::I32, "i8::from_le_bytes({m}) as i32"),
+		Instruction::I32Load8u(_) => (Type::I32, "u8::from_le_bytes({m}) as i32"),
+		Instruction::I32Load16s(_) => (Type::I32, "i16::from_le_bytes({m}) as i32"),
+		Instruction::I32Load16u(_) => (Type::I32, "u16::from_le_bytes({m}) as i32"),
+		Instruction::I64Load8s(_) => (Type::I64, "i8::from_le_bytes({m}) as i64"),
+		Instruction::I64Load8u(_) => (Type::I64, "u8::from_le_bytes({m}) as i64"),
+		Instruction::I64Load16s(_) => (Type::I64, "i16::from_le_bytes({m}) as i64"),
+		Instruction::I64Load16u(_) => (Type::I64, "u16::from_le_bytes({m}) as i64"),
+		Instruction::I64Load32s(_) => (Type::I64, "i32::from_le_bytes({m}) as i64"),
+		Instruction::I64Load32u(_) => (Type::I64, "u32::from_le_bytes({m}) as i64"),
+		_ => return None,
+	})
+}
+
+/// The Rust expression converting the value `{v}` of a store instruction into the stored bytes.
+fn store(instruction: &Instruction) -> Option<&'static str> {
+	Some(match instruction {
+		Instruction::I32Store(_) | Instruction::I64Store(_)
+		| Instruction::F32Store(_) | Instruction::F64Store(_) => "{v}.to_le_bytes()",
+		Instruction::I32Store8(_) | Instruction::I64Store8(_) => "({v} as u8).to_le_bytes()",
+		Instruction::I32Store16(_) | Instruction::I64Store16(_) => "({v} as u16).to_le_bytes()",
+		Instruction::I64Store32(_) => "({v} as u32).to_le_bytes()",
+		_ => return None,
+	})
+}
\ No newline at end of file
@@ -0,0 +1,112 @@
+//! Bindings for running the interpreter in the browser, built for `wasm32-unknown-unknown` with the `web` feature:
+//!
+//! ```sh
+//! cargo build --lib --release --target wasm32-unknown-unknown --no-default-features --features web
+//! wasm-bindgen --target web --out-dir web target/wasm32-unknown-unknown/release/rust_wasm_runtime.wasm
+//! ```
+//!
+//! JavaScript then instantiates modules with `new WebInstance(bytes)`, where numbers are passed and returned as
+//! JavaScript numbers and the output of the guest is collected instead of written to a terminal.
+
+use std::io::{self, Write};
+use wasm_bindgen::prelude::*;
+use crate::exec::{Clock, Config, Instance, Linker, TimeSource, Value, WasiCtx};
+use crate::parse::{Module, Type};
+use crate::shared::{self, SharedMut};
+
+/// The clocks of JavaScript, as `wasm32-unknown-unknown` has none.
+#[derive(Debug)]
+struct JsClocks {
+	/// Start of the monotonic clock in milliseconds since the Unix epoch.
+	started: f64,
+}
+
+impl TimeSource for JsClocks {
+	fn now(&self, clock: Clock) -> u64 {
+		let millis = match clock {
+			Clock::Realtime => js_sys::Date::now(),
+			Clock::Monotonic => js_sys::Date::now() - self.started,
+		};
+		(millis * 1_000_000.0) as u64
+	}
+}
+
+/// Appends the output of the guest to a buffer, which JavaScript takes with [`WebInstance::take_stdout`].
+struct Captured(SharedMut<Vec<u8>>);
+
+impl Write for Captured {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		shared::lock(&self.0).extend_from_slice(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+/// An [`Instance`] driven from JavaScript.
+#[wasm_bindgen]
+pub struct WebInstance {
+	instance: Instance,
+	/// What the guest wrote to stdout and stderr since the last [`WebInstance::take_stdout`].
+	stdout: SharedMut<Vec<u8>>,
+}
+
+#[wasm_bindgen]
+impl WebInstance {
+	/// Parses and instantiates the binary module `bytes` with the WASI imports of [`Linker::new`].
+	#[wasm_bindgen(constructor)]
+	pub fn new(bytes: &[u8]) -> Result<WebInstance, JsError> {
+		let module = Module::from_bytes(bytes.to_vec()).map_err(|error| JsError::new(&error.to_string()))?;
+		let config = Config::default().time_source(JsClocks { started: js_sys::Date::now() });
+		let mut instance = Linker::new().instantiate(module, config).map_err(|error| JsError::new(&error.to_string()))?;
+		let stdout = shared::shared_mut(Vec::new());
+		let mut wasi = WasiCtx::new();
+		wasi.set_stdout(Captured(stdout.clone()));
+		wasi.set_stderr(Captured(stdout.clone()));
+		instance.set_wasi(wasi);
+		Ok(WebInstance { instance, stdout })
+	}
+
+	/// Runs the exported `_start` function, e.g. the `main` of a WASI program.
+	pub fn start(&mut self) -> Result<(), JsError> {
+		self.instance.start().map_err(|error| JsError::new(&error.to_string()))
+	}
+
+	/// Calls the exported function `name` with `args`, which are converted to the types of its parameters. 64 bit
+	/// integers beyond 2^53 lose precision as JavaScript numbers.
+	pub fn call(&mut self, name: &str, args: Vec<f64>) -> Result<Vec<f64>, JsError> {
+		let func = self.instance.get_func(name).ok_or_else(|| JsError::new(&format!("unknown export {}", name)))?;
+		let args = func.signature().params.iter().zip(args)
+			.map(|(ty, arg)| match ty {
+				Type::I32 => Ok(Value::I32(arg as i32)),
+				Type::I64 => Ok(Value::I64(arg as i64)),
+				Type::F32 => Ok(Value::F32(arg as f32)),
+				Type::F64 => Ok(Value::F64(arg)),
+				other => Err(JsError::new(&format!("parameters of type {:?} are not supported", other))),
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+		let results = func.call(&mut self.instance, args).map_err(|error| JsError::new(&error.to_string()))?;
+		results.into_iter()
+			.map(|result| match result {
+				Value::I32(value) => Ok(value as f64),
+				Value::I64(value) => Ok(value as f64),
+				Value::F32(value) => Ok(value as f64),
+				Value::F64(value) => Ok(value),
+				other => Err(JsError::new(&format!("the result {:?} is not supported", other))),
+			})
+			.collect()
+	}
+
+	/// A copy of the memory of the guest, empty if it has none.
+	pub fn memory(&self) -> Vec<u8> {
+		self.instance.memory().as_ref().map_or_else(Vec::new, |memory| memory.data().to_vec())
+	}
+
+	/// What the guest wrote to stdout and stderr since the last call, decoded as UTF-8.
+	#[wasm_bindgen(js_name = takeStdout)]
+	pub fn take_stdout(&mut self) -> String {
+		String::from_utf8_lossy(&std::mem::take(&mut *shared::lock(&self.stdout))).into_owned()
+	}
+}
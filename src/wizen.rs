@@ -0,0 +1,69 @@
+//! Pre-initialization of modules, which snapshots the state after an initialization function into a new module.
+
+use crate::exec::{Config, ConstExpr, Error, Instance};
+use crate::parse::{DataSegment, ExportKind, Module};
+use crate::shared::Shared;
+
+/// Runs of zero bytes shorter than this are kept inside a data segment instead of starting a new one, as every
+/// segment costs a few bytes of encoding overhead.
+const MIN_ZERO_GAP: usize = 8;
+
+/// Executes the exported function `init` of `module` and returns a module whose memory starts in the state `init`
+/// and globals start in the state `init` left them in, so that startup-heavy guests can be initialized once and
+/// started instantly thereafter.
+///
+/// The data segments of the returned module are replaced by the non-zero contents of the memory, and its minimum
+/// number of pages is the one after initialization. The initializers of the defined globals are replaced by their values,
+/// except for references to host objects, which keep their initializer. The export of `init` is removed, as running
+/// it again would initialize the state twice. Imports are resolved like in [`Instance::with_config`].
+///
+/// Changes `init` made to tables are discarded.
+pub fn wizen(module: Module, init: &str) -> Result<Module, Error> {
+	let export = module.export(init)
+		.filter(|export| export.kind == ExportKind::Function)
+		.ok_or_else(|| Error::UnknownExport(init.to_owned()))?;
+	let module = Shared::new(module);
+	let mut instance = Instance::with_config(Shared::clone(&module), Config::default())?;
+	instance.invoke(export.index, Vec::new())?;
+	let memory = instance.memory().as_ref().map(|memory| (memory.page_size(), data_segments(memory.data())));
+	let globals = instance.globals().to_vec();
+	drop(instance);
+
+	let mut module = Shared::try_unwrap(module).expect("The instance was dropped");
+	if let (Some(blueprint), Some((page_size, init))) = (module.memory_blueprint.as_mut(), memory) {
+		blueprint.page_limit.start = page_size;
+		blueprint.init = init;
+	}
+	for (global, value) in module.globals.iter_mut().zip(globals).filter(|(global, _)| global.import.is_none()) {
+		if let Ok(init) = ConstExpr::try_from(value) {
+			global.init = init;
+		}
+	}
+	for function in module.functions.local_mut() {
+		if function.export_name.as_deref() == Some(init) {
+			Shared::make_mut(function).export_name = None;
+		}
+	}
+	module.export_map.remove(&ExportKind::Function, init);
+	Ok(module)
+}
+
+/// Data segments covering all non-zero bytes of `data`.
+fn data_segments(data: &[u8]) -> Vec<DataSegment> {
+	let mut segments = Vec::new();
+	let mut addr = 0;
+	while let Some(start) = data[addr..].iter().position(|byte| *byte != 0).map(|offset| addr + offset) {
+		let mut end = start;
+		loop {
+			end += data[end..].iter().position(|byte| *byte == 0).unwrap_or(data.len() - end);
+			let gap = data[end..].iter().take(MIN_ZERO_GAP).take_while(|byte| **byte == 0).count();
+			if gap == MIN_ZERO_GAP || end + gap == data.len() {
+				break;
+			}
+			end += gap;
+		}
+		segments.push(DataSegment { addr: start, offset: None, data: data[start..end].to_vec().into() });
+		addr = end;
+	}
+	segments
+}
\ No newline at end of file
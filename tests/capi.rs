@@ -0,0 +1,67 @@
+//! Smoke test of the C API, which drives a module through the exported functions like a C embedder would.
+//!
+//! Run with `cargo test --features capi --test capi`.
+#![cfg(feature = "capi")]
+
+use std::ffi::CStr;
+use std::ptr;
+use rust_wasm_runtime::capi::*;
+use rust_wasm_runtime::exec::Instruction;
+use rust_wasm_runtime::parse::{ModuleBuilder, Type};
+
+/// A module exporting `add(i32, i32) -> i32` and a memory starting with `hi`.
+fn module_bytes() -> Vec<u8> {
+	let mut builder = ModuleBuilder::new();
+	let signature = builder.signature(vec![Type::I32, Type::I32], vec![Type::I32]);
+	let add = builder.function(signature, vec![], vec![
+		Instruction::LocalGet(0),
+		Instruction::LocalGet(1),
+		Instruction::I32Add,
+	]);
+	builder.export_function("add", add);
+	builder.memory(1, None).data(0, b"hi".to_vec()).export_memory("memory");
+	builder.build().encode()
+}
+
+#[test]
+fn call_and_read_memory() {
+	let bytes = module_bytes();
+	unsafe {
+		let mut error = ptr::null_mut();
+		let module = rwr_module_new(bytes.as_ptr(), bytes.len(), &mut error);
+		assert!(!module.is_null());
+		let instance = rwr_instance_new(module, &mut error);
+		assert!(!instance.is_null());
+		rwr_module_delete(module);
+
+		let args = [
+			RwrValue { kind: RwrValueKind::I32, of: RwrValueOf { i32: 40 } },
+			RwrValue { kind: RwrValueKind::I32, of: RwrValueOf { i32: 2 } },
+		];
+		let mut result = RwrValue { kind: RwrValueKind::I64, of: RwrValueOf { i64: 0 } };
+		assert!(rwr_call(instance, c"add".as_ptr(), args.as_ptr(), args.len(), &mut result, 1, &mut error));
+		assert_eq!(result.kind, RwrValueKind::I32);
+		assert_eq!(result.of.i32, 42);
+
+		let mut len = 0;
+		let data = rwr_memory_data(instance, &mut len);
+		assert_eq!(len, 65536);
+		assert_eq!(*data, b'h');
+
+		assert!(!rwr_call(instance, c"missing".as_ptr(), ptr::null(), 0, ptr::null_mut(), 0, &mut error));
+		assert_eq!(CStr::from_ptr(rwr_error_message(error)).to_str().unwrap(), "unknown export missing");
+		rwr_error_delete(error);
+		rwr_instance_delete(instance);
+	}
+}
+
+#[test]
+fn invalid_module() {
+	unsafe {
+		let mut error = ptr::null_mut();
+		let module = rwr_module_new(b"wasm".as_ptr(), 4, &mut error);
+		assert!(module.is_null());
+		assert!(!error.is_null());
+		rwr_error_delete(error);
+	}
+}
@@ -0,0 +1,64 @@
+//! Parses the same binaries with the native parser and the `wasmparser` frontend, which have to build equal modules.
+
+#![cfg(feature = "frontend-wasmparser")]
+
+use rust_wasm_runtime::encode::encode;
+use rust_wasm_runtime::exec::{BlockType, Instruction, MemArg};
+use rust_wasm_runtime::parse::{Module, ModuleBuilder, Type};
+
+/// Encodes `module` and asserts that both frontends parse the binary into the same module.
+fn assert_frontends_agree(module: Module) {
+	let binary = encode(&module);
+	let native = Module::from_bytes(binary.clone()).expect("The native parser accepts the encoded module");
+	let wasmparser = Module::from_bytes_wasmparser(binary).expect("wasmparser accepts the encoded module");
+	// Modules do not implement `PartialEq`, as functions may be defined by the host
+	assert_eq!(format!("{:#?}", native), format!("{:#?}", wasmparser));
+}
+
+#[test]
+fn frontends_agree_on_functions_and_exports() {
+	let mut builder = ModuleBuilder::new();
+	let signature = builder.signature(vec![Type::I32, Type::I64], vec![Type::I64]);
+	let log = builder.signature(vec![Type::I32], vec![]);
+	let import = builder.import("env", "log", log);
+	let body = vec![
+		Instruction::LocalGet(0),
+		Instruction::Call { function_index: import },
+		Instruction::Block {
+			block_type: BlockType::Value(Type::I64),
+			instructions: vec![
+				Instruction::LocalGet(1),
+				Instruction::LocalGet(0),
+				Instruction::BrIf { label_index: 0 },
+				Instruction::I64Const(-1),
+				Instruction::I64Add,
+			],
+		},
+	];
+	let function = builder.function(signature, vec![Type::F64], body);
+	builder.export_function("run", function);
+	assert_frontends_agree(builder.build());
+}
+
+#[test]
+fn frontends_agree_on_memories_and_data_segments() {
+	let mut builder = ModuleBuilder::new();
+	builder.memory(1, Some(2)).data(0x10, b"active".to_vec()).export_memory("memory");
+	let passive = builder.passive_data(b"passive".to_vec());
+	let signature = builder.signature(vec![], vec![]);
+	let mem_arg = MemArg { align: 2, offset: 4 };
+	let body = vec![
+		Instruction::I32Const(0), Instruction::I32Const(0), Instruction::I32Const(7),
+		Instruction::MemoryInit(passive),
+		Instruction::DataDrop(passive),
+		Instruction::I32Const(0), Instruction::I32Const(0x10), Instruction::I32Const(6),
+		Instruction::MemoryCopy,
+		Instruction::I32Const(0), Instruction::I32Const(0xff), Instruction::I32Const(4),
+		Instruction::MemoryFill,
+		Instruction::I32Const(0), Instruction::I32Const(0), Instruction::I32Load(mem_arg.clone()),
+		Instruction::I32Store(mem_arg),
+	];
+	let function = builder.function(signature, vec![], body);
+	builder.start(function);
+	assert_frontends_agree(builder.build());
+}
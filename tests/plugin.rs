@@ -0,0 +1,74 @@
+//! A long-lived instance which is instantiated once and whose exports are called many times, like a plugin.
+
+use rust_wasm_runtime::exec::{Config, Error, Instance, Instruction, MemArg, Value};
+use rust_wasm_runtime::parse::{ModuleBuilder, Type};
+
+/// A module exporting `bump() -> i32`, which increments and returns a counter in memory, `add(i32, i32) -> i32` and
+/// `trap(i32)`, which pushes its argument and traps.
+fn plugin(config: Config) -> Instance {
+	let mut builder = ModuleBuilder::new();
+	let bump_signature = builder.signature(vec![], vec![Type::I32]);
+	let bump = builder.function(bump_signature, vec![], vec![
+		Instruction::I32Const(0),
+		Instruction::I32Const(0),
+		Instruction::I32Load(MemArg::default()),
+		Instruction::I32Const(1),
+		Instruction::I32Add,
+		Instruction::I32Store(MemArg::default()),
+		Instruction::I32Const(0),
+		Instruction::I32Load(MemArg::default()),
+	]);
+	let add_signature = builder.signature(vec![Type::I32, Type::I32], vec![Type::I32]);
+	let add = builder.function(add_signature, vec![], vec![
+		Instruction::LocalGet(0),
+		Instruction::LocalGet(1),
+		Instruction::I32Add,
+	]);
+	let trap_signature = builder.signature(vec![Type::I32], vec![]);
+	let trap = builder.function(trap_signature, vec![], vec![
+		Instruction::LocalGet(0),
+		Instruction::LocalGet(0),
+		Instruction::Call { function_index: bump },
+		Instruction::Unreachable,
+	]);
+	builder.export_function("bump", bump).export_function("add", add).export_function("trap", trap);
+	builder.memory(1, None);
+	Instance::with_config(builder.build(), config).expect("The module instantiates")
+}
+
+#[test]
+fn memory_persists_between_calls() {
+	let mut instance = plugin(Config::default());
+	let bump = instance.get_func("bump").unwrap();
+	for i in 1..=10_000 {
+		assert_eq!(bump.call(&mut instance, vec![]).unwrap(), vec![Value::I32(i)]);
+	}
+	assert!(instance.operand_stack().is_empty());
+}
+
+#[test]
+fn calls_after_traps_start_on_empty_stacks() {
+	let mut instance = plugin(Config::default().allow_resume_after_trap(true));
+	let add = instance.get_func("add").unwrap();
+	let trap = instance.get_func("trap").unwrap();
+	for i in 0..5_000 {
+		assert!(matches!(trap.call(&mut instance, vec![Value::I32(i)]), Err(Error::Trap(_))));
+		assert_eq!(add.call(&mut instance, vec![Value::I32(i), Value::I32(1)]).unwrap(), vec![Value::I32(i + 1)]);
+		assert!(instance.operand_stack().is_empty());
+	}
+	// Each trap bumped the counter before trapping
+	let bump = instance.get_func("bump").unwrap();
+	assert_eq!(bump.call(&mut instance, vec![]).unwrap(), vec![Value::I32(5_001)]);
+}
+
+#[test]
+fn trap_poisons_instance() {
+	let mut instance = plugin(Config::default());
+	let add = instance.get_func("add").unwrap();
+	let trap = instance.get_func("trap").unwrap();
+	assert!(matches!(trap.call(&mut instance, vec![Value::I32(0)]), Err(Error::Trap(_))));
+	assert!(instance.is_poisoned());
+	assert!(matches!(add.call(&mut instance, vec![Value::I32(1), Value::I32(2)]), Err(Error::InstancePoisoned(_))));
+	let mut fork = instance.fork();
+	assert!(matches!(add.call(&mut fork, vec![Value::I32(1), Value::I32(2)]), Err(Error::InstancePoisoned(_))));
+}
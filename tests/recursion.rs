@@ -0,0 +1,22 @@
+//! Deep recursion of the guest, which must trap instead of overflowing the stack of the host.
+
+use rust_wasm_runtime::exec::{Config, Error, Instance, Instruction, TrapKind, Value};
+use rust_wasm_runtime::parse::{ModuleBuilder, Type};
+
+#[test]
+fn deep_recursion_traps() {
+	let mut builder = ModuleBuilder::new();
+	let signature = builder.signature(vec![Type::I32], vec![Type::I32]);
+	// Calls itself without a base case
+	let recurse = builder.function(signature, vec![], vec![
+		Instruction::LocalGet(0),
+		Instruction::I32Const(1),
+		Instruction::I32Add,
+		Instruction::Call { function_index: 0 },
+	]);
+	builder.export_function("recurse", recurse);
+	let mut instance = Instance::with_config(builder.build(), Config::default()).expect("The module instantiates");
+	let recurse = instance.get_func("recurse").unwrap();
+	let result = recurse.call(&mut instance, vec![Value::I32(0)]);
+	assert!(matches!(result, Err(Error::Trap(TrapKind::StackExhausted))), "{:?}", result);
+}
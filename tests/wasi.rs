@@ -0,0 +1,102 @@
+//! Calls the WASI functions of a [`Linker`] directly from a guest, which passes pointers into its memory.
+
+use std::fs::{self, File};
+use rust_wasm_runtime::exec::{Config, Instance, Instruction, Linker, Value, WasiCtx, WasiPolicy, WasiVersion};
+use rust_wasm_runtime::parse::{ModuleBuilder, Type};
+
+/// Address of an iovec pointing to the 5 bytes of [`HELLO`].
+const IOVEC: i32 = 0x10;
+/// Address of the bytes written by the guest.
+const HELLO: usize = 0x20;
+/// Address at which the WASI functions return the number of written bytes, which initially holds garbage.
+const NWRITTEN: i32 = 0x30;
+/// Errno of WASI for a file descriptor which is not open.
+const ERRNO_BADF: i32 = 8;
+/// Errno of WASI for an exhausted quota.
+const ERRNO_DQUOT: i32 = 19;
+/// Errno of WASI for a pointer outside of the memory.
+const ERRNO_FAULT: i32 = 21;
+
+/// An instance whose function 1 calls `fd_write(fd, iovs_ptr, iovs_len, nwritten_ptr)` with its parameters.
+fn guest(wasi: WasiCtx) -> Instance {
+	let mut builder = ModuleBuilder::new();
+	let signature = builder.signature(vec![Type::I32; 4], vec![Type::I32]);
+	let fd_write = builder.import("wasi_snapshot_preview1", "fd_write", signature);
+	let body = (0..4).map(Instruction::LocalGet).chain([Instruction::Call { function_index: fd_write }]).collect();
+	builder.function(signature, vec![], body);
+	builder.memory(1, None);
+	let mut iovec = (HELLO as u32).to_le_bytes().to_vec();
+	iovec.extend(5u32.to_le_bytes());
+	builder.data(IOVEC as usize, iovec).data(HELLO, b"hello".to_vec()).data(NWRITTEN as usize, vec![0xff; 4]);
+	let mut instance = Linker::with_wasi(WasiVersion::Preview1)
+		.instantiate(builder.build(), Config::default())
+		.expect("The imports are WASI functions");
+	instance.set_wasi(wasi);
+	instance
+}
+
+/// Calls `fd_write` in `instance` and returns the errno.
+fn fd_write(instance: &mut Instance, fd: i32, iovs_ptr: i32, iovs_len: i32, nwritten_ptr: i32) -> i32 {
+	let args = vec![Value::I32(fd), Value::I32(iovs_ptr), Value::I32(iovs_len), Value::I32(nwritten_ptr)];
+	match instance.invoke(1, args).expect("fd_write does not trap")[..] {
+		[Value::I32(errno)] => errno,
+		ref results => panic!("fd_write returned {:?}", results),
+	}
+}
+
+/// The number of written bytes the last call of `fd_write` returned.
+fn nwritten(instance: &Instance) -> u32 {
+	let data = instance.memory().as_ref().expect("The guest has a memory").data();
+	u32::from_le_bytes(data[NWRITTEN as usize..][..4].try_into().unwrap())
+}
+
+#[test]
+fn fd_write_returns_the_number_of_written_bytes() {
+	let mut wasi = WasiCtx::new();
+	wasi.set_stdout(Vec::new());
+	let mut instance = guest(wasi);
+	assert_eq!(fd_write(&mut instance, 1, IOVEC, 1, NWRITTEN), 0);
+	assert_eq!(nwritten(&instance), 5);
+}
+
+#[test]
+fn fd_write_faults_on_pointers_outside_of_the_memory() {
+	let mut wasi = WasiCtx::new();
+	wasi.set_stdout(Vec::new());
+	let mut instance = guest(wasi);
+	assert_eq!(fd_write(&mut instance, 1, IOVEC, 1, -2), ERRNO_FAULT);
+	assert_eq!(fd_write(&mut instance, 1, -4, 1, NWRITTEN), ERRNO_FAULT);
+	assert_eq!(fd_write(&mut instance, 1, IOVEC, i32::MAX, NWRITTEN), ERRNO_FAULT);
+}
+
+#[test]
+fn fd_write_fails_when_denied_by_the_policy() {
+	let mut wasi = WasiCtx::new();
+	wasi.set_stdout(Vec::new());
+	wasi.set_policy(WasiPolicy::default().write_quota(0));
+	let mut instance = guest(wasi);
+	assert_eq!(fd_write(&mut instance, 1, IOVEC, 1, NWRITTEN), ERRNO_DQUOT);
+	assert_eq!(nwritten(&instance), 0);
+	assert_eq!(fd_write(&mut instance, 1, IOVEC, 1, -2), ERRNO_FAULT);
+}
+
+#[test]
+fn fd_write_writes_to_files() {
+	let path = std::env::temp_dir().join(format!("rust-wasm-runtime-fd-write-{}", std::process::id()));
+	let mut wasi = WasiCtx::new();
+	let fd = wasi.preopen_file(File::create(&path).expect("The temporary directory is writable"));
+	let mut instance = guest(wasi);
+	assert_eq!(fd_write(&mut instance, fd as i32, IOVEC, 1, NWRITTEN), 0);
+	assert_eq!(fd_write(&mut instance, fd as i32, IOVEC, 1, NWRITTEN), 0);
+	assert_eq!(nwritten(&instance), 5);
+	drop(instance);
+	assert_eq!(fs::read(&path).unwrap(), b"hellohello");
+	fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn fd_write_fails_on_unknown_file_descriptors() {
+	let mut instance = guest(WasiCtx::new());
+	assert_eq!(fd_write(&mut instance, 42, IOVEC, 1, NWRITTEN), ERRNO_BADF);
+	assert_eq!(nwritten(&instance), 0);
+}
@@ -0,0 +1,160 @@
+//! Tasks for developing the runtime, run with `cargo xtask <task>`.
+//!
+//! `cargo xtask guests [<name>...]` compiles the programs in `examples/guests` to WebAssembly and runs each of them
+//! through the runtime, comparing their stdout with the `.stdout` file next to their source. Rust guests need the
+//! `wasm32-wasip1` target (`rustup target add wasm32-wasip1`), C guests the WASI SDK at `$WASI_SDK_PATH`, and both
+//! are skipped without them.
+
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode, Stdio};
+use std::io::Write;
+use std::{env, fs};
+
+/// The Rust target of WASI preview 1, which was called `wasm32-wasi` before.
+const RUST_TARGET: &str = "wasm32-wasip1";
+
+/// A program of `examples/guests` and how it is run.
+struct Guest {
+	/// File name of the source without extension, which also names the expected output.
+	name: &'static str,
+	/// Arguments passed to the guest.
+	args: &'static [&'static str],
+	/// Flags of the runtime.
+	flags: &'static [&'static str],
+	/// Run the guest in an empty directory, which it gets access to as `.`.
+	working_dir: bool,
+}
+
+const GUESTS: &[Guest] = &[
+	Guest { name: "arith", args: &[], flags: &[], working_dir: false },
+	Guest { name: "memory", args: &[], flags: &[], working_dir: false },
+	Guest { name: "stdio", args: &[], flags: &[], working_dir: false },
+	Guest { name: "args", args: &["one", "two words"], flags: &["--env=GREETING=hi"], working_dir: false },
+	Guest { name: "files", args: &[], flags: &["--dir=."], working_dir: true },
+	Guest { name: "indirect", args: &[], flags: &[], working_dir: false },
+];
+
+enum Outcome {
+	Passed,
+	Failed(String),
+	Skipped(&'static str),
+}
+
+fn main() -> ExitCode {
+	let args: Vec<String> = env::args().skip(1).collect();
+	let result = match args.first().map(String::as_str) {
+		Some("guests") => guests(&args[1..]),
+		_ => Err("Usage: cargo xtask guests [<name>...]".into()),
+	};
+	match result {
+		Ok(true) => ExitCode::SUCCESS,
+		Ok(false) => ExitCode::FAILURE,
+		Err(error) => {
+			eprintln!("{}", error);
+			ExitCode::FAILURE
+		},
+	}
+}
+
+/// Builds and runs the guests named in `filter`, or all of them if it is empty. Returns whether none failed.
+fn guests(filter: &[String]) -> Result<bool, Box<dyn Error>> {
+	let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().ok_or("xtask is inside the repository")?;
+	let out_dir = root.join("target").join("guests");
+	fs::create_dir_all(&out_dir)?;
+
+	let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned());
+	let status = Command::new(cargo)
+		.args(["build", "--release", "--bin", "rust_wasm_runtime"])
+		.current_dir(root)
+		.status()?;
+	if !status.success() {
+		return Err("Building the runtime failed".into());
+	}
+	let runtime = root.join("target").join("release").join(format!("rust_wasm_runtime{}", env::consts::EXE_SUFFIX));
+
+	let mut failed = 0;
+	for guest in GUESTS.iter().filter(|guest| filter.is_empty() || filter.iter().any(|name| name == guest.name)) {
+		let outcome = run_guest(guest, &root.join("examples").join("guests"), &out_dir, &runtime)
+			.unwrap_or_else(|error| Outcome::Failed(error.to_string()));
+		match outcome {
+			Outcome::Passed => println!("{:<10} ok", guest.name),
+			Outcome::Skipped(reason) => println!("{:<10} skipped: {}", guest.name, reason),
+			Outcome::Failed(reason) => {
+				println!("{:<10} FAILED\n{}", guest.name, reason);
+				failed += 1;
+			},
+		}
+	}
+	Ok(failed == 0)
+}
+
+fn run_guest(guest: &Guest, source_dir: &Path, out_dir: &Path, runtime: &Path) -> Result<Outcome, Box<dyn Error>> {
+	let wasm = out_dir.join(guest.name).with_extension("wasm");
+	let compiled = match compile(source_dir, guest.name, &wasm)? {
+		Ok(compiled) => compiled,
+		Err(missing) => return Ok(Outcome::Skipped(missing)),
+	};
+	if !compiled.status.success() {
+		return Ok(Outcome::Failed(format!("Compiling failed:\n{}", String::from_utf8_lossy(&compiled.stderr))));
+	}
+
+	let mut command = Command::new(runtime);
+	command.arg("run").args(guest.flags).arg(&wasm).args(guest.args);
+	if guest.working_dir {
+		let dir = out_dir.join(format!("{}.dir", guest.name));
+		// Leftovers of a previous failed run would change the output
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir)?;
+		command.current_dir(dir);
+	}
+	let stdin = fs::read(source_dir.join(guest.name).with_extension("stdin")).unwrap_or_default();
+	let mut child = command.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+	child.stdin.take().expect("stdin is piped").write_all(&stdin)?;
+	let output = child.wait_with_output()?;
+
+	let expected = fs::read_to_string(source_dir.join(guest.name).with_extension("stdout"))?;
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	Ok(match (output.status.success(), stdout == expected) {
+		(true, true) => Outcome::Passed,
+		(false, _) => Outcome::Failed(format!("The runtime exited with {}:\n{}", output.status, stderr)),
+		(true, false) => Outcome::Failed(format!("Expected stdout:\n{}Actual stdout:\n{}", expected, stdout)),
+	})
+}
+
+/// Compiles the Rust or C source `name` in `source_dir` to `wasm`, or returns why the compiler is unavailable.
+fn compile(
+	source_dir: &Path, name: &str, wasm: &Path,
+) -> Result<Result<std::process::Output, &'static str>, Box<dyn Error>> {
+	let rust_source = source_dir.join(name).with_extension("rs");
+	let mut command = match rust_source.exists() {
+		true => {
+			let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+			if !rust_target_installed(&rustc)? {
+				return Ok(Err("the wasm32-wasip1 target is not installed"));
+			}
+			let mut command = Command::new(rustc);
+			command.args(["--edition", "2021", "-O", "--target", RUST_TARGET]).arg(&rust_source);
+			command
+		},
+		false => {
+			let Some(sdk) = env::var_os("WASI_SDK_PATH").map(PathBuf::from) else {
+				return Ok(Err("WASI_SDK_PATH is not set"));
+			};
+			let mut command = Command::new(sdk.join("bin").join("clang"));
+			command.arg("--sysroot").arg(sdk.join("share").join("wasi-sysroot"))
+				.args(["-O2", "--target=wasm32-wasip1"])
+				.arg(source_dir.join(name).with_extension("c"));
+			command
+		},
+	};
+	Ok(Ok(command.arg("-o").arg(wasm).output()?))
+}
+
+/// Whether `rustc` has the standard library for [`RUST_TARGET`]. The directory of the library is printed even if the
+/// target is not installed, but only exists if it is.
+fn rust_target_installed(rustc: &str) -> Result<bool, Box<dyn Error>> {
+	let output = Command::new(rustc).args(["--print", "target-libdir", "--target", RUST_TARGET]).output()?;
+	Ok(output.status.success() && Path::new(String::from_utf8(output.stdout)?.trim()).is_dir())
+}